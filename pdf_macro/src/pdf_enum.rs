@@ -78,6 +78,12 @@ pub fn pdf_enum_inner(attr: TokenStream, item: TokenStream) -> TokenStream {
                     })
                 })
             }
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    #(Self::#field_names => #field_values),*
+                }
+            }
         })
     } else {
         quote!(impl #name {
@@ -90,6 +96,10 @@ pub fn pdf_enum_inner(attr: TokenStream, item: TokenStream) -> TokenStream {
                     })
                 })
             }
+
+            pub fn as_integer(self) -> i32 {
+                self as i32
+            }
         })
     };
 
@@ -9,7 +9,7 @@ use syn::{
 use crate::util::extract_type_from_option;
 
 fn field_getter(name: &Ident, ty: &Type, key: &LitStr, default: &Option<Expr>) -> TokenStream2 {
-    if name.to_string() == "stream" {
+    if name.to_string() == "stream" || name.to_string() == "indirect_reference" {
         return TokenStream2::new();
     }
 
@@ -133,7 +133,10 @@ pub fn pdf_obj_inner(input: TokenStream) -> TokenStream {
                     name.to_string()
                 ));
 
-            let (key, default) = if name.to_string() == "other" || name.to_string() == "stream" {
+            let (key, default) = if name.to_string() == "other"
+                || name.to_string() == "stream"
+                || name.to_string() == "indirect_reference"
+            {
                 (LitStr::new("", Span::call_site()), None)
             } else {
                 let nested = field_attr.parse_args_with(HelperArgs::parse).unwrap();
@@ -159,6 +162,9 @@ pub fn pdf_obj_inner(input: TokenStream) -> TokenStream {
 
     let has_other = field_name.iter().any(|field| field.to_string() == "other");
     let has_stream = field_name.iter().any(|field| field.to_string() == "stream");
+    let has_indirect_reference = field_name
+        .iter()
+        .any(|field| field.to_string() == "indirect_reference");
 
     let return_val = if has_other {
         field_name = field_name
@@ -176,7 +182,7 @@ pub fn pdf_obj_inner(input: TokenStream) -> TokenStream {
     } else {
         // todo: clone is superfluous, let's replace with macro
         quote!(
-            crate::assert_empty(dict.clone());
+            crate::assert_empty(dict.clone(), indirect_reference, resolver);
 
             Ok(Self {
                 #(
@@ -202,6 +208,16 @@ pub fn pdf_obj_inner(input: TokenStream) -> TokenStream {
     let lifetimes = generics.lifetimes();
     let (_, ty_generics, where_clause) = generics.split_for_impl();
 
+    // the indirect object the dictionary is read from, for reporting unknown keys
+    // and for an `indirect_reference` field
+    let reference_decl = if has_other && !has_indirect_reference {
+        TokenStream2::new()
+    } else {
+        quote!(
+            let indirect_reference = obj.as_reference();
+        )
+    };
+
     let dict_decl = if has_stream {
         quote!(
             let mut stream = resolver.assert_stream(obj)?;
@@ -217,6 +233,7 @@ pub fn pdf_obj_inner(input: TokenStream) -> TokenStream {
         impl<#(#lifetimes,)* #(#type_params,)* #from_obj_lt> crate::FromObj<'from_obj> for #name #ty_generics #where_clause {
             fn from_obj(obj: crate::Object<'from_obj>, resolver: &mut dyn crate::Resolve<'from_obj>) -> crate::PdfResult<Self> {
                 use anyhow::Context;
+                #reference_decl
                 #dict_decl
 
                 #obj_type
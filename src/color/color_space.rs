@@ -25,6 +25,16 @@ pub struct SeparationColorSpace<'a> {
     pub tint: f32,
 }
 
+#[derive(Debug, Clone)]
+pub struct PatternColorSpace<'a> {
+    /// The current pattern. Initially this is `None`, a pattern which paints nothing
+    pub pattern: Option<Rc<Pattern<'a>>>,
+
+    /// The colour space in which uncoloured tiling patterns specify the colour to
+    /// paint their cell with, carrying that colour once it has been set
+    pub underlying: Option<Rc<ColorSpace<'a>>>,
+}
+
 #[derive(Debug, Clone)]
 pub enum ColorSpace<'a> {
     // Device
@@ -65,7 +75,7 @@ pub enum ColorSpace<'a> {
         index: u32,
         space: Rc<IndexedColorSpace<'a>>,
     },
-    Pattern(Option<Rc<Pattern<'a>>>),
+    Pattern(PatternColorSpace<'a>),
     Separation(SeparationColorSpace<'a>),
     DeviceN(DeviceNColorSpace<'a>),
 }
@@ -94,7 +104,10 @@ impl<'a> ColorSpace<'a> {
             ColorSpaceName::Lab => todo!(),
             ColorSpaceName::ICCBased => todo!(),
             ColorSpaceName::Indexed => todo!(),
-            ColorSpaceName::Pattern => ColorSpace::Pattern(None),
+            ColorSpaceName::Pattern => ColorSpace::Pattern(PatternColorSpace {
+                pattern: None,
+                underlying: None,
+            }),
             ColorSpaceName::Separation => todo!(),
             ColorSpaceName::DeviceN => todo!(),
         }
@@ -116,6 +129,91 @@ impl<'a> ColorSpace<'a> {
         }
     }
 
+    /// Whether this is a special colour space, which can't be the alternate or
+    /// base space of another special colour space
+    pub fn is_special(&self) -> bool {
+        matches!(
+            self,
+            ColorSpace::Pattern(..)
+                | ColorSpace::Indexed { .. }
+                | ColorSpace::Separation(..)
+                | ColorSpace::DeviceN(..)
+        )
+    }
+
+    /// The number of colour components needed to specify a colour in this colour
+    /// space
+    pub fn num_components(&self) -> usize {
+        match self {
+            ColorSpace::DeviceGray(..)
+            | ColorSpace::CalGray { .. }
+            | ColorSpace::Indexed { .. }
+            | ColorSpace::Separation(..) => 1,
+            ColorSpace::DeviceRGB { .. } | ColorSpace::CalRGB { .. } | ColorSpace::Lab { .. } => 3,
+            ColorSpace::DeviceCMYK { .. } => 4,
+            ColorSpace::IccBased { stream, .. } => stream.num_of_color_components as usize,
+            ColorSpace::DeviceN(space) => space.names.len(),
+            // only uncoloured tiling patterns have components, which are those of
+            // their underlying colour space
+            ColorSpace::Pattern(space) => space
+                .underlying
+                .as_ref()
+                .map_or(0, |underlying| underlying.num_components()),
+        }
+    }
+
+    /// The colour with the given components in this colour space, such as the
+    /// output of a shading's function or a tint transform
+    pub fn with_components(&self, components: &[f32]) -> PdfResult<Self> {
+        let component = |idx: usize| components.get(idx).copied().unwrap_or(0.0);
+
+        Ok(match self {
+            ColorSpace::DeviceGray(..) => ColorSpace::DeviceGray(component(0)),
+            ColorSpace::DeviceRGB { .. } => ColorSpace::DeviceRGB {
+                red: component(0),
+                green: component(1),
+                blue: component(2),
+            },
+            ColorSpace::DeviceCMYK { .. } => ColorSpace::DeviceCMYK {
+                cyan: component(0),
+                magenta: component(1),
+                yellow: component(2),
+                key: component(3),
+            },
+            ColorSpace::CalGray { .. } => ColorSpace::CalGray { a: component(0) },
+            ColorSpace::CalRGB { .. } => ColorSpace::CalRGB {
+                a: component(0),
+                b: component(1),
+                c: component(2),
+            },
+            ColorSpace::Lab { .. } => ColorSpace::Lab {
+                a: component(0),
+                b: component(1),
+                c: component(2),
+            },
+            ColorSpace::IccBased { stream, .. } => ColorSpace::IccBased {
+                stream: Rc::clone(stream),
+                channels: (0..stream.num_of_color_components as usize)
+                    .map(component)
+                    .collect(),
+            },
+            ColorSpace::Indexed { space, .. } => ColorSpace::Indexed {
+                index: component(0) as u32,
+                space: Rc::clone(space),
+            },
+            ColorSpace::Separation(space) => ColorSpace::Separation(SeparationColorSpace {
+                tint: component(0),
+                ..space.clone()
+            }),
+            ColorSpace::DeviceN(..) | ColorSpace::Pattern(..) => {
+                anyhow::bail!(
+                    "cannot set the components of {:?} colour space",
+                    self.name()
+                )
+            }
+        })
+    }
+
     #[allow(unused)]
     fn blend(&self, background: Self) -> Self {
         todo!()
@@ -127,7 +225,7 @@ impl<'a> ColorSpace<'a> {
     pub fn as_u32(&self) -> u32 {
         match self {
             &Self::DeviceGray(n) => {
-                let n = n.round() as u32;
+                let n = (n * 255.0).round() as u32;
 
                 (0xff << 24) | (n << 16) | (n << 8) | n
             }
@@ -150,27 +248,39 @@ impl<'a> ColorSpace<'a> {
 
                 (0xff << 24) | (b << 16) | (g << 8) | r
             }
-            Self::Pattern(..) => {
-                // todo: we just set color to red for now
-                let r = (1.0 * 255.0) as u32;
-                let g = (0.0 * 255.0) as u32;
-                let b = (0.0 * 255.0) as u32;
-
-                (0xff << 24) | (b << 16) | (g << 8) | r
-            }
+            Self::Pattern(PatternColorSpace {
+                underlying: Some(color),
+                ..
+            }) => color.as_u32(),
+            // patterns have no colour of their own, and are painted by a shader or
+            // tile instead
+            Self::Pattern(..) => 0,
             Self::IccBased { stream, channels } => {
-                // ensure we don't silently render colors we don't support
-                assert_eq!(stream.num_of_color_components, 3);
-                assert_eq!(channels.len(), 3);
-
-                let r = (channels[0] * 255.0) as u32;
-                let g = (channels[1] * 255.0) as u32;
-                let b = (channels[2] * 255.0) as u32;
+                // ICC profiles aren't interpreted, so colours are painted in the
+                // device colour space with the same number of components
+                let device = match stream.num_of_color_components {
+                    1 => ColorSpaceName::DeviceGray,
+                    3 => ColorSpaceName::DeviceRGB,
+                    4 => ColorSpaceName::DeviceCMYK,
+                    // ensure we don't silently render colors we don't support
+                    n => todo!("ICCBased color space with {} components", n),
+                };
 
-                (0xff << 24) | (b << 16) | (g << 8) | r
+                match ColorSpace::init(device).with_components(channels) {
+                    Ok(color) => color.as_u32(),
+                    Err(..) => unreachable!("device colour spaces accept any components"),
+                }
             }
             Self::Separation(space) => {
-                todo!("unimplemented separation color space: {:#?}", space)
+                let components = space.tint_transform.evaluate(&[space.tint]);
+
+                // alternate spaces whose components can't be set are rejected when
+                // parsed, so only leniently parsed documents paint the alternate
+                // space's initial colour
+                match space.alternate_space.with_components(&components) {
+                    Ok(color) => color.as_u32(),
+                    Err(..) => space.alternate_space.as_u32(),
+                }
             }
             c => todo!("unimplemented color space: {:#?}", c),
         }
@@ -204,7 +314,10 @@ impl<'a> FromObj<'a> for ColorSpace<'a> {
 
                         let icc_profile = IccProfile::new(&stream)?;
                         assert!(
-                            matches!(&icc_profile.header.colour_space.0, b"RGB " | b"GRAY"),
+                            matches!(
+                                &icc_profile.header.colour_space.0,
+                                b"RGB " | b"GRAY" | b"CMYK"
+                            ),
                             "unimplemented ICC color profile: {:?}",
                             icc_profile.header.colour_space
                         );
@@ -231,7 +344,19 @@ impl<'a> FromObj<'a> for ColorSpace<'a> {
 
                         Ok(ColorSpace::Indexed { index: 0, space })
                     }
-                    ColorSpaceName::Pattern => todo!("pattern color space"),
+                    ColorSpaceName::Pattern => {
+                        let underlying = match arr.get(1) {
+                            Some(obj) => {
+                                Some(Rc::new(ColorSpace::from_obj(obj.clone(), resolver)?))
+                            }
+                            None => None,
+                        };
+
+                        Ok(ColorSpace::Pattern(PatternColorSpace {
+                            pattern: None,
+                            underlying,
+                        }))
+                    }
                     ColorSpaceName::Separation => {
                         assert_len(&arr, 4)?;
 
@@ -239,6 +364,14 @@ impl<'a> FromObj<'a> for ColorSpace<'a> {
                         let alternate_space = ColorSpace::from_obj(arr[2].clone(), resolver)?;
                         let tint_transform = Function::from_obj(arr[3].clone(), resolver)?;
 
+                        if alternate_space.is_special() {
+                            anyhow::bail!(
+                                "separation colour space {} has special alternate space {:?}",
+                                name.0,
+                                alternate_space.name()
+                            );
+                        }
+
                         let space = SeparationColorSpace {
                             name,
                             alternate_space: Rc::new(alternate_space),
@@ -36,3 +36,15 @@ impl<'a> FromObj<'a> for IndexedLookupTable {
         Ok(Self { buffer })
     }
 }
+
+impl IndexedLookupTable {
+    /// The components of the colour at `index`, one byte for each of the
+    /// `num_components` components of the base colour space. Entries past the
+    /// end of a truncated table are cut short
+    pub fn entry(&self, index: usize, num_components: usize) -> &[u8] {
+        let start = (index * num_components).min(self.buffer.len());
+        let end = (start + num_components).min(self.buffer.len());
+
+        &self.buffer[start..end]
+    }
+}
@@ -3,7 +3,7 @@ mod device_n;
 mod icc;
 mod indexed;
 
-pub use color_space::{ColorSpace, ColorSpaceName};
+pub use color_space::{ColorSpace, ColorSpaceName, PatternColorSpace};
 
 pub struct Color;
 
@@ -1,6 +1,7 @@
 use std::borrow::{Borrow, Cow};
 
 use crate::{
+    diagnostic::{Diagnostic, ParseOptions},
     error::PdfResult,
     lex::{LexBase, LexObject},
     objects::Object,
@@ -21,6 +22,12 @@ pub struct ContentLexer<'a> {
     /// Set to true when encountering a `BX` operator, and to false
     /// when an `EX` operator is encountered
     in_compatibility_mode: u128,
+
+    options: ParseOptions,
+
+    /// Unknown operators outside of a compatibility section, which are ignored
+    /// when parsing leniently
+    diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -43,6 +50,14 @@ impl<'a> Iterator for ContentLexer<'a> {
             Some(Ok(ContentTokenOrUnknownOperator::Token(tok))) => Some(Ok(tok)),
             Some(Ok(ContentTokenOrUnknownOperator::UnknownOperator(s))) => {
                 if self.in_compatibility_mode() {
+                    self.next()
+                } else if self.options.lenient {
+                    self.diagnostics.push(Diagnostic::new(
+                        None,
+                        None,
+                        format!("unknown operator: {:?}", s),
+                    ));
+
                     self.next()
                 } else {
                     todo!("Unknown operator: {:?}", s)
@@ -56,13 +71,28 @@ impl<'a> Iterator for ContentLexer<'a> {
 
 impl<'a> ContentLexer<'a> {
     pub fn new(buffer: Cow<'a, [u8]>) -> Self {
+        Self::with_options(buffer, ParseOptions::default())
+    }
+
+    pub fn with_options(buffer: Cow<'a, [u8]>, options: ParseOptions) -> Self {
         Self {
             buffer,
             cursor: 0,
             in_compatibility_mode: 0,
+            options,
+            diagnostics: Vec::new(),
         }
     }
 
+    /// The unknown operators ignored so far while parsing leniently
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
     pub fn debug_contents(&self) -> Cow<'_, str> {
         String::from_utf8_lossy(&self.buffer)
     }
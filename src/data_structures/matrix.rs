@@ -13,7 +13,7 @@ use crate::{
 /// [a b 0]
 /// [c d 0]
 /// [e f 1]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Matrix {
     a: f32,
     b: f32,
@@ -32,10 +32,8 @@ impl Mul<Matrix> for Matrix {
         let b = self.a * other.b + self.b * other.d;
         let d = self.c * other.b + self.d * other.d;
 
-        // todo: this is almost certainly wrong, but it works for the test case
-        // i'm using
-        let e = self.e + other.e;
-        let f = self.f + other.f;
+        let e = self.e * other.a + self.f * other.c + other.e;
+        let f = self.e * other.b + self.f * other.d + other.f;
 
         Matrix::new(a, b, c, d, e, f)
     }
@@ -103,6 +101,34 @@ impl Matrix {
         Self::new(1.0, a.tan(), b.tan(), 1.0, 0.0, 0.0)
     }
 
+    /// Transform a displacement rather than a position, so the translation of
+    /// the matrix is ignored
+    pub fn transform_vector(&self, vector: Point) -> Point {
+        Point::new(
+            self.a * vector.x + self.c * vector.y,
+            self.b * vector.x + self.d * vector.y,
+        )
+    }
+
+    /// The matrix undoing this transformation, if it is not degenerate
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+
+        let e = -(self.e * a + self.f * c);
+        let f = -(self.e * b + self.f * d);
+
+        Some(Self::new(a, b, c, d, e, f))
+    }
+
     pub fn from_arr(arr: [f32; 6]) -> Self {
         let [a, b, c, d, e, f] = arr;
         Self { a, b, c, d, e, f }
@@ -135,3 +161,34 @@ impl Into<[[f32; 4]; 4]> for Matrix {
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+
+    use super::Matrix;
+
+    #[test]
+    fn product_applies_left_matrix_first() {
+        let translate_then_scale =
+            Matrix::new_translation(10.0, 20.0) * Matrix::new_scale(2.0, 3.0);
+        let scale_then_translate =
+            Matrix::new_scale(2.0, 3.0) * Matrix::new_translation(10.0, 20.0);
+
+        let p = Point::new(1.0, 1.0);
+
+        // the translation is scaled when it is applied before the scale
+        assert_eq!(translate_then_scale * p, Point::new(22.0, 63.0));
+        assert_eq!(scale_then_translate * p, Point::new(12.0, 23.0));
+    }
+
+    #[test]
+    fn product_transforms_translation_of_left_matrix() {
+        // a quarter turn anticlockwise, applied after moving right by 10
+        let matrix =
+            Matrix::new_translation(10.0, 0.0) * Matrix::new(0.0, 1.0, -1.0, 0.0, 5.0, 5.0);
+
+        assert_eq!(matrix * Point::new(0.0, 0.0), Point::new(5.0, 15.0));
+        assert_eq!(matrix * Point::new(1.0, 0.0), Point::new(5.0, 16.0));
+    }
+}
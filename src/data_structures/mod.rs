@@ -1,4 +1,4 @@
-pub(crate) use matrix::Matrix;
+pub use matrix::Matrix;
 pub(crate) use name_tree::NameTree;
 pub(crate) use number_tree::NumberTree;
 pub(crate) use rectangle::Rectangle;
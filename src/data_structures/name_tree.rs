@@ -37,11 +37,12 @@ fn get_names<'a>(
 
 impl<'a> FromObj<'a> for NameTree<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut dict = resolver.assert_dict(obj)?;
 
         let names = get_names(&mut dict, resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, reference, resolver);
 
         Ok(Self {
             root: NameTreeRoot { names, kids: None },
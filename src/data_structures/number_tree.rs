@@ -36,11 +36,12 @@ fn get_nums<'a>(
 
 impl<'a> FromObj<'a> for NumberTree<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut dict = resolver.assert_dict(obj)?;
 
         let nums = get_nums(&mut dict, resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, reference, resolver);
 
         Ok(Self {
             root: NumberTreeRoot { nums, kids: None },
@@ -1,7 +1,7 @@
 use crate::{
     catalog::assert_len,
     error::PdfResult,
-    geometry::{path_builder::PathBuilder, Path},
+    geometry::{path_builder::PathBuilder, Path, Point},
     objects::Object,
     FromObj, Resolve,
 };
@@ -35,6 +35,14 @@ impl<'a> FromObj<'a> for Rectangle {
 }
 
 impl Rectangle {
+    /// A US Letter page, 8.5 by 11 inches, for pages which don't give their size
+    pub const US_LETTER: Self = Self {
+        lower_left_x: 0.0,
+        lower_left_y: 0.0,
+        upper_right_x: 612.0,
+        upper_right_y: 792.0,
+    };
+
     pub fn width(&self) -> f32 {
         self.upper_right_x - self.lower_left_x
     }
@@ -43,13 +51,55 @@ impl Rectangle {
         self.upper_right_y - self.lower_left_y
     }
 
+    pub fn lower_left(&self) -> Point {
+        Point::new(self.lower_left_x, self.lower_left_y)
+    }
+
+    pub fn upper_right(&self) -> Point {
+        Point::new(self.upper_right_x, self.upper_right_y)
+    }
+
+    /// Whether `point` lies within the rectangle, including its edges
+    pub fn contains(&self, point: Point) -> bool {
+        let (min_x, max_x) = min_max(self.lower_left_x, self.upper_right_x);
+        let (min_y, max_y) = min_max(self.lower_left_y, self.upper_right_y);
+
+        (min_x..=max_x).contains(&point.x) && (min_y..=max_y).contains(&point.y)
+    }
+
+    /// The area covered by both rectangles, which is empty if they don't overlap
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (min_x, max_x) = min_max(self.lower_left_x, self.upper_right_x);
+        let (min_y, max_y) = min_max(self.lower_left_y, self.upper_right_y);
+        let (other_min_x, other_max_x) = min_max(other.lower_left_x, other.upper_right_x);
+        let (other_min_y, other_max_y) = min_max(other.lower_left_y, other.upper_right_y);
+
+        let lower_left_x = min_x.max(other_min_x);
+        let lower_left_y = min_y.max(other_min_y);
+
+        Self {
+            lower_left_x,
+            lower_left_y,
+            upper_right_x: max_x.min(other_max_x).max(lower_left_x),
+            upper_right_y: max_y.min(other_max_y).max(lower_left_y),
+        }
+    }
+
     pub fn as_path(&self) -> Path {
         let mut path_builder = PathBuilder::new();
         path_builder.relative_move_to(self.lower_left_x, self.lower_left_y);
-        path_builder.relative_line_to(0.0, self.upper_right_y);
-        path_builder.relative_line_to(self.upper_right_x, 0.0);
-        path_builder.relative_line_to(0.0, -self.upper_right_y);
+        path_builder.relative_line_to(0.0, self.height());
+        path_builder.relative_line_to(self.width(), 0.0);
+        path_builder.relative_line_to(0.0, -self.height());
         path_builder.close_path();
         path_builder.outline.paths[0].clone()
     }
 }
+
+fn min_max(a: f32, b: f32) -> (f32, f32) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::objects::Reference;
+
+/// Options controlling how a document is parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Report unknown dictionary keys, optional entries of the wrong type and
+    /// unknown content stream operators as diagnostics, rather than failing
+    ///
+    /// Off by default, so that problems are found during development rather
+    /// than silently ignored
+    pub lenient: bool,
+
+    /// Rebuild a missing or damaged cross-reference table, and find objects
+    /// whose offsets in it are wrong, by scanning the file for objects. Each
+    /// repair is reported as a diagnostic
+    ///
+    /// On by default, as damaged files are common and readers are expected to
+    /// open them
+    pub recover: bool,
+}
+
+impl ParseOptions {
+    pub fn lenient() -> Self {
+        Self {
+            lenient: true,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            recover: true,
+        }
+    }
+}
+
+/// A problem with a document that was ignored while parsing leniently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The indirect object the problem was found in, if known
+    pub reference: Option<Reference>,
+
+    /// The dictionary key the problem was found at, if any
+    pub key: Option<String>,
+
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        reference: Option<Reference>,
+        key: Option<&str>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            reference,
+            key: key.map(str::to_owned),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(reference) = self.reference {
+            write!(
+                f,
+                "{} {} R: ",
+                reference.object_number, reference.generation
+            )?;
+        }
+
+        if let Some(key) = &self.key {
+            write!(f, "/{}: ", key)?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::{
+        objects::Object,
+        test_util::{self, dict, name, reference},
+        ContentLexer, Parser, PdfResult, XrefFormat,
+    };
+
+    use super::{Diagnostic, ParseOptions};
+
+    /// A document whose page has an unknown key and a `Rotate` entry of the
+    /// wrong type
+    fn document() -> Vec<u8> {
+        let objects = vec![
+            dict(vec![
+                ("Type", name("Catalog")),
+                ("Pages", Object::Reference(reference(2))),
+            ]),
+            dict(vec![
+                ("Type", name("Pages")),
+                ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+                ("Count", Object::Integer(1)),
+            ]),
+            dict(vec![
+                ("Type", name("Page")),
+                ("Parent", Object::Reference(reference(2))),
+                ("Rotate", name("Ninety")),
+                ("Unknown", Object::Integer(1)),
+            ]),
+        ];
+
+        test_util::document(
+            objects.into_iter().map(Object::Dictionary).collect(),
+            XrefFormat::Table,
+        )
+    }
+
+    #[test]
+    fn lenient_parsing_reports_diagnostics() {
+        assert!(Parser::from_bytes(document()).unwrap().page(0).is_err());
+
+        let mut parser =
+            Parser::from_bytes_with_options(document(), "", ParseOptions::lenient()).unwrap();
+
+        parser.page(0).unwrap();
+
+        let diagnostics = parser.diagnostics();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].key.as_deref(), Some("Rotate"));
+        assert_eq!(
+            diagnostics[1],
+            Diagnostic::new(Some(reference(3)), Some("Unknown"), "unknown key")
+        );
+    }
+
+    #[test]
+    fn lenient_content_ignores_unknown_operators() {
+        let mut content =
+            ContentLexer::with_options(Cow::Borrowed(b"1 foo q"), ParseOptions::lenient());
+
+        let tokens = content.by_ref().collect::<PdfResult<Vec<_>>>().unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(content.diagnostics().len(), 1);
+    }
+}
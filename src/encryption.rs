@@ -4,12 +4,14 @@ use std::collections::HashMap;
 
 use aes::cipher::{
     block_padding::{NoPadding, Pkcs7},
-    generic_array::GenericArray,
-    BlockDecrypt, BlockDecryptMut, KeyInit, KeyIvInit,
+    BlockDecryptMut, BlockEncryptMut, KeyIvInit,
 };
+use sha2::{Digest, Sha256, Sha384, Sha512};
 
 use crate::{
+    error::ParseError,
     file_specification::FileIdentifier,
+    filter::FilterKind,
     objects::{Dictionary, Name, Object, Reference},
     resolve::Resolve,
     stream::Stream,
@@ -45,8 +47,8 @@ pub struct Encryption<'a> {
     /// The value shall be a multiple of 8, in the range 40 to 128.
     ///
     /// Default value: 40
-    #[field("Length", default = 40)]
-    length: i32,
+    #[field("Length")]
+    length: Option<i32>,
 
     /// A dictionary whose keys shall be crypt filter names and whose values
     /// shall be the corresponding crypt filter dictionaries. Every crypt
@@ -109,20 +111,45 @@ pub struct Encryption<'a> {
     /// to 0
     ///
     /// 4 => if the document is encrypted with a V value of 4
+    ///
+    /// 6 => if the document is encrypted with a V value of 5, which uses
+    /// AES-256 (5 is a deprecated form of the same)
     #[field("R")]
     revision_number: i32,
 
     /// A 32-byte string, based on both the owner and user passwords, that shall
     /// be used in computing the encryption key and in determining whether
     /// a valid owner password was entered
+    ///
+    /// For revision 5 and above, a 48-byte string made up of a hash of the
+    /// owner password and the U string, and two salts
     #[field("O")]
     owner: String,
 
-    /// A set of flags specifying which operations shall be permitted when the
-    /// document is opened with user access
+    /// A 32-byte string, based on the user password, that shall be used in
+    /// determining whether to prompt the user for a password and, if so,
+    /// whether a valid user password was entered
+    ///
+    /// For revision 5 and above, a 48-byte string made up of a hash of the
+    /// user password and two salts
     #[field("U")]
     user: String,
 
+    /// A 32-byte string, used in revision 5 and above, holding the file
+    /// encryption key encrypted with a key derived from the owner password
+    #[field("OE")]
+    owner_encryption_key: Option<String>,
+
+    /// A 32-byte string, used in revision 5 and above, holding the file
+    /// encryption key encrypted with a key derived from the user password
+    #[field("UE")]
+    user_encryption_key: Option<String>,
+
+    /// A 16-byte string, used in revision 5 and above, holding the P entry
+    /// encrypted with the file encryption key
+    #[field("Perms")]
+    encrypted_permissions: Option<String>,
+
     /// A set of flags specifying which operations shall be permitted when the
     /// document is opened with user access
     #[field("P")]
@@ -158,6 +185,10 @@ pub enum EncryptionAlgorithm {
     /// document, using the rules specified by the CF, StmF, and StrF
     /// entries.
     BasedOnOtherEntries = 4,
+
+    /// The same as 4, with a 256-bit encryption key used directly, rather
+    /// than keys derived from it for each object
+    Aes256 = 5,
 }
 
 #[derive(Debug, Clone, FromObj)]
@@ -202,7 +233,7 @@ enum AuthEvent {
 
 #[pdf_enum]
 #[derive(Default)]
-enum CryptFilterMethod {
+pub enum CryptFilterMethod {
     /// The application shall not decrypt data but shall direct the input stream
     /// to the security handler for decryption.
     #[default]
@@ -220,10 +251,93 @@ enum CryptFilterMethod {
     /// initialization vector that shall be randomly generated and placed
     /// as the first 16 bytes in the stream or string.
     AesV2 = "AESV2",
+
+    /// The same as AESV2, but using AES-256 with the file encryption key
+    /// itself, rather than a key derived from it for each object
+    AesV3 = "AESV3",
 }
 
-#[derive(Debug, Copy, Clone)]
-struct UserAccessPermissions(i32);
+/// The operations permitted when a document is opened with the user password
+/// (Table 22)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UserAccessPermissions(i32);
+
+impl UserAccessPermissions {
+    /// Bits 7, 8 and 13 to 32 are reserved, and must be set
+    const RESERVED: i32 = !0xf3f;
+
+    pub const PRINT: Self = Self(Self::RESERVED | 1 << 2);
+    pub const MODIFY: Self = Self(Self::RESERVED | 1 << 3);
+    pub const COPY: Self = Self(Self::RESERVED | 1 << 4);
+    pub const ANNOTATE: Self = Self(Self::RESERVED | 1 << 5);
+    pub const FILL_FORMS: Self = Self(Self::RESERVED | 1 << 8);
+    pub const EXTRACT_FOR_ACCESSIBILITY: Self = Self(Self::RESERVED | 1 << 9);
+    pub const ASSEMBLE: Self = Self(Self::RESERVED | 1 << 10);
+    pub const PRINT_HIGH_QUALITY: Self = Self(Self::RESERVED | 1 << 11);
+
+    /// Every operation is permitted
+    pub const fn all() -> Self {
+        Self(!0b11)
+    }
+
+    /// No operation is permitted
+    pub const fn none() -> Self {
+        Self(Self::RESERVED)
+    }
+
+    fn bit(self, n: u32) -> bool {
+        self.0 & (1 << (n - 1)) != 0
+    }
+
+    /// Print the document, possibly not at the highest quality unless
+    /// [`Self::print_high_quality`] is also permitted
+    pub fn print(self) -> bool {
+        self.bit(3)
+    }
+
+    /// Modify the contents of the document, other than by the operations
+    /// controlled by the other flags
+    pub fn modify(self) -> bool {
+        self.bit(4)
+    }
+
+    /// Copy or otherwise extract text and graphics from the document
+    pub fn copy(self) -> bool {
+        self.bit(5)
+    }
+
+    /// Add or modify annotations, and fill in form fields
+    pub fn annotate(self) -> bool {
+        self.bit(6)
+    }
+
+    /// Fill in existing form fields, even if [`Self::annotate`] is not
+    /// permitted
+    pub fn fill_forms(self) -> bool {
+        self.bit(9)
+    }
+
+    /// Extract text and graphics in support of accessibility
+    pub fn extract_for_accessibility(self) -> bool {
+        self.bit(10)
+    }
+
+    /// Insert, rotate or delete pages and create bookmarks or thumbnails, even
+    /// if [`Self::modify`] is not permitted
+    pub fn assemble(self) -> bool {
+        self.bit(11)
+    }
+
+    /// Print the document faithfully to its digital representation
+    pub fn print_high_quality(self) -> bool {
+        self.bit(12)
+    }
+
+    /// The raw value of the P entry
+    pub fn bits(self) -> i32 {
+        self.0
+    }
+}
 
 impl<'a> FromObj<'a> for UserAccessPermissions {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
@@ -231,107 +345,1072 @@ impl<'a> FromObj<'a> for UserAccessPermissions {
     }
 }
 
+impl std::ops::BitOr for UserAccessPermissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
 const PADDING: [u8; 32] = [
     0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
     0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
 ];
 
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+impl<'a> Encryption<'a> {
+    /// The crypt filter method of the crypt filter `name`
+    fn crypt_filter_method(&self, name: &str) -> PdfResult<CryptFilterMethod> {
+        // before crypt filters, everything was encrypted with RC4
+        if !matches!(
+            self.v,
+            Some(EncryptionAlgorithm::BasedOnOtherEntries | EncryptionAlgorithm::Aes256)
+        ) {
+            return Ok(CryptFilterMethod::V2);
+        }
+
+        if name == "Identity" {
+            return Ok(CryptFilterMethod::None);
+        }
+
+        match self
+            .crypt_filters
+            .as_ref()
+            .and_then(|filters| filters.get(name))
+        {
+            Some(filter) => Ok(filter.crypt_filter_method),
+            None => anyhow::bail!("unknown crypt filter {:?}", name),
+        }
+    }
+
+    /// The length of the file encryption key, in bytes
+    fn key_len(&self) -> PdfResult<usize> {
+        if self.revision_number == 2 {
+            return Ok(5);
+        }
+
+        if self.revision_number >= 5 {
+            return Ok(32);
+        }
+
+        // producers often give the length only in the default crypt filter,
+        // sometimes in bytes rather than bits
+        let bits = match self.length {
+            Some(length) => length,
+            None => match self
+                .crypt_filters
+                .as_ref()
+                .and_then(|filters| filters.get(&self.stream_filter.0))
+            {
+                Some(CryptFilter {
+                    length: Some(length),
+                    ..
+                }) if *length < 40 => length * 8,
+                Some(CryptFilter {
+                    length: Some(length),
+                    ..
+                }) => *length,
+                Some(..) => 128,
+                None => 40,
+            },
+        };
+
+        if bits % 8 != 0 || !(40..=128).contains(&bits) {
+            anyhow::bail!("invalid encryption key length {}", bits);
+        }
+
+        Ok(bits as usize / 8)
+    }
+
+    /// The encryption dictionary as it is written
+    fn to_dict(&self) -> Dictionary<'a> {
+        let mut dict = self.other.clone();
+
+        dict.insert("Filter", Object::Name(self.filter.0.clone()));
+
+        if let Some(sub_filter) = &self.sub_filter {
+            dict.insert("SubFilter", Object::Name(sub_filter.0.clone()));
+        }
+
+        if let Some(v) = self.v {
+            dict.insert("V", Object::Integer(v.as_integer()));
+        }
+
+        if let Some(length) = self.length {
+            dict.insert("Length", Object::Integer(length));
+        }
+
+        if let Some(crypt_filters) = &self.crypt_filters {
+            dict.insert(
+                "CF",
+                Object::Dictionary(Dictionary::new(
+                    crypt_filters
+                        .iter()
+                        .map(|(name, filter)| (name.clone(), Object::Dictionary(filter.to_dict())))
+                        .collect(),
+                )),
+            );
+        }
+
+        dict.insert("StmF", Object::Name(self.stream_filter.0.clone()));
+        dict.insert("StrF", Object::Name(self.string_filter.0.clone()));
+        dict.insert("EFF", Object::Name(self.embedded_file_filter.0.clone()));
+        dict.insert("R", Object::Integer(self.revision_number));
+        dict.insert("O", Object::String(self.owner.clone()));
+        dict.insert("U", Object::String(self.user.clone()));
+
+        for (key, value) in [
+            ("OE", &self.owner_encryption_key),
+            ("UE", &self.user_encryption_key),
+            ("Perms", &self.encrypted_permissions),
+        ] {
+            if let Some(value) = value {
+                dict.insert(key, Object::String(value.clone()));
+            }
+        }
+
+        dict.insert("P", Object::Integer(self.user_permission_flags.0));
+
+        if !self.encrypt_metadata {
+            dict.insert("EncryptMetadata", Object::False);
+        }
+
+        dict
+    }
+}
+
+impl CryptFilter {
+    fn to_dict<'a>(&self) -> Dictionary<'a> {
+        let mut dict = Dictionary::empty();
+
+        dict.insert("Type", Object::Name("CryptFilter".to_owned()));
+        dict.insert(
+            "CFM",
+            Object::Name(self.crypt_filter_method.as_str().to_owned()),
+        );
+
+        if let Some(auth_event) = self.auth_event {
+            dict.insert("AuthEvent", Object::Name(auth_event.as_str().to_owned()));
+        }
+
+        if let Some(length) = self.length {
+            dict.insert("Length", Object::Integer(length));
+        }
+
+        dict
+    }
+}
+
 /// Manages encryption for entire document
+#[derive(Debug, Clone)]
 pub struct SecurityHandler<'a> {
     encryption: Encryption<'a>,
     file_identifier: FileIdentifier,
+
+    /// The encryption dictionary, whose strings are not encrypted
+    reference: Option<Reference>,
+
+    /// The file encryption key
+    key: Vec<u8>,
+
+    /// Whether the owner password was given
+    owner_access: bool,
+
+    stream_method: CryptFilterMethod,
+    string_method: CryptFilterMethod,
 }
 
 impl<'a> SecurityHandler<'a> {
-    pub fn new(encryption: Encryption<'a>, file_identifier: FileIdentifier) -> Self {
-        assert_eq!(encryption.length % 8, 0);
+    /// Authenticate `password` as either the user or the owner password of
+    /// the document, failing with [`ParseError::InvalidPassword`] if it is
+    /// neither
+    pub fn new(
+        encryption: Encryption<'a>,
+        reference: Option<Reference>,
+        file_identifier: FileIdentifier,
+        password: &str,
+    ) -> PdfResult<Self> {
+        if encryption.filter.0 != "Standard" {
+            anyhow::bail!("unsupported security handler {:?}", encryption.filter.0);
+        }
 
-        Self {
+        if !(2..=6).contains(&encryption.revision_number) {
+            anyhow::bail!(
+                "unsupported standard security handler revision {}",
+                encryption.revision_number
+            );
+        }
+
+        let stream_method = encryption.crypt_filter_method(&encryption.stream_filter.0)?;
+        let string_method = encryption.crypt_filter_method(&encryption.string_filter.0)?;
+
+        let mut handler = Self {
             encryption,
             file_identifier,
+            reference,
+            key: Vec::new(),
+            owner_access: false,
+            stream_method,
+            string_method,
+        };
+
+        let password = handler.password_bytes(password);
+
+        if let Some(key) = handler.authenticate_user(&password)? {
+            handler.key = key;
+        } else if let Some(key) = handler.authenticate_owner(&password)? {
+            handler.key = key;
+            handler.owner_access = true;
+        } else {
+            anyhow::bail!(ParseError::InvalidPassword);
+        }
+
+        if handler.encryption.revision_number >= 5 {
+            handler.check_permissions()?;
         }
+
+        Ok(handler)
     }
 
-    fn compute_encryption_key(&self, password: &[u8]) -> Vec<u8> {
-        let padded_password = if password.len() >= 32 {
-            password[..32].to_owned()
+    /// A security handler for encrypting a new document with `method`, which
+    /// is either AESV2 (revision 4) or AESV3 (revision 6)
+    ///
+    /// The encryption dictionary is written as the object `reference`. An
+    /// empty owner password is replaced by the user password
+    pub(crate) fn with_passwords(
+        user_password: &str,
+        owner_password: &str,
+        permissions: UserAccessPermissions,
+        method: CryptFilterMethod,
+        reference: Reference,
+        file_identifier: FileIdentifier,
+    ) -> PdfResult<Self> {
+        let (v, revision_number, key_len) = match method {
+            CryptFilterMethod::AesV2 => (EncryptionAlgorithm::BasedOnOtherEntries, 4, 16),
+            CryptFilterMethod::AesV3 => (EncryptionAlgorithm::Aes256, 6, 32),
+            CryptFilterMethod::None | CryptFilterMethod::V2 => {
+                anyhow::bail!("documents can only be encrypted with AESV2 or AESV3")
+            }
+        };
+
+        let owner_password = if owner_password.is_empty() {
+            user_password
         } else {
-            let mut b = password.to_owned();
-            b.extend_from_slice(&PADDING[..32 - password.len()]);
-            b
+            owner_password
+        };
+
+        let crypt_filter = CryptFilter {
+            crypt_filter_method: method,
+            auth_event: Some(AuthEvent::DocOpen),
+            length: Some(key_len),
         };
 
-        assert_eq!(padded_password.len(), 32);
+        let encryption = Encryption {
+            filter: Name("Standard".to_owned()),
+            sub_filter: None,
+            v: Some(v),
+            length: Some(key_len * 8),
+            crypt_filters: Some(HashMap::from([("StdCF".to_owned(), crypt_filter)])),
+            stream_filter: Name("StdCF".to_owned()),
+            string_filter: Name("StdCF".to_owned()),
+            embedded_file_filter: Name("StdCF".to_owned()),
+            revision_number,
+            owner: String::new(),
+            user: String::new(),
+            owner_encryption_key: None,
+            user_encryption_key: None,
+            encrypted_permissions: None,
+            user_permission_flags: permissions,
+            encrypt_metadata: true,
+            other: Dictionary::empty(),
+        };
+
+        let mut handler = Self {
+            encryption,
+            file_identifier,
+            reference: Some(reference),
+            key: Vec::new(),
+            owner_access: true,
+            stream_method: method,
+            string_method: method,
+        };
+
+        let user_password = handler.password_bytes(user_password);
+        let owner_password = handler.password_bytes(owner_password);
+
+        if revision_number == 6 {
+            handler.key = random_bytes::<32>()?.to_vec();
+
+            // the validation salt and key salt of each password (Algorithm 8
+            // and Algorithm 9)
+            let user_salts = random_bytes::<16>()?;
+            let owner_salts = random_bytes::<16>()?;
+
+            let mut user = handler.hash(&user_password, &user_salts[..8], &[]);
+            user.extend_from_slice(&user_salts);
+
+            let user_key = handler.hash(&user_password, &user_salts[8..], &[]);
+
+            let mut owner = handler.hash(&owner_password, &owner_salts[..8], &user);
+            owner.extend_from_slice(&owner_salts);
+
+            let owner_key = handler.hash(&owner_password, &owner_salts[8..], &user);
+
+            // the permissions, extended to 64 bits, followed by the value of
+            // EncryptMetadata, a marker and random padding (Algorithm 10)
+            let mut perms = [0; 16];
+            perms[..4].copy_from_slice(&permissions.0.to_le_bytes());
+            perms[4..8].copy_from_slice(&[0xff; 4]);
+            perms[8] = b'T';
+            perms[9..12].copy_from_slice(b"adb");
+            perms[12..].copy_from_slice(&random_bytes::<4>()?);
+
+            let encryption = &mut handler.encryption;
+            encryption.user_encryption_key =
+                Some(byte_string(&aes256_encrypt_key(&user_key, &handler.key)));
+            encryption.owner_encryption_key =
+                Some(byte_string(&aes256_encrypt_key(&owner_key, &handler.key)));
+            encryption.encrypted_permissions =
+                Some(byte_string(&aes256_encrypt_key(&handler.key, &perms)));
+            encryption.user = byte_string(&user);
+            encryption.owner = byte_string(&owner);
+        } else {
+            // the padded user password encrypted with a key derived from the
+            // owner password (Algorithm 3)
+            let owner_key = handler.owner_key(&owner_password)?;
+
+            let owner = (0..20).fold(pad_password(&user_password).to_vec(), |data, i| {
+                let key = owner_key.iter().map(|&b| b ^ i).collect::<Vec<_>>();
+                rc4(&key, &data)
+            });
+
+            handler.encryption.owner = byte_string(&owner);
+            handler.key = handler.compute_encryption_key(&user_password)?;
+
+            // only the first 16 bytes are used, and the rest are arbitrary
+            let mut user = handler.compute_user_entry(&handler.key);
+            user.resize(32, 0);
+
+            handler.encryption.user = byte_string(&user);
+        }
+
+        Ok(handler)
+    }
+
+    /// The encryption dictionary, to be written as the object given when the
+    /// handler was created
+    pub(crate) fn encryption_dict(&self) -> Dictionary<'a> {
+        self.encryption.to_dict()
+    }
+
+    /// The operations permitted to a user who isn't the owner
+    pub fn user_permission_flags(&self) -> UserAccessPermissions {
+        self.encryption.user_permission_flags
+    }
+
+    /// Whether the document was opened with the owner password, which grants
+    /// every permission
+    pub fn has_owner_access(&self) -> bool {
+        self.owner_access
+    }
+
+    /// Passwords are encoded with PDFDocEncoding before revision 5, and as
+    /// UTF-8 after
+    fn password_bytes(&self, password: &str) -> Vec<u8> {
+        if self.encryption.revision_number >= 5 {
+            return password.as_bytes().to_vec();
+        }
+
+        password
+            .chars()
+            .filter_map(|c| u8::try_from(c).ok())
+            .collect()
+    }
+
+    /// Compute the file encryption key from the user password (Algorithm 2)
+    fn compute_encryption_key(&self, password: &[u8]) -> PdfResult<Vec<u8>> {
+        let n = self.encryption.key_len()?;
 
         let mut hash = md5::Context::new();
 
-        hash.consume(&padded_password);
-        hash.consume(&self.encryption.owner.as_bytes());
-        hash.consume(&self.encryption.user_permission_flags.0.to_le_bytes());
-        hash.consume(&self.file_identifier.0[0].as_bytes());
+        hash.consume(pad_password(password));
+        hash.consume(string_bytes(&self.encryption.owner));
+        hash.consume(self.encryption.user_permission_flags.0.to_le_bytes());
+        hash.consume(string_bytes(&self.file_identifier.0[0]));
 
         if self.encryption.revision_number >= 4 && !self.encryption.encrypt_metadata {
-            hash.consume(&[0xFF, 0xFF, 0xFF, 0xFF]);
+            hash.consume([0xFF, 0xFF, 0xFF, 0xFF]);
         }
 
         let mut hash = hash.compute();
 
         if self.encryption.revision_number >= 3 {
-            let n = self.encryption.length / 8;
             for _ in 0..50 {
-                hash = md5::compute(&hash[..n as usize]);
+                hash = md5::compute(&hash[..n]);
+            }
+        }
+
+        Ok(hash[..n].to_vec())
+    }
+
+    /// The U entry for the file encryption key `key` (Algorithm 4 and
+    /// Algorithm 5). From revision 3, only the first 16 bytes are compared
+    fn compute_user_entry(&self, key: &[u8]) -> Vec<u8> {
+        if self.encryption.revision_number == 2 {
+            return rc4(key, &PADDING);
+        }
+
+        let mut hash = md5::Context::new();
+
+        hash.consume(PADDING);
+        hash.consume(string_bytes(&self.file_identifier.0[0]));
+
+        let mut user = hash.compute().to_vec();
+
+        for i in 0..20 {
+            let key = key.iter().map(|&b| b ^ i).collect::<Vec<_>>();
+            user = rc4(&key, &user);
+        }
+
+        user
+    }
+
+    /// The file encryption key, if `password` is the user password (Algorithm 6
+    /// and Algorithm 11)
+    fn authenticate_user(&self, password: &[u8]) -> PdfResult<Option<Vec<u8>>> {
+        let user = string_bytes(&self.encryption.user);
+
+        if self.encryption.revision_number >= 5 {
+            let (Some(hash), Some(validation_salt), Some(key_salt)) =
+                (user.get(..32), user.get(32..40), user.get(40..48))
+            else {
+                anyhow::bail!("invalid U entry in encryption dictionary");
+            };
+
+            if self.hash(password, validation_salt, &[]) != hash {
+                return Ok(None);
+            }
+
+            let Some(encrypted_key) = &self.encryption.user_encryption_key else {
+                anyhow::bail!("missing UE entry in encryption dictionary");
+            };
+
+            let key = self.hash(password, key_salt, &[]);
+
+            return aes256_decrypt_key(&key, &string_bytes(encrypted_key)).map(Some);
+        }
+
+        let key = self.compute_encryption_key(password)?;
+        let expected = self.compute_user_entry(&key);
+
+        let len = if self.encryption.revision_number == 2 {
+            32
+        } else {
+            16
+        };
+
+        Ok((user.get(..len) == expected.get(..len)).then_some(key))
+    }
+
+    /// The file encryption key, if `password` is the owner password (Algorithm 7
+    /// and Algorithm 12)
+    fn authenticate_owner(&self, password: &[u8]) -> PdfResult<Option<Vec<u8>>> {
+        let owner = string_bytes(&self.encryption.owner);
+
+        if self.encryption.revision_number >= 5 {
+            let user = string_bytes(&self.encryption.user);
+
+            let (Some(hash), Some(validation_salt), Some(key_salt), Some(user)) = (
+                owner.get(..32),
+                owner.get(32..40),
+                owner.get(40..48),
+                user.get(..48),
+            ) else {
+                anyhow::bail!("invalid O or U entry in encryption dictionary");
+            };
+
+            if self.hash(password, validation_salt, user) != hash {
+                return Ok(None);
             }
+
+            let Some(encrypted_key) = &self.encryption.owner_encryption_key else {
+                anyhow::bail!("missing OE entry in encryption dictionary");
+            };
+
+            let key = self.hash(password, key_salt, user);
+
+            return aes256_decrypt_key(&key, &string_bytes(encrypted_key)).map(Some);
         }
 
-        let n = if self.encryption.revision_number == 2 {
-            5
+        // the O entry is the user password encrypted with a key derived from
+        // the owner password
+        let key = self.owner_key(password)?;
+
+        let user_password = if self.encryption.revision_number == 2 {
+            rc4(&key, &owner)
         } else {
-            self.encryption.length / 8
+            (0..20).rev().fold(owner, |data, i| {
+                let key = key.iter().map(|&b| b ^ i).collect::<Vec<_>>();
+                rc4(&key, &data)
+            })
         };
 
-        hash[..n as usize].to_vec()
+        self.authenticate_user(&user_password)
     }
 
-    pub fn decrypt_string(&mut self, s: Vec<u8>) -> PdfResult<String> {
-        todo!()
+    /// The RC4 key that the O entry is encrypted with (steps a to d of
+    /// Algorithm 3)
+    fn owner_key(&self, password: &[u8]) -> PdfResult<Vec<u8>> {
+        let n = self.encryption.key_len()?;
+
+        let mut hash = md5::compute(pad_password(password));
+
+        if self.encryption.revision_number >= 3 {
+            for _ in 0..50 {
+                hash = md5::compute(hash.0);
+            }
+        }
+
+        Ok(hash[..n].to_vec())
     }
 
-    pub fn decrypt_stream(&self, mut stream: Vec<u8>, reference: Reference) -> PdfResult<Vec<u8>> {
-        let filter_name = &self.encryption.stream_filter.0;
-        let filter = self
-            .encryption
-            .crypt_filters
-            .as_ref()
+    /// Check that the P entry hasn't been altered, by comparing it with the
+    /// encrypted copy in Perms (Algorithm 13)
+    fn check_permissions(&self) -> PdfResult<()> {
+        // the Perms entry was optional in revision 5
+        let Some(perms) = &self.encryption.encrypted_permissions else {
+            return Ok(());
+        };
+
+        let Some(perms) = string_bytes(perms).get(..16).map(<[u8]>::to_vec) else {
+            anyhow::bail!("invalid Perms entry in encryption dictionary");
+        };
+
+        let mut buffer = perms;
+
+        // a single block in CBC mode with a zero initialization vector is the
+        // same as ECB mode
+        Aes256CbcDec::new_from_slices(&self.key, &[0; 16])
             .unwrap()
-            .get(filter_name)
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
             .unwrap();
 
-        assert_eq!(filter.crypt_filter_method, CryptFilterMethod::AesV2);
+        if &buffer[9..12] != b"adb"
+            || buffer[..4] != self.encryption.user_permission_flags.0.to_le_bytes()
+        {
+            anyhow::bail!("the permissions of the encrypted document have been altered");
+        }
+
+        Ok(())
+    }
+
+    /// The hash of a password used by revision 5 and above (Algorithm 2.B)
+    fn hash(&self, password: &[u8], salt: &[u8], user: &[u8]) -> Vec<u8> {
+        let password = &password[..password.len().min(127)];
+
+        let mut key = Sha256::new()
+            .chain_update(password)
+            .chain_update(salt)
+            .chain_update(user)
+            .finalize()
+            .to_vec();
+
+        if self.encryption.revision_number == 5 {
+            return key;
+        }
+
+        let mut round = 0;
+
+        loop {
+            let mut block = [password, &key, user].concat().repeat(64);
+            let len = block.len();
+
+            let encrypted = Aes128CbcEnc::new_from_slices(&key[..16], &key[16..32])
+                .unwrap()
+                .encrypt_padded_mut::<NoPadding>(&mut block, len)
+                .unwrap();
 
-        dbg!(&self.encryption);
+            let remainder = encrypted[..16].iter().map(|&b| u32::from(b)).sum::<u32>() % 3;
 
-        let mut key = self.compute_encryption_key(&[]);
-        let key_len = key.len();
+            key = match remainder {
+                0 => Sha256::digest(&*encrypted).to_vec(),
+                1 => Sha384::digest(&*encrypted).to_vec(),
+                _ => Sha512::digest(&*encrypted).to_vec(),
+            };
+
+            round += 1;
+
+            if round >= 64 && u32::from(encrypted[encrypted.len() - 1]) + 32 <= round {
+                break;
+            }
+        }
+
+        key.truncate(32);
+
+        key
+    }
+
+    /// The key used to encrypt the strings and streams of the object
+    /// `reference` (Algorithm 1)
+    fn object_key(&self, reference: Reference, method: CryptFilterMethod) -> Vec<u8> {
+        if method == CryptFilterMethod::AesV3 {
+            return self.key.clone();
+        }
+
+        let mut key = self.key.clone();
 
         key.extend_from_slice(&reference.object_number.to_le_bytes()[..3]);
         key.extend_from_slice(&reference.generation.to_le_bytes()[..2]);
-        key.extend_from_slice(b"sAlT");
 
-        let key = &md5::compute(&key)[..key_len];
+        if method == CryptFilterMethod::AesV2 {
+            key.extend_from_slice(b"sAlT");
+        }
+
+        md5::compute(&key)[..(self.key.len() + 5).min(16)].to_vec()
+    }
+
+    fn crypt(
+        &self,
+        data: &[u8],
+        reference: Reference,
+        method: CryptFilterMethod,
+        direction: Direction,
+    ) -> PdfResult<Vec<u8>> {
+        let key = self.object_key(reference, method);
+
+        Ok(match (method, direction) {
+            (CryptFilterMethod::None, _) => data.to_vec(),
+            (CryptFilterMethod::V2, _) => rc4(&key, data),
+            (CryptFilterMethod::AesV2 | CryptFilterMethod::AesV3, Direction::Decrypt) => {
+                aes_decrypt(&key, data)
+            }
+            (CryptFilterMethod::AesV2 | CryptFilterMethod::AesV3, Direction::Encrypt) => {
+                aes_encrypt(&key, data)?
+            }
+        })
+    }
+
+    pub fn decrypt_string(&self, s: &str, reference: Reference) -> PdfResult<String> {
+        let bytes = self.crypt(
+            &string_bytes(s),
+            reference,
+            self.string_method,
+            Direction::Decrypt,
+        )?;
+
+        Ok(bytes_to_string(&bytes))
+    }
+
+    pub fn decrypt_stream(&self, stream: &[u8], reference: Reference) -> PdfResult<Vec<u8>> {
+        self.crypt(stream, reference, self.stream_method, Direction::Decrypt)
+    }
+
+    /// Decrypt every string and stream of the object `reference`
+    pub fn decrypt_object(&self, obj: Object<'a>, reference: Reference) -> PdfResult<Object<'a>> {
+        self.crypt_object(obj, reference, Direction::Decrypt)
+    }
+
+    /// Encrypt every string and stream of the object `reference`
+    pub fn encrypt_object(&self, obj: Object<'a>, reference: Reference) -> PdfResult<Object<'a>> {
+        self.crypt_object(obj, reference, Direction::Encrypt)
+    }
+
+    fn crypt_object(
+        &self,
+        obj: Object<'a>,
+        reference: Reference,
+        direction: Direction,
+    ) -> PdfResult<Object<'a>> {
+        if self.reference == Some(reference) {
+            return Ok(obj);
+        }
+
+        Ok(match obj {
+            Object::String(s) => Object::String(match direction {
+                Direction::Decrypt => self.decrypt_string(&s, reference)?,
+                Direction::Encrypt => byte_string(&self.crypt(
+                    &string_bytes(&s),
+                    reference,
+                    self.string_method,
+                    direction,
+                )?),
+            }),
+            Object::Array(arr) => Object::Array(
+                arr.into_iter()
+                    .map(|obj| self.crypt_object(obj, reference, direction))
+                    .collect::<PdfResult<Vec<_>>>()?,
+            ),
+            Object::Dictionary(dict) => {
+                Object::Dictionary(self.crypt_dict(dict, reference, direction)?)
+            }
+            Object::Stream(mut stream) => {
+                stream.dict.other = self.crypt_dict(stream.dict.other, reference, direction)?;
+
+                if let Some(method) = self.stream_crypt_filter_method(&stream)? {
+                    stream.stream = self
+                        .crypt(&stream.stream, reference, method, direction)?
+                        .into();
+                }
+
+                Object::Stream(stream)
+            }
+            obj => obj,
+        })
+    }
+
+    fn crypt_dict(
+        &self,
+        dict: Dictionary<'a>,
+        reference: Reference,
+        direction: Direction,
+    ) -> PdfResult<Dictionary<'a>> {
+        Ok(Dictionary::new(
+            dict.entries()
+                .map(|(key, value)| Ok((key, self.crypt_object(value, reference, direction)?)))
+                .collect::<PdfResult<HashMap<_, _>>>()?,
+        ))
+    }
+
+    /// The method used to encrypt the data of a stream, or `None` if it isn't
+    /// encrypted
+    fn stream_crypt_filter_method(&self, stream: &Stream) -> PdfResult<Option<CryptFilterMethod>> {
+        let kind = stream
+            .dict
+            .other
+            .iter()
+            .find_map(|(key, value)| match value {
+                Object::Name(name) if key == "Type" => Some(name.as_str()),
+                _ => None,
+            });
+
+        match kind {
+            Some("XRef") => return Ok(None),
+            Some("Metadata") if !self.encryption.encrypt_metadata => return Ok(None),
+            _ => {}
+        }
+
+        // a crypt filter at the start of the filters overrides the default
+        if stream
+            .dict
+            .filter
+            .as_ref()
+            .and_then(|filters| filters.first())
+            == Some(&FilterKind::Crypt)
+        {
+            let name = stream
+                .dict
+                .decode_parms
+                .as_ref()
+                .and_then(|params| params.get(0))
+                .and_then(|params| {
+                    params.iter().find_map(|(key, value)| match value {
+                        Object::Name(name) if key == "Name" => Some(name.as_str()),
+                        _ => None,
+                    })
+                })
+                .unwrap_or("Identity");
+
+            return self.encryption.crypt_filter_method(name).map(Some);
+        }
+
+        Ok(Some(self.stream_method))
+    }
+}
+
+/// Pad or truncate a password to exactly 32 bytes
+fn pad_password(password: &[u8]) -> [u8; 32] {
+    let mut padded = PADDING;
+
+    let len = password.len().min(32);
+    padded[..len].copy_from_slice(&password[..len]);
+    padded[len..].copy_from_slice(&PADDING[..32 - len]);
+
+    padded
+}
+
+/// Strings are stored with each byte as a `char`, except for text strings
+/// written in hex with a byte order mark, which are decoded as UTF-16
+fn string_bytes(s: &str) -> Vec<u8> {
+    if s.chars().any(|c| u8::try_from(c).is_err()) {
+        let mut bytes = vec![0xfe, 0xff];
+        bytes.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+        return bytes;
+    }
+
+    s.chars().map(|c| c as u8).collect()
+}
+
+/// A string with each byte stored as a `char`, as the lexer reads binary
+/// strings
+fn byte_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn bytes_to_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => String::from_utf16_lossy(
+            &utf16
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+                .collect::<Vec<_>>(),
+        ),
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// RC4 is symmetric, so this both encrypts and decrypts
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+    let mut j = 0_u8;
 
-        let mut dec = aes::Aes128Dec::new_from_slice(&key).unwrap();
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, usize::from(j));
+    }
+
+    let mut i = 0_u8;
+    let mut j = 0_u8;
+
+    data.iter()
+        .map(|&b| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[usize::from(i)]);
+            state.swap(usize::from(i), usize::from(j));
 
-        let key = &[0; 32];
+            b ^ state[usize::from(state[usize::from(i)].wrapping_add(state[usize::from(j)]))]
+        })
+        .collect()
+}
+
+/// Decrypt AES-CBC data whose first 16 bytes are the initialization vector,
+/// removing its padding
+fn aes_decrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let Some((iv, ciphertext)) = data.split_at_checked(16) else {
+        return Vec::new();
+    };
 
-        assert_eq!(stream.len() % 16, 0);
+    // a partial block at the end can't be decrypted, and is ignored
+    let mut buffer = ciphertext[..ciphertext.len() / 16 * 16].to_vec();
 
-        type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+    // the key is 16 or 32 bytes, so this can't fail
+    match key.len() {
+        16 => Aes128CbcDec::new_from_slices(key, iv)
+            .unwrap()
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .unwrap(),
+        _ => Aes256CbcDec::new_from_slices(key, iv)
+            .unwrap()
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .unwrap(),
+    };
+
+    // producers don't always pad correctly, so invalid padding is kept
+    if let Some(&padding) = buffer.last() {
+        let len = buffer.len().wrapping_sub(usize::from(padding));
+
+        if (1..=16).contains(&padding)
+            && len <= buffer.len()
+            && buffer[len..].iter().all(|&b| b == padding)
+        {
+            buffer.truncate(len);
+        }
+    }
+
+    buffer
+}
+
+/// Encrypt data with AES-CBC and a random initialization vector, which is
+/// placed before it
+fn aes_encrypt(key: &[u8], data: &[u8]) -> PdfResult<Vec<u8>> {
+    let iv = random_bytes::<16>()?;
+
+    // PKCS#7 padding always adds at least one byte
+    let mut buffer = vec![0; (data.len() / 16 + 1) * 16];
+    buffer[..data.len()].copy_from_slice(data);
+
+    // the key is 16 or 32 bytes, and the buffer has room for the padding, so
+    // this can't fail
+    match key.len() {
+        16 => Aes128CbcEnc::new_from_slices(key, &iv)
+            .unwrap()
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len())
+            .unwrap(),
+        _ => Aes256CbcEnc::new_from_slices(key, &iv)
+            .unwrap()
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len())
+            .unwrap(),
+    };
+
+    let mut encrypted = iv.to_vec();
+    encrypted.extend_from_slice(&buffer);
+
+    Ok(encrypted)
+}
 
-        let (iv, ciphertext) = stream.split_at_mut(16);
-        let cipher = Aes128CbcDec::new_from_slices(key, iv).unwrap();
+/// Encrypt a 32 byte file encryption key, or the 16 byte permissions, with
+/// AES-256 without an initialization vector or padding
+fn aes256_encrypt_key(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut buffer = data.to_vec();
+    let len = buffer.len();
 
-        cipher.decrypt_padded_mut::<Pkcs7>(ciphertext).unwrap();
+    Aes256CbcEnc::new_from_slices(key, &[0; 16])
+        .unwrap()
+        .encrypt_padded_mut::<NoPadding>(&mut buffer, len)
+        .unwrap();
 
-        Ok(stream)
+    buffer
+}
+
+fn random_bytes<const N: usize>() -> PdfResult<[u8; N]> {
+    let mut bytes = [0; N];
+
+    if let Err(err) = getrandom::fill(&mut bytes) {
+        anyhow::bail!("failed to generate random bytes: {}", err);
+    }
+
+    Ok(bytes)
+}
+
+/// Decrypt the file encryption key from the UE or OE entries, which are
+/// encrypted with AES-256 without an initialization vector or padding
+fn aes256_decrypt_key(key: &[u8], encrypted_key: &[u8]) -> PdfResult<Vec<u8>> {
+    let Some(encrypted_key) = encrypted_key.get(..32) else {
+        anyhow::bail!("invalid encrypted file encryption key");
+    };
+
+    let mut buffer = encrypted_key.to_vec();
+
+    Aes256CbcDec::new_from_slices(key, &[0; 16])
+        .unwrap()
+        .decrypt_padded_mut::<NoPadding>(&mut buffer)
+        .unwrap();
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::ParseError,
+        file_specification::FileIdentifier,
+        objects::{Object, Reference},
+    };
+
+    use super::{
+        aes_decrypt, rc4, Aes128CbcEnc, CryptFilterMethod, SecurityHandler, UserAccessPermissions,
+    };
+    use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit};
+
+    #[test]
+    fn rc4_test_vector() {
+        assert_eq!(
+            rc4(b"Key", b"Plaintext"),
+            [0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3]
+        );
+    }
+
+    #[test]
+    fn aes_round_trip() {
+        let key = [7; 16];
+        let iv = [3; 16];
+
+        let mut buffer = [0; 32];
+        buffer[..16].copy_from_slice(b"sixteen byte str");
+
+        let encrypted = Aes128CbcEnc::new_from_slices(&key, &iv)
+            .unwrap()
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, 16)
+            .unwrap();
+
+        let mut data = iv.to_vec();
+        data.extend_from_slice(encrypted);
+
+        assert_eq!(data.len(), 48);
+        assert_eq!(aes_decrypt(&key, &data), b"sixteen byte str");
+    }
+
+    #[test]
+    fn permission_flags() {
+        // only printing is permitted
+        let permissions = UserAccessPermissions(-3900);
+
+        assert!(permissions.print());
+        assert!(!permissions.modify());
+        assert!(!permissions.copy());
+        assert!(!permissions.annotate());
+        assert!(!permissions.fill_forms());
+        assert!(!permissions.print_high_quality());
+    }
+
+    #[test]
+    fn encrypt_then_authenticate() {
+        let encrypt_dict = Reference {
+            object_number: 10,
+            generation: 0,
+        };
+
+        let reference = Reference {
+            object_number: 3,
+            generation: 0,
+        };
+
+        let id = FileIdentifier(["0123456789abcdef".to_owned(), "0123456789abcdef".to_owned()]);
+
+        for method in [CryptFilterMethod::AesV2, CryptFilterMethod::AesV3] {
+            let writer = SecurityHandler::with_passwords(
+                "user",
+                "owner",
+                UserAccessPermissions::PRINT,
+                method,
+                encrypt_dict,
+                id.clone(),
+            )
+            .unwrap();
+
+            let encrypted = writer
+                .encrypt_object(Object::String("secret".to_owned()), reference)
+                .unwrap();
+
+            assert_ne!(encrypted, Object::String("secret".to_owned()));
+
+            for (password, owner_access) in [("user", false), ("owner", true)] {
+                let reader = SecurityHandler::new(
+                    writer.encryption.clone(),
+                    Some(encrypt_dict),
+                    id.clone(),
+                    password,
+                )
+                .unwrap();
+
+                assert_eq!(reader.has_owner_access(), owner_access);
+                assert!(reader.user_permission_flags().print());
+                assert!(!reader.user_permission_flags().copy());
+                assert_eq!(
+                    reader.decrypt_object(encrypted.clone(), reference).unwrap(),
+                    Object::String("secret".to_owned())
+                );
+            }
+
+            let err = SecurityHandler::new(writer.encryption.clone(), None, id.clone(), "")
+                .err()
+                .unwrap();
+
+            assert!(matches!(
+                err.downcast_ref::<ParseError>(),
+                Some(ParseError::InvalidPassword)
+            ));
+        }
     }
 }
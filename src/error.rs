@@ -38,6 +38,10 @@ pub enum ParseError {
     ParseIntegerError(ParseIntError),
     PostScriptError(PostScriptError),
     RenderError(PdfRenderError),
+
+    /// The password of an encrypted document is neither its user nor its
+    /// owner password
+    InvalidPassword,
 }
 
 impl From<io::Error> for ParseError {
@@ -11,6 +11,17 @@ pub enum FileSpecification<'a> {
     Full(FullFileSpecification<'a>),
 }
 
+impl<'a> FileSpecification<'a> {
+    pub(crate) fn to_obj(&self) -> PdfResult<Object<'a>> {
+        match self {
+            FileSpecification::Simple(s) => Ok(Object::String(s.0.clone())),
+            FileSpecification::Full(..) => {
+                anyhow::bail!("writing full file specifications is not yet supported")
+            }
+        }
+    }
+}
+
 impl<'a> FromObj<'a> for FileSpecification<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
         match resolver.resolve(obj)? {
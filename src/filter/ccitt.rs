@@ -0,0 +1,810 @@
+//! Decoding of CCITT Group 3 (ITU-T T.4) and Group 4 (ITU-T T.6) facsimile
+//! data
+//!
+//! A row is decoded into its changing elements, the positions of pixels whose
+//! color differs from the pixel to the left of them, starting from an
+//! imaginary white pixel before the start of the row. Two dimensional rows are
+//! coded relative to the changing elements of the row above them
+
+use crate::error::PdfResult;
+
+/// The largest number of pixels of an image that will be decoded
+const MAX_PIXELS: usize = 1 << 28;
+
+/// The number of pixels of an image `columns` wide and `rows` high, if it is
+/// small enough to be decoded
+fn num_pixels(columns: usize, rows: usize) -> PdfResult<usize> {
+    match columns.checked_mul(rows) {
+        Some(len) if len <= MAX_PIXELS => Ok(len),
+        _ => anyhow::bail!("CCITT image of {}x{} pixels is too large", columns, rows),
+    }
+}
+
+#[derive(Debug, FromObj)]
+pub struct CcittFaxDecoderParams {
+    /// A code identifying the encoding scheme used:
+    ///   * Negative: pure two-dimensional encoding (Group 4)
+    ///   * Zero: pure one-dimensional encoding (Group 3, 1-D)
+    ///   * Positive: mixed one- and two-dimensional encoding (Group 3, 2-D), in
+    ///     which a line encoded one-dimensionally may be followed by at most
+    ///     K − 1 lines encoded two-dimensionally
+    ///
+    /// The filter distinguishes among negative, zero, and positive values of K to
+    /// determine how to interpret the encoded data; however, it does not
+    /// distinguish between different positive K values.
+    ///
+    /// Default value: 0
+    #[field("K", default = 0)]
+    k: i32,
+
+    /// A flag specifying whether end-of-line bit patterns shall be present in the
+    /// encoding. The CCITTFaxDecode filter shall always accept end-of-line bit
+    /// patterns. If EndOfLine is true, end-of-line bit patterns shall be present.
+    ///
+    /// Default value: false
+    #[field("EndOfLine", default = false)]
+    end_of_line: bool,
+
+    /// A flag specifying whether the filter shall expect extra 0 bits before each
+    /// encoded line so that the line begins on a byte boundary. If true, the filter
+    /// shall skip over encoded bits to begin decoding each line at a byte boundary.
+    /// If false, the filter shall not expect extra bits in the encoded representation.
+    ///
+    /// Default value: false
+    #[field("EncodedByteAlign", default = false)]
+    encoded_byte_align: bool,
+
+    /// The width of the image in pixels. If the value is not a multiple of 8, the
+    /// filter shall adjust the width of the unencoded image to the next multiple
+    /// of 8 so that each line starts on a byte boundary.
+    ///
+    /// Default value: 1728
+    #[field("Columns", default = 1728)]
+    columns: u32,
+
+    /// The height of the image in scan lines. If the value is 0 or absent, the
+    /// image's height is not predetermined, and the encoded data shall be
+    /// terminated by an end-of-block bit pattern or by the end of the filter's
+    /// data.
+    ///
+    /// Default value: 0
+    #[field("Rows", default = 0)]
+    rows: u32,
+
+    /// A flag specifying whether the filter shall expect the encoded data to be
+    /// terminated by an end-of-block pattern, overriding the Rows parameter. If
+    /// false, the filter shall stop when it has decoded the number of lines
+    /// indicated by Rows or when its data has been exhausted, whichever occurs
+    /// first. The end-of-block pattern shall be the CCITT end-of-facsimile-block
+    /// (EOFB) or return-to-control (RTC) appropriate for the K parameter.
+    ///
+    /// Default value: true
+    #[field("EndOfBlock", default = true)]
+    end_of_block: bool,
+
+    /// A flag specifying the meaning of 1 bits in the decoded image. If false,
+    /// 0 pixels shall represent black, and 1 pixels white
+    ///
+    /// Default value: false
+    #[field("BlackIs1", default = false)]
+    black_is_1: bool,
+
+    /// The number of damaged rows of data that shall be tolerated before an
+    /// error occurs. This entry shall apply only if EndOfLine is true and K is
+    /// non-negative. Tolerating a damaged row shall mean locating its end in the
+    /// encoded data by searching for an EndOfLine pattern and then substituting
+    /// decoded data from the previous row if the previous row was not damaged,
+    /// or a white scan line if the previous row was also damaged.
+    ///
+    /// Default value: 0
+    #[field("DamagedRowsBeforeError", default = 0)]
+    damaged_rows_before_error: u32,
+}
+
+impl CcittFaxDecoderParams {
+    /// The parameters of MMR coded data embedded in other formats, which is
+    /// Group 4 data in which black is 1
+    pub(crate) fn mmr(columns: u32, rows: u32) -> Self {
+        Self {
+            k: -1,
+            end_of_line: false,
+            encoded_byte_align: false,
+            columns,
+            rows,
+            end_of_block: true,
+            black_is_1: true,
+            damaged_rows_before_error: 0,
+        }
+    }
+}
+
+/// The longest run length code is 13 bits
+const MAX_RUN_CODE_LEN: u32 = 13;
+
+const END_OF_LINE: u32 = 0b0000_0000_0001;
+
+/// Run lengths from 0 to 63 are encoded using a single terminating code. Longer
+/// runs are encoded by one or more makeup codes giving a multiple of 64,
+/// followed by a terminating code
+const WHITE_TERMINATING_CODES: [(&str, u16); 64] = [
+    ("00110101", 0),
+    ("000111", 1),
+    ("0111", 2),
+    ("1000", 3),
+    ("1011", 4),
+    ("1100", 5),
+    ("1110", 6),
+    ("1111", 7),
+    ("10011", 8),
+    ("10100", 9),
+    ("00111", 10),
+    ("01000", 11),
+    ("001000", 12),
+    ("000011", 13),
+    ("110100", 14),
+    ("110101", 15),
+    ("101010", 16),
+    ("101011", 17),
+    ("0100111", 18),
+    ("0001100", 19),
+    ("0001000", 20),
+    ("0010111", 21),
+    ("0000011", 22),
+    ("0000100", 23),
+    ("0101000", 24),
+    ("0101011", 25),
+    ("0010011", 26),
+    ("0100100", 27),
+    ("0011000", 28),
+    ("00000010", 29),
+    ("00000011", 30),
+    ("00011010", 31),
+    ("00011011", 32),
+    ("00010010", 33),
+    ("00010011", 34),
+    ("00010100", 35),
+    ("00010101", 36),
+    ("00010110", 37),
+    ("00010111", 38),
+    ("00101000", 39),
+    ("00101001", 40),
+    ("00101010", 41),
+    ("00101011", 42),
+    ("00101100", 43),
+    ("00101101", 44),
+    ("00000100", 45),
+    ("00000101", 46),
+    ("00001010", 47),
+    ("00001011", 48),
+    ("01010010", 49),
+    ("01010011", 50),
+    ("01010100", 51),
+    ("01010101", 52),
+    ("00100100", 53),
+    ("00100101", 54),
+    ("01011000", 55),
+    ("01011001", 56),
+    ("01011010", 57),
+    ("01011011", 58),
+    ("01001010", 59),
+    ("01001011", 60),
+    ("00110010", 61),
+    ("00110011", 62),
+    ("00110100", 63),
+];
+
+const WHITE_MAKEUP_CODES: [(&str, u16); 27] = [
+    ("11011", 64),
+    ("10010", 128),
+    ("010111", 192),
+    ("0110111", 256),
+    ("00110110", 320),
+    ("00110111", 384),
+    ("01100100", 448),
+    ("01100101", 512),
+    ("01101000", 576),
+    ("01100111", 640),
+    ("011001100", 704),
+    ("011001101", 768),
+    ("011010010", 832),
+    ("011010011", 896),
+    ("011010100", 960),
+    ("011010101", 1024),
+    ("011010110", 1088),
+    ("011010111", 1152),
+    ("011011000", 1216),
+    ("011011001", 1280),
+    ("011011010", 1344),
+    ("011011011", 1408),
+    ("010011000", 1472),
+    ("010011001", 1536),
+    ("010011010", 1600),
+    ("011000", 1664),
+    ("010011011", 1728),
+];
+
+const BLACK_TERMINATING_CODES: [(&str, u16); 64] = [
+    ("0000110111", 0),
+    ("010", 1),
+    ("11", 2),
+    ("10", 3),
+    ("011", 4),
+    ("0011", 5),
+    ("0010", 6),
+    ("00011", 7),
+    ("000101", 8),
+    ("000100", 9),
+    ("0000100", 10),
+    ("0000101", 11),
+    ("0000111", 12),
+    ("00000100", 13),
+    ("00000111", 14),
+    ("000011000", 15),
+    ("0000010111", 16),
+    ("0000011000", 17),
+    ("0000001000", 18),
+    ("00001100111", 19),
+    ("00001101000", 20),
+    ("00001101100", 21),
+    ("00000110111", 22),
+    ("00000101000", 23),
+    ("00000010111", 24),
+    ("00000011000", 25),
+    ("000011001010", 26),
+    ("000011001011", 27),
+    ("000011001100", 28),
+    ("000011001101", 29),
+    ("000001101000", 30),
+    ("000001101001", 31),
+    ("000001101010", 32),
+    ("000001101011", 33),
+    ("000011010010", 34),
+    ("000011010011", 35),
+    ("000011010100", 36),
+    ("000011010101", 37),
+    ("000011010110", 38),
+    ("000011010111", 39),
+    ("000001101100", 40),
+    ("000001101101", 41),
+    ("000011011010", 42),
+    ("000011011011", 43),
+    ("000001010100", 44),
+    ("000001010101", 45),
+    ("000001010110", 46),
+    ("000001010111", 47),
+    ("000001100100", 48),
+    ("000001100101", 49),
+    ("000001010010", 50),
+    ("000001010011", 51),
+    ("000000100100", 52),
+    ("000000110111", 53),
+    ("000000111000", 54),
+    ("000000100111", 55),
+    ("000000101000", 56),
+    ("000001011000", 57),
+    ("000001011001", 58),
+    ("000000101011", 59),
+    ("000000101100", 60),
+    ("000001011010", 61),
+    ("000001100110", 62),
+    ("000001100111", 63),
+];
+
+const BLACK_MAKEUP_CODES: [(&str, u16); 27] = [
+    ("0000001111", 64),
+    ("000011001000", 128),
+    ("000011001001", 192),
+    ("000001011011", 256),
+    ("000000110011", 320),
+    ("000000110100", 384),
+    ("000000110101", 448),
+    ("0000001101100", 512),
+    ("0000001101101", 576),
+    ("0000001001010", 640),
+    ("0000001001011", 704),
+    ("0000001001100", 768),
+    ("0000001001101", 832),
+    ("0000001110010", 896),
+    ("0000001110011", 960),
+    ("0000001110100", 1024),
+    ("0000001110101", 1088),
+    ("0000001110110", 1152),
+    ("0000001110111", 1216),
+    ("0000001010010", 1280),
+    ("0000001010011", 1344),
+    ("0000001010100", 1408),
+    ("0000001010101", 1472),
+    ("0000001011010", 1536),
+    ("0000001011011", 1600),
+    ("0000001100100", 1664),
+    ("0000001100101", 1728),
+];
+
+/// Makeup codes for runs longer than 1728, shared by both colors
+const EXTENDED_MAKEUP_CODES: [(&str, u16); 13] = [
+    ("00000001000", 1792),
+    ("00000001100", 1856),
+    ("00000001101", 1920),
+    ("000000010010", 1984),
+    ("000000010011", 2048),
+    ("000000010100", 2112),
+    ("000000010101", 2176),
+    ("000000010110", 2240),
+    ("000000010111", 2304),
+    ("000000011100", 2368),
+    ("000000011101", 2432),
+    ("000000011110", 2496),
+    ("000000011111", 2560),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    fn invert(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// The coding modes of two dimensional rows
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// The run ends at the changing element after `b1` on the reference line
+    Pass,
+
+    /// The next two runs are encoded as in one dimensional rows
+    Horizontal,
+
+    /// The run ends at the given offset from `b1` on the reference line
+    Vertical(isize),
+}
+
+/// A lookup table indexed by the next 13 bits of the data, giving the length of
+/// the run length code at the start of those bits and the run it encodes
+struct RunTable(Vec<(u32, u16)>);
+
+impl RunTable {
+    fn new(codes: &[&[(&str, u16)]]) -> Self {
+        let mut table = vec![(0, 0); 1 << MAX_RUN_CODE_LEN];
+
+        for &(code, run) in codes.iter().flat_map(|codes| codes.iter()) {
+            let len = code.len() as u32;
+            let shift = MAX_RUN_CODE_LEN - len;
+            let start = (usize::from_str_radix(code, 2).unwrap()) << shift;
+
+            table[start..start + (1 << shift)].fill((len, run));
+        }
+
+        Self(table)
+    }
+}
+
+/// Reads the data one bit at a time, most significant bit first
+struct BitReader<'a> {
+    buffer: &'a [u8],
+    bit: usize,
+
+    /// The position following the last 1 bit in the data. Anything after this
+    /// is padding, and can't contain a code
+    end: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        let end = buffer
+            .iter()
+            .rposition(|&b| b != 0)
+            .map_or(0, |idx| idx * 8 + 8 - buffer[idx].trailing_zeros() as usize);
+
+        Self {
+            buffer,
+            bit: 0,
+            end,
+        }
+    }
+
+    /// The next `len` bits, where bits past the end of the data are zero
+    fn peek(&self, len: u32) -> u32 {
+        let mut bits = 0;
+
+        for bit in self.bit..self.bit + len as usize {
+            let b = self
+                .buffer
+                .get(bit / 8)
+                .map_or(0, |b| (b >> (7 - bit % 8)) & 1);
+            bits = (bits << 1) | u32::from(b);
+        }
+
+        bits
+    }
+
+    fn consume(&mut self, len: u32) {
+        self.bit += len as usize;
+    }
+
+    fn read_bit(&mut self) -> u32 {
+        let bit = self.peek(1);
+        self.consume(1);
+        bit
+    }
+
+    fn align(&mut self) {
+        self.bit = self.bit.next_multiple_of(8);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bit >= self.end
+    }
+}
+
+pub struct CcittFaxDecoder<'a> {
+    reader: BitReader<'a>,
+    params: CcittFaxDecoderParams,
+    white: RunTable,
+    black: RunTable,
+}
+
+impl<'a> CcittFaxDecoder<'a> {
+    pub fn new(buffer: &'a [u8], params: CcittFaxDecoderParams) -> Self {
+        Self {
+            reader: BitReader::new(buffer),
+            params,
+            white: RunTable::new(&[
+                &WHITE_TERMINATING_CODES,
+                &WHITE_MAKEUP_CODES,
+                &EXTENDED_MAKEUP_CODES,
+            ]),
+            black: RunTable::new(&[
+                &BLACK_TERMINATING_CODES,
+                &BLACK_MAKEUP_CODES,
+                &EXTENDED_MAKEUP_CODES,
+            ]),
+        }
+    }
+
+    /// Decode the image into rows of 1 bit pixels, each padded to a whole byte
+    pub fn decode(mut self) -> PdfResult<Vec<u8>> {
+        let columns = self.params.columns as usize;
+        let k = self.params.k;
+
+        if columns == 0 {
+            anyhow::bail!("CCITT image has no columns");
+        }
+
+        num_pixels(columns, self.params.rows as usize)?;
+
+        let mut out = Vec::new();
+
+        // the row above the first row is treated as entirely white
+        let mut reference = Vec::new();
+
+        let mut rows = 0;
+        let mut damaged_rows = 0;
+
+        while self.params.rows == 0 || rows < self.params.rows {
+            if k < 0 {
+                if self.params.encoded_byte_align {
+                    self.reader.align();
+                }
+
+                if self.params.end_of_block
+                    && self.reader.peek(24) == END_OF_LINE << 12 | END_OF_LINE
+                {
+                    break;
+                }
+            } else {
+                let found_end_of_line = self.skip_end_of_line();
+
+                if found_end_of_line && self.params.end_of_block && self.at_return_to_control() {
+                    break;
+                }
+
+                if !found_end_of_line && self.params.encoded_byte_align {
+                    self.reader.align();
+                }
+            }
+
+            if self.reader.is_empty() {
+                break;
+            }
+
+            // the height may not be known up front, so each row is checked too
+            num_pixels(columns, rows as usize + 1)?;
+
+            let two_dimensional = match k {
+                ..=-1 => true,
+                0 => false,
+                1.. => self.reader.read_bit() == 0,
+            };
+
+            let row = if two_dimensional {
+                self.decode_two_dimensional_row(&reference, columns)
+            } else {
+                self.decode_one_dimensional_row(columns)
+            };
+
+            match row {
+                Ok(row) => {
+                    reference = row;
+                    damaged_rows = 0;
+                }
+                // the last row was cut short
+                Err(..) if self.reader.is_empty() => break,
+                Err(..)
+                    if self.params.end_of_line
+                        && k >= 0
+                        && damaged_rows < self.params.damaged_rows_before_error =>
+                {
+                    // resume at the next end of line, repeating the previous row
+                    // unless it was also damaged
+                    while !self.reader.is_empty() && self.reader.peek(12) != END_OF_LINE {
+                        self.reader.consume(1);
+                    }
+
+                    if damaged_rows > 0 {
+                        reference.clear();
+                    }
+
+                    damaged_rows += 1;
+                }
+                Err(err) => return Err(err),
+            }
+
+            self.write_row(&mut out, &reference, columns);
+            rows += 1;
+        }
+
+        Ok(out)
+    }
+
+    /// Skip over any fill bits and an end of line code, returning whether an
+    /// end of line code was found
+    fn skip_end_of_line(&mut self) -> bool {
+        while !self.reader.is_empty() && self.reader.peek(12) == 0 {
+            self.reader.consume(1);
+        }
+
+        if self.reader.peek(12) == END_OF_LINE {
+            self.reader.consume(12);
+            return true;
+        }
+
+        false
+    }
+
+    /// The return to control code ending Group 3 data is six consecutive end of
+    /// line codes, each followed by a 1 bit in mixed mode. We have already
+    /// consumed the first, and stop upon seeing the second
+    fn at_return_to_control(&self) -> bool {
+        if self.params.k > 0 {
+            self.reader.peek(13) == 1 << 12 | END_OF_LINE
+        } else {
+            self.reader.peek(12) == END_OF_LINE
+        }
+    }
+
+    /// Write a row of packed pixels, given the positions at which the color of
+    /// the row changes
+    fn write_row(&self, out: &mut Vec<u8>, changes: &[usize], columns: usize) {
+        let start = out.len();
+        let white = if self.params.black_is_1 { 0x00 } else { 0xff };
+
+        out.resize(start + columns.div_ceil(8), white);
+
+        let row = &mut out[start..];
+
+        for run in changes.chunks(2) {
+            let end = run.get(1).copied().unwrap_or(columns).min(columns);
+
+            for x in run[0]..end {
+                let mask = 0x80 >> (x % 8);
+
+                if self.params.black_is_1 {
+                    row[x / 8] |= mask;
+                } else {
+                    row[x / 8] &= !mask;
+                }
+            }
+        }
+    }
+
+    /// Two changes at the same position cancel out, as the run between them
+    /// is empty
+    fn push_change(changes: &mut Vec<usize>, position: usize) {
+        if changes.last() == Some(&position) {
+            changes.pop();
+        } else {
+            changes.push(position);
+        }
+    }
+
+    fn decode_one_dimensional_row(&mut self, columns: usize) -> PdfResult<Vec<usize>> {
+        let mut changes = Vec::new();
+        let mut position = 0;
+        let mut color = Color::White;
+
+        while position < columns {
+            position = (position + self.read_run(color)?).min(columns);
+            Self::push_change(&mut changes, position);
+            color = color.invert();
+        }
+
+        Ok(changes)
+    }
+
+    fn decode_two_dimensional_row(
+        &mut self,
+        reference: &[usize],
+        columns: usize,
+    ) -> PdfResult<Vec<usize>> {
+        let mut changes = Vec::new();
+        let mut color = Color::White;
+
+        // the start of the next run, which is `None` before the first pixel
+        let mut a0: Option<usize> = None;
+
+        // the index of the first changing element on the reference line to the
+        // right of `a0`
+        let mut ref_idx = 0;
+
+        while a0.is_none_or(|a0| a0 < columns) {
+            if let Some(a0) = a0 {
+                while reference.get(ref_idx).is_some_and(|&b| b <= a0) {
+                    ref_idx += 1;
+                }
+            }
+
+            // changes to black are at even indices, so `b1`, the first change on
+            // the reference line to the opposite color of `a0`, is at an even
+            // index when the current color is white
+            let b1_idx = match (color, ref_idx % 2) {
+                (Color::White, 0) | (Color::Black, 1) => ref_idx,
+                _ => ref_idx + 1,
+            };
+
+            let b1 = reference.get(b1_idx).copied().unwrap_or(columns);
+            let b2 = reference.get(b1_idx + 1).copied().unwrap_or(columns);
+
+            match self.read_mode()? {
+                Mode::Pass => a0 = Some(b2),
+                Mode::Horizontal => {
+                    let a1 = (a0.unwrap_or(0) + self.read_run(color)?).min(columns);
+                    let a2 = (a1 + self.read_run(color.invert())?).min(columns);
+
+                    Self::push_change(&mut changes, a1);
+                    Self::push_change(&mut changes, a2);
+
+                    a0 = Some(a2);
+                }
+                Mode::Vertical(offset) => {
+                    let a1 = b1
+                        .saturating_add_signed(offset)
+                        .clamp(a0.unwrap_or(0), columns);
+
+                    Self::push_change(&mut changes, a1);
+
+                    a0 = Some(a1);
+                    color = color.invert();
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn read_mode(&mut self) -> PdfResult<Mode> {
+        let (len, mode) = match self.reader.peek(7) {
+            0b100_0000..=0b111_1111 => (1, Mode::Vertical(0)),
+            0b011_0000..=0b011_1111 => (3, Mode::Vertical(1)),
+            0b010_0000..=0b010_1111 => (3, Mode::Vertical(-1)),
+            0b001_0000..=0b001_1111 => (3, Mode::Horizontal),
+            0b000_1000..=0b000_1111 => (4, Mode::Pass),
+            0b000_0110..=0b000_0111 => (6, Mode::Vertical(2)),
+            0b000_0100..=0b000_0101 => (6, Mode::Vertical(-2)),
+            0b000_0011 => (7, Mode::Vertical(3)),
+            0b000_0010 => (7, Mode::Vertical(-3)),
+            0b000_0001 => anyhow::bail!("unsupported CCITT uncompressed mode"),
+            _ => anyhow::bail!("unexpected CCITT end of line"),
+        };
+
+        self.reader.consume(len);
+
+        Ok(mode)
+    }
+
+    /// Read a run of pixels of the given color, made up of any number of
+    /// makeup codes followed by a terminating code
+    fn read_run(&mut self, color: Color) -> PdfResult<usize> {
+        let table = match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        };
+
+        let mut run = 0;
+
+        loop {
+            let (len, length) = table.0[self.reader.peek(MAX_RUN_CODE_LEN) as usize];
+
+            if len == 0 {
+                anyhow::bail!("invalid CCITT {:?} run length code", color);
+            }
+
+            self.reader.consume(len);
+            run += length as usize;
+
+            if length < 64 {
+                return Ok(run);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CcittFaxDecoder, CcittFaxDecoderParams};
+
+    fn params(k: i32, columns: u32) -> CcittFaxDecoderParams {
+        CcittFaxDecoderParams {
+            k,
+            end_of_line: false,
+            encoded_byte_align: false,
+            columns,
+            rows: 0,
+            end_of_block: true,
+            black_is_1: false,
+            damaged_rows_before_error: 0,
+        }
+    }
+
+    #[test]
+    fn one_dimensional() {
+        // white 2, black 3, white 3, then a row of white 8
+        let data = [0b0111_1010, 0b0010_0110];
+
+        assert_eq!(
+            CcittFaxDecoder::new(&data, params(0, 8)).decode().unwrap(),
+            [0b1100_0111, 0xff]
+        );
+    }
+
+    #[test]
+    fn two_dimensional() {
+        // horizontal white 2 and black 3, then V0 to the end of the row. The
+        // second row repeats it with three V0 codes, followed by an EOFB
+        let data = [0b0010_1111, 0b0111_1000, 0, 0b1000_0000, 0b0000_1000];
+
+        assert_eq!(
+            CcittFaxDecoder::new(&data, params(-1, 8)).decode().unwrap(),
+            [0b1100_0111, 0b1100_0111]
+        );
+    }
+
+    #[test]
+    fn too_many_pixels_is_an_error() {
+        let mut tall = params(-1, 1 << 20);
+        tall.rows = 1 << 20;
+
+        assert!(CcittFaxDecoder::new(&[0xff], tall).decode().is_err());
+
+        // every bit is a V0 code for a whole row, so the height is only
+        // limited by the length of the data
+        let data = [0xff; 64];
+
+        assert!(CcittFaxDecoder::new(&data, params(-1, 1 << 20))
+            .decode()
+            .is_err());
+    }
+
+    #[test]
+    fn zero_columns_is_an_error() {
+        assert!(CcittFaxDecoder::new(&[0xff], params(0, 0))
+            .decode()
+            .is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! The procedures for decoding integers with the MQ arithmetic decoder (T.88
+//! Annex A)
+
+use crate::filter::mq::{contexts, ArithmeticDecoder, Context};
+
+/// Decodes signed integers, with their own set of 512 contexts (the IAx
+/// decoding procedures)
+pub(super) struct IntegerDecoder {
+    contexts: Vec<Context>,
+}
+
+impl IntegerDecoder {
+    pub fn new() -> Self {
+        Self {
+            contexts: contexts(512),
+        }
+    }
+
+    fn decode_bit(&mut self, decoder: &mut ArithmeticDecoder, prev: &mut usize) -> u32 {
+        let d = decoder.decode(&mut self.contexts[*prev]);
+
+        *prev = if *prev < 256 {
+            (*prev << 1) | d as usize
+        } else {
+            (((*prev << 1) | d as usize) & 511) | 256
+        };
+
+        u32::from(d)
+    }
+
+    /// Returns `None` for the out-of-band value
+    pub fn decode(&mut self, decoder: &mut ArithmeticDecoder) -> Option<i32> {
+        let mut prev = 1;
+
+        let sign = self.decode_bit(decoder, &mut prev);
+
+        // the number of bits in the value, and the offset added to it, are
+        // given by a prefix of up to five 1 bits
+        let (len, offset) = if self.decode_bit(decoder, &mut prev) == 0 {
+            (2, 0)
+        } else if self.decode_bit(decoder, &mut prev) == 0 {
+            (4, 4)
+        } else if self.decode_bit(decoder, &mut prev) == 0 {
+            (6, 20)
+        } else if self.decode_bit(decoder, &mut prev) == 0 {
+            (8, 84)
+        } else if self.decode_bit(decoder, &mut prev) == 0 {
+            (12, 340)
+        } else {
+            (32, 4436)
+        };
+
+        let mut value: u32 = 0;
+
+        for _ in 0..len {
+            value = (value << 1) | self.decode_bit(decoder, &mut prev);
+        }
+
+        let value = value.wrapping_add(offset) as i32;
+
+        match (sign, value) {
+            (1, 0) => None,
+            (1, value) => Some(-value),
+            (_, value) => Some(value),
+        }
+    }
+}
+
+/// Decodes symbol IDs, which are fixed length codes (the IAID decoding
+/// procedure)
+pub(super) struct IdDecoder {
+    code_len: u32,
+    contexts: Vec<Context>,
+}
+
+impl IdDecoder {
+    pub fn new(code_len: u32) -> Self {
+        Self {
+            code_len,
+            contexts: contexts(1 << (code_len + 1)),
+        }
+    }
+
+    pub fn decode(&mut self, decoder: &mut ArithmeticDecoder) -> usize {
+        let mut prev = 1;
+
+        for _ in 0..self.code_len {
+            prev = (prev << 1) | decoder.decode(&mut self.contexts[prev]) as usize;
+        }
+
+        prev - (1 << self.code_len)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::PdfResult;
+
+/// The largest number of pixels of a bitmap that will be decoded
+const MAX_PIXELS: usize = 1 << 28;
+
+/// The number of pixels of a `width` by `height` bitmap, if it is small enough
+/// to be decoded
+pub(super) fn num_pixels(width: usize, height: usize) -> PdfResult<usize> {
+    match width.checked_mul(height) {
+        Some(len) if len <= MAX_PIXELS => Ok(len),
+        _ => anyhow::bail!("JBIG2 bitmap of {}x{} pixels is too large", width, height),
+    }
+}
+
+/// The ways in which a bitmap may be combined with the bitmap it is drawn onto
+#[pdf_enum(Integer)]
+pub(super) enum CombinationOperator {
+    Or = 0,
+    And = 1,
+    Xor = 2,
+    Xnor = 3,
+    Replace = 4,
+}
+
+impl CombinationOperator {
+    fn apply(self, dest: u8, src: u8) -> u8 {
+        match self {
+            CombinationOperator::Or => dest | src,
+            CombinationOperator::And => dest & src,
+            CombinationOperator::Xor => dest ^ src,
+            CombinationOperator::Xnor => !(dest ^ src) & 1,
+            CombinationOperator::Replace => src,
+        }
+    }
+}
+
+/// A bilevel image, in which 1 is black and 0 is white. Each pixel is stored as
+/// a whole byte
+#[derive(Debug, Clone)]
+pub(super) struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize, value: u8) -> PdfResult<Self> {
+        Ok(Self {
+            width,
+            height,
+            pixels: vec![value; num_pixels(width, height)?],
+        })
+    }
+
+    /// Unpack rows of 1 bit pixels, each padded to a whole byte
+    pub fn from_packed(data: &[u8], width: usize, height: usize) -> PdfResult<Self> {
+        let mut bitmap = Self::new(width, height, 0)?;
+        let bytes_per_row = width.div_ceil(8);
+
+        for (y, row) in data.chunks(bytes_per_row).take(height).enumerate() {
+            for x in 0..width.min(row.len() * 8) {
+                bitmap.set(x, y, (row[x / 8] >> (7 - x % 8)) & 1);
+            }
+        }
+
+        Ok(bitmap)
+    }
+
+    /// Pack the pixels into rows padded to a whole byte. The result follows
+    /// the convention of PDF image data, where 0 is black
+    pub fn to_packed(&self) -> Vec<u8> {
+        let bytes_per_row = self.width.div_ceil(8);
+        let mut out = vec![0xff; bytes_per_row * self.height];
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.pixels[y * self.width + x] == 1 {
+                    out[y * bytes_per_row + x / 8] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Pixels outside of the bitmap are white
+    pub fn get(&self, x: i64, y: i64) -> u8 {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return 0;
+        }
+
+        self.pixels[y as usize * self.width + x as usize]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn copy_row(&mut self, from: usize, to: usize) {
+        let width = self.width;
+        self.pixels
+            .copy_within(from * width..(from + 1) * width, to * width);
+    }
+
+    /// Extend the bitmap downwards, for pages whose height is not known up front
+    pub fn grow(&mut self, height: usize, value: u8) -> PdfResult<()> {
+        if height > self.height {
+            self.pixels.resize(num_pixels(self.width, height)?, value);
+            self.height = height;
+        }
+
+        Ok(())
+    }
+
+    /// A copy of part of the bitmap, for use as the reference of a refinement
+    pub fn region(&self, x: i64, y: i64, width: usize, height: usize) -> PdfResult<Bitmap> {
+        let mut region = Bitmap::new(width, height, 0)?;
+
+        for dy in 0..height {
+            for dx in 0..width {
+                region.set(dx, dy, self.get(x + dx as i64, y + dy as i64));
+            }
+        }
+
+        Ok(region)
+    }
+
+    /// Draw `other` onto this bitmap with its top left corner at `(x, y)`,
+    /// discarding any pixels that fall outside of it
+    pub fn combine(&mut self, other: &Bitmap, x: i64, y: i64, op: CombinationOperator) {
+        for dy in 0..other.height {
+            let y = y + dy as i64;
+
+            if y < 0 || y >= self.height as i64 {
+                continue;
+            }
+
+            for dx in 0..other.width {
+                let x = x + dx as i64;
+
+                if x < 0 || x >= self.width as i64 {
+                    continue;
+                }
+
+                let idx = y as usize * self.width + x as usize;
+                self.pixels[idx] = op.apply(self.pixels[idx], other.pixels[dy * other.width + dx]);
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{
+    error::PdfResult,
+    filter::{
+        ccitt::{CcittFaxDecoder, CcittFaxDecoderParams},
+        mq::{ArithmeticDecoder, Context},
+    },
+};
+
+use super::bitmap::{num_pixels, Bitmap};
+
+/// The contexts of the pixel deciding whether a row is identical to the one
+/// above it, when typical prediction is used
+const TYPICAL_PREDICTION_CONTEXTS: [usize; 4] = [0x9b25, 0x0795, 0x00e5, 0x0195];
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct GenericRegionParams {
+    pub template: u8,
+
+    /// Whether each row begins with a bit indicating whether it is identical
+    /// to the row above it
+    pub typical_prediction: bool,
+
+    /// The positions of the adaptive template pixels, relative to the pixel
+    /// being decoded. Only template 0 uses all four
+    pub adaptive_template: [(i8, i8); 4],
+}
+
+impl GenericRegionParams {
+    pub fn num_contexts(template: u8) -> usize {
+        match template {
+            0 => 1 << 16,
+            1 => 1 << 13,
+            _ => 1 << 10,
+        }
+    }
+
+    fn context(&self, bitmap: &Bitmap, x: usize, y: usize) -> usize {
+        let x = x as i64;
+        let y = y as i64;
+
+        let pixel = |dx: i64, dy: i64| bitmap.get(x + dx, y + dy) as usize;
+        let at = |idx: usize| {
+            let (dx, dy) = self.adaptive_template[idx];
+            pixel(i64::from(dx), i64::from(dy))
+        };
+
+        match self.template {
+            0 => {
+                pixel(-1, 0)
+                    | pixel(-2, 0) << 1
+                    | pixel(-3, 0) << 2
+                    | pixel(-4, 0) << 3
+                    | at(0) << 4
+                    | pixel(2, -1) << 5
+                    | pixel(1, -1) << 6
+                    | pixel(0, -1) << 7
+                    | pixel(-1, -1) << 8
+                    | pixel(-2, -1) << 9
+                    | at(1) << 10
+                    | at(2) << 11
+                    | pixel(1, -2) << 12
+                    | pixel(0, -2) << 13
+                    | pixel(-1, -2) << 14
+                    | at(3) << 15
+            }
+            1 => {
+                pixel(-1, 0)
+                    | pixel(-2, 0) << 1
+                    | pixel(-3, 0) << 2
+                    | at(0) << 3
+                    | pixel(2, -1) << 4
+                    | pixel(1, -1) << 5
+                    | pixel(0, -1) << 6
+                    | pixel(-1, -1) << 7
+                    | pixel(-2, -1) << 8
+                    | pixel(2, -2) << 9
+                    | pixel(1, -2) << 10
+                    | pixel(0, -2) << 11
+                    | pixel(-1, -2) << 12
+            }
+            2 => {
+                pixel(-1, 0)
+                    | pixel(-2, 0) << 1
+                    | at(0) << 2
+                    | pixel(1, -1) << 3
+                    | pixel(0, -1) << 4
+                    | pixel(-1, -1) << 5
+                    | pixel(-2, -1) << 6
+                    | pixel(1, -2) << 7
+                    | pixel(0, -2) << 8
+                    | pixel(-1, -2) << 9
+            }
+            _ => {
+                pixel(-1, 0)
+                    | pixel(-2, 0) << 1
+                    | pixel(-3, 0) << 2
+                    | pixel(-4, 0) << 3
+                    | at(0) << 4
+                    | pixel(1, -1) << 5
+                    | pixel(0, -1) << 6
+                    | pixel(-1, -1) << 7
+                    | pixel(-2, -1) << 8
+                    | pixel(-3, -1) << 9
+            }
+        }
+    }
+}
+
+/// Decode an arithmetic coded generic region. The contexts are shared between
+/// all of the symbols of a symbol dictionary
+pub(super) fn decode_generic_region(
+    decoder: &mut ArithmeticDecoder,
+    contexts: &mut [Context],
+    width: usize,
+    height: usize,
+    params: &GenericRegionParams,
+) -> PdfResult<Bitmap> {
+    let mut bitmap = Bitmap::new(width, height, 0)?;
+
+    // whether the current row is identical to the row above it
+    let mut typical = false;
+
+    for y in 0..height {
+        if params.typical_prediction {
+            let context = TYPICAL_PREDICTION_CONTEXTS[params.template as usize];
+            typical ^= decoder.decode(&mut contexts[context]) == 1;
+
+            if typical {
+                // the row above the first row is white, like the bitmap
+                if y > 0 {
+                    bitmap.copy_row(y - 1, y);
+                }
+
+                continue;
+            }
+        }
+
+        for x in 0..width {
+            let context = params.context(&bitmap, x, y);
+            let pixel = decoder.decode(&mut contexts[context]);
+
+            bitmap.set(x, y, pixel);
+        }
+    }
+
+    Ok(bitmap)
+}
+
+/// MMR coded regions are Group 4 facsimile data, in which black is 1
+pub(super) fn decode_mmr_region(data: &[u8], width: usize, height: usize) -> PdfResult<Bitmap> {
+    num_pixels(width, height)?;
+
+    let params = CcittFaxDecoderParams::mmr(width as u32, height as u32);
+    let data = CcittFaxDecoder::new(data, params).decode()?;
+
+    Bitmap::from_packed(&data, width, height)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RefinementParams {
+    pub template: u8,
+    pub typical_prediction: bool,
+
+    /// Template 0 has one adaptive pixel in the bitmap being decoded, and one
+    /// in the reference bitmap
+    pub adaptive_template: [(i8, i8); 2],
+
+    /// The offset of the reference bitmap from the bitmap being decoded
+    pub reference_dx: i64,
+    pub reference_dy: i64,
+}
+
+impl RefinementParams {
+    pub fn num_contexts(template: u8) -> usize {
+        if template == 0 {
+            1 << 13
+        } else {
+            1 << 10
+        }
+    }
+
+    fn context(&self, bitmap: &Bitmap, reference: &Bitmap, x: usize, y: usize) -> usize {
+        let x = x as i64;
+        let y = y as i64;
+
+        let pixel = |dx: i64, dy: i64| bitmap.get(x + dx, y + dy) as usize;
+
+        let rx = x - self.reference_dx;
+        let ry = y - self.reference_dy;
+        let reference = |dx: i64, dy: i64| reference.get(rx + dx, ry + dy) as usize;
+
+        if self.template == 0 {
+            let (at_x, at_y) = self.adaptive_template[0];
+            let (ref_at_x, ref_at_y) = self.adaptive_template[1];
+
+            pixel(-1, 0)
+                | pixel(1, -1) << 1
+                | pixel(0, -1) << 2
+                | pixel(i64::from(at_x), i64::from(at_y)) << 3
+                | reference(1, 1) << 4
+                | reference(0, 1) << 5
+                | reference(-1, 1) << 6
+                | reference(1, 0) << 7
+                | reference(0, 0) << 8
+                | reference(-1, 0) << 9
+                | reference(1, -1) << 10
+                | reference(0, -1) << 11
+                | reference(i64::from(ref_at_x), i64::from(ref_at_y)) << 12
+        } else {
+            pixel(-1, 0)
+                | pixel(1, -1) << 1
+                | pixel(0, -1) << 2
+                | pixel(-1, -1) << 3
+                | reference(1, 1) << 4
+                | reference(0, 1) << 5
+                | reference(1, 0) << 6
+                | reference(0, 0) << 7
+                | reference(-1, 0) << 8
+                | reference(0, -1) << 9
+        }
+    }
+
+    /// With typical prediction, a pixel whose 3x3 neighbourhood in the
+    /// reference bitmap is a single color takes that color
+    fn typical_pixel(&self, reference: &Bitmap, x: usize, y: usize) -> Option<u8> {
+        let rx = x as i64 - self.reference_dx;
+        let ry = y as i64 - self.reference_dy;
+
+        let value = reference.get(rx, ry);
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if reference.get(rx + dx, ry + dy) != value {
+                    return None;
+                }
+            }
+        }
+
+        Some(value)
+    }
+}
+
+/// Decode a bitmap as a refinement of `reference`
+pub(super) fn decode_refinement_region(
+    decoder: &mut ArithmeticDecoder,
+    contexts: &mut [Context],
+    width: usize,
+    height: usize,
+    reference: &Bitmap,
+    params: &RefinementParams,
+) -> PdfResult<Bitmap> {
+    let mut bitmap = Bitmap::new(width, height, 0)?;
+
+    let mut typical = false;
+
+    for y in 0..height {
+        if params.typical_prediction {
+            // the context in which only the reference pixel corresponding to
+            // the pixel being decoded is set
+            let context = if params.template == 0 { 0x0100 } else { 0x0080 };
+            typical ^= decoder.decode(&mut contexts[context]) == 1;
+        }
+
+        for x in 0..width {
+            if typical {
+                if let Some(pixel) = params.typical_pixel(reference, x, y) {
+                    bitmap.set(x, y, pixel);
+                    continue;
+                }
+            }
+
+            let context = params.context(&bitmap, reference, x, y);
+            let pixel = decoder.decode(&mut contexts[context]);
+
+            bitmap.set(x, y, pixel);
+        }
+    }
+
+    Ok(bitmap)
+}
@@ -0,0 +1,535 @@
+//! Huffman coded integers (T.88 Annex B)
+
+use std::collections::HashMap;
+
+use crate::{error::PdfResult, parse_binary::BinaryParser};
+
+use super::SegmentReader;
+
+/// Reads the data one bit at a time, most significant bit first
+pub(super) struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, bit: 0 }
+    }
+
+    pub fn read_bit(&mut self) -> PdfResult<u32> {
+        let Some(byte) = self.data.get(self.bit / 8) else {
+            anyhow::bail!("unexpected end of JBIG2 Huffman coded data");
+        };
+
+        let bit = (byte >> (7 - self.bit % 8)) & 1;
+        self.bit += 1;
+
+        Ok(u32::from(bit))
+    }
+
+    pub fn read_bits(&mut self, len: u32) -> PdfResult<u32> {
+        let mut value: u32 = 0;
+
+        for _ in 0..len {
+            value = (value << 1) | self.read_bit()?;
+        }
+
+        Ok(value)
+    }
+
+    pub fn align(&mut self) {
+        self.bit = self.bit.next_multiple_of(8);
+    }
+
+    /// The data following the current byte boundary
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data.get(self.bit.div_ceil(8)..).unwrap_or(&[])
+    }
+
+    pub fn skip_bytes(&mut self, len: usize) {
+        self.bit = self.bit.next_multiple_of(8) + len * 8;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Normal,
+
+    /// Values below the lowest range, which are given by a 32 bit offset
+    /// subtracted from the range low
+    Lower,
+
+    /// Values above the highest range, which are given by a 32 bit offset
+    /// added to the range low
+    Upper,
+
+    OutOfBand,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TableLine {
+    prefix_len: u32,
+    range_len: u32,
+    range_low: i32,
+    kind: LineKind,
+}
+
+#[derive(Debug)]
+pub(super) struct HuffmanTable {
+    /// The table line for each `(prefix length, prefix code)`
+    codes: HashMap<(u32, u32), TableLine>,
+}
+
+/// The lines of one of the standard tables, as (prefix length, range length,
+/// range low). The lower and upper range lines are (prefix length, range low),
+/// and the out-of-band line is given by its prefix length
+struct StandardTable {
+    lines: &'static [(u32, u32, i32)],
+    lower: Option<(u32, i32)>,
+    upper: Option<(u32, i32)>,
+    out_of_band: Option<u32>,
+}
+
+const STANDARD_TABLES: [StandardTable; 15] = [
+    // B.1
+    StandardTable {
+        lines: &[(1, 4, 0), (2, 8, 16), (3, 16, 272)],
+        lower: None,
+        upper: Some((3, 65808)),
+        out_of_band: None,
+    },
+    // B.2
+    StandardTable {
+        lines: &[(1, 0, 0), (2, 0, 1), (3, 0, 2), (4, 3, 3), (5, 6, 11)],
+        lower: None,
+        upper: Some((6, 75)),
+        out_of_band: Some(6),
+    },
+    // B.3
+    StandardTable {
+        lines: &[
+            (8, 8, -256),
+            (1, 0, 0),
+            (2, 0, 1),
+            (3, 0, 2),
+            (4, 3, 3),
+            (5, 6, 11),
+        ],
+        lower: Some((8, -257)),
+        upper: Some((7, 75)),
+        out_of_band: Some(6),
+    },
+    // B.4
+    StandardTable {
+        lines: &[(1, 0, 1), (2, 0, 2), (3, 0, 3), (4, 3, 4), (5, 6, 12)],
+        lower: None,
+        upper: Some((5, 76)),
+        out_of_band: None,
+    },
+    // B.5
+    StandardTable {
+        lines: &[
+            (7, 8, -255),
+            (1, 0, 1),
+            (2, 0, 2),
+            (3, 0, 3),
+            (4, 3, 4),
+            (5, 6, 12),
+        ],
+        lower: Some((7, -256)),
+        upper: Some((6, 76)),
+        out_of_band: None,
+    },
+    // B.6
+    StandardTable {
+        lines: &[
+            (5, 10, -2048),
+            (4, 9, -1024),
+            (4, 8, -512),
+            (4, 7, -256),
+            (5, 6, -128),
+            (5, 5, -64),
+            (4, 5, -32),
+            (2, 7, 0),
+            (3, 7, 128),
+            (3, 8, 256),
+            (4, 9, 512),
+            (4, 10, 1024),
+        ],
+        lower: Some((6, -2049)),
+        upper: Some((6, 2048)),
+        out_of_band: None,
+    },
+    // B.7
+    StandardTable {
+        lines: &[
+            (4, 9, -1024),
+            (3, 8, -512),
+            (4, 7, -256),
+            (5, 6, -128),
+            (5, 5, -64),
+            (4, 5, -32),
+            (4, 5, 0),
+            (5, 5, 32),
+            (5, 6, 64),
+            (4, 7, 128),
+            (3, 8, 256),
+            (3, 9, 512),
+            (3, 10, 1024),
+        ],
+        lower: Some((5, -1025)),
+        upper: Some((5, 2048)),
+        out_of_band: None,
+    },
+    // B.8
+    StandardTable {
+        lines: &[
+            (8, 3, -15),
+            (9, 1, -7),
+            (8, 1, -5),
+            (9, 0, -3),
+            (7, 0, -2),
+            (4, 0, -1),
+            (2, 1, 0),
+            (5, 0, 2),
+            (6, 0, 3),
+            (3, 4, 4),
+            (6, 1, 20),
+            (4, 4, 22),
+            (4, 5, 38),
+            (5, 6, 70),
+            (5, 7, 134),
+            (6, 7, 262),
+            (7, 8, 390),
+            (6, 10, 646),
+        ],
+        lower: Some((9, -16)),
+        upper: Some((9, 1670)),
+        out_of_band: Some(2),
+    },
+    // B.9
+    StandardTable {
+        lines: &[
+            (8, 4, -31),
+            (9, 2, -15),
+            (8, 2, -11),
+            (9, 1, -7),
+            (7, 1, -5),
+            (4, 1, -3),
+            (3, 1, -1),
+            (3, 1, 1),
+            (5, 1, 3),
+            (6, 1, 5),
+            (3, 5, 7),
+            (6, 2, 39),
+            (4, 5, 43),
+            (4, 6, 75),
+            (5, 7, 139),
+            (5, 8, 267),
+            (6, 8, 523),
+            (7, 9, 779),
+            (6, 11, 1291),
+        ],
+        lower: Some((9, -32)),
+        upper: Some((9, 3339)),
+        out_of_band: Some(2),
+    },
+    // B.10
+    StandardTable {
+        lines: &[
+            (7, 4, -21),
+            (8, 0, -5),
+            (7, 0, -4),
+            (5, 0, -3),
+            (2, 2, -2),
+            (5, 0, 2),
+            (6, 0, 3),
+            (7, 0, 4),
+            (8, 0, 5),
+            (2, 6, 6),
+            (5, 5, 70),
+            (6, 5, 102),
+            (6, 6, 134),
+            (6, 7, 198),
+            (6, 8, 326),
+            (6, 9, 582),
+            (6, 10, 1094),
+            (7, 11, 2118),
+        ],
+        lower: Some((8, -22)),
+        upper: Some((8, 4166)),
+        out_of_band: Some(2),
+    },
+    // B.11
+    StandardTable {
+        lines: &[
+            (1, 0, 1),
+            (2, 1, 2),
+            (4, 0, 4),
+            (4, 1, 5),
+            (5, 1, 7),
+            (5, 2, 9),
+            (6, 2, 13),
+            (7, 2, 17),
+            (7, 3, 21),
+            (7, 4, 29),
+            (7, 5, 45),
+            (7, 6, 77),
+        ],
+        lower: None,
+        upper: Some((7, 141)),
+        out_of_band: None,
+    },
+    // B.12
+    StandardTable {
+        lines: &[
+            (1, 0, 1),
+            (2, 0, 2),
+            (3, 1, 3),
+            (5, 0, 5),
+            (5, 1, 6),
+            (6, 1, 8),
+            (7, 0, 10),
+            (7, 1, 11),
+            (7, 2, 13),
+            (7, 3, 17),
+            (7, 4, 25),
+            (8, 5, 41),
+        ],
+        lower: None,
+        upper: Some((8, 73)),
+        out_of_band: None,
+    },
+    // B.13
+    StandardTable {
+        lines: &[
+            (1, 0, 1),
+            (3, 0, 2),
+            (4, 0, 3),
+            (5, 0, 4),
+            (4, 1, 5),
+            (3, 3, 7),
+            (6, 1, 15),
+            (6, 2, 17),
+            (6, 3, 21),
+            (6, 4, 29),
+            (6, 5, 45),
+            (7, 6, 77),
+        ],
+        lower: None,
+        upper: Some((7, 141)),
+        out_of_band: None,
+    },
+    // B.14
+    StandardTable {
+        lines: &[(3, 0, -2), (3, 0, -1), (1, 0, 0), (3, 0, 1), (3, 0, 2)],
+        lower: None,
+        upper: None,
+        out_of_band: None,
+    },
+    // B.15
+    StandardTable {
+        lines: &[
+            (7, 4, -24),
+            (6, 2, -8),
+            (5, 1, -4),
+            (4, 0, -2),
+            (3, 0, -1),
+            (1, 0, 0),
+            (3, 0, 1),
+            (4, 0, 2),
+            (5, 1, 3),
+            (6, 2, 5),
+            (7, 4, 9),
+        ],
+        lower: Some((7, -25)),
+        upper: Some((7, 25)),
+        out_of_band: None,
+    },
+];
+
+impl HuffmanTable {
+    /// Assign prefix codes to the lines of a table, in order of increasing
+    /// prefix length and then in the order the lines appear in. Lines with a
+    /// prefix length of 0 are never used
+    fn new(lines: Vec<TableLine>) -> Self {
+        let max_len = lines.iter().map(|line| line.prefix_len).max().unwrap_or(0);
+
+        let mut codes = HashMap::new();
+        let mut first_code = 0;
+        let mut prev_len_count = 0;
+
+        for len in 1..=max_len {
+            first_code = (first_code + prev_len_count) << 1;
+            prev_len_count = 0;
+
+            for &line in lines.iter().filter(|line| line.prefix_len == len) {
+                codes.insert((len, first_code + prev_len_count), line);
+                prev_len_count += 1;
+            }
+        }
+
+        Self { codes }
+    }
+
+    /// One of the standard tables B.1 through B.15
+    pub fn standard(table: usize) -> Self {
+        let StandardTable {
+            lines,
+            lower,
+            upper,
+            out_of_band,
+        } = &STANDARD_TABLES[table - 1];
+
+        let mut table_lines = lines
+            .iter()
+            .map(|&(prefix_len, range_len, range_low)| TableLine {
+                prefix_len,
+                range_len,
+                range_low,
+                kind: LineKind::Normal,
+            })
+            .collect::<Vec<_>>();
+
+        table_lines.extend(lower.map(|(prefix_len, range_low)| TableLine {
+            prefix_len,
+            range_len: 32,
+            range_low,
+            kind: LineKind::Lower,
+        }));
+
+        table_lines.extend(upper.map(|(prefix_len, range_low)| TableLine {
+            prefix_len,
+            range_len: 32,
+            range_low,
+            kind: LineKind::Upper,
+        }));
+
+        table_lines.extend(out_of_band.map(|prefix_len| TableLine {
+            prefix_len,
+            range_len: 0,
+            range_low: 0,
+            kind: LineKind::OutOfBand,
+        }));
+
+        Self::new(table_lines)
+    }
+
+    /// A table decoding to the index of each prefix length, as used for the
+    /// symbol IDs of text regions
+    pub fn from_prefix_lengths(lengths: &[u32]) -> Self {
+        Self::new(
+            lengths
+                .iter()
+                .enumerate()
+                .map(|(idx, &prefix_len)| TableLine {
+                    prefix_len,
+                    range_len: 0,
+                    range_low: idx as i32,
+                    kind: LineKind::Normal,
+                })
+                .collect(),
+        )
+    }
+
+    /// A table given by a table segment (7.4.13)
+    pub fn from_segment(data: &[u8]) -> PdfResult<Self> {
+        let mut parser = SegmentReader::new(data);
+
+        let flags = parser.next()?;
+        let has_out_of_band = flags & 1 != 0;
+        let prefix_size = u32::from((flags >> 1) & 0b111) + 1;
+        let range_size = u32::from((flags >> 4) & 0b111) + 1;
+
+        let low = parser.parse_i32()?;
+        let high = parser.parse_i32()?;
+
+        let mut reader = BitReader::new(&data[parser.cursor()..]);
+        let mut lines = Vec::new();
+
+        let mut range_low = low;
+
+        while range_low < high {
+            let prefix_len = reader.read_bits(prefix_size)?;
+            let range_len = reader.read_bits(range_size)?;
+
+            lines.push(TableLine {
+                prefix_len,
+                range_len,
+                range_low,
+                kind: LineKind::Normal,
+            });
+
+            range_low = range_low.saturating_add(1 << range_len.min(31));
+        }
+
+        lines.push(TableLine {
+            prefix_len: reader.read_bits(prefix_size)?,
+            range_len: 32,
+            range_low: low - 1,
+            kind: LineKind::Lower,
+        });
+
+        lines.push(TableLine {
+            prefix_len: reader.read_bits(prefix_size)?,
+            range_len: 32,
+            range_low: high,
+            kind: LineKind::Upper,
+        });
+
+        if has_out_of_band {
+            lines.push(TableLine {
+                prefix_len: reader.read_bits(prefix_size)?,
+                range_len: 0,
+                range_low: 0,
+                kind: LineKind::OutOfBand,
+            });
+        }
+
+        Ok(Self::new(lines))
+    }
+
+    /// Returns `None` for the out-of-band value
+    pub fn decode(&self, reader: &mut BitReader) -> PdfResult<Option<i32>> {
+        let mut code = 0;
+
+        for len in 1..=32 {
+            code = (code << 1) | reader.read_bit()?;
+
+            let Some(line) = self.codes.get(&(len, code)) else {
+                continue;
+            };
+
+            let offset = reader.read_bits(line.range_len)?;
+
+            return Ok(match line.kind {
+                LineKind::Normal | LineKind::Upper => {
+                    Some(line.range_low.wrapping_add(offset as i32))
+                }
+                LineKind::Lower => Some(line.range_low.wrapping_sub(offset as i32)),
+                LineKind::OutOfBand => None,
+            });
+        }
+
+        anyhow::bail!("invalid JBIG2 Huffman code")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitReader, HuffmanTable};
+
+    #[test]
+    fn standard_table_codes() {
+        // B.8: 0 is `00` followed by 1 bit, the out-of-band value is `01`, and
+        // the lower range is `111111110` followed by 32 bits
+        let table = HuffmanTable::standard(8);
+        let data = [0b0010_1111, 0b1111_1000, 0, 0, 0, 0b0000_1000];
+        let mut reader = BitReader::new(&data);
+
+        assert_eq!(table.decode(&mut reader).unwrap(), Some(1));
+        assert_eq!(table.decode(&mut reader).unwrap(), None);
+        assert_eq!(table.decode(&mut reader).unwrap(), Some(-18));
+    }
+}
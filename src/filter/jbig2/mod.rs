@@ -0,0 +1,857 @@
+//! Decoding of JBIG2 (ITU-T T.88) images, in the embedded stream format used
+//! by PDF
+//!
+//! The image data is a sequence of segments, each of which may refer to
+//! earlier segments. Segments shared between several images, such as symbol
+//! dictionaries, may be placed in a separate `JBIG2Globals` stream, which is
+//! decoded before the segments of the image itself
+//!
+//! Halftone regions are not supported
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    error::PdfResult,
+    filter::mq::{contexts, ArithmeticDecoder},
+    parse_binary::BinaryParser,
+    stream::Stream,
+};
+
+use bitmap::{Bitmap, CombinationOperator};
+use generic::{
+    decode_generic_region, decode_mmr_region, decode_refinement_region, GenericRegionParams,
+    RefinementParams,
+};
+use huffman::{BitReader, HuffmanTable};
+use symbol::{decode_symbol_dictionary, symbol_code_len};
+use text::{
+    decode_text_region, ReferenceCorner, SymbolIdTable, TextRegionCoding, TextRegionIntegers,
+    TextRegionParams, TextRegionTables,
+};
+
+mod arithmetic;
+mod bitmap;
+mod generic;
+mod huffman;
+mod symbol;
+mod text;
+
+#[derive(Debug, FromObj)]
+pub struct Jbig2DecoderParams<'a> {
+    /// A stream containing the JBIG2 global (page 0) segments. Global segments
+    /// shall be placed in this stream even if only a single JBIG2 image
+    /// XObject refers to it
+    #[field("JBIG2Globals")]
+    pub(crate) globals: Option<Stream<'a>>,
+}
+
+const SYMBOL_DICTIONARY: u8 = 0;
+const INTERMEDIATE_TEXT_REGION: u8 = 4;
+const IMMEDIATE_TEXT_REGION: u8 = 6;
+const IMMEDIATE_LOSSLESS_TEXT_REGION: u8 = 7;
+const PATTERN_DICTIONARY: u8 = 16;
+const INTERMEDIATE_HALFTONE_REGION: u8 = 20;
+const IMMEDIATE_HALFTONE_REGION: u8 = 22;
+const IMMEDIATE_LOSSLESS_HALFTONE_REGION: u8 = 23;
+const INTERMEDIATE_GENERIC_REGION: u8 = 36;
+const IMMEDIATE_GENERIC_REGION: u8 = 38;
+const IMMEDIATE_LOSSLESS_GENERIC_REGION: u8 = 39;
+const INTERMEDIATE_REFINEMENT_REGION: u8 = 40;
+const IMMEDIATE_REFINEMENT_REGION: u8 = 42;
+const IMMEDIATE_LOSSLESS_REFINEMENT_REGION: u8 = 43;
+const PAGE_INFORMATION: u8 = 48;
+const END_OF_STRIPE: u8 = 50;
+const TABLES: u8 = 53;
+
+struct SegmentReader<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> SegmentReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.buffer[self.cursor..]
+    }
+
+    fn take(&mut self, len: usize) -> PdfResult<&'a [u8]> {
+        let Some(data) = self.buffer.get(self.cursor..self.cursor + len) else {
+            anyhow::bail!("unexpected eof");
+        };
+
+        self.cursor += len;
+
+        Ok(data)
+    }
+
+    /// The positions of adaptive template pixels, as pairs of signed bytes
+    fn adaptive_template<const N: usize>(&mut self) -> PdfResult<[(i8, i8); N]> {
+        let mut adaptive_template = [(0, 0); N];
+
+        for (x, y) in &mut adaptive_template {
+            *x = self.next()? as i8;
+            *y = self.next()? as i8;
+        }
+
+        Ok(adaptive_template)
+    }
+}
+
+impl<'a> BinaryParser for SegmentReader<'a> {
+    fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+    fn cursor(&self) -> usize {
+        self.cursor
+    }
+    fn cursor_mut(&mut self) -> &mut usize {
+        &mut self.cursor
+    }
+}
+
+/// The tables of the table segments a segment refers to, which are used in
+/// order wherever the segment selects a user supplied table
+struct UserTables {
+    tables: std::vec::IntoIter<Rc<HuffmanTable>>,
+}
+
+impl UserTables {
+    fn next(&mut self) -> PdfResult<Rc<HuffmanTable>> {
+        match self.tables.next() {
+            Some(table) => Ok(table),
+            None => anyhow::bail!("missing JBIG2 user supplied Huffman table"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SegmentHeader {
+    number: u32,
+    kind: u8,
+    referred_to: Vec<u32>,
+
+    /// `None` if the length is not known until the segment has been decoded,
+    /// which is only allowed for immediate generic regions
+    data_len: Option<usize>,
+}
+
+/// The location of a region on the page, and how it is to be drawn onto it
+#[derive(Debug, Clone, Copy)]
+struct RegionInfo {
+    width: usize,
+    height: usize,
+    x: i64,
+    y: i64,
+    combination_operator: CombinationOperator,
+}
+
+impl RegionInfo {
+    fn parse(parser: &mut SegmentReader) -> PdfResult<Self> {
+        Ok(Self {
+            width: parser.parse_u32()? as usize,
+            height: parser.parse_u32()? as usize,
+            x: i64::from(parser.parse_u32()?),
+            y: i64::from(parser.parse_u32()?),
+            combination_operator: CombinationOperator::from_integer(i32::from(
+                parser.next()? & 0b111,
+            ))?,
+        })
+    }
+}
+
+/// The result of decoding a segment, which may be used by the segments that
+/// refer to it
+enum SegmentResult {
+    Symbols(Vec<Rc<Bitmap>>),
+    Table(Rc<HuffmanTable>),
+    Region(Bitmap),
+}
+
+struct Page {
+    bitmap: Bitmap,
+
+    /// Whether the height of the page grows as stripes are decoded
+    unknown_height: bool,
+}
+
+pub struct Jbig2Decoder<'a> {
+    buffer: &'a [u8],
+    globals: Option<&'a [u8]>,
+    results: HashMap<u32, SegmentResult>,
+    page: Option<Page>,
+}
+
+impl<'a> Jbig2Decoder<'a> {
+    pub fn new(buffer: &'a [u8], globals: Option<&'a [u8]>) -> Self {
+        Self {
+            buffer,
+            globals,
+            results: HashMap::new(),
+            page: None,
+        }
+    }
+
+    /// Decode the page, as rows of 1 bit pixels padded to a whole byte in which
+    /// 0 is black
+    pub fn decode(mut self) -> PdfResult<Vec<u8>> {
+        if let Some(globals) = self.globals {
+            self.decode_segments(globals)?;
+        }
+
+        self.decode_segments(self.buffer)?;
+
+        match self.page {
+            Some(page) => Ok(page.bitmap.to_packed()),
+            None => anyhow::bail!("JBIG2 data has no page information segment"),
+        }
+    }
+
+    fn decode_segments(&mut self, buffer: &[u8]) -> PdfResult<()> {
+        let mut parser = SegmentReader::new(buffer);
+
+        while parser.peek().is_some() {
+            let header = parse_segment_header(&mut parser)?;
+
+            let data_len = match header.data_len {
+                Some(len) => len,
+                None => unknown_data_len(&parser)?,
+            };
+
+            let data = parser.take(data_len)?;
+
+            self.decode_segment(&header, data)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode_segment(&mut self, header: &SegmentHeader, data: &[u8]) -> PdfResult<()> {
+        match header.kind {
+            SYMBOL_DICTIONARY => {
+                let input_symbols = self.referred_symbols(header);
+                let mut user_tables = self.referred_tables(header);
+
+                let symbols = decode_symbol_dictionary(data, &input_symbols, &mut user_tables)?;
+
+                self.results
+                    .insert(header.number, SegmentResult::Symbols(symbols));
+            }
+            INTERMEDIATE_TEXT_REGION | IMMEDIATE_TEXT_REGION | IMMEDIATE_LOSSLESS_TEXT_REGION => {
+                let mut parser = SegmentReader::new(data);
+                let info = RegionInfo::parse(&mut parser)?;
+
+                let region = self.decode_text_region(header, &info, parser.rest())?;
+
+                self.place_region(header, &info, region)?;
+            }
+            PATTERN_DICTIONARY
+            | INTERMEDIATE_HALFTONE_REGION
+            | IMMEDIATE_HALFTONE_REGION
+            | IMMEDIATE_LOSSLESS_HALFTONE_REGION => {
+                anyhow::bail!("JBIG2 halftone regions are unsupported")
+            }
+            INTERMEDIATE_GENERIC_REGION
+            | IMMEDIATE_GENERIC_REGION
+            | IMMEDIATE_LOSSLESS_GENERIC_REGION => {
+                let mut parser = SegmentReader::new(data);
+                let mut info = RegionInfo::parse(&mut parser)?;
+
+                // when the length of the segment is unknown, it ends with the
+                // number of rows in the region
+                if header.data_len.is_none() {
+                    let Some(rows) = data.get(data.len() - 4..) else {
+                        anyhow::bail!("unexpected eof");
+                    };
+
+                    info.height = u32::from_be_bytes(rows.try_into()?) as usize;
+                }
+
+                let region = decode_generic_region_segment(&info, &mut parser)?;
+
+                self.place_region(header, &info, region)?;
+            }
+            INTERMEDIATE_REFINEMENT_REGION
+            | IMMEDIATE_REFINEMENT_REGION
+            | IMMEDIATE_LOSSLESS_REFINEMENT_REGION => {
+                let mut parser = SegmentReader::new(data);
+                let mut info = RegionInfo::parse(&mut parser)?;
+
+                let region = self.decode_refinement_region(header, &info, &mut parser)?;
+
+                // the refined region takes the place of what it refines
+                info.combination_operator = CombinationOperator::Replace;
+
+                self.place_region(header, &info, region)?;
+            }
+            PAGE_INFORMATION => {
+                let mut parser = SegmentReader::new(data);
+
+                let width = parser.parse_u32()? as usize;
+                let height = parser.parse_u32()?;
+
+                // the resolution
+                parser.take(8)?;
+
+                let flags = parser.next()?;
+                let default_pixel = (flags >> 2) & 1;
+
+                let unknown_height = height == u32::MAX;
+                let height = if unknown_height { 0 } else { height as usize };
+
+                self.page = Some(Page {
+                    bitmap: Bitmap::new(width, height, default_pixel)?,
+                    unknown_height,
+                });
+            }
+            END_OF_STRIPE => {
+                let end_row = SegmentReader::new(data).parse_u32()? as usize;
+
+                let page = self.page_mut()?;
+
+                if page.unknown_height {
+                    page.bitmap.grow(end_row + 1, 0)?;
+                }
+            }
+            TABLES => {
+                let table = HuffmanTable::from_segment(data)?;
+
+                self.results
+                    .insert(header.number, SegmentResult::Table(Rc::new(table)));
+            }
+            // the remaining segments, such as the end of the page or file,
+            // profiles and extensions, do not affect the image
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn page_mut(&mut self) -> PdfResult<&mut Page> {
+        match &mut self.page {
+            Some(page) => Ok(page),
+            None => anyhow::bail!("JBIG2 region segment before page information segment"),
+        }
+    }
+
+    /// The symbols exported by the symbol dictionaries a segment refers to
+    fn referred_symbols(&self, header: &SegmentHeader) -> Vec<Rc<Bitmap>> {
+        header
+            .referred_to
+            .iter()
+            .filter_map(|number| match self.results.get(number) {
+                Some(SegmentResult::Symbols(symbols)) => Some(symbols),
+                _ => None,
+            })
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    fn referred_tables(&self, header: &SegmentHeader) -> UserTables {
+        let tables = header
+            .referred_to
+            .iter()
+            .filter_map(|number| match self.results.get(number) {
+                Some(SegmentResult::Table(table)) => Some(Rc::clone(table)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        UserTables {
+            tables: tables.into_iter(),
+        }
+    }
+
+    /// Intermediate regions are kept to be refined by later segments, while
+    /// immediate regions are drawn onto the page
+    fn place_region(
+        &mut self,
+        header: &SegmentHeader,
+        info: &RegionInfo,
+        region: Bitmap,
+    ) -> PdfResult<()> {
+        if matches!(
+            header.kind,
+            INTERMEDIATE_TEXT_REGION | INTERMEDIATE_GENERIC_REGION | INTERMEDIATE_REFINEMENT_REGION
+        ) {
+            self.results
+                .insert(header.number, SegmentResult::Region(region));
+
+            return Ok(());
+        }
+
+        let page = self.page_mut()?;
+
+        if page.unknown_height {
+            page.bitmap
+                .grow(usize::try_from(info.y)? + region.height, 0)?;
+        }
+
+        page.bitmap
+            .combine(&region, info.x, info.y, info.combination_operator);
+
+        Ok(())
+    }
+
+    /// 7.4.4
+    fn decode_text_region(
+        &self,
+        header: &SegmentHeader,
+        info: &RegionInfo,
+        data: &[u8],
+    ) -> PdfResult<Bitmap> {
+        let mut parser = SegmentReader::new(data);
+
+        let flags = parser.parse_u16()?;
+
+        let huffman = flags & 1 != 0;
+        let refine = flags & 0b10 != 0;
+        let refinement_template = ((flags >> 15) & 1) as u8;
+
+        // a signed 5 bit integer
+        let ds_offset = i32::from((flags >> 10) & 0b1_1111);
+        let ds_offset = if ds_offset >= 16 {
+            ds_offset - 32
+        } else {
+            ds_offset
+        };
+
+        let huffman_flags = if huffman { parser.parse_u16()? } else { 0 };
+
+        let refinement_adaptive_template = if refine && refinement_template == 0 {
+            parser.adaptive_template::<2>()?
+        } else {
+            [(0, 0); 2]
+        };
+
+        let params = TextRegionParams {
+            width: info.width,
+            height: info.height,
+            num_instances: parser.parse_u32()?,
+            refine,
+            log_strips: u32::from((flags >> 2) & 0b11),
+            reference_corner: ReferenceCorner::from_integer(i32::from((flags >> 4) & 0b11))?,
+            transposed: flags & (1 << 6) != 0,
+            combination_operator: CombinationOperator::from_integer(i32::from(
+                (flags >> 7) & 0b11,
+            ))?,
+            default_pixel: ((flags >> 9) & 1) as u8,
+            ds_offset,
+            refinement_template,
+            refinement_adaptive_template,
+        };
+
+        let symbols = self.referred_symbols(header);
+
+        let mut refinement_contexts = contexts(RefinementParams::num_contexts(refinement_template));
+
+        if !huffman {
+            let mut decoder = ArithmeticDecoder::new(parser.rest());
+            let mut integers = TextRegionIntegers::new(symbol_code_len(symbols.len()));
+
+            let mut coding = TextRegionCoding::Arithmetic {
+                decoder: &mut decoder,
+                integers: &mut integers,
+            };
+
+            return decode_text_region(&mut coding, &symbols, &params, &mut refinement_contexts);
+        }
+
+        let mut user_tables = self.referred_tables(header);
+
+        let mut select = |selection: u16, standard: &[usize]| -> PdfResult<Rc<HuffmanTable>> {
+            match standard.get(selection as usize) {
+                Some(&table) => Ok(Rc::new(HuffmanTable::standard(table))),
+                None if selection == 3 => user_tables.next(),
+                None => anyhow::bail!("invalid text region table selection"),
+            }
+        };
+
+        let first_s = select(huffman_flags & 0b11, &[6, 7])?;
+        let delta_s = select((huffman_flags >> 2) & 0b11, &[8, 9, 10])?;
+        let strip_t = select((huffman_flags >> 4) & 0b11, &[11, 12, 13])?;
+        let refinement_width = select((huffman_flags >> 6) & 0b11, &[14, 15])?;
+        let refinement_height = select((huffman_flags >> 8) & 0b11, &[14, 15])?;
+        let refinement_x = select((huffman_flags >> 10) & 0b11, &[14, 15])?;
+        let refinement_y = select((huffman_flags >> 12) & 0b11, &[14, 15])?;
+        let refinement_size = select((huffman_flags >> 14) & 1, &[1])?;
+
+        let mut reader = BitReader::new(parser.rest());
+
+        let symbol_ids = SymbolIdTable::Table(decode_symbol_id_table(&mut reader, symbols.len())?);
+
+        let tables = TextRegionTables {
+            first_s,
+            delta_s,
+            strip_t,
+            refinement_width,
+            refinement_height,
+            refinement_x,
+            refinement_y,
+            refinement_size,
+            symbol_ids,
+        };
+
+        let mut coding = TextRegionCoding::Huffman {
+            reader: &mut reader,
+            tables: &tables,
+        };
+
+        decode_text_region(&mut coding, &symbols, &params, &mut refinement_contexts)
+    }
+
+    /// 7.4.7
+    fn decode_refinement_region(
+        &self,
+        header: &SegmentHeader,
+        info: &RegionInfo,
+        parser: &mut SegmentReader,
+    ) -> PdfResult<Bitmap> {
+        let flags = parser.next()?;
+
+        let template = flags & 1;
+
+        let adaptive_template = if template == 0 {
+            parser.adaptive_template::<2>()?
+        } else {
+            [(0, 0); 2]
+        };
+
+        let params = RefinementParams {
+            template,
+            typical_prediction: flags & 0b10 != 0,
+            adaptive_template,
+            reference_dx: 0,
+            reference_dy: 0,
+        };
+
+        // the reference is either an intermediate region, or the part of the
+        // page the region is drawn onto
+        let referred_region =
+            header
+                .referred_to
+                .iter()
+                .find_map(|number| match self.results.get(number) {
+                    Some(SegmentResult::Region(region)) => Some(region),
+                    _ => None,
+                });
+
+        let page_region;
+
+        let reference = match referred_region {
+            Some(region) => region,
+            None => {
+                let Some(page) = &self.page else {
+                    anyhow::bail!("JBIG2 region segment before page information segment");
+                };
+
+                page_region = page
+                    .bitmap
+                    .region(info.x, info.y, info.width, info.height)?;
+
+                &page_region
+            }
+        };
+
+        let mut decoder = ArithmeticDecoder::new(parser.rest());
+        let mut contexts = contexts(RefinementParams::num_contexts(template));
+
+        decode_refinement_region(
+            &mut decoder,
+            &mut contexts,
+            info.width,
+            info.height,
+            reference,
+            &params,
+        )
+    }
+}
+
+/// 7.2
+fn parse_segment_header(parser: &mut SegmentReader) -> PdfResult<SegmentHeader> {
+    let number = parser.parse_u32()?;
+
+    let flags = parser.next()?;
+    let kind = flags & 0b11_1111;
+    let large_page_association = flags & (1 << 6) != 0;
+
+    let count_and_retention = parser.next()?;
+
+    // the number of referred-to segments is in the top 3 bits, unless they are
+    // all set, in which case it is given by the following 29 bits
+    let num_referred_to = if count_and_retention >> 5 == 7 {
+        let count = u32::from_be_bytes([
+            count_and_retention & 0b1_1111,
+            parser.next()?,
+            parser.next()?,
+            parser.next()?,
+        ]) as usize;
+
+        // the retention flags, one bit for the segment and each it refers to
+        parser.take((count + 1).div_ceil(8))?;
+
+        count
+    } else {
+        usize::from(count_and_retention >> 5)
+    };
+
+    let mut referred_to = Vec::with_capacity(num_referred_to);
+
+    for _ in 0..num_referred_to {
+        referred_to.push(if number <= 256 {
+            u32::from(parser.next()?)
+        } else if number <= 65536 {
+            u32::from(parser.parse_u16()?)
+        } else {
+            parser.parse_u32()?
+        });
+    }
+
+    // the page the segment is associated with
+    if large_page_association {
+        parser.parse_u32()?;
+    } else {
+        parser.next()?;
+    }
+
+    let data_len = match parser.parse_u32()? {
+        u32::MAX => None,
+        len => Some(len as usize),
+    };
+
+    Ok(SegmentHeader {
+        number,
+        kind,
+        referred_to,
+        data_len,
+    })
+}
+
+/// The length of an immediate generic region segment whose length is not
+/// given in its header, which is found by scanning for the end of its coded
+/// data (7.2.7)
+fn unknown_data_len(parser: &SegmentReader) -> PdfResult<usize> {
+    let data = parser.rest();
+
+    // the region segment information field, followed by the region flags
+    let Some(&flags) = data.get(17) else {
+        anyhow::bail!("unexpected eof");
+    };
+
+    let mmr = flags & 1 != 0;
+
+    let end_marker: [u8; 2] = if mmr { [0x00, 0x00] } else { [0xff, 0xac] };
+
+    match data[18..]
+        .windows(2)
+        .position(|window| window == end_marker)
+    {
+        // the marker is followed by the number of rows in the region
+        Some(pos) => Ok(18 + pos + 2 + 4),
+        None => anyhow::bail!("unable to find end of JBIG2 generic region"),
+    }
+}
+
+/// 7.4.6
+fn decode_generic_region_segment(
+    info: &RegionInfo,
+    parser: &mut SegmentReader,
+) -> PdfResult<Bitmap> {
+    let flags = parser.next()?;
+
+    let mmr = flags & 1 != 0;
+    let template = (flags >> 1) & 0b11;
+
+    if mmr {
+        return decode_mmr_region(parser.rest(), info.width, info.height);
+    }
+
+    let mut adaptive_template = [(0, 0); 4];
+
+    if template == 0 {
+        adaptive_template = parser.adaptive_template::<4>()?;
+    } else {
+        adaptive_template[0] = parser.adaptive_template::<1>()?[0];
+    }
+
+    let params = GenericRegionParams {
+        template,
+        typical_prediction: flags & 0b1000 != 0,
+        adaptive_template,
+    };
+
+    let mut decoder = ArithmeticDecoder::new(parser.rest());
+    let mut contexts = contexts(GenericRegionParams::num_contexts(template));
+
+    decode_generic_region(
+        &mut decoder,
+        &mut contexts,
+        info.width,
+        info.height,
+        &params,
+    )
+}
+
+/// The code lengths of the symbol IDs of a Huffman coded text region are
+/// themselves run length coded, using a table whose code lengths precede them
+/// (7.4.3.1.7)
+fn decode_symbol_id_table(reader: &mut BitReader, num_symbols: usize) -> PdfResult<HuffmanTable> {
+    let mut run_code_lengths = [0; 35];
+
+    for len in &mut run_code_lengths {
+        *len = reader.read_bits(4)?;
+    }
+
+    let run_codes = HuffmanTable::from_prefix_lengths(&run_code_lengths);
+
+    let mut lengths = Vec::with_capacity(num_symbols);
+
+    while lengths.len() < num_symbols {
+        let Some(code) = run_codes.decode(reader)? else {
+            anyhow::bail!("unexpected out-of-band symbol ID code length");
+        };
+
+        match code {
+            0..=31 => lengths.push(code as u32),
+            32 => {
+                let Some(&prev) = lengths.last() else {
+                    anyhow::bail!("symbol ID code length repeated before any were given");
+                };
+
+                let repeat = 3 + reader.read_bits(2)?;
+                lengths.extend((0..repeat).map(|_| prev));
+            }
+            33 => lengths.extend((0..3 + reader.read_bits(3)?).map(|_| 0)),
+            _ => lengths.extend((0..11 + reader.read_bits(7)?).map(|_| 0)),
+        }
+    }
+
+    reader.align();
+
+    lengths.truncate(num_symbols);
+
+    Ok(HuffmanTable::from_prefix_lengths(&lengths))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Jbig2Decoder;
+
+    #[test]
+    fn generic_region() {
+        let data = [
+            // page information: 16x4
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // immediate generic region: template 0 with typical prediction
+            0x00, 0x00, 0x00, 0x01, 0x26, 0x00, 0x01, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x08, 0x03, 0xff, 0xfd, 0xff, 0x02, 0xfe, 0xfe, 0xfe, 0x64, 0x96, 0xea, 0x41, 0xdb,
+            0x43, 0xff, 0xac, // end of page
+            0x00, 0x00, 0x00, 0x02, 0x31, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let decoded = Jbig2Decoder::new(&data, None).decode().unwrap();
+
+        assert_eq!(decoded, [0xaa, 0xaa, 0xaa, 0xaa, 0xf0, 0x0f, 0xff, 0xff]);
+    }
+
+    /// A symbol dictionary of two 3 pixel high symbols, exporting both
+    const SYMBOL_DICTIONARY: [u8; 37] = [
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1a, 0x00, 0x00, 0x03, 0xff,
+        0xfd, 0xff, 0x02, 0xfe, 0xfe, 0xfe, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x66,
+        0x93, 0xba, 0xb5, 0xdb, 0x0b, 0xff, 0xac,
+    ];
+
+    /// Three instances of the symbols of `SYMBOL_DICTIONARY` on a 16x4 page
+    const TEXT_PAGE: [u8; 8] = [0xaf, 0xff, 0xdc, 0x35, 0xad, 0xbb, 0xfc, 0x35];
+
+    #[test]
+    fn symbol_dictionary_and_text_region() {
+        let mut data = vec![
+            // page information: 16x4
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+
+        // the symbol dictionary, associated with the page rather than the globals
+        data.extend_from_slice(&SYMBOL_DICTIONARY[..6]);
+        data.push(0x01);
+        data.extend_from_slice(&SYMBOL_DICTIONARY[7..]);
+
+        data.extend_from_slice(&[
+            // immediate text region referring to the symbol dictionary
+            0x00, 0x00, 0x00, 0x02, 0x06, 0x20, 0x01, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x03, 0xa9, 0x05, 0x95, 0x30, 0xff, 0xac,
+            // end of page
+            0x00, 0x00, 0x00, 0x03, 0x31, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        let decoded = Jbig2Decoder::new(&data, None).decode().unwrap();
+
+        assert_eq!(decoded, TEXT_PAGE);
+    }
+
+    #[test]
+    fn symbol_dictionary_in_globals() {
+        let data = [
+            // page information: 16x4
+            0x00, 0x00, 0x00, 0x02, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // immediate text region referring to the global symbol dictionary
+            0x00, 0x00, 0x00, 0x03, 0x06, 0x20, 0x01, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x03, 0xa9, 0x05, 0x95, 0x30, 0xff, 0xac,
+            // end of page
+            0x00, 0x00, 0x00, 0x04, 0x31, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let decoded = Jbig2Decoder::new(&data, Some(&SYMBOL_DICTIONARY))
+            .decode()
+            .unwrap();
+
+        assert_eq!(decoded, TEXT_PAGE);
+
+        assert!(Jbig2Decoder::new(&data, None).decode().is_err());
+    }
+
+    #[test]
+    fn refinement_region() {
+        let data = [
+            // page information: 16x4
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // immediate generic region: template 0
+            0x00, 0x00, 0x00, 0x01, 0x26, 0x00, 0x01, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x03, 0xff, 0xfd, 0xff, 0x02, 0xfe, 0xfe, 0xfe, 0xd8, 0xf6, 0x37, 0x1d, 0x18,
+            0x16, 0x7f, 0xff, 0xac, // immediate refinement of the page: template 0
+            0x00, 0x00, 0x00, 0x02, 0x2a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0xff, 0xff, 0xff, 0xff, 0xe7, 0x20, 0xbd, 0xcf, 0xcf, 0xff, 0xac,
+            // end of page
+            0x00, 0x00, 0x00, 0x03, 0x31, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let decoded = Jbig2Decoder::new(&data, None).decode().unwrap();
+
+        assert_eq!(decoded, [0x33, 0x33, 0x99, 0x99, 0xcc, 0xcc, 0x66, 0x66]);
+    }
+
+    #[test]
+    fn oversized_page() {
+        let data = [
+            // page information: 65536x65536
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x01, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+
+        assert!(Jbig2Decoder::new(&data, None).decode().is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! Symbol dictionaries, which hold the bitmaps drawn by text regions (T.88 6.5)
+
+use std::rc::Rc;
+
+use crate::{
+    error::PdfResult,
+    filter::mq::{contexts, ArithmeticDecoder, Context},
+    parse_binary::BinaryParser,
+};
+
+use super::{
+    arithmetic::IntegerDecoder,
+    bitmap::{num_pixels, Bitmap, CombinationOperator},
+    generic::{decode_generic_region, decode_mmr_region, GenericRegionParams, RefinementParams},
+    huffman::{BitReader, HuffmanTable},
+    text::{
+        decode_text_region, ReferenceCorner, SymbolIdTable, TextRegionCoding, TextRegionIntegers,
+        TextRegionParams, TextRegionTables, Value,
+    },
+    SegmentReader, UserTables,
+};
+
+struct SymbolDictionaryParams {
+    /// Whether symbols may be refinements or aggregates of other symbols
+    refinement_aggregate: bool,
+
+    template: u8,
+    adaptive_template: [(i8, i8); 4],
+    refinement_template: u8,
+    refinement_adaptive_template: [(i8, i8); 2],
+
+    num_exported: u32,
+    num_new: u32,
+}
+
+/// The Huffman tables of a symbol dictionary
+struct SymbolDictionaryTables {
+    delta_height: Rc<HuffmanTable>,
+    delta_width: Rc<HuffmanTable>,
+    bitmap_size: Rc<HuffmanTable>,
+    aggregate_instances: Rc<HuffmanTable>,
+    export: HuffmanTable,
+    text: TextRegionTables,
+}
+
+/// The integer decoders of an arithmetic coded symbol dictionary
+struct SymbolDictionaryIntegers {
+    delta_height: IntegerDecoder,
+    delta_width: IntegerDecoder,
+    aggregate_instances: IntegerDecoder,
+    export: IntegerDecoder,
+    text: TextRegionIntegers,
+}
+
+enum Coding<'a> {
+    Arithmetic {
+        decoder: ArithmeticDecoder<'a>,
+        integers: Box<SymbolDictionaryIntegers>,
+    },
+    Huffman {
+        reader: BitReader<'a>,
+        tables: Box<SymbolDictionaryTables>,
+    },
+}
+
+impl<'a> Coding<'a> {
+    fn text(&mut self) -> TextRegionCoding<'a, '_> {
+        match self {
+            Coding::Arithmetic { decoder, integers } => TextRegionCoding::Arithmetic {
+                decoder,
+                integers: &mut integers.text,
+            },
+            Coding::Huffman { reader, tables } => TextRegionCoding::Huffman {
+                reader,
+                tables: &tables.text,
+            },
+        }
+    }
+
+    /// Returns `None` for the out-of-band value
+    fn decode(
+        &mut self,
+        integer: fn(&mut SymbolDictionaryIntegers) -> &mut IntegerDecoder,
+        table: fn(&SymbolDictionaryTables) -> &HuffmanTable,
+    ) -> PdfResult<Option<i32>> {
+        match self {
+            Coding::Arithmetic { decoder, integers } => Ok(integer(integers).decode(decoder)),
+            Coding::Huffman { reader, tables } => table(tables).decode(reader),
+        }
+    }
+
+    fn decode_value(
+        &mut self,
+        integer: fn(&mut SymbolDictionaryIntegers) -> &mut IntegerDecoder,
+        table: fn(&SymbolDictionaryTables) -> &HuffmanTable,
+    ) -> PdfResult<i32> {
+        match self.decode(integer, table)? {
+            Some(v) => Ok(v),
+            None => anyhow::bail!("unexpected out-of-band value in symbol dictionary"),
+        }
+    }
+}
+
+/// The number of bits needed to give each of `n` symbols a distinct code
+pub(super) fn symbol_code_len(n: usize) -> u32 {
+    n.next_power_of_two().trailing_zeros()
+}
+
+/// Decode a symbol dictionary segment (7.4.2), returning the symbols it
+/// exports. `input_symbols` are those exported by the dictionaries it refers to
+pub(super) fn decode_symbol_dictionary(
+    data: &[u8],
+    input_symbols: &[Rc<Bitmap>],
+    user_tables: &mut UserTables,
+) -> PdfResult<Vec<Rc<Bitmap>>> {
+    let mut parser = SegmentReader::new(data);
+
+    let flags = parser.parse_u16()?;
+
+    let huffman = flags & 1 != 0;
+    let refinement_aggregate = flags & 0b10 != 0;
+    let template = ((flags >> 10) & 0b11) as u8;
+    let refinement_template = ((flags >> 12) & 1) as u8;
+
+    if flags & (1 << 8) != 0 {
+        anyhow::bail!(
+            "JBIG2 symbol dictionaries reusing arithmetic coding contexts are unsupported"
+        );
+    }
+
+    let mut adaptive_template = [(0, 0); 4];
+
+    if !huffman {
+        if template == 0 {
+            adaptive_template = parser.adaptive_template::<4>()?;
+        } else {
+            adaptive_template[0] = parser.adaptive_template::<1>()?[0];
+        }
+    }
+
+    let refinement_adaptive_template = if refinement_aggregate && refinement_template == 0 {
+        parser.adaptive_template::<2>()?
+    } else {
+        [(0, 0); 2]
+    };
+
+    let params = SymbolDictionaryParams {
+        refinement_aggregate,
+        template,
+        adaptive_template,
+        refinement_template,
+        refinement_adaptive_template,
+        num_exported: parser.parse_u32()?,
+        num_new: parser.parse_u32()?,
+    };
+
+    let num_symbols = input_symbols.len() + params.num_new as usize;
+    let code_len = symbol_code_len(num_symbols);
+
+    let data = parser.rest();
+
+    let coding = if huffman {
+        let delta_height = match (flags >> 2) & 0b11 {
+            0 => Rc::new(HuffmanTable::standard(4)),
+            1 => Rc::new(HuffmanTable::standard(5)),
+            3 => user_tables.next()?,
+            _ => anyhow::bail!("invalid symbol dictionary height table selection"),
+        };
+
+        let delta_width = match (flags >> 4) & 0b11 {
+            0 => Rc::new(HuffmanTable::standard(2)),
+            1 => Rc::new(HuffmanTable::standard(3)),
+            3 => user_tables.next()?,
+            _ => anyhow::bail!("invalid symbol dictionary width table selection"),
+        };
+
+        let bitmap_size = if flags & (1 << 6) == 0 {
+            Rc::new(HuffmanTable::standard(1))
+        } else {
+            user_tables.next()?
+        };
+
+        let aggregate_instances = if flags & (1 << 7) == 0 {
+            Rc::new(HuffmanTable::standard(1))
+        } else {
+            user_tables.next()?
+        };
+
+        let b15 = Rc::new(HuffmanTable::standard(15));
+
+        Coding::Huffman {
+            reader: BitReader::new(data),
+            tables: Box::new(SymbolDictionaryTables {
+                delta_height,
+                delta_width,
+                bitmap_size,
+                aggregate_instances,
+                export: HuffmanTable::standard(1),
+                text: TextRegionTables {
+                    first_s: Rc::new(HuffmanTable::standard(6)),
+                    delta_s: Rc::new(HuffmanTable::standard(8)),
+                    strip_t: Rc::new(HuffmanTable::standard(11)),
+                    refinement_width: Rc::clone(&b15),
+                    refinement_height: Rc::clone(&b15),
+                    refinement_x: Rc::clone(&b15),
+                    refinement_y: b15,
+                    refinement_size: Rc::new(HuffmanTable::standard(1)),
+                    symbol_ids: SymbolIdTable::Fixed(code_len),
+                },
+            }),
+        }
+    } else {
+        Coding::Arithmetic {
+            decoder: ArithmeticDecoder::new(data),
+            integers: Box::new(SymbolDictionaryIntegers {
+                delta_height: IntegerDecoder::new(),
+                delta_width: IntegerDecoder::new(),
+                aggregate_instances: IntegerDecoder::new(),
+                export: IntegerDecoder::new(),
+                text: TextRegionIntegers::new(code_len),
+            }),
+        }
+    };
+
+    SymbolDictionaryDecoder {
+        params,
+        coding,
+        input_symbols,
+        new_symbols: Vec::new(),
+    }
+    .decode()
+}
+
+struct SymbolDictionaryDecoder<'a, 'b> {
+    params: SymbolDictionaryParams,
+    coding: Coding<'a>,
+    input_symbols: &'b [Rc<Bitmap>],
+    new_symbols: Vec<Rc<Bitmap>>,
+}
+
+impl SymbolDictionaryDecoder<'_, '_> {
+    /// 6.5.5
+    fn decode(mut self) -> PdfResult<Vec<Rc<Bitmap>>> {
+        let mut generic_contexts =
+            contexts(GenericRegionParams::num_contexts(self.params.template));
+        let mut refinement_contexts = contexts(RefinementParams::num_contexts(
+            self.params.refinement_template,
+        ));
+
+        let generic = GenericRegionParams {
+            template: self.params.template,
+            typical_prediction: false,
+            adaptive_template: self.params.adaptive_template,
+        };
+
+        let mut height_class_height: usize = 0;
+
+        while self.new_symbols.len() < self.params.num_new as usize {
+            let delta_height = self
+                .coding
+                .decode_value(|i| &mut i.delta_height, |t| &t.delta_height)?;
+
+            height_class_height =
+                usize::try_from(height_class_height as i64 + i64::from(delta_height))?;
+
+            let mut symbol_width: usize = 0;
+
+            // the widths of the symbols of the height class, when they are
+            // stored as one collective bitmap
+            let mut widths = Vec::new();
+
+            loop {
+                let Some(delta_width) = self
+                    .coding
+                    .decode(|i| &mut i.delta_width, |t| &t.delta_width)?
+                else {
+                    break;
+                };
+
+                if self.new_symbols.len() + widths.len() >= self.params.num_new as usize {
+                    anyhow::bail!("symbol dictionary has more symbols than declared");
+                }
+
+                symbol_width = usize::try_from(symbol_width as i64 + i64::from(delta_width))?;
+
+                if self.params.refinement_aggregate {
+                    let symbol = self.decode_aggregate(
+                        symbol_width,
+                        height_class_height,
+                        &mut refinement_contexts,
+                    )?;
+
+                    self.new_symbols.push(Rc::new(symbol));
+                } else if let Coding::Arithmetic { decoder, .. } = &mut self.coding {
+                    let symbol = decode_generic_region(
+                        decoder,
+                        &mut generic_contexts,
+                        symbol_width,
+                        height_class_height,
+                        &generic,
+                    )?;
+
+                    self.new_symbols.push(Rc::new(symbol));
+                } else {
+                    widths.push(symbol_width);
+                }
+            }
+
+            if !widths.is_empty() {
+                self.decode_collective_bitmap(&widths, height_class_height)?;
+            }
+        }
+
+        self.export()
+    }
+
+    /// Decode a symbol which is either a refinement of a single symbol, or made
+    /// up of several symbols as though it were a text region (6.5.8.2)
+    fn decode_aggregate(
+        &mut self,
+        width: usize,
+        height: usize,
+        refinement_contexts: &mut [Context],
+    ) -> PdfResult<Bitmap> {
+        let num_instances = self
+            .coding
+            .decode_value(|i| &mut i.aggregate_instances, |t| &t.aggregate_instances)?;
+
+        let symbols = self
+            .input_symbols
+            .iter()
+            .chain(&self.new_symbols)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut coding = self.coding.text();
+
+        if num_instances == 1 {
+            let id = coding.decode_symbol_id()?;
+            let delta_x = coding.decode_value(Value::RefinementX)?;
+            let delta_y = coding.decode_value(Value::RefinementY)?;
+
+            let Some(reference) = symbols.get(id) else {
+                anyhow::bail!("invalid symbol ID {}", id);
+            };
+
+            let params = RefinementParams {
+                template: self.params.refinement_template,
+                typical_prediction: false,
+                adaptive_template: self.params.refinement_adaptive_template,
+                reference_dx: i64::from(delta_x),
+                reference_dy: i64::from(delta_y),
+            };
+
+            return coding.decode_refinement(
+                reference,
+                width,
+                height,
+                &params,
+                refinement_contexts,
+            );
+        }
+
+        let params = TextRegionParams {
+            width,
+            height,
+            num_instances: u32::try_from(num_instances)?,
+            refine: true,
+            log_strips: 0,
+            reference_corner: ReferenceCorner::TopLeft,
+            transposed: false,
+            combination_operator: CombinationOperator::Or,
+            default_pixel: 0,
+            ds_offset: 0,
+            refinement_template: self.params.refinement_template,
+            refinement_adaptive_template: self.params.refinement_adaptive_template,
+        };
+
+        decode_text_region(&mut coding, &symbols, &params, refinement_contexts)
+    }
+
+    /// With Huffman coding, the symbols of a height class are stored side by
+    /// side in a single bitmap, either uncompressed or MMR coded (6.5.9)
+    fn decode_collective_bitmap(&mut self, widths: &[usize], height: usize) -> PdfResult<()> {
+        let Coding::Huffman { reader, tables } = &mut self.coding else {
+            unreachable!("collective bitmaps are only used with Huffman coding");
+        };
+
+        let Some(size) = tables.bitmap_size.decode(reader)? else {
+            anyhow::bail!("unexpected out-of-band collective bitmap size");
+        };
+
+        reader.align();
+
+        let Some(total_width) = widths
+            .iter()
+            .try_fold(0_usize, |total, &width| total.checked_add(width))
+        else {
+            anyhow::bail!("collective bitmap is too large");
+        };
+
+        num_pixels(total_width, height)?;
+
+        // a size of 0 indicates that the bitmap is uncompressed
+        let uncompressed = size == 0;
+
+        let size = if uncompressed {
+            total_width.div_ceil(8) * height
+        } else {
+            size as usize
+        };
+
+        let Some(data) = reader.remaining().get(..size) else {
+            anyhow::bail!("collective bitmap extends past end of segment");
+        };
+
+        let collective = if uncompressed {
+            Bitmap::from_packed(data, total_width, height)?
+        } else {
+            decode_mmr_region(data, total_width, height)?
+        };
+
+        reader.skip_bytes(size);
+
+        let mut x = 0;
+
+        for &width in widths {
+            self.new_symbols
+                .push(Rc::new(collective.region(x as i64, 0, width, height)?));
+            x += width;
+        }
+
+        Ok(())
+    }
+
+    /// The exported symbols are given by runs which alternate between symbols
+    /// that are and are not exported, beginning with those that are not
+    /// (6.5.10)
+    fn export(mut self) -> PdfResult<Vec<Rc<Bitmap>>> {
+        let mut exported = Vec::new();
+        let mut export = false;
+
+        let symbols = self
+            .input_symbols
+            .iter()
+            .chain(&self.new_symbols)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut idx = 0;
+
+        while idx < symbols.len() {
+            let run = usize::try_from(self.coding.decode_value(|i| &mut i.export, |t| &t.export)?)?;
+
+            let Some(run_symbols) = symbols.get(idx..idx + run) else {
+                anyhow::bail!("symbol dictionary exports more symbols than it has");
+            };
+
+            if export {
+                exported.extend_from_slice(run_symbols);
+            }
+
+            idx += run;
+            export = !export;
+        }
+
+        if exported.len() != self.params.num_exported as usize {
+            anyhow::bail!(
+                "symbol dictionary exports {} symbols, but declares {}",
+                exported.len(),
+                self.params.num_exported
+            );
+        }
+
+        Ok(exported)
+    }
+}
@@ -0,0 +1,377 @@
+//! Text regions, which are made up of the symbols of symbol dictionaries
+//! (T.88 6.4)
+
+use std::rc::Rc;
+
+use crate::{
+    error::PdfResult,
+    filter::mq::{ArithmeticDecoder, Context},
+};
+
+use super::{
+    arithmetic::{IdDecoder, IntegerDecoder},
+    bitmap::{Bitmap, CombinationOperator},
+    generic::{decode_refinement_region, RefinementParams},
+    huffman::{BitReader, HuffmanTable},
+};
+
+/// The corner of each symbol instance that its coordinates refer to
+#[pdf_enum(Integer)]
+pub(super) enum ReferenceCorner {
+    BottomLeft = 0,
+    TopLeft = 1,
+    BottomRight = 2,
+    TopRight = 3,
+}
+
+impl ReferenceCorner {
+    fn is_left(self) -> bool {
+        matches!(self, ReferenceCorner::BottomLeft | ReferenceCorner::TopLeft)
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, ReferenceCorner::TopLeft | ReferenceCorner::TopRight)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TextRegionParams {
+    pub width: usize,
+    pub height: usize,
+    pub num_instances: u32,
+
+    /// Whether symbol instances may be refinements of their symbol
+    pub refine: bool,
+
+    /// The instances are grouped into strips of `1 << log_strips` rows
+    pub log_strips: u32,
+
+    pub reference_corner: ReferenceCorner,
+
+    /// Whether the instances are laid out in columns rather than rows
+    pub transposed: bool,
+
+    pub combination_operator: CombinationOperator,
+    pub default_pixel: u8,
+
+    /// Added to the distance between consecutive instances in a strip
+    pub ds_offset: i32,
+
+    pub refinement_template: u8,
+    pub refinement_adaptive_template: [(i8, i8); 2],
+}
+
+/// The integer decoders of a text region, which are shared with the symbol
+/// dictionary when a text region is used to build a symbol
+pub(super) struct TextRegionIntegers {
+    pub strip_t: IntegerDecoder,
+    pub first_s: IntegerDecoder,
+    pub delta_s: IntegerDecoder,
+    pub instance_t: IntegerDecoder,
+    pub refine: IntegerDecoder,
+    pub refinement_width: IntegerDecoder,
+    pub refinement_height: IntegerDecoder,
+    pub refinement_x: IntegerDecoder,
+    pub refinement_y: IntegerDecoder,
+    pub symbol_id: IdDecoder,
+}
+
+impl TextRegionIntegers {
+    pub fn new(symbol_code_len: u32) -> Self {
+        Self {
+            strip_t: IntegerDecoder::new(),
+            first_s: IntegerDecoder::new(),
+            delta_s: IntegerDecoder::new(),
+            instance_t: IntegerDecoder::new(),
+            refine: IntegerDecoder::new(),
+            refinement_width: IntegerDecoder::new(),
+            refinement_height: IntegerDecoder::new(),
+            refinement_x: IntegerDecoder::new(),
+            refinement_y: IntegerDecoder::new(),
+            symbol_id: IdDecoder::new(symbol_code_len),
+        }
+    }
+}
+
+pub(super) enum SymbolIdTable {
+    Table(HuffmanTable),
+
+    /// Every symbol ID is a code of the same number of bits
+    Fixed(u32),
+}
+
+pub(super) struct TextRegionTables {
+    pub first_s: Rc<HuffmanTable>,
+    pub delta_s: Rc<HuffmanTable>,
+    pub strip_t: Rc<HuffmanTable>,
+    pub refinement_width: Rc<HuffmanTable>,
+    pub refinement_height: Rc<HuffmanTable>,
+    pub refinement_x: Rc<HuffmanTable>,
+    pub refinement_y: Rc<HuffmanTable>,
+    pub refinement_size: Rc<HuffmanTable>,
+    pub symbol_ids: SymbolIdTable,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Value {
+    StripT,
+    FirstS,
+    DeltaS,
+    RefinementWidth,
+    RefinementHeight,
+    RefinementX,
+    RefinementY,
+}
+
+pub(super) enum TextRegionCoding<'a, 'b> {
+    Arithmetic {
+        decoder: &'b mut ArithmeticDecoder<'a>,
+        integers: &'b mut TextRegionIntegers,
+    },
+    Huffman {
+        reader: &'b mut BitReader<'a>,
+        tables: &'b TextRegionTables,
+    },
+}
+
+impl TextRegionCoding<'_, '_> {
+    /// Returns `None` for the out-of-band value
+    pub fn decode(&mut self, value: Value) -> PdfResult<Option<i32>> {
+        match self {
+            TextRegionCoding::Arithmetic { decoder, integers } => {
+                let integer = match value {
+                    Value::StripT => &mut integers.strip_t,
+                    Value::FirstS => &mut integers.first_s,
+                    Value::DeltaS => &mut integers.delta_s,
+                    Value::RefinementWidth => &mut integers.refinement_width,
+                    Value::RefinementHeight => &mut integers.refinement_height,
+                    Value::RefinementX => &mut integers.refinement_x,
+                    Value::RefinementY => &mut integers.refinement_y,
+                };
+
+                Ok(integer.decode(decoder))
+            }
+            TextRegionCoding::Huffman { reader, tables } => {
+                let table = match value {
+                    Value::StripT => &tables.strip_t,
+                    Value::FirstS => &tables.first_s,
+                    Value::DeltaS => &tables.delta_s,
+                    Value::RefinementWidth => &tables.refinement_width,
+                    Value::RefinementHeight => &tables.refinement_height,
+                    Value::RefinementX => &tables.refinement_x,
+                    Value::RefinementY => &tables.refinement_y,
+                };
+
+                table.decode(reader)
+            }
+        }
+    }
+
+    /// Decode a value which may not be out-of-band
+    pub fn decode_value(&mut self, value: Value) -> PdfResult<i32> {
+        match self.decode(value)? {
+            Some(v) => Ok(v),
+            None => anyhow::bail!("unexpected out-of-band value for {:?}", value),
+        }
+    }
+
+    fn decode_instance_t(&mut self, log_strips: u32) -> PdfResult<i32> {
+        if log_strips == 0 {
+            return Ok(0);
+        }
+
+        match self {
+            TextRegionCoding::Arithmetic { decoder, integers } => {
+                match integers.instance_t.decode(decoder) {
+                    Some(t) => Ok(t),
+                    None => anyhow::bail!("unexpected out-of-band value for instance T"),
+                }
+            }
+            TextRegionCoding::Huffman { reader, .. } => Ok(reader.read_bits(log_strips)? as i32),
+        }
+    }
+
+    pub fn decode_symbol_id(&mut self) -> PdfResult<usize> {
+        match self {
+            TextRegionCoding::Arithmetic { decoder, integers } => {
+                Ok(integers.symbol_id.decode(decoder))
+            }
+            TextRegionCoding::Huffman { reader, tables } => match &tables.symbol_ids {
+                SymbolIdTable::Table(table) => match table.decode(reader)? {
+                    Some(id) => Ok(id as usize),
+                    None => anyhow::bail!("unexpected out-of-band symbol ID"),
+                },
+                SymbolIdTable::Fixed(len) => Ok(reader.read_bits(*len)? as usize),
+            },
+        }
+    }
+
+    fn decode_refine(&mut self) -> PdfResult<bool> {
+        match self {
+            TextRegionCoding::Arithmetic { decoder, integers } => {
+                Ok(integers.refine.decode(decoder).is_some_and(|ri| ri != 0))
+            }
+            TextRegionCoding::Huffman { reader, .. } => Ok(reader.read_bit()? != 0),
+        }
+    }
+
+    /// Decode a refinement of `reference`. With Huffman coding, the refinement
+    /// is arithmetic coded data of its own, preceded by its size
+    pub fn decode_refinement(
+        &mut self,
+        reference: &Bitmap,
+        width: usize,
+        height: usize,
+        params: &RefinementParams,
+        contexts: &mut [Context],
+    ) -> PdfResult<Bitmap> {
+        match self {
+            TextRegionCoding::Arithmetic { decoder, .. } => {
+                decode_refinement_region(decoder, contexts, width, height, reference, params)
+            }
+            TextRegionCoding::Huffman { reader, tables } => {
+                let Some(size) = tables.refinement_size.decode(reader)? else {
+                    anyhow::bail!("unexpected out-of-band refinement size");
+                };
+
+                reader.align();
+
+                let Some(data) = reader.remaining().get(..size as usize) else {
+                    anyhow::bail!("refinement data extends past end of segment");
+                };
+
+                let mut decoder = ArithmeticDecoder::new(data);
+                let bitmap = decode_refinement_region(
+                    &mut decoder,
+                    contexts,
+                    width,
+                    height,
+                    reference,
+                    params,
+                )?;
+
+                reader.skip_bytes(size as usize);
+
+                Ok(bitmap)
+            }
+        }
+    }
+}
+
+/// Decode a text region (6.4.5). `refinement_contexts` are only used if the
+/// region is refined
+pub(super) fn decode_text_region(
+    coding: &mut TextRegionCoding,
+    symbols: &[Rc<Bitmap>],
+    params: &TextRegionParams,
+    refinement_contexts: &mut [Context],
+) -> PdfResult<Bitmap> {
+    let mut region = Bitmap::new(params.width, params.height, params.default_pixel)?;
+
+    let strips = 1 << params.log_strips;
+    let corner = params.reference_corner;
+
+    let mut strip_t = -coding.decode_value(Value::StripT)? * strips;
+    let mut first_s = 0;
+    let mut num_instances = 0;
+
+    while num_instances < params.num_instances {
+        strip_t += coding.decode_value(Value::StripT)? * strips;
+
+        let mut cur_s = 0;
+        let mut first = true;
+
+        loop {
+            if first {
+                first_s += coding.decode_value(Value::FirstS)?;
+                cur_s = first_s;
+                first = false;
+            } else {
+                let Some(delta_s) = coding.decode(Value::DeltaS)? else {
+                    break;
+                };
+
+                cur_s += delta_s + params.ds_offset;
+            }
+
+            let t = i64::from(strip_t + coding.decode_instance_t(params.log_strips)?);
+
+            let id = coding.decode_symbol_id()?;
+
+            let Some(symbol) = symbols.get(id) else {
+                anyhow::bail!("invalid symbol ID {}", id);
+            };
+
+            let refined;
+
+            let bitmap = if params.refine && coding.decode_refine()? {
+                let delta_width = coding.decode_value(Value::RefinementWidth)?;
+                let delta_height = coding.decode_value(Value::RefinementHeight)?;
+                let delta_x = coding.decode_value(Value::RefinementX)?;
+                let delta_y = coding.decode_value(Value::RefinementY)?;
+
+                let width = usize::try_from(symbol.width as i64 + i64::from(delta_width))?;
+                let height = usize::try_from(symbol.height as i64 + i64::from(delta_height))?;
+
+                let refinement = RefinementParams {
+                    template: params.refinement_template,
+                    typical_prediction: false,
+                    adaptive_template: params.refinement_adaptive_template,
+                    reference_dx: i64::from(delta_width.div_euclid(2) + delta_x),
+                    reference_dy: i64::from(delta_height.div_euclid(2) + delta_y),
+                };
+
+                refined = coding.decode_refinement(
+                    symbol,
+                    width,
+                    height,
+                    &refinement,
+                    refinement_contexts,
+                )?;
+
+                &refined
+            } else {
+                &**symbol
+            };
+
+            let width = bitmap.width as i32;
+            let height = bitmap.height as i32;
+
+            // `cur_s` is moved to the far edge of the instance on either side
+            // of placing it, depending on which corner is the reference point
+            if !params.transposed && !corner.is_left() {
+                cur_s += width - 1;
+            } else if params.transposed && !corner.is_top() {
+                cur_s += height - 1;
+            }
+
+            let s = i64::from(cur_s);
+
+            let (x, y) = if params.transposed { (t, s) } else { (s, t) };
+
+            let x = if corner.is_left() {
+                x
+            } else {
+                x - i64::from(width) + 1
+            };
+
+            let y = if corner.is_top() {
+                y
+            } else {
+                y - i64::from(height) + 1
+            };
+
+            region.combine(bitmap, x, y, params.combination_operator);
+
+            if !params.transposed && corner.is_left() {
+                cur_s += width - 1;
+            } else if params.transposed && corner.is_top() {
+                cur_s += height - 1;
+            }
+
+            num_instances += 1;
+        }
+    }
+
+    Ok(region)
+}
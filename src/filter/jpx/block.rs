@@ -0,0 +1,557 @@
+//! Code-blocks, whose coefficients are coded one bitplane at a time in three
+//! coding passes each (T.800 Annex D)
+
+use crate::{
+    error::PdfResult,
+    filter::mq::{ArithmeticDecoder, Context},
+};
+
+use super::{codestream::BlockStyle, tile::Orientation, tile::Rect};
+
+/// The coefficient is known to be nonzero
+const SIGNIFICANT: u8 = 1 << 0;
+
+/// The coefficient is negative, if it is significant
+const NEGATIVE: u8 = 1 << 1;
+
+/// The coefficient has already been coded in the current bitplane, by the
+/// significance propagation pass
+const VISITED: u8 = 1 << 2;
+
+/// The coefficient has been through at least one magnitude refinement pass
+const REFINED: u8 = 1 << 3;
+
+/// The contexts following the nine used to code significance (Table D.7)
+const SIGN_CONTEXTS: usize = 9;
+const REFINEMENT_CONTEXTS: usize = 14;
+const RUN_LENGTH_CONTEXT: usize = 17;
+const UNIFORM_CONTEXT: usize = 18;
+
+fn initial_contexts() -> [Context; 19] {
+    let mut contexts = [Context::default(); 19];
+
+    contexts[0] = Context::new(4);
+    contexts[RUN_LENGTH_CONTEXT] = Context::new(3);
+    contexts[UNIFORM_CONTEXT] = Context::new(46);
+
+    contexts
+}
+
+/// A run of coding passes which were coded together, and so whose data must
+/// be decoded together
+#[derive(Debug)]
+pub(super) struct Segment {
+    pub data: Vec<u8>,
+    passes: u32,
+    max_passes: u32,
+}
+
+#[derive(Debug)]
+pub(super) struct CodeBlock {
+    pub area: Rect,
+
+    /// Whether the code-block has been included in a packet yet
+    pub included: bool,
+
+    /// The number of most significant bitplanes which are zero in every
+    /// coefficient, and so aren't coded
+    pub zero_bitplanes: u32,
+
+    /// The base number of bits used to code the length of its data in each
+    /// packet (Lblock)
+    pub length_bits: u32,
+
+    /// The number of coding passes included so far
+    pub passes: u32,
+
+    pub segments: Vec<Segment>,
+}
+
+impl CodeBlock {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            included: false,
+            zero_bitplanes: 0,
+            length_bits: 3,
+            passes: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Add coding passes to the code-block, giving the segment and number of
+    /// passes of each piece of data which make them up
+    pub fn add_passes(&mut self, mut passes: u32, style: BlockStyle) -> Vec<(usize, u32)> {
+        let mut pieces = Vec::new();
+
+        while passes > 0 {
+            if self
+                .segments
+                .last()
+                .is_none_or(|segment| segment.passes == segment.max_passes)
+            {
+                self.segments.push(Segment {
+                    data: Vec::new(),
+                    passes: 0,
+                    max_passes: max_segment_passes(self.passes, style),
+                });
+            }
+
+            let index = self.segments.len() - 1;
+            let segment = &mut self.segments[index];
+
+            let added = passes.min(segment.max_passes - segment.passes);
+
+            segment.passes += added;
+            self.passes += added;
+            passes -= added;
+
+            pieces.push((index, added));
+        }
+
+        pieces
+    }
+
+    /// Decode the coefficients of the code-block, row by row, in units of
+    /// their quantization step size
+    pub fn decode(&self, params: &BlockParams) -> PdfResult<Vec<f32>> {
+        let width = self.area.width() as usize;
+        let height = self.area.height() as usize;
+
+        if params.magnitude_bits > 31 {
+            anyhow::bail!(
+                "unsupported JPEG 2000 code-block with {} bitplanes",
+                params.magnitude_bits
+            );
+        }
+
+        let Some(mut bitplane) = params.magnitude_bits.checked_sub(self.zero_bitplanes + 1) else {
+            anyhow::bail!("JPEG 2000 code-block has too many zero bitplanes");
+        };
+
+        let mut decoder = BlockDecoder::new(width, height, params);
+
+        // the first pass of every bitplane but the first is the significance
+        // propagation pass
+        let mut pass = 0;
+
+        'segments: for segment in &self.segments {
+            let mut coder = if params.style.bypass() && pass >= 10 && pass % 3 != 0 {
+                Coder::Raw(RawDecoder::new(&segment.data))
+            } else {
+                Coder::Arithmetic(ArithmeticDecoder::new(&segment.data))
+            };
+
+            for _ in 0..segment.passes {
+                match pass % 3 {
+                    0 => decoder.cleanup_pass(&mut coder, bitplane),
+                    1 => decoder.significance_pass(&mut coder, bitplane),
+                    _ => decoder.refinement_pass(&mut coder, bitplane),
+                }
+
+                if params.style.reset() {
+                    decoder.contexts = initial_contexts();
+                }
+
+                if pass % 3 == 0 {
+                    if bitplane == 0 {
+                        break 'segments;
+                    }
+
+                    bitplane -= 1;
+                }
+
+                pass += 1;
+            }
+        }
+
+        Ok(decoder.coefficients(params))
+    }
+}
+
+/// The number of coding passes of a segment beginning with the `pass`th, if
+/// it were to contain every pass up to where the coder is terminated
+fn max_segment_passes(pass: u32, style: BlockStyle) -> u32 {
+    if style.terminate_all() {
+        1
+    } else if style.bypass() {
+        // after the first four bitplanes, the significance propagation and
+        // magnitude refinement passes of each are stored raw, separately from
+        // the arithmetic coded cleanup pass
+        match pass {
+            0..=9 => 10 - pass,
+            _ if pass % 3 == 1 => 2,
+            _ => 1,
+        }
+    } else {
+        u32::MAX
+    }
+}
+
+pub(super) struct BlockParams {
+    pub orientation: Orientation,
+    pub magnitude_bits: u32,
+    pub style: BlockStyle,
+    pub reversible: bool,
+    pub roi_shift: u32,
+}
+
+/// Reads the bits of a raw segment, which are stuffed in the same way as
+/// packet headers (D.6)
+struct RawDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    byte: u8,
+    bits: u32,
+}
+
+impl<'a> RawDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            byte: 0,
+            bits: 0,
+        }
+    }
+
+    fn decode(&mut self) -> u8 {
+        if self.bits == 0 {
+            let stuffed = self.byte == 0xff;
+
+            self.byte = self.data.get(self.pos).copied().unwrap_or(0xff);
+            self.pos += 1;
+            self.bits = if stuffed { 7 } else { 8 };
+        }
+
+        self.bits -= 1;
+
+        (self.byte >> self.bits) & 1
+    }
+}
+
+enum Coder<'a> {
+    Arithmetic(ArithmeticDecoder<'a>),
+    Raw(RawDecoder<'a>),
+}
+
+impl Coder<'_> {
+    fn decode(&mut self, cx: &mut Context) -> u8 {
+        match self {
+            Coder::Arithmetic(decoder) => decoder.decode(cx),
+            Coder::Raw(decoder) => decoder.decode(),
+        }
+    }
+}
+
+struct BlockDecoder {
+    width: usize,
+    height: usize,
+
+    /// The flags of each coefficient, with a border of one coefficient on
+    /// each side so that neighbours can be read without bounds checks
+    flags: Vec<u8>,
+
+    /// The magnitude of each coefficient, with one extra bit of precision,
+    /// such that the undecoded bitplanes are half their maximum (D.7)
+    magnitudes: Vec<u32>,
+
+    contexts: [Context; 19],
+
+    orientation: Orientation,
+    vertically_causal: bool,
+    segmentation_symbols: bool,
+}
+
+impl BlockDecoder {
+    fn new(width: usize, height: usize, params: &BlockParams) -> Self {
+        Self {
+            width,
+            height,
+            flags: vec![0; (width + 2) * (height + 2)],
+            magnitudes: vec![0; width * height],
+            contexts: initial_contexts(),
+            orientation: params.orientation,
+            vertically_causal: params.style.vertically_causal(),
+            segmentation_symbols: params.style.segmentation_symbols(),
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y + 1) * (self.width + 2) + x + 1
+    }
+
+    /// Call `f` on each coefficient in the order they are coded: in stripes of
+    /// four rows, column by column
+    fn scan(&mut self, mut f: impl FnMut(&mut Self, usize, usize)) {
+        for stripe in (0..self.height).step_by(4) {
+            for x in 0..self.width {
+                for y in stripe..(stripe + 4).min(self.height) {
+                    f(self, x, y);
+                }
+            }
+        }
+    }
+
+    /// Whether the row below `y` is ignored, as it is in the next stripe
+    fn ignores_below(&self, y: usize) -> bool {
+        self.vertically_causal && y % 4 == 3
+    }
+
+    /// The number of significant horizontal, vertical and diagonal neighbours
+    fn neighbours(&self, x: usize, y: usize) -> (u32, u32, u32) {
+        let i = self.index(x, y);
+        let stride = self.width + 2;
+
+        let significant = |i: usize| u32::from(self.flags[i] & SIGNIFICANT);
+
+        let horizontal = significant(i - 1) + significant(i + 1);
+
+        let (vertical, diagonal) = if self.ignores_below(y) {
+            (
+                significant(i - stride),
+                significant(i - stride - 1) + significant(i - stride + 1),
+            )
+        } else {
+            (
+                significant(i - stride) + significant(i + stride),
+                significant(i - stride - 1)
+                    + significant(i - stride + 1)
+                    + significant(i + stride - 1)
+                    + significant(i + stride + 1),
+            )
+        };
+
+        (horizontal, vertical, diagonal)
+    }
+
+    /// The context used to code whether a coefficient becomes significant,
+    /// which is 0 only if none of its neighbours are significant (Table D.1)
+    fn significance_context(&self, x: usize, y: usize) -> usize {
+        let (horizontal, vertical, diagonal) = self.neighbours(x, y);
+
+        // the horizontally high-pass subband is most likely to hold vertical
+        // lines, and so the roles of its neighbours are swapped
+        let (horizontal, vertical) = match self.orientation {
+            Orientation::HighLow => (vertical, horizontal),
+            _ => (horizontal, vertical),
+        };
+
+        if self.orientation == Orientation::HighHigh {
+            return match (diagonal, horizontal + vertical) {
+                (3.., _) => 8,
+                (2, 1..) => 7,
+                (2, 0) => 6,
+                (1, 2..) => 5,
+                (1, 1) => 4,
+                (1, 0) => 3,
+                (0, 2..) => 2,
+                (0, 1) => 1,
+                (0, 0) => 0,
+            };
+        }
+
+        match (horizontal, vertical, diagonal) {
+            (2.., _, _) => 8,
+            (1, 1.., _) => 7,
+            (1, 0, 1..) => 6,
+            (1, 0, 0) => 5,
+            (0, 2.., _) => 4,
+            (0, 1, _) => 3,
+            (0, 0, 2..) => 2,
+            (0, 0, 1) => 1,
+            (0, 0, 0) => 0,
+        }
+    }
+
+    /// Decode the sign of a coefficient which has just become significant,
+    /// using a context chosen from the signs of its horizontal and vertical
+    /// neighbours (Table D.3)
+    fn decode_sign(&mut self, coder: &mut Coder, x: usize, y: usize, bitplane: u32) {
+        let i = self.index(x, y);
+        let stride = self.width + 2;
+
+        let contribution = |i: usize| match self.flags[i] & (SIGNIFICANT | NEGATIVE) {
+            SIGNIFICANT => 1,
+            0 => 0,
+            _ => -1,
+        };
+
+        let horizontal = (contribution(i - 1) + contribution(i + 1)).clamp(-1, 1);
+
+        let vertical = if self.ignores_below(y) {
+            contribution(i - stride)
+        } else {
+            (contribution(i - stride) + contribution(i + stride)).clamp(-1, 1)
+        };
+
+        let (context, flip) = match (horizontal, vertical) {
+            (1, 1) => (4, 0),
+            (1, 0) => (3, 0),
+            (1, _) => (2, 0),
+            (0, 1) => (1, 0),
+            (0, 0) => (0, 0),
+            (0, _) => (1, 1),
+            (_, 1) => (2, 1),
+            (_, 0) => (3, 1),
+            (_, _) => (4, 1),
+        };
+
+        // raw passes hold the sign itself, without the prediction
+        let negative = match coder {
+            Coder::Arithmetic(..) => {
+                coder.decode(&mut self.contexts[SIGN_CONTEXTS + context]) ^ flip
+            }
+            Coder::Raw(decoder) => decoder.decode(),
+        };
+
+        self.flags[i] |= SIGNIFICANT | if negative == 1 { NEGATIVE } else { 0 };
+        self.magnitudes[y * self.width + x] = 3 << bitplane;
+    }
+
+    /// Code whether each coefficient with a significant neighbour has become
+    /// significant (D.3.1)
+    fn significance_pass(&mut self, coder: &mut Coder, bitplane: u32) {
+        self.scan(|this, x, y| {
+            let i = this.index(x, y);
+
+            if this.flags[i] & SIGNIFICANT != 0 {
+                return;
+            }
+
+            let context = this.significance_context(x, y);
+
+            if context == 0 {
+                return;
+            }
+
+            this.flags[i] |= VISITED;
+
+            if coder.decode(&mut this.contexts[context]) == 1 {
+                this.decode_sign(coder, x, y, bitplane);
+            }
+        });
+    }
+
+    /// Code the next bit of each coefficient which was already significant
+    /// (D.3.3)
+    fn refinement_pass(&mut self, coder: &mut Coder, bitplane: u32) {
+        self.scan(|this, x, y| {
+            let i = this.index(x, y);
+            let flags = this.flags[i];
+
+            if flags & (SIGNIFICANT | VISITED) != SIGNIFICANT {
+                return;
+            }
+
+            let context = if flags & REFINED != 0 {
+                2
+            } else if this.significance_context(x, y) != 0 {
+                1
+            } else {
+                0
+            };
+
+            let bit = coder.decode(&mut this.contexts[REFINEMENT_CONTEXTS + context]);
+
+            // replace the half of this bitplane assumed so far with half of
+            // the bitplane below
+            let magnitude = &mut this.magnitudes[y * this.width + x];
+
+            if bit == 1 {
+                *magnitude += 1 << bitplane;
+            } else {
+                *magnitude -= 1 << bitplane;
+            }
+
+            this.flags[i] |= REFINED;
+        });
+    }
+
+    /// Code whether each remaining coefficient has become significant, with
+    /// runs of four without significant neighbours coded together (D.3.4)
+    fn cleanup_pass(&mut self, coder: &mut Coder, bitplane: u32) {
+        for stripe in (0..self.height).step_by(4) {
+            let rows = (self.height - stripe).min(4);
+
+            for x in 0..self.width {
+                let mut first_row = stripe;
+
+                let is_run = rows == 4
+                    && (stripe..stripe + 4).all(|y| {
+                        self.flags[self.index(x, y)] & (SIGNIFICANT | VISITED) == 0
+                            && self.significance_context(x, y) == 0
+                    });
+
+                if is_run {
+                    if coder.decode(&mut self.contexts[RUN_LENGTH_CONTEXT]) == 0 {
+                        continue;
+                    }
+
+                    let high = coder.decode(&mut self.contexts[UNIFORM_CONTEXT]);
+                    let low = coder.decode(&mut self.contexts[UNIFORM_CONTEXT]);
+
+                    let y = stripe + usize::from((high << 1) | low);
+
+                    self.decode_sign(coder, x, y, bitplane);
+
+                    first_row = y + 1;
+                }
+
+                for y in first_row..stripe + rows {
+                    let i = self.index(x, y);
+
+                    if self.flags[i] & (SIGNIFICANT | VISITED) != 0 {
+                        continue;
+                    }
+
+                    let context = self.significance_context(x, y);
+
+                    if coder.decode(&mut self.contexts[context]) == 1 {
+                        self.decode_sign(coder, x, y, bitplane);
+                    }
+                }
+            }
+        }
+
+        for flags in &mut self.flags {
+            *flags &= !VISITED;
+        }
+
+        if self.segmentation_symbols {
+            for _ in 0..4 {
+                coder.decode(&mut self.contexts[UNIFORM_CONTEXT]);
+            }
+        }
+    }
+
+    fn coefficients(&self, params: &BlockParams) -> Vec<f32> {
+        let mut coefficients = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut magnitude = self.magnitudes[y * self.width + x];
+
+                // coefficients of the region of interest were shifted above
+                // every coefficient of the background (H.1)
+                if params.roi_shift > 0
+                    && u64::from(magnitude) >= 1 << (params.roi_shift + 1).min(32)
+                {
+                    magnitude >>= params.roi_shift;
+                }
+
+                let value = if params.reversible {
+                    (magnitude >> 1) as f32
+                } else {
+                    magnitude as f32 / 2.0
+                };
+
+                let negative = self.flags[self.index(x, y)] & NEGATIVE != 0;
+
+                coefficients.push(if negative { -value } else { value });
+            }
+        }
+
+        coefficients
+    }
+}
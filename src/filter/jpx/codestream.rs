@@ -0,0 +1,739 @@
+//! The markers of the codestream, which describe the image and how it was
+//! coded, and divide the coded data into tiles (T.800 Annex A)
+
+use crate::{error::PdfResult, parse_binary::BinaryParser};
+
+use super::{
+    tile::{decode_tile, Rect},
+    ByteReader,
+};
+
+const SOC: u16 = 0xff4f;
+const SIZ: u16 = 0xff51;
+const COD: u16 = 0xff52;
+const COC: u16 = 0xff53;
+const QCD: u16 = 0xff5c;
+const QCC: u16 = 0xff5d;
+const RGN: u16 = 0xff5e;
+const POC: u16 = 0xff5f;
+const PPM: u16 = 0xff60;
+const PPT: u16 = 0xff61;
+const SOT: u16 = 0xff90;
+const SOD: u16 = 0xff93;
+const EOC: u16 = 0xffd9;
+
+/// The largest number of samples, across all components, of an image that will
+/// be decoded
+const MAX_SAMPLES: u64 = 1 << 28;
+
+/// The precision, signedness and subsampling of a component (SIZ marker)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ComponentSize {
+    pub precision: u32,
+    pub signed: bool,
+    pub dx: u32,
+    pub dy: u32,
+}
+
+/// The image and tile sizes (SIZ marker)
+#[derive(Debug)]
+pub(super) struct ImageSize {
+    /// The image area on the reference grid
+    pub area: Rect,
+
+    pub tile_width: u32,
+    pub tile_height: u32,
+
+    /// The origin of the tile grid on the reference grid
+    pub tile_x0: u32,
+    pub tile_y0: u32,
+
+    pub components: Vec<ComponentSize>,
+}
+
+impl ImageSize {
+    fn parse(reader: &mut ByteReader) -> PdfResult<Self> {
+        // the capabilities of the decoder needed, which are implied by the
+        // other markers
+        reader.parse_u16()?;
+
+        let x1 = reader.parse_u32()?;
+        let y1 = reader.parse_u32()?;
+        let x0 = reader.parse_u32()?;
+        let y0 = reader.parse_u32()?;
+        let tile_width = reader.parse_u32()?;
+        let tile_height = reader.parse_u32()?;
+        let tile_x0 = reader.parse_u32()?;
+        let tile_y0 = reader.parse_u32()?;
+
+        let num_components = reader.parse_u16()?;
+
+        let components = (0..num_components)
+            .map(|_| {
+                let depth = reader.next()?;
+
+                Ok(ComponentSize {
+                    precision: u32::from(depth & 0x7f) + 1,
+                    signed: depth & 0x80 != 0,
+                    dx: u32::from(reader.next()?),
+                    dy: u32::from(reader.next()?),
+                })
+            })
+            .collect::<PdfResult<Vec<_>>>()?;
+
+        if x0 >= x1 || y0 >= y1 || tile_width == 0 || tile_height == 0 {
+            anyhow::bail!("invalid JPEG 2000 image size");
+        }
+
+        if tile_x0 > x0
+            || tile_y0 > y0
+            || tile_x0.saturating_add(tile_width) <= x0
+            || tile_y0.saturating_add(tile_height) <= y0
+        {
+            anyhow::bail!("JPEG 2000 tile grid does not begin at the image");
+        }
+
+        if components.is_empty() {
+            anyhow::bail!("JPEG 2000 image has no components");
+        }
+
+        for component in &components {
+            if component.dx == 0 || component.dy == 0 {
+                anyhow::bail!("invalid JPEG 2000 component subsampling");
+            }
+
+            if component.precision > 31 {
+                anyhow::bail!(
+                    "unsupported JPEG 2000 component precision {}",
+                    component.precision
+                );
+            }
+        }
+
+        if u64::from(x1 - x0) * u64::from(y1 - y0) * components.len() as u64 > MAX_SAMPLES {
+            anyhow::bail!("JPEG 2000 image is too large");
+        }
+
+        let size = Self {
+            area: Rect { x0, y0, x1, y1 },
+            tile_width,
+            tile_height,
+            tile_x0,
+            tile_y0,
+            components,
+        };
+
+        // tile indices are 16 bits
+        if u64::from(size.tiles_wide()) * u64::from(size.tiles_high()) > 65535 {
+            anyhow::bail!("JPEG 2000 image has too many tiles");
+        }
+
+        Ok(size)
+    }
+
+    fn tiles_wide(&self) -> u32 {
+        (self.area.x1 - self.tile_x0).div_ceil(self.tile_width)
+    }
+
+    fn tiles_high(&self) -> u32 {
+        (self.area.y1 - self.tile_y0).div_ceil(self.tile_height)
+    }
+
+    /// The area of a tile on the reference grid (B.3)
+    pub fn tile_area(&self, index: u32) -> Rect {
+        let p = index % self.tiles_wide();
+        let q = index / self.tiles_wide();
+
+        let x0 = u64::from(self.tile_x0) + u64::from(p) * u64::from(self.tile_width);
+        let y0 = u64::from(self.tile_y0) + u64::from(q) * u64::from(self.tile_height);
+
+        Rect {
+            x0: x0.max(u64::from(self.area.x0)) as u32,
+            y0: y0.max(u64::from(self.area.y0)) as u32,
+            x1: (x0 + u64::from(self.tile_width)).min(u64::from(self.area.x1)) as u32,
+            y1: (y0 + u64::from(self.tile_height)).min(u64::from(self.area.y1)) as u32,
+        }
+    }
+
+    /// The width of the index of a component in marker segments, which is
+    /// wider if there are too many components to fit in a byte
+    fn parse_component_index(&self, reader: &mut ByteReader) -> PdfResult<usize> {
+        let index = if self.components.len() < 257 {
+            usize::from(reader.next()?)
+        } else {
+            usize::from(reader.parse_u16()?)
+        };
+
+        if index >= self.components.len() {
+            anyhow::bail!("invalid JPEG 2000 component index {}", index);
+        }
+
+        Ok(index)
+    }
+}
+
+/// The order in which the packets of a tile are interleaved
+#[pdf_enum(Integer)]
+pub(super) enum ProgressionOrder {
+    /// Layer-resolution-component-position
+    Lrcp = 0,
+
+    /// Resolution-layer-component-position
+    Rlcp = 1,
+
+    /// Resolution-position-component-layer
+    Rpcl = 2,
+
+    /// Position-component-resolution-layer
+    Pcrl = 3,
+
+    /// Component-position-resolution-layer
+    Cprl = 4,
+}
+
+/// The options used when coding the code-blocks of a component (Table A.19)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BlockStyle(u8);
+
+impl BlockStyle {
+    /// Whether most significance propagation and magnitude refinement passes
+    /// are stored without arithmetic coding
+    pub fn bypass(self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    /// Whether the contexts are reset after each coding pass
+    pub fn reset(self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Whether the arithmetic coder is terminated after each coding pass
+    pub fn terminate_all(self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// Whether the contexts of each stripe ignore the stripe below it
+    pub fn vertically_causal(self) -> bool {
+        self.0 & 0x08 != 0
+    }
+
+    /// Whether each cleanup pass ends with a segmentation symbol
+    pub fn segmentation_symbols(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+}
+
+/// The coding style of a single component (COD and COC markers)
+#[derive(Debug, Clone)]
+pub(super) struct ComponentCodingStyle {
+    /// The number of wavelet decomposition levels
+    pub levels: u32,
+
+    /// The base 2 logarithm of the nominal code-block width and height
+    pub block_width: u32,
+    pub block_height: u32,
+
+    pub block_style: BlockStyle,
+
+    /// Whether the reversible 5-3 wavelet transform is used, rather than the
+    /// irreversible 9-7 transform
+    pub reversible: bool,
+
+    /// The base 2 logarithm of the precinct width and height at each
+    /// resolution
+    pub precincts: Vec<(u32, u32)>,
+}
+
+impl ComponentCodingStyle {
+    fn parse(reader: &mut ByteReader, has_precincts: bool) -> PdfResult<Self> {
+        let levels = u32::from(reader.next()?);
+        let block_width = u32::from(reader.next()?) + 2;
+        let block_height = u32::from(reader.next()?) + 2;
+        let block_style = BlockStyle(reader.next()?);
+
+        let reversible = match reader.next()? {
+            0 => false,
+            1 => true,
+            n => anyhow::bail!("unknown JPEG 2000 wavelet transform {}", n),
+        };
+
+        if levels > 32 {
+            anyhow::bail!(
+                "invalid number of JPEG 2000 decomposition levels {}",
+                levels
+            );
+        }
+
+        if block_width > 10 || block_height > 10 || block_width + block_height > 12 {
+            anyhow::bail!("invalid JPEG 2000 code-block size");
+        }
+
+        if block_style.0 & 0x40 != 0 {
+            anyhow::bail!("unsupported High Throughput JPEG 2000 code-blocks");
+        }
+
+        let precincts = if has_precincts {
+            (0..=levels)
+                .map(|_| {
+                    let size = reader.next()?;
+
+                    Ok((u32::from(size & 0xf), u32::from(size >> 4)))
+                })
+                .collect::<PdfResult<Vec<_>>>()?
+        } else {
+            vec![(15, 15); levels as usize + 1]
+        };
+
+        Ok(Self {
+            levels,
+            block_width,
+            block_height,
+            block_style,
+            reversible,
+            precincts,
+        })
+    }
+}
+
+/// The coding style of a tile (COD marker)
+#[derive(Debug, Clone)]
+pub(super) struct CodingStyle {
+    pub progression_order: ProgressionOrder,
+    pub layers: u32,
+
+    /// Whether the first three components are transformed between RGB and a
+    /// luminance-chrominance representation
+    pub multiple_component_transform: bool,
+
+    /// Whether packets may be preceded by start of packet markers
+    pub start_of_packet: bool,
+
+    /// Whether packet headers are followed by end of packet header markers
+    pub end_of_packet_header: bool,
+
+    /// The coding style of every component without one of its own
+    pub component: ComponentCodingStyle,
+}
+
+impl CodingStyle {
+    fn parse(reader: &mut ByteReader) -> PdfResult<Self> {
+        let flags = reader.next()?;
+        let progression_order = ProgressionOrder::from_integer(i32::from(reader.next()?))?;
+        let layers = u32::from(reader.parse_u16()?);
+        let multiple_component_transform = reader.next()? != 0;
+
+        Ok(Self {
+            progression_order,
+            layers,
+            multiple_component_transform,
+            start_of_packet: flags & 0x02 != 0,
+            end_of_packet_header: flags & 0x04 != 0,
+            component: ComponentCodingStyle::parse(reader, flags & 0x01 != 0)?,
+        })
+    }
+}
+
+#[pdf_enum(Integer)]
+pub(super) enum QuantizationStyle {
+    /// The coefficients are integers, from the reversible transform
+    None = 0,
+
+    /// Only the step size of the lowest resolution subband is given, from
+    /// which the others are derived
+    ScalarDerived = 1,
+
+    /// The step size of every subband is given
+    ScalarExpounded = 2,
+}
+
+/// The quantization of a component (QCD and QCC markers)
+#[derive(Debug, Clone)]
+pub(super) struct Quantization {
+    pub style: QuantizationStyle,
+    pub guard_bits: u32,
+
+    /// The exponent and mantissa of the step size of each subband
+    step_sizes: Vec<(u32, u32)>,
+}
+
+impl Quantization {
+    fn parse(reader: &mut ByteReader) -> PdfResult<Self> {
+        let flags = reader.next()?;
+        let style = QuantizationStyle::from_integer(i32::from(flags & 0x1f))?;
+
+        let mut step_sizes = Vec::new();
+
+        while !reader.rest().is_empty() {
+            step_sizes.push(match style {
+                QuantizationStyle::None => (u32::from(reader.next()? >> 3), 0),
+                _ => {
+                    let step_size = reader.parse_u16()?;
+
+                    (u32::from(step_size >> 11), u32::from(step_size & 0x7ff))
+                }
+            });
+        }
+
+        Ok(Self {
+            style,
+            guard_bits: u32::from(flags >> 5),
+            step_sizes,
+        })
+    }
+
+    /// The exponent and mantissa of the step size of the `band`th subband,
+    /// counting from the lowest resolution (E.1)
+    pub fn step_size(&self, band: usize, resolution: u32) -> PdfResult<(u32, u32)> {
+        if self.style == QuantizationStyle::ScalarDerived {
+            let Some(&(exponent, mantissa)) = self.step_sizes.first() else {
+                anyhow::bail!("missing JPEG 2000 step size");
+            };
+
+            // the exponent is one smaller for each decomposition level fewer
+            // than that of the lowest resolution subband (E-5)
+            let Some(exponent) = (exponent + 1).checked_sub(resolution.max(1)) else {
+                anyhow::bail!("invalid JPEG 2000 step size");
+            };
+
+            return Ok((exponent, mantissa));
+        }
+
+        match self.step_sizes.get(band) {
+            Some(&step_size) => Ok(step_size),
+            None => anyhow::bail!("missing JPEG 2000 step size for subband {}", band),
+        }
+    }
+}
+
+/// A progression order which applies to part of a tile (POC marker)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ProgressionChange {
+    pub resolution_start: u32,
+    pub component_start: usize,
+    pub layer_end: u32,
+    pub resolution_end: u32,
+    pub component_end: usize,
+    pub order: ProgressionOrder,
+}
+
+/// The coding parameters of a single component of a tile
+#[derive(Debug, Clone)]
+pub(super) struct ComponentParams {
+    pub coding_style: ComponentCodingStyle,
+    pub quantization: Quantization,
+
+    /// The number of bitplanes the region of interest was shifted above the
+    /// background (RGN marker)
+    pub roi_shift: u32,
+}
+
+/// The coding parameters of a tile, from both the main header and the tile's
+/// own headers
+#[derive(Debug)]
+pub(super) struct TileParams {
+    pub coding_style: CodingStyle,
+    pub components: Vec<ComponentParams>,
+    pub progression_changes: Vec<ProgressionChange>,
+}
+
+/// The markers of either the main header or the headers of a tile
+#[derive(Debug, Default)]
+struct Markers {
+    coding_style: Option<CodingStyle>,
+    component_coding_styles: Vec<Option<ComponentCodingStyle>>,
+    quantization: Option<Quantization>,
+    component_quantization: Vec<Option<Quantization>>,
+    roi_shifts: Vec<Option<u32>>,
+    progression_changes: Vec<ProgressionChange>,
+}
+
+impl Markers {
+    fn new(size: &ImageSize) -> Self {
+        let num_components = size.components.len();
+
+        Self {
+            component_coding_styles: vec![None; num_components],
+            component_quantization: vec![None; num_components],
+            roi_shifts: vec![None; num_components],
+            ..Self::default()
+        }
+    }
+
+    /// Parse the marker segment `body` of a marker which may appear in either
+    /// the main header or a tile-part header
+    fn parse(&mut self, marker: u16, body: &[u8], size: &ImageSize) -> PdfResult<()> {
+        let mut reader = ByteReader::new(body);
+
+        match marker {
+            COD => self.coding_style = Some(CodingStyle::parse(&mut reader)?),
+            COC => {
+                let component = size.parse_component_index(&mut reader)?;
+                let has_precincts = reader.next()? & 0x01 != 0;
+
+                self.component_coding_styles[component] =
+                    Some(ComponentCodingStyle::parse(&mut reader, has_precincts)?);
+            }
+            QCD => self.quantization = Some(Quantization::parse(&mut reader)?),
+            QCC => {
+                let component = size.parse_component_index(&mut reader)?;
+
+                self.component_quantization[component] = Some(Quantization::parse(&mut reader)?);
+            }
+            RGN => {
+                let component = size.parse_component_index(&mut reader)?;
+
+                if reader.next()? != 0 {
+                    anyhow::bail!("unsupported JPEG 2000 region of interest style");
+                }
+
+                self.roi_shifts[component] = Some(u32::from(reader.next()?));
+            }
+            POC => {
+                while !reader.rest().is_empty() {
+                    let resolution_start = u32::from(reader.next()?);
+                    let component_start = size.parse_component_index(&mut reader)?;
+                    let layer_end = u32::from(reader.parse_u16()?);
+                    let resolution_end = u32::from(reader.next()?);
+
+                    // the end is exclusive, so may be one past the last
+                    // component, which is written as 0 if that doesn't fit
+                    let component_end = if size.components.len() < 257 {
+                        usize::from(reader.next()?)
+                    } else {
+                        usize::from(reader.parse_u16()?)
+                    };
+
+                    let component_end = match component_end {
+                        0 => 256,
+                        n => n,
+                    };
+
+                    let order = ProgressionOrder::from_integer(i32::from(reader.next()?))?;
+
+                    self.progression_changes.push(ProgressionChange {
+                        resolution_start,
+                        component_start,
+                        layer_end,
+                        resolution_end,
+                        component_end: component_end.min(size.components.len()),
+                        order,
+                    });
+                }
+            }
+            PPM | PPT => anyhow::bail!("unsupported JPEG 2000 packed packet headers"),
+            // comments, and markers which only make it quicker to find data
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the parameters of a tile, where the markers of the tile's own
+    /// headers take precedence over those of the main header, and those of a
+    /// component over those of every component (A.6)
+    fn tile_params(&self, main: &Markers) -> PdfResult<TileParams> {
+        let Some(coding_style) = self.coding_style.as_ref().or(main.coding_style.as_ref()) else {
+            anyhow::bail!("missing JPEG 2000 COD marker");
+        };
+
+        let components = (0..self.component_coding_styles.len())
+            .map(|component| {
+                let coding_style = self.component_coding_styles[component]
+                    .as_ref()
+                    .or(self.coding_style.as_ref().map(|style| &style.component))
+                    .or(main.component_coding_styles[component].as_ref())
+                    .unwrap_or(&coding_style.component);
+
+                let quantization = self.component_quantization[component]
+                    .as_ref()
+                    .or(self.quantization.as_ref())
+                    .or(main.component_quantization[component].as_ref())
+                    .or(main.quantization.as_ref());
+
+                let Some(quantization) = quantization else {
+                    anyhow::bail!("missing JPEG 2000 QCD marker");
+                };
+
+                Ok(ComponentParams {
+                    coding_style: coding_style.clone(),
+                    quantization: quantization.clone(),
+                    roi_shift: self.roi_shifts[component]
+                        .or(main.roi_shifts[component])
+                        .unwrap_or(0),
+                })
+            })
+            .collect::<PdfResult<Vec<_>>>()?;
+
+        let progression_changes = if self.progression_changes.is_empty() {
+            main.progression_changes.clone()
+        } else {
+            self.progression_changes.clone()
+        };
+
+        Ok(TileParams {
+            coding_style: coding_style.clone(),
+            components,
+            progression_changes,
+        })
+    }
+}
+
+/// The tile-parts of a single tile
+#[derive(Default)]
+struct TileParts {
+    markers: Option<Markers>,
+
+    /// The packets of every tile-part, which are decoded as though they were
+    /// contiguous
+    data: Vec<u8>,
+}
+
+/// A decoded component, at its own resolution
+#[derive(Debug)]
+pub(super) struct Component {
+    pub size: ComponentSize,
+
+    /// The area of the component, in its own coordinates, which are those of
+    /// the reference grid divided by the subsampling factors
+    pub area: Rect,
+
+    pub samples: Vec<i32>,
+}
+
+#[derive(Debug)]
+pub(super) struct DecodedImage {
+    /// The image area on the reference grid
+    pub area: Rect,
+    pub components: Vec<Component>,
+}
+
+fn parse_marker_segment<'a>(reader: &mut ByteReader<'a>) -> PdfResult<&'a [u8]> {
+    let len = reader.parse_u16()?;
+
+    let Some(len) = usize::from(len).checked_sub(2) else {
+        anyhow::bail!("invalid JPEG 2000 marker segment length");
+    };
+
+    reader.take(len)
+}
+
+pub(super) fn decode_codestream(data: &[u8]) -> PdfResult<DecodedImage> {
+    let mut reader = ByteReader::new(data);
+
+    if reader.parse_u16()? != SOC {
+        anyhow::bail!("JPEG 2000 codestream does not begin with SOC marker");
+    }
+
+    if reader.parse_u16()? != SIZ {
+        anyhow::bail!("JPEG 2000 codestream does not begin with SIZ marker");
+    }
+
+    let size = ImageSize::parse(&mut ByteReader::new(parse_marker_segment(&mut reader)?))?;
+
+    let mut main = Markers::new(&size);
+
+    loop {
+        let marker = reader.parse_u16()?;
+
+        if marker == SOT {
+            break;
+        }
+
+        main.parse(marker, parse_marker_segment(&mut reader)?, &size)?;
+    }
+
+    let num_tiles = size.tiles_wide() as usize * size.tiles_high() as usize;
+
+    let mut tiles = Vec::new();
+    tiles.resize_with(num_tiles, TileParts::default);
+
+    // the SOT marker of the first tile-part has already been read
+    loop {
+        let start = reader.cursor() - 2;
+
+        let mut header = ByteReader::new(parse_marker_segment(&mut reader)?);
+
+        let index = usize::from(header.parse_u16()?);
+        let len = header.parse_u32()? as usize;
+
+        let Some(tile) = tiles.get_mut(index) else {
+            anyhow::bail!("invalid JPEG 2000 tile index {}", index);
+        };
+
+        let markers = tile.markers.get_or_insert_with(|| Markers::new(&size));
+
+        loop {
+            let marker = reader.parse_u16()?;
+
+            if marker == SOD {
+                break;
+            }
+
+            markers.parse(marker, parse_marker_segment(&mut reader)?, &size)?;
+        }
+
+        // a length of 0 means the tile-part extends to the end of the
+        // codestream, and truncated codestreams are decoded as far as they go
+        let end = if len == 0 {
+            data.len() - if data.ends_with(&[0xff, 0xd9]) { 2 } else { 0 }
+        } else {
+            (start + len).min(data.len())
+        };
+
+        tile.data
+            .extend_from_slice(reader.take(end.saturating_sub(reader.cursor()))?);
+
+        match reader.parse_u16() {
+            Ok(SOT) => continue,
+            Ok(EOC) | Err(..) => break,
+            Ok(marker) => anyhow::bail!("unexpected JPEG 2000 marker {:#x}", marker),
+        }
+    }
+
+    let mut components = size
+        .components
+        .iter()
+        .map(|&component| {
+            let area = size.area.scale_down(component.dx, component.dy);
+
+            Component {
+                size: component,
+                area,
+                samples: vec![0; area.width() as usize * area.height() as usize],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let Some(markers) = &tile.markers else {
+            continue;
+        };
+
+        let params = markers.tile_params(&main)?;
+        let area = size.tile_area(index as u32);
+
+        let tile_components = decode_tile(&size, area, &params, &tile.data)?;
+
+        for (component, (tile_area, samples)) in components.iter_mut().zip(tile_components) {
+            let width = tile_area.width() as usize;
+
+            if width == 0 {
+                continue;
+            }
+
+            let x = (tile_area.x0 - component.area.x0) as usize;
+
+            for (row, line) in samples.chunks(width).enumerate() {
+                let y = (tile_area.y0 - component.area.y0) as usize + row;
+                let start = y * component.area.width() as usize + x;
+
+                component.samples[start..start + width].copy_from_slice(line);
+            }
+        }
+    }
+
+    Ok(DecodedImage {
+        area: size.area,
+        components,
+    })
+}
@@ -0,0 +1,203 @@
+//! The JP2 file format, which wraps a codestream in boxes describing how its
+//! components make up the channels of the image (T.800 Annex I)
+
+use crate::{error::PdfResult, parse_binary::BinaryParser};
+
+use super::ByteReader;
+
+/// A table of colours, indexed by the samples of a component (pclr box)
+#[derive(Debug)]
+pub(super) struct Palette {
+    /// The bit depth of each column
+    depths: Vec<u32>,
+
+    /// The value of each column of each entry
+    entries: Vec<Vec<u64>>,
+}
+
+impl Palette {
+    fn parse(reader: &mut ByteReader) -> PdfResult<Self> {
+        let num_entries = reader.parse_u16()?;
+        let num_columns = reader.next()?;
+
+        let depths = (0..num_columns)
+            .map(|_| Ok(u32::from(reader.next()? & 0x7f) + 1))
+            .collect::<PdfResult<Vec<_>>>()?;
+
+        let entries = (0..num_entries)
+            .map(|_| {
+                depths
+                    .iter()
+                    .map(|depth| {
+                        (0..depth.div_ceil(8))
+                            .try_fold(0, |value, _| Ok((value << 8) | u64::from(reader.next()?)))
+                    })
+                    .collect::<PdfResult<Vec<_>>>()
+            })
+            .collect::<PdfResult<Vec<_>>>()?;
+
+        Ok(Self { depths, entries })
+    }
+
+    /// The value of a column of the entry at `index`, scaled to 8 bits
+    pub fn get(&self, index: i32, column: usize) -> PdfResult<u8> {
+        let Some(&depth) = self.depths.get(column) else {
+            anyhow::bail!("invalid JPEG 2000 palette column {}", column);
+        };
+
+        let index = usize::try_from(index)
+            .unwrap_or(0)
+            .min(self.entries.len().saturating_sub(1));
+
+        let value = self.entries.get(index).map_or(0, |entry| entry[column]);
+
+        Ok(scale_to_u8(value, depth))
+    }
+}
+
+/// Scale an unsigned sample with `depth` bits to 8 bits
+pub(super) fn scale_to_u8(value: u64, depth: u32) -> u8 {
+    let max = (1 << depth) - 1;
+
+    ((value.min(max) * 255 + max / 2) / max) as u8
+}
+
+/// The source of a channel: either a component, or a column of the palette
+/// indexed by a component (cmap box)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ComponentMapping {
+    pub component: usize,
+    pub palette_column: Option<usize>,
+}
+
+#[pdf_enum(Integer)]
+pub(super) enum ChannelType {
+    Color = 0,
+    Opacity = 1,
+    PremultipliedOpacity = 2,
+    Unspecified = 65535,
+}
+
+/// The role of a channel (cdef box)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ChannelDefinition {
+    pub channel: usize,
+    pub kind: ChannelType,
+
+    /// The colour the channel is for, counting from 1, where 0 is the whole
+    /// image
+    pub association: u16,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Jp2Header {
+    /// The enumerated colour space of the image, if it isn't given by an ICC
+    /// profile
+    pub color_space: Option<u32>,
+
+    pub palette: Option<Palette>,
+    pub component_mapping: Option<Vec<ComponentMapping>>,
+    pub channel_definitions: Option<Vec<ChannelDefinition>>,
+}
+
+impl Jp2Header {
+    fn parse(data: &[u8]) -> PdfResult<Self> {
+        let mut reader = ByteReader::new(data);
+        let mut header = Self::default();
+        let mut has_color = false;
+
+        while !reader.rest().is_empty() {
+            let (kind, body) = next_box(&mut reader)?;
+            let mut reader = ByteReader::new(body);
+
+            match &kind {
+                // only the first colour specification is used
+                b"colr" if !has_color => {
+                    let method = reader.next()?;
+
+                    // the precedence and approximation
+                    reader.take(2)?;
+
+                    if method == 1 {
+                        header.color_space = Some(reader.parse_u32()?);
+                    }
+
+                    has_color = true;
+                }
+                b"pclr" => header.palette = Some(Palette::parse(&mut reader)?),
+                b"cmap" => {
+                    let mut mapping = Vec::new();
+
+                    while !reader.rest().is_empty() {
+                        let component = usize::from(reader.parse_u16()?);
+                        let kind = reader.next()?;
+                        let column = usize::from(reader.next()?);
+
+                        mapping.push(ComponentMapping {
+                            component,
+                            palette_column: (kind == 1).then_some(column),
+                        });
+                    }
+
+                    header.component_mapping = Some(mapping);
+                }
+                b"cdef" => {
+                    let num_channels = reader.parse_u16()?;
+
+                    let definitions = (0..num_channels)
+                        .map(|_| {
+                            Ok(ChannelDefinition {
+                                channel: usize::from(reader.parse_u16()?),
+                                kind: ChannelType::from_integer(i32::from(reader.parse_u16()?))?,
+                                association: reader.parse_u16()?,
+                            })
+                        })
+                        .collect::<PdfResult<Vec<_>>>()?;
+
+                    header.channel_definitions = Some(definitions);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(header)
+    }
+}
+
+/// The type and contents of the next box
+fn next_box<'a>(reader: &mut ByteReader<'a>) -> PdfResult<([u8; 4], &'a [u8])> {
+    let len = reader.parse_u32()?;
+    let kind = reader.parse_u32_bytes()?;
+
+    let len = match len {
+        // the box extends to the end of the file
+        0 => reader.rest().len(),
+        // the length doesn't fit in 32 bits
+        1 => usize::try_from(reader.parse_u64()?)?.saturating_sub(16),
+        n => (n as usize).saturating_sub(8),
+    };
+
+    // the codestream is often all that remains of a truncated file, so the
+    // last box is allowed to be cut short
+    let len = len.min(reader.rest().len());
+
+    Ok((kind, reader.take(len)?))
+}
+
+/// Find the header and codestream of a JP2 file
+pub(super) fn parse_jp2(data: &[u8]) -> PdfResult<(Jp2Header, &[u8])> {
+    let mut reader = ByteReader::new(data);
+    let mut header = Jp2Header::default();
+
+    while !reader.rest().is_empty() {
+        let (kind, body) = next_box(&mut reader)?;
+
+        match &kind {
+            b"jp2h" => header = Jp2Header::parse(body)?,
+            b"jp2c" => return Ok((header, body)),
+            _ => {}
+        }
+    }
+
+    anyhow::bail!("JPEG 2000 file has no codestream")
+}
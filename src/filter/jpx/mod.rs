@@ -0,0 +1,531 @@
+//! Decoding of JPEG 2000 (ITU-T T.800) images, either as a bare codestream or
+//! wrapped in the JP2 file format
+//!
+//! Every component of the codestream is decoded at full resolution, and then
+//! the palette, component mapping and channel definitions of the JP2 header
+//! decide how the components make up the colour and opacity channels of the
+//! image
+//!
+//! Packed packet headers (the PPM and PPT markers) and the High Throughput
+//! block coder of T.814 are not supported
+
+use crate::{error::PdfResult, parse_binary::BinaryParser};
+
+use codestream::{decode_codestream, Component, DecodedImage};
+use jp2::{parse_jp2, scale_to_u8, ChannelType, Jp2Header};
+
+mod block;
+mod codestream;
+mod jp2;
+mod packet;
+mod tile;
+mod wavelet;
+
+/// Enumerated colour spaces of the JP2 colour specification box
+const CMYK: u32 = 12;
+const SRGB: u32 = 16;
+const GREYSCALE: u32 = 17;
+const SYCC: u32 = 18;
+const E_SRGB: u32 = 20;
+const ROMM_RGB: u32 = 21;
+const E_SYCC: u32 = 24;
+
+/// Reads the markers of a codestream and the boxes of a JP2 file
+struct ByteReader<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.buffer[self.cursor..]
+    }
+
+    fn take(&mut self, len: usize) -> PdfResult<&'a [u8]> {
+        let Some(data) = self.buffer.get(self.cursor..self.cursor + len) else {
+            anyhow::bail!("unexpected eof");
+        };
+
+        self.cursor += len;
+
+        Ok(data)
+    }
+}
+
+impl<'a> BinaryParser for ByteReader<'a> {
+    fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn cursor_mut(&mut self) -> &mut usize {
+        &mut self.cursor
+    }
+}
+
+/// A decoded image, with 8 bits per sample
+#[derive(Debug)]
+pub struct JpxImage {
+    pub width: usize,
+    pub height: usize,
+
+    /// The number of colour channels, which is 1 for greyscale, 3 for RGB and
+    /// 4 for CMYK images
+    pub components: usize,
+
+    /// The samples of the colour channels, interleaved
+    pub data: Vec<u8>,
+
+    /// The opacity of each pixel, if the image has an opacity channel
+    pub alpha: Option<Vec<u8>>,
+}
+
+pub struct JpxDecoder<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> JpxDecoder<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+
+    pub fn decode(self) -> PdfResult<JpxImage> {
+        let (header, codestream) = if self.buffer.starts_with(&[0xff, 0x4f]) {
+            (Jp2Header::default(), self.buffer)
+        } else {
+            parse_jp2(self.buffer)?
+        };
+
+        let image = decode_codestream(codestream)?;
+
+        let width = image.area.width() as usize;
+        let height = image.area.height() as usize;
+
+        let mut channels = channels(&header, &image)?;
+
+        let mut colors = Vec::new();
+        let mut alpha = None;
+        let mut premultiplied = false;
+
+        match &header.channel_definitions {
+            Some(definitions) => {
+                for definition in definitions {
+                    let Some(channel) = channels.get_mut(definition.channel) else {
+                        anyhow::bail!("invalid JPEG 2000 channel {}", definition.channel);
+                    };
+
+                    let channel = std::mem::take(channel);
+
+                    match (definition.kind, definition.association) {
+                        (ChannelType::Color, association) => colors.push((association, channel)),
+                        (ChannelType::Opacity, 0) => alpha = Some(channel),
+                        (ChannelType::PremultipliedOpacity, 0) => {
+                            alpha = Some(channel);
+                            premultiplied = true;
+                        }
+                        _ => {}
+                    }
+                }
+
+                colors.sort_by_key(|&(association, _)| association);
+            }
+            None => colors = channels.into_iter().map(|channel| (0, channel)).collect(),
+        }
+
+        let mut colors = colors
+            .into_iter()
+            .map(|(_, channel)| channel)
+            .collect::<Vec<_>>();
+
+        let components = match header.color_space {
+            Some(GREYSCALE) => 1,
+            Some(SRGB | E_SRGB | ROMM_RGB | SYCC | E_SYCC) => 3,
+            Some(CMYK) => 4,
+            // other colour spaces, and those given by ICC profiles, are
+            // assumed to be the device colour space with as many components
+            _ => match colors.len() {
+                0 => anyhow::bail!("JPEG 2000 image has no colour channels"),
+                1 | 2 => 1,
+                3 => 3,
+                _ => 4,
+            },
+        };
+
+        if colors.len() < components {
+            anyhow::bail!("JPEG 2000 image has too few colour channels");
+        }
+
+        colors.truncate(components);
+
+        if matches!(header.color_space, Some(SYCC | E_SYCC)) {
+            ycc_to_rgb(&mut colors);
+        }
+
+        if let (Some(alpha), true) = (&alpha, premultiplied) {
+            for channel in &mut colors {
+                for (sample, &alpha) in channel.iter_mut().zip(alpha) {
+                    if alpha != 0 {
+                        *sample = (u32::from(*sample) * 255 / u32::from(alpha)).min(255) as u8;
+                    }
+                }
+            }
+        }
+
+        let mut data = Vec::with_capacity(width * height * components);
+
+        for pixel in 0..width * height {
+            data.extend(colors.iter().map(|channel| channel[pixel]));
+        }
+
+        Ok(JpxImage {
+            width,
+            height,
+            components,
+            data,
+            alpha,
+        })
+    }
+}
+
+/// Convert luminance and chrominance to RGB (T.800 Annex G.3, with the
+/// chrominance centred on the middle of the 8 bit range)
+fn ycc_to_rgb(colors: &mut [Vec<u8>]) {
+    let [y, cb, cr] = colors else {
+        return;
+    };
+
+    for ((y, cb), cr) in y.iter_mut().zip(cb.iter_mut()).zip(cr.iter_mut()) {
+        let luma = f32::from(*y);
+        let cb_diff = f32::from(*cb) - 128.0;
+        let cr_diff = f32::from(*cr) - 128.0;
+
+        *y = (luma + 1.402 * cr_diff).round().clamp(0.0, 255.0) as u8;
+        *cb = (luma - 0.344_136 * cb_diff - 0.714_136 * cr_diff)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+        *cr = (luma + 1.772 * cb_diff).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+/// Scale a sample of a component to 8 bits
+fn component_sample(component: &Component, sample: i32) -> u8 {
+    let precision = component.size.precision;
+
+    let sample = if component.size.signed {
+        i64::from(sample) + (1 << (precision - 1))
+    } else {
+        i64::from(sample)
+    };
+
+    scale_to_u8(sample.max(0) as u64, precision)
+}
+
+/// Resample a component to the full resolution of the image, mapping each
+/// sample with `f`
+fn resample(
+    image: &DecodedImage,
+    component: &Component,
+    f: impl Fn(i32) -> PdfResult<u8>,
+) -> PdfResult<Vec<u8>> {
+    let area = image.area;
+    let component_width = component.area.width();
+    let component_height = component.area.height();
+
+    if component_width == 0 || component_height == 0 {
+        return Ok(vec![0; area.width() as usize * area.height() as usize]);
+    }
+
+    let mut samples = Vec::with_capacity(area.width() as usize * area.height() as usize);
+
+    for y in area.y0..area.y1 {
+        let row = (y / component.size.dy)
+            .saturating_sub(component.area.y0)
+            .min(component_height - 1);
+
+        for x in area.x0..area.x1 {
+            let col = (x / component.size.dx)
+                .saturating_sub(component.area.x0)
+                .min(component_width - 1);
+
+            samples.push(f(component.samples[(row * component_width + col) as usize])?);
+        }
+    }
+
+    Ok(samples)
+}
+
+/// The samples of each channel of the image, which are either components or
+/// colours from the palette
+fn channels(header: &Jp2Header, image: &DecodedImage) -> PdfResult<Vec<Vec<u8>>> {
+    let Some(mapping) = &header.component_mapping else {
+        return image
+            .components
+            .iter()
+            .map(|component| {
+                resample(image, component, |sample| {
+                    Ok(component_sample(component, sample))
+                })
+            })
+            .collect();
+    };
+
+    mapping
+        .iter()
+        .map(|mapping| {
+            let Some(component) = image.components.get(mapping.component) else {
+                anyhow::bail!("invalid JPEG 2000 component {}", mapping.component);
+            };
+
+            match (mapping.palette_column, &header.palette) {
+                (None, _) => resample(image, component, |sample| {
+                    Ok(component_sample(component, sample))
+                }),
+                (Some(column), Some(palette)) => {
+                    resample(image, component, |sample| palette.get(sample, column))
+                }
+                (Some(..), None) => anyhow::bail!("JPEG 2000 image has no palette"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_util::JP2_WITH_PALETTE_AND_ALPHA;
+
+    use super::JpxDecoder;
+
+    #[test]
+    fn lossless_greyscale() {
+        let data = [
+            // SIZ: 8x4, one 8 bit component
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x07, 0x01, 0x01, // COD: one decomposition level with the 5-3 filter
+            0xff, 0x52, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x04, 0x04, 0x00, 0x01,
+            // QCD: no quantization
+            0xff, 0x5c, 0x00, 0x07, 0x40, 0x40, 0x48, 0x48, 0x50, // SOT
+            0xff, 0x90, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x00, 0x01, 0xff, 0x93,
+            0xdf, 0x80, 0x50, 0x07, 0xf3, 0x3e, 0x3f, 0x34, 0x97, 0xc2, 0x42, 0xff, 0x3f, 0xcf,
+            0xc0, 0x22, 0x7e, 0x01, 0x73, 0xf3, 0x05, 0x16, 0xbb, 0x5e, 0xa0, 0x93, 0x68, 0x52,
+            0x4c, 0x0c, 0x21, 0xa5, 0x44, 0x2a, 0x70, 0x95, 0x7e, 0xd3, 0xe7, 0x3f, 0x18, 0xb2,
+            0x92, 0x91, 0xdf, 0xff, 0xd9,
+        ];
+
+        let image = JpxDecoder::new(&data).decode().unwrap();
+
+        assert_eq!((image.width, image.height, image.components), (8, 4, 1));
+        assert!(image.alpha.is_none());
+        assert_eq!(
+            image.data,
+            [
+                0, 37, 74, 111, 148, 185, 222, 3, 91, 128, 165, 202, 239, 20, 57, 94, 182, 219, 0,
+                37, 74, 111, 148, 185, 17, 54, 91, 128, 165, 202, 239, 20,
+            ]
+        );
+    }
+
+    /// The samples of an image, row by row, from a function of their position
+    fn samples(width: u32, height: u32, f: impl Fn(u32, u32) -> u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y) as u8)
+            .collect()
+    }
+
+    /// Interleave the samples of each colour channel
+    fn interleave(channels: &[Vec<u8>]) -> Vec<u8> {
+        (0..channels[0].len())
+            .flat_map(|i| channels.iter().map(move |channel| channel[i]))
+            .collect()
+    }
+
+    fn assert_close(decoded: &[u8], expected: &[u8], tolerance: u8) {
+        assert_eq!(decoded.len(), expected.len());
+
+        for (i, (&decoded, &expected)) in decoded.iter().zip(expected).enumerate() {
+            assert!(
+                decoded.abs_diff(expected) <= tolerance,
+                "sample {} is {}, expected {}",
+                i,
+                decoded,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn irreversible_greyscale() {
+        // 16x16, two decomposition levels with the 9-7 filter, and the step
+        // sizes of every subband derived from that of the lowest resolution
+        let data = [
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x07, 0x01, 0x01, 0xff, 0x52, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00,
+            0x00, 0x00, 0x00, 0xff, 0x5c, 0x00, 0x05, 0x41, 0x50, 0x00, 0xff, 0x90, 0x00, 0x0a,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x68, 0x00, 0x01, 0xff, 0x93, 0xcf, 0xcc, 0x58, 0x11,
+            0x1d, 0xa7, 0x8e, 0x5e, 0x13, 0x6f, 0xdc, 0x14, 0x78, 0x19, 0x23, 0x11, 0x40, 0xd6,
+            0xf4, 0xbc, 0x83, 0x3a, 0x2f, 0x4c, 0x67, 0xc0, 0xf9, 0xc4, 0x40, 0xf9, 0xc4, 0x80,
+            0x22, 0x18, 0x23, 0x3a, 0x9c, 0x22, 0xe6, 0xc1, 0x03, 0x7f, 0x66, 0x90, 0xcb, 0x7e,
+            0x82, 0xff, 0x7f, 0xe0, 0x2e, 0x85, 0xf8, 0x44, 0xba, 0x17, 0xe1, 0x13, 0x01, 0x22,
+            0x91, 0x7e, 0x11, 0x3f, 0x08, 0x80, 0x14, 0x1a, 0x22, 0x18, 0x23, 0xb7, 0x14, 0x1a,
+            0x22, 0x18, 0x23, 0xb7, 0x15, 0x37, 0x15, 0x37, 0x03, 0x7f, 0x66, 0x9f, 0x03, 0x7f,
+            0x66, 0x9f, 0xff, 0xd9,
+        ];
+
+        let image = JpxDecoder::new(&data).decode().unwrap();
+
+        assert_eq!((image.width, image.height, image.components), (16, 16, 1));
+        assert_close(&image.data, &samples(16, 16, |x, y| x * 8 + y * 6), 2);
+    }
+
+    #[test]
+    fn reversible_color_transform() {
+        // 8x8 RGB, with the reversible component transform and 5-3 filter
+        let data = [
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+            0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0xff, 0x52, 0x00, 0x0c, 0x00,
+            0x00, 0x00, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0xff, 0x5c, 0x00, 0x07, 0x40,
+            0x48, 0x50, 0x50, 0x58, 0xff, 0x5d, 0x00, 0x08, 0x01, 0x40, 0x50, 0x58, 0x58, 0x60,
+            0xff, 0x5d, 0x00, 0x08, 0x02, 0x40, 0x50, 0x58, 0x58, 0x60, 0xff, 0x90, 0x00, 0x0a,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x6b, 0x00, 0x01, 0xff, 0x93, 0xc7, 0xda, 0x20, 0x12,
+            0x0f, 0x31, 0xb4, 0x5c, 0x26, 0x9a, 0x84, 0x29, 0x03, 0x66, 0x2f, 0xc4, 0x38, 0xd3,
+            0x1f, 0xc7, 0xe0, 0x20, 0x13, 0x92, 0x62, 0x61, 0xe4, 0x0f, 0x89, 0xd4, 0x42, 0xbd,
+            0xec, 0x6c, 0x10, 0xf3, 0xe9, 0x38, 0xc7, 0xe0, 0x1a, 0x06, 0x41, 0x94, 0x8c, 0xb4,
+            0x07, 0x36, 0xee, 0x67, 0x18, 0xd3, 0xff, 0x7f, 0xc0, 0x3e, 0x10, 0xe0, 0x3e, 0x40,
+            0x60, 0x22, 0x1a, 0x0f, 0x03, 0x7e, 0x97, 0xc0, 0x7c, 0xe0, 0xe0, 0x7d, 0x40, 0x60,
+            0x21, 0x6e, 0x13, 0x00, 0xce, 0x91, 0xc0, 0xfa, 0x80, 0xe0, 0x7d, 0x40, 0x60, 0x22,
+            0x1a, 0x15, 0x00, 0xcf, 0xe3, 0xff, 0xd9,
+        ];
+
+        let image = JpxDecoder::new(&data).decode().unwrap();
+
+        let expected = interleave(&[
+            samples(8, 8, |x, _| x * 32),
+            samples(8, 8, |_, y| y * 32),
+            samples(8, 8, |x, y| 255 - x * 16 - y * 16),
+        ]);
+
+        assert_eq!((image.width, image.height, image.components), (8, 8, 3));
+        assert_eq!(image.data, expected);
+    }
+
+    #[test]
+    fn irreversible_color_transform() {
+        // the image of `reversible_color_transform`, with the irreversible
+        // component transform and 9-7 filter, and a step size of a half in
+        // every subband
+        let data = [
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+            0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0xff, 0x52, 0x00, 0x0c, 0x00,
+            0x00, 0x00, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0xff, 0x5c, 0x00, 0x0b, 0x42,
+            0x48, 0x00, 0x50, 0x00, 0x50, 0x00, 0x58, 0x00, 0xff, 0x90, 0x00, 0x0a, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x98, 0x00, 0x01, 0xff, 0x93, 0xcf, 0xc0, 0x4c, 0x12, 0x5c, 0x85,
+            0xbe, 0x69, 0xf5, 0xc5, 0x24, 0xfa, 0x51, 0x7b, 0x5f, 0xd9, 0xe1, 0xd2, 0x15, 0x49,
+            0x6d, 0x7f, 0xcf, 0xc0, 0x50, 0x13, 0x92, 0x33, 0x4f, 0x16, 0x28, 0x3c, 0x0e, 0xb0,
+            0x1c, 0xcf, 0x19, 0xc1, 0xd1, 0xb7, 0x40, 0xfd, 0x39, 0x9a, 0x93, 0xcf, 0xc0, 0x48,
+            0x00, 0xfd, 0x7d, 0x4f, 0xb0, 0xb3, 0x1d, 0x64, 0x56, 0xe5, 0x55, 0x3d, 0xfe, 0x84,
+            0x81, 0x16, 0xa3, 0x25, 0xc0, 0x7c, 0x81, 0xe0, 0x7c, 0xe2, 0x40, 0x22, 0x18, 0x23,
+            0x8a, 0x9c, 0xe2, 0xdf, 0x03, 0x7d, 0xf8, 0x3b, 0x93, 0x8a, 0x57, 0x81, 0x97, 0xc0,
+            0xf9, 0xc4, 0xc1, 0xf5, 0x04, 0x80, 0x21, 0x6d, 0x61, 0x3d, 0x96, 0x29, 0xd6, 0x20,
+            0x67, 0x00, 0xcf, 0xcb, 0x18, 0x6f, 0x2c, 0x39, 0xa3, 0x9f, 0xc0, 0xf9, 0xc4, 0xc0,
+            0xf9, 0xc4, 0x00, 0x22, 0x18, 0x09, 0x3c, 0x22, 0xfb, 0x20, 0xff, 0x21, 0x00, 0xcf,
+            0x5e, 0x90, 0xc6, 0x01, 0xf2, 0xd7, 0xff, 0xd9,
+        ];
+
+        let image = JpxDecoder::new(&data).decode().unwrap();
+
+        let expected = interleave(&[
+            samples(8, 8, |x, _| x * 32),
+            samples(8, 8, |_, y| y * 32),
+            samples(8, 8, |x, y| 255 - x * 16 - y * 16),
+        ]);
+
+        assert_eq!((image.width, image.height, image.components), (8, 8, 3));
+        assert_close(&image.data, &expected, 2);
+    }
+
+    #[test]
+    fn multiple_tiles_and_precincts() {
+        // 16x12 in 8x8 tiles, with two decomposition levels and precincts
+        // that split the higher resolutions of each tile in four
+        let data = [
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+            0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x07, 0x01, 0x01, 0xff, 0x52, 0x00, 0x0f, 0x01, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00,
+            0x00, 0x00, 0x01, 0x11, 0x11, 0x22, 0xff, 0x5c, 0x00, 0x0a, 0x40, 0x48, 0x50, 0x50,
+            0x58, 0x50, 0x50, 0x58, 0xff, 0x90, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+            0x00, 0x01, 0xff, 0x93, 0xcf, 0xc0, 0x14, 0x07, 0x83, 0x5a, 0xd4, 0xec, 0x00, 0xc0,
+            0xf9, 0xc1, 0x00, 0x04, 0x3f, 0xa0, 0x7c, 0xe0, 0x40, 0x03, 0xc0, 0xf9, 0xc1, 0x40,
+            0xf9, 0xc0, 0x80, 0x04, 0x3f, 0x03, 0x00, 0xc0, 0x7c, 0x80, 0x80, 0x0d, 0x03, 0xa0,
+            0x3e, 0x40, 0x60, 0x0b, 0x3c, 0x63, 0xc0, 0x7c, 0x80, 0xa0, 0x3e, 0x40, 0x60, 0x0d,
+            0x03, 0x0b, 0x3c, 0x63, 0xff, 0x90, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x46,
+            0x00, 0x01, 0xff, 0x93, 0xc7, 0xda, 0x0a, 0x07, 0xcf, 0x9b, 0x95, 0x7f, 0x00, 0xc0,
+            0xf9, 0xc1, 0x00, 0x04, 0x3f, 0xa0, 0x7c, 0xe0, 0x40, 0x03, 0xc0, 0xf9, 0xc1, 0x40,
+            0xf9, 0xc0, 0x80, 0x04, 0x3f, 0x03, 0x00, 0xc0, 0x7c, 0x80, 0x80, 0x0d, 0x03, 0xa0,
+            0x3e, 0x40, 0x60, 0x0b, 0x3c, 0x63, 0xc0, 0x7c, 0x80, 0xa0, 0x3e, 0x40, 0x60, 0x0d,
+            0x03, 0x0b, 0x3c, 0x63, 0xff, 0x90, 0x00, 0x0a, 0x00, 0x02, 0x00, 0x00, 0x00, 0x36,
+            0x00, 0x01, 0xff, 0x93, 0xc3, 0xea, 0x03, 0x07, 0x40, 0x7f, 0xa0, 0x7c, 0xe0, 0x40,
+            0x03, 0xc0, 0xf9, 0xc1, 0x40, 0xf9, 0xc0, 0x80, 0x04, 0x3f, 0x03, 0xa0, 0x3e, 0x40,
+            0x60, 0x0b, 0x3c, 0x63, 0xc0, 0x7c, 0x80, 0xa0, 0x3e, 0x40, 0x60, 0x0d, 0x03, 0x0b,
+            0x3c, 0x63, 0xff, 0x90, 0x00, 0x0a, 0x00, 0x03, 0x00, 0x00, 0x00, 0x36, 0x00, 0x01,
+            0xff, 0x93, 0xc7, 0xda, 0x06, 0x0b, 0x5a, 0x17, 0xa0, 0x7c, 0xe0, 0x40, 0x03, 0xc0,
+            0xf9, 0xc1, 0x40, 0xf9, 0xc0, 0x80, 0x04, 0x3f, 0x03, 0xa0, 0x3e, 0x40, 0x60, 0x0b,
+            0x3c, 0x63, 0xc0, 0x7c, 0x80, 0xa0, 0x3e, 0x40, 0x60, 0x0d, 0x03, 0x0b, 0x3c, 0x63,
+            0xff, 0xd9,
+        ];
+
+        let image = JpxDecoder::new(&data).decode().unwrap();
+
+        assert_eq!((image.width, image.height, image.components), (16, 12, 1));
+        assert_eq!(image.data, samples(16, 12, |x, y| x * 8 + y * 10));
+    }
+
+    #[test]
+    fn jp2_palette_and_opacity() {
+        let image = JpxDecoder::new(JP2_WITH_PALETTE_AND_ALPHA)
+            .decode()
+            .unwrap();
+
+        let palette = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+
+        assert_eq!((image.width, image.height, image.components), (4, 2, 3));
+        assert_eq!(
+            image.data,
+            samples(4, 2, |x, y| (x + y) % 3)
+                .into_iter()
+                .flat_map(|index| palette[index as usize])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(image.alpha, Some(samples(4, 2, |x, _| x * 85)));
+    }
+
+    #[test]
+    fn samples_of_all_components_are_limited() {
+        let data = [
+            // SIZ: 16384x16384, three 8 bit components
+            0xff, 0x4f, 0xff, 0x51, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00,
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+            0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0xff, 0xd9,
+        ];
+
+        let err = JpxDecoder::new(&data).decode().unwrap_err();
+
+        assert_eq!(err.to_string(), "JPEG 2000 image is too large");
+    }
+}
@@ -0,0 +1,534 @@
+//! The packets of a tile, each of which holds the coded data contributed to
+//! one layer by the code-blocks of a precinct (T.800 B.9 to B.12)
+
+use crate::error::PdfResult;
+
+use super::{
+    codestream::{ImageSize, ProgressionChange, ProgressionOrder, TileParams},
+    tile::{Rect, TileComponent},
+};
+
+/// Reads the bits of a packet header, where each byte following 0xff has a
+/// 0 bit stuffed into its most significant bit (B.10.1)
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    byte: u8,
+    bits: u32,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            byte: 0,
+            bits: 0,
+        }
+    }
+
+    fn read_bit(&mut self) -> PdfResult<u32> {
+        if self.bits == 0 {
+            let stuffed = self.byte == 0xff;
+
+            let Some(&byte) = self.data.get(self.pos) else {
+                anyhow::bail!("unexpected end of JPEG 2000 packet header");
+            };
+
+            self.byte = byte;
+            self.pos += 1;
+            self.bits = if stuffed { 7 } else { 8 };
+        }
+
+        self.bits -= 1;
+
+        Ok(u32::from(self.byte >> self.bits) & 1)
+    }
+
+    fn read_bits(&mut self, len: u32) -> PdfResult<u32> {
+        let mut value = 0;
+
+        for _ in 0..len {
+            value = (value << 1) | self.read_bit()?;
+        }
+
+        Ok(value)
+    }
+
+    /// The position of the end of the header, which is padded to a byte
+    /// boundary, and never ends with 0xff
+    fn end(self) -> usize {
+        if self.byte == 0xff {
+            self.pos + 1
+        } else {
+            self.pos
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TagTreeNode {
+    /// The value of the node, if it is known yet
+    value: Option<u32>,
+
+    /// The value is known to be at least this
+    low: u32,
+}
+
+/// A tree of the minimum of groups of values, such that a value is coded
+/// relative to the nodes above it (B.10.2)
+pub(super) struct TagTree {
+    /// The width and nodes of each level of the tree, from the leaves to the
+    /// root
+    levels: Vec<(u32, Vec<TagTreeNode>)>,
+}
+
+impl TagTree {
+    pub fn new(mut width: u32, mut height: u32) -> Self {
+        let mut levels = Vec::new();
+
+        loop {
+            let node = TagTreeNode {
+                value: None,
+                low: 0,
+            };
+
+            levels.push((width, vec![node; width as usize * height as usize]));
+
+            if width <= 1 && height <= 1 {
+                break;
+            }
+
+            width = width.div_ceil(2);
+            height = height.div_ceil(2);
+        }
+
+        Self { levels }
+    }
+
+    /// Decode whether the leaf at `(x, y)` is less than `threshold`, reading
+    /// only as much as is needed to tell
+    fn decode(
+        &mut self,
+        reader: &mut HeaderReader,
+        x: u32,
+        y: u32,
+        threshold: u32,
+    ) -> PdfResult<Option<u32>> {
+        let mut low = 0;
+        let mut value = None;
+
+        for (depth, (width, nodes)) in self.levels.iter_mut().enumerate().rev() {
+            let node = &mut nodes[((y >> depth) * *width + (x >> depth)) as usize];
+
+            low = low.max(node.low);
+
+            while low < threshold && node.value.is_none() {
+                if reader.read_bit()? == 1 {
+                    node.value = Some(low);
+                } else {
+                    low += 1;
+                }
+            }
+
+            node.low = low;
+            value = node.value;
+        }
+
+        Ok(value.filter(|&value| value < threshold))
+    }
+
+    /// Decode the value of the leaf at `(x, y)`
+    fn decode_value(&mut self, reader: &mut HeaderReader, x: u32, y: u32) -> PdfResult<u32> {
+        let mut threshold = 1;
+
+        loop {
+            if let Some(value) = self.decode(reader, x, y, threshold)? {
+                return Ok(value);
+            }
+
+            threshold += 1;
+        }
+    }
+}
+
+/// Decode the number of coding passes contributed by a code-block (Table B.4)
+fn decode_num_passes(reader: &mut HeaderReader) -> PdfResult<u32> {
+    if reader.read_bit()? == 0 {
+        return Ok(1);
+    }
+
+    if reader.read_bit()? == 0 {
+        return Ok(2);
+    }
+
+    match reader.read_bits(2)? {
+        3 => {}
+        n => return Ok(3 + n),
+    }
+
+    match reader.read_bits(5)? {
+        31 => Ok(37 + reader.read_bits(7)?),
+        n => Ok(6 + n),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Packet {
+    layer: u32,
+    resolution: usize,
+    component: usize,
+    precinct: usize,
+}
+
+/// The precinct of a resolution which begins at `(x, y)` on the reference
+/// grid, if there is one (B.12.1.3)
+fn precinct_at(
+    tile_area: Rect,
+    component: &TileComponent,
+    dx: u32,
+    dy: u32,
+    resolution: usize,
+    x: u32,
+    y: u32,
+) -> Option<usize> {
+    let level = component.levels() - resolution as u32;
+    let res = &component.resolutions[resolution];
+
+    if res.num_precincts() == 0 {
+        return None;
+    }
+
+    // precincts begin on multiples of their size, and the first precincts
+    // may begin at the edge of the tile instead
+    let begins = |pos: u32, tile_start: u32, res_start: u32, scale: u32, size: u32| {
+        u64::from(pos) % (u64::from(scale) << (size + level)) == 0
+            || (pos == tile_start && u64::from(res_start) % (1 << size) != 0)
+    };
+
+    if !begins(y, tile_area.y0, res.area.y0, dy, res.precinct_height)
+        || !begins(x, tile_area.x0, res.area.x0, dx, res.precinct_width)
+    {
+        return None;
+    }
+
+    let px = (u64::from(x).div_ceil(u64::from(dx) << level) >> res.precinct_width)
+        - u64::from(res.area.x0 >> res.precinct_width);
+    let py = (u64::from(y).div_ceil(u64::from(dy) << level) >> res.precinct_height)
+        - u64::from(res.area.y0 >> res.precinct_height);
+
+    if px >= u64::from(res.precincts_wide) || py >= u64::from(res.precincts_high) {
+        return None;
+    }
+
+    Some(py as usize * res.precincts_wide as usize + px as usize)
+}
+
+/// The order of the packets of a tile, following its progression order and
+/// any changes to it
+pub(super) fn packet_order(
+    tile_area: Rect,
+    size: &ImageSize,
+    components: &[TileComponent],
+    params: &TileParams,
+) -> Vec<Packet> {
+    let layers = params.coding_style.layers;
+
+    let progressions = if params.progression_changes.is_empty() {
+        vec![ProgressionChange {
+            resolution_start: 0,
+            component_start: 0,
+            layer_end: layers,
+            resolution_end: 33,
+            component_end: components.len(),
+            order: params.coding_style.progression_order,
+        }]
+    } else {
+        params.progression_changes.clone()
+    };
+
+    // the next layer of each precinct, as a packet may already have been
+    // included by an earlier progression
+    let mut next_layers = components
+        .iter()
+        .map(|component| {
+            component
+                .resolutions
+                .iter()
+                .map(|resolution| vec![0_u32; resolution.num_precincts()])
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut packets = Vec::new();
+
+    for progression in progressions {
+        let layer_end = progression.layer_end.min(layers);
+        let resolutions =
+            progression.resolution_start as usize..progression.resolution_end as usize;
+        let components_range = progression.component_start..progression.component_end;
+
+        let mut emit = |layer: u32, resolution: usize, component: usize, precinct: usize| {
+            let next_layer = &mut next_layers[component][resolution][precinct];
+
+            if layer == *next_layer {
+                *next_layer += 1;
+
+                packets.push(Packet {
+                    layer,
+                    resolution,
+                    component,
+                    precinct,
+                });
+            }
+        };
+
+        let num_precincts = |component: usize, resolution: usize| {
+            components[component]
+                .resolutions
+                .get(resolution)
+                .map_or(0, |resolution| resolution.num_precincts())
+        };
+
+        // the positions on the reference grid at which precincts may begin
+        let positions = || {
+            let mut step_x = u64::MAX;
+            let mut step_y = u64::MAX;
+
+            for component in components_range.clone() {
+                let tile_component = &components[component];
+                let size = size.components[component];
+
+                for resolution in resolutions.clone() {
+                    let Some(res) = tile_component.resolutions.get(resolution) else {
+                        continue;
+                    };
+
+                    let level = tile_component.levels() - resolution as u32;
+
+                    step_x = step_x.min(u64::from(size.dx) << (res.precinct_width + level));
+                    step_y = step_y.min(u64::from(size.dy) << (res.precinct_height + level));
+                }
+            }
+
+            let mut positions = Vec::new();
+
+            if step_x == u64::MAX {
+                return positions;
+            }
+
+            let mut y = u64::from(tile_area.y0);
+
+            while y < u64::from(tile_area.y1) {
+                let mut x = u64::from(tile_area.x0);
+
+                while x < u64::from(tile_area.x1) {
+                    positions.push((x as u32, y as u32));
+                    x += step_x - x % step_x;
+                }
+
+                y += step_y - y % step_y;
+            }
+
+            positions
+        };
+
+        let precinct = |component: usize, resolution: usize, (x, y): (u32, u32)| {
+            let tile_component = &components[component];
+            let size = size.components[component];
+
+            if resolution >= tile_component.resolutions.len() {
+                return None;
+            }
+
+            precinct_at(
+                tile_area,
+                tile_component,
+                size.dx,
+                size.dy,
+                resolution,
+                x,
+                y,
+            )
+        };
+
+        match progression.order {
+            ProgressionOrder::Lrcp => {
+                for layer in 0..layer_end {
+                    for resolution in resolutions.clone() {
+                        for component in components_range.clone() {
+                            for precinct in 0..num_precincts(component, resolution) {
+                                emit(layer, resolution, component, precinct);
+                            }
+                        }
+                    }
+                }
+            }
+            ProgressionOrder::Rlcp => {
+                for resolution in resolutions.clone() {
+                    for layer in 0..layer_end {
+                        for component in components_range.clone() {
+                            for precinct in 0..num_precincts(component, resolution) {
+                                emit(layer, resolution, component, precinct);
+                            }
+                        }
+                    }
+                }
+            }
+            ProgressionOrder::Rpcl => {
+                let positions = positions();
+
+                for resolution in resolutions.clone() {
+                    for &position in &positions {
+                        for component in components_range.clone() {
+                            if let Some(precinct) = precinct(component, resolution, position) {
+                                for layer in 0..layer_end {
+                                    emit(layer, resolution, component, precinct);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ProgressionOrder::Pcrl => {
+                for position in positions() {
+                    for component in components_range.clone() {
+                        for resolution in resolutions.clone() {
+                            if let Some(precinct) = precinct(component, resolution, position) {
+                                for layer in 0..layer_end {
+                                    emit(layer, resolution, component, precinct);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ProgressionOrder::Cprl => {
+                let positions = positions();
+
+                for component in components_range.clone() {
+                    for &position in &positions {
+                        for resolution in resolutions.clone() {
+                            if let Some(precinct) = precinct(component, resolution, position) {
+                                for layer in 0..layer_end {
+                                    emit(layer, resolution, component, precinct);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    packets
+}
+
+/// Read a packet beginning at `pos`, adding the data it contains to its
+/// code-blocks, and giving the position of the end of the packet (B.10)
+fn decode_packet(
+    components: &mut [TileComponent],
+    packet: Packet,
+    params: &TileParams,
+    data: &[u8],
+    mut pos: usize,
+) -> PdfResult<usize> {
+    let style = params.components[packet.component].coding_style.block_style;
+    let resolution = &mut components[packet.component].resolutions[packet.resolution];
+
+    if params.coding_style.start_of_packet && data[pos..].starts_with(&[0xff, 0x91]) {
+        pos = (pos + 6).min(data.len());
+    }
+
+    let mut reader = HeaderReader::new(data, pos);
+
+    // the band, code-block and segment each piece of data belongs to, and its
+    // length
+    let mut contributions = Vec::new();
+
+    // the first bit is 0 if the packet is empty
+    if reader.read_bit()? == 1 {
+        for (band_index, band) in resolution.bands.iter_mut().enumerate() {
+            let precinct = &mut band.precincts[packet.precinct];
+
+            for (block_index, block) in precinct.blocks.iter_mut().enumerate() {
+                let x = block_index as u32 % precinct.blocks_wide;
+                let y = block_index as u32 / precinct.blocks_wide;
+
+                let included = if block.included {
+                    reader.read_bit()? == 1
+                } else {
+                    precinct
+                        .inclusion
+                        .decode(&mut reader, x, y, packet.layer + 1)?
+                        .is_some()
+                };
+
+                if !included {
+                    continue;
+                }
+
+                if !block.included {
+                    block.zero_bitplanes =
+                        precinct.zero_bitplanes.decode_value(&mut reader, x, y)?;
+                    block.included = true;
+                }
+
+                let passes = decode_num_passes(&mut reader)?;
+
+                while reader.read_bit()? == 1 {
+                    block.length_bits += 1;
+                }
+
+                for (segment, passes) in block.add_passes(passes, style) {
+                    let len = reader.read_bits(block.length_bits + passes.ilog2())?;
+
+                    contributions.push((band_index, block_index, segment, len as usize));
+                }
+            }
+        }
+    }
+
+    pos = reader.end().min(data.len());
+
+    if params.coding_style.end_of_packet_header && data[pos..].starts_with(&[0xff, 0x92]) {
+        pos += 2;
+    }
+
+    for (band, block, segment, len) in contributions {
+        let end = (pos + len).min(data.len());
+
+        resolution.bands[band].precincts[packet.precinct].blocks[block].segments[segment]
+            .data
+            .extend_from_slice(&data[pos..end]);
+
+        pos = end;
+    }
+
+    Ok(pos)
+}
+
+/// Read the packets of a tile, in order, stopping early if the data has been
+/// truncated
+pub(super) fn decode_packets(
+    components: &mut [TileComponent],
+    packets: &[Packet],
+    params: &TileParams,
+    data: &[u8],
+) {
+    let mut pos = 0;
+
+    for &packet in packets {
+        if pos >= data.len() {
+            break;
+        }
+
+        // the only error is a packet header that is cut off by the end of
+        // the data
+        let Ok(next) = decode_packet(components, packet, params, data, pos) else {
+            break;
+        };
+
+        pos = next;
+    }
+}
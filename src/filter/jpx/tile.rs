@@ -0,0 +1,512 @@
+//! The division of each tile-component into resolutions, subbands, precincts
+//! and code-blocks (T.800 Annex B), and the reconstruction of its samples once
+//! the code-blocks have been read
+
+use crate::error::PdfResult;
+
+use super::{
+    block::{BlockParams, CodeBlock},
+    codestream::{ComponentParams, ComponentSize, ImageSize, TileParams},
+    packet::{decode_packets, packet_order, TagTree},
+    wavelet::inverse_transform,
+};
+
+/// An area of the reference grid, or of one of the domains derived from it,
+/// from `(x0, y0)` inclusive to `(x1, y1)` exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct Rect {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl Rect {
+    pub fn width(&self) -> u32 {
+        self.x1.saturating_sub(self.x0)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1.saturating_sub(self.y0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.height() == 0
+    }
+
+    /// Divide the coordinates by `dx` and `dy`, rounding up
+    pub fn scale_down(&self, dx: u32, dy: u32) -> Self {
+        Self {
+            x0: self.x0.div_ceil(dx),
+            y0: self.y0.div_ceil(dy),
+            x1: self.x1.div_ceil(dx),
+            y1: self.y1.div_ceil(dy),
+        }
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        Self {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        }
+    }
+}
+
+/// `value / 2^shift`, rounded up
+pub(super) fn ceil_shift(value: u32, shift: u32) -> u32 {
+    ((u64::from(value) + (1 << shift) - 1) >> shift) as u32
+}
+
+/// `value / 2^shift`, rounded up, for values which may be negative
+fn ceil_shift_signed(value: i64, shift: u32) -> u32 {
+    (value + (1 << shift) - 1).div_euclid(1 << shift) as u32
+}
+
+/// Which of the horizontal and vertical filters of a subband are high-pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Orientation {
+    LowLow,
+    HighLow,
+    LowHigh,
+    HighHigh,
+}
+
+impl Orientation {
+    fn is_horizontally_high_pass(self) -> bool {
+        matches!(self, Orientation::HighLow | Orientation::HighHigh)
+    }
+
+    fn is_vertically_high_pass(self) -> bool {
+        matches!(self, Orientation::LowHigh | Orientation::HighHigh)
+    }
+
+    /// The base 2 logarithm of the gain of the subband, which adds to its
+    /// nominal dynamic range (E-4)
+    fn gain(self) -> u32 {
+        u32::from(self.is_horizontally_high_pass()) + u32::from(self.is_vertically_high_pass())
+    }
+}
+
+/// The code-blocks of a subband which lie within a single precinct
+pub(super) struct Precinct {
+    pub blocks_wide: u32,
+    pub blocks: Vec<CodeBlock>,
+
+    /// The first layer in which each code-block is included
+    pub inclusion: TagTree,
+
+    /// The number of missing most significant bitplanes of each code-block
+    pub zero_bitplanes: TagTree,
+}
+
+pub(super) struct Band {
+    pub orientation: Orientation,
+    pub area: Rect,
+
+    /// The precincts of the band, in the same order as those of the resolution
+    pub precincts: Vec<Precinct>,
+
+    /// The number of bitplanes of the magnitudes of the coefficients
+    pub magnitude_bits: u32,
+
+    pub step_size: f32,
+}
+
+pub(super) struct Resolution {
+    pub area: Rect,
+
+    /// The base 2 logarithm of the precinct width and height
+    pub precinct_width: u32,
+    pub precinct_height: u32,
+
+    pub precincts_wide: u32,
+    pub precincts_high: u32,
+
+    pub bands: Vec<Band>,
+}
+
+impl Resolution {
+    pub fn num_precincts(&self) -> usize {
+        self.precincts_wide as usize * self.precincts_high as usize
+    }
+}
+
+pub(super) struct TileComponent {
+    pub area: Rect,
+
+    /// The resolutions, from lowest to highest
+    pub resolutions: Vec<Resolution>,
+}
+
+impl TileComponent {
+    fn new(tile_area: Rect, size: ComponentSize, params: &ComponentParams) -> PdfResult<Self> {
+        let area = tile_area.scale_down(size.dx, size.dy);
+
+        let style = &params.coding_style;
+        let levels = style.levels;
+
+        let resolutions = (0..=levels)
+            .map(|resolution| {
+                // the number of decompositions applied to the full resolution
+                // to reach this one
+                let level = levels - resolution;
+
+                let resolution_area = Rect {
+                    x0: ceil_shift(area.x0, level),
+                    y0: ceil_shift(area.y0, level),
+                    x1: ceil_shift(area.x1, level),
+                    y1: ceil_shift(area.y1, level),
+                };
+
+                let (precinct_width, precinct_height) = style.precincts[resolution as usize];
+
+                let (precincts_wide, precincts_high) = if resolution_area.is_empty() {
+                    (0, 0)
+                } else {
+                    (
+                        ceil_shift(resolution_area.x1, precinct_width)
+                            - (resolution_area.x0 >> precinct_width),
+                        ceil_shift(resolution_area.y1, precinct_height)
+                            - (resolution_area.y0 >> precinct_height),
+                    )
+                };
+
+                let orientations: &[Orientation] = if resolution == 0 {
+                    &[Orientation::LowLow]
+                } else {
+                    &[
+                        Orientation::HighLow,
+                        Orientation::LowHigh,
+                        Orientation::HighHigh,
+                    ]
+                };
+
+                let mut resolution = Resolution {
+                    area: resolution_area,
+                    precinct_width,
+                    precinct_height,
+                    precincts_wide,
+                    precincts_high,
+                    bands: Vec::new(),
+                };
+
+                resolution.bands = orientations
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &orientation)| {
+                        Self::band(area, size, params, &resolution, level, index, orientation)
+                    })
+                    .collect::<PdfResult<Vec<_>>>()?;
+
+                Ok(resolution)
+            })
+            .collect::<PdfResult<Vec<_>>>()?;
+
+        Ok(Self { area, resolutions })
+    }
+
+    fn band(
+        area: Rect,
+        size: ComponentSize,
+        params: &ComponentParams,
+        resolution: &Resolution,
+        level: u32,
+        index: usize,
+        orientation: Orientation,
+    ) -> PdfResult<Band> {
+        let style = &params.coding_style;
+
+        // the number of decompositions applied to the full resolution to
+        // produce the band
+        let band_level = if orientation == Orientation::LowLow {
+            level
+        } else {
+            level + 1
+        };
+
+        // the high-pass coefficients lie between the low-pass coefficients,
+        // offset by half a sample (B-15)
+        let offset = |high_pass: bool| {
+            if high_pass {
+                1_i64 << (band_level - 1)
+            } else {
+                0
+            }
+        };
+
+        let x_offset = offset(orientation.is_horizontally_high_pass());
+        let y_offset = offset(orientation.is_vertically_high_pass());
+
+        let band_area = Rect {
+            x0: ceil_shift_signed(i64::from(area.x0) - x_offset, band_level),
+            y0: ceil_shift_signed(i64::from(area.y0) - y_offset, band_level),
+            x1: ceil_shift_signed(i64::from(area.x1) - x_offset, band_level),
+            y1: ceil_shift_signed(i64::from(area.y1) - y_offset, band_level),
+        };
+
+        let resolution_index = style.levels - level;
+
+        let band_index = match resolution_index {
+            0 => 0,
+            r => 1 + 3 * (r as usize - 1) + index,
+        };
+
+        let (exponent, mantissa) = params
+            .quantization
+            .step_size(band_index, resolution_index)?;
+
+        let Some(magnitude_bits) =
+            (params.quantization.guard_bits + exponent + params.roi_shift).checked_sub(1)
+        else {
+            anyhow::bail!("invalid JPEG 2000 subband dynamic range");
+        };
+
+        let step_size = if style.reversible {
+            1.0
+        } else {
+            let range = i32::try_from(size.precision + orientation.gain())? - exponent as i32;
+
+            (2.0_f64.powi(range) * (1.0 + f64::from(mantissa) / 2048.0)) as f32
+        };
+
+        // precincts and code-blocks are half the size in the subbands of the
+        // higher resolutions, which are half the size of the resolution
+        let (precinct_width, precinct_height) = if resolution_index == 0 {
+            (resolution.precinct_width, resolution.precinct_height)
+        } else {
+            (
+                resolution.precinct_width.saturating_sub(1),
+                resolution.precinct_height.saturating_sub(1),
+            )
+        };
+
+        let block_width = style.block_width.min(precinct_width);
+        let block_height = style.block_height.min(precinct_height);
+
+        let mut precincts = Vec::with_capacity(resolution.num_precincts());
+
+        for py in 0..resolution.precincts_high {
+            for px in 0..resolution.precincts_wide {
+                let x0 = u64::from((resolution.area.x0 >> resolution.precinct_width) + px)
+                    << precinct_width;
+                let y0 = u64::from((resolution.area.y0 >> resolution.precinct_height) + py)
+                    << precinct_height;
+
+                let precinct_area = Rect {
+                    x0: x0.min(u64::from(u32::MAX)) as u32,
+                    y0: y0.min(u64::from(u32::MAX)) as u32,
+                    x1: (x0 + (1 << precinct_width)).min(u64::from(u32::MAX)) as u32,
+                    y1: (y0 + (1 << precinct_height)).min(u64::from(u32::MAX)) as u32,
+                }
+                .intersection(&band_area);
+
+                precincts.push(Self::precinct(precinct_area, block_width, block_height));
+            }
+        }
+
+        Ok(Band {
+            orientation,
+            area: band_area,
+            precincts,
+            magnitude_bits,
+            step_size,
+        })
+    }
+
+    fn precinct(area: Rect, block_width: u32, block_height: u32) -> Precinct {
+        let (blocks_wide, blocks_high) = if area.is_empty() {
+            (0, 0)
+        } else {
+            (
+                ceil_shift(area.x1, block_width) - (area.x0 >> block_width),
+                ceil_shift(area.y1, block_height) - (area.y0 >> block_height),
+            )
+        };
+
+        let mut blocks = Vec::with_capacity(blocks_wide as usize * blocks_high as usize);
+
+        for by in 0..blocks_high {
+            for bx in 0..blocks_wide {
+                let x0 = ((area.x0 >> block_width) + bx) << block_width;
+                let y0 = ((area.y0 >> block_height) + by) << block_height;
+
+                let block_area = Rect {
+                    x0,
+                    y0,
+                    x1: x0 + (1 << block_width),
+                    y1: y0 + (1 << block_height),
+                }
+                .intersection(&area);
+
+                blocks.push(CodeBlock::new(block_area));
+            }
+        }
+
+        Precinct {
+            blocks_wide,
+            blocks,
+            inclusion: TagTree::new(blocks_wide, blocks_high),
+            zero_bitplanes: TagTree::new(blocks_wide, blocks_high),
+        }
+    }
+
+    /// The number of decomposition levels, which is one less than the number
+    /// of resolutions
+    pub fn levels(&self) -> u32 {
+        self.resolutions.len() as u32 - 1
+    }
+
+    /// Decode the code-blocks of a subband, giving its dequantized
+    /// coefficients
+    fn band_coefficients(band: &Band, params: &ComponentParams) -> PdfResult<Vec<f32>> {
+        let width = band.area.width() as usize;
+        let mut coefficients = vec![0.0; width * band.area.height() as usize];
+
+        let block_params = BlockParams {
+            orientation: band.orientation,
+            magnitude_bits: band.magnitude_bits,
+            style: params.coding_style.block_style,
+            reversible: params.coding_style.reversible,
+            roi_shift: params.roi_shift,
+        };
+
+        for precinct in &band.precincts {
+            for block in &precinct.blocks {
+                if block.passes == 0 {
+                    continue;
+                }
+
+                let values = block.decode(&block_params)?;
+
+                let block_width = block.area.width() as usize;
+                let x = (block.area.x0 - band.area.x0) as usize;
+
+                for (row, line) in values.chunks(block_width).enumerate() {
+                    let start = (block.area.y0 - band.area.y0) as usize * width + row * width + x;
+
+                    for (coefficient, value) in coefficients[start..start + block_width]
+                        .iter_mut()
+                        .zip(line)
+                    {
+                        *coefficient = value * band.step_size;
+                    }
+                }
+            }
+        }
+
+        Ok(coefficients)
+    }
+
+    /// The samples of the tile-component, before any component transform or
+    /// level shift
+    fn reconstruct(&self, params: &ComponentParams) -> PdfResult<Vec<f32>> {
+        let mut samples = Self::band_coefficients(&self.resolutions[0].bands[0], params)?;
+
+        for resolution in &self.resolutions[1..] {
+            let bands = resolution
+                .bands
+                .iter()
+                .map(|band| Self::band_coefficients(band, params))
+                .collect::<PdfResult<Vec<_>>>()?;
+
+            samples = inverse_transform(
+                resolution.area,
+                [&samples, &bands[0], &bands[1], &bands[2]],
+                params.coding_style.reversible,
+            );
+        }
+
+        Ok(samples)
+    }
+}
+
+/// Undo the transform of the first three components from RGB (G.2 and G.3)
+fn inverse_component_transform(samples: &mut [Vec<f32>], reversible: bool) -> PdfResult<()> {
+    let [y0, y1, y2, ..] = samples else {
+        anyhow::bail!("JPEG 2000 component transform requires three components");
+    };
+
+    if y0.len() != y1.len() || y0.len() != y2.len() {
+        anyhow::bail!("JPEG 2000 component transform of subsampled components");
+    }
+
+    for ((y0, y1), y2) in y0.iter_mut().zip(y1.iter_mut()).zip(y2.iter_mut()) {
+        let (red, green, blue) = if reversible {
+            let green = *y0 - ((*y1 + *y2) / 4.0).floor();
+
+            (*y2 + green, green, *y1 + green)
+        } else {
+            (
+                *y0 + 1.402 * *y2,
+                *y0 - 0.34413 * *y1 - 0.71414 * *y2,
+                *y0 + 1.772 * *y1,
+            )
+        };
+
+        *y0 = red;
+        *y1 = green;
+        *y2 = blue;
+    }
+
+    Ok(())
+}
+
+/// Decode a tile, giving the area and samples of each of its components
+pub(super) fn decode_tile(
+    size: &ImageSize,
+    area: Rect,
+    params: &TileParams,
+    data: &[u8],
+) -> PdfResult<Vec<(Rect, Vec<i32>)>> {
+    let mut components = size
+        .components
+        .iter()
+        .zip(&params.components)
+        .map(|(&component, params)| TileComponent::new(area, component, params))
+        .collect::<PdfResult<Vec<_>>>()?;
+
+    let packets = packet_order(area, size, &components, params);
+
+    decode_packets(&mut components, &packets, params, data);
+
+    let mut samples = components
+        .iter()
+        .zip(&params.components)
+        .map(|(component, params)| component.reconstruct(params))
+        .collect::<PdfResult<Vec<_>>>()?;
+
+    if params.coding_style.multiple_component_transform {
+        inverse_component_transform(&mut samples, params.components[0].coding_style.reversible)?;
+    }
+
+    Ok(components
+        .iter()
+        .zip(samples)
+        .zip(&size.components)
+        .map(|((component, samples), size)| {
+            // samples of unsigned components are centred on 0 when coded
+            let (min, max, shift) = if size.signed {
+                (
+                    -(1_i64 << (size.precision - 1)),
+                    (1_i64 << (size.precision - 1)) - 1,
+                    0,
+                )
+            } else {
+                (
+                    0,
+                    (1_i64 << size.precision) - 1,
+                    1_i64 << (size.precision - 1),
+                )
+            };
+
+            let samples = samples
+                .iter()
+                .map(|&sample| (sample.round() as i64 + shift).clamp(min, max) as i32)
+                .collect();
+
+            (component.area, samples)
+        })
+        .collect())
+}
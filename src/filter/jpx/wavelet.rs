@@ -0,0 +1,154 @@
+//! The inverse discrete wavelet transform, which reconstructs each resolution
+//! of a tile-component from the one below it and its three high-pass subbands
+//! (T.800 Annex F)
+
+use super::tile::Rect;
+
+/// The lifting parameters of the irreversible 9-7 filter (Table F.4)
+const ALPHA: f64 = -1.586_134_342_059_924;
+const BETA: f64 = -0.052_980_118_572_961;
+const GAMMA: f64 = 0.882_911_075_530_934;
+const DELTA: f64 = 0.443_506_852_043_971;
+const K: f64 = 1.230_174_104_914_001;
+
+/// The number of samples each line is extended by on either side, which is
+/// enough for the four lifting steps of the 9-7 filter
+const EXTENSION: usize = 4;
+
+/// Reconstruct the samples of the resolution covering `area` from the
+/// resolution below it and the high-pass subbands, in the order LL, HL, LH
+/// and HH (F.3.2)
+pub(super) fn inverse_transform(area: Rect, bands: [&[f32]; 4], reversible: bool) -> Vec<f32> {
+    let width = area.width() as usize;
+    let height = area.height() as usize;
+
+    // the low-pass coefficients are at even positions, and the high-pass at
+    // odd positions
+    let low_x0 = area.x0.div_ceil(2);
+    let high_x0 = area.x0 / 2;
+    let low_y0 = area.y0.div_ceil(2);
+    let high_y0 = area.y0 / 2;
+
+    let low_width = (area.x1.div_ceil(2) - low_x0) as usize;
+    let high_width = (area.x1 / 2 - high_x0) as usize;
+
+    let mut samples = Vec::with_capacity(width * height);
+
+    for v in area.y0..area.y1 {
+        for u in area.x0..area.x1 {
+            let (x, band_width) = if u % 2 == 0 {
+                (u / 2 - low_x0, low_width)
+            } else {
+                (u / 2 - high_x0, high_width)
+            };
+
+            let y = if v % 2 == 0 {
+                v / 2 - low_y0
+            } else {
+                v / 2 - high_y0
+            };
+
+            let band = bands[(u % 2 + 2 * (v % 2)) as usize];
+
+            samples.push(
+                band.get(y as usize * band_width + x as usize)
+                    .copied()
+                    .unwrap_or(0.0),
+            );
+        }
+    }
+
+    if width == 0 || height == 0 {
+        return samples;
+    }
+
+    for row in samples.chunks_mut(width) {
+        synthesize(row, area.x0, reversible);
+    }
+
+    let mut column = vec![0.0; height];
+
+    for x in 0..width {
+        for (y, sample) in column.iter_mut().enumerate() {
+            *sample = samples[y * width + x];
+        }
+
+        synthesize(&mut column, area.y0, reversible);
+
+        for (y, &sample) in column.iter().enumerate() {
+            samples[y * width + x] = sample;
+        }
+    }
+
+    samples
+}
+
+/// Apply one lifting step to the samples at positions of the given parity,
+/// which are updated from their neighbours
+fn lift(line: &mut [f64], parity: usize, f: impl Fn(f64, f64, f64) -> f64) {
+    for i in 1..line.len() - 1 {
+        if i % 2 == parity {
+            line[i] = f(line[i - 1], line[i], line[i + 1]);
+        }
+    }
+}
+
+/// The one-dimensional inverse transform of a row or column whose first
+/// sample is at position `start` (F.3.6)
+fn synthesize(line: &mut [f32], start: u32, reversible: bool) {
+    let len = line.len();
+
+    if len == 1 {
+        if start % 2 == 1 {
+            line[0] /= 2.0;
+        }
+
+        return;
+    }
+
+    // the line is extended by mirroring it about its first and last samples
+    // (F.3.7)
+    let period = 2 * (len - 1);
+
+    let mut extended = (0..len + 2 * EXTENSION)
+        .map(|i| {
+            let i = (i + period * EXTENSION - EXTENSION) % period;
+
+            f64::from(line[i.min(period - i)])
+        })
+        .collect::<Vec<_>>();
+
+    // the parity of the positions of low-pass coefficients in `extended`
+    let even = (start as usize + EXTENSION) % 2;
+    let odd = 1 - even;
+
+    if reversible {
+        lift(&mut extended, even, |prev, x, next| {
+            x - ((prev + next + 2.0) / 4.0).floor()
+        });
+        lift(&mut extended, odd, |prev, x, next| {
+            x + ((prev + next) / 2.0).floor()
+        });
+    } else {
+        for (i, x) in extended.iter_mut().enumerate() {
+            *x *= if i % 2 == even { K } else { 1.0 / K };
+        }
+
+        lift(&mut extended, even, |prev, x, next| {
+            x - DELTA * (prev + next)
+        });
+        lift(&mut extended, odd, |prev, x, next| {
+            x - GAMMA * (prev + next)
+        });
+        lift(&mut extended, even, |prev, x, next| {
+            x - BETA * (prev + next)
+        });
+        lift(&mut extended, odd, |prev, x, next| {
+            x - ALPHA * (prev + next)
+        });
+    }
+
+    for (sample, &x) in line.iter_mut().zip(&extended[EXTENSION..]) {
+        *sample = x as f32;
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    error::PdfResult, filter::predictor::PredictorParams, objects::Object, FromObj, Resolve,
+};
+
+const CLEAR_TABLE: u16 = 256;
+const END_OF_DATA: u16 = 257;
+const FIRST_CODE: u16 = 258;
+const MAX_CODE_LEN: u32 = 12;
+
+#[derive(Debug)]
+pub struct LzwDecoderParams {
+    /// Whether the code length increases one code earlier than strictly
+    /// necessary. The default value is true
+    early_change: bool,
+
+    predictor: PredictorParams,
+}
+
+impl<'a> FromObj<'a> for LzwDecoderParams {
+    fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let mut dict = resolver.assert_dict(obj)?;
+
+        let early_change = dict.get::<i32>("EarlyChange", resolver)?.unwrap_or(1) != 0;
+        let predictor = PredictorParams::from_obj(Object::Dictionary(dict), resolver)?;
+
+        Ok(Self {
+            early_change,
+            predictor,
+        })
+    }
+}
+
+/// Reads codes of varying length, most significant bit first
+struct CodeReader<'a> {
+    buffer: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> CodeReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, bit: 0 }
+    }
+
+    fn next_code(&mut self, len: u32) -> Option<u16> {
+        if self.bit + len as usize > self.buffer.len() * 8 {
+            return None;
+        }
+
+        let mut code = 0;
+
+        for _ in 0..len {
+            let byte = self.buffer[self.bit / 8];
+            let bit = (byte >> (7 - self.bit % 8)) & 1;
+
+            code = (code << 1) | bit as u16;
+            self.bit += 1;
+        }
+
+        Some(code)
+    }
+}
+
+#[derive(Debug)]
+pub struct LzwDecoder<'a> {
+    buffer: &'a [u8],
+    params: LzwDecoderParams,
+}
+
+impl<'a> LzwDecoder<'a> {
+    pub fn new(buffer: &'a [u8], params: LzwDecoderParams) -> Self {
+        Self { buffer, params }
+    }
+
+    pub fn decode(self) -> PdfResult<Vec<u8>> {
+        let out = decode_lzw(self.buffer, self.params.early_change)?;
+
+        self.params.predictor.decode(out)
+    }
+}
+
+/// Every table entry is the output of an earlier code followed by one more
+/// byte, and so is stored as a range of the output rather than copied
+fn decode_lzw(buffer: &[u8], early_change: bool) -> PdfResult<Vec<u8>> {
+    let mut reader = CodeReader::new(buffer);
+    let mut out = Vec::with_capacity(buffer.len() * 2);
+
+    // the `(start, len)` in the output of each code from `FIRST_CODE`
+    let mut table: Vec<(usize, usize)> = Vec::new();
+
+    // the output of the previous code
+    let mut prev: Option<(usize, usize)> = None;
+
+    let mut code_len = 9;
+
+    while let Some(code) = reader.next_code(code_len) {
+        match code {
+            CLEAR_TABLE => {
+                table.clear();
+                prev = None;
+                code_len = 9;
+                continue;
+            }
+            END_OF_DATA => break,
+            _ => {}
+        }
+
+        let start = out.len();
+        let next_code = FIRST_CODE as usize + table.len();
+
+        if code < CLEAR_TABLE {
+            out.push(code as u8);
+        } else if (code as usize) < next_code {
+            let (entry_start, entry_len) = table[(code - FIRST_CODE) as usize];
+            out.extend_from_within(entry_start..entry_start + entry_len);
+        } else if code as usize == next_code {
+            // the code being defined by this very code, which is the output
+            // of the previous code followed by its own first byte
+            let Some((prev_start, prev_len)) = prev else {
+                anyhow::bail!("invalid LZW code {} following a clear table code", code);
+            };
+
+            out.extend_from_within(prev_start..prev_start + prev_len);
+            out.push(out[prev_start]);
+        } else {
+            anyhow::bail!("invalid LZW code {}, expected at most {}", code, next_code);
+        }
+
+        if let Some((prev_start, prev_len)) = prev {
+            if next_code < 1 << MAX_CODE_LEN {
+                table.push((prev_start, prev_len + 1));
+            }
+        }
+
+        prev = Some((start, out.len() - start));
+
+        let next_code = FIRST_CODE as usize + table.len() + early_change as usize;
+
+        code_len = match next_code {
+            ..=511 => 9,
+            512..=1023 => 10,
+            1024..=2047 => 11,
+            _ => MAX_CODE_LEN,
+        };
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode_lzw;
+
+    #[test]
+    fn example_from_spec() {
+        assert_eq!(
+            decode_lzw(
+                &[0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01],
+                true
+            )
+            .unwrap(),
+            b"-----A---B"
+        );
+    }
+}
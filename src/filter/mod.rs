@@ -8,11 +8,23 @@ use crate::{
     FromObj, Resolve,
 };
 
-use flate::{FlateDecoder, FlateDecoderParams};
+use ccitt::{CcittFaxDecoder, CcittFaxDecoderParams};
+use flate::FlateDecoder;
+use jbig2::{Jbig2Decoder, Jbig2DecoderParams};
+use jpx::{JpxDecoder, JpxImage};
+use lzw::{LzwDecoder, LzwDecoderParams};
+use predictor::PredictorParams;
 
 pub mod ascii;
+pub mod ccitt;
 pub mod dct;
 pub mod flate;
+pub mod jbig2;
+pub mod jpx;
+pub mod lzw;
+mod mq;
+pub mod predictor;
+pub mod run_length;
 
 pub(crate) fn decode_stream<'a, 'b>(
     stream: &'b [u8],
@@ -40,19 +52,47 @@ pub(crate) fn decode_stream<'a, 'b>(
                 FilterKind::Ascii85 => {
                     stream = ascii::decode_ascii_85(&stream);
                 }
-                FilterKind::Lzw => todo!("lwz compression"),
+                FilterKind::Lzw => {
+                    let decoder_params =
+                        LzwDecoderParams::from_obj(Object::Dictionary(decode_params), resolver)?;
+
+                    stream = LzwDecoder::new(&stream, decoder_params).decode()?;
+                }
                 FilterKind::Flate => {
                     let decoder_params =
-                        FlateDecoderParams::from_obj(Object::Dictionary(decode_params), resolver)?;
+                        PredictorParams::from_obj(Object::Dictionary(decode_params), resolver)?;
+
+                    stream = FlateDecoder::new(Cow::Owned(stream), decoder_params)?.decode()?;
+                }
+                FilterKind::RunLength => {
+                    stream = run_length::decode_run_length(&stream);
+                }
+                FilterKind::CcittFax => {
+                    let decoder_params = CcittFaxDecoderParams::from_obj(
+                        Object::Dictionary(decode_params),
+                        resolver,
+                    )?;
+
+                    stream = CcittFaxDecoder::new(&stream, decoder_params).decode()?;
+                }
+                FilterKind::Jbig2 => {
+                    let decoder_params =
+                        Jbig2DecoderParams::from_obj(Object::Dictionary(decode_params), resolver)?;
 
-                    stream = FlateDecoder::new(Cow::Owned(stream), decoder_params)?.decode();
+                    let globals = match &decoder_params.globals {
+                        Some(globals) => {
+                            Some(decode_stream(&globals.stream, &globals.dict, resolver)?)
+                        }
+                        None => None,
+                    };
+
+                    stream = Jbig2Decoder::new(&stream, globals.as_deref()).decode()?;
                 }
-                FilterKind::RunLength => todo!("runlength compression"),
-                FilterKind::CcittFax => todo!("ccittfax compression"),
-                FilterKind::Jbig2 => todo!("jbig2 compression"),
                 FilterKind::Dct => stream = DctDecoder::new(Cow::Owned(stream)).decode()?,
-                FilterKind::Jpx => todo!("jpx compression"),
-                FilterKind::Crypt => todo!("crypt compression"),
+                FilterKind::Jpx => stream = JpxDecoder::new(&stream).decode()?.data,
+                // crypt filters are applied by the security handler when the
+                // stream is read
+                FilterKind::Crypt => {}
             }
         }
 
@@ -62,6 +102,26 @@ pub(crate) fn decode_stream<'a, 'b>(
     Ok(Cow::Borrowed(stream))
 }
 
+/// Decode an image compressed with JPXDecode, keeping the number of colour
+/// channels and the opacity of the JPEG 2000 data, which the image dictionary
+/// may not give
+pub(crate) fn decode_jpx_image<'a>(
+    stream: &[u8],
+    stream_dict: &StreamDict<'a>,
+    resolver: &mut dyn Resolve<'a>,
+) -> PdfResult<JpxImage> {
+    // any filters before JPXDecode are applied first
+    let mut stream_dict = stream_dict.clone();
+
+    if let Some(filters) = &mut stream_dict.filter {
+        filters.pop();
+    }
+
+    let stream = decode_stream(stream, &stream_dict, resolver)?;
+
+    JpxDecoder::new(&stream).decode()
+}
+
 #[pdf_enum]
 pub enum FilterKind {
     /// Decodes data encoded in an ASCII hexadecimal representation, reproducing
@@ -0,0 +1,217 @@
+//! The MQ arithmetic decoder, which is shared by JBIG2 (T.88 Annex E) and
+//! JPEG 2000 (T.800 Annex C)
+
+/// The probability estimation state machine: the LPS probability estimate, the
+/// next state after an MPS and after an LPS, and whether to exchange the sense
+/// of the MPS after an LPS
+const QE_TABLE: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true),
+    (0x3401, 2, 6, false),
+    (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false),
+    (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true),
+    (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false),
+    (0x3001, 11, 17, false),
+    (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false),
+    (0x1601, 29, 21, false),
+    (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false),
+    (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false),
+    (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false),
+    (0x2401, 23, 20, false),
+    (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false),
+    (0x1801, 26, 23, false),
+    (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false),
+    (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false),
+    (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false),
+    (0x0441, 35, 32, false),
+    (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false),
+    (0x0141, 38, 35, false),
+    (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false),
+    (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false),
+    (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false),
+    (0x5601, 46, 46, false),
+];
+
+/// The adaptive state of a single context
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Context {
+    index: u8,
+    mps: u8,
+}
+
+impl Context {
+    /// A context which starts in the state at `index` of the probability
+    /// estimation table, rather than the first
+    pub fn new(index: u8) -> Self {
+        Self { index, mps: 0 }
+    }
+}
+
+pub(crate) fn contexts(len: usize) -> Vec<Context> {
+    vec![Context::default(); len]
+}
+
+pub(crate) struct ArithmeticDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    c: u32,
+    a: u32,
+    ct: u32,
+}
+
+impl<'a> ArithmeticDecoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        let mut decoder = Self {
+            data,
+            pos: 0,
+            c: 0,
+            a: 0x8000,
+            ct: 0,
+        };
+
+        decoder.c = u32::from(decoder.byte(0)) << 16;
+        decoder.byte_in();
+        decoder.c <<= 7;
+        decoder.ct -= 7;
+
+        decoder
+    }
+
+    /// Reading past the end of the data behaves as though it were followed by
+    /// a marker, which supplies 1 bits
+    fn byte(&self, pos: usize) -> u8 {
+        self.data.get(pos).copied().unwrap_or(0xff)
+    }
+
+    fn byte_in(&mut self) {
+        if self.byte(self.pos) == 0xff {
+            if self.byte(self.pos + 1) > 0x8f {
+                self.c += 0xff00;
+                self.ct = 8;
+            } else {
+                self.pos += 1;
+                self.c += u32::from(self.byte(self.pos)) << 9;
+                self.ct = 7;
+            }
+        } else {
+            self.pos += 1;
+            self.c += u32::from(self.byte(self.pos)) << 8;
+            self.ct = 8;
+        }
+    }
+
+    pub fn decode(&mut self, cx: &mut Context) -> u8 {
+        let (qe, nmps, nlps, switch) = QE_TABLE[cx.index as usize];
+
+        self.a -= qe;
+
+        let d = if (self.c >> 16) < qe {
+            // the LPS sub-interval, which may be larger than the MPS sub-interval
+            let d = if self.a < qe {
+                cx.index = nmps;
+                cx.mps
+            } else {
+                let d = 1 - cx.mps;
+
+                if switch {
+                    cx.mps = d;
+                }
+
+                cx.index = nlps;
+                d
+            };
+
+            self.a = qe;
+
+            d
+        } else {
+            self.c -= qe << 16;
+
+            if self.a & 0x8000 != 0 {
+                return cx.mps;
+            }
+
+            if self.a < qe {
+                let d = 1 - cx.mps;
+
+                if switch {
+                    cx.mps = d;
+                }
+
+                cx.index = nlps;
+                d
+            } else {
+                cx.index = nmps;
+                cx.mps
+            }
+        };
+
+        loop {
+            if self.ct == 0 {
+                self.byte_in();
+            }
+
+            self.a <<= 1;
+            self.c <<= 1;
+            self.ct -= 1;
+
+            if self.a & 0x8000 != 0 {
+                break;
+            }
+        }
+
+        d
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{contexts, ArithmeticDecoder};
+
+    /// The test sequence for the arithmetic decoder from T.88 Annex H.2
+    #[test]
+    fn test_sequence() {
+        let encoded = [
+            0x84, 0xC7, 0x3B, 0xFC, 0xE1, 0xA1, 0x43, 0x04, 0x02, 0x20, 0x00, 0x00, 0x41, 0x0D,
+            0xBB, 0x86, 0xF4, 0x31, 0x7F, 0xFF, 0x88, 0xFF, 0x37, 0x47, 0x1A, 0xDB, 0x6A, 0xDF,
+            0xFF, 0xAC,
+        ];
+
+        let expected = [
+            0x00, 0x02, 0x00, 0x51, 0x00, 0x00, 0x00, 0xC0, 0x03, 0x52, 0x87, 0x2A, 0xAA, 0xAA,
+            0xAA, 0xAA, 0x82, 0xC0, 0x20, 0x00, 0xFC, 0xD7, 0x9E, 0xF6, 0xBF, 0x7F, 0xED, 0x90,
+            0x4F, 0x46, 0xA3, 0xBF,
+        ];
+
+        let mut decoder = ArithmeticDecoder::new(&encoded);
+        let mut cx = contexts(1);
+
+        let decoded = (0..expected.len())
+            .map(|_| (0..8).fold(0, |byte, _| (byte << 1) | decoder.decode(&mut cx[0])))
+            .collect::<Vec<u8>>();
+
+        assert_eq!(decoded, expected);
+    }
+}
@@ -0,0 +1,303 @@
+use std::cmp::min;
+
+use crate::{error::PdfResult, filter::flate::BitsPerComponent};
+
+/// The parameters of the predictor functions shared by the LZWDecode and
+/// FlateDecode filters
+///
+/// <https://www.adobe.com/content/dam/acom/en/devnet/postscript/pdfs/TN5603.Filters.pdf>
+#[derive(Debug, FromObj)]
+pub struct PredictorParams {
+    /// The default value is 1 (Predictor::Unused)
+    #[field("Predictor", default = Predictor::Unused)]
+    predictor: Predictor,
+
+    /// Specifies the number of samples in the sampled row.
+    ///
+    /// The value of this key only has an effect on the filter if
+    /// the value of `predictor` is greater than 1.
+    ///
+    /// The default value is 1
+    #[field("Columns", default = 1)]
+    columns: u32,
+
+    /// Specifies the number of interleaved color components in a sample.
+    ///
+    /// The value of this key only has an effect on the filter if
+    /// the value of `predictor` is greater than 1
+    ///
+    /// The default value is 1
+    #[field("Colors", default = 1)]
+    colors: u32,
+
+    /// The number of bits used to represent each component.
+    ///
+    /// The possible values are 1, 2, 4, 8, and 16
+    ///
+    /// The default value is 8
+    #[field("BitsPerComponent", default = BitsPerComponent::Eight)]
+    bits_per_component: BitsPerComponent,
+}
+
+#[pdf_enum(Integer)]
+enum Predictor {
+    /// No filter is applied *and* no byte precedes each row
+    Unused = 1,
+
+    /// Each component is subtracted by the corresponding component of the
+    /// pixel to the left of it
+    Tiff = 2,
+
+    /// No filter is applied
+    None = 10,
+
+    /// The pixel is subtracted by the pixel to the left of it
+    Sub = 11,
+
+    /// The pixel is subtracted by the pixel above it
+    Up = 12,
+
+    /// The pixel is subtracted by the average of the pixel to the left and above
+    Average = 13,
+
+    /// The pixel is subtracted by the pixel that comes out of a prediction algorithm
+    Paeth = 14,
+
+    /// A hybrid of all 4
+    Optimum = 15,
+}
+
+/// The filter applied to a single row of data using PNG prediction, given by
+/// the byte preceding each row
+#[pdf_enum(Integer)]
+enum PngFilter {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Average = 3,
+    Paeth = 4,
+}
+
+impl PredictorParams {
+    const fn bits_per_pixel(&self) -> u32 {
+        self.colors * self.bits_per_component as u32
+    }
+
+    /// PNG prediction operates on whole bytes, so pixels smaller than a byte
+    /// are predicted from the previous byte
+    const fn bytes_per_pixel(&self) -> usize {
+        let bytes = self.bits_per_pixel().div_ceil(8) as usize;
+
+        if bytes == 0 {
+            1
+        } else {
+            bytes
+        }
+    }
+
+    const fn bytes_per_row(&self) -> usize {
+        (self.bits_per_pixel() * self.columns).div_ceil(8) as usize
+    }
+
+    /// Undo the prediction applied to `buffer`, the output of the decoding
+    /// filter
+    pub fn decode(&self, buffer: Vec<u8>) -> PdfResult<Vec<u8>> {
+        match self.predictor {
+            Predictor::Unused => Ok(buffer),
+            Predictor::Tiff => Ok(self.decode_tiff(buffer)),
+            Predictor::None
+            | Predictor::Sub
+            | Predictor::Up
+            | Predictor::Average
+            | Predictor::Paeth
+            | Predictor::Optimum => self.decode_png(&buffer),
+        }
+    }
+
+    /// With PNG prediction, the predictor named in the parameters is only a
+    /// hint, and each row begins with a byte giving the filter used for it
+    fn decode_png(&self, buffer: &[u8]) -> PdfResult<Vec<u8>> {
+        let bytes_per_row = self.bytes_per_row();
+        let bpp = self.bytes_per_pixel();
+
+        let mut out = Vec::with_capacity(buffer.len());
+
+        // the row above the first row is treated as all zeroes
+        let mut row_above = vec![0; bytes_per_row];
+
+        for row in buffer.chunks(bytes_per_row + 1) {
+            let filter = PngFilter::from_integer(row[0] as i32)?;
+
+            let this_row = &mut row_above[..row.len() - 1];
+
+            match filter {
+                PngFilter::None => this_row.copy_from_slice(&row[1..]),
+                PngFilter::Sub => Self::decode_sub(this_row, &row[1..], bpp),
+                PngFilter::Up => Self::decode_up(this_row, &row[1..]),
+                PngFilter::Average => Self::decode_average(this_row, &row[1..], bpp),
+                PngFilter::Paeth => Self::decode_paeth(this_row, &row[1..], bpp),
+            }
+
+            out.extend_from_slice(this_row);
+        }
+
+        Ok(out)
+    }
+
+    /// The PNG filters decode a row in place, replacing the row above it
+    fn decode_sub(row: &mut [u8], encoded: &[u8], bpp: usize) {
+        for idx in 0..row.len() {
+            let left = if idx >= bpp { row[idx - bpp] } else { 0 };
+            row[idx] = encoded[idx].wrapping_add(left);
+        }
+    }
+
+    fn decode_up(row: &mut [u8], encoded: &[u8]) {
+        for (b, &encoded) in row.iter_mut().zip(encoded) {
+            *b = encoded.wrapping_add(*b);
+        }
+    }
+
+    fn decode_average(row: &mut [u8], encoded: &[u8], bpp: usize) {
+        for idx in 0..row.len() {
+            let left = if idx >= bpp { row[idx - bpp] } else { 0 };
+            let above = row[idx];
+
+            row[idx] = encoded[idx].wrapping_add(((left as u16 + above as u16) / 2) as u8);
+        }
+    }
+
+    fn decode_paeth(row: &mut [u8], encoded: &[u8], bpp: usize) {
+        // the value above the previous pixel is overwritten as we go, so we
+        // keep it around separately
+        let mut above_left = vec![0; bpp];
+
+        for idx in 0..row.len() {
+            let above = row[idx];
+            let (left, c) = if idx >= bpp {
+                (row[idx - bpp], above_left[idx % bpp])
+            } else {
+                (0, 0)
+            };
+
+            above_left[idx % bpp] = above;
+
+            row[idx] = encoded[idx].wrapping_add(Self::paeth_predictor(
+                i16::from(left),
+                i16::from(above),
+                i16::from(c),
+            ));
+        }
+    }
+
+    fn paeth_predictor(a: i16, b: i16, c: i16) -> u8 {
+        let p = a + b - c;
+        let pa = (p - a).abs();
+        let pb = (p - b).abs();
+        let pc = (p - c).abs();
+
+        match min(min(pa, pb), pc) {
+            // order here for ties is important
+            diff if diff == pa => a as u8,
+            diff if diff == pb => b as u8,
+            diff if diff == pc => c as u8,
+            _ => unreachable!(),
+        }
+    }
+
+    /// TIFF prediction operates on individual components, which may be
+    /// smaller or larger than a byte. Each row starts from zero
+    fn decode_tiff(&self, mut buffer: Vec<u8>) -> Vec<u8> {
+        let bits = self.bits_per_component as u32 as usize;
+        let colors = self.colors as usize;
+        let bytes_per_row = self.bytes_per_row();
+
+        if bytes_per_row == 0 {
+            return buffer;
+        }
+
+        for row in buffer.chunks_mut(bytes_per_row) {
+            match self.bits_per_component {
+                BitsPerComponent::Eight => {
+                    for idx in colors..row.len() {
+                        row[idx] = row[idx].wrapping_add(row[idx - colors]);
+                    }
+                }
+                BitsPerComponent::Sixteen => {
+                    for idx in (colors * 2..row.len().saturating_sub(1)).step_by(2) {
+                        let left =
+                            u16::from_be_bytes([row[idx - colors * 2], row[idx - colors * 2 + 1]]);
+                        let this = u16::from_be_bytes([row[idx], row[idx + 1]]);
+
+                        row[idx..idx + 2].copy_from_slice(&this.wrapping_add(left).to_be_bytes());
+                    }
+                }
+                BitsPerComponent::One | BitsPerComponent::Two | BitsPerComponent::Four => {
+                    let mask = (1u8 << bits) - 1;
+                    let num_components = self.columns as usize * colors;
+
+                    let get = |row: &[u8], idx: usize| {
+                        let bit = idx * bits;
+                        (row[bit / 8] >> (8 - bits - bit % 8)) & mask
+                    };
+
+                    for idx in colors..num_components {
+                        let value = get(row, idx).wrapping_add(get(row, idx - colors)) & mask;
+
+                        let bit = idx * bits;
+                        let shift = 8 - bits - bit % 8;
+
+                        row[bit / 8] = (row[bit / 8] & !(mask << shift)) | (value << shift);
+                    }
+                }
+            }
+        }
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitsPerComponent, Predictor, PredictorParams};
+
+    fn params(predictor: Predictor, columns: u32, colors: u32) -> PredictorParams {
+        PredictorParams {
+            predictor,
+            columns,
+            colors,
+            bits_per_component: BitsPerComponent::Eight,
+        }
+    }
+
+    #[test]
+    fn png_rows() {
+        let encoded = [
+            // sub
+            1, 10, 1, 1, //
+            // up
+            2, 1, 1, 1, //
+            // average
+            3, 5, 0, 0, //
+            // paeth
+            4, 0, 0, 0,
+        ];
+
+        assert_eq!(
+            params(Predictor::Optimum, 3, 1)
+                .decode(encoded.to_vec())
+                .unwrap(),
+            [10, 11, 12, 11, 12, 13, 10, 11, 12, 10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn tiff() {
+        assert_eq!(
+            params(Predictor::Tiff, 3, 2)
+                .decode(vec![1, 2, 1, 1, 1, 1, 5, 5, 1, 1, 1, 1])
+                .unwrap(),
+            [1, 2, 2, 3, 3, 4, 5, 5, 6, 6, 7, 7]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+const END_OF_DATA: u8 = 128;
+
+/// The data is a sequence of runs, each beginning with a length byte. A length
+/// from 0 to 127 is followed by that many plus one bytes to be copied
+/// literally, and a length from 129 to 255 is followed by a single byte to be
+/// repeated 257 minus the length times
+pub(crate) fn decode_run_length(stream: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(stream.len() * 2);
+
+    let mut iter = stream.iter();
+
+    while let Some(&len) = iter.next() {
+        match len {
+            0..=127 => {
+                let literal = iter.as_slice();
+                let len = (len as usize + 1).min(literal.len());
+
+                buffer.extend_from_slice(&literal[..len]);
+
+                iter = literal[len..].iter();
+            }
+            END_OF_DATA => break,
+            129..=255 => match iter.next() {
+                Some(&b) => buffer.resize(buffer.len() + 257 - len as usize, b),
+                None => break,
+            },
+        }
+    }
+
+    buffer
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode_run_length;
+
+    #[test]
+    fn literal_and_repeat_runs() {
+        assert_eq!(
+            decode_run_length(&[2, b'a', b'b', b'c', 253, b'x', 0, b'y', 128]),
+            b"abcxxxxy"
+        );
+
+        assert_eq!(decode_run_length(&[129, 0, 128]), [0; 128]);
+    }
+
+    #[test]
+    fn stops_at_end_of_data() {
+        assert_eq!(decode_run_length(&[1, b'a', b'b', 128, 0, b'c']), b"ab");
+        assert_eq!(decode_run_length(&[128]), b"");
+    }
+
+    #[test]
+    fn truncated_runs() {
+        assert_eq!(decode_run_length(&[4, b'a', b'b']), b"ab");
+        assert_eq!(decode_run_length(&[255]), b"");
+    }
+}
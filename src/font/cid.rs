@@ -167,7 +167,7 @@ impl<'a> FromObj<'a> for CidFontWidths {
                     idx += 1;
                     let width = resolver.assert_number(arr[idx].clone())?;
 
-                    for i in first..last {
+                    for i in first..=last {
                         map.insert(i, width);
                     }
                 }
@@ -180,3 +180,41 @@ impl<'a> FromObj<'a> for CidFontWidths {
         Ok(Self { map, default: 1000 })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, rc::Rc};
+
+    use crate::{objects::Object, xref::Xref, FromObj, Lexer};
+
+    use super::CidFontWidths;
+
+    #[test]
+    fn widths_of_ranges_include_last_cid() {
+        let mut lexer = Lexer::new(
+            Vec::new(),
+            Rc::new(Xref {
+                objects: HashMap::new(),
+            }),
+        )
+        .unwrap();
+
+        // [c_first c_last w] gives every CID from c_first to c_last inclusive
+        // the same width, and [c [w_1 w_2 ...]] gives consecutive CIDs from c
+        // their own widths
+        let w = Object::Array(vec![
+            Object::Integer(1),
+            Object::Integer(3),
+            Object::Integer(500),
+            Object::Integer(10),
+            Object::Array(vec![Object::Integer(250), Object::Integer(750)]),
+        ]);
+
+        let widths = CidFontWidths::from_obj(w, &mut lexer).unwrap();
+
+        assert_eq!(
+            widths.map.into_iter().collect::<Vec<_>>(),
+            vec![(1, 500.0), (2, 500.0), (3, 500.0), (10, 250.0), (11, 750.0)]
+        );
+    }
+}
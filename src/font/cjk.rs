@@ -1,3 +1,5 @@
+use super::cmap::{code_to_u32, CodespaceRange};
+
 #[pdf_enum]
 pub(crate) enum PredefinedCjkCmapName {
     // chinese (simplified)
@@ -145,7 +147,7 @@ pub(crate) enum PredefinedCjkCmapName {
     UniJisUtf16H = "UniJIS-UTF16-H",
 
     /// Vertical version of UniJIS-UTF16-H
-    UniJisUtf16V = "UniJIS-UTF16-",
+    UniJisUtf16V = "UniJIS-UTF16-V",
 
     // korean
     /// KS X 1001:1992 character set, EUC-KR encoding
@@ -179,7 +181,7 @@ pub(crate) enum PredefinedCjkCmapName {
     UniKsUtf16H = "UniKS-UTF16-H",
 
     /// Vertical version of UniKS-UTF16-H
-    UniKsUtf16V = "UniKS-UTF16-",
+    UniKsUtf16V = "UniKS-UTF16-V",
 
     // generic
     /// The horizontal identity mapping for 2-byte CIDs; may be used with CIDFonts using any Registry, Ordering, and Supplement values. It maps 2-byte character codes ranging from 0 to 65,535 to the same 2-byte CID value, interpreted high-order byte first.
@@ -188,3 +190,216 @@ pub(crate) enum PredefinedCjkCmapName {
     /// Vertical version of Identity-H. The mapping is the same as for Identity-H.
     IdentityV = "Identity-V",
 }
+
+/// The form of the character codes used by a predefined CMap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CjkCodeForm {
+    /// Two-byte codes that are Unicode values in the basic multilingual plane
+    Ucs2,
+
+    /// UTF-16BE, so either two-byte codes or four-byte surrogate pairs
+    Utf16,
+
+    /// Single-byte ASCII and two-byte EUC codes
+    Euc,
+
+    /// Single-byte ASCII and two-byte GBK codes
+    Gbk,
+
+    /// GB 18030-2000, with one-, two- and four-byte codes
+    Gb18030,
+
+    /// Single-byte ASCII and two-byte Big Five codes
+    Big5,
+
+    /// Shift-JIS, with single-byte ASCII and half-width katakana, and two-byte
+    /// JIS X 0208 codes
+    ShiftJis,
+
+    /// Two-byte codes using 7-bit ISO-2022-JP
+    Iso2022,
+
+    /// Single-byte ASCII and two-byte Unified Hangul Code
+    Uhc,
+
+    /// Two-byte codes that are equal to their CID
+    Identity,
+}
+
+impl PredefinedCjkCmapName {
+    fn code_form(self) -> CjkCodeForm {
+        match self {
+            Self::UniGbUcs2H
+            | Self::UniGbUcs2V
+            | Self::UniCnsUcs2H
+            | Self::UniCnsUcs2V
+            | Self::UniJisUcs2H
+            | Self::UniJisUcs2V
+            | Self::UniJisUcs2HwH
+            | Self::UniJisUcs2HwV
+            | Self::UniKsUcs2H
+            | Self::UniKsUcs2V => CjkCodeForm::Ucs2,
+            Self::UniGbUtf16H
+            | Self::UniGbUtf16V
+            | Self::UniCnsUtf16H
+            | Self::UniCnsUtf16V
+            | Self::UniJisUtf16H
+            | Self::UniJisUtf16V
+            | Self::UniKsUtf16H
+            | Self::UniKsUtf16V => CjkCodeForm::Utf16,
+            Self::GbEucH
+            | Self::GbEucV
+            | Self::GBpcEucH
+            | Self::GBpcEucV
+            | Self::CnsEucH
+            | Self::CnsEucV
+            | Self::EucH
+            | Self::EucV
+            | Self::KscEucH
+            | Self::KscEucV
+            | Self::KsCpcEucH => CjkCodeForm::Euc,
+            Self::GbkEucH | Self::GbkEucV | Self::GbKpEucH | Self::GbKpEucV => CjkCodeForm::Gbk,
+            Self::Gbk2KH | Self::Gbk2KV => CjkCodeForm::Gb18030,
+            Self::B5pcH
+            | Self::B5pcV
+            | Self::HKscsB5H
+            | Self::HKscsB5V
+            | Self::ETenB5H
+            | Self::ETenB5V
+            | Self::ETenmsB5H
+            | Self::ETenmsB5V => CjkCodeForm::Big5,
+            Self::_83pvRksjH
+            | Self::_90msRksjH
+            | Self::_90msRksjV
+            | Self::_90mspRksjH
+            | Self::_90mspRksjV
+            | Self::_90pvRksjH
+            | Self::AddRksjH
+            | Self::AddRksjV
+            | Self::ExtRksjH
+            | Self::ExtRksjV => CjkCodeForm::ShiftJis,
+            Self::H | Self::V => CjkCodeForm::Iso2022,
+            Self::KsCmsUhcH | Self::KsCmsUhcV | Self::KsCmsUhcHwH | Self::KsCmsUhcHwV => {
+                CjkCodeForm::Uhc
+            }
+            Self::IdentityH | Self::IdentityV => CjkCodeForm::Identity,
+        }
+    }
+
+    /// Whether the CMap maps each 2-byte code to the CID of the same value
+    pub fn is_identity(self) -> bool {
+        matches!(self.code_form(), CjkCodeForm::Identity)
+    }
+
+    pub fn is_vertical(self) -> bool {
+        matches!(
+            self,
+            Self::GbEucV
+                | Self::GBpcEucV
+                | Self::GbkEucV
+                | Self::GbKpEucV
+                | Self::Gbk2KV
+                | Self::UniGbUcs2V
+                | Self::UniGbUtf16V
+                | Self::B5pcV
+                | Self::HKscsB5V
+                | Self::ETenB5V
+                | Self::ETenmsB5V
+                | Self::CnsEucV
+                | Self::UniCnsUcs2V
+                | Self::UniCnsUtf16V
+                | Self::_90msRksjV
+                | Self::_90mspRksjV
+                | Self::AddRksjV
+                | Self::EucV
+                | Self::ExtRksjV
+                | Self::V
+                | Self::UniJisUcs2V
+                | Self::UniJisUcs2HwV
+                | Self::UniJisUtf16V
+                | Self::KscEucV
+                | Self::KsCmsUhcV
+                | Self::KsCmsUhcHwV
+                | Self::UniKsUcs2V
+                | Self::UniKsUtf16V
+                | Self::IdentityV
+        )
+    }
+
+    /// The codespace ranges of the CMap, used to split strings into
+    /// character codes
+    ///
+    /// Where a legacy encoding has gaps in its trail bytes, the ranges are
+    /// widened to cover them, since only the length of each code matters here
+    pub fn codespace_ranges(self) -> Vec<CodespaceRange> {
+        let range = |low: &[u8], high: &[u8]| CodespaceRange::new(low.to_vec(), high.to_vec());
+
+        match self.code_form() {
+            CjkCodeForm::Ucs2 | CjkCodeForm::Identity => vec![range(&[0x00, 0x00], &[0xff, 0xff])],
+            CjkCodeForm::Utf16 => vec![
+                range(&[0x00, 0x00], &[0xd7, 0xff]),
+                range(&[0xd8, 0x00, 0xdc, 0x00], &[0xdb, 0xff, 0xdf, 0xff]),
+                range(&[0xe0, 0x00], &[0xff, 0xff]),
+            ],
+            CjkCodeForm::Euc => vec![
+                range(&[0x00], &[0x80]),
+                range(&[0x8e, 0xa0], &[0x8e, 0xdf]),
+                range(&[0x8f, 0xa1, 0xa1], &[0x8f, 0xfe, 0xfe]),
+                range(&[0xa1, 0xa1], &[0xfe, 0xfe]),
+            ],
+            CjkCodeForm::Gbk => vec![range(&[0x00], &[0x80]), range(&[0x81, 0x40], &[0xfe, 0xfe])],
+            CjkCodeForm::Gb18030 => vec![
+                range(&[0x00], &[0x80]),
+                range(&[0x81, 0x40], &[0xfe, 0xfe]),
+                range(&[0x81, 0x30, 0x81, 0x30], &[0xfe, 0x39, 0xfe, 0x39]),
+            ],
+            CjkCodeForm::Big5 => vec![range(&[0x00], &[0x80]), range(&[0x81, 0x40], &[0xfe, 0xfe])],
+            CjkCodeForm::ShiftJis => vec![
+                range(&[0x00], &[0x80]),
+                range(&[0xa0], &[0xdf]),
+                range(&[0x81, 0x40], &[0x9f, 0xfc]),
+                range(&[0xe0, 0x40], &[0xfc, 0xfc]),
+            ],
+            CjkCodeForm::Iso2022 => vec![range(&[0x21, 0x21], &[0x7e, 0x7e])],
+            CjkCodeForm::Uhc => vec![range(&[0x00], &[0x80]), range(&[0x81, 0x41], &[0xfe, 0xfe])],
+        }
+    }
+
+    /// Map a character code to Unicode without consulting the CMap files
+    /// themselves
+    ///
+    /// This is exact for the Unicode-based CMaps. For the legacy encodings,
+    /// only the single-byte ASCII and half-width katakana codes can be mapped
+    /// without the character collection's tables
+    ///
+    /// Mapping CIDs to Unicode through the `CIDSystemInfo` ordering (Adobe-Japan1,
+    /// Adobe-GB1, Adobe-CNS1 or Adobe-Korea1) is not supported, as those tables are
+    /// not bundled. The two-byte codes of the legacy encodings, and every code of
+    /// `Identity-H` and `Identity-V`, therefore map to `None`
+    pub fn to_unicode(self, code: &[u8]) -> Option<String> {
+        match (self.code_form(), code) {
+            (CjkCodeForm::Ucs2, [_, _]) => char::from_u32(code_to_u32(code)).map(String::from),
+            (CjkCodeForm::Utf16, _) => String::from_utf16(
+                &code
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect::<Vec<u16>>(),
+            )
+            .ok(),
+            (
+                CjkCodeForm::Euc
+                | CjkCodeForm::Gbk
+                | CjkCodeForm::Gb18030
+                | CjkCodeForm::Big5
+                | CjkCodeForm::ShiftJis
+                | CjkCodeForm::Uhc,
+                &[b @ 0x20..=0x7e],
+            ) => Some((b as char).to_string()),
+            (CjkCodeForm::ShiftJis, &[b @ 0xa1..=0xdf])
+            | (CjkCodeForm::Euc, &[0x8e, b @ 0xa1..=0xdf]) => {
+                char::from_u32(0xff61 + (b - 0xa1) as u32).map(String::from)
+            }
+            _ => None,
+        }
+    }
+}
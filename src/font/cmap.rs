@@ -1,4 +1,8 @@
-use crate::stream::Stream;
+use std::collections::HashMap;
+
+use crate::{
+    error::PdfResult, filter::decode_stream, postscript::parse_cmap, stream::Stream, Resolve,
+};
 
 // todo: rename file? to_unicode.rs
 
@@ -8,3 +12,235 @@ pub struct ToUnicodeCmapStream<'a> {
     #[field]
     stream: Stream<'a>,
 }
+
+impl<'a> ToUnicodeCmapStream<'a> {
+    pub fn parse(&self, resolver: &mut dyn Resolve<'a>) -> PdfResult<CMap> {
+        let buffer = decode_stream(&self.stream.stream, &self.stream.dict, resolver)?;
+
+        parse_cmap(&buffer)
+    }
+}
+
+/// A range of character codes of a fixed byte length. A code of `n` bytes is
+/// within the range if each of its bytes is within the bounds of the
+/// corresponding bytes of `low` and `high`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodespaceRange {
+    pub low: Vec<u8>,
+    pub high: Vec<u8>,
+}
+
+impl CodespaceRange {
+    pub fn new(low: Vec<u8>, high: Vec<u8>) -> Self {
+        debug_assert_eq!(low.len(), high.len());
+
+        Self { low, high }
+    }
+
+    pub fn len(&self) -> usize {
+        self.low.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.low.is_empty()
+    }
+
+    pub fn contains(&self, code: &[u8]) -> bool {
+        code.len() == self.len()
+            && code
+                .iter()
+                .zip(self.low.iter().zip(&self.high))
+                .all(|(b, (low, high))| (low..=high).contains(&b))
+    }
+}
+
+/// The destination of a `bfrange` mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BfRangeDestination {
+    /// The first code in the range maps to this UTF-16BE string, and each
+    /// subsequent code maps to the same string with its final code unit
+    /// incremented by one
+    Incrementing(Vec<u16>),
+
+    /// Each code in the range maps to the string at the corresponding index
+    Array(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BfRange {
+    low: u32,
+    high: u32,
+    destination: BfRangeDestination,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CidRange {
+    low: u32,
+    high: u32,
+    cid: u32,
+}
+
+/// A parsed CMap, mapping character codes to either Unicode values (as in a
+/// ToUnicode CMap) or CIDs (as in the Encoding of a Type 0 font)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CMap {
+    pub(crate) name: Option<String>,
+
+    /// The name of another CMap whose mappings this CMap extends, as specified
+    /// by the `usecmap` operator
+    pub(crate) use_cmap: Option<String>,
+
+    /// 0 for horizontal writing, 1 for vertical
+    pub(crate) writing_mode: i32,
+
+    pub(crate) codespace_ranges: Vec<CodespaceRange>,
+
+    bf_chars: HashMap<u32, String>,
+    bf_ranges: Vec<BfRange>,
+
+    cid_chars: HashMap<u32, u32>,
+    cid_ranges: Vec<CidRange>,
+
+    notdef_chars: HashMap<u32, u32>,
+    notdef_ranges: Vec<CidRange>,
+}
+
+impl CMap {
+    /// A CMap with no mappings, used only to split strings into codes
+    pub(crate) fn with_codespace_ranges(codespace_ranges: Vec<CodespaceRange>) -> Self {
+        Self {
+            codespace_ranges,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn add_bf_char(&mut self, code: u32, unicode: String) {
+        self.bf_chars.insert(code, unicode);
+    }
+
+    pub(crate) fn add_bf_range(&mut self, low: u32, high: u32, destination: Vec<u16>) {
+        self.bf_ranges.push(BfRange {
+            low,
+            high,
+            destination: BfRangeDestination::Incrementing(destination),
+        });
+    }
+
+    pub(crate) fn add_bf_range_array(&mut self, low: u32, high: u32, destination: Vec<String>) {
+        self.bf_ranges.push(BfRange {
+            low,
+            high,
+            destination: BfRangeDestination::Array(destination),
+        });
+    }
+
+    pub(crate) fn add_cid_char(&mut self, code: u32, cid: u32) {
+        self.cid_chars.insert(code, cid);
+    }
+
+    pub(crate) fn add_cid_range(&mut self, low: u32, high: u32, cid: u32) {
+        self.cid_ranges.push(CidRange { low, high, cid });
+    }
+
+    pub(crate) fn add_notdef_char(&mut self, code: u32, cid: u32) {
+        self.notdef_chars.insert(code, cid);
+    }
+
+    pub(crate) fn add_notdef_range(&mut self, low: u32, high: u32, cid: u32) {
+        self.notdef_ranges.push(CidRange { low, high, cid });
+    }
+
+    /// Read a single character code from the start of `bytes`, returning the
+    /// code and the number of bytes it occupies
+    ///
+    /// Codes are matched against the codespace ranges from shortest to longest.
+    /// If no range matches, the bytes are consumed as if they matched the
+    /// shortest range whose leading bytes match, or a single byte if there are
+    /// no codespace ranges at all
+    pub fn next_code(&self, bytes: &[u8]) -> (u32, usize) {
+        if self.codespace_ranges.is_empty() {
+            return (
+                bytes.first().copied().unwrap_or(0) as u32,
+                1.min(bytes.len()),
+            );
+        }
+
+        for len in 1..=4.min(bytes.len()) {
+            let code = &bytes[..len];
+
+            if self
+                .codespace_ranges
+                .iter()
+                .any(|range| range.contains(code))
+            {
+                return (code_to_u32(code), len);
+            }
+        }
+
+        let shortest = |ranges: &mut dyn Iterator<Item = &CodespaceRange>| {
+            ranges.map(CodespaceRange::len).min()
+        };
+
+        let len = shortest(&mut self.codespace_ranges.iter().filter(|range| {
+            range.low.first() <= bytes.first() && bytes.first() <= range.high.first()
+        }))
+        .or_else(|| shortest(&mut self.codespace_ranges.iter()))
+        .unwrap_or(1)
+        .min(bytes.len());
+
+        (code_to_u32(&bytes[..len]), len)
+    }
+
+    /// Look up the Unicode value of a character code in a ToUnicode CMap
+    pub fn to_unicode(&self, code: u32) -> Option<String> {
+        if let Some(unicode) = self.bf_chars.get(&code) {
+            return Some(unicode.clone());
+        }
+
+        let range = self
+            .bf_ranges
+            .iter()
+            .find(|range| (range.low..=range.high).contains(&code))?;
+
+        let offset = code - range.low;
+
+        match &range.destination {
+            BfRangeDestination::Incrementing(units) => {
+                let mut units = units.clone();
+
+                if let Some(last) = units.last_mut() {
+                    *last = last.wrapping_add(offset as u16);
+                }
+
+                Some(String::from_utf16_lossy(&units))
+            }
+            BfRangeDestination::Array(strings) => strings.get(offset as usize).cloned(),
+        }
+    }
+
+    /// Look up the CID of a character code in an encoding CMap
+    pub fn cid(&self, code: u32) -> Option<u32> {
+        fn lookup(chars: &HashMap<u32, u32>, ranges: &[CidRange], code: u32) -> Option<u32> {
+            if let Some(&cid) = chars.get(&code) {
+                return Some(cid);
+            }
+
+            ranges
+                .iter()
+                .find(|range| (range.low..=range.high).contains(&code))
+                .map(|range| range.cid + (code - range.low))
+        }
+
+        lookup(&self.cid_chars, &self.cid_ranges, code)
+            .or_else(|| lookup(&self.notdef_chars, &self.notdef_ranges, code))
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.writing_mode == 1
+    }
+}
+
+/// Interpret a code of up to 4 bytes as a big-endian integer
+pub(crate) fn code_to_u32(code: &[u8]) -> u32 {
+    code.iter().fold(0, |acc, &b| acc << 8 | b as u32)
+}
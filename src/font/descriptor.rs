@@ -58,13 +58,13 @@ pub struct FontDescriptor<'a> {
     /// The maximum height above the baseline reached by glyphs in this font. The height of
     /// glyphs for accented characters shall be excluded
     #[field("Ascent")]
-    ascent: Option<f32>,
+    pub ascent: Option<f32>,
 
     /// The maximum depth below the baseline reached by glyphs in this font
     ///
     /// The value shall be a negative number
     #[field("Descent")]
-    descent: Option<f32>,
+    pub descent: Option<f32>,
 
     /// The spacing between baselines of consecutive lines of text.
     ///
@@ -177,6 +177,14 @@ pub struct FontDescriptor<'a> {
     cid_set: Option<TypedReference<'a, Stream<'a>>>,
 }
 
+impl FontDescriptor<'_> {
+    /// Whether the font contains glyphs outside the Adobe standard Latin
+    /// character set
+    pub fn is_symbolic(&self) -> bool {
+        self.flags.is_symbolic()
+    }
+}
+
 #[derive(Debug)]
 struct CidFontDescriptor<'a> {
     base: FontDescriptor<'a>,
@@ -1,7 +1,7 @@
 use crate::{
     catalog::MetadataStream,
     error::PdfResult,
-    objects::{Dictionary, Object},
+    objects::{Dictionary, Object, Reference},
     stream::Stream,
     FromObj, Resolve,
 };
@@ -50,14 +50,22 @@ impl<'a> EmbeddedFontDictionary<'a> {
 pub struct Type1FontFile<'a> {
     dict: EmbeddedFontDictionary<'a>,
     pub stream: Stream<'a>,
+
+    /// The font file stream, by which the loaded font program is cached
+    pub(crate) reference: Option<Reference>,
 }
 
 impl<'a> FromObj<'a> for Type1FontFile<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut stream = resolver.assert_stream(obj)?;
         let dict = EmbeddedFontDictionary::from_dict(&mut stream.dict.other, resolver)?;
 
-        Ok(Self { dict, stream })
+        Ok(Self {
+            dict,
+            stream,
+            reference,
+        })
     }
 }
 
@@ -67,14 +75,22 @@ impl<'a> FromObj<'a> for Type1FontFile<'a> {
 pub struct TrueTypeFontFile<'a> {
     pub(crate) dict: EmbeddedFontDictionary<'a>,
     pub(crate) stream: Stream<'a>,
+
+    /// The font file stream, by which the loaded font program is cached
+    pub(crate) reference: Option<Reference>,
 }
 
 impl<'a> FromObj<'a> for TrueTypeFontFile<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut stream = resolver.assert_stream(obj)?;
         let dict = EmbeddedFontDictionary::from_dict(&mut stream.dict.other, resolver)?;
 
-        Ok(Self { dict, stream })
+        Ok(Self {
+            dict,
+            stream,
+            reference,
+        })
     }
 }
 
@@ -92,6 +108,9 @@ pub enum Type3FontFile<'a> {
 pub struct CompactType1FontFile<'a> {
     pub dict: EmbeddedFontDictionary<'a>,
     pub stream: Stream<'a>,
+
+    /// The font file stream, by which the loaded font program is cached
+    pub(crate) reference: Option<Reference>,
 }
 
 /// Type 0 CIDFont program represented in the Compact Font Format (CFF), as described in Adobe
@@ -101,6 +120,9 @@ pub struct CompactType1FontFile<'a> {
 pub struct CompactType0CidFontFile<'a> {
     dict: EmbeddedFontDictionary<'a>,
     stream: Stream<'a>,
+
+    /// The font file stream, by which the loaded font program is cached
+    pub(crate) reference: Option<Reference>,
 }
 
 /// OpenType® font program, as described in the OpenType Specification v.1.4. OpenType is an
@@ -128,6 +150,9 @@ pub struct CompactType0CidFontFile<'a> {
 pub struct OpenTypeFontFile<'a> {
     dict: EmbeddedFontDictionary<'a>,
     stream: Stream<'a>,
+
+    /// The font file stream, by which the loaded font program is cached
+    pub(crate) reference: Option<Reference>,
 }
 
 #[pdf_enum]
@@ -139,43 +164,68 @@ enum Type3Subtype {
 
 impl<'a> FromObj<'a> for Type3FontFile<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut stream = resolver.assert_stream(obj)?;
         let subtype = Type3Subtype::from_str(&stream.dict.other.expect_name("Subtype", resolver)?)?;
 
         Ok(match subtype {
-            Type3Subtype::Type1C => {
-                Self::CompactType1(CompactType1FontFile::from_stream(stream, resolver)?)
-            }
-            Type3Subtype::CIDFontType0C => {
-                Self::CompactType0Cid(CompactType0CidFontFile::from_stream(stream, resolver)?)
-            }
+            Type3Subtype::Type1C => Self::CompactType1(CompactType1FontFile::from_stream(
+                stream, reference, resolver,
+            )?),
+            Type3Subtype::CIDFontType0C => Self::CompactType0Cid(
+                CompactType0CidFontFile::from_stream(stream, reference, resolver)?,
+            ),
             Type3Subtype::OpenType => {
-                Self::OpenType(OpenTypeFontFile::from_stream(stream, resolver)?)
+                Self::OpenType(OpenTypeFontFile::from_stream(stream, reference, resolver)?)
             }
         })
     }
 }
 
 impl<'a> CompactType1FontFile<'a> {
-    pub fn from_stream(mut stream: Stream<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+    pub fn from_stream(
+        mut stream: Stream<'a>,
+        reference: Option<Reference>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
         let dict = EmbeddedFontDictionary::from_dict(&mut stream.dict.other, resolver)?;
 
-        Ok(Self { dict, stream })
+        Ok(Self {
+            dict,
+            stream,
+            reference,
+        })
     }
 }
 
 impl<'a> CompactType0CidFontFile<'a> {
-    pub fn from_stream(mut stream: Stream<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+    pub fn from_stream(
+        mut stream: Stream<'a>,
+        reference: Option<Reference>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
         let dict = EmbeddedFontDictionary::from_dict(&mut stream.dict.other, resolver)?;
 
-        Ok(Self { dict, stream })
+        Ok(Self {
+            dict,
+            stream,
+            reference,
+        })
     }
 }
 
 impl<'a> OpenTypeFontFile<'a> {
-    pub fn from_stream(mut stream: Stream<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+    pub fn from_stream(
+        mut stream: Stream<'a>,
+        reference: Option<Reference>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
         let dict = EmbeddedFontDictionary::from_dict(&mut stream.dict.other, resolver)?;
 
-        Ok(Self { dict, stream })
+        Ok(Self {
+            dict,
+            stream,
+            reference,
+        })
     }
 }
@@ -1,8 +1,11 @@
 use std::{collections::HashMap, mem};
 
+use once_cell::sync::Lazy;
+
 use crate::{
     error::{ParseError, PdfResult},
     objects::{Object, ObjectType},
+    postscript::STANDARD_ENCODING,
     FromObj, Resolve,
 };
 
@@ -28,6 +31,35 @@ impl<'a> FromObj<'a> for FontEncoding {
     }
 }
 
+impl FontEncoding {
+    /// The name of the glyph that `code` maps to under this encoding
+    ///
+    /// If there is no base encoding, codes that are not described by the
+    /// differences array fall back to the standard encoding for nonsymbolic
+    /// fonts. For symbolic fonts the implicit base encoding is the font's
+    /// built-in encoding, which is not known here
+    pub fn glyph_name(&self, code: u8, is_symbolic: bool) -> Option<&str> {
+        match self {
+            Self::Base(base) => base.glyph_name(code),
+            Self::Dictionary(dict) => {
+                if let Some(name) = dict
+                    .differences
+                    .as_ref()
+                    .and_then(|differences| differences.0.get(&(code as u32)))
+                {
+                    return Some(name);
+                }
+
+                match &dict.base_encoding {
+                    Some(base) => base.glyph_name(code),
+                    None if is_symbolic => None,
+                    None => standard_encoding_glyph_name(code),
+                }
+            }
+        }
+    }
+}
+
 #[pdf_enum]
 pub enum BaseFontEncoding {
     /// Mac OS standard encoding for Latin text in Western writing systems.
@@ -119,3 +151,130 @@ impl<'a> FromObj<'a> for FontDifferences {
         Ok(Self(map))
     }
 }
+
+impl BaseFontEncoding {
+    /// The name of the glyph that `code` maps to in this encoding
+    pub fn glyph_name(&self, code: u8) -> Option<&'static str> {
+        match self {
+            Self::WinAnsiEncoding => WIN_ANSI_ENCODING[code as usize],
+            Self::MacRomanEncoding => MAC_ROMAN_ENCODING[code as usize],
+            // todo: MacExpertEncoding consists almost entirely of glyphs, like
+            // small caps and oldstyle figures, that are variants of characters
+            // in the standard encoding
+            Self::MacExpertEncoding => standard_encoding_glyph_name(code),
+        }
+    }
+}
+
+/// The name of the glyph that `code` maps to in the Adobe standard encoding
+pub(crate) fn standard_encoding_glyph_name(code: u8) -> Option<&'static str> {
+    STANDARD_ENCODING[code as usize]
+}
+
+/// Glyph names for the printable ASCII codes, 0x20 through 0x7e, as they appear
+/// in both WinAnsiEncoding and MacRomanEncoding
+#[rustfmt::skip]
+static PRINTABLE_ASCII: [&str; 95] = [
+    "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quotesingle",
+    "parenleft", "parenright", "asterisk", "plus", "comma", "hyphen", "period", "slash",
+    "zero", "one", "two", "three", "four", "five", "six", "seven",
+    "eight", "nine", "colon", "semicolon", "less", "equal", "greater", "question",
+    "at", "A", "B", "C", "D", "E", "F", "G",
+    "H", "I", "J", "K", "L", "M", "N", "O",
+    "P", "Q", "R", "S", "T", "U", "V", "W",
+    "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum", "underscore",
+    "grave", "a", "b", "c", "d", "e", "f", "g",
+    "h", "i", "j", "k", "l", "m", "n", "o",
+    "p", "q", "r", "s", "t", "u", "v", "w",
+    "x", "y", "z", "braceleft", "bar", "braceright", "asciitilde",
+];
+
+#[rustfmt::skip]
+static WIN_ANSI_HIGH: [Option<&str>; 128] = [
+    /*\x80*/ Some("Euro"), None, Some("quotesinglbase"), Some("florin"),
+             Some("quotedblbase"), Some("ellipsis"), Some("dagger"), Some("daggerdbl"),
+    /*\x88*/ Some("circumflex"), Some("perthousand"), Some("Scaron"), Some("guilsinglleft"),
+             Some("OE"), None, Some("Zcaron"), None,
+    /*\x90*/ None, Some("quoteleft"), Some("quoteright"), Some("quotedblleft"),
+             Some("quotedblright"), Some("bullet"), Some("endash"), Some("emdash"),
+    /*\x98*/ Some("tilde"), Some("trademark"), Some("scaron"), Some("guilsinglright"),
+             Some("oe"), None, Some("zcaron"), Some("Ydieresis"),
+    /*\xa0*/ Some("space"), Some("exclamdown"), Some("cent"), Some("sterling"),
+             Some("currency"), Some("yen"), Some("brokenbar"), Some("section"),
+    /*\xa8*/ Some("dieresis"), Some("copyright"), Some("ordfeminine"), Some("guillemotleft"),
+             Some("logicalnot"), Some("hyphen"), Some("registered"), Some("macron"),
+    /*\xb0*/ Some("degree"), Some("plusminus"), Some("twosuperior"), Some("threesuperior"),
+             Some("acute"), Some("mu"), Some("paragraph"), Some("periodcentered"),
+    /*\xb8*/ Some("cedilla"), Some("onesuperior"), Some("ordmasculine"), Some("guillemotright"),
+             Some("onequarter"), Some("onehalf"), Some("threequarters"), Some("questiondown"),
+    /*\xc0*/ Some("Agrave"), Some("Aacute"), Some("Acircumflex"), Some("Atilde"),
+             Some("Adieresis"), Some("Aring"), Some("AE"), Some("Ccedilla"),
+    /*\xc8*/ Some("Egrave"), Some("Eacute"), Some("Ecircumflex"), Some("Edieresis"),
+             Some("Igrave"), Some("Iacute"), Some("Icircumflex"), Some("Idieresis"),
+    /*\xd0*/ Some("Eth"), Some("Ntilde"), Some("Ograve"), Some("Oacute"),
+             Some("Ocircumflex"), Some("Otilde"), Some("Odieresis"), Some("multiply"),
+    /*\xd8*/ Some("Oslash"), Some("Ugrave"), Some("Uacute"), Some("Ucircumflex"),
+             Some("Udieresis"), Some("Yacute"), Some("Thorn"), Some("germandbls"),
+    /*\xe0*/ Some("agrave"), Some("aacute"), Some("acircumflex"), Some("atilde"),
+             Some("adieresis"), Some("aring"), Some("ae"), Some("ccedilla"),
+    /*\xe8*/ Some("egrave"), Some("eacute"), Some("ecircumflex"), Some("edieresis"),
+             Some("igrave"), Some("iacute"), Some("icircumflex"), Some("idieresis"),
+    /*\xf0*/ Some("eth"), Some("ntilde"), Some("ograve"), Some("oacute"),
+             Some("ocircumflex"), Some("otilde"), Some("odieresis"), Some("divide"),
+    /*\xf8*/ Some("oslash"), Some("ugrave"), Some("uacute"), Some("ucircumflex"),
+             Some("udieresis"), Some("yacute"), Some("thorn"), Some("ydieresis"),
+];
+
+#[rustfmt::skip]
+static MAC_ROMAN_HIGH: [Option<&str>; 128] = [
+    /*\x80*/ Some("Adieresis"), Some("Aring"), Some("Ccedilla"), Some("Eacute"),
+             Some("Ntilde"), Some("Odieresis"), Some("Udieresis"), Some("aacute"),
+    /*\x88*/ Some("agrave"), Some("acircumflex"), Some("adieresis"), Some("atilde"),
+             Some("aring"), Some("ccedilla"), Some("eacute"), Some("egrave"),
+    /*\x90*/ Some("ecircumflex"), Some("edieresis"), Some("iacute"), Some("igrave"),
+             Some("icircumflex"), Some("idieresis"), Some("ntilde"), Some("oacute"),
+    /*\x98*/ Some("ograve"), Some("ocircumflex"), Some("odieresis"), Some("otilde"),
+             Some("uacute"), Some("ugrave"), Some("ucircumflex"), Some("udieresis"),
+    /*\xa0*/ Some("dagger"), Some("degree"), Some("cent"), Some("sterling"),
+             Some("section"), Some("bullet"), Some("paragraph"), Some("germandbls"),
+    /*\xa8*/ Some("registered"), Some("copyright"), Some("trademark"), Some("acute"),
+             Some("dieresis"), Some("notequal"), Some("AE"), Some("Oslash"),
+    /*\xb0*/ Some("infinity"), Some("plusminus"), Some("lessequal"), Some("greaterequal"),
+             Some("yen"), Some("mu"), Some("partialdiff"), Some("summation"),
+    /*\xb8*/ Some("product"), Some("pi"), Some("integral"), Some("ordfeminine"),
+             Some("ordmasculine"), Some("Omega"), Some("ae"), Some("oslash"),
+    /*\xc0*/ Some("questiondown"), Some("exclamdown"), Some("logicalnot"), Some("radical"),
+             Some("florin"), Some("approxequal"), Some("Delta"), Some("guillemotleft"),
+    /*\xc8*/ Some("guillemotright"), Some("ellipsis"), Some("space"), Some("Agrave"),
+             Some("Atilde"), Some("Otilde"), Some("OE"), Some("oe"),
+    /*\xd0*/ Some("endash"), Some("emdash"), Some("quotedblleft"), Some("quotedblright"),
+             Some("quoteleft"), Some("quoteright"), Some("divide"), Some("lozenge"),
+    /*\xd8*/ Some("ydieresis"), Some("Ydieresis"), Some("fraction"), Some("currency"),
+             Some("guilsinglleft"), Some("guilsinglright"), Some("fi"), Some("fl"),
+    /*\xe0*/ Some("daggerdbl"), Some("periodcentered"), Some("quotesinglbase"), Some("quotedblbase"),
+             Some("perthousand"), Some("Acircumflex"), Some("Ecircumflex"), Some("Aacute"),
+    /*\xe8*/ Some("Edieresis"), Some("Egrave"), Some("Iacute"), Some("Icircumflex"),
+             Some("Idieresis"), Some("Igrave"), Some("Oacute"), Some("Ocircumflex"),
+    /*\xf0*/ Some("apple"), Some("Ograve"), Some("Uacute"), Some("Ucircumflex"),
+             Some("Ugrave"), Some("dotlessi"), Some("circumflex"), Some("tilde"),
+    /*\xf8*/ Some("macron"), Some("breve"), Some("dotaccent"), Some("ring"),
+             Some("cedilla"), Some("hungarumlaut"), Some("ogonek"), Some("caron"),
+];
+
+static WIN_ANSI_ENCODING: Lazy<[Option<&str>; 256]> =
+    Lazy::new(|| encoding_from_high_half(&WIN_ANSI_HIGH));
+
+static MAC_ROMAN_ENCODING: Lazy<[Option<&str>; 256]> =
+    Lazy::new(|| encoding_from_high_half(&MAC_ROMAN_HIGH));
+
+fn encoding_from_high_half(high: &[Option<&'static str>; 128]) -> [Option<&'static str>; 256] {
+    let mut encoding = [None; 256];
+
+    for (code, name) in PRINTABLE_ASCII.iter().enumerate() {
+        encoding[code + 0x20] = Some(*name);
+    }
+
+    encoding[0x80..].copy_from_slice(high);
+
+    encoding
+}
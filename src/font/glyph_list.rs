@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+/// Map a glyph name to the Unicode value it represents
+///
+/// This follows the algorithm of the Adobe Glyph List Specification: any
+/// suffix after the first period is dropped, the remaining name is split into
+/// components on underscores, and each component is looked up in the glyph
+/// list or interpreted as a `uniXXXX`, `uXXXX[XX]` or single letter name.
+///
+/// Only the portion of the Adobe Glyph List covering the Latin, Greek and
+/// symbol glyphs found in the standard encodings and fonts is included
+pub(crate) fn glyph_name_to_unicode(name: &str) -> Option<String> {
+    let name = name.split('.').next().unwrap_or(name);
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut unicode = String::new();
+
+    for component in name.split('_') {
+        unicode.push_str(&component_to_unicode(component)?);
+    }
+
+    Some(unicode)
+}
+
+fn component_to_unicode(component: &str) -> Option<String> {
+    if let Some(&c) = GLYPH_LIST.get(component) {
+        return Some(c.to_string());
+    }
+
+    if let Some(hex) = component.strip_prefix("uni") {
+        if hex.len() % 4 == 0 && !hex.is_empty() {
+            return hex
+                .as_bytes()
+                .chunks(4)
+                .map(|chunk| {
+                    let chunk = std::str::from_utf8(chunk).ok()?;
+                    char::from_u32(u32::from_str_radix(chunk, 16).ok()?)
+                })
+                .collect();
+        }
+    }
+
+    if let Some(hex) = component.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                return Some(c.to_string());
+            }
+        }
+    }
+
+    match component.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => Some((*c as char).to_string()),
+        _ => None,
+    }
+}
+
+static GLYPH_LIST: Lazy<HashMap<&'static str, char>> = Lazy::new(|| {
+    HashMap::from_iter([
+        // ascii
+        ("space", ' '),
+        ("exclam", '!'),
+        ("quotedbl", '"'),
+        ("numbersign", '#'),
+        ("dollar", '$'),
+        ("percent", '%'),
+        ("ampersand", '&'),
+        ("quotesingle", '\''),
+        ("parenleft", '('),
+        ("parenright", ')'),
+        ("asterisk", '*'),
+        ("plus", '+'),
+        ("comma", ','),
+        ("hyphen", '-'),
+        ("period", '.'),
+        ("slash", '/'),
+        ("zero", '0'),
+        ("one", '1'),
+        ("two", '2'),
+        ("three", '3'),
+        ("four", '4'),
+        ("five", '5'),
+        ("six", '6'),
+        ("seven", '7'),
+        ("eight", '8'),
+        ("nine", '9'),
+        ("colon", ':'),
+        ("semicolon", ';'),
+        ("less", '<'),
+        ("equal", '='),
+        ("greater", '>'),
+        ("question", '?'),
+        ("at", '@'),
+        ("bracketleft", '['),
+        ("backslash", '\\'),
+        ("bracketright", ']'),
+        ("asciicircum", '^'),
+        ("underscore", '_'),
+        ("grave", '`'),
+        ("braceleft", '{'),
+        ("bar", '|'),
+        ("braceright", '}'),
+        ("asciitilde", '~'),
+        // latin-1 supplement
+        ("nbspace", '\u{a0}'),
+        ("nonbreakingspace", '\u{a0}'),
+        ("exclamdown", '¡'),
+        ("cent", '¢'),
+        ("sterling", '£'),
+        ("currency", '¤'),
+        ("yen", '¥'),
+        ("brokenbar", '¦'),
+        ("section", '§'),
+        ("dieresis", '¨'),
+        ("copyright", '©'),
+        ("ordfeminine", 'ª'),
+        ("guillemotleft", '«'),
+        ("logicalnot", '¬'),
+        ("sfthyphen", '\u{ad}'),
+        ("registered", '®'),
+        ("macron", '¯'),
+        ("degree", '°'),
+        ("plusminus", '±'),
+        ("twosuperior", '²'),
+        ("threesuperior", '³'),
+        ("acute", '´'),
+        ("mu", 'µ'),
+        ("paragraph", '¶'),
+        ("periodcentered", '·'),
+        ("cedilla", '¸'),
+        ("onesuperior", '¹'),
+        ("ordmasculine", 'º'),
+        ("guillemotright", '»'),
+        ("onequarter", '¼'),
+        ("onehalf", '½'),
+        ("threequarters", '¾'),
+        ("questiondown", '¿'),
+        ("Agrave", 'À'),
+        ("Aacute", 'Á'),
+        ("Acircumflex", 'Â'),
+        ("Atilde", 'Ã'),
+        ("Adieresis", 'Ä'),
+        ("Aring", 'Å'),
+        ("AE", 'Æ'),
+        ("Ccedilla", 'Ç'),
+        ("Egrave", 'È'),
+        ("Eacute", 'É'),
+        ("Ecircumflex", 'Ê'),
+        ("Edieresis", 'Ë'),
+        ("Igrave", 'Ì'),
+        ("Iacute", 'Í'),
+        ("Icircumflex", 'Î'),
+        ("Idieresis", 'Ï'),
+        ("Eth", 'Ð'),
+        ("Ntilde", 'Ñ'),
+        ("Ograve", 'Ò'),
+        ("Oacute", 'Ó'),
+        ("Ocircumflex", 'Ô'),
+        ("Otilde", 'Õ'),
+        ("Odieresis", 'Ö'),
+        ("multiply", '×'),
+        ("Oslash", 'Ø'),
+        ("Ugrave", 'Ù'),
+        ("Uacute", 'Ú'),
+        ("Ucircumflex", 'Û'),
+        ("Udieresis", 'Ü'),
+        ("Yacute", 'Ý'),
+        ("Thorn", 'Þ'),
+        ("germandbls", 'ß'),
+        ("agrave", 'à'),
+        ("aacute", 'á'),
+        ("acircumflex", 'â'),
+        ("atilde", 'ã'),
+        ("adieresis", 'ä'),
+        ("aring", 'å'),
+        ("ae", 'æ'),
+        ("ccedilla", 'ç'),
+        ("egrave", 'è'),
+        ("eacute", 'é'),
+        ("ecircumflex", 'ê'),
+        ("edieresis", 'ë'),
+        ("igrave", 'ì'),
+        ("iacute", 'í'),
+        ("icircumflex", 'î'),
+        ("idieresis", 'ï'),
+        ("eth", 'ð'),
+        ("ntilde", 'ñ'),
+        ("ograve", 'ò'),
+        ("oacute", 'ó'),
+        ("ocircumflex", 'ô'),
+        ("otilde", 'õ'),
+        ("odieresis", 'ö'),
+        ("divide", '÷'),
+        ("oslash", 'ø'),
+        ("ugrave", 'ù'),
+        ("uacute", 'ú'),
+        ("ucircumflex", 'û'),
+        ("udieresis", 'ü'),
+        ("yacute", 'ý'),
+        ("thorn", 'þ'),
+        ("ydieresis", 'ÿ'),
+        // latin extended-a
+        ("Amacron", 'Ā'),
+        ("amacron", 'ā'),
+        ("Abreve", 'Ă'),
+        ("abreve", 'ă'),
+        ("Aogonek", 'Ą'),
+        ("aogonek", 'ą'),
+        ("Cacute", 'Ć'),
+        ("cacute", 'ć'),
+        ("Ccircumflex", 'Ĉ'),
+        ("ccircumflex", 'ĉ'),
+        ("Cdotaccent", 'Ċ'),
+        ("cdotaccent", 'ċ'),
+        ("Ccaron", 'Č'),
+        ("ccaron", 'č'),
+        ("Dcaron", 'Ď'),
+        ("dcaron", 'ď'),
+        ("Dcroat", 'Đ'),
+        ("dcroat", 'đ'),
+        ("Emacron", 'Ē'),
+        ("emacron", 'ē'),
+        ("Ebreve", 'Ĕ'),
+        ("ebreve", 'ĕ'),
+        ("Edotaccent", 'Ė'),
+        ("edotaccent", 'ė'),
+        ("Eogonek", 'Ę'),
+        ("eogonek", 'ę'),
+        ("Ecaron", 'Ě'),
+        ("ecaron", 'ě'),
+        ("Gcircumflex", 'Ĝ'),
+        ("gcircumflex", 'ĝ'),
+        ("Gbreve", 'Ğ'),
+        ("gbreve", 'ğ'),
+        ("Gdotaccent", 'Ġ'),
+        ("gdotaccent", 'ġ'),
+        ("Gcommaaccent", 'Ģ'),
+        ("gcommaaccent", 'ģ'),
+        ("Hcircumflex", 'Ĥ'),
+        ("hcircumflex", 'ĥ'),
+        ("Hbar", 'Ħ'),
+        ("hbar", 'ħ'),
+        ("Itilde", 'Ĩ'),
+        ("itilde", 'ĩ'),
+        ("Imacron", 'Ī'),
+        ("imacron", 'ī'),
+        ("Ibreve", 'Ĭ'),
+        ("ibreve", 'ĭ'),
+        ("Iogonek", 'Į'),
+        ("iogonek", 'į'),
+        ("Idotaccent", 'İ'),
+        ("dotlessi", 'ı'),
+        ("IJ", 'Ĳ'),
+        ("ij", 'ĳ'),
+        ("Jcircumflex", 'Ĵ'),
+        ("jcircumflex", 'ĵ'),
+        ("Kcommaaccent", 'Ķ'),
+        ("kcommaaccent", 'ķ'),
+        ("kgreenlandic", 'ĸ'),
+        ("Lacute", 'Ĺ'),
+        ("lacute", 'ĺ'),
+        ("Lcommaaccent", 'Ļ'),
+        ("lcommaaccent", 'ļ'),
+        ("Lcaron", 'Ľ'),
+        ("lcaron", 'ľ'),
+        ("Ldot", 'Ŀ'),
+        ("ldot", 'ŀ'),
+        ("Lslash", 'Ł'),
+        ("lslash", 'ł'),
+        ("Nacute", 'Ń'),
+        ("nacute", 'ń'),
+        ("Ncommaaccent", 'Ņ'),
+        ("ncommaaccent", 'ņ'),
+        ("Ncaron", 'Ň'),
+        ("ncaron", 'ň'),
+        ("napostrophe", 'ŉ'),
+        ("Eng", 'Ŋ'),
+        ("eng", 'ŋ'),
+        ("Omacron", 'Ō'),
+        ("omacron", 'ō'),
+        ("Obreve", 'Ŏ'),
+        ("obreve", 'ŏ'),
+        ("Ohungarumlaut", 'Ő'),
+        ("ohungarumlaut", 'ő'),
+        ("OE", 'Œ'),
+        ("oe", 'œ'),
+        ("Racute", 'Ŕ'),
+        ("racute", 'ŕ'),
+        ("Rcommaaccent", 'Ŗ'),
+        ("rcommaaccent", 'ŗ'),
+        ("Rcaron", 'Ř'),
+        ("rcaron", 'ř'),
+        ("Sacute", 'Ś'),
+        ("sacute", 'ś'),
+        ("Scircumflex", 'Ŝ'),
+        ("scircumflex", 'ŝ'),
+        ("Scedilla", 'Ş'),
+        ("scedilla", 'ş'),
+        ("Scaron", 'Š'),
+        ("scaron", 'š'),
+        ("Tcommaaccent", 'Ţ'),
+        ("tcommaaccent", 'ţ'),
+        ("Tcaron", 'Ť'),
+        ("tcaron", 'ť'),
+        ("Tbar", 'Ŧ'),
+        ("tbar", 'ŧ'),
+        ("Utilde", 'Ũ'),
+        ("utilde", 'ũ'),
+        ("Umacron", 'Ū'),
+        ("umacron", 'ū'),
+        ("Ubreve", 'Ŭ'),
+        ("ubreve", 'ŭ'),
+        ("Uring", 'Ů'),
+        ("uring", 'ů'),
+        ("Uhungarumlaut", 'Ű'),
+        ("uhungarumlaut", 'ű'),
+        ("Uogonek", 'Ų'),
+        ("uogonek", 'ų'),
+        ("Wcircumflex", 'Ŵ'),
+        ("wcircumflex", 'ŵ'),
+        ("Ycircumflex", 'Ŷ'),
+        ("ycircumflex", 'ŷ'),
+        ("Ydieresis", 'Ÿ'),
+        ("Zacute", 'Ź'),
+        ("zacute", 'ź'),
+        ("Zdotaccent", 'Ż'),
+        ("zdotaccent", 'ż'),
+        ("Zcaron", 'Ž'),
+        ("zcaron", 'ž'),
+        ("longs", 'ſ'),
+        ("florin", 'ƒ'),
+        ("dotlessj", 'ȷ'),
+        // spacing modifiers
+        ("circumflex", 'ˆ'),
+        ("caron", 'ˇ'),
+        ("breve", '˘'),
+        ("dotaccent", '˙'),
+        ("ring", '˚'),
+        ("ogonek", '˛'),
+        ("tilde", '˜'),
+        ("hungarumlaut", '˝'),
+        // greek
+        ("Alpha", 'Α'),
+        ("Beta", 'Β'),
+        ("Gamma", 'Γ'),
+        ("Delta", 'Δ'),
+        ("Epsilon", 'Ε'),
+        ("Zeta", 'Ζ'),
+        ("Eta", 'Η'),
+        ("Theta", 'Θ'),
+        ("Iota", 'Ι'),
+        ("Kappa", 'Κ'),
+        ("Lambda", 'Λ'),
+        ("Mu", 'Μ'),
+        ("Nu", 'Ν'),
+        ("Xi", 'Ξ'),
+        ("Omicron", 'Ο'),
+        ("Pi", 'Π'),
+        ("Rho", 'Ρ'),
+        ("Sigma", 'Σ'),
+        ("Tau", 'Τ'),
+        ("Upsilon", 'Υ'),
+        ("Phi", 'Φ'),
+        ("Chi", 'Χ'),
+        ("Psi", 'Ψ'),
+        ("Omega", 'Ω'),
+        ("alpha", 'α'),
+        ("beta", 'β'),
+        ("gamma", 'γ'),
+        ("delta", 'δ'),
+        ("epsilon", 'ε'),
+        ("zeta", 'ζ'),
+        ("eta", 'η'),
+        ("theta", 'θ'),
+        ("iota", 'ι'),
+        ("kappa", 'κ'),
+        ("lambda", 'λ'),
+        ("nu", 'ν'),
+        ("xi", 'ξ'),
+        ("omicron", 'ο'),
+        ("pi", 'π'),
+        ("rho", 'ρ'),
+        ("sigma1", 'ς'),
+        ("sigma", 'σ'),
+        ("tau", 'τ'),
+        ("upsilon", 'υ'),
+        ("phi", 'φ'),
+        ("chi", 'χ'),
+        ("psi", 'ψ'),
+        ("omega", 'ω'),
+        ("theta1", 'ϑ'),
+        ("Upsilon1", 'ϒ'),
+        ("phi1", 'ϕ'),
+        ("omega1", 'ϖ'),
+        // general punctuation
+        ("figuredash", '‒'),
+        ("endash", '–'),
+        ("emdash", '—'),
+        ("quoteleft", '‘'),
+        ("quoteright", '’'),
+        ("quotesinglbase", '‚'),
+        ("quotereversed", '‛'),
+        ("quotedblleft", '“'),
+        ("quotedblright", '”'),
+        ("quotedblbase", '„'),
+        ("dagger", '†'),
+        ("daggerdbl", '‡'),
+        ("bullet", '•'),
+        ("onedotenleader", '․'),
+        ("twodotenleader", '‥'),
+        ("ellipsis", '…'),
+        ("perthousand", '‰'),
+        ("minute", '′'),
+        ("second", '″'),
+        ("guilsinglleft", '‹'),
+        ("guilsinglright", '›'),
+        ("exclamdbl", '‼'),
+        ("fraction", '⁄'),
+        ("Euro", '€'),
+        // letterlike symbols
+        ("Ifraktur", 'ℑ'),
+        ("weierstrass", '℘'),
+        ("Rfraktur", 'ℜ'),
+        ("trademark", '™'),
+        ("aleph", 'ℵ'),
+        ("onethird", '⅓'),
+        ("twothirds", '⅔'),
+        ("oneeighth", '⅛'),
+        ("threeeighths", '⅜'),
+        ("fiveeighths", '⅝'),
+        ("seveneighths", '⅞'),
+        // arrows
+        ("arrowleft", '←'),
+        ("arrowup", '↑'),
+        ("arrowright", '→'),
+        ("arrowdown", '↓'),
+        ("arrowboth", '↔'),
+        ("arrowupdn", '↕'),
+        ("carriagereturn", '↵'),
+        ("arrowdblleft", '⇐'),
+        ("arrowdblup", '⇑'),
+        ("arrowdblright", '⇒'),
+        ("arrowdbldown", '⇓'),
+        ("arrowdblboth", '⇔'),
+        // mathematical operators
+        ("universal", '∀'),
+        ("partialdiff", '∂'),
+        ("existential", '∃'),
+        ("emptyset", '∅'),
+        ("increment", '∆'),
+        ("gradient", '∇'),
+        ("element", '∈'),
+        ("notelement", '∉'),
+        ("suchthat", '∋'),
+        ("product", '∏'),
+        ("summation", '∑'),
+        ("minus", '−'),
+        ("asteriskmath", '∗'),
+        ("radical", '√'),
+        ("proportional", '∝'),
+        ("infinity", '∞'),
+        ("angle", '∠'),
+        ("logicaland", '∧'),
+        ("logicalor", '∨'),
+        ("intersection", '∩'),
+        ("union", '∪'),
+        ("integral", '∫'),
+        ("therefore", '∴'),
+        ("similar", '∼'),
+        ("congruent", '≅'),
+        ("approxequal", '≈'),
+        ("notequal", '≠'),
+        ("equivalence", '≡'),
+        ("lessequal", '≤'),
+        ("greaterequal", '≥'),
+        ("propersubset", '⊂'),
+        ("propersuperset", '⊃'),
+        ("notsubset", '⊄'),
+        ("reflexsubset", '⊆'),
+        ("reflexsuperset", '⊇'),
+        ("circleplus", '⊕'),
+        ("circlemultiply", '⊗'),
+        ("perpendicular", '⊥'),
+        ("dotmath", '⋅'),
+        ("integraltp", '⌠'),
+        ("integralbt", '⌡'),
+        ("angleleft", '\u{2329}'),
+        ("angleright", '\u{232a}'),
+        // geometric shapes and dingbats
+        ("lozenge", '◊'),
+        ("spade", '♠'),
+        ("club", '♣'),
+        ("heart", '♥'),
+        ("diamond", '♦'),
+        ("apple", '\u{f8ff}'),
+        // alphabetic presentation forms
+        ("ff", 'ﬀ'),
+        ("fi", 'ﬁ'),
+        ("fl", 'ﬂ'),
+        ("ffi", 'ﬃ'),
+        ("ffl", 'ﬄ'),
+    ])
+});
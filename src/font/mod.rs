@@ -16,6 +16,7 @@ use crate::{
 pub use self::{
     cff::{CffCharStringInterpreter, CffFile, CffParser},
     cid::{CidFontSubtype, CidFontWidths, CidToGidMap},
+    cmap::{CMap, CodespaceRange},
     descriptor::FontDescriptor,
     embedded::Type3FontFile,
     encoding::{FontEncoding, FontEncodingDict},
@@ -26,6 +27,11 @@ pub use self::{
     type3::Type3Font,
 };
 
+pub(crate) use self::{
+    cjk::PredefinedCjkCmapName, cmap::code_to_u32, encoding::standard_encoding_glyph_name,
+    glyph_list::glyph_name_to_unicode, type0::Type0FontEncoding,
+};
+
 mod cff;
 mod cid;
 mod cid_font_type0;
@@ -36,6 +42,7 @@ mod descriptor;
 mod embedded;
 mod encoding;
 mod glyph;
+mod glyph_list;
 pub mod true_type;
 mod type0;
 mod type1;
@@ -50,6 +57,8 @@ pub enum Font<'a> {
     Type0(Type0Font<'a>),
 }
 
+/// The font programs used for the standard 14 fonts, and common aliases of
+/// them, when a document doesn't embed its own
 pub(crate) static BASE_14_FONTS: Lazy<BTreeMap<&'static str, Arc<RwLock<Type1PostscriptFont>>>> =
     Lazy::new(|| {
         BTreeMap::from_iter(
@@ -85,19 +94,29 @@ pub(crate) static BASE_14_FONTS: Lazy<BTreeMap<&'static str, Arc<RwLock<Type1Pos
                 ("Arial-ItalicMT", "pdf_fonts/n019023l.pfb"),
                 ("Arial-Italic", "pdf_fonts/n019023l.pfb"),
             ]
-            .map(|(name, path)| {
-                (
-                    name,
-                    Arc::new(RwLock::new(
-                        Type1PostscriptFont::load(&std::fs::read(path).unwrap()).unwrap(),
-                    )),
-                )
+            // fonts whose programs can't be found are left out
+            .into_iter()
+            .filter_map(|(name, path)| {
+                let font = Type1PostscriptFont::load(&std::fs::read(path).ok()?).ok()?;
+
+                Some((name, Arc::new(RwLock::new(font))))
             }),
         )
     });
 
 impl<'a> Font<'a> {
     const TYPE: &'static str = "Font";
+
+    /// The PostScript name of the font. Type 3 fonts do not have one
+    pub fn base_font(&self) -> Option<&str> {
+        match self {
+            Self::Type1(font) => Some(&font.base_font),
+            Self::MmType1(font) => Some(&font.type1.base_font),
+            Self::TrueType(font) => Some(&font.base_font),
+            Self::Type0(font) => Some(&font.base_font),
+            Self::Type3(..) => None,
+        }
+    }
 }
 
 impl<'a> FromObj<'a> for Font<'a> {
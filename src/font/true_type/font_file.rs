@@ -1,8 +1,12 @@
+use crate::font::{
+    encoding::BaseFontEncoding, glyph_name_to_unicode, standard_encoding_glyph_name, FontEncoding,
+};
+
 use super::{
     parse::TrueTypeParser,
     table::{
-        CmapTable, CvtTable, FontDirectory, GlyfTable, Head, LocaTable, MaxpTable, NameTable,
-        SimpleGlyph, TableName, TrueTypeGlyph,
+        CmapTable, CvtTable, FontDirectory, GlyfTable, Head, HheaTable, HmtxTable, LocaTable,
+        MaxpTable, NameTable, Os2Table, PostTable, SimpleGlyph, TableName, TrueTypeGlyph,
     },
     FWord,
 };
@@ -15,6 +19,10 @@ pub struct ParsedTrueTypeFontFile<'a> {
     pub loca: LocaTable,
     pub cvt: Option<CvtTable>,
     pub cmap: Option<CmapTable>,
+    pub hhea: Option<HheaTable>,
+    pub hmtx: Option<HmtxTable>,
+    pub post: Option<PostTable>,
+    pub os2: Option<Os2Table>,
     parser: TrueTypeParser<'a>,
 }
 
@@ -29,6 +37,13 @@ impl<'a> ParsedTrueTypeFontFile<'a> {
         let loca = Self::get_loca(&mut parser, &font_directory, head.index_to_loc_format)?;
         let cvt = Self::get_cvt(&mut parser, &font_directory)?;
         let cmap = Self::get_cmap(&mut parser, &font_directory)?;
+        let hhea = Self::get_hhea(&mut parser, &font_directory)?;
+        let hmtx = match &hhea {
+            Some(hhea) => Self::get_hmtx(&mut parser, &font_directory, hhea, &maxp)?,
+            None => None,
+        };
+        let post = Self::get_post(&mut parser, &font_directory)?;
+        let os2 = Self::get_os2(&mut parser, &font_directory)?;
 
         Ok(Self {
             font_directory,
@@ -37,6 +52,10 @@ impl<'a> ParsedTrueTypeFontFile<'a> {
             loca,
             cvt,
             cmap,
+            hhea,
+            hmtx,
+            post,
+            os2,
             parser,
         })
     }
@@ -84,6 +103,58 @@ impl<'a> ParsedTrueTypeFontFile<'a> {
         parser.read_cmap_table(offset as usize).map(Some)
     }
 
+    fn get_hhea(
+        parser: &mut TrueTypeParser,
+        font_directory: &FontDirectory,
+    ) -> anyhow::Result<Option<HheaTable>> {
+        let offset = match font_directory.find_table_offset(HheaTable::TAG) {
+            Some(o) => o,
+            None => return Ok(None),
+        };
+
+        parser.read_hhea_table(offset as usize).map(Some)
+    }
+
+    fn get_hmtx(
+        parser: &mut TrueTypeParser,
+        font_directory: &FontDirectory,
+        hhea: &HheaTable,
+        maxp: &MaxpTable,
+    ) -> anyhow::Result<Option<HmtxTable>> {
+        let entry = match font_directory.find_table_entry(HmtxTable::TAG) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        parser
+            .read_hmtx_table(entry, hhea.num_of_long_hor_metrics, maxp.num_glyphs)
+            .map(Some)
+    }
+
+    fn get_post(
+        parser: &mut TrueTypeParser,
+        font_directory: &FontDirectory,
+    ) -> anyhow::Result<Option<PostTable>> {
+        let entry = match font_directory.find_table_entry(PostTable::TAG) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        parser.read_post_table(entry).map(Some)
+    }
+
+    fn get_os2(
+        parser: &mut TrueTypeParser,
+        font_directory: &FontDirectory,
+    ) -> anyhow::Result<Option<Os2Table>> {
+        let entry = match font_directory.find_table_entry(Os2Table::TAG) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        parser.read_os2_table(entry).map(Some)
+    }
+
     fn get_loca(
         parser: &mut TrueTypeParser,
         font_directory: &FontDirectory,
@@ -95,15 +166,101 @@ impl<'a> ParsedTrueTypeFontFile<'a> {
         parser.read_loca_table(entry.offset as usize, entry.length as usize, loca_format)
     }
 
-    pub fn glyph(&mut self, char_code: u32) -> anyhow::Result<TrueTypeGlyph> {
-        let glyph_index = if let Some(cmap) = &self.cmap {
-            // todo: be smarter about subtable selection
-            cmap.subtables[0].lookup_char_code(char_code)
-        } else {
-            char_code
+    /// The glyph index that `char_code`, a single-byte code in a string shown
+    /// with this font, maps to
+    ///
+    /// This follows the rules for TrueType fonts embedded in PDF files. Codes
+    /// of a nonsymbolic font are mapped to glyph names through `encoding`, or
+    /// the standard encoding if the font has none. The name is then looked up
+    /// by its Unicode value in a (3, 1) subtable or, failing that, by its Mac OS
+    /// Roman code in a (1, 0) subtable, and finally by name in the "post" table
+    ///
+    /// Codes of symbolic fonts, and codes whose glyph could not be found by
+    /// name, are looked up directly. A (3, 0) subtable maps codes in the range
+    /// 0xF000 through 0xF0FF (or 0xF100 or 0xF200), so the code is looked up
+    /// both on its own and in each of these ranges. Otherwise, a (1, 0)
+    /// subtable maps codes as-is
+    pub fn glyph_index(
+        &self,
+        char_code: u32,
+        encoding: Option<&FontEncoding>,
+        is_symbolic: bool,
+    ) -> u32 {
+        let cmap = match &self.cmap {
+            Some(cmap) => cmap,
+            None => return char_code,
+        };
+
+        let name = match u8::try_from(char_code) {
+            Ok(code) if !is_symbolic => match encoding {
+                Some(encoding) => encoding.glyph_name(code, is_symbolic),
+                None => standard_encoding_glyph_name(code),
+            },
+            _ => None,
         };
 
-        let glyf_entry = self.loca.get_glyf_entry(glyph_index).unwrap();
+        if let Some(name) = name {
+            let glyph_index = if let Some(unicode) = cmap.find_subtable(3, 1) {
+                glyph_name_to_unicode(name)
+                    .and_then(|unicode| unicode.chars().next())
+                    .map_or(0, |c| unicode.lookup_char_code(c as u32))
+            } else if let Some(mac_roman) = cmap.find_subtable(1, 0) {
+                (0..=u8::MAX)
+                    .find(|&code| BaseFontEncoding::MacRomanEncoding.glyph_name(code) == Some(name))
+                    .map_or(0, |code| mac_roman.lookup_char_code(u32::from(code)))
+            } else {
+                0
+            };
+
+            if glyph_index != 0 {
+                return glyph_index;
+            }
+
+            if let Some(glyph_index) = self.glyph_index_for_name(name) {
+                return glyph_index;
+            }
+        }
+
+        if let Some(symbol) = cmap.find_subtable(3, 0) {
+            return [0, 0xF000, 0xF100, 0xF200]
+                .into_iter()
+                .map(|prefix| symbol.lookup_char_code(prefix | char_code))
+                .find(|&glyph_index| glyph_index != 0)
+                .unwrap_or(0);
+        }
+
+        match cmap.find_subtable(1, 0) {
+            Some(mac_roman) => mac_roman.lookup_char_code(char_code),
+            None => cmap
+                .subtables
+                .first()
+                .map_or(0, |encoding| encoding.subtable.lookup_char_code(char_code)),
+        }
+    }
+
+    /// The index of the glyph named `name` in the "post" table
+    pub fn glyph_index_for_name(&self, name: &str) -> Option<u32> {
+        self.post.as_ref()?.glyph_index(name)
+    }
+
+    pub fn glyph_name(&self, glyph_index: u32) -> Option<&str> {
+        self.post.as_ref()?.glyph_name(glyph_index)
+    }
+
+    /// The advance width of the glyph, in font units
+    pub fn advance_width(&self, glyph_index: u32) -> Option<u16> {
+        self.hmtx.as_ref()?.advance_width(glyph_index)
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.head.units_per_em
+    }
+
+    pub fn glyph(&mut self, glyph_index: u32) -> anyhow::Result<TrueTypeGlyph> {
+        let glyf_entry = self
+            .loca
+            .get_glyf_entry(glyph_index)
+            .ok_or_else(|| anyhow::anyhow!("glyph index out of range: {}", glyph_index))?;
 
         let glyf_table_offset = self
             .font_directory
@@ -117,7 +274,7 @@ impl<'a> ParsedTrueTypeFontFile<'a> {
         }
 
         self.parser.cursor = glyf_offset as usize;
-        let glyf = self.parser.parse_glyph().unwrap();
+        let glyf = self.parser.parse_glyph()?;
 
         // todo: this should be true
         // assert_eq!(
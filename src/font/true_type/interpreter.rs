@@ -1,14 +1,14 @@
 use anyhow::anyhow;
 
 use crate::{
-    font::{true_type::table::TrueTypeGlyph, Glyph},
+    font::{true_type::table::TrueTypeGlyph, FontEncoding, Glyph},
     geometry::{Outline, Path, Point},
 };
 
 use super::{
     graphics_state::{RoundState, TrueTypeGraphicsState, Vector, Zone},
     instruction::TrueTypeInstruction,
-    table::OutlineFlag,
+    table::{CompoundGlyphComponentFlags, CompoundGlyphPartDescription, OutlineFlag},
     F26Dot6, ParsedTrueTypeFontFile,
 };
 
@@ -183,113 +183,192 @@ impl<'a> TrueTypeInterpreter<'a> {
         }
     }
 
-    pub fn render_glyph(&mut self, char_code: u32) -> anyhow::Result<Glyph> {
-        self.reset();
-
-        let ttf_glyph = self.ttf_file.glyph(char_code)?;
+    /// The glyph index that a single-byte code of a simple font maps to. See
+    /// [`ParsedTrueTypeFontFile::glyph_index`]
+    pub fn glyph_index(
+        &self,
+        char_code: u32,
+        encoding: Option<&FontEncoding>,
+        is_symbolic: bool,
+    ) -> u32 {
+        self.ttf_file.glyph_index(char_code, encoding, is_symbolic)
+    }
 
-        let simple = match ttf_glyph {
-            TrueTypeGlyph::Simple(simple) => simple,
-            TrueTypeGlyph::Compound(_) => todo!(),
-        };
+    pub fn render_glyph(&mut self, glyph_index: u32) -> anyhow::Result<Glyph> {
+        self.reset();
 
-        assert_eq!(simple.x_coords.len(), simple.y_coords.len());
-        assert_eq!(simple.x_coords.len(), simple.flags.len());
+        let points = self.glyph_points(glyph_index, 0)?;
 
-        // todo: initialize glyph zone and initial positions
-        self.instruction_stream = InstructionStream::new(simple.instructions);
+        // todo: initialize glyph zone and initial positions, and execute the
+        // glyph's instructions
+        let scale = 1.0 / f32::from(self.ttf_file.units_per_em());
 
         let mut paths = Vec::new();
-
         let mut start = 0;
 
-        for contour_end in simple.end_points_of_contours {
-            let contour_end = contour_end as usize;
+        for &contour_end in &points.end_points_of_contours {
+            let contour = (start..=contour_end)
+                .map(|idx| {
+                    let point = points.points[idx];
+                    (
+                        Point::new(point.x * scale, point.y * scale),
+                        points.on_curve[idx],
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(path) = contour_path(&contour) {
+                paths.push(path);
+            }
 
-            let x_coords = &simple.x_coords[start..=contour_end];
-            let y_coords = &simple.y_coords[start..=contour_end];
-            let flags = &simple.flags[start..=contour_end];
+            start = contour_end + 1;
+        }
 
-            fn get_points(x_coords: &[i16], y_coords: &[i16], flags: &[u8]) -> Vec<(Point, bool)> {
-                let mut points = Vec::new();
-                let mut prev = Point::origin();
-                let mut prev_on_curve = false;
+        let outline = Outline { paths };
 
-                for i in 0..x_coords.len() {
-                    let x = x_coords[i];
-                    let y = y_coords[i];
-                    let flag = flags[i];
-                    let on_curve = flag & OutlineFlag::ON_CURVE != 0;
+        let width_vector = match points.advance_width {
+            Some(advance_width) => Point::new(f32::from(advance_width) * scale, 0.0),
+            None => outline.bounding_box().max - outline.bounding_box().min,
+        };
 
-                    let point = Point::new(x as f32 / 2048.0, y as f32 / 2048.0);
+        Ok(Glyph {
+            width_vector,
+            outline,
+        })
+    }
 
-                    if !on_curve && !prev_on_curve {
-                        points.push((point.midpoint(prev), true));
-                    } else if on_curve && prev_on_curve {
-                    }
+    /// The points of a glyph, in font units. The points of a compound glyph are
+    /// those of its components, transformed and positioned
+    fn glyph_points(&mut self, glyph_index: u32, depth: usize) -> anyhow::Result<GlyphPoints> {
+        if depth > MAX_COMPONENT_DEPTH {
+            anyhow::bail!("compound glyphs nested too deeply");
+        }
 
-                    points.push((point, on_curve));
+        let mut glyph_points = GlyphPoints {
+            points: Vec::new(),
+            on_curve: Vec::new(),
+            end_points_of_contours: Vec::new(),
+            advance_width: self.ttf_file.advance_width(glyph_index),
+        };
 
-                    prev = point;
-                    prev_on_curve = on_curve;
+        match self.ttf_file.glyph(glyph_index)? {
+            TrueTypeGlyph::Simple(simple) => {
+                let number_of_points = simple.flags.len();
+
+                if simple.x_coords.len() != number_of_points
+                    || simple.y_coords.len() != number_of_points
+                {
+                    anyhow::bail!(
+                        "glyph {} has {} flags but {} x and {} y coordinates",
+                        glyph_index,
+                        number_of_points,
+                        simple.x_coords.len(),
+                        simple.y_coords.len()
+                    );
                 }
 
-                if !prev_on_curve {
-                    points.push((
-                        points.last().unwrap().0.midpoint(points.first().unwrap().0),
-                        true,
-                    ));
+                // the number of points is taken from the last contour, so an
+                // earlier contour may end beyond it
+                if let Some(&end_point) = simple
+                    .end_points_of_contours
+                    .iter()
+                    .find(|&&end_point| usize::from(end_point) >= number_of_points)
+                {
+                    anyhow::bail!(
+                        "contour of glyph {} ends at point {}, but it only has {} points",
+                        glyph_index,
+                        end_point,
+                        number_of_points
+                    );
                 }
 
-                points
+                glyph_points.points = simple
+                    .x_coords
+                    .iter()
+                    .zip(&simple.y_coords)
+                    .map(|(&x, &y)| Point::new(f32::from(x), f32::from(y)))
+                    .collect();
+                glyph_points.on_curve = simple
+                    .flags
+                    .iter()
+                    .map(|&flag| flag & OutlineFlag::ON_CURVE != 0)
+                    .collect();
+                glyph_points.end_points_of_contours = simple
+                    .end_points_of_contours
+                    .iter()
+                    .map(|&end_point| usize::from(end_point))
+                    .collect();
             }
-
-            let mut points = get_points(x_coords, y_coords, flags).into_iter();
-
-            let mut path = None;
-
-            let p1 = points.next();
-            let mut last_on_curve = false;
-            while let Some((c1, c1_on_curve)) = points.next() {
-                if c1_on_curve && (last_on_curve || path.is_none()) {
-                    let path = path.get_or_insert_with(|| Path::new(p1.unwrap().0));
-                    path.line_to(c1);
-                    last_on_curve = true;
-                    continue;
+            TrueTypeGlyph::Compound(components) => {
+                for component in components {
+                    self.add_component(&mut glyph_points, component, depth)?;
                 }
-
-                let (p2, p2_on_curve) = match points.next() {
-                    Some(v) => v,
-                    None => todo!("unexpected ttf glyph end"),
-                };
-                let path = path.get_or_insert_with(|| Path::new(p1.unwrap().0));
-
-                assert!(!c1_on_curve);
-                assert!(p2_on_curve);
-
-                path.quadratic_curve_to(c1, p2);
-
-                last_on_curve = p2_on_curve;
             }
+        }
 
-            if let Some(mut path) = path.take() {
-                path.close_path();
-                paths.push(path);
+        Ok(glyph_points)
+    }
+
+    fn add_component(
+        &mut self,
+        glyph_points: &mut GlyphPoints,
+        component: CompoundGlyphPartDescription,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        let flags = component.flags;
+        let transform = component.transformation_option;
+
+        let child = self.glyph_points(u32::from(component.glyph_index), depth + 1)?;
+
+        let points = child
+            .points
+            .iter()
+            .map(|&point| transform.apply(point))
+            .collect::<Vec<_>>();
+
+        let offset = if flags.contains(CompoundGlyphComponentFlags::ARGS_ARE_XY_VALUES) {
+            let offset = Point::new(component.argument_one as f32, component.argument_two as f32);
+
+            // offsets aren't scaled unless the font asks for it
+            if flags.contains(CompoundGlyphComponentFlags::SCALED_COMPONENT_OFFSET)
+                && !flags.contains(CompoundGlyphComponentFlags::UNSCALED_COMPONENT_OFFSET)
+            {
+                transform.apply(offset)
+            } else {
+                offset
             }
+        } else {
+            // the arguments are the indices of a point in the glyph so far and
+            // a point in the component, which are moved to coincide
+            let parent_point = glyph_points
+                .points
+                .get(component.argument_one as usize)
+                .ok_or_else(|| anyhow!("invalid compound glyph point"))?;
+            let child_point = points
+                .get(component.argument_two as usize)
+                .ok_or_else(|| anyhow!("invalid compound glyph point"))?;
+
+            *parent_point - *child_point
+        };
 
-            start = contour_end + 1;
+        if flags.contains(CompoundGlyphComponentFlags::USE_MY_METRICS) {
+            glyph_points.advance_width = child.advance_width;
         }
 
-        // self.execute().unwrap();
-
-        // assert!(self.interpreter_stack.is_empty());
+        let first_point = glyph_points.points.len();
 
-        let outline = Outline { paths };
+        glyph_points
+            .points
+            .extend(points.into_iter().map(|point| point + offset));
+        glyph_points.on_curve.extend(child.on_curve);
+        glyph_points.end_points_of_contours.extend(
+            child
+                .end_points_of_contours
+                .into_iter()
+                .map(|end_point| end_point + first_point),
+        );
 
-        Ok(Glyph {
-            width_vector: outline.bounding_box().max - outline.bounding_box().min,
-            outline,
-        })
+        Ok(())
     }
 
     pub fn reset(&mut self) {
@@ -798,3 +877,357 @@ impl<'a> TrueTypeInterpreter<'a> {
         Ok(())
     }
 }
+
+/// Compound glyphs may be made up of other compound glyphs, so this limits the
+/// recursion in malformed fonts
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+struct GlyphPoints {
+    points: Vec<Point>,
+    on_curve: Vec<bool>,
+
+    /// Indices of the last point of each contour
+    end_points_of_contours: Vec<usize>,
+
+    advance_width: Option<u16>,
+}
+
+/// Build the path of a contour made up of on-curve points, joined by lines,
+/// and off-curve quadratic control points. Two consecutive off-curve points
+/// have an implied on-curve point at their midpoint
+fn contour_path(contour: &[(Point, bool)]) -> Option<Path> {
+    let (first, first_on_curve) = *contour.first()?;
+    let (last, last_on_curve) = *contour.last()?;
+
+    // the path starts at an on-curve point, which may be implied if the first
+    // and last points are both off the curve
+    let (start, rest) = if first_on_curve {
+        (first, &contour[1..])
+    } else if last_on_curve {
+        (last, &contour[..contour.len() - 1])
+    } else {
+        (first.midpoint(last), contour)
+    };
+
+    let mut path = Path::new(start);
+    let mut control = None;
+
+    for &(point, on_curve) in rest.iter().chain(std::iter::once(&(start, true))) {
+        match (control, on_curve) {
+            (None, true) => path.line_to(point),
+            (None, false) => control = Some(point),
+            (Some(c), true) => {
+                path.quadratic_curve_to(c, point);
+                control = None;
+            }
+            (Some(c), false) => {
+                path.quadratic_curve_to(c, c.midpoint(point));
+                control = Some(point);
+            }
+        }
+    }
+
+    path.close_path();
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{
+        font::{
+            encoding::{BaseFontEncoding, FontDifferences},
+            FontEncoding, FontEncodingDict,
+        },
+        geometry::Point,
+    };
+
+    use super::{ParsedTrueTypeFontFile, TrueTypeInterpreter};
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn i16s(values: &[i16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    /// A format 0 cmap subtable, mapping single bytes to glyph indices
+    fn format_0(glyphs: &[(u8, u8)]) -> Vec<u8> {
+        let mut glyph_index_array = vec![0; 256];
+
+        for &(code, glyph_index) in glyphs {
+            glyph_index_array[usize::from(code)] = glyph_index;
+        }
+
+        [u16s(&[0, 262, 0]), glyph_index_array].concat()
+    }
+
+    /// A format 4 cmap subtable, mapping each range of codes `(start, end, glyph)`
+    /// to consecutive glyph indices starting at `glyph`
+    fn format_4(ranges: &[(u16, u16, u16)]) -> Vec<u8> {
+        let seg_count = ranges.len() as u16 + 1;
+
+        let end_codes = ranges.iter().map(|&(_, end, _)| end).chain([0xFFFF]);
+        let start_codes = ranges.iter().map(|&(start, ..)| start).chain([0xFFFF]);
+        let id_deltas = ranges
+            .iter()
+            .map(|&(start, _, glyph)| glyph.wrapping_sub(start))
+            .chain([1]);
+
+        [
+            u16s(&[4, 16 + 8 * seg_count, 0, seg_count * 2, 0, 0, 0]),
+            u16s(&end_codes.collect::<Vec<u16>>()),
+            u16s(&[0]),
+            u16s(&start_codes.collect::<Vec<u16>>()),
+            u16s(&id_deltas.collect::<Vec<u16>>()),
+            vec![0; usize::from(seg_count) * 2],
+        ]
+        .concat()
+    }
+
+    /// A cmap table made up of subtables keyed by their platform and encoding IDs
+    fn cmap(subtables: Vec<((u16, u16), Vec<u8>)>) -> Vec<u8> {
+        let mut header = u16s(&[0, subtables.len() as u16]);
+        let mut data = Vec::new();
+        let data_offset = header.len() + subtables.len() * 8;
+
+        for ((platform_id, encoding_id), subtable) in subtables {
+            header.extend(u16s(&[platform_id, encoding_id]));
+            header.extend(((data_offset + data.len()) as u32).to_be_bytes());
+
+            data.extend(subtable);
+        }
+
+        [header, data].concat()
+    }
+
+    /// A font whose only character, "A", is a compound glyph made up of three
+    /// copies of a square: one as-is, one scaled by half and offset, and one
+    /// positioned by matching its first point to the third point of the first
+    fn font_file() -> Vec<u8> {
+        font_file_with(Vec::new())
+    }
+
+    /// The font of [`font_file`], with some of its tables replaced or added
+    fn font_file_with(replacements: Vec<(&'static [u8; 4], Vec<u8>)>) -> Vec<u8> {
+        let square = [
+            i16s(&[1, 0, 0, 100, 100]),
+            u16s(&[3, 0]),
+            vec![1; 4],
+            i16s(&[0, 100, 0, -100]),
+            i16s(&[0, 0, 100, 0]),
+        ]
+        .concat();
+
+        let compound = [
+            i16s(&[-1, 0, 0, 250, 200]),
+            u16s(&[0x23, 1, 0, 0]),
+            u16s(&[0x2B, 1, 200, 50, 0x2000]),
+            u16s(&[0x01, 1, 2, 0]),
+        ]
+        .concat();
+
+        let loca = u16s(&[0, 0, 17, 35]);
+
+        let head = [
+            u16s(&[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0, 1000]),
+            vec![0; 16],
+            i16s(&[0, 0, 250, 200]),
+            u16s(&[0, 8, 2, 0, 0]),
+        ]
+        .concat();
+
+        let maxp = [u16s(&[1, 0, 3]), vec![0; 26]].concat();
+
+        let cmap = u16s(&[
+            0,
+            1,
+            3,
+            1,
+            0,
+            12,
+            4,
+            32,
+            0,
+            4,
+            4,
+            1,
+            0,
+            0x41,
+            0xFFFF,
+            0,
+            0x41,
+            0xFFFF,
+            (2 - 0x41_i16) as u16,
+            1,
+            0,
+            0,
+        ]);
+
+        let hhea = [u16s(&[1, 0]), vec![0; 30], u16s(&[3])].concat();
+        let hmtx = u16s(&[500, 0, 500, 0, 1000, 0]);
+
+        let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"cmap", cmap),
+            (b"glyf", [square, compound].concat()),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+
+        for (tag, table) in replacements {
+            tables.retain(|&(existing, _)| existing != tag);
+            tables.push((tag, table));
+        }
+
+        tables.sort_by_key(|&(tag, _)| tag);
+
+        let mut directory = [vec![0, 1, 0, 0], u16s(&[tables.len() as u16, 0, 0, 0])].concat();
+        let mut data = Vec::new();
+        let data_offset = directory.len() + tables.len() * 16;
+
+        for (tag, table) in tables {
+            directory.extend_from_slice(tag);
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&((data_offset + data.len()) as u32).to_be_bytes());
+            directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+
+            data.extend(table);
+        }
+
+        [directory, data].concat()
+    }
+
+    #[test]
+    fn compound_glyph_is_assembled_from_components() {
+        let buffer = font_file();
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+        let mut interpreter = TrueTypeInterpreter::new(font);
+
+        let glyph_index = interpreter.glyph_index(b'A' as u32, None, false);
+        let glyph = interpreter.render_glyph(glyph_index).unwrap();
+        let bounding_box = glyph.outline.bounding_box();
+
+        assert_eq!(glyph.outline.paths.len(), 3);
+        assert_eq!(bounding_box.min, Point::new(0.0, 0.0));
+        assert_eq!(bounding_box.max, Point::new(0.25, 0.2));
+        assert_eq!(glyph.width_vector, Point::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn contour_ending_past_the_last_point_is_an_error() {
+        // the number of points is taken from the last contour, which ends
+        // before the first
+        let glyph = [
+            i16s(&[2, 0, 0, 100, 100]),
+            u16s(&[7, 3, 0]),
+            vec![1; 4],
+            i16s(&[0, 100, 0, -100]),
+            i16s(&[0, 0, 100, 0]),
+        ]
+        .concat();
+
+        let buffer = font_file_with(vec![(b"glyf", glyph), (b"loca", u16s(&[0, 0, 18, 18]))]);
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+        let mut interpreter = TrueTypeInterpreter::new(font);
+
+        assert!(interpreter.render_glyph(1).is_err());
+    }
+
+    #[test]
+    fn cmap_subtable_is_selected_by_encoding_and_symbolic_flag() {
+        let win_ansi = FontEncoding::Base(BaseFontEncoding::WinAnsiEncoding);
+        let differences = FontEncoding::Dictionary(FontEncodingDict {
+            base_encoding: Some(BaseFontEncoding::WinAnsiEncoding),
+            differences: Some(FontDifferences(HashMap::from([(
+                0x41,
+                "eacute".to_owned(),
+            )]))),
+        });
+
+        // é is 0xE9 in WinAnsiEncoding and in Unicode, and 0x8E in MacRomanEncoding
+        let buffer = font_file_with(vec![(
+            b"cmap",
+            cmap(vec![
+                ((3, 1), format_4(&[(0xE9, 0xE9, 1)])),
+                ((3, 0), format_4(&[(0xF0E9, 0xF0E9, 2)])),
+            ]),
+        )]);
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+
+        assert_eq!(font.glyph_index(0xE9, Some(&win_ansi), false), 1);
+        assert_eq!(font.glyph_index(0x41, Some(&differences), false), 1);
+        assert_eq!(font.glyph_index(0xE9, Some(&win_ansi), true), 2);
+        assert_eq!(font.glyph_index(0xE9, None, true), 2);
+
+        let buffer = font_file_with(vec![(
+            b"cmap",
+            cmap(vec![((1, 0), format_0(&[(0x8E, 1), (0xE9, 2)]))]),
+        )]);
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+
+        assert_eq!(font.glyph_index(0xE9, Some(&win_ansi), false), 1);
+        assert_eq!(font.glyph_index(0xE9, Some(&win_ansi), true), 2);
+    }
+
+    #[test]
+    fn post_table_names_glyphs() {
+        // glyph 1 is named from the standard Macintosh set, and glyph 2 by a
+        // name of its own
+        let post = [
+            u16s(&[2, 0]),
+            vec![0; 28],
+            u16s(&[3, 0, 36, 258]),
+            vec![6],
+            b"custom".to_vec(),
+        ]
+        .concat();
+
+        let buffer = font_file_with(vec![(b"post", post)]);
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+
+        assert_eq!(font.glyph_name(1), Some("A"));
+        assert_eq!(font.glyph_name(2), Some("custom"));
+
+        // names missing from the cmap are looked up in the post table
+        let differences = FontEncoding::Dictionary(FontEncodingDict {
+            base_encoding: None,
+            differences: Some(FontDifferences(HashMap::from([(
+                0x42,
+                "custom".to_owned(),
+            )]))),
+        });
+
+        assert_eq!(font.glyph_index(0x42, Some(&differences), false), 2);
+    }
+
+    #[test]
+    fn glyphs_past_the_long_metrics_share_the_last_advance_width() {
+        let hhea = [u16s(&[1, 0]), vec![0; 30], u16s(&[2])].concat();
+        let hmtx = [u16s(&[500, 0, 700, 0]), i16s(&[10])].concat();
+
+        let buffer = font_file_with(vec![(b"hhea", hhea), (b"hmtx", hmtx)]);
+        let font = ParsedTrueTypeFontFile::new(&buffer).unwrap();
+
+        assert_eq!(font.advance_width(0), Some(500));
+        assert_eq!(font.advance_width(1), Some(700));
+        assert_eq!(font.advance_width(2), Some(700));
+
+        let mut interpreter = TrueTypeInterpreter::new(font);
+        let glyph = interpreter.render_glyph(2).unwrap();
+
+        assert!((glyph.width_vector.x - 0.7).abs() < 1e-6);
+        assert_eq!(glyph.width_vector.y, 0.0);
+    }
+}
@@ -9,7 +9,7 @@ pub(crate) mod table;
 
 use crate::{error::PdfResult, objects::Dictionary, Resolve};
 
-use super::{encoding::FontEncoding, BaseFontDict};
+use super::{cmap::ToUnicodeCmapStream, encoding::FontEncoding, BaseFontDict};
 
 pub use data_types::*;
 pub use font_file::ParsedTrueTypeFontFile;
@@ -35,9 +35,12 @@ pub use interpreter::TrueTypeInterpreter;
 pub struct TrueTypeFont<'a> {
     pub(crate) base: BaseFontDict<'a>,
 
-    base_font: String,
+    pub(crate) base_font: String,
 
-    encoding: Option<FontEncoding>,
+    pub(crate) encoding: Option<FontEncoding>,
+
+    /// A stream containing a CMap file that maps character codes to Unicode values
+    pub(crate) to_unicode: Option<ToUnicodeCmapStream<'a>>,
 }
 
 impl<'a> TrueTypeFont<'a> {
@@ -45,11 +48,13 @@ impl<'a> TrueTypeFont<'a> {
         let base = BaseFontDict::from_dict(&mut dict, resolver)?;
         let base_font = dict.expect_name("BaseFont", resolver)?;
         let encoding = dict.get::<FontEncoding>("Encoding", resolver)?;
+        let to_unicode = dict.get("ToUnicode", resolver)?;
 
         Ok(Self {
             base,
             base_font,
             encoding,
+            to_unicode,
         })
     }
 }
@@ -4,10 +4,12 @@ use crate::font::true_type::{table::Cmap8Group, Fixed};
 
 use super::{
     table::{
-        CmapSubtable, CmapTable, CompoundGlyphPartDescription, CvtTable, DirectoryTableEntry,
-        FontDirectory, GlyfTable, Head, HeadFlags, LocaTable, MacStyle, MaxpTable, NameRecord,
-        NameTable, OffsetSubtable, OutlineFlag, SimpleGlyph, TableDirectory, TableTag,
-        TrueTypeGlyph,
+        CmapEncodingSubtable, CmapSubtable, CmapTable, CompoundGlyphComponentFlags,
+        CompoundGlyphPartDescription, CompoundTransformationOption, CvtTable, DirectoryTableEntry,
+        FontDirectory, GlyfTable, Head, HeadFlags, HheaTable, HmtxTable, LocaTable, LongHorMetric,
+        MacStyle, MaxpTable, NameRecord, NameTable, OffsetSubtable, Os2ExtendedMetrics, Os2Table,
+        Os2TypographicMetrics, OutlineFlag, PostTable, SimpleGlyph, TableDirectory, TableTag,
+        TrueTypeGlyph, MAC_GLYPH_NAMES,
     },
     FWord, LongDateTime,
 };
@@ -105,13 +107,21 @@ impl<'a> TrueTypeParser<'a> {
         Ok(FWord(self.read_i16()?))
     }
 
+    /// A 16-bit signed fixed number with the low 14 bits representing fraction
+    fn read_f2dot14(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from(self.read_i16()?) / 16384.0)
+    }
+
     fn read_long_date_time(&mut self) -> anyhow::Result<LongDateTime> {
         Ok(LongDateTime(self.read_i64()?))
     }
 
     #[track_caller]
-    pub fn get_byte_range(&self, length: usize) -> &[u8] {
-        &self.buffer[self.cursor..(self.cursor + length)]
+    pub fn get_byte_range(&self, length: usize) -> anyhow::Result<&[u8]> {
+        self.cursor
+            .checked_add(length)
+            .and_then(|end| self.buffer.get(self.cursor..end))
+            .ok_or(anyhow::anyhow!("unexpected eof"))
     }
 }
 
@@ -170,25 +180,31 @@ impl<'a> TrueTypeParser<'a> {
         })
     }
 
-    pub fn parse_simple_glyph_flags(&mut self, number_of_points: usize) -> Vec<u8> {
+    pub fn parse_simple_glyph_flags(&mut self, number_of_points: usize) -> anyhow::Result<Vec<u8>> {
         let mut flags = Vec::new();
 
         while flags.len() < number_of_points {
-            let next = self.next().unwrap();
+            let next = self.next()?;
             let should_repeat = next & 0b1000 != 0;
             flags.push(next);
 
             if should_repeat {
-                let num_repeat = self.next().unwrap();
+                let num_repeat = self.next()?;
                 for _ in 0..num_repeat {
                     flags.push(next);
                 }
             }
         }
 
-        assert_eq!(flags.len(), number_of_points);
+        if flags.len() != number_of_points {
+            anyhow::bail!(
+                "expected {} glyph flags, found {}",
+                number_of_points,
+                flags.len()
+            );
+        }
 
-        flags
+        Ok(flags)
     }
 
     fn parse_simple_glyph(&mut self, number_of_contours: i16) -> anyhow::Result<SimpleGlyph> {
@@ -196,55 +212,57 @@ impl<'a> TrueTypeParser<'a> {
 
         // todo: this should just reinterpret bytes
         for _ in 0..number_of_contours {
-            end_points_of_contours.push(self.read_u16().unwrap());
+            end_points_of_contours.push(self.read_u16()?);
         }
 
-        let instruction_length = self.read_u16().unwrap();
-        let instructions = self.get_byte_range(instruction_length as usize).to_vec();
+        let instruction_length = self.read_u16()?;
+        let instructions = self.get_byte_range(instruction_length as usize)?.to_vec();
         self.cursor += instruction_length as usize;
 
-        let number_of_points = *end_points_of_contours.last().unwrap() as usize + 1;
+        let number_of_points = end_points_of_contours
+            .last()
+            .map_or(0, |&last| usize::from(last) + 1);
 
-        let flags = self.parse_simple_glyph_flags(number_of_points);
+        let flags = self.parse_simple_glyph_flags(number_of_points)?;
 
         let mut x_coords = Vec::with_capacity(number_of_points);
         let mut y_coords = Vec::with_capacity(number_of_points);
 
-        let mut last_x = 0;
+        let mut last_x: i16 = 0;
         for &flag in &flags {
             let is_short = flag & OutlineFlag::X_SHORT_VECTOR != 0;
             let is_same_or_positive = flag & OutlineFlag::X_SAME_OR_POSITIVE != 0;
 
             let delta_x = match (is_short, is_same_or_positive) {
-                (false, false) => self.read_i16().unwrap(),
+                (false, false) => self.read_i16()?,
                 (false, true) => {
                     x_coords.push(last_x);
                     continue;
                 }
-                (true, false) => -(self.next().unwrap() as i16),
-                (true, true) => self.next().unwrap() as i16,
+                (true, false) => -(self.next()? as i16),
+                (true, true) => self.next()? as i16,
             };
 
-            last_x += delta_x;
+            last_x = last_x.wrapping_add(delta_x);
             x_coords.push(last_x);
         }
 
-        let mut last_y = 0;
+        let mut last_y: i16 = 0;
         for &flag in &flags {
             let is_short = flag & OutlineFlag::Y_SHORT_VECTOR != 0;
             let is_same_or_positive = flag & OutlineFlag::Y_SAME_OR_POSITIVE != 0;
 
             let delta_y = match (is_short, is_same_or_positive) {
-                (false, false) => self.read_i16().unwrap(),
+                (false, false) => self.read_i16()?,
                 (false, true) => {
                     y_coords.push(last_y);
                     continue;
                 }
-                (true, false) => -(self.next().unwrap() as i16),
-                (true, true) => self.next().unwrap() as i16,
+                (true, false) => -(self.next()? as i16),
+                (true, true) => self.next()? as i16,
             };
 
-            last_y += delta_y;
+            last_y = last_y.wrapping_add(delta_y);
             y_coords.push(last_y);
         }
 
@@ -258,23 +276,75 @@ impl<'a> TrueTypeParser<'a> {
     }
 
     fn parse_compound_glyph(&mut self) -> anyhow::Result<Vec<CompoundGlyphPartDescription>> {
-        todo!("ttf compound glyph")
+        let mut components = Vec::new();
+
+        loop {
+            let flags = CompoundGlyphComponentFlags(self.read_u16()?);
+            let glyph_index = self.read_u16()?;
+
+            let are_words = flags.contains(CompoundGlyphComponentFlags::ARG_1_AND_2_ARE_WORDS);
+            let are_xy_values = flags.contains(CompoundGlyphComponentFlags::ARGS_ARE_XY_VALUES);
+
+            // offsets are signed, while point numbers are unsigned
+            let (argument_one, argument_two) = match (are_words, are_xy_values) {
+                (true, true) => (i32::from(self.read_i16()?), i32::from(self.read_i16()?)),
+                (true, false) => (i32::from(self.read_u16()?), i32::from(self.read_u16()?)),
+                (false, true) => (i32::from(self.next()? as i8), i32::from(self.next()? as i8)),
+                (false, false) => (i32::from(self.next()?), i32::from(self.next()?)),
+            };
+
+            let transformation_option =
+                if flags.contains(CompoundGlyphComponentFlags::WE_HAVE_A_SCALE) {
+                    CompoundTransformationOption::Scale(self.read_f2dot14()?)
+                } else if flags.contains(CompoundGlyphComponentFlags::WE_HAVE_AN_X_AND_Y_SCALE) {
+                    CompoundTransformationOption::XyScale {
+                        x_scale: self.read_f2dot14()?,
+                        y_scale: self.read_f2dot14()?,
+                    }
+                } else if flags.contains(CompoundGlyphComponentFlags::WE_HAVE_A_TWO_BY_TWO) {
+                    CompoundTransformationOption::TwoByTwo {
+                        x_scale: self.read_f2dot14()?,
+                        scale_01: self.read_f2dot14()?,
+                        scale_10: self.read_f2dot14()?,
+                        y_scale: self.read_f2dot14()?,
+                    }
+                } else {
+                    CompoundTransformationOption::None
+                };
+
+            components.push(CompoundGlyphPartDescription {
+                flags,
+                glyph_index,
+                argument_one,
+                argument_two,
+                transformation_option,
+            });
+
+            if !flags.contains(CompoundGlyphComponentFlags::MORE_COMPONENTS) {
+                break;
+            }
+        }
+
+        // the instructions that follow the last component aren't needed, as
+        // glyphs aren't hinted
+
+        Ok(components)
     }
 
     pub fn parse_glyph(&mut self) -> anyhow::Result<TrueTypeGlyph> {
-        let number_of_contours = self.read_i16().unwrap();
-        let _x_min = self.read_fword().unwrap();
-        let _y_min = self.read_fword().unwrap();
-        let _x_max = self.read_fword().unwrap();
-        let _y_max = self.read_fword().unwrap();
+        let number_of_contours = self.read_i16()?;
+        let _x_min = self.read_fword()?;
+        let _y_min = self.read_fword()?;
+        let _x_max = self.read_fword()?;
+        let _y_max = self.read_fword()?;
 
         Ok(
             if number_of_contours.is_positive() || number_of_contours == 0 {
-                let glyph = self.parse_simple_glyph(number_of_contours).unwrap();
+                let glyph = self.parse_simple_glyph(number_of_contours)?;
 
                 TrueTypeGlyph::Simple(glyph)
             } else {
-                let glyph = self.parse_compound_glyph().unwrap();
+                let glyph = self.parse_compound_glyph()?;
 
                 TrueTypeGlyph::Compound(glyph)
             },
@@ -305,7 +375,7 @@ impl<'a> TrueTypeParser<'a> {
     ) -> anyhow::Result<LocaTable> {
         self.cursor = offset;
 
-        let buffer = self.get_byte_range(length);
+        let buffer = self.get_byte_range(length)?;
 
         let offsets = match format {
             // short
@@ -367,6 +437,248 @@ impl<'a> TrueTypeParser<'a> {
         })
     }
 
+    pub fn read_hhea_table(&mut self, offset: usize) -> anyhow::Result<HheaTable> {
+        self.cursor = offset;
+
+        let version = self.read_fixed()?;
+        let ascent = self.read_fword()?;
+        let descent = self.read_fword()?;
+        let line_gap = self.read_fword()?;
+        let advance_width_max = self.read_u16()?;
+        let min_left_side_bearing = self.read_fword()?;
+        let min_right_side_bearing = self.read_fword()?;
+        let x_max_extent = self.read_fword()?;
+        let caret_slope_rise = self.read_i16()?;
+        let caret_slope_run = self.read_i16()?;
+        let caret_offset = self.read_fword()?;
+
+        // reserved
+        for _ in 0..4 {
+            self.read_i16()?;
+        }
+
+        let metric_data_format = self.read_i16()?;
+        let num_of_long_hor_metrics = self.read_u16()?;
+
+        Ok(HheaTable {
+            version,
+            ascent,
+            descent,
+            line_gap,
+            advance_width_max,
+            min_left_side_bearing,
+            min_right_side_bearing,
+            x_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            metric_data_format,
+            num_of_long_hor_metrics,
+        })
+    }
+
+    pub fn read_hmtx_table(
+        &mut self,
+        entry: DirectoryTableEntry,
+        num_of_long_hor_metrics: u16,
+        num_glyphs: u16,
+    ) -> anyhow::Result<HmtxTable> {
+        self.cursor = entry.offset as usize;
+
+        let mut h_metrics = Vec::with_capacity(usize::from(num_of_long_hor_metrics));
+
+        for _ in 0..num_of_long_hor_metrics {
+            let advance_width = self.read_u16()?;
+            let left_side_bearing = self.read_i16()?;
+
+            h_metrics.push(LongHorMetric {
+                advance_width,
+                left_side_bearing,
+            });
+        }
+
+        // subset fonts often leave out the trailing left side bearings
+        let end = entry.offset as usize + entry.length as usize;
+        let num_left_side_bearings = num_glyphs
+            .saturating_sub(num_of_long_hor_metrics)
+            .min((end.saturating_sub(self.cursor) / 2) as u16);
+
+        let mut left_side_bearings = Vec::with_capacity(usize::from(num_left_side_bearings));
+
+        for _ in 0..num_left_side_bearings {
+            left_side_bearings.push(self.read_i16()?);
+        }
+
+        Ok(HmtxTable {
+            h_metrics,
+            left_side_bearings,
+        })
+    }
+
+    pub fn read_post_table(&mut self, entry: DirectoryTableEntry) -> anyhow::Result<PostTable> {
+        self.cursor = entry.offset as usize;
+
+        let version = self.read_u32()?;
+        let italic_angle = self.read_fixed()?;
+        let underline_position = self.read_fword()?;
+        let underline_thickness = self.read_fword()?;
+        let is_fixed_pitch = self.read_u32()?;
+        let _min_mem_type_42 = self.read_u32()?;
+        let _max_mem_type_42 = self.read_u32()?;
+        let _min_mem_type_1 = self.read_u32()?;
+        let _max_mem_type_1 = self.read_u32()?;
+
+        let glyph_names = match version {
+            0x00010000 => MAC_GLYPH_NAMES
+                .iter()
+                .map(|&name| name.to_owned())
+                .collect(),
+            0x00020000 => {
+                self.read_post_glyph_names(entry.offset as usize + entry.length as usize)?
+            }
+            // version 2.5 is deprecated, and version 3.0 has no glyph names
+            _ => Vec::new(),
+        };
+
+        Ok(PostTable {
+            version,
+            italic_angle,
+            underline_position,
+            underline_thickness,
+            is_fixed_pitch,
+            glyph_names,
+        })
+    }
+
+    fn read_post_glyph_names(&mut self, end: usize) -> anyhow::Result<Vec<String>> {
+        let num_glyphs = self.read_u16()?;
+
+        let mut glyph_name_index = Vec::with_capacity(usize::from(num_glyphs));
+
+        for _ in 0..num_glyphs {
+            glyph_name_index.push(self.read_u16()?);
+        }
+
+        // the names of glyphs not in the standard Macintosh set, as pascal strings
+        let mut names = Vec::new();
+
+        while self.cursor < end {
+            let len = usize::from(self.next()?);
+
+            if self.cursor + len > end.min(self.buffer.len()) {
+                break;
+            }
+
+            names.push(String::from_utf8_lossy(self.get_byte_range(len)?).into_owned());
+            self.cursor += len;
+        }
+
+        Ok(glyph_name_index
+            .into_iter()
+            .map(|idx| match MAC_GLYPH_NAMES.get(usize::from(idx)) {
+                Some(name) => (*name).to_owned(),
+                None => names
+                    .get(usize::from(idx) - MAC_GLYPH_NAMES.len())
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    pub fn read_os2_table(&mut self, entry: DirectoryTableEntry) -> anyhow::Result<Os2Table> {
+        self.cursor = entry.offset as usize;
+
+        let version = self.read_u16()?;
+        let x_avg_char_width = self.read_fword()?;
+        let us_weight_class = self.read_u16()?;
+        let us_width_class = self.read_u16()?;
+        let fs_type = self.read_i16()?;
+        let y_subscript_x_size = self.read_fword()?;
+        let y_subscript_y_size = self.read_fword()?;
+        let y_subscript_x_offset = self.read_fword()?;
+        let y_subscript_y_offset = self.read_fword()?;
+        let y_superscript_x_size = self.read_fword()?;
+        let y_superscript_y_size = self.read_fword()?;
+        let y_superscript_x_offset = self.read_fword()?;
+        let y_superscript_y_offset = self.read_fword()?;
+        let y_strikeout_size = self.read_fword()?;
+        let y_strikeout_position = self.read_fword()?;
+        let s_family_class = self.read_i16()?;
+        let panose = self.get_byte_range(10)?.try_into()?;
+        self.cursor += 10;
+
+        let mut ul_unicode_range = [0; 4];
+        for range in &mut ul_unicode_range {
+            *range = self.read_u32()?;
+        }
+
+        let ach_vend_id = self.read_u32_bytes()?;
+        let fs_selection = self.read_u16()?;
+        let fs_first_char_index = self.read_u16()?;
+        let fs_last_char_index = self.read_u16()?;
+
+        let end = entry.offset as usize + entry.length as usize;
+
+        // apple's version 0 tables end here
+        let typographic_metrics = if end >= self.cursor + 10 {
+            Some(Os2TypographicMetrics {
+                s_typo_ascender: self.read_fword()?,
+                s_typo_descender: self.read_fword()?,
+                s_typo_line_gap: self.read_fword()?,
+                us_win_ascent: self.read_u16()?,
+                us_win_descent: self.read_u16()?,
+            })
+        } else {
+            None
+        };
+
+        let ul_code_page_range = if version >= 1 {
+            Some([self.read_u32()?, self.read_u32()?])
+        } else {
+            None
+        };
+
+        let extended_metrics = if version >= 2 {
+            Some(Os2ExtendedMetrics {
+                sx_height: self.read_fword()?,
+                s_cap_height: self.read_fword()?,
+                us_default_char: self.read_u16()?,
+                us_break_char: self.read_u16()?,
+                us_max_context: self.read_u16()?,
+            })
+        } else {
+            None
+        };
+
+        Ok(Os2Table {
+            version,
+            x_avg_char_width,
+            us_weight_class,
+            us_width_class,
+            fs_type,
+            y_subscript_x_size,
+            y_subscript_y_size,
+            y_subscript_x_offset,
+            y_subscript_y_offset,
+            y_superscript_x_size,
+            y_superscript_y_size,
+            y_superscript_x_offset,
+            y_superscript_y_offset,
+            y_strikeout_size,
+            y_strikeout_position,
+            s_family_class,
+            panose,
+            ul_unicode_range,
+            ach_vend_id,
+            fs_selection,
+            fs_first_char_index,
+            fs_last_char_index,
+            typographic_metrics,
+            ul_code_page_range,
+            extended_metrics,
+        })
+    }
+
     pub fn read_maxp_table(&mut self, offset: usize) -> anyhow::Result<MaxpTable> {
         self.cursor = offset;
 
@@ -466,7 +778,7 @@ impl<'a> TrueTypeParser<'a> {
         assert_eq!(version, 0);
 
         let number_subtables = self.read_u16()?;
-        let mut offsets = Vec::with_capacity(usize::from(number_subtables));
+        let mut encodings = Vec::with_capacity(usize::from(number_subtables));
 
         for _ in 0..number_subtables {
             let platform_id = self.read_u16()?;
@@ -485,14 +797,19 @@ impl<'a> TrueTypeParser<'a> {
                 _ => unreachable!(),
             }
 
-            offsets.push(offset as usize);
+            encodings.push((platform_id, platform_specific_id, offset as usize));
         }
 
         let mut subtables = Vec::with_capacity(usize::from(number_subtables));
 
-        for subtable_offset in offsets {
+        for (platform_id, platform_specific_id, subtable_offset) in encodings {
             self.cursor = table_offset + subtable_offset;
-            subtables.push(self.parse_cmap_subtable()?);
+
+            subtables.push(CmapEncodingSubtable {
+                platform_id,
+                platform_specific_id,
+                subtable: self.parse_cmap_subtable()?,
+            });
         }
 
         Ok(CmapTable { version, subtables })
@@ -508,9 +825,15 @@ impl<'a> TrueTypeParser<'a> {
             6 => self.parse_cmap_subtable_6(),
             8 => self.parse_cmap_subtable_8(),
             10 => todo!("cmap subtable 10"),
-            12 => todo!("cmap subtable 12"),
-            13 => todo!("cmap subtable 13"),
-            14 => todo!("cmap subtable 14"),
+            12 => Ok(CmapSubtable::Twelve {
+                language: self.parse_cmap_subtable_groups_header()?,
+                groups: self.parse_cmap_subtable_groups()?,
+            }),
+            13 => Ok(CmapSubtable::Thirteen {
+                language: self.parse_cmap_subtable_groups_header()?,
+                groups: self.parse_cmap_subtable_groups()?,
+            }),
+            14 => Ok(CmapSubtable::Fourteen),
             _ => anyhow::bail!("invalid cmap subtable format: {:?}", format),
         }
     }
@@ -519,7 +842,7 @@ impl<'a> TrueTypeParser<'a> {
         let length = self.read_u16()?;
         assert_eq!(length, 262, "length must be 262 for type 0 cmap subtable");
         let language = self.read_u16()?;
-        let glyph_index_array = self.get_byte_range(256).try_into()?;
+        let glyph_index_array = self.get_byte_range(256)?.try_into()?;
 
         Ok(CmapSubtable::Zero {
             language,
@@ -566,7 +889,7 @@ impl<'a> TrueTypeParser<'a> {
         assert!(start_pos + length as usize >= self.cursor);
 
         let glyph_index_array = self
-            .get_byte_range(start_pos + length as usize - self.cursor)
+            .get_byte_range(start_pos + length as usize - self.cursor)?
             .chunks_exact(2)
             .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
             .collect();
@@ -614,7 +937,29 @@ impl<'a> TrueTypeParser<'a> {
         assert_eq!(reserved, 0);
         let length = self.read_u32()?;
         let language = self.read_u32()?;
-        let is32 = self.get_byte_range(65536).to_vec();
+        let is32 = self.get_byte_range(8192)?.to_vec();
+        self.cursor += 8192;
+        let groups = self.parse_cmap_subtable_groups()?;
+
+        assert_eq!(self.cursor, start + length as usize);
+
+        Ok(CmapSubtable::Eight {
+            language,
+            is32,
+            groups,
+        })
+    }
+
+    /// The header of format 12 and 13 subtables, returning the language
+    fn parse_cmap_subtable_groups_header(&mut self) -> anyhow::Result<u32> {
+        let _reserved = self.read_u16()?;
+        let _length = self.read_u32()?;
+        let language = self.read_u32()?;
+
+        Ok(language)
+    }
+
+    fn parse_cmap_subtable_groups(&mut self) -> anyhow::Result<Vec<Cmap8Group>> {
         let n_groups = self.read_u32()?;
 
         let mut groups = Vec::with_capacity(n_groups as usize);
@@ -631,12 +976,6 @@ impl<'a> TrueTypeParser<'a> {
             })
         }
 
-        assert_eq!(self.cursor, start + length as usize);
-
-        Ok(CmapSubtable::Eight {
-            language,
-            is32,
-            groups,
-        })
+        Ok(groups)
     }
 }
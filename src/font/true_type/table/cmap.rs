@@ -2,7 +2,36 @@
 pub struct CmapTable {
     /// Version number (Set to zero)
     pub version: u16,
-    pub subtables: Vec<CmapSubtable>,
+    pub subtables: Vec<CmapEncodingSubtable>,
+}
+
+impl CmapTable {
+    pub fn find_subtable(
+        &self,
+        platform_id: u16,
+        platform_specific_id: u16,
+    ) -> Option<&CmapSubtable> {
+        self.subtables
+            .iter()
+            .find(|encoding| {
+                encoding.platform_id == platform_id
+                    && encoding.platform_specific_id == platform_specific_id
+            })
+            .map(|encoding| &encoding.subtable)
+    }
+}
+
+/// A subtable, along with the platform and platform-specific encoding of the
+/// character codes it maps
+#[derive(Debug)]
+pub struct CmapEncodingSubtable {
+    /// Platform identifier
+    pub platform_id: u16,
+
+    /// Platform-specific encoding identifier
+    pub platform_specific_id: u16,
+
+    pub subtable: CmapSubtable,
 }
 
 #[derive(Debug)]
@@ -76,12 +105,32 @@ pub enum CmapSubtable {
         num_chars: u32,
         glyphs: Vec<u16>,
     },
-    Twelve,
-    Thirteen,
+
+    /// Segmented coverage
+    ///
+    /// This is the standard character-to-glyph-index mapping table for fonts
+    /// supporting Unicode supplementary-plane characters
+    Twelve {
+        language: u32,
+        groups: Vec<Cmap8Group>,
+    },
+
+    /// Many-to-one range mappings
+    ///
+    /// Each group maps every character code in its range to the same glyph,
+    /// which is used for "last resort" fonts
+    Thirteen {
+        language: u32,
+        groups: Vec<Cmap8Group>,
+    },
+
+    /// Unicode variation sequences, which don't map character codes on their own
     Fourteen,
 }
 
 impl CmapSubtable {
+    /// The glyph index `char_code` maps to, or 0 (the missing glyph) if it
+    /// isn't mapped
     pub fn lookup_char_code(&self, char_code: u32) -> u32 {
         match self {
             CmapSubtable::Four {
@@ -89,59 +138,71 @@ impl CmapSubtable {
                 start_code,
                 id_delta,
                 id_range_offset,
-                seg_count_x2,
                 glyph_index_array,
                 ..
             } => {
-                let (idx, _) = end_code
+                let idx = match end_code
                     .iter()
-                    .enumerate()
-                    .find(|(_, end_code)| **end_code as u32 >= char_code)
-                    .unwrap();
+                    .position(|&end_code| end_code as u32 >= char_code)
+                {
+                    Some(idx) => idx,
+                    None => return 0,
+                };
 
-                let start_code = start_code[idx];
-                if start_code as u32 > char_code {
+                let start_code = start_code[idx] as u32;
+                if start_code > char_code {
                     return 0;
                 }
 
                 if id_range_offset[idx] == 0 {
-                    (id_delta[idx] as i32 + char_code as i32) as u32 % 65536
-                } else {
-                    let addr = glyph_index_array[(idx as u16
-                        + id_range_offset[idx] / 2
-                        + (char_code as u16 - start_code)
-                        - *seg_count_x2 / 2)
-                        as usize];
-
-                    (id_delta[idx] as i32 + addr as i32) as u32 % 65536
+                    return (id_delta[idx] as i32 + char_code as i32) as u32 % 65536;
+                }
+
+                // the offset is relative to this segment's entry in
+                // `id_range_offset`, which immediately precedes the glyph index
+                // array
+                let glyph_idx =
+                    (id_range_offset[idx] / 2) as usize + (char_code - start_code) as usize + idx;
+
+                match glyph_idx
+                    .checked_sub(id_range_offset.len())
+                    .and_then(|idx| glyph_index_array.get(idx))
+                {
+                    Some(0) | None => 0,
+                    Some(&glyph) => (id_delta[idx] as i32 + glyph as i32) as u32 % 65536,
                 }
             }
             CmapSubtable::Zero {
                 glyph_index_array, ..
-            } => {
-                assert!(char_code <= 256);
-                glyph_index_array[char_code as usize] as u32
-            }
+            } => glyph_index_array
+                .get(char_code as usize)
+                .map_or(0, |&glyph| glyph as u32),
             CmapSubtable::Six {
                 glyph_index_array,
                 first_code,
                 ..
-            } => {
-                println!("likely incorrect ttf cmap subtable 6");
-                if char_code == 0 {
-                    return *first_code as u32;
-                }
-
-                glyph_index_array
-                    .get(char_code as usize - 1)
-                    .copied()
-                    .unwrap_or(0) as u32
-            }
+            } => char_code
+                .checked_sub(*first_code as u32)
+                .and_then(|idx| glyph_index_array.get(idx as usize))
+                .map_or(0, |&glyph| glyph as u32),
+            CmapSubtable::Eight { groups, .. } | CmapSubtable::Twelve { groups, .. } => groups
+                .iter()
+                .find(|group| (group.start_char_code..=group.end_char_code).contains(&char_code))
+                .map_or(0, |group| {
+                    group.start_glyph_code + (char_code - group.start_char_code)
+                }),
+            CmapSubtable::Thirteen { groups, .. } => groups
+                .iter()
+                .find(|group| (group.start_char_code..=group.end_char_code).contains(&char_code))
+                .map_or(0, |group| group.start_glyph_code),
+            CmapSubtable::Fourteen => 0,
             _ => todo!("unimplemented cmap table lookup: {:#?}", self),
         }
     }
 }
 
+/// A range of character codes mapped to consecutive glyphs. Formats 12 and 13
+/// use groups with the same layout
 #[derive(Debug)]
 pub struct Cmap8Group {
     /// First character code in this group; note that if this group is for one or
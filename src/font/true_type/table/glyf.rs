@@ -1,3 +1,5 @@
+use crate::geometry::Point;
+
 use super::TableTag;
 
 #[derive(Debug)]
@@ -95,22 +97,22 @@ impl OutlineFlag {
 
 #[derive(Debug)]
 pub struct CompoundGlyphPartDescription {
-    flags: CompoundGlyphComponentFlags,
+    pub(crate) flags: CompoundGlyphComponentFlags,
 
     /// Glyph index of component
-    glyph_index: u16,
+    pub(crate) glyph_index: u16,
 
     /// X-offset for component or point number; type depends on bits 0 and 1 in component flags
-    argument_one: u16,
+    pub(crate) argument_one: i32,
 
     /// Y-offset for component or point number type depends on bits 0 and 1 in component flags
-    argument_two: u16,
+    pub(crate) argument_two: i32,
 
-    transformation_option: CompoundTransformationOption,
+    pub(crate) transformation_option: CompoundTransformationOption,
 }
 
-#[derive(Debug)]
-pub struct CompoundGlyphComponentFlags(u16);
+#[derive(Debug, Clone, Copy)]
+pub struct CompoundGlyphComponentFlags(pub u16);
 
 impl CompoundGlyphComponentFlags {
     pub const ARG_1_AND_2_ARE_WORDS: u16 = 1 << 0;
@@ -124,11 +126,47 @@ impl CompoundGlyphComponentFlags {
     pub const WE_HAVE_INSTRUCTIONS: u16 = 1 << 8;
     pub const USE_MY_METRICS: u16 = 1 << 9;
     pub const OVERLAP_COMPOUND: u16 = 1 << 10;
+    pub const SCALED_COMPONENT_OFFSET: u16 = 1 << 11;
+    pub const UNSCALED_COMPONENT_OFFSET: u16 = 1 << 12;
+
+    pub const fn contains(self, flag: u16) -> bool {
+        self.0 & flag != 0
+    }
 }
 
-#[derive(Debug)]
-enum CompoundTransformationOption {
-    One,
-    Two,
-    Three,
+/// The transformation applied to the points of a component, in the order
+/// they're stored in the font
+#[derive(Debug, Clone, Copy)]
+pub enum CompoundTransformationOption {
+    None,
+    Scale(f32),
+    XyScale {
+        x_scale: f32,
+        y_scale: f32,
+    },
+    TwoByTwo {
+        x_scale: f32,
+        scale_01: f32,
+        scale_10: f32,
+        y_scale: f32,
+    },
+}
+
+impl CompoundTransformationOption {
+    pub fn apply(self, point: Point) -> Point {
+        match self {
+            Self::None => point,
+            Self::Scale(scale) => Point::new(point.x * scale, point.y * scale),
+            Self::XyScale { x_scale, y_scale } => Point::new(point.x * x_scale, point.y * y_scale),
+            Self::TwoByTwo {
+                x_scale,
+                scale_01,
+                scale_10,
+                y_scale,
+            } => Point::new(
+                point.x * x_scale + point.y * scale_10,
+                point.x * scale_01 + point.y * y_scale,
+            ),
+        }
+    }
 }
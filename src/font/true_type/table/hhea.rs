@@ -0,0 +1,39 @@
+use crate::font::true_type::{FWord, Fixed};
+
+use super::TableTag;
+
+/// Information needed to lay out fonts whose characters are written
+/// horizontally
+#[derive(Debug)]
+pub struct HheaTable {
+    /// 0x00010000 (1.0)
+    pub version: Fixed,
+    /// Distance from baseline of highest ascender
+    pub ascent: FWord,
+    /// Distance from baseline of lowest descender
+    pub descent: FWord,
+    /// typographic line gap
+    pub line_gap: FWord,
+    /// must be consistent with horizontal metrics
+    pub advance_width_max: u16,
+    /// must be consistent with horizontal metrics
+    pub min_left_side_bearing: FWord,
+    /// must be consistent with horizontal metrics
+    pub min_right_side_bearing: FWord,
+    /// max(lsb + (xMax-xMin))
+    pub x_max_extent: FWord,
+    /// used to calculate the slope of the caret (rise/run) set to 1 for vertical caret
+    pub caret_slope_rise: i16,
+    /// 0 for vertical
+    pub caret_slope_run: i16,
+    /// set value to 0 for non-slanted fonts
+    pub caret_offset: FWord,
+    /// 0 for current format
+    pub metric_data_format: i16,
+    /// number of advance widths in metrics table
+    pub num_of_long_hor_metrics: u16,
+}
+
+impl HheaTable {
+    pub const TAG: TableTag = TableTag::new(*b"hhea");
+}
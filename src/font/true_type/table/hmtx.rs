@@ -0,0 +1,30 @@
+use super::TableTag;
+
+/// The horizontal metrics of each glyph in the font
+#[derive(Debug)]
+pub struct HmtxTable {
+    /// The advance width and left side bearing of the first
+    /// `num_of_long_hor_metrics` glyphs
+    pub h_metrics: Vec<LongHorMetric>,
+
+    /// The left side bearings of the remaining glyphs, which share the advance
+    /// width of the last entry in `h_metrics`
+    pub left_side_bearings: Vec<i16>,
+}
+
+impl HmtxTable {
+    pub const TAG: TableTag = TableTag::new(*b"hmtx");
+
+    pub fn advance_width(&self, glyph_index: u32) -> Option<u16> {
+        self.h_metrics
+            .get(glyph_index as usize)
+            .or_else(|| self.h_metrics.last())
+            .map(|metric| metric.advance_width)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LongHorMetric {
+    pub advance_width: u16,
+    pub left_side_bearing: i16,
+}
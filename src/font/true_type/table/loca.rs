@@ -4,9 +4,12 @@ pub struct LocaTable {
 }
 
 impl LocaTable {
-    pub fn get_glyf_entry(&self, char_code: u32) -> Option<GlyfEntry> {
-        let offset = self.offsets[char_code as usize];
-        let len = self.offsets[char_code as usize + 1] - offset;
+    pub fn get_glyf_entry(&self, glyph_index: u32) -> Option<GlyfEntry> {
+        let offset = *self.offsets.get(glyph_index as usize)?;
+        let len = self
+            .offsets
+            .get(glyph_index as usize + 1)?
+            .checked_sub(offset)?;
 
         Some(GlyfEntry { offset, len })
     }
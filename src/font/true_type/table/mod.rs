@@ -5,24 +5,32 @@ mod cvt;
 mod font_directory;
 mod glyf;
 mod head;
+mod hhea;
+mod hmtx;
 mod loca;
 mod maxp;
 mod name;
+mod os2;
+mod post;
 mod table_name;
 mod tag;
 
-pub(super) use cmap::{Cmap8Group, CmapSubtable, CmapTable};
+pub(super) use cmap::{Cmap8Group, CmapEncodingSubtable, CmapSubtable, CmapTable};
 pub(super) use cvt::CvtTable;
 pub(super) use font_directory::{
     DirectoryTableEntry, FontDirectory, OffsetSubtable, TableDirectory,
 };
 pub(super) use glyf::{
-    CompoundGlyphPartDescription, GlyfTable, GlyphDescription, OutlineFlag, SimpleGlyph,
-    TrueTypeGlyph,
+    CompoundGlyphComponentFlags, CompoundGlyphPartDescription, CompoundTransformationOption,
+    GlyfTable, GlyphDescription, OutlineFlag, SimpleGlyph, TrueTypeGlyph,
 };
 pub(super) use head::{Head, HeadFlags, MacStyle};
+pub(super) use hhea::HheaTable;
+pub(super) use hmtx::{HmtxTable, LongHorMetric};
 pub(super) use loca::LocaTable;
 pub(super) use maxp::{MaxpPostscriptTable, MaxpTable};
 pub(super) use name::{NameRecord, NameTable};
+pub(super) use os2::{Os2ExtendedMetrics, Os2Table, Os2TypographicMetrics};
+pub(super) use post::{PostTable, MAC_GLYPH_NAMES};
 pub(super) use table_name::TableName;
 pub(super) use tag::TableTag;
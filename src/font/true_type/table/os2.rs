@@ -0,0 +1,86 @@
+use crate::font::true_type::FWord;
+
+use super::TableTag;
+
+/// Metrics and other information required by Windows. Fields added in later
+/// versions of the table are `None` in fonts with an earlier version
+#[derive(Debug)]
+pub struct Os2Table {
+    pub version: u16,
+    /// Average weighted advance width of lower case letters and space
+    pub x_avg_char_width: FWord,
+    /// Visual weight (degree of blackness or thickness) of stroke in glyphs
+    pub us_weight_class: u16,
+    /// Relative change from the normal aspect ratio (width to height ratio) as
+    /// specified by a font designer for the glyphs in the font
+    pub us_width_class: u16,
+    /// Characteristics and properties of this font (set to 0); bit 1 is
+    /// embedding restricted
+    pub fs_type: i16,
+    pub y_subscript_x_size: FWord,
+    pub y_subscript_y_size: FWord,
+    pub y_subscript_x_offset: FWord,
+    pub y_subscript_y_offset: FWord,
+    pub y_superscript_x_size: FWord,
+    pub y_superscript_y_size: FWord,
+    pub y_superscript_x_offset: FWord,
+    pub y_superscript_y_offset: FWord,
+    /// Width of the strikeout stroke
+    pub y_strikeout_size: FWord,
+    /// Position of the strikeout stroke relative to the baseline
+    pub y_strikeout_position: FWord,
+    /// Classification of font-family design
+    pub s_family_class: i16,
+    /// 10 byte series of number used to describe the visual characteristics of
+    /// a given typeface
+    pub panose: [u8; 10],
+    /// The Unicode blocks or ranges supported by the font
+    pub ul_unicode_range: [u32; 4],
+    /// Four character identifier for the font vendor
+    pub ach_vend_id: [u8; 4],
+    /// Information concerning the nature of the font patterns
+    pub fs_selection: u16,
+    /// The minimum Unicode index in this font
+    pub fs_first_char_index: u16,
+    /// The maximum Unicode index in this font
+    pub fs_last_char_index: u16,
+
+    /// Only present in tables whose length includes them, which isn't always
+    /// the case for version 0
+    pub typographic_metrics: Option<Os2TypographicMetrics>,
+
+    /// The code pages supported by the font, from version 1
+    pub ul_code_page_range: Option<[u32; 2]>,
+
+    /// Additional metrics, from version 2
+    pub extended_metrics: Option<Os2ExtendedMetrics>,
+}
+
+impl Os2Table {
+    pub const TAG: TableTag = TableTag::new(*b"OS/2");
+}
+
+#[derive(Debug)]
+pub struct Os2TypographicMetrics {
+    pub s_typo_ascender: FWord,
+    pub s_typo_descender: FWord,
+    pub s_typo_line_gap: FWord,
+    pub us_win_ascent: u16,
+    pub us_win_descent: u16,
+}
+
+#[derive(Debug)]
+pub struct Os2ExtendedMetrics {
+    /// The distance between the baseline and the approximate height of
+    /// non-ascending lowercase letters
+    pub sx_height: FWord,
+    /// The distance between the baseline and the approximate height of
+    /// uppercase letters
+    pub s_cap_height: FWord,
+    /// The character used for characters not in the font
+    pub us_default_char: u16,
+    /// The character used to separate words
+    pub us_break_char: u16,
+    /// The maximum length of a target glyph context for any feature in the font
+    pub us_max_context: u16,
+}
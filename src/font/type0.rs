@@ -10,7 +10,7 @@ use super::{
 };
 
 #[derive(Debug)]
-enum Type0FontEncoding<'a> {
+pub(crate) enum Type0FontEncoding<'a> {
     Predefined(PredefinedCjkCmapName),
     Stream(Stream<'a>),
 }
@@ -51,14 +51,14 @@ pub struct Type0Font<'a> {
     /// maps character codes to font numbers and CIDs. If the descendant is
     /// a Type 2 CIDFont whose associated TrueType font program is not embedded
     /// in the PDF file, the Encoding entry shall be a predefined CMap name
-    encoding: Type0FontEncoding<'a>,
+    pub(crate) encoding: Type0FontEncoding<'a>,
 
     /// A one-element array specifying the CIDFont dictionary that is the descendant
     /// of this Type 0 font
     pub descendant_font: [CidFontDictionary<'a>; 1],
 
     /// A stream containing a CMap file that maps character codes to Unicode values
-    to_unicode: Option<ToUnicodeCmapStream<'a>>,
+    pub(crate) to_unicode: Option<ToUnicodeCmapStream<'a>>,
 }
 
 impl<'a> FromObj<'a> for Type0Font<'a> {
@@ -55,7 +55,7 @@ impl<'a> Type1Font<'a> {
 /// in the font
 #[derive(Debug)]
 pub struct MmType1Font<'a> {
-    type1: Type1Font<'a>,
+    pub(crate) type1: Type1Font<'a>,
 }
 
 impl<'a> MmType1Font<'a> {
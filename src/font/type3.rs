@@ -57,7 +57,7 @@ pub struct Type3Font<'a> {
     resources: Option<Resources<'a>>,
 
     /// A stream containing a CMap file that maps character codes to Unicode values
-    to_unicode: Option<ToUnicodeCmapStream<'a>>,
+    pub(crate) to_unicode: Option<ToUnicodeCmapStream<'a>>,
 }
 
 impl<'a> Type3Font<'a> {
@@ -65,7 +65,7 @@ impl<'a> Type3Font<'a> {
         let base = BaseFontDict::from_dict(&mut dict, resolver)?;
         let font_bounding_box = dict.expect::<Rectangle>("FontBBox", resolver)?;
         let font_matrix = dict
-            .get::<Matrix>("Matrix", resolver)?
+            .get::<Matrix>("FontMatrix", resolver)?
             .unwrap_or_else(|| Matrix::new(0.001, 0.0, 0.0, 0.001, 0.0, 0.0));
         let char_procs = dict
             .expect_dict("CharProcs", resolver)?
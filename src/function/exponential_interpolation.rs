@@ -30,4 +30,14 @@ impl ExponentialInterpolationFunction {
 
         Ok(Self { c0, c1, n })
     }
+
+    pub fn evaluate(&self, x: f32) -> Vec<f32> {
+        let x_n = x.powf(self.n);
+
+        self.c0
+            .iter()
+            .zip(&self.c1)
+            .map(|(c0, c1)| c0 + x_n * (c1 - c0))
+            .collect()
+    }
 }
@@ -30,7 +30,8 @@ pub struct Function<'a> {
     /// and the jth output value, yj , shall lie in the interval Range2j <= yj <= Range2j+1.
     /// Output values outside the declared range shall be clipped to the nearest
     /// boundary value. If this entry is absent, no clipping shall be done.
-    // todo: optional for type 0 and type 4
+    ///
+    /// Required for type 0 and type 4 functions
     range: Option<Vec<f32>>,
 
     subtype: FunctionSubtype<'a>,
@@ -85,11 +86,28 @@ impl<'a> FromObj<'a> for Function<'a> {
 
         let dict = stream_or_dict.dict();
 
-        let domain = dict.expect("Domain", resolver)?;
+        let domain: Vec<f32> = dict.expect("Domain", resolver)?;
         let range = dict.get("Range", resolver)?;
 
         let subtype = FunctionSubtype::from_obj(stream_or_dict.into_obj(), resolver)?;
 
+        if range.is_none()
+            && matches!(
+                subtype,
+                FunctionSubtype::Sampled(..) | FunctionSubtype::PostScriptCalculator(..)
+            )
+        {
+            anyhow::bail!("type 0 and type 4 functions must have a range");
+        }
+
+        if let FunctionSubtype::Sampled(f) = &subtype {
+            if f.num_inputs() * 2 != domain.len() {
+                anyhow::bail!("type 0 function has a domain of {} numbers", domain.len());
+            }
+
+            f.check_sample_table(range.as_ref().map_or(0, Vec::len) / 2)?;
+        }
+
         Ok(Self {
             domain,
             range,
@@ -98,6 +116,59 @@ impl<'a> FromObj<'a> for Function<'a> {
     }
 }
 
+impl<'a> Function<'a> {
+    /// Evaluate the function at `input`
+    ///
+    /// Inputs are clipped to the domain, with missing inputs taken to be the lower bound of their
+    /// interval, and outputs are clipped to the range, if present
+    pub fn evaluate(&self, input: &[f32]) -> Vec<f32> {
+        let input = self
+            .domain
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(i, interval)| clip(input.get(i).copied().unwrap_or(interval[0]), interval))
+            .collect::<Vec<f32>>();
+
+        if input.is_empty() {
+            return Vec::new();
+        }
+
+        let mut output = match &self.subtype {
+            FunctionSubtype::Sampled(f) => {
+                f.evaluate(&input, &self.domain, self.range.as_deref().unwrap_or(&[]))
+            }
+            // type 2 and type 3 functions have a single input
+            FunctionSubtype::ExponentialInterpolation(f) => f.evaluate(input[0]),
+            FunctionSubtype::Stitching(f) => f.evaluate(input[0], &self.domain),
+            FunctionSubtype::PostScriptCalculator(f) => {
+                f.evaluate(&input, self.range.as_deref().unwrap_or(&[]))
+            }
+        };
+
+        if let Some(range) = &self.range {
+            for (y, interval) in output.iter_mut().zip(range.chunks_exact(2)) {
+                *y = clip(*y, interval);
+            }
+        }
+
+        output
+    }
+}
+
+/// Clip `x` to the interval `[min max]`
+fn clip(x: f32, interval: &[f32]) -> f32 {
+    x.max(interval[0]).min(interval[1])
+}
+
+/// Linearly map `x` from the interval `[xmin xmax]` to the interval `[ymin ymax]`
+fn interpolate(x: f32, xmin: f32, xmax: f32, ymin: f32, ymax: f32) -> f32 {
+    if xmax == xmin {
+        return ymin;
+    }
+
+    ymin + (x - xmin) * (ymax - ymin) / (xmax - xmin)
+}
+
 #[derive(Debug, Clone)]
 enum FunctionSubtype<'a> {
     Sampled(SampledFunction<'a>),
@@ -206,3 +277,127 @@ impl<'a> FromObj<'a> for TransferFunction<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        objects::Object,
+        stream::{DirectObjects, Stream},
+        test_util::{dict, numbers},
+        FromObj,
+    };
+
+    use super::Function;
+
+    fn function(obj: Object<'static>) -> Function<'static> {
+        Function::from_obj(obj, &mut DirectObjects).unwrap()
+    }
+
+    #[test]
+    fn sampled_function_interpolates_between_samples() {
+        // a 2x2 table of 8 bit samples, with the first input varying fastest
+        let stream = Stream::new(
+            dict(vec![
+                ("FunctionType", Object::Integer(0)),
+                ("Domain", numbers(&[0.0, 1.0, 0.0, 1.0])),
+                ("Range", numbers(&[0.0, 1.0])),
+                ("Size", Object::Array(vec![Object::Integer(2); 2])),
+                ("BitsPerSample", Object::Integer(8)),
+            ]),
+            vec![0, 255, 51, 102],
+        )
+        .unwrap();
+
+        let f = function(Object::Stream(stream));
+
+        assert_eq!(f.evaluate(&[1.0, 0.0]), vec![1.0]);
+        assert_eq!(f.evaluate(&[0.0, 1.0]), vec![0.2]);
+        assert_eq!(f.evaluate(&[0.5, 0.0]), vec![0.5]);
+        assert_eq!(f.evaluate(&[0.5, 2.0]), vec![0.3]);
+    }
+
+    #[test]
+    fn sampled_function_with_too_many_inputs_is_an_error() {
+        let sampled = |inputs: usize, domain: usize| {
+            let stream = Stream::new(
+                dict(vec![
+                    ("FunctionType", Object::Integer(0)),
+                    ("Domain", numbers(&[0.0, 1.0].repeat(domain))),
+                    ("Range", numbers(&[0.0, 1.0])),
+                    ("Size", Object::Array(vec![Object::Integer(1); inputs])),
+                    ("BitsPerSample", Object::Integer(8)),
+                ]),
+                vec![0],
+            )
+            .unwrap();
+
+            Function::from_obj(Object::Stream(stream), &mut DirectObjects)
+        };
+
+        assert!(sampled(16, 16).is_ok());
+        assert!(sampled(64, 64).is_err());
+
+        // every input must have a size
+        assert!(sampled(1, 64).is_err());
+    }
+
+    #[test]
+    fn sampled_function_larger_than_its_stream_is_an_error() {
+        let sampled = |size: Vec<i32>, range: &[f32], samples: Vec<u8>| {
+            let stream = Stream::new(
+                dict(vec![
+                    ("FunctionType", Object::Integer(0)),
+                    ("Domain", numbers(&[0.0, 1.0].repeat(size.len()))),
+                    ("Range", numbers(range)),
+                    (
+                        "Size",
+                        Object::Array(size.into_iter().map(Object::Integer).collect()),
+                    ),
+                    ("BitsPerSample", Object::Integer(8)),
+                ]),
+                samples,
+            )
+            .unwrap();
+
+            Function::from_obj(Object::Stream(stream), &mut DirectObjects)
+        };
+
+        assert!(sampled(vec![2, 2], &[0.0, 1.0, 0.0, 1.0], vec![0; 8]).is_ok());
+        assert!(sampled(vec![2, 2], &[0.0, 1.0, 0.0, 1.0], vec![0; 7]).is_err());
+        assert!(sampled(vec![2, 0], &[0.0, 1.0], vec![0; 4]).is_err());
+
+        // the number of entries overflows
+        assert!(sampled(vec![i32::MAX; 4], &[0.0, 1.0], vec![0; 4]).is_err());
+    }
+
+    #[test]
+    fn stitching_function_encodes_into_subdomains() {
+        let exponential = |c0: f32, c1: f32| {
+            Object::Dictionary(dict(vec![
+                ("FunctionType", Object::Integer(2)),
+                ("Domain", numbers(&[0.0, 1.0])),
+                ("C0", numbers(&[c0])),
+                ("C1", numbers(&[c1])),
+                ("N", Object::Integer(1)),
+            ]))
+        };
+
+        let f = function(Object::Dictionary(dict(vec![
+            ("FunctionType", Object::Integer(3)),
+            ("Domain", numbers(&[0.0, 2.0])),
+            (
+                "Functions",
+                Object::Array(vec![exponential(0.0, 1.0), exponential(1.0, 0.0)]),
+            ),
+            ("Bounds", numbers(&[1.0])),
+            ("Encode", numbers(&[0.0, 1.0, 0.0, 1.0])),
+            ("Range", numbers(&[0.0, 0.5])),
+        ])));
+
+        assert_eq!(f.evaluate(&[0.5]), vec![0.5]);
+        assert_eq!(f.evaluate(&[0.75]), vec![0.5]);
+        assert_eq!(f.evaluate(&[1.5]), vec![0.5]);
+        assert_eq!(f.evaluate(&[1.75]), vec![0.25]);
+        assert_eq!(f.evaluate(&[-1.0]), vec![0.0]);
+    }
+}
@@ -138,10 +138,18 @@ impl PostScriptFunctionLexer {
         Self { buffer, cursor: 0 }
     }
 
-    fn skip_whitespace(&mut self) {
+    fn skip_whitespace_and_comments(&mut self) {
         while let Some(b) = self.peek_byte() {
-            if b.is_ascii_whitespace() {
-                self.next_byte();
+            match b {
+                b'%' => while !matches!(self.next_byte(), Some(b'\n' | b'\r') | None) {},
+                // PostScript also treats NUL as whitespace
+                b'\0' => {
+                    self.next_byte();
+                }
+                _ if b.is_ascii_whitespace() => {
+                    self.next_byte();
+                }
+                _ => break,
             }
         }
     }
@@ -169,7 +177,7 @@ impl PostScriptFunctionLexer {
             self.next_byte();
         }
 
-        ident_token_from_bytes(&self.buffer[start..=self.cursor])
+        ident_token_from_bytes(&self.buffer[start..self.cursor])
     }
 
     fn lex_whole_number(&mut self) {
@@ -193,45 +201,71 @@ impl PostScriptFunctionLexer {
 
     fn lex_number(&mut self) -> PostScriptResult<PostScriptFunctionToken> {
         let start = self.cursor;
-        self.consume_if_next_byte_is(b'-');
+        let mut is_real = false;
+
+        if !self.consume_if_next_byte_is(b'-') {
+            self.consume_if_next_byte_is(b'+');
+        }
+
         self.lex_whole_number();
 
         if self.consume_if_next_byte_is(b'.') {
+            is_real = true;
             self.lex_whole_number();
+        }
+
+        if self.consume_if_next_byte_is(b'e') || self.consume_if_next_byte_is(b'E') {
+            is_real = true;
 
-            return Ok(PostScriptFunctionToken::Real(
-                std::str::from_utf8(&self.buffer[start..self.cursor])?.parse::<f32>()?,
-            ));
+            if !self.consume_if_next_byte_is(b'-') {
+                self.consume_if_next_byte_is(b'+');
+            }
+
+            self.lex_whole_number();
+        }
+
+        let number = std::str::from_utf8(&self.buffer[start..self.cursor])?;
+
+        if !is_real {
+            // integers that overflow are converted to reals
+            if let Ok(n) = number.parse::<i32>() {
+                return Ok(PostScriptFunctionToken::Integer(n));
+            }
         }
 
-        Ok(PostScriptFunctionToken::Integer(parse_integer(
-            &self.buffer[start..self.cursor],
-        )))
+        match number.parse::<f32>() {
+            Ok(n) => Ok(PostScriptFunctionToken::Real(n)),
+            Err(..) => anyhow::bail!(PostScriptError::ParseError(Cow::Owned(format!(
+                "invalid number: {:?}",
+                number
+            )))),
+        }
     }
 
     fn next_token(&mut self) -> Option<PostScriptResult<PostScriptFunctionToken>> {
+        self.skip_whitespace_and_comments();
+
         Some(match self.peek_byte()? {
-            b'0'..=b'9' | b'-' | b'.' => self.lex_number(),
+            b'0'..=b'9' | b'-' | b'+' | b'.' => self.lex_number(),
             b'a'..=b'z' | b'A'..=b'Z' => self.lex_ident(),
-            b'{' => Ok(PostScriptFunctionToken::OpenCurlyBrace),
-            b'}' => Ok(PostScriptFunctionToken::CloseCurlyBrace),
-            b => todo!("unexpected token start {:?}", b),
+            b'{' => {
+                self.next_byte();
+                Ok(PostScriptFunctionToken::OpenCurlyBrace)
+            }
+            b'}' => {
+                self.next_byte();
+                Ok(PostScriptFunctionToken::CloseCurlyBrace)
+            }
+            b => {
+                self.next_byte();
+                Err(anyhow::anyhow!(PostScriptError::ParseError(Cow::Owned(
+                    format!("unexpected byte: {:?}", b as char)
+                ))))
+            }
         })
     }
 }
 
-// todo: overflow
-fn parse_integer(bytes: &[u8]) -> i32 {
-    let mut n = 0;
-
-    for b in bytes {
-        n *= 10;
-        n += *b as i32;
-    }
-
-    n
-}
-
 impl Iterator for PostScriptFunctionLexer {
     type Item = PostScriptResult<PostScriptFunctionToken>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -1,23 +1,37 @@
 use crate::{error::PdfResult, filter::decode_stream, stream::Stream, Resolve};
 
-use self::lexer::PostScriptFunctionLexer;
+use self::{lexer::PostScriptFunctionLexer, program::Program};
 
 mod lexer;
+mod program;
 
 /// A type 4 function, also called a PostScript calculator function, shall be
 /// represented as a stream containing code written in a small subset of the PostScript language
 #[derive(Debug, Clone)]
 pub struct PostScriptCalculatorFunction {
-    // todo: probably want to actually store the AST instead of just the lexer
-    tokens: PostScriptFunctionLexer,
+    program: Program,
 }
 
 impl PostScriptCalculatorFunction {
     pub fn from_stream<'a>(stream: Stream<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
         let buffer = decode_stream(&stream.stream, &stream.dict, resolver)?;
 
-        Ok(Self {
-            tokens: PostScriptFunctionLexer::new(buffer.into_owned().into_boxed_slice()),
-        })
+        let program = Program::compile(PostScriptFunctionLexer::new(
+            buffer.into_owned().into_boxed_slice(),
+        ))?;
+
+        Ok(Self { program })
+    }
+
+    /// Run the function, producing one output for each interval of `range`
+    ///
+    /// Errors while running the program, such as a stack underflow or a division by zero,
+    /// produce the lower bound of each interval instead
+    pub fn evaluate(&self, input: &[f32], range: &[f32]) -> Vec<f32> {
+        let num_outputs = range.len() / 2;
+
+        self.program
+            .execute(input, num_outputs)
+            .unwrap_or_else(|_| range.iter().step_by(2).copied().collect())
     }
 }
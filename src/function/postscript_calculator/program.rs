@@ -0,0 +1,548 @@
+use std::borrow::Cow;
+
+use crate::postscript::{PostScriptError, PostScriptResult};
+
+use super::lexer::{PostScriptFunctionLexer, PostScriptFunctionOperator, PostScriptFunctionToken};
+
+/// The maximum depth of the operand stack. Conforming readers need only support 100 entries
+const MAX_STACK_DEPTH: usize = 100;
+
+/// The maximum nesting of procedures, to avoid overflowing our own stack on malicious input
+const MAX_PROCEDURE_DEPTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Integer(i32),
+    Real(f32),
+    Boolean(bool),
+}
+
+impl Value {
+    fn as_number(self) -> PostScriptResult<f32> {
+        match self {
+            Value::Integer(i) => Ok(i as f32),
+            Value::Real(r) => Ok(r),
+            Value::Boolean(..) => anyhow::bail!(PostScriptError::TypeCheck),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    Push(Value),
+    Operator(PostScriptFunctionOperator),
+
+    /// A procedure followed by the `if` operator
+    If(Vec<Instruction>),
+
+    /// Two procedures followed by the `ifelse` operator
+    IfElse(Vec<Instruction>, Vec<Instruction>),
+}
+
+/// A type 4 function compiled from its PostScript source. The only procedures allowed in
+/// the calculator subset are the operands to `if` and `ifelse`, so these are resolved into
+/// conditional instructions at compile time
+#[derive(Debug, Clone)]
+pub(crate) struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn compile(mut lexer: PostScriptFunctionLexer) -> PostScriptResult<Self> {
+        match lexer.next().transpose()? {
+            Some(PostScriptFunctionToken::OpenCurlyBrace) => {}
+            _ => anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                "type 4 function must be enclosed in braces"
+            ))),
+        }
+
+        let instructions = Self::compile_procedure(&mut lexer, 0)?;
+
+        if lexer.next().is_some() {
+            anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                "unexpected tokens after type 4 function"
+            )));
+        }
+
+        Ok(Self { instructions })
+    }
+
+    /// Compile the body of a procedure, after its opening brace, up to and including its
+    /// closing brace
+    fn compile_procedure(
+        lexer: &mut PostScriptFunctionLexer,
+        depth: usize,
+    ) -> PostScriptResult<Vec<Instruction>> {
+        if depth > MAX_PROCEDURE_DEPTH {
+            anyhow::bail!(PostScriptError::ExecStackOverflow);
+        }
+
+        let mut instructions = Vec::new();
+
+        loop {
+            let token = match lexer.next().transpose()? {
+                Some(token) => token,
+                None => anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                    "unterminated procedure"
+                ))),
+            };
+
+            instructions.push(match token {
+                PostScriptFunctionToken::CloseCurlyBrace => return Ok(instructions),
+                PostScriptFunctionToken::Integer(i) => Instruction::Push(Value::Integer(i)),
+                PostScriptFunctionToken::Real(r) => Instruction::Push(Value::Real(r)),
+                PostScriptFunctionToken::Operator(PostScriptFunctionOperator::True) => {
+                    Instruction::Push(Value::Boolean(true))
+                }
+                PostScriptFunctionToken::Operator(PostScriptFunctionOperator::False) => {
+                    Instruction::Push(Value::Boolean(false))
+                }
+                PostScriptFunctionToken::Operator(
+                    PostScriptFunctionOperator::If | PostScriptFunctionOperator::Ifelse,
+                ) => anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                    "conditional operator must directly follow its procedures"
+                ))),
+                PostScriptFunctionToken::Operator(op) => Instruction::Operator(op),
+                PostScriptFunctionToken::OpenCurlyBrace => {
+                    Self::compile_conditional(lexer, depth + 1)?
+                }
+            });
+        }
+    }
+
+    /// Compile a procedure, after its opening brace, and the `if` or `ifelse` that must
+    /// follow it
+    fn compile_conditional(
+        lexer: &mut PostScriptFunctionLexer,
+        depth: usize,
+    ) -> PostScriptResult<Instruction> {
+        let first = Self::compile_procedure(lexer, depth)?;
+
+        match lexer.next().transpose()? {
+            Some(PostScriptFunctionToken::Operator(PostScriptFunctionOperator::If)) => {
+                Ok(Instruction::If(first))
+            }
+            Some(PostScriptFunctionToken::OpenCurlyBrace) => {
+                let second = Self::compile_procedure(lexer, depth)?;
+
+                match lexer.next().transpose()? {
+                    Some(PostScriptFunctionToken::Operator(PostScriptFunctionOperator::Ifelse)) => {
+                        Ok(Instruction::IfElse(first, second))
+                    }
+                    _ => anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                        "expected ifelse after two procedures"
+                    ))),
+                }
+            }
+            _ => anyhow::bail!(PostScriptError::ParseError(Cow::Borrowed(
+                "expected if or ifelse after procedure"
+            ))),
+        }
+    }
+
+    /// Run the program with `input` on the operand stack, returning the `num_outputs` values
+    /// left on top of the stack
+    pub fn execute(&self, input: &[f32], num_outputs: usize) -> PostScriptResult<Vec<f32>> {
+        let mut machine = Machine {
+            stack: Vec::with_capacity(MAX_STACK_DEPTH),
+        };
+
+        for &x in input {
+            machine.push(Value::Real(x))?;
+        }
+
+        machine.run(&self.instructions)?;
+
+        if machine.stack.len() < num_outputs {
+            anyhow::bail!(PostScriptError::StackUnderflow);
+        }
+
+        machine.stack[machine.stack.len() - num_outputs..]
+            .iter()
+            .map(|value| value.as_number())
+            .collect()
+    }
+}
+
+struct Machine {
+    stack: Vec<Value>,
+}
+
+impl Machine {
+    fn push(&mut self, value: Value) -> PostScriptResult<()> {
+        if self.stack.len() >= MAX_STACK_DEPTH {
+            anyhow::bail!(PostScriptError::StackOverflow);
+        }
+
+        self.stack.push(value);
+
+        Ok(())
+    }
+
+    fn push_real(&mut self, r: f32) -> PostScriptResult<()> {
+        self.push(Value::Real(r))
+    }
+
+    fn pop(&mut self) -> PostScriptResult<Value> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => anyhow::bail!(PostScriptError::StackUnderflow),
+        }
+    }
+
+    fn pop_number(&mut self) -> PostScriptResult<f32> {
+        self.pop()?.as_number()
+    }
+
+    fn pop_integer(&mut self) -> PostScriptResult<i32> {
+        match self.pop()? {
+            Value::Integer(i) => Ok(i),
+            _ => anyhow::bail!(PostScriptError::TypeCheck),
+        }
+    }
+
+    fn pop_bool(&mut self) -> PostScriptResult<bool> {
+        match self.pop()? {
+            Value::Boolean(b) => Ok(b),
+            _ => anyhow::bail!(PostScriptError::TypeCheck),
+        }
+    }
+
+    /// Pop the count operand of `copy`, `index` or `roll`, which must be between 0 and the
+    /// depth of the stack
+    fn pop_count(&mut self) -> PostScriptResult<usize> {
+        let n = self.pop_integer()?;
+
+        if n < 0 || n as usize > self.stack.len() {
+            anyhow::bail!(PostScriptError::RangeCheck);
+        }
+
+        Ok(n as usize)
+    }
+
+    /// Apply an arithmetic operator, producing an integer if both operands are integers and
+    /// the result fits, or a real otherwise
+    fn arithmetic(
+        &mut self,
+        integer: fn(i32, i32) -> Option<i32>,
+        real: fn(f32, f32) -> f32,
+    ) -> PostScriptResult<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+
+        if let (Value::Integer(a), Value::Integer(b)) = (a, b) {
+            if let Some(n) = integer(a, b) {
+                return self.push(Value::Integer(n));
+            }
+        }
+
+        self.push_real(real(a.as_number()?, b.as_number()?))
+    }
+
+    /// Apply a rounding operator, which leaves integers unchanged
+    fn round(&mut self, round: fn(f32) -> f32) -> PostScriptResult<()> {
+        match self.pop()? {
+            Value::Integer(i) => self.push(Value::Integer(i)),
+            Value::Real(r) => self.push_real(round(r)),
+            Value::Boolean(..) => anyhow::bail!(PostScriptError::TypeCheck),
+        }
+    }
+
+    fn integer_division(&mut self, op: fn(i32, i32) -> Option<i32>) -> PostScriptResult<()> {
+        let b = self.pop_integer()?;
+        let a = self.pop_integer()?;
+
+        if b == 0 {
+            anyhow::bail!(PostScriptError::RangeCheck);
+        }
+
+        match op(a, b) {
+            Some(n) => self.push(Value::Integer(n)),
+            None => anyhow::bail!(PostScriptError::RangeCheck),
+        }
+    }
+
+    /// Apply a logical operator to two booleans, or a bitwise operator to two integers
+    fn logical(
+        &mut self,
+        boolean: fn(bool, bool) -> bool,
+        integer: fn(i32, i32) -> i32,
+    ) -> PostScriptResult<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+
+        match (a, b) {
+            (Value::Boolean(a), Value::Boolean(b)) => self.push(Value::Boolean(boolean(a, b))),
+            (Value::Integer(a), Value::Integer(b)) => self.push(Value::Integer(integer(a, b))),
+            _ => anyhow::bail!(PostScriptError::TypeCheck),
+        }
+    }
+
+    fn compare(&mut self, cmp: fn(f32, f32) -> bool) -> PostScriptResult<()> {
+        let b = self.pop_number()?;
+        let a = self.pop_number()?;
+
+        self.push(Value::Boolean(cmp(a, b)))
+    }
+
+    fn equal(&mut self) -> PostScriptResult<bool> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+
+        Ok(match (a, b) {
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Boolean(..), _) | (_, Value::Boolean(..)) => false,
+            (a, b) => a.as_number()? == b.as_number()?,
+        })
+    }
+
+    fn run(&mut self, instructions: &[Instruction]) -> PostScriptResult<()> {
+        for instruction in instructions {
+            match instruction {
+                Instruction::Push(value) => self.push(*value)?,
+                Instruction::Operator(op) => self.execute_operator(*op)?,
+                Instruction::If(procedure) => {
+                    if self.pop_bool()? {
+                        self.run(procedure)?;
+                    }
+                }
+                Instruction::IfElse(if_true, if_false) => {
+                    if self.pop_bool()? {
+                        self.run(if_true)?;
+                    } else {
+                        self.run(if_false)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_operator(&mut self, op: PostScriptFunctionOperator) -> PostScriptResult<()> {
+        match op {
+            PostScriptFunctionOperator::Abs => match self.pop()? {
+                Value::Integer(i) => match i.checked_abs() {
+                    Some(i) => self.push(Value::Integer(i)),
+                    None => self.push_real((i as f32).abs()),
+                },
+                value => self.push_real(value.as_number()?.abs()),
+            },
+            PostScriptFunctionOperator::Add => self.arithmetic(i32::checked_add, |a, b| a + b),
+            PostScriptFunctionOperator::Sub => self.arithmetic(i32::checked_sub, |a, b| a - b),
+            PostScriptFunctionOperator::Mul => self.arithmetic(i32::checked_mul, |a, b| a * b),
+            PostScriptFunctionOperator::Div => {
+                let b = self.pop_number()?;
+                let a = self.pop_number()?;
+
+                if b == 0.0 {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push_real(a / b)
+            }
+            PostScriptFunctionOperator::Idiv => self.integer_division(i32::checked_div),
+            PostScriptFunctionOperator::Mod => self.integer_division(i32::checked_rem),
+            PostScriptFunctionOperator::Neg => match self.pop()? {
+                Value::Integer(i) => match i.checked_neg() {
+                    Some(i) => self.push(Value::Integer(i)),
+                    None => self.push_real(-(i as f32)),
+                },
+                value => self.push_real(-value.as_number()?),
+            },
+            PostScriptFunctionOperator::Atan => {
+                let den = self.pop_number()?;
+                let num = self.pop_number()?;
+
+                if num == 0.0 && den == 0.0 {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                // the angle in degrees, between 0 and 360
+                let angle = num.atan2(den).to_degrees();
+
+                self.push_real(if angle < 0.0 { angle + 360.0 } else { angle })
+            }
+            PostScriptFunctionOperator::Cos => {
+                let angle = self.pop_number()?;
+                self.push_real(angle.to_radians().cos())
+            }
+            PostScriptFunctionOperator::Sin => {
+                let angle = self.pop_number()?;
+                self.push_real(angle.to_radians().sin())
+            }
+            PostScriptFunctionOperator::Exp => {
+                let exponent = self.pop_number()?;
+                let base = self.pop_number()?;
+
+                let n = base.powf(exponent);
+
+                if !n.is_finite() {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push_real(n)
+            }
+            PostScriptFunctionOperator::Ln | PostScriptFunctionOperator::Log => {
+                let n = self.pop_number()?;
+
+                if n <= 0.0 {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push_real(if op == PostScriptFunctionOperator::Ln {
+                    n.ln()
+                } else {
+                    n.log10()
+                })
+            }
+            PostScriptFunctionOperator::Sqrt => {
+                let n = self.pop_number()?;
+
+                if n < 0.0 {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push_real(n.sqrt())
+            }
+            PostScriptFunctionOperator::Ceiling => self.round(f32::ceil),
+            PostScriptFunctionOperator::Floor => self.round(f32::floor),
+            // PostScript rounds halves up, rather than away from zero
+            PostScriptFunctionOperator::Round => self.round(|n| (n + 0.5).floor()),
+            PostScriptFunctionOperator::Truncate => self.round(f32::trunc),
+            PostScriptFunctionOperator::Cvi => {
+                let n = self.pop_number()?.trunc();
+
+                if n < i32::MIN as f32 || n > i32::MAX as f32 || n.is_nan() {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push(Value::Integer(n as i32))
+            }
+            PostScriptFunctionOperator::Cvr => {
+                let n = self.pop_number()?;
+                self.push_real(n)
+            }
+
+            PostScriptFunctionOperator::And => self.logical(|a, b| a && b, |a, b| a & b),
+            PostScriptFunctionOperator::Or => self.logical(|a, b| a || b, |a, b| a | b),
+            PostScriptFunctionOperator::Xor => self.logical(|a, b| a ^ b, |a, b| a ^ b),
+            PostScriptFunctionOperator::Not => match self.pop()? {
+                Value::Boolean(b) => self.push(Value::Boolean(!b)),
+                Value::Integer(i) => self.push(Value::Integer(!i)),
+                Value::Real(..) => anyhow::bail!(PostScriptError::TypeCheck),
+            },
+            PostScriptFunctionOperator::Bitshift => {
+                let shift = self.pop_integer()?;
+                let n = self.pop_integer()? as u32;
+
+                // positive shifts are to the left, and negative to the right. Bits shifted
+                // out are lost, and the shift is logical rather than arithmetic
+                let n = if shift >= 0 {
+                    n.checked_shl(shift as u32).unwrap_or(0)
+                } else {
+                    n.checked_shr(shift.unsigned_abs()).unwrap_or(0)
+                };
+
+                self.push(Value::Integer(n as i32))
+            }
+            PostScriptFunctionOperator::Eq => {
+                let eq = self.equal()?;
+                self.push(Value::Boolean(eq))
+            }
+            PostScriptFunctionOperator::Ne => {
+                let eq = self.equal()?;
+                self.push(Value::Boolean(!eq))
+            }
+            PostScriptFunctionOperator::Ge => self.compare(|a, b| a >= b),
+            PostScriptFunctionOperator::Gt => self.compare(|a, b| a > b),
+            PostScriptFunctionOperator::Le => self.compare(|a, b| a <= b),
+            PostScriptFunctionOperator::Lt => self.compare(|a, b| a < b),
+            PostScriptFunctionOperator::True => self.push(Value::Boolean(true)),
+            PostScriptFunctionOperator::False => self.push(Value::Boolean(false)),
+
+            // resolved at compile time
+            PostScriptFunctionOperator::If | PostScriptFunctionOperator::Ifelse => {
+                anyhow::bail!(PostScriptError::TypeCheck)
+            }
+
+            PostScriptFunctionOperator::Copy => {
+                let n = self.pop_count()?;
+
+                for i in self.stack.len() - n..self.stack.len() {
+                    self.push(self.stack[i])?;
+                }
+
+                Ok(())
+            }
+            PostScriptFunctionOperator::Dup => {
+                let value = self.pop()?;
+
+                self.push(value)?;
+                self.push(value)
+            }
+            PostScriptFunctionOperator::Exch => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+
+                self.push(b)?;
+                self.push(a)
+            }
+            PostScriptFunctionOperator::Index => {
+                let n = self.pop_count()?;
+
+                if n >= self.stack.len() {
+                    anyhow::bail!(PostScriptError::RangeCheck);
+                }
+
+                self.push(self.stack[self.stack.len() - 1 - n])
+            }
+            PostScriptFunctionOperator::Pop => self.pop().map(drop),
+            PostScriptFunctionOperator::Roll => {
+                let j = self.pop_integer()?;
+                let n = self.pop_count()?;
+
+                if n == 0 {
+                    return Ok(());
+                }
+
+                // a positive `j` rolls the top `n` elements towards the top of the stack
+                let len = self.stack.len();
+                let shift = j.rem_euclid(n as i32) as usize;
+
+                self.stack[len - n..].rotate_right(shift);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::lexer::PostScriptFunctionLexer, Program};
+
+    fn execute(source: &[u8], input: &[f32], num_outputs: usize) -> Vec<f32> {
+        let lexer = PostScriptFunctionLexer::new(source.to_vec().into_boxed_slice());
+
+        Program::compile(lexer)
+            .unwrap()
+            .execute(input, num_outputs)
+            .unwrap()
+    }
+
+    #[test]
+    fn calculator_conditionals_and_stack_operators() {
+        // clamp to [0.25 0.75], then compute both x and 1 - x
+        let source = b"{ dup 0.25 lt { pop 0.25 } { dup 0.75 gt { pop 0.75 } if } ifelse
+                         dup 1 exch sub }";
+
+        assert_eq!(execute(source, &[0.1], 2), vec![0.25, 0.75]);
+        assert_eq!(execute(source, &[0.5], 2), vec![0.5, 0.5]);
+        assert_eq!(execute(source, &[0.9], 2), vec![0.75, 0.25]);
+
+        assert_eq!(execute(b"{ 1 2 3 3 1 roll }", &[], 3), vec![3.0, 1.0, 2.0]);
+        assert_eq!(execute(b"{ 7 2 idiv 7 2 mod }", &[], 2), vec![3.0, 1.0]);
+        assert_eq!(execute(b"{ 0 1 atan 90 sin }", &[], 2), vec![0.0, 1.0]);
+    }
+}
@@ -1,47 +1,228 @@
-use crate::stream::Stream;
+use crate::{
+    assert_empty, error::PdfResult, filter::decode_stream, objects::Object, stream::Stream,
+    FromObj, Resolve,
+};
+
+use super::interpolate;
+
+/// The largest number of inputs of a sampled function. Each evaluation interpolates between
+/// 2^m samples, so this keeps a single evaluation reasonably cheap
+const MAX_INPUTS: usize = 16;
 
 /// Type 0 functions use a sequence of sample values (contained in a stream) to provide an
 /// approximation for functions whose domains and ranges are bounded. The samples are organized
 /// as an m-dimensional table in which each entry has n components.
-#[derive(Debug, Clone, FromObj)]
+#[derive(Debug, Clone)]
 pub struct SampledFunction<'a> {
     /// An array of m positive integers that shall specify the number of samples in each
     /// input dimension of the sample table
-    #[field("Size")]
     size: Vec<u32>,
 
     /// The number of bits that shall represent each sample. (If the function has multiple
     /// output values, each one shall occupy BitsPerSample bits.)
-    #[field("BitsPerSample")]
     bits_per_sample: BitsPerSample,
 
     /// The order of interpolation between samples. Valid values shall be 1 and 3, specifying
     /// linear and cubic spline interpolation, respectively
     ///
     /// Default value: 1
-    #[field("Order", default = InterpolationOrder::default())]
     order: InterpolationOrder,
 
     /// An array of 2 * m numbers specifying the linear mapping of input values into the domain
     /// of the function's sample table.
     ///
     /// Default value: [0 (Size0 - 1) 0 (Size1 - 1) ...]
-    #[field(
-        "Encode",
-        default = size.iter().flat_map(|&i| vec![0.0, (i as f32) - 1.0]).collect()
-    )]
     encode: Vec<f32>,
 
     /// An array of 2 * n numbers specifying the linear mapping of sample values into the range
     /// appropriate for the function's output values
     ///
     /// Default value: same as the value of Range
-    // todo: doesn't have to be Option because of default
-    #[field("Decode")]
     decode: Option<Vec<f32>>,
 
-    #[field]
     stream: Stream<'a>,
+
+    /// The unpacked sample table, with the first input dimension varying fastest and each entry
+    /// holding n consecutive output values
+    samples: Vec<u32>,
+}
+
+impl<'a> FromObj<'a> for SampledFunction<'a> {
+    fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
+        let mut stream = resolver.assert_stream(obj)?;
+        let dict = &mut stream.dict.other;
+
+        let size: Vec<u32> = dict.expect("Size", resolver)?;
+
+        if size.is_empty() || size.len() > MAX_INPUTS {
+            anyhow::bail!("sampled function has {} inputs", size.len());
+        }
+
+        let bits_per_sample = dict.expect("BitsPerSample", resolver)?;
+        let order = dict.get("Order", resolver)?.unwrap_or_default();
+        let encode = dict
+            .get("Encode", resolver)?
+            .unwrap_or_else(|| size.iter().flat_map(|&i| [0.0, (i as f32) - 1.0]).collect());
+        let decode = dict.get("Decode", resolver)?;
+
+        assert_empty(dict.clone(), reference, resolver);
+
+        let samples = unpack_samples(
+            &decode_stream(&stream.stream, &stream.dict, resolver)?,
+            bits_per_sample,
+        );
+
+        Ok(Self {
+            size,
+            bits_per_sample,
+            order,
+            encode,
+            decode,
+            stream,
+            samples,
+        })
+    }
+}
+
+/// Split the sample stream into big-endian samples of `bits_per_sample` bits. Unlike images,
+/// rows of samples aren't padded to a byte boundary
+fn unpack_samples(data: &[u8], bits_per_sample: BitsPerSample) -> Vec<u32> {
+    let bits = bits_per_sample.bits();
+    let num_samples = data.len() * 8 / bits as usize;
+
+    let mut samples = Vec::with_capacity(num_samples);
+    let mut bit_offset = 0;
+
+    for _ in 0..num_samples {
+        let mut sample = 0_u32;
+        let mut remaining = bits;
+
+        while remaining > 0 {
+            let byte = data[bit_offset / 8];
+            let available = 8 - (bit_offset % 8) as u32;
+            let take = available.min(remaining);
+            let shift = available - take;
+            let bits = (byte as u32 >> shift) & ((1 << take) - 1);
+
+            sample = (sample << take) | bits;
+            remaining -= take;
+            bit_offset += take as usize;
+        }
+
+        samples.push(sample);
+    }
+
+    samples
+}
+
+impl<'a> SampledFunction<'a> {
+    /// The number of inputs, m
+    pub(super) fn num_inputs(&self) -> usize {
+        self.size.len()
+    }
+
+    /// Check that the stream holds a sample for each of the `num_outputs` outputs of every
+    /// entry in the sample table
+    pub(super) fn check_sample_table(&self, num_outputs: usize) -> PdfResult<()> {
+        if self.size.contains(&0) {
+            anyhow::bail!("sampled function has an input with no samples");
+        }
+
+        let table_len = self
+            .size
+            .iter()
+            .try_fold(num_outputs, |len, &size| len.checked_mul(size as usize));
+
+        match table_len {
+            Some(table_len) if table_len <= self.samples.len() => Ok(()),
+            Some(table_len) => anyhow::bail!(
+                "sampled function has a table of {} samples, but its stream holds {}",
+                table_len,
+                self.samples.len()
+            ),
+            None => anyhow::bail!("sampled function has a table of {:?} entries", self.size),
+        }
+    }
+
+    pub fn evaluate(&self, input: &[f32], domain: &[f32], range: &[f32]) -> Vec<f32> {
+        let num_outputs = range.len() / 2;
+        let decode = self.decode.as_deref().unwrap_or(range);
+        let max_sample = (((1_u64 << self.bits_per_sample.bits()) - 1) as f32).max(1.0);
+
+        // todo: cubic spline interpolation for `InterpolationOrder::Cubic`. we fall back to
+        // linear interpolation for now
+
+        // for each input dimension, the index of the sample below the encoded input, and how far
+        // the input lies towards the next sample
+        let mut lower = Vec::with_capacity(input.len());
+        let mut fraction = Vec::with_capacity(input.len());
+
+        for (i, &x) in input.iter().enumerate() {
+            let size = self.size.get(i).copied().unwrap_or(1).max(1);
+            let max_index = (size - 1) as f32;
+
+            let e = interpolate(
+                x,
+                domain[i * 2],
+                domain[i * 2 + 1],
+                self.encode.get(i * 2).copied().unwrap_or(0.0),
+                self.encode.get(i * 2 + 1).copied().unwrap_or(max_index),
+            )
+            .clamp(0.0, max_index);
+
+            let floor = e.floor().min((size as f32 - 2.0).max(0.0));
+
+            lower.push(floor as usize);
+            fraction.push(e - floor);
+        }
+
+        let mut output = vec![0.0; num_outputs];
+
+        // interpolate between the 2^m samples surrounding the input
+        for corner in 0..(1_usize << input.len()) {
+            let mut weight = 1.0;
+            let mut index = 0;
+            let mut stride = 1;
+
+            for (i, (&lower, &fraction)) in lower.iter().zip(&fraction).enumerate() {
+                let size = self.size.get(i).copied().unwrap_or(1).max(1) as usize;
+                let upper = corner & (1 << i) != 0;
+
+                weight *= if upper { fraction } else { 1.0 - fraction };
+                index += (lower + usize::from(upper)).min(size - 1) * stride;
+                stride *= size;
+            }
+
+            if weight == 0.0 {
+                continue;
+            }
+
+            for (j, out) in output.iter_mut().enumerate() {
+                let sample = self
+                    .samples
+                    .get(index * num_outputs + j)
+                    .copied()
+                    .unwrap_or(0);
+
+                *out += weight * sample as f32;
+            }
+        }
+
+        output
+            .into_iter()
+            .enumerate()
+            .map(|(j, sample)| {
+                interpolate(
+                    sample,
+                    0.0,
+                    max_sample,
+                    decode.get(j * 2).copied().unwrap_or(0.0),
+                    decode.get(j * 2 + 1).copied().unwrap_or(1.0),
+                )
+            })
+            .collect()
+    }
 }
 
 #[pdf_enum(Integer)]
@@ -63,3 +244,9 @@ enum BitsPerSample {
     TwentyFour = 24,
     ThirtyTwo = 32,
 }
+
+impl BitsPerSample {
+    pub fn bits(self) -> u32 {
+        self.as_integer() as u32
+    }
+}
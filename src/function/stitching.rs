@@ -1,6 +1,6 @@
 use crate::{error::PdfResult, objects::Dictionary, Resolve};
 
-use super::Function;
+use super::{interpolate, Function};
 
 /// Type 3 functions (PDF 1.3) define a stitching of the subdomains of several 1-input functions to
 /// produce a single new 1-input function. Since the resulting stitching function is a 1-input function,
@@ -37,4 +37,31 @@ impl<'a> StitchingFunction<'a> {
             encode,
         })
     }
+
+    pub fn evaluate(&self, x: f32, domain: &[f32]) -> Vec<f32> {
+        // the subdomain `x` falls in. Each interval includes its lower bound, except for the last,
+        // which includes both
+        let k = self.bounds.iter().take_while(|&&bound| x >= bound).count();
+        let k = k.min(self.functions.len().saturating_sub(1));
+
+        let lower = if k == 0 {
+            domain[0]
+        } else {
+            self.bounds[k - 1]
+        };
+        let upper = self.bounds.get(k).copied().unwrap_or(domain[1]);
+
+        let x = interpolate(
+            x,
+            lower,
+            upper,
+            self.encode.get(k * 2).copied().unwrap_or(0.0),
+            self.encode.get(k * 2 + 1).copied().unwrap_or(1.0),
+        );
+
+        match self.functions.get(k) {
+            Some(function) => function.evaluate(&[x]),
+            None => Vec::new(),
+        }
+    }
 }
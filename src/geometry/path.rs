@@ -80,16 +80,6 @@ impl Path {
         }
     }
 
-    // todo: implement clipping
-    // maybe see:
-    //   * https://davis.wpi.edu/~matt/courses/clipping/
-    //   * https://en.wikipedia.org/wiki/Sutherland%E2%80%93Hodgman_algorithm
-    //   * https://en.wikipedia.org/wiki/Weiler%E2%80%93Atherton_clipping_algorithm
-    //   * https://en.wikipedia.org/wiki/Vatti_clipping_algorithm
-    //   * Martinez-Rueda (https://github.com/w8r/martinez)
-    //   * https://en.wikipedia.org/wiki/Greiner%E2%80%93Hormann_clipping_algorithm
-    pub fn clip(&mut self, _clipping_path: &Path) {}
-
     pub fn close_path(&mut self) {
         if self.start != self.current_point {
             self.line_to(self.start);
@@ -149,6 +149,15 @@ pub(crate) trait LexBase<'a> {
         }
     }
 
+    fn hex_byte_to_digit(b: u8) -> u8 {
+        match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            b'A'..=b'F' => b - b'A' + 10,
+            _ => todo!(),
+        }
+    }
+
     fn expect_bytes(&mut self, bytes: &[u8]) -> PdfResult<()> {
         for &b in bytes {
             self.expect_byte(b)?;
@@ -532,15 +541,6 @@ pub(crate) trait LexObject<'a>: LexBase<'a> {
         })
     }
 
-    fn hex_byte_to_digit(b: u8) -> u8 {
-        match b {
-            b'0'..=b'9' => b - b'0',
-            b'a'..=b'f' => b - b'a' + 10,
-            b'A'..=b'F' => b - b'A' + 10,
-            _ => todo!(),
-        }
-    }
-
     fn read_obj_prelude(&mut self) -> PdfResult<()> {
         self.skip_whitespace();
         self.lex_whole_number();
@@ -21,6 +21,7 @@ mod content;
 mod data_structures;
 mod date;
 mod destination;
+mod diagnostic;
 mod encryption;
 mod error;
 mod file_specification;
@@ -32,6 +33,7 @@ mod halftones;
 mod icc_profile;
 mod job_ticket;
 mod lex;
+mod object_cache;
 mod object_stream;
 mod objects;
 mod optional_content;
@@ -44,12 +46,22 @@ mod resources;
 mod shading;
 mod stream;
 mod structure;
+#[cfg(test)]
+pub(crate) mod test_util;
+mod text;
 mod trailer;
 mod viewer_preferences;
+mod writer;
 mod xobject;
 mod xref;
 
-use std::{borrow::Cow, cell::RefCell, collections::HashMap, io, rc::Rc};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::{self, Read, Seek, SeekFrom},
+    rc::Rc,
+};
 
 use encryption::SecurityHandler;
 
@@ -58,28 +70,59 @@ pub(crate) use crate::{objects::FromObj, resolve::Resolve};
 use crate::{
     annotation::Annotation,
     catalog::{DocumentCatalog, InformationDictionary},
-    error::ParseError,
+    file_specification::FileIdentifier,
     filter::decode_stream,
     lex::{LexBase, LexObject},
+    object_cache::ObjectCache,
     object_stream::{ObjectStream, ObjectStreamDict, ObjectStreamParser},
-    objects::{Dictionary, Object, Reference},
     page::{InheritablePageFields, PageNode, PageObject, PageTree, PageTreeNode},
+    render::font_cache::FontCache,
     stream::StreamDict,
+    text::TextExtractor,
     trailer::Trailer,
     xref::{ByteOffset, TrailerOrOffset, Xref, XrefParser},
 };
 
-pub use crate::{content::ContentLexer, error::PdfResult, render::Renderer};
+pub use crate::{
+    content::ContentLexer,
+    data_structures::Matrix,
+    diagnostic::{Diagnostic, ParseOptions},
+    encryption::{CryptFilterMethod, UserAccessPermissions},
+    error::{ParseError, PdfResult},
+    geometry::Point,
+    objects::{Dictionary, Object, Reference},
+    render::{shading::rasterize_mesh, text_state::TextRenderingMode, Renderer, RgbaImage},
+    shading::{Mesh, ShadingObject},
+    stream::Stream,
+    text::{Quad, SpanGlyph, TextSpan},
+    writer::{PdfWriter, XrefFormat},
+};
 
-/// Assert that the dictionary has no keys
+/// Assert that the dictionary has no keys, where `reference` is the indirect
+/// object it was read from, if any
 ///
 /// This is done during development to ensure there aren't silent bugs or missing
-/// features
+/// features. When parsing leniently, each key is reported as a diagnostic instead
 #[track_caller]
-pub(crate) fn assert_empty(dict: Dictionary) {
-    if !dict.is_empty() {
+pub(crate) fn assert_empty(
+    dict: Dictionary,
+    reference: Option<Reference>,
+    resolver: &mut dyn Resolve,
+) {
+    if dict.is_empty() {
+        return;
+    }
+
+    if !resolver.is_lenient() {
         todo!("dict not empty: {:#?}", dict);
     }
+
+    let mut keys = dict.entries().map(|(key, _)| key).collect::<Vec<_>>();
+    keys.sort();
+
+    for key in keys {
+        resolver.report(Diagnostic::new(reference, Some(&key), "unknown key"));
+    }
 }
 
 pub fn assert_len(arr: &[Object], len: usize) -> PdfResult<()> {
@@ -128,26 +171,170 @@ impl<'a> LexObject<'a> for Lexer<'a> {
     }
 }
 
+/// The largest number of indirect objects kept in the object cache
+const MAX_CACHED_OBJECTS: usize = 4096;
+
 pub struct Lexer<'a> {
-    file: Vec<u8>,
+    /// The whole file, shared with the cross-reference parser
+    file: Rc<Vec<u8>>,
     pos: usize,
     xref: Rc<Xref>,
     /// None if file isn't encrypted
     security_handler: Option<SecurityHandler<'a>>,
     cached_object_streams: HashMap<usize, ObjectStreamParser<'a>>,
+
+    /// The cross-reference table rebuilt by scanning the file, which is only
+    /// built once an object isn't found where the cross-reference table says
+    recovered_xref: Option<Rc<Xref>>,
+
+    options: ParseOptions,
+
+    /// Problems ignored while parsing leniently
+    diagnostics: Vec<Diagnostic>,
+
+    /// Indirect objects resolved so far, after decryption
+    object_cache: ObjectCache<'a>,
+
+    /// Font programs loaded from embedded font files
+    font_cache: FontCache<'a>,
+
+    /// The tiling patterns whose resources are being parsed, innermost last
+    parsing_patterns: Vec<Reference>,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(file: Vec<u8>, xref: Rc<Xref>) -> io::Result<Self> {
+    pub fn new(file: impl Into<Rc<Vec<u8>>>, xref: Rc<Xref>) -> io::Result<Self> {
         Ok(Self {
-            file,
+            file: file.into(),
             xref,
             pos: 0,
             security_handler: None,
             cached_object_streams: HashMap::new(),
+            recovered_xref: None,
+            options: ParseOptions::default(),
+            diagnostics: Vec::new(),
+            object_cache: ObjectCache::new(MAX_CACHED_OBJECTS),
+            font_cache: FontCache::new(),
+            parsing_patterns: Vec::new(),
         })
     }
 
+    /// Lex the indirect object `reference` from the file, decrypting it if the
+    /// document is encrypted
+    fn lex_uncached_object(&mut self, reference: Reference) -> PdfResult<Object<'a>> {
+        let init_pos = self.pos;
+
+        self.pos = match Rc::clone(&self.xref).get_offset(reference)? {
+            Some(ByteOffset::MainFile(p)) => p,
+            Some(ByteOffset::ObjectStream {
+                byte_offset,
+                stream_reference,
+                ..
+            }) => {
+                return self.lex_object_from_object_stream(
+                    byte_offset,
+                    stream_reference,
+                    reference,
+                );
+            }
+            None => return Ok(Object::Null),
+        };
+
+        let offset = self.pos;
+
+        let obj = match self.lex_object_at(offset, reference) {
+            Ok(obj) => obj,
+            Err(err) if !self.options.recover => return Err(err),
+            // the offset in the cross-reference table is wrong, so look for the
+            // object in the rest of the file
+            Err(err) => match self.recovered_offset(reference) {
+                Ok(Some(ByteOffset::MainFile(recovered))) if recovered != offset => {
+                    let obj = self.lex_object_at(recovered, reference)?;
+
+                    self.report_misplaced_object(reference, offset);
+
+                    obj
+                }
+                Ok(Some(ByteOffset::ObjectStream {
+                    byte_offset,
+                    stream_reference,
+                    ..
+                })) => {
+                    self.pos = init_pos;
+
+                    let obj = self.lex_object_from_object_stream(
+                        byte_offset,
+                        stream_reference,
+                        reference,
+                    )?;
+
+                    self.report_misplaced_object(reference, offset);
+
+                    return Ok(obj);
+                }
+                _ => return Err(err),
+            },
+        };
+
+        self.pos = init_pos;
+
+        match &self.security_handler {
+            Some(security_handler) => security_handler.decrypt_object(obj, reference),
+            None => Ok(obj),
+        }
+    }
+
+    /// Lex the indirect object `reference` at `offset`, failing if the object
+    /// there has a different object number
+    fn lex_object_at(&mut self, offset: usize, reference: Reference) -> PdfResult<Object<'a>> {
+        self.pos = offset;
+        self.skip_whitespace();
+
+        if self.lex_whole_number().parse::<usize>().ok() != Some(reference.object_number) {
+            anyhow::bail!(
+                "expected object {} at byte offset {}",
+                reference.object_number,
+                offset
+            );
+        }
+
+        self.pos = offset;
+        self.read_obj_prelude()?;
+
+        let obj = self.lex_object()?;
+
+        self.read_obj_trailer()?;
+
+        Ok(obj)
+    }
+
+    fn report_misplaced_object(&mut self, reference: Reference, offset: usize) {
+        self.report(Diagnostic::new(
+            Some(reference),
+            None,
+            format!(
+                "object not found at byte offset {} given by the cross-reference table, \
+                so it was found by scanning the file",
+                offset
+            ),
+        ));
+    }
+
+    /// Where the object `reference` was found by scanning the file
+    fn recovered_offset(&mut self, reference: Reference) -> PdfResult<Option<ByteOffset>> {
+        let xref = match &self.recovered_xref {
+            Some(xref) => Rc::clone(xref),
+            None => {
+                let (xref, _) = XrefParser::new(Rc::clone(&self.file)).recover()?;
+                let xref = Rc::new(xref);
+                self.recovered_xref = Some(Rc::clone(&xref));
+                xref
+            }
+        };
+
+        xref.get_offset(reference)
+    }
+
     fn lex_object_stream(&mut self, byte_offset: usize) -> PdfResult<ObjectStream<'a>> {
         self.pos = byte_offset;
         self.read_obj_prelude()?;
@@ -176,9 +363,35 @@ impl<'a> Lexer<'a> {
         Ok(trailer)
     }
 
+    /// The trailer dictionary of the cross-reference section at `start_xref`
+    /// exactly as it appears in the file, and whether the section is a
+    /// cross-reference stream, in which case the trailer is the dictionary of
+    /// the stream
+    fn lex_raw_trailer(&mut self, start_xref: usize) -> PdfResult<(Dictionary<'a>, bool)> {
+        self.pos = start_xref;
+
+        if !self.next_matches(b"xref") {
+            self.read_obj_prelude()?;
+
+            return Ok((self.lex_dict_ignore_stream()?, true));
+        }
+
+        let offset = self.file[start_xref..]
+            .windows(b"trailer".len())
+            .position(|window| window == b"trailer")
+            .ok_or(anyhow::anyhow!("failed to find trailer"))?;
+
+        self.pos = start_xref + offset;
+        self.expect_bytes(b"trailer")?;
+        self.skip_whitespace();
+
+        Ok((self.lex_dict_ignore_stream()?, false))
+    }
+
     fn lex_object_from_object_stream(
         &mut self,
         byte_offset: usize,
+        stream_reference: Reference,
         reference: Reference,
     ) -> PdfResult<Object<'a>> {
         let parser = match self.cached_object_streams.get_mut(&byte_offset) {
@@ -188,9 +401,7 @@ impl<'a> Lexer<'a> {
 
                 let stream = match &self.security_handler {
                     Some(security_handler) => {
-                        let stream =
-                            security_handler.decrypt_stream(stream.into_owned(), reference)?;
-                        Cow::Owned(stream)
+                        Cow::Owned(security_handler.decrypt_stream(&stream, stream_reference)?)
                     }
                     None => stream,
                 };
@@ -208,75 +419,62 @@ impl<'a> Lexer<'a> {
         parser.parse_object(reference)
     }
 
-    fn lex_page_tree(&mut self, xref: &Xref, root_reference: Reference) -> PdfResult<PageNode<'a>> {
-        if xref.get_offset(root_reference)?.is_none() {
+    /// The root of the page tree. Its descendants are loaded as they are needed
+    fn lex_page_tree(&mut self, root_reference: Reference) -> PdfResult<PageNode<'a>> {
+        if !self.reference_exists(root_reference)? {
             return Ok(PageNode::Root(Rc::new(RefCell::new(PageTree {
                 kids: Vec::new(),
-                pages: HashMap::new(),
                 count: 0,
                 inheritable_page_fields: InheritablePageFields::new(),
             }))));
         };
 
         let mut root_dict = self.assert_dict(Object::Reference(root_reference))?;
-        let count = root_dict.expect_integer("Count", self)? as usize;
-        let raw_kids = root_dict.expect_arr("Kids", self)?;
+        let count = usize::try_from(root_dict.expect_unsigned_integer("Count", self)?)?;
+        let kids = root_dict.expect_arr("Kids", self)?;
         let inheritable_page_fields = InheritablePageFields::from_dict(&mut root_dict, self)?;
 
         root_dict.expect_type("Pages", self, true)?;
 
-        assert_empty(root_dict);
+        assert_empty(root_dict, Some(root_reference), self);
 
-        let root = PageNode::Root(Rc::new(RefCell::new(PageTree {
+        Ok(PageNode::Root(Rc::new(RefCell::new(PageTree {
             count,
             inheritable_page_fields,
-            pages: HashMap::new(),
-            kids: Vec::new(),
-        })));
-
-        let mut pages = HashMap::new();
-
-        pages.insert(root_reference, root.clone());
-
-        let mut page_queue = raw_kids
-            .into_iter()
-            .map(assert_reference)
-            .collect::<PdfResult<Vec<Reference>>>()?;
-
-        while let Some(kid_ref) = page_queue.pop() {
-            let mut kid_dict = self.assert_dict(Object::Reference(kid_ref))?;
-
-            match kid_dict.expect_name("Type", self)?.as_ref() {
-                "Pages" => {
-                    self.lex_page_tree_node(kid_dict, kid_ref, &mut page_queue, &mut pages)?
-                }
-                "Page" => self.lex_page_object(kid_dict, kid_ref, &mut pages)?,
-                found => {
-                    anyhow::bail!(ParseError::MismatchedTypeKey {
-                        expected: "Page",
-                        found: found.to_owned(),
-                    })
-                }
-            };
-        }
+            kids: kids
+                .into_iter()
+                .map(assert_reference)
+                .collect::<PdfResult<Vec<Reference>>>()?,
+        }))))
+    }
 
-        match root.clone() {
-            PageNode::Root(root) => {
-                root.borrow_mut().pages = pages;
+    /// A page object or intermediate page tree node that is a child of `parent`
+    fn lex_page_node(
+        &mut self,
+        mut dict: Dictionary<'a>,
+        kid_ref: Reference,
+        parent: PageNode<'a>,
+    ) -> PdfResult<PageNode<'a>> {
+        match dict.expect_name("Type", self)?.as_ref() {
+            "Pages" => self.lex_page_tree_node(dict, parent),
+            "Page" => self.lex_page_object(dict, kid_ref, parent),
+            found => {
+                anyhow::bail!(ParseError::MismatchedTypeKey {
+                    expected: "Page",
+                    found: found.to_owned(),
+                })
             }
-            _ => unreachable!(),
         }
-
-        Ok(root)
     }
 
     fn lex_page_object(
         &mut self,
         mut dict: Dictionary<'a>,
         kid_ref: Reference,
-        pages: &mut HashMap<Reference, PageNode<'a>>,
-    ) -> PdfResult<()> {
-        let parent = dict.expect_reference("Parent")?;
+        parent: PageNode<'a>,
+    ) -> PdfResult<PageNode<'a>> {
+        // the parent is known from the path taken to reach the page
+        dict.expect_reference("Parent")?;
         let last_modified = dict.get("LastModified", self)?;
         let resources = dict.get("Resources", self)?;
         let media_box = dict.get("MediaBox", self)?;
@@ -306,12 +504,10 @@ impl<'a> Lexer<'a> {
         let user_unit = dict.get("UserUnit", self)?.unwrap_or(1.0);
         let vp = dict.get("VP", self)?;
 
-        assert_empty(dict);
+        assert_empty(dict, Some(kid_ref), self);
 
-        let parent = pages.get(&parent).unwrap().clone();
-
-        let this_node = PageNode::Leaf(Rc::new(PageObject {
-            parent: parent.clone(),
+        Ok(PageNode::Leaf(Rc::new(PageObject {
+            parent,
             last_modified,
             resources,
             media_box,
@@ -340,78 +536,44 @@ impl<'a> Lexer<'a> {
             pres_steps,
             user_unit,
             vp,
-        }));
-
-        pages.insert(kid_ref, this_node.clone());
-
-        match parent {
-            PageNode::Node(node) => node.borrow_mut().kids.push(this_node),
-            PageNode::Root(node) => node.borrow_mut().kids.push(this_node),
-            PageNode::Leaf(..) => todo!("unreachable"),
-        }
-
-        Ok(())
+        })))
     }
 
     fn lex_page_tree_node(
         &mut self,
         mut dict: Dictionary<'a>,
-        kid_ref: Reference,
-        page_queue: &mut Vec<Reference>,
-        pages: &mut HashMap<Reference, PageNode<'a>>,
-    ) -> PdfResult<()> {
+        parent: PageNode<'a>,
+    ) -> PdfResult<PageNode<'a>> {
         let kids = dict.expect_arr("Kids", self)?;
-        let parent = dict.expect_reference("Parent")?;
-        let count = dict.expect_integer("Count", self)? as usize;
+        dict.expect_reference("Parent")?;
+        let count = usize::try_from(dict.expect_unsigned_integer("Count", self)?)?;
         let inheritable_page_fields = InheritablePageFields::from_dict(&mut dict, self)?;
 
-        let parent = pages.get(&parent).unwrap().clone();
-
-        let this_node = PageNode::Node(Rc::new(RefCell::new(PageTreeNode {
+        Ok(PageNode::Node(Rc::new(RefCell::new(PageTreeNode {
             count,
             inheritable_page_fields,
-            kids: Vec::new(),
-            parent: parent.clone(),
-        })));
-
-        match parent {
-            PageNode::Node(node) => node.borrow_mut().kids.push(this_node.clone()),
-            PageNode::Root(node) => node.borrow_mut().kids.push(this_node.clone()),
-            PageNode::Leaf(..) => todo!("unreachable"),
-        }
-
-        pages.insert(kid_ref, this_node);
-
-        page_queue.append(
-            &mut kids
+            kids: kids
                 .into_iter()
                 .map(assert_reference)
                 .collect::<PdfResult<Vec<Reference>>>()?,
-        );
-
-        Ok(())
+            parent,
+        }))))
     }
 }
 
 impl<'a> Resolve<'a> for Lexer<'a> {
     fn lex_object_from_reference(&mut self, reference: Reference) -> PdfResult<Object<'a>> {
-        let init_pos = self.pos;
-
-        self.pos = match Rc::clone(&self.xref).get_offset(reference)? {
-            Some(ByteOffset::MainFile(p)) => p,
-            Some(ByteOffset::ObjectStream { byte_offset, .. }) => {
-                return self.lex_object_from_object_stream(byte_offset, reference);
-            }
-            None => return Ok(Object::Null),
-        };
-
-        self.read_obj_prelude()?;
+        if let Some(obj) = self.object_cache.get(reference) {
+            return Ok(obj.clone());
+        }
 
-        let obj = self.lex_object()?;
+        let obj = self.lex_uncached_object(reference)?;
 
-        self.read_obj_trailer()?;
-
-        self.pos = init_pos;
+        // streams read from the file borrow their data, so are cheap to share,
+        // but decrypted streams hold a copy of theirs
+        if !matches!(&obj, Object::Stream(stream) if matches!(stream.stream, Cow::Owned(..))) {
+            self.object_cache.insert(reference, obj.clone());
+        }
 
         Ok(obj)
     }
@@ -419,32 +581,162 @@ impl<'a> Resolve<'a> for Lexer<'a> {
     fn reference_exists(&mut self, reference: Reference) -> PdfResult<bool> {
         Ok(self.xref.get_offset(reference)?.is_some())
     }
+
+    fn is_lenient(&self) -> bool {
+        self.options.lenient
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn font_cache(&mut self) -> Option<&mut FontCache<'a>> {
+        Some(&mut self.font_cache)
+    }
+
+    fn parsing_patterns(&mut self) -> Option<&mut Vec<Reference>> {
+        Some(&mut self.parsing_patterns)
+    }
 }
 
 pub struct Parser<'a> {
     pub lexer: Lexer<'a>,
     xref: Rc<Xref>,
     trailer: Trailer<'a>,
+
+    /// The byte offset of the last cross-reference section in the file, or
+    /// `None` if the file was damaged and its objects were found by scanning
+    start_xref: Option<usize>,
+
     catalog: DocumentCatalog<'a>,
+
+    /// The root of the page tree
     page_tree: PageNode<'a>,
+
+    /// The descendants of the root of the page tree seen so far
+    page_nodes: HashMap<Reference, SeenPageNode<'a>>,
+}
+
+/// A descendant of the root of the page tree, as far as it has been loaded
+enum SeenPageNode<'a> {
+    /// A page passed over while looking up a later one, which is only known to
+    /// be a single page
+    Skipped,
+    Loaded(PageNode<'a>),
 }
 
 impl<'a> Parser<'a> {
     pub fn new(p: impl AsRef<std::path::Path>) -> PdfResult<Self> {
-        let file = std::fs::read(p)?;
+        Self::open_with_password(p, "")
+    }
+
+    /// Open a document encrypted with a user or owner password. Fails with
+    /// [`ParseError::InvalidPassword`] if the password is neither
+    ///
+    /// Documents that aren't encrypted are opened regardless of the password
+    pub fn open_with_password(p: impl AsRef<std::path::Path>, password: &str) -> PdfResult<Self> {
+        Self::from_bytes_with_password(std::fs::read(p)?, password)
+    }
+
+    /// Open a document, which may be encrypted, with the given options. See
+    /// [`Parser::open_with_password`]
+    pub fn open_with_options(
+        p: impl AsRef<std::path::Path>,
+        password: &str,
+        options: ParseOptions,
+    ) -> PdfResult<Self> {
+        Self::from_bytes_with_options(std::fs::read(p)?, password, options)
+    }
+
+    /// Open a document held in memory
+    pub fn from_bytes(file: Vec<u8>) -> PdfResult<Self> {
+        Self::from_bytes_with_password(file, "")
+    }
+
+    /// Open a document read in full from `reader`, starting from its beginning
+    pub fn from_reader(mut reader: impl Read + Seek) -> PdfResult<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+
+        let mut file = Vec::with_capacity(usize::try_from(len)?);
+        reader.read_to_end(&mut file)?;
+
+        Self::from_bytes(file)
+    }
+
+    /// Open a document held in memory, which is encrypted with a user or owner
+    /// password. See [`Parser::open_with_password`]
+    ///
+    /// If the cross-reference table is missing or damaged, it is rebuilt by
+    /// scanning the file for objects, and a diagnostic is reported. See
+    /// [`ParseOptions::recover`]
+    pub fn from_bytes_with_password(file: Vec<u8>, password: &str) -> PdfResult<Self> {
+        Self::from_bytes_with_options(file, password, ParseOptions::default())
+    }
+
+    /// Open a document held in memory, which may be encrypted, with the given
+    /// options. See [`Parser::from_bytes_with_password`]
+    pub fn from_bytes_with_options(
+        file: Vec<u8>,
+        password: &str,
+        options: ParseOptions,
+    ) -> PdfResult<Self> {
+        let file = Rc::new(file);
+
+        let is_invalid_password =
+            |err: &anyhow::Error| matches!(err.downcast_ref(), Some(ParseError::InvalidPassword));
+
+        match Self::load(Rc::clone(&file), password, options) {
+            Ok(parser) => Ok(parser),
+            Err(err) if is_invalid_password(&err) || !options.recover => Err(err),
+            Err(err) => match Self::load_recovered(file, password, options) {
+                Ok(mut parser) => {
+                    parser.lexer.report(Diagnostic::new(
+                        None,
+                        None,
+                        format!(
+                            "the cross-reference table was rebuilt by scanning the file: {}",
+                            err
+                        ),
+                    ));
+
+                    Ok(parser)
+                }
+                Err(recovery_err) if is_invalid_password(&recovery_err) => Err(recovery_err),
+                // report the original error, which is more useful than whatever
+                // went wrong during recovery
+                Err(..) => Err(err),
+            },
+        }
+    }
 
-        let mut xref_parser = XrefParser::new(file.clone());
-        let xref_and_trailer = xref_parser.read_xref()?;
-        let mut xref = Rc::new(xref_and_trailer.xref);
+    /// Open a document using its cross-reference table
+    fn load(file: Rc<Vec<u8>>, password: &str, options: ParseOptions) -> PdfResult<Self> {
+        let mut xref_parser = XrefParser::new(Rc::clone(&file));
+        let start_xref = xref_parser.start_xref()?;
+        let xref_and_trailer = xref_parser.parse_xref_at_offset(start_xref)?;
+        let xref = Rc::new(xref_and_trailer.xref);
         let mut lexer = Lexer::new(file, Rc::clone(&xref))?;
+        lexer.options = options;
 
         let trailer = match xref_and_trailer.trailer_or_offset {
             TrailerOrOffset::Offset(offset) => {
                 let trailer = lexer.lex_trailer(offset, false)?;
                 let mut xref = (*xref).clone();
 
+                // the offsets of the sections read so far, so that a `Prev` entry
+                // pointing back at one of them can't loop forever
+                let mut visited = HashSet::from([start_xref]);
+
                 let mut prev = trailer.prev;
                 while let Some(prev_offset) = prev {
+                    if !visited.insert(prev_offset) {
+                        anyhow::bail!(
+                            "cross-reference section at byte offset {} is read twice",
+                            prev_offset
+                        );
+                    }
+
                     let xref_and_trailer = xref_parser.parse_xref_at_offset(prev_offset)?;
 
                     xref.merge_with_previous(xref_and_trailer.xref);
@@ -465,30 +757,166 @@ impl<'a> Parser<'a> {
             TrailerOrOffset::Trailer(trailer) => trailer,
         };
 
-        xref = Rc::clone(&lexer.xref);
+        Self::with_trailer(lexer, trailer, Some(start_xref), password)
+    }
+
+    /// Open a damaged document, whose cross-reference table is rebuilt by
+    /// scanning the file
+    fn load_recovered(file: Rc<Vec<u8>>, password: &str, options: ParseOptions) -> PdfResult<Self> {
+        let (xref, trailer) = XrefParser::new(Rc::clone(&file)).recover()?;
+        let xref = Rc::new(xref);
+
+        let mut lexer = Lexer::new(file, Rc::clone(&xref))?;
+        lexer.options = options;
+        lexer.recovered_xref = Some(xref);
+
+        let trailer = Trailer::from_dict(trailer, false, &mut lexer)?;
+
+        Self::with_trailer(lexer, trailer, None, password)
+    }
+
+    fn with_trailer(
+        mut lexer: Lexer<'a>,
+        trailer: Trailer<'a>,
+        start_xref: Option<usize>,
+        password: &str,
+    ) -> PdfResult<Self> {
+        let xref = Rc::clone(&lexer.xref);
         lexer.security_handler = if let Some(encryption) = &trailer.encryption {
+            let reference = match encryption {
+                objects::TypedReference::Indirect { reference, .. } => Some(*reference),
+                objects::TypedReference::Direct(..) => None,
+            };
+
+            // the ID is required in encrypted documents, but some leave it out
+            let id = trailer
+                .id
+                .clone()
+                .unwrap_or_else(|| FileIdentifier([String::new(), String::new()]));
+
             let handler = SecurityHandler::new(
                 encryption.get_ref(&mut lexer)?.into_owned(),
-                trailer.id.clone().unwrap(),
-            );
+                reference,
+                id,
+                password,
+            )?;
             Some(handler)
         } else {
             None
         };
 
+        // objects resolved while reading the trailer weren't decrypted
+        lexer.object_cache.clear();
+
         let catalog = DocumentCatalog::from_obj(Object::Reference(trailer.root), &mut lexer)?;
 
-        let page_tree = lexer.lex_page_tree(&xref, catalog.pages)?;
+        let page_tree = lexer.lex_page_tree(catalog.pages)?;
 
         Ok(Self {
             lexer,
             xref,
             trailer,
+            start_xref,
             catalog,
             page_tree,
+            page_nodes: HashMap::new(),
         })
     }
 
+    /// The operations permitted to a user who isn't the owner, or `None` if the
+    /// document isn't encrypted
+    pub fn user_permission_flags(&self) -> Option<UserAccessPermissions> {
+        self.lexer
+            .security_handler
+            .as_ref()
+            .map(SecurityHandler::user_permission_flags)
+    }
+
+    /// Whether the document was opened with its owner password, which grants
+    /// every permission. Documents that aren't encrypted have no owner password,
+    /// and are always fully accessible
+    pub fn has_owner_access(&self) -> bool {
+        self.lexer
+            .security_handler
+            .as_ref()
+            .is_none_or(SecurityHandler::has_owner_access)
+    }
+
+    /// The problems ignored so far while parsing leniently, and the repairs
+    /// made to a damaged file, in the order they were found. Always empty
+    /// unless [`ParseOptions::lenient`] or [`ParseOptions::recover`] is set
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.lexer.diagnostics
+    }
+
+    /// The reference to the document catalog
+    pub fn root(&self) -> Reference {
+        self.trailer.root
+    }
+
+    /// The object referred to by `reference`, or `Object::Null` if it does not
+    /// exist
+    pub fn object(&mut self, reference: Reference) -> PdfResult<Object<'a>> {
+        self.lexer.lex_object_from_reference(reference)
+    }
+
+    /// Begin an incremental update of the document. Objects added to the
+    /// returned writer are written after the unmodified bytes of the original
+    /// file, followed by a new cross-reference section of the same kind as the
+    /// last one in the file. Existing objects are changed by adding an object
+    /// with the same reference
+    ///
+    /// Leaving the original bytes untouched keeps any digital signatures in the
+    /// document valid. The objects of an update to an encrypted document are
+    /// encrypted with the key of the password the document was opened with
+    pub fn incremental_update(&mut self) -> PdfResult<PdfWriter<'a>> {
+        let Some(start_xref) = self.start_xref else {
+            anyhow::bail!("incremental updates of damaged documents are not supported");
+        };
+
+        let (trailer, is_xref_stream) = self.lexer.lex_raw_trailer(start_xref)?;
+
+        // drop the entries describing the old cross-reference section itself
+        let trailer = Dictionary::new(
+            trailer
+                .entries()
+                .filter(|(key, _)| {
+                    !matches!(
+                        key.as_str(),
+                        "Size"
+                            | "Prev"
+                            | "XRefStm"
+                            | "Type"
+                            | "W"
+                            | "Index"
+                            | "Length"
+                            | "Filter"
+                            | "DecodeParms"
+                            | "F"
+                            | "FFilter"
+                            | "FDecodeParms"
+                            | "DL"
+                    )
+                })
+                .collect(),
+        );
+
+        let xref_format = if is_xref_stream {
+            XrefFormat::Stream
+        } else {
+            XrefFormat::Table
+        };
+
+        Ok(PdfWriter::incremental(
+            Rc::clone(&self.lexer.file),
+            start_xref,
+            self.trailer.size,
+            trailer,
+            xref_format,
+            self.lexer.security_handler.clone(),
+        ))
+    }
+
     pub fn info(&mut self) -> PdfResult<Option<Cow<InformationDictionary<'a>>>> {
         Ok(match &self.trailer.info {
             Some(v) => Some(v.get_ref(&mut self.lexer)?),
@@ -496,11 +924,117 @@ impl<'a> Parser<'a> {
         })
     }
 
-    // todo: make this an iterator
-    pub fn pages(&self) -> Vec<Rc<PageObject<'a>>> {
-        let mut leaves = self.page_tree.leaves();
-        leaves.reverse();
-        leaves
+    /// The number of pages in the document, read from the root of the page tree
+    pub fn page_count(&self) -> usize {
+        self.page_tree.count()
+    }
+
+    /// The page with the zero-based index `n`
+    ///
+    /// Only the page tree nodes on the path to the page are loaded, using the
+    /// number of pages below each node to skip over the others
+    pub fn page(&mut self, n: usize) -> PdfResult<Rc<PageObject<'a>>> {
+        if n >= self.page_count() {
+            anyhow::bail!(
+                "page {} is out of range; document has {} pages",
+                n,
+                self.page_count()
+            );
+        }
+
+        let mut node = self.page_tree.clone();
+        let mut remaining = n;
+
+        // the page tree nodes descended into so far. a node reached again is its
+        // own ancestor, and descending into it would never end
+        let mut path = Vec::new();
+
+        'descend: loop {
+            for kid in node.kids() {
+                let child = match self.page_nodes.get(&kid) {
+                    Some(SeenPageNode::Loaded(child)) => child.clone(),
+                    Some(SeenPageNode::Skipped) if remaining > 0 => {
+                        remaining -= 1;
+                        continue;
+                    }
+                    _ => {
+                        let dict = self.lexer.assert_dict(Object::Reference(kid))?;
+
+                        // pages before the one we're looking for are only read
+                        // far enough to find their type, and are recorded as
+                        // skipped so that they aren't read again
+                        let is_page = dict
+                            .iter()
+                            .any(|(key, value)| key == "Type" && value.name_is("Page"));
+
+                        if is_page && remaining > 0 {
+                            self.page_nodes.insert(kid, SeenPageNode::Skipped);
+                            remaining -= 1;
+                            continue;
+                        }
+
+                        self.load_page_node(dict, kid, &node)?
+                    }
+                };
+
+                match child {
+                    PageNode::Leaf(page) if remaining == 0 => return Ok(page),
+                    PageNode::Node(..) if remaining < child.count() => {
+                        if path.contains(&kid) {
+                            anyhow::bail!("page tree node {:?} is its own ancestor", kid);
+                        }
+
+                        path.push(kid);
+                        node = child;
+                        continue 'descend;
+                    }
+                    _ => remaining -= child.count(),
+                }
+            }
+
+            anyhow::bail!(
+                "page tree has fewer pages than its count of {}",
+                self.page_count()
+            );
+        }
+    }
+
+    /// The pages of the document, in order. Each page is loaded as it is reached
+    pub fn pages(&mut self) -> Pages<'_, 'a> {
+        let root = self.page_tree.clone();
+        let kids = root.kids();
+
+        Pages {
+            parser: self,
+            stack: vec![(root, kids.into_iter())],
+            path: Vec::new(),
+        }
+    }
+
+    /// The child `kid` of the page tree node `parent`, loading it if it hasn't
+    /// been already
+    fn page_node(&mut self, kid: Reference, parent: &PageNode<'a>) -> PdfResult<PageNode<'a>> {
+        if let Some(SeenPageNode::Loaded(node)) = self.page_nodes.get(&kid) {
+            return Ok(node.clone());
+        }
+
+        let dict = self.lexer.assert_dict(Object::Reference(kid))?;
+
+        self.load_page_node(dict, kid, parent)
+    }
+
+    fn load_page_node(
+        &mut self,
+        dict: Dictionary<'a>,
+        kid: Reference,
+        parent: &PageNode<'a>,
+    ) -> PdfResult<PageNode<'a>> {
+        let node = self.lexer.lex_page_node(dict, kid, parent.clone())?;
+
+        self.page_nodes
+            .insert(kid, SeenPageNode::Loaded(node.clone()));
+
+        Ok(node)
     }
 
     pub fn page_annotations(
@@ -526,15 +1060,148 @@ impl<'a> Parser<'a> {
         Ok(None)
     }
 
+    /// The content stream of the page. A page without `Contents` is empty, so
+    /// its content stream has no operators
     pub fn page_contents(&mut self, page: &PageObject<'a>) -> PdfResult<ContentLexer<'a>> {
         let stream = match &page.contents {
             Some(stream) => stream,
-            _ => todo!(),
+            None => {
+                return Ok(ContentLexer::with_options(
+                    Cow::Borrowed(&[]),
+                    self.lexer.options,
+                ))
+            }
         };
 
         // todo: no copy
-        Ok(ContentLexer::new(Cow::Owned(
-            stream.get_ref(&mut self.lexer)?.combined_buffer.clone(),
-        )))
+        Ok(ContentLexer::with_options(
+            Cow::Owned(stream.get_ref(&mut self.lexer)?.combined_buffer.clone()),
+            self.lexer.options,
+        ))
+    }
+
+    /// The text shown on the page, in content stream order, with lines and
+    /// words separated based on the positions of the glyphs
+    ///
+    /// Characters are mapped to Unicode through the font's `ToUnicode` CMap, then
+    /// the glyph names of a simple font's encoding, then the predefined CMap of a
+    /// Type 0 font. Type 0 fonts using `Identity-H`, `Identity-V` or a CMap for a
+    /// legacy CJK encoding need a `ToUnicode` CMap for anything beyond ASCII, as
+    /// the CID to Unicode tables of the Adobe character collections are not
+    /// supported. Characters that can't be mapped are left out of the text
+    pub fn page_text(&mut self, page: &PageObject<'a>) -> PdfResult<String> {
+        if page.contents.is_none() {
+            return Ok(String::new());
+        }
+
+        let content = self.page_contents(page)?;
+
+        TextExtractor::new(content, &mut self.lexer, page.resources()).text()
+    }
+
+    /// The text shown on the page as a list of spans, one for each string
+    /// shown, with the position of every glyph in default user space
+    pub fn page_text_spans(&mut self, page: &PageObject<'a>) -> PdfResult<Vec<TextSpan>> {
+        if page.contents.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let content = self.page_contents(page)?;
+
+        TextExtractor::new(content, &mut self.lexer, page.resources()).spans()
+    }
+}
+
+/// A lazy iterator over the pages of a document, returned by [`Parser::pages`]
+pub struct Pages<'p, 'a> {
+    parser: &'p mut Parser<'a>,
+
+    /// The page tree nodes on the path to the next page, and the children of
+    /// each yet to be visited
+    stack: Vec<(PageNode<'a>, std::vec::IntoIter<Reference>)>,
+
+    /// The references of the page tree nodes in `stack`, other than the root
+    path: Vec<Reference>,
+}
+
+impl<'a> Iterator for Pages<'_, 'a> {
+    type Item = PdfResult<Rc<PageObject<'a>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, kids) = self.stack.last_mut()?;
+
+            let Some(kid) = kids.next() else {
+                self.stack.pop();
+                self.path.pop();
+                continue;
+            };
+
+            let node = node.clone();
+
+            match self.parser.page_node(kid, &node) {
+                Ok(PageNode::Leaf(page)) => return Some(Ok(page)),
+                Ok(..) if self.path.contains(&kid) => {
+                    self.stack.clear();
+
+                    return Some(Err(anyhow::anyhow!(
+                        "page tree node {:?} is its own ancestor",
+                        kid
+                    )));
+                }
+                Ok(child) => {
+                    let kids = child.kids();
+                    self.stack.push((child, kids.into_iter()));
+                    self.path.push(kid);
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        objects::Object,
+        test_util::{dict, reference, single_page_document},
+        Parser, Resolve,
+    };
+
+    use super::MAX_CACHED_OBJECTS;
+
+    #[test]
+    fn streams_are_cached() {
+        let file = single_page_document(b"0 0 m", dict(Vec::new()), Vec::new());
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        // the page's content stream
+        let stream = parser
+            .lexer
+            .lex_object_from_reference(reference(4))
+            .unwrap();
+
+        assert!(matches!(stream, Object::Stream(..)));
+        assert!(parser.lexer.object_cache.contains(reference(4)));
+    }
+
+    #[test]
+    fn object_cache_is_bounded() {
+        let objects = (0..MAX_CACHED_OBJECTS + 1)
+            .map(|n| Object::Integer(n as i32))
+            .collect();
+        let file = single_page_document(b"", dict(Vec::new()), objects);
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        for n in 0..=MAX_CACHED_OBJECTS {
+            let obj = parser.lexer.lex_object_from_reference(reference(n + 5));
+
+            assert_eq!(obj.unwrap(), Object::Integer(n as i32));
+            assert!(parser.lexer.object_cache.len() <= MAX_CACHED_OBJECTS);
+        }
+
+        // the first object was used least recently, so was evicted
+        assert!(!parser.lexer.object_cache.contains(reference(5)));
+        assert!(parser.lexer.object_cache.contains(reference(6)));
     }
 }
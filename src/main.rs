@@ -1,23 +1,344 @@
-use std::rc::Rc;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
+use anyhow::{bail, Context};
 use pdf::{Parser, PdfResult, Renderer};
 
+const USAGE: &str = "\
+usage: pdf <file> [options]
+
+options:
+    -p, --pages <ranges>      pages to render, e.g. `1-3,5,8-` (default: all)
+    -o, --output <template>   output directory, or a filename template in which
+                              `{page}` is replaced by the page number
+                              (default: `<file stem>-{page}.png`)
+    -r, --dpi <dpi>           resolution of the rendered pages (default: 72)
+    -s, --scale <factor>      shorthand for a dpi of 72 * factor
+    -b, --background <color>  background color as `rrggbb`, `rrggbbaa` or
+                              `transparent` (default: ffffff)
+        --view                show the first selected page in a window instead
+                              of writing PNG files
+    -h, --help                print this message";
+
+struct Options {
+    path: PathBuf,
+    pages: Vec<RangeInclusive<usize>>,
+    output: Option<String>,
+    dpi: f32,
+    background: u32,
+    view: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> PdfResult<Self> {
+        let mut path = None;
+        let mut pages = Vec::new();
+        let mut output = None;
+        let mut dpi = 72.0;
+        let mut background = u32::MAX;
+        let mut view = false;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .with_context(|| format!("missing value for `{}`", name))
+            };
+
+            match arg.as_str() {
+                "-p" | "--pages" => pages = parse_page_ranges(&value(&arg)?)?,
+                "-o" | "--output" => output = Some(value(&arg)?),
+                "-r" | "--dpi" => dpi = parse_positive(&value(&arg)?, "dpi")?,
+                "-s" | "--scale" => dpi = 72.0 * parse_positive(&value(&arg)?, "scale")?,
+                "-b" | "--background" => background = parse_color(&value(&arg)?)?,
+                "--view" => view = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => bail!("unknown option `{}`", arg),
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => bail!("unexpected argument `{}`", arg),
+            }
+        }
+
+        let path = match path {
+            Some(path) => path,
+            None => bail!("no input file given\n\n{}", USAGE),
+        };
+
+        Ok(Self {
+            path,
+            pages,
+            output,
+            dpi,
+            background,
+            view,
+        })
+    }
+
+    /// Zero-based indices of the selected pages, in ascending order
+    fn page_indices(&self, page_count: usize) -> PdfResult<Vec<usize>> {
+        if self.pages.is_empty() {
+            return Ok((0..page_count).collect());
+        }
+
+        let mut indices = Vec::new();
+
+        for range in &self.pages {
+            if *range.start() > page_count {
+                bail!(
+                    "page {} is out of range; document has {} pages",
+                    range.start(),
+                    page_count
+                );
+            }
+
+            indices.extend((*range.start()..=(*range.end()).min(page_count)).map(|n| n - 1));
+        }
+
+        indices.sort_unstable();
+        indices.dedup();
+
+        Ok(indices)
+    }
+
+    /// The file the page with the one-based number `page` should be written to
+    ///
+    /// Page numbers are zero-padded to the width of the largest page number so
+    /// that the files sort in page order
+    fn output_path(&self, page: usize, page_count: usize) -> PathBuf {
+        let number = format!("{:0width$}", page, width = page_count.to_string().len());
+
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "page".to_owned());
+        let default_name = format!("{}-{}.png", stem, number);
+
+        match &self.output {
+            Some(template) if template.contains("{page}") => {
+                PathBuf::from(template.replace("{page}", &number))
+            }
+            Some(dir) if dir.ends_with('/') || Path::new(dir).is_dir() => {
+                Path::new(dir).join(default_name)
+            }
+            // a single fixed name only makes sense when rendering a single page
+            Some(name) if self.is_single_page() => PathBuf::from(name),
+            Some(name) => {
+                let name = Path::new(name);
+                let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = name.extension().unwrap_or_default().to_string_lossy();
+
+                name.with_file_name(format!("{}-{}.{}", stem, number, extension))
+            }
+            None => PathBuf::from(default_name),
+        }
+    }
+
+    fn is_single_page(&self) -> bool {
+        matches!(self.pages.as_slice(), [range] if range.start() == range.end())
+    }
+}
+
+/// Parse a comma separated list of one-based page numbers and ranges, such as
+/// `1-3,5,8-`. A range without an end extends to the last page
+fn parse_page_ranges(s: &str) -> PdfResult<Vec<RangeInclusive<usize>>> {
+    let parse_page = |n: &str| -> PdfResult<usize> {
+        match n.trim().parse::<usize>() {
+            Ok(0) | Err(..) => bail!("invalid page number `{}`", n),
+            Ok(n) => Ok(n),
+        }
+    };
+
+    s.split(',')
+        .map(|range| {
+            let (start, end) = match range.split_once('-') {
+                Some((start, "")) => (parse_page(start)?, usize::MAX),
+                Some((start, end)) => (parse_page(start)?, parse_page(end)?),
+                None => {
+                    let page = parse_page(range)?;
+                    (page, page)
+                }
+            };
+
+            if start > end {
+                bail!("invalid page range `{}`", range);
+            }
+
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+fn parse_positive(s: &str, name: &str) -> PdfResult<f32> {
+    match s.parse::<f32>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+        _ => bail!("invalid {} `{}`", name, s),
+    }
+}
+
+/// Parse a hex color into the little-endian RGBA layout used by the canvas
+fn parse_color(s: &str) -> PdfResult<u32> {
+    if s == "transparent" {
+        return Ok(0);
+    }
+
+    let hex = s.trim_start_matches('#');
+
+    // `from_str_radix` would also accept a leading sign
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("invalid color `{}`", s);
+    }
+
+    let rgba = match hex.len() {
+        6 => u32::from_str_radix(hex, 16).map(|rgb| rgb << 8 | 0xff),
+        8 => u32::from_str_radix(hex, 16),
+        _ => bail!("invalid color `{}`", s),
+    }
+    .with_context(|| format!("invalid color `{}`", s))?;
+
+    Ok(rgba.swap_bytes())
+}
+
 fn main() -> PdfResult<()> {
     env_logger::init();
 
-    let mut args = std::env::args().skip(1);
-    let path = args.next().unwrap_or_else(String::new);
-    let page = args.next().map(|n| n.parse::<u32>().unwrap()).unwrap_or(1);
-    let mut parser = Parser::new(path)?;
+    let options = Options::parse(std::env::args().skip(1))?;
+
+    let mut parser = Parser::new(&options.path)?;
+    let page_count = parser.page_count();
+    let indices = options.page_indices(page_count)?;
+
+    // the page tree is walked once, rather than from its root for every page
+    let last = indices.last().map_or(0, |idx| idx + 1);
+    let pages = parser.pages().take(last).collect::<PdfResult<Vec<_>>>()?;
+
+    for idx in indices {
+        let page = match pages.get(idx) {
+            Some(page) => Rc::clone(page),
+            None => bail!("page tree has fewer pages than its count of {}", page_count),
+        };
+        let mut content = parser.page_contents(&page)?;
 
-    for page in parser.pages().into_iter().skip(page as usize - 1) {
-        let mut content = parser.page_contents(&page).unwrap();
+        let renderer = Renderer::new(&mut content, &mut parser.lexer, page);
 
-        let renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        if options.view {
+            renderer.render()?;
+            break;
+        }
 
-        renderer.render().unwrap();
-        break;
+        let image = renderer.rasterize_with_background(options.dpi, options.background)?;
+
+        let out = options.output_path(idx + 1, page_count);
+
+        if let Some(dir) = out.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+        }
+
+        let file = File::create(&out).with_context(|| format!("failed to create {:?}", out))?;
+
+        image.write_png(BufWriter::new(file))?;
+
+        log::info!("wrote page {} to {:?}", idx + 1, out);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{parse_color, parse_page_ranges, Options};
+
+    fn options(pages: &str, output: Option<&str>) -> Options {
+        let mut args = vec!["doc.pdf".to_owned(), "--pages".to_owned(), pages.to_owned()];
+
+        if let Some(output) = output {
+            args.extend(["--output".to_owned(), output.to_owned()]);
+        }
+
+        Options::parse(args.into_iter()).unwrap()
+    }
+
+    #[test]
+    fn page_ranges() {
+        assert_eq!(
+            parse_page_ranges("1-3,5,8-").unwrap(),
+            [1..=3, 5..=5, 8..=usize::MAX]
+        );
+
+        assert!(parse_page_ranges("0").is_err());
+        assert!(parse_page_ranges("3-1").is_err());
+        assert!(parse_page_ranges("1,,2").is_err());
+        assert!(parse_page_ranges("-2").is_err());
+    }
+
+    #[test]
+    fn open_ranges_end_at_the_last_page() {
+        assert_eq!(options("3-", None).page_indices(5).unwrap(), [2, 3, 4]);
+        assert_eq!(options("2-9", None).page_indices(3).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn ranges_starting_past_the_last_page_are_an_error() {
+        assert!(options("6-", None).page_indices(5).is_err());
+        assert!(options("1,6", None).page_indices(5).is_err());
+    }
+
+    #[test]
+    fn duplicate_pages_are_rendered_once_in_order() {
+        assert_eq!(
+            options("4,1-3,2,3-4", None).page_indices(5).unwrap(),
+            [0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn colors_are_stored_as_little_endian_rgba() {
+        assert_eq!(
+            parse_color("ff8000").unwrap().to_le_bytes(),
+            [0xff, 0x80, 0x00, 0xff]
+        );
+        assert_eq!(
+            parse_color("#11223344").unwrap().to_le_bytes(),
+            [0x11, 0x22, 0x33, 0x44]
+        );
+        assert_eq!(parse_color("transparent").unwrap(), 0);
+
+        assert!(parse_color("fff").is_err());
+        assert!(parse_color("gggggg").is_err());
+        assert!(parse_color("+fffff").is_err());
+        assert!(parse_color("#+ff00ff0").is_err());
+    }
+
+    #[test]
+    fn output_names_are_zero_padded() {
+        assert_eq!(
+            options("1-", None).output_path(7, 120),
+            PathBuf::from("doc-007.png")
+        );
+        assert_eq!(
+            options("1-", Some("out/{page}.png")).output_path(7, 120),
+            PathBuf::from("out/007.png")
+        );
+        assert_eq!(
+            options("1-", Some("out/")).output_path(12, 120),
+            PathBuf::from("out/doc-012.png")
+        );
+        assert_eq!(
+            options("1-", Some("page.png")).output_path(7, 9),
+            PathBuf::from("page-7.png")
+        );
+        assert_eq!(
+            options("7", Some("page.png")).output_path(7, 120),
+            PathBuf::from("page.png")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::objects::{Object, Reference};
+
+/// Indirect objects resolved so far, up to a fixed number of them. Once the
+/// cache is full, the object used least recently makes room for the next
+pub(crate) struct ObjectCache<'a> {
+    /// Each object, along with the time it was last used
+    objects: HashMap<Reference, (Object<'a>, u64)>,
+
+    /// The references of the cached objects, keyed by the time they were last
+    /// used
+    recently_used: BTreeMap<u64, Reference>,
+
+    /// Counts every use of the cache, so that later uses are given later times
+    clock: u64,
+
+    capacity: usize,
+}
+
+impl<'a> ObjectCache<'a> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            objects: HashMap::new(),
+            recently_used: BTreeMap::new(),
+            clock: 0,
+            capacity,
+        }
+    }
+
+    /// The object `reference`, if it is cached, marking it as the object used
+    /// most recently
+    pub fn get(&mut self, reference: Reference) -> Option<&Object<'a>> {
+        let time = self.tick();
+        let (obj, last_used) = self.objects.get_mut(&reference)?;
+
+        self.recently_used.remove(last_used);
+        self.recently_used.insert(time, reference);
+        *last_used = time;
+
+        Some(obj)
+    }
+
+    /// Cache `obj` as the object `reference`, evicting the object used least
+    /// recently if the cache is full
+    pub fn insert(&mut self, reference: Reference, obj: Object<'a>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let time = self.tick();
+
+        if let Some((_, last_used)) = self.objects.insert(reference, (obj, time)) {
+            self.recently_used.remove(&last_used);
+        } else if self.objects.len() > self.capacity {
+            if let Some((_, evicted)) = self.recently_used.pop_first() {
+                self.objects.remove(&evicted);
+            }
+        }
+
+        self.recently_used.insert(time, reference);
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.recently_used.clear();
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, reference: Reference) -> bool {
+        self.objects.contains_key(&reference)
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{objects::Object, test_util::reference};
+
+    use super::ObjectCache;
+
+    #[test]
+    fn least_recently_used_object_is_evicted() {
+        let mut cache = ObjectCache::new(2);
+
+        cache.insert(reference(1), Object::Integer(1));
+        cache.insert(reference(2), Object::Integer(2));
+
+        // using the first object leaves the second as the least recently used
+        assert_eq!(cache.get(reference(1)), Some(&Object::Integer(1)));
+
+        cache.insert(reference(3), Object::Integer(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(reference(1)));
+        assert!(!cache.contains(reference(2)));
+        assert!(cache.contains(reference(3)));
+
+        // replacing an object doesn't evict another
+        cache.insert(reference(3), Object::Integer(4));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(reference(3)), Some(&Object::Integer(4)));
+        assert!(cache.contains(reference(1)));
+    }
+}
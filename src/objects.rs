@@ -3,8 +3,8 @@ use std::{borrow::Cow, collections::HashMap, fmt, marker::PhantomData, rc::Rc};
 use anyhow::Context;
 
 use crate::{
-    assert_reference, catalog::assert_len, date::Date, stream::Stream, ParseError, PdfResult,
-    Resolve,
+    assert_reference, catalog::assert_len, date::Date, diagnostic::Diagnostic, stream::Stream,
+    ParseError, PdfResult, Resolve,
 };
 
 #[derive(Debug)]
@@ -48,6 +48,14 @@ impl<'a> Object<'a> {
             false
         }
     }
+
+    /// The reference, if this is an indirect object
+    pub(crate) fn as_reference(&self) -> Option<Reference> {
+        match self {
+            Object::Reference(reference) => Some(*reference),
+            _ => None,
+        }
+    }
 }
 
 /// A reference to a non-existing object is considered a `null`
@@ -67,7 +75,8 @@ pub enum TypedReference<'a, T: FromObj<'a>> {
 }
 
 impl<'a, T: FromObj<'a> + Clone> TypedReference<'a, T> {
-    // todo: cache somehow
+    /// Resolve the value. Indirect objects are cached by the resolver, but are
+    /// converted to `T` each time
     pub fn get_ref(&self, resolver: &mut dyn Resolve<'a>) -> PdfResult<Cow<T>> {
         match self {
             TypedReference::Indirect { reference, _t } => {
@@ -116,24 +125,46 @@ impl<'a> Dictionary<'a> {
         self.dict.into_iter()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Object<'a>)> {
+        self.dict.iter()
+    }
+
+    /// Add an entry to the dictionary, returning the previous value of `key`
+    pub fn insert(&mut self, key: impl Into<String>, value: Object<'a>) -> Option<Object<'a>> {
+        self.dict.insert(key.into(), value)
+    }
+
+    /// An optional entry. When parsing leniently, an entry that can't be read
+    /// is reported and treated as missing
     pub fn get<T: FromObj<'a>>(
         &mut self,
         key: &str,
         resolver: &mut dyn Resolve<'a>,
     ) -> PdfResult<Option<T>> {
-        self.dict
-            .remove(key)
-            .and_then(|obj| match obj {
-                Object::Null => None,
-                Object::Reference(reference) => match resolver.reference_exists(reference) {
-                    Ok(true) => Some(T::from_obj(obj, resolver)),
-                    Ok(false) => None,
-                    Err(e) => Some(Err(e)),
-                },
-                obj => Some(T::from_obj(obj, resolver)),
-            })
-            .transpose()
-            .with_context(|| format!("error getting key {}", key))
+        let Some(obj) = self.dict.remove(key) else {
+            return Ok(None);
+        };
+
+        let reference = obj.as_reference();
+
+        let value = match obj {
+            Object::Null => None,
+            Object::Reference(reference) => match resolver.reference_exists(reference) {
+                Ok(true) => Some(T::from_obj(obj, resolver)),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            },
+            obj => Some(T::from_obj(obj, resolver)),
+        };
+
+        match value.transpose() {
+            Err(err) if resolver.is_lenient() => {
+                resolver.report(Diagnostic::new(reference, Some(key), format!("{:#}", err)));
+
+                Ok(None)
+            }
+            value => value.with_context(|| format!("error getting key {}", key)),
+        }
     }
 
     pub fn expect<T: FromObj<'a>>(
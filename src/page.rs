@@ -1,4 +1,4 @@
-use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc};
 
 use crate::{
     annotation::Annotation,
@@ -17,8 +17,9 @@ use crate::{
 };
 
 pub struct PageTree<'a> {
-    pub kids: Vec<PageNode<'a>>,
-    pub pages: HashMap<Reference, PageNode<'a>>,
+    /// References to the immediate children of the root, which are loaded as
+    /// they are needed
+    pub kids: Vec<Reference>,
     pub count: usize,
 
     /// Fields inheritable by child nodes
@@ -33,20 +34,24 @@ pub enum PageNode<'a> {
 }
 
 impl<'a> PageNode<'a> {
-    pub fn leaves(&self) -> Vec<Rc<PageObject<'a>>> {
-        let mut leaves = Vec::new();
-
+    /// References to the immediate children of this node, which are empty for
+    /// a page object
+    pub fn kids(&self) -> Vec<Reference> {
         match self {
-            PageNode::Root(root) => {
-                for kid in &root.borrow().kids {
-                    leaves.append(&mut kid.leaves());
-                }
-            }
-            PageNode::Node(node) => leaves.append(&mut node.borrow().leaves()),
-            PageNode::Leaf(leaf) => leaves.push(Rc::clone(leaf)),
+            PageNode::Root(root) => root.borrow().kids.clone(),
+            PageNode::Node(node) => node.borrow().kids.clone(),
+            PageNode::Leaf(..) => Vec::new(),
         }
+    }
 
-        leaves
+    /// The number of page objects that are descendants of this node, or 1 for a
+    /// page object
+    pub fn count(&self) -> usize {
+        match self {
+            PageNode::Root(root) => root.borrow().count,
+            PageNode::Node(node) => node.borrow().count,
+            PageNode::Leaf(..) => 1,
+        }
     }
 
     pub fn crop_box(&self) -> Option<Rectangle> {
@@ -73,6 +78,18 @@ impl<'a> PageNode<'a> {
         }
     }
 
+    pub fn rotate(&self) -> Option<i32> {
+        match self {
+            Self::Root(tree) => tree.borrow().inheritable_page_fields.rotate,
+            Self::Node(node) => node
+                .borrow()
+                .inheritable_page_fields
+                .rotate
+                .or_else(|| node.borrow().parent.rotate()),
+            Self::Leaf(leaf) => leaf.rotate(),
+        }
+    }
+
     pub fn resources(&self) -> Option<Rc<Resources<'a>>> {
         match self {
             Self::Root(tree) => tree
@@ -118,7 +135,7 @@ pub struct PageTreeNode<'a> {
     /// An array of indirect references to the immediate children
     /// of this node. The children shall only be page objects or
     /// other page tree nodes.
-    pub kids: Vec<PageNode<'a>>,
+    pub kids: Vec<Reference>,
 
     /// The number of leaf nodes (page objects) that are descendants
     /// of this node within the page tree
@@ -128,22 +145,6 @@ pub struct PageTreeNode<'a> {
     pub(crate) inheritable_page_fields: InheritablePageFields<'a>,
 }
 
-impl<'a> PageTreeNode<'a> {
-    pub fn leaves(&self) -> Vec<Rc<PageObject<'a>>> {
-        let mut leaves = Vec::new();
-
-        for node in &self.kids {
-            match node {
-                PageNode::Root(..) => unreachable!(),
-                PageNode::Leaf(leaf) => leaves.push(Rc::clone(leaf)),
-                PageNode::Node(node) => leaves.append(&mut node.borrow().leaves()),
-            }
-        }
-
-        leaves
-    }
-}
-
 // "Page"
 // #[derive(FromObj)]
 pub struct PageObject<'a> {
@@ -358,6 +359,12 @@ impl<'a> PageObject<'a> {
             .or(self.media_box)
     }
 
+    /// The number of degrees by which the page is rotated clockwise, which may
+    /// be inherited from the page tree
+    pub fn rotate(&self) -> Option<i32> {
+        self.rotate.or_else(|| self.parent.rotate())
+    }
+
     pub fn resources(&self) -> Option<Rc<Resources<'a>>> {
         self.resources.as_ref().map(Rc::clone)
     }
@@ -418,3 +425,141 @@ impl<'a> InheritablePageFields<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        objects::Object,
+        test_util::{dict, document, name, reference},
+        Parser, PdfResult, XrefFormat,
+    };
+
+    fn pages(parent: usize, kids: &[usize], count: i32) -> Object<'static> {
+        Object::Dictionary(dict(vec![
+            ("Type", name("Pages")),
+            ("Parent", Object::Reference(reference(parent))),
+            (
+                "Kids",
+                Object::Array(
+                    kids.iter()
+                        .map(|&n| Object::Reference(reference(n)))
+                        .collect(),
+                ),
+            ),
+            ("Count", Object::Integer(count)),
+        ]))
+    }
+
+    /// A page whose `Rotate` identifies it
+    fn page(parent: usize, rotate: i32) -> Object<'static> {
+        Object::Dictionary(dict(vec![
+            ("Type", name("Page")),
+            ("Parent", Object::Reference(reference(parent))),
+            ("Rotate", Object::Integer(rotate)),
+        ]))
+    }
+
+    fn catalog() -> Object<'static> {
+        Object::Dictionary(dict(vec![
+            ("Type", name("Catalog")),
+            ("Pages", Object::Reference(reference(2))),
+        ]))
+    }
+
+    #[test]
+    fn nested_page_tree() {
+        let objects = vec![
+            catalog(),
+            Object::Dictionary(dict(vec![
+                ("Type", name("Pages")),
+                (
+                    "Kids",
+                    Object::Array(vec![
+                        Object::Reference(reference(3)),
+                        Object::Reference(reference(4)),
+                    ]),
+                ),
+                ("Count", Object::Integer(4)),
+            ])),
+            pages(2, &[5, 6], 3),
+            page(2, 270),
+            page(3, 0),
+            pages(3, &[7, 8], 2),
+            page(6, 90),
+            page(6, 180),
+        ];
+
+        let file = document(objects, XrefFormat::Table);
+
+        let mut parser = Parser::from_bytes(file.clone()).unwrap();
+        assert_eq!(parser.page_count(), 4);
+        assert_eq!(parser.page(3).unwrap().rotate, Some(270));
+        assert_eq!(parser.page(1).unwrap().rotate, Some(90));
+        assert!(parser.page(4).is_err());
+
+        // the first page was skipped over while looking up the second
+        assert_eq!(parser.page(0).unwrap().rotate, Some(0));
+
+        let rotations = |parser: &mut Parser| {
+            parser
+                .pages()
+                .map(|page| Ok(page?.rotate))
+                .collect::<PdfResult<Vec<_>>>()
+                .unwrap()
+        };
+        assert_eq!(
+            rotations(&mut parser),
+            [Some(0), Some(90), Some(180), Some(270)]
+        );
+
+        let mut parser = Parser::from_bytes(file.clone()).unwrap();
+        assert_eq!(
+            rotations(&mut parser),
+            [Some(0), Some(90), Some(180), Some(270)]
+        );
+
+        // pages skipped over are loaded when iterated over
+        let mut parser = Parser::from_bytes(file).unwrap();
+        assert_eq!(parser.page(2).unwrap().rotate, Some(180));
+        assert_eq!(
+            rotations(&mut parser),
+            [Some(0), Some(90), Some(180), Some(270)]
+        );
+    }
+
+    #[test]
+    fn page_tree_node_that_is_its_own_kid() {
+        let objects = vec![
+            catalog(),
+            Object::Dictionary(dict(vec![
+                ("Type", name("Pages")),
+                ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+                ("Count", Object::Integer(1)),
+            ])),
+            pages(2, &[3], 1),
+        ];
+
+        let file = document(objects, XrefFormat::Table);
+
+        let mut parser = Parser::from_bytes(file).unwrap();
+        assert!(parser.page(0).is_err());
+
+        let pages = parser.pages().collect::<Vec<_>>();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].is_err());
+    }
+
+    #[test]
+    fn negative_page_count() {
+        let objects = vec![
+            catalog(),
+            Object::Dictionary(dict(vec![
+                ("Type", name("Pages")),
+                ("Kids", Object::Array(Vec::new())),
+                ("Count", Object::Integer(-1)),
+            ])),
+        ];
+
+        assert!(Parser::from_bytes(document(objects, XrefFormat::Table)).is_err());
+    }
+}
@@ -122,7 +122,7 @@ pub(super) fn gen_system_dict() -> PostScriptDictionary {
 }
 
 #[rustfmt::skip]
-pub(super) static STANDARD_ENCODING: &[Option<&str>] = &[
+pub(crate) static STANDARD_ENCODING: &[Option<&str>] = &[
     /*\00x*/ None, None, None, None, None, None, None, None,
     /*\01x*/ None, None, None, None, None, None, None, None,
     /*\02x*/ None, None, None, None, None, None, None, None,
@@ -164,7 +164,7 @@ pub(super) static STANDARD_ENCODING: &[Option<&str>] = &[
     /*\27x*/ Some("quotesinglbase"), Some("quotedblbase"), Some("quotedblright"), Some("guillemotright"),
              Some("ellipsis"), Some("perthousand"), None, Some("questiondown"),
     /*\30x*/ None, Some("grave"), Some("acute"), Some("circumflex"),
-             Some("tilde"), Some("macron2"), Some("breve"), Some("dotaccent"),
+             Some("tilde"), Some("macron"), Some("breve"), Some("dotaccent"),
     /*\31x*/ Some("dieresis"), None, Some("ring"), Some("cedilla"),
              None, Some("hungarumlaut"), Some("ogonek"), Some("caron"),
     /*\32x*/ Some("emdash"), None, None, None, None, None, None, None,
@@ -0,0 +1,335 @@
+//! CMap files are PostScript programs, but they are restricted enough in form
+//! that they can be parsed from the token stream alone, without executing them
+//! in the interpreter
+
+use std::borrow::Cow;
+
+use crate::{
+    error::PdfResult,
+    font::{code_to_u32, glyph_name_to_unicode, CMap, CodespaceRange},
+};
+
+use super::{lexer::PostScriptLexer, object::PostScriptObject, operator::PostscriptOperator};
+
+#[derive(Debug, Clone, PartialEq)]
+enum CMapOperand {
+    Bytes(Vec<u8>),
+    Integer(i32),
+    Name(String),
+    Array(Vec<CMapOperand>),
+    Mark,
+
+    /// An object that has no meaning within a CMap, such as a dictionary
+    Other,
+}
+
+struct CMapParser<'a> {
+    lexer: PostScriptLexer<'a>,
+    operands: Vec<CMapOperand>,
+    cmap: CMap,
+}
+
+/// Parse a CMap file, such as the contents of a ToUnicode stream or of an
+/// embedded CMap used as the encoding of a Type 0 font
+pub(crate) fn parse_cmap(buffer: &[u8]) -> PdfResult<CMap> {
+    CMapParser {
+        lexer: PostScriptLexer::new(Cow::Borrowed(buffer)),
+        operands: Vec::new(),
+        cmap: CMap::default(),
+    }
+    .parse()
+}
+
+impl<'a> CMapParser<'a> {
+    fn parse(mut self) -> PdfResult<CMap> {
+        while let Some(obj) = self.lexer.next() {
+            match obj? {
+                PostScriptObject::String(idx) => {
+                    let bytes = self.lexer.strings.get(&idx).unwrap().as_bytes().to_vec();
+                    self.operands.push(CMapOperand::Bytes(bytes));
+                }
+                PostScriptObject::Int(n) => self.operands.push(CMapOperand::Integer(n)),
+                PostScriptObject::Name(name) => self.operands.push(CMapOperand::Name(
+                    String::from_utf8_lossy(name.as_bytes()).into_owned(),
+                )),
+                PostScriptObject::Operator(
+                    PostscriptOperator::ArrayStart
+                    | PostscriptOperator::DictStart
+                    | PostscriptOperator::ProcedureStart,
+                ) => self.operands.push(CMapOperand::Mark),
+                PostScriptObject::Operator(PostscriptOperator::ArrayEnd) => {
+                    let arr = self.pop_to_mark();
+                    self.operands.push(CMapOperand::Array(arr));
+                }
+                PostScriptObject::Operator(
+                    PostscriptOperator::DictEnd | PostscriptOperator::ProcedureEnd,
+                ) => {
+                    self.pop_to_mark();
+                    self.operands.push(CMapOperand::Other);
+                }
+                PostScriptObject::Operator(PostscriptOperator::Def) => self.def(),
+                PostScriptObject::Literal(op) => self.execute(op.as_bytes())?,
+                PostScriptObject::Operator(..) => self.operands.clear(),
+                _ => self.operands.push(CMapOperand::Other),
+            }
+        }
+
+        Ok(self.cmap)
+    }
+
+    fn execute(&mut self, op: &[u8]) -> PdfResult<()> {
+        match op {
+            b"endcodespacerange" => {
+                for [low, high] in self.take_groups::<2>() {
+                    let low = expect_bytes(low)?;
+                    let high = expect_bytes(high)?;
+
+                    if low.len() != high.len() {
+                        anyhow::bail!("mismatched codespace range bounds: {:?} {:?}", low, high);
+                    }
+
+                    self.cmap
+                        .codespace_ranges
+                        .push(CodespaceRange::new(low, high));
+                }
+            }
+            b"endbfchar" => {
+                for [code, destination] in self.take_groups::<2>() {
+                    let code = code_to_u32(&expect_bytes(code)?);
+
+                    let unicode = match destination {
+                        CMapOperand::Bytes(bytes) => String::from_utf16_lossy(&utf16_units(&bytes)),
+                        CMapOperand::Name(name) => match glyph_name_to_unicode(&name) {
+                            Some(unicode) => unicode,
+                            None => continue,
+                        },
+                        obj => anyhow::bail!("invalid bfchar destination: {:?}", obj),
+                    };
+
+                    self.cmap.add_bf_char(code, unicode);
+                }
+            }
+            b"endbfrange" => {
+                for [low, high, destination] in self.take_groups::<3>() {
+                    let low = code_to_u32(&expect_bytes(low)?);
+                    let high = code_to_u32(&expect_bytes(high)?);
+
+                    match destination {
+                        CMapOperand::Bytes(bytes) => {
+                            self.cmap.add_bf_range(low, high, utf16_units(&bytes))
+                        }
+                        CMapOperand::Array(arr) => {
+                            let destination = arr
+                                .into_iter()
+                                .map(|obj| {
+                                    Ok(String::from_utf16_lossy(&utf16_units(&expect_bytes(obj)?)))
+                                })
+                                .collect::<PdfResult<Vec<String>>>()?;
+
+                            self.cmap.add_bf_range_array(low, high, destination);
+                        }
+                        obj => anyhow::bail!("invalid bfrange destination: {:?}", obj),
+                    }
+                }
+            }
+            b"endcidchar" | b"endnotdefchar" => {
+                for [code, cid] in self.take_groups::<2>() {
+                    let code = code_to_u32(&expect_bytes(code)?);
+                    let cid = expect_cid(cid)?;
+
+                    if op == b"endcidchar" {
+                        self.cmap.add_cid_char(code, cid);
+                    } else {
+                        self.cmap.add_notdef_char(code, cid);
+                    }
+                }
+            }
+            b"endcidrange" | b"endnotdefrange" => {
+                for [low, high, cid] in self.take_groups::<3>() {
+                    let low = code_to_u32(&expect_bytes(low)?);
+                    let high = code_to_u32(&expect_bytes(high)?);
+                    let cid = expect_cid(cid)?;
+
+                    if op == b"endcidrange" {
+                        self.cmap.add_cid_range(low, high, cid);
+                    } else {
+                        self.cmap.add_notdef_range(low, high, cid);
+                    }
+                }
+            }
+            b"usecmap" => {
+                if let Some(CMapOperand::Name(name)) = self.operands.pop() {
+                    self.cmap.use_cmap = Some(name);
+                }
+
+                self.operands.clear();
+            }
+            // `begin*` operators are preceded by the number of entries that
+            // follow, which we don't need. other operators, like `findresource`
+            // or `begincmap`, do not affect the mappings
+            _ => self.operands.clear(),
+        }
+
+        Ok(())
+    }
+
+    fn def(&mut self) {
+        let value = self.operands.pop();
+        let key = self.operands.pop();
+
+        match (key, value) {
+            (Some(CMapOperand::Name(key)), Some(CMapOperand::Name(value))) if key == "CMapName" => {
+                self.cmap.name = Some(value);
+            }
+            (Some(CMapOperand::Name(key)), Some(CMapOperand::Integer(value))) if key == "WMode" => {
+                self.cmap.writing_mode = value;
+            }
+            _ => {}
+        }
+    }
+
+    fn pop_to_mark(&mut self) -> Vec<CMapOperand> {
+        let start = self
+            .operands
+            .iter()
+            .rposition(|obj| *obj == CMapOperand::Mark)
+            .unwrap_or(0);
+
+        let mut objects = self.operands.split_off(start);
+
+        if objects.first() == Some(&CMapOperand::Mark) {
+            objects.remove(0);
+        }
+
+        objects
+    }
+
+    /// Take the operands of a `beginx`/`endx` block in groups of `N`, ignoring
+    /// any trailing operands that do not form a complete group
+    fn take_groups<const N: usize>(&mut self) -> Vec<[CMapOperand; N]> {
+        let mut operands = std::mem::take(&mut self.operands).into_iter();
+        let mut groups = Vec::new();
+
+        loop {
+            let group = (0..N).map_while(|_| operands.next()).collect::<Vec<_>>();
+
+            match <[CMapOperand; N]>::try_from(group) {
+                Ok(group) => groups.push(group),
+                Err(..) => break,
+            }
+        }
+
+        groups
+    }
+}
+
+fn expect_bytes(obj: CMapOperand) -> PdfResult<Vec<u8>> {
+    match obj {
+        CMapOperand::Bytes(bytes) => Ok(bytes),
+        obj => anyhow::bail!("expected string in cmap, found {:?}", obj),
+    }
+}
+
+fn expect_cid(obj: CMapOperand) -> PdfResult<u32> {
+    match obj {
+        CMapOperand::Integer(n) => Ok(u32::try_from(n)?),
+        obj => anyhow::bail!("expected cid in cmap, found {:?}", obj),
+    }
+}
+
+/// Destination strings are UTF-16BE, though some producers write single byte
+/// destinations
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    if let [b] = bytes {
+        return vec![*b as u16];
+    }
+
+    bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TO_UNICODE: &[u8] = b"/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def
+/CMapName /Adobe-Identity-UCS def
+/CMapType 2 def
+1 begincodespacerange
+<0000> <FFFF>
+endcodespacerange
+3 beginbfchar
+<0003> <0020>
+<0011> <0066006C>
+<0012> <D835DC00>
+endbfchar
+2 beginbfrange
+<0024> <0026> <0041>
+<0030> <0032> [<0061> <0062> <00660069>]
+endbfrange
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end
+";
+
+    #[test]
+    fn bfchar_and_bfrange() {
+        let cmap = parse_cmap(TO_UNICODE).unwrap();
+
+        assert_eq!(cmap.name.as_deref(), Some("Adobe-Identity-UCS"));
+        assert_eq!(
+            cmap.codespace_ranges,
+            vec![CodespaceRange::new(vec![0, 0], vec![0xff, 0xff])]
+        );
+
+        assert_eq!(cmap.to_unicode(0x03).as_deref(), Some(" "));
+        assert_eq!(cmap.to_unicode(0x11).as_deref(), Some("fl"));
+        assert_eq!(cmap.to_unicode(0x12).as_deref(), Some("\u{1D400}"));
+        assert_eq!(cmap.to_unicode(0x24).as_deref(), Some("A"));
+        assert_eq!(cmap.to_unicode(0x26).as_deref(), Some("C"));
+        assert_eq!(cmap.to_unicode(0x27), None);
+        assert_eq!(cmap.to_unicode(0x31).as_deref(), Some("b"));
+        assert_eq!(cmap.to_unicode(0x32).as_deref(), Some("fi"));
+    }
+
+    #[test]
+    fn mixed_length_codespace() {
+        let cmap = parse_cmap(
+            b"begincmap
+2 begincodespacerange
+<00> <80>
+<8140> <9FFC>
+endcodespacerange
+1 begincidrange
+<8140> <817E> 633
+endcidrange
+/WMode 1 def
+endcmap",
+        )
+        .unwrap();
+
+        assert_eq!(cmap.next_code(&[0x41, 0x81, 0x40]), (0x41, 1));
+        assert_eq!(cmap.next_code(&[0x81, 0x40, 0x41]), (0x8140, 2));
+        assert_eq!(cmap.cid(0x8141), Some(634));
+        assert!(cmap.is_vertical());
+    }
+
+    #[test]
+    fn ascii85_string_is_an_error() {
+        let result = parse_cmap(
+            b"begincmap
+1 beginbfchar
+<01> <~+9~>
+endbfchar
+endcmap",
+        );
+
+        assert!(result.is_err());
+    }
+}
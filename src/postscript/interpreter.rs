@@ -179,6 +179,8 @@ impl<'a> PostscriptInterpreter<'a> {
             PostscriptOperator::ArrayEnd => self.array_end(),
             PostscriptOperator::ProcedureStart => self.procedure_start(),
             PostscriptOperator::ProcedureEnd => todo!(),
+            PostscriptOperator::DictStart => self.mark(),
+            PostscriptOperator::DictEnd => self.dict_end(),
             PostscriptOperator::CurrentDict => self.current_dict(),
             PostscriptOperator::CurrentFile => self.current_file(),
             PostscriptOperator::EExec => self.eexec(),
@@ -956,6 +958,29 @@ impl<'a> PostscriptInterpreter<'a> {
         Ok(())
     }
 
+    /// Creates a dictionary from the key-value pairs on the operand stack above
+    /// the topmost mark, which is removed
+    fn dict_end(&mut self) -> PdfResult<()> {
+        let mut dict = PostScriptDictionary::with_capacity(0);
+
+        loop {
+            let value = match self.pop()? {
+                PostScriptObject::Mark => break,
+                obj => obj,
+            };
+
+            let key = self.pop_name()?;
+
+            dict.insert(key, value);
+        }
+
+        let dict = self.new_dict(dict);
+
+        self.push(PostScriptObject::Dictionary(dict));
+
+        Ok(())
+    }
+
     /// Pushes a mark object on the operand stack
     ///
     /// All marks are identical, and the operand stack may contain any number
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
-use crate::{error::PdfResult, lex::LexBase};
+use crate::{
+    error::{ParseError, PdfResult},
+    lex::LexBase,
+};
 
 use super::{
     object::{Container, PostScriptObject, PostScriptString, StringIndex},
@@ -140,7 +143,44 @@ impl<'a> PostScriptLexer<'a> {
     }
 
     fn lex_gt(&mut self) -> PdfResult<PostScriptObject> {
-        todo!()
+        self.expect_byte(b'<')?;
+
+        match self.peek_byte() {
+            Some(b'<') => {
+                self.next_byte();
+                return Ok(PostScriptObject::Operator(PostscriptOperator::DictStart));
+            }
+            Some(b'~') => anyhow::bail!("ASCII base-85 strings are not supported"),
+            Some(..) | None => {}
+        }
+
+        let mut bytes = Vec::new();
+        let mut high_nibble = None;
+
+        loop {
+            let b = match self.next_byte() {
+                Some(b'>') => break,
+                Some(b) if Self::is_whitespace(b) => continue,
+                Some(b @ (b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F')) => Self::hex_byte_to_digit(b),
+                Some(b) => anyhow::bail!("unexpected byte in hex string: {:?}", b as char),
+                None => anyhow::bail!(ParseError::UnexpectedEof),
+            };
+
+            match high_nibble.take() {
+                Some(high) => bytes.push(high << 4 | b),
+                None => high_nibble = Some(b),
+            }
+        }
+
+        // if there is an odd number of digits, the final digit is assumed to be
+        // followed by a 0
+        if let Some(high) = high_nibble {
+            bytes.push(high << 4);
+        }
+
+        Ok(PostScriptObject::String(
+            self.strings.insert(PostScriptString::from_bytes(bytes)),
+        ))
     }
 
     fn lex_operator(&mut self) -> PdfResult<PostScriptObject> {
@@ -163,7 +203,10 @@ impl<'a> PostScriptLexer<'a> {
                 self.next_byte();
                 return Ok(PostScriptObject::Operator(PostscriptOperator::ProcedureEnd));
             }
-            Some(b'<') => todo!(),
+            Some(b'>') => {
+                self.expect_bytes(b">>")?;
+                return Ok(PostScriptObject::Operator(PostscriptOperator::DictEnd));
+            }
             Some(..) => {}
             None => todo!(),
         }
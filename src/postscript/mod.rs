@@ -1,4 +1,6 @@
 pub(crate) use {
+    builtin::STANDARD_ENCODING,
+    cmap::parse_cmap,
     error::{PostScriptError, PostScriptResult},
     interpreter::PostscriptInterpreter,
 };
@@ -7,6 +9,7 @@ pub(crate) use {
 
 mod builtin;
 pub mod charstring;
+mod cmap;
 mod decode;
 mod error;
 pub mod font;
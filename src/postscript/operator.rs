@@ -66,6 +66,13 @@ pub(super) enum PostscriptOperator {
     ArrayEnd,
     ProcedureStart,
     ProcedureEnd,
+
+    /// `<<`; pushes a mark object on the operand stack, equivalent to `mark`
+    DictStart,
+
+    /// `>>`; creates a dictionary containing the key-value pairs on the operand
+    /// stack down to the topmost mark, then pushes the dictionary
+    DictEnd,
     CurrentDict,
     String,
     Exch,
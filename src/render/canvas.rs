@@ -1,11 +1,16 @@
-use std::{fs::File, io::BufWriter, mem, path::Path as FilePath};
-
-use bitvec::{prelude::Lsb0, slice::BitSlice};
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+    mem,
+    path::Path as FilePath,
+};
 
 use crate::{
     color::{Color, ColorSpace, ColorSpaceName},
+    data_structures::Matrix,
     error::PdfResult,
-    filter::{decode_stream, flate::BitsPerComponent},
+    filter::{decode_jpx_image, decode_stream, FilterKind},
     geometry::{CubicBezierCurve, Line, Outline, Path, Point, QuadraticBezierCurve, Subpath},
     resolve::Resolve,
     xobject::ImageXObject,
@@ -14,7 +19,7 @@ use crate::{
 #[cfg(feature = "window")]
 use minifb::{Key, Window, WindowOptions};
 
-use super::FillRule;
+use super::{clip::ClipMask, FillRule};
 
 pub fn fuzzy_eq(a: f32, b: f32) -> bool {
     let a = a.abs();
@@ -30,12 +35,41 @@ pub fn fuzzy_eq(a: f32, b: f32) -> bool {
     }
 }
 
+/// A rendered page, with 8 bits for each of the red, green, blue and alpha
+/// channels of a pixel
+///
+/// Pixels are stored row by row, starting from the top left corner of the page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Encode the image as a PNG
+    pub fn write_png(&self, w: impl Write) -> PdfResult<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+
+        Ok(())
+    }
+}
+
 pub(super) struct Canvas {
     pub(super) width: usize,
     pub(super) height: usize,
     buffer: Vec<u32>,
+
+    /// Confines painting to the current clipping path. Without one, the whole
+    /// canvas may be painted
+    clip: Option<ClipMask>,
     #[cfg(feature = "window")]
-    pub(super) window: Window,
+    window: Option<Window>,
 }
 
 fn parse_rgba(color: u32) -> [u8; 4] {
@@ -60,7 +94,273 @@ fn apply_opacity(color: u32, opacity: f32, background: u32) -> u32 {
     .as_u32()
 }
 
+/// An image with its samples decoded, ready to be painted
+#[derive(Debug)]
+pub(super) struct DecodedImage {
+    width: usize,
+    height: usize,
+
+    /// The color of each pixel of the image, row by row from the top, or `None`
+    /// where an image mask leaves the page unpainted. Pixels of images with their
+    /// own opacity may be partly transparent
+    pixels: Vec<Option<u32>>,
+}
+
+/// How the samples of an image are turned into colours
+enum ImageColors<'a> {
+    /// The samples of a pixel are the components of its colour in this device
+    /// colour space
+    Direct(ColorSpace<'a>),
+
+    /// The single sample of a pixel is an index into this colour table
+    Indexed(Vec<u32>),
+}
+
+impl<'a> ImageColors<'a> {
+    fn new(space: &ColorSpace<'a>) -> PdfResult<Self> {
+        Ok(match space {
+            ColorSpace::Indexed { space, .. } => {
+                let base = image_device_space(&space.base)?;
+                let num_components = base.num_components();
+
+                let table = (0..=usize::from(space.hival))
+                    .map(|index| {
+                        let components: Vec<f32> = space
+                            .lookup
+                            .entry(index, num_components)
+                            .iter()
+                            .map(|&b| f32::from(b) / 255.0)
+                            .collect();
+
+                        Ok(base.with_components(&components)?.as_u32())
+                    })
+                    .collect::<PdfResult<_>>()?;
+
+                ImageColors::Indexed(table)
+            }
+            space => ImageColors::Direct(image_device_space(space)?),
+        })
+    }
+
+    fn num_components(&self) -> usize {
+        match self {
+            ImageColors::Direct(space) => space.num_components(),
+            ImageColors::Indexed(..) => 1,
+        }
+    }
+}
+
+/// The device colour space in which to paint samples given in `space`
+///
+/// ICC profiles aren't interpreted, so ICCBased colour spaces are painted in the
+/// device colour space with the same number of components, or their alternate
+/// colour space
+fn image_device_space<'a>(space: &ColorSpace<'a>) -> PdfResult<ColorSpace<'a>> {
+    Ok(match space {
+        ColorSpace::DeviceGray(..)
+        | ColorSpace::DeviceRGB { .. }
+        | ColorSpace::DeviceCMYK { .. } => space.clone(),
+        ColorSpace::IccBased { stream, .. } => {
+            let components = stream.num_of_color_components;
+
+            if matches!(components, 1 | 3 | 4) {
+                device_space(components as usize)?
+            } else if let Some(alternate) = &stream.alternate {
+                image_device_space(alternate)?
+            } else {
+                anyhow::bail!(
+                    "ICCBased colour space with {} components has no alternate colour space",
+                    components
+                )
+            }
+        }
+        space => anyhow::bail!("unsupported colour space for images: {:?}", space.name()),
+    })
+}
+
+/// The device colour space with `components` colour components
+fn device_space<'a>(components: usize) -> PdfResult<ColorSpace<'a>> {
+    Ok(ColorSpace::init(match components {
+        1 => ColorSpaceName::DeviceGray,
+        3 => ColorSpaceName::DeviceRGB,
+        4 => ColorSpaceName::DeviceCMYK,
+        n => anyhow::bail!("no device colour space has {} components", n),
+    }))
+}
+
+impl DecodedImage {
+    /// Decode the samples of `image`. Image masks are painted with `fill_color`
+    pub fn new<'a>(
+        image: &ImageXObject<'a>,
+        fill_color: u32,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
+        let is_jpx = image
+            .stream
+            .dict
+            .filter
+            .as_ref()
+            .and_then(|filters| filters.last())
+            == Some(&FilterKind::Jpx);
+
+        // JPEG 2000 data gives its own number of channels and bit depth, and
+        // may include an opacity channel
+        let jpx = if is_jpx {
+            Some(decode_jpx_image(
+                &image.stream.stream,
+                &image.stream.dict,
+                resolver,
+            )?)
+        } else {
+            None
+        };
+
+        let pixel_data = match &jpx {
+            Some(jpx) => Cow::Borrowed(jpx.data.as_slice()),
+            None => decode_stream(&image.stream.stream, &image.stream.dict, resolver)?,
+        };
+
+        let colors = if image.image_mask {
+            ImageColors::Direct(ColorSpace::init(ColorSpaceName::DeviceGray))
+        } else {
+            match &image.color_space {
+                Some(space) => ImageColors::new(space)?,
+                None => match &jpx {
+                    Some(jpx) => ImageColors::Direct(device_space(jpx.components)?),
+                    None => ImageColors::Direct(ColorSpace::init(ColorSpaceName::DeviceRGB)),
+                },
+            }
+        };
+
+        let components = colors.num_components();
+
+        if let Some(jpx) = &jpx {
+            if jpx.components != components {
+                anyhow::bail!(
+                    "JPEG 2000 image has {} channels, but its colour space has {} components",
+                    jpx.components,
+                    components
+                );
+            }
+
+            if jpx.width != image.width as usize || jpx.height != image.height as usize {
+                anyhow::bail!(
+                    "JPEG 2000 image is {}x{}, but its image dictionary gives {}x{}",
+                    jpx.width,
+                    jpx.height,
+                    image.width,
+                    image.height
+                );
+            }
+        }
+
+        let bits = if jpx.is_some() {
+            8
+        } else if image.image_mask {
+            1
+        } else {
+            image
+                .bits_per_component
+                .map_or(8, |bits| bits as u32 as usize)
+        };
+
+        let max_sample = ((1_u32 << bits) - 1) as f32;
+
+        // the Decode array of JPEG 2000 images only applies to image masks
+        let decode = match &jpx {
+            Some(..) if !image.image_mask => None,
+            _ => image.decode.as_deref(),
+        };
+
+        let alpha = match &jpx {
+            Some(jpx) if image.s_mask_in_data != 0 => jpx.alpha.as_deref(),
+            _ => None,
+        };
+
+        let is_indexed = matches!(colors, ImageColors::Indexed(..));
+
+        let width = image.width as usize;
+        let height = image.height as usize;
+
+        // each row of samples begins on a byte boundary
+        let bytes_per_row = (width * components * bits).div_ceil(8);
+
+        let mut samples = vec![0.0; components];
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for (row_index, row) in pixel_data.chunks(bytes_per_row).take(height).enumerate() {
+            for col in 0..width {
+                for (component, sample) in samples.iter_mut().enumerate() {
+                    let idx = col * components + component;
+
+                    let value = match bits {
+                        8 => row.get(idx).copied().map(u32::from),
+                        16 => row
+                            .get(idx * 2..idx * 2 + 2)
+                            .map(|b| u32::from(u16::from_be_bytes([b[0], b[1]]))),
+                        _ => {
+                            let bit = idx * bits;
+                            let mask = (1 << bits) - 1;
+
+                            row.get(bit / 8)
+                                .map(|&b| u32::from(b >> (8 - bits - bit % 8)) & mask)
+                        }
+                    };
+
+                    let (d_min, d_max) = match decode {
+                        Some(decode) if decode.len() >= component * 2 + 2 => {
+                            (decode[component * 2], decode[component * 2 + 1])
+                        }
+                        // samples of indexed images are indices into the colour
+                        // table rather than colour components
+                        _ if is_indexed => (0.0, max_sample),
+                        _ => (0.0, 1.0),
+                    };
+
+                    let value = value.unwrap_or(0) as f32 / max_sample;
+                    let value = d_min + value * (d_max - d_min);
+
+                    *sample = if is_indexed {
+                        value
+                    } else {
+                        value.clamp(0.0, 1.0)
+                    };
+                }
+
+                let color = if image.image_mask {
+                    // samples decoding to 0 are painted, and 1 are left unpainted
+                    (samples[0] < 0.5).then_some(fill_color)
+                } else {
+                    let color = match &colors {
+                        ImageColors::Direct(space) => space.with_components(&samples)?.as_u32(),
+                        ImageColors::Indexed(table) => {
+                            let index = samples[0].round().clamp(0.0, (table.len() - 1) as f32);
+
+                            table[index as usize]
+                        }
+                    };
+
+                    match alpha.and_then(|alpha| alpha.get(row_index * width + col)) {
+                        Some(0) => None,
+                        Some(&alpha) => Some((color & 0x00ff_ffff) | (u32::from(alpha) << 24)),
+                        None => Some(color),
+                    }
+                };
+
+                pixels.push(color);
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
 impl Canvas {
+    /// A canvas shown in a window, which is updated by [`Canvas::refresh`]
     #[cfg(feature = "window")]
     pub fn new(width: usize, height: usize) -> Self {
         let mut window = Window::new("PDF", width, height, WindowOptions::default()).unwrap();
@@ -68,27 +368,60 @@ impl Canvas {
         window.limit_update_rate(Some(std::time::Duration::from_millis(33)));
 
         Self {
-            width,
-            height,
-            buffer: vec![u32::MAX; width * height],
-            window,
+            window: Some(window),
+            ..Self::offscreen(width, height)
         }
     }
 
-    #[cfg(not(feature = "window"))]
-    pub fn new(width: usize, height: usize) -> Self {
+    /// A canvas which is only painted in memory, and never opens a window
+    pub fn offscreen(width: usize, height: usize) -> Self {
         Self {
             width,
             height,
             buffer: vec![u32::MAX; width * height],
+            clip: None,
+            #[cfg(feature = "window")]
+            window: None,
         }
     }
 
+    /// The window the canvas is shown in
+    ///
+    /// Panics if the canvas is offscreen
+    #[cfg(feature = "window")]
+    pub fn window(&self) -> &Window {
+        self.window
+            .as_ref()
+            .expect("offscreen canvas is not shown in a window")
+    }
+
+    /// Confine painting to the pixels covered by `clip`, or allow painting
+    /// anywhere on the canvas if it is `None`
+    pub fn set_clip(&mut self, clip: Option<ClipMask>) {
+        self.clip = clip;
+    }
+
+    /// How much of the pixel `x` pixels from the left and `y` pixels from the
+    /// bottom of the canvas may be painted
+    fn clip_coverage(&self, x: usize, y: usize) -> f32 {
+        self.clip.as_ref().map_or(1.0, |clip| clip.coverage(x, y))
+    }
+
     pub fn fill_path_non_zero_winding_number(&mut self, path: &Path, color: u32) {
         self.fill_path_even_odd(path, color)
     }
 
     pub fn fill_path_even_odd(&mut self, path: &Path, color: u32) {
+        self.shade_path_even_odd(path, |_| Some(color))
+    }
+
+    /// Fill `path` with the colour given by `color_at` at the centre of each pixel,
+    /// in device space. Pixels for which it gives `None` are left unpainted
+    pub fn shade_path_even_odd(
+        &mut self,
+        path: &Path,
+        mut color_at: impl FnMut(Point) -> Option<u32>,
+    ) {
         let bbox = path.bounding_box();
 
         let x = bbox.min.x as u32;
@@ -114,8 +447,8 @@ impl Canvas {
 
                 let end = Point::new(point.x + bbox.max.x + 1.0, point.y + bbox.max.y + 1.0);
 
-                if path.intersects_line_even_odd(Line::new(point, end)) {
-                    self.paint_point(point, color, 1.0);
+                let coverage = if path.intersects_line_even_odd(Line::new(point, end)) {
+                    1.0
                 } else {
                     let count = [
                         left,
@@ -131,7 +464,17 @@ impl Canvas {
                     .filter(|point| path.intersects_line_even_odd(Line::new(*point, end)))
                     .count();
 
-                    self.paint_point(point, color, count as f32 / 8.0);
+                    count as f32 / 8.0
+                };
+
+                if coverage > 0.0 {
+                    if let Some(color) = color_at(point) {
+                        // colours may be partially transparent, such as the edges of
+                        // a tiling pattern's cell
+                        let alpha = (color >> 24) as f32 / 255.0;
+
+                        self.paint_point(point, color | 0xff_00_00_00, coverage * alpha);
+                    }
                 }
 
                 w += 1.0;
@@ -148,7 +491,23 @@ impl Canvas {
         }
     }
 
-    pub fn fill_outline_even_odd(&mut self, outline: &Outline, color: u32) {
+    pub fn fill_outline(&mut self, outline: &Outline, color: u32, fill_rule: FillRule) {
+        self.fill_path(&Self::flatten_outline(outline), color, fill_rule);
+    }
+
+    /// Like [`Canvas::fill_outline`], but with a colour that varies across the
+    /// outline, as given by `color_at`
+    pub fn shade_outline(
+        &mut self,
+        outline: &Outline,
+        color_at: impl FnMut(Point) -> Option<u32>,
+        _fill_rule: FillRule,
+    ) {
+        // both fill rules are currently treated as even-odd
+        self.shade_path_even_odd(&Self::flatten_outline(outline), color_at);
+    }
+
+    fn flatten_outline(outline: &Outline) -> Path {
         // todo: optimize to not require allocation or iteration
         let subpaths = outline
             .paths
@@ -156,9 +515,13 @@ impl Canvas {
             .flat_map(|path| path.subpaths.clone())
             .collect();
 
-        let path = Path::from_subpaths(subpaths);
+        let mut path = Path::from_subpaths(subpaths);
+
+        // every pixel is tested against every segment, so flatten curves once
+        // up front rather than once per intersection test
+        path.flatten();
 
-        self.fill_path_even_odd(&path, color);
+        path
     }
 
     pub fn stroke_outline(&mut self, outline: &Outline, color: u32) {
@@ -361,38 +724,49 @@ impl Canvas {
     }
 
     fn paint_point(&mut self, point: Point, color: u32, opacity: f32) {
-        if point.x >= self.width as f32 || point.y >= self.height as f32 {
+        if point.x < 0.0 || point.y < 0.0 {
             return;
         }
 
-        assert!(
-            (point.x as usize) < self.width,
-            "{} < {}",
-            point.x,
-            self.width
-        );
-        assert!((point.y as usize) < self.height);
+        let (x, y) = (point.x as usize, point.y as usize);
 
-        let end = self.width * self.height - 1;
+        let Some(idx) = self.index(x, y) else {
+            return;
+        };
 
-        // todo: what does this condition imply
-        if point.y as usize * self.width > point.x as usize + (end - self.width) {
+        let opacity = opacity * self.clip_coverage(x, y);
+
+        if opacity <= 0.0 {
             return;
         }
 
-        let idx = point.x as usize + (end - self.width) - point.y as usize * self.width;
-
-        self.buffer[idx.min(self.width * self.height - 1)] = if opacity != 1.0 {
-            apply_opacity(
-                color,
-                opacity,
-                self.buffer[idx.min(self.width * self.height - 1)],
-            )
+        self.buffer[idx] = if opacity != 1.0 {
+            apply_opacity(color, opacity, self.buffer[idx])
         } else {
             color
         };
     }
 
+    /// The colour of the pixel `x` pixels from the left and `y` pixels from the bottom
+    /// of the canvas
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.index(x, y).map_or(0, |idx| self.buffer[idx])
+    }
+
+    /// The index into the buffer of the pixel `x` pixels from the left and `y` pixels
+    /// from the bottom of the canvas
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        // rows are stored from the top of the canvas down
+        //
+        // todo: this is offset by one pixel, and the pixel that would come before
+        // the start of the buffer is skipped
+        (x + self.width * self.height - 1).checked_sub((y + 1) * self.width)
+    }
+
     pub fn stroke_quadratic_bezier_curve(&mut self, curve: QuadraticBezierCurve, color: u32) {
         let mut t = 0.0_f32;
 
@@ -417,110 +791,102 @@ impl Canvas {
         }
     }
 
-    pub fn draw_image<'a>(
-        &mut self,
-        image: &ImageXObject<'a>,
-        resolver: &mut dyn Resolve<'a>,
-    ) -> PdfResult<()> {
-        let pixel_data = decode_stream(&image.stream.stream, &image.stream.dict, resolver)?;
-
-        let rgb_data = match image.color_space.as_ref().map(ColorSpace::name) {
-            Some(ColorSpaceName::DeviceGray) => match image.bits_per_component {
-                Some(BitsPerComponent::Eight) => pixel_data
-                    .iter()
-                    .map(|b| ColorSpace::DeviceGray(*b as f32).as_u32())
-                    .collect::<Vec<u32>>(),
-                Some(BitsPerComponent::One) => BitSlice::<u8, Lsb0>::from_slice(&pixel_data)
-                    .iter()
-                    .by_vals()
-                    .map(|b| ColorSpace::DeviceGray(b as u8 as f32).as_u32())
-                    .collect::<Vec<u32>>(),
-                _ => todo!(),
-            },
-            Some(ColorSpaceName::DeviceCMYK) => pixel_data
-                .chunks_exact(4)
-                .map(|chunk| {
-                    ColorSpace::DeviceCMYK {
-                        cyan: chunk[3] as f32,
-                        magenta: chunk[2] as f32,
-                        yellow: chunk[1] as f32,
-                        key: chunk[0] as f32,
-                    }
-                    .as_u32()
-                })
-                .collect::<Vec<u32>>(),
-            Some(ColorSpaceName::CalGray) => todo!("calgray color space image"),
-            Some(ColorSpaceName::CalRGB) => todo!("calrgb color space image"),
-            Some(ColorSpaceName::Lab) => todo!("lab color space image"),
-            Some(ColorSpaceName::ICCBased) => todo!("icc based color space image"),
-            Some(ColorSpaceName::Indexed) => todo!("indexed color space image"),
-            Some(ColorSpaceName::Pattern) => todo!("pattern color space image"),
-            Some(ColorSpaceName::Separation) => todo!("separation color space image"),
-            Some(ColorSpaceName::DeviceN) => todo!("devicen color space image"),
-            Some(ColorSpaceName::DeviceRGB) | None => pixel_data
-                .chunks_exact(3)
-                .map(|chunk| {
-                    ColorSpace::DeviceRGB {
-                        red: chunk[2] as f32,
-                        green: chunk[1] as f32,
-                        blue: chunk[0] as f32,
-                    }
-                    .as_u32()
-                })
-                .collect::<Vec<u32>>(),
+    /// Paint `image` onto the canvas, where `transform` maps the unit square the
+    /// image fills to device space. The first row of pixels is painted at the top
+    /// of the unit square
+    pub fn draw_image(&mut self, image: &DecodedImage, transform: Matrix) {
+        let Some(inverse) = transform.inverse() else {
+            return;
         };
 
-        assert_eq!(rgb_data.len() % image.width as usize, 0);
+        let DecodedImage {
+            width,
+            height,
+            ref pixels,
+        } = *image;
 
-        for i in 0..self.height {
-            let start: usize = i * self.width;
-            let end = start + (image.width as usize).min(self.width);
+        if width == 0 || height == 0 {
+            return;
+        }
 
-            if end > self.width * self.height {
-                break;
-            }
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| transform * Point::new(x, y));
 
-            let image_start = i * image.width as usize;
-            let image_end = image_start + (end - start);
+        let min_x = corners.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let max_y = corners
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::NEG_INFINITY, f32::max);
 
-            if image_end > image.width as usize * image.height as usize
-                || image_end >= rgb_data.len()
-            {
-                break;
-            }
+        let min_x = min_x.floor().max(0.0) as usize;
+        let max_x = (max_x.ceil().max(0.0) as usize).min(self.width);
+        let min_y = min_y.floor().max(0.0) as usize;
+        let max_y = (max_y.ceil().max(0.0) as usize).min(self.height);
 
-            self.buffer
-                .get_mut(start..end)
-                .unwrap()
-                .copy_from_slice(&rgb_data[image_start..image_end]);
-        }
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // sample the image at the center of each device pixel
+                let p = inverse * Point::new(x as f32 + 0.5, y as f32 + 0.5);
 
-        Ok(())
-    }
+                if !(0.0..1.0).contains(&p.x) || !(0.0..1.0).contains(&p.y) {
+                    continue;
+                }
 
-    fn render_to_image(&mut self, p: impl AsRef<FilePath>) {
-        let file = File::create(p).unwrap();
-        let w = &mut BufWriter::new(file);
-        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32); // Width is 2 pixels and height is 1.
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
+                let col = ((p.x * width as f32) as usize).min(width - 1);
+                let row = (((1.0 - p.y) * height as f32) as usize).min(height - 1);
 
-        let mut writer = encoder.write_header().unwrap();
+                if let Some(&Some(color)) = pixels.get(row * width + col) {
+                    let idx = (self.height - 1 - y) * self.width + x;
 
+                    let alpha = (color >> 24) as f32 / 255.0 * self.clip_coverage(x, y);
+
+                    if alpha <= 0.0 {
+                        continue;
+                    }
+
+                    self.buffer[idx] = if alpha >= 1.0 {
+                        color
+                    } else {
+                        apply_opacity(color, alpha, self.buffer[idx])
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn to_rgba_image(&self) -> RgbaImage {
         let data = self
             .buffer
             .iter()
             .flat_map(|x| x.to_le_bytes())
             .collect::<Vec<_>>();
-        writer.write_image_data(&data).unwrap();
+
+        RgbaImage {
+            width: self.width as u32,
+            height: self.height as u32,
+            data,
+        }
+    }
+
+    fn render_to_image(&mut self, p: impl AsRef<FilePath>) {
+        let file = File::create(p).unwrap();
+
+        self.to_rgba_image()
+            .write_png(BufWriter::new(file))
+            .unwrap();
     }
 
     #[allow(unused_variables)]
     pub fn draw(&mut self, scale: &mut f32) {
         #[cfg(feature = "window")]
         {
-            while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
-                if let Some((x, y)) = self.window.get_scroll_wheel() {
+            while self.window().is_open() && !self.window().is_key_down(Key::Escape) {
+                if let Some((x, y)) = self.window().get_scroll_wheel() {
                     if y > 0.0 {
                         *scale *= y;
                     } else if y < 0.0 {
@@ -540,8 +906,8 @@ impl Canvas {
 
     pub fn refresh(&mut self) {
         #[cfg(feature = "window")]
-        {
-            self.window
+        if let Some(window) = &mut self.window {
+            window
                 .update_with_buffer(&self.buffer, self.width, self.height)
                 .unwrap();
         }
@@ -550,4 +916,9 @@ impl Canvas {
     pub fn clear(&mut self) {
         self.buffer = vec![u32::MAX; self.width * self.height];
     }
+
+    /// Set every pixel of the canvas to `color`
+    pub fn fill(&mut self, color: u32) {
+        self.buffer.fill(color);
+    }
 }
@@ -0,0 +1,310 @@
+use crate::{
+    data_structures::Matrix,
+    geometry::{BoundingBox, Line, Path, Point, Subpath},
+};
+
+use super::FillRule;
+
+/// The number of rows sampled within each row of pixels when finding how much of a
+/// pixel lies within a clipping path
+const SAMPLES_PER_PIXEL: usize = 4;
+
+/// The largest distance, in pixels, between the end of one line and the start of
+/// the next for them to be considered part of the same subpath
+const GAP_TOLERANCE: f32 = 1e-3;
+
+/// The region of the page that painting is confined to, in default user space
+///
+/// This is the intersection of the regions enclosed by each of the paths, under their
+/// own fill rule. With no paths, painting is unconfined
+#[derive(Debug, Clone, Default)]
+pub struct ClippingPath {
+    paths: Vec<(Path, FillRule)>,
+}
+
+impl ClippingPath {
+    pub fn new(path: Path) -> Self {
+        Self {
+            paths: vec![(path, FillRule::NonZeroWindingNumber)],
+        }
+    }
+
+    /// Confine the clipping path to the region enclosed by `path`, as determined
+    /// by `fill_rule`
+    pub fn intersect(&mut self, path: Path, fill_rule: FillRule) {
+        self.paths.push((path, fill_rule));
+    }
+
+    /// A box containing every point within the clipping path. The box is empty if
+    /// the clipping path is, and infinite if there is no clipping path
+    pub fn bounding_box(&self) -> BoundingBox {
+        let mut bbox = BoundingBox {
+            min: Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            max: Point::new(f32::INFINITY, f32::INFINITY),
+        };
+
+        for (path, ..) in &self.paths {
+            let other = path.bounding_box();
+
+            bbox.min.x = bbox.min.x.max(other.min.x);
+            bbox.min.y = bbox.min.y.max(other.min.y);
+            bbox.max.x = bbox.max.x.min(other.max.x);
+            bbox.max.y = bbox.max.y.min(other.max.y);
+        }
+
+        bbox
+    }
+}
+
+/// How much of each pixel of a canvas lies within a clipping path, from 0.0 for
+/// pixels outside of it to 1.0 for pixels entirely within it
+#[derive(Debug)]
+pub(super) struct ClipMask {
+    /// The pixels outside of this region, in device space, lie outside of the
+    /// clipping path
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+
+    /// The coverage of each pixel of the region, row by row from the bottom
+    coverage: Vec<f32>,
+}
+
+impl ClipMask {
+    /// Rasterize `clip` for a `width` by `height` canvas, where `transform` maps
+    /// default user space to device space
+    pub fn new(clip: &ClippingPath, transform: Matrix, width: usize, height: usize) -> Self {
+        let clip_lines = clip
+            .paths
+            .iter()
+            .map(|(path, fill_rule)| (device_lines(path, transform), *fill_rule))
+            .collect::<Vec<_>>();
+
+        // only the pixels within the bounds of every path need to be rasterized
+        let (mut min_x, mut min_y) = (0.0_f32, 0.0_f32);
+        let (mut max_x, mut max_y) = (width as f32, height as f32);
+
+        for (lines, ..) in &clip_lines {
+            let mut bbox = BoundingBox::new();
+
+            for line in lines {
+                bbox.merge(line.bounding_box());
+            }
+
+            min_x = min_x.max(bbox.min.x.floor());
+            min_y = min_y.max(bbox.min.y.floor());
+            max_x = max_x.min(bbox.max.x.ceil());
+            max_y = max_y.min(bbox.max.y.ceil());
+        }
+
+        if min_x >= max_x || min_y >= max_y {
+            return Self {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+                coverage: Vec::new(),
+            };
+        }
+
+        let mut mask = Self {
+            x: min_x as usize,
+            y: min_y as usize,
+            width: (max_x - min_x) as usize,
+            height: (max_y - min_y) as usize,
+            coverage: Vec::new(),
+        };
+
+        mask.coverage = vec![1.0; mask.width * mask.height];
+
+        let mut row = vec![0.0; mask.width];
+
+        for (lines, fill_rule) in &clip_lines {
+            for (y, coverage) in mask.coverage.chunks_mut(mask.width).enumerate() {
+                row.fill(0.0);
+
+                for sample in 0..SAMPLES_PER_PIXEL {
+                    let sample_y =
+                        (mask.y + y) as f32 + (sample as f32 + 0.5) / SAMPLES_PER_PIXEL as f32;
+
+                    fill_row(&mut row, lines, *fill_rule, sample_y, mask.x as f32);
+                }
+
+                for (coverage, row) in coverage.iter_mut().zip(&row) {
+                    *coverage *= row.min(1.0);
+                }
+            }
+        }
+
+        mask
+    }
+
+    /// How much of the pixel `x` pixels from the left and `y` pixels from the
+    /// bottom of the canvas lies within the clipping path
+    pub fn coverage(&self, x: usize, y: usize) -> f32 {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return 0.0;
+        }
+
+        self.coverage[(y - self.y) * self.width + (x - self.x)]
+    }
+}
+
+/// The lines making up `path` in device space, with curves flattened and every
+/// subpath closed, as is implied when filling
+fn device_lines(path: &Path, transform: Matrix) -> Vec<Line> {
+    let mut path = path.clone();
+    path.apply_transform(transform);
+    path.flatten();
+
+    let mut lines: Vec<Line> = Vec::with_capacity(path.subpaths.len());
+    let mut subpath_start = None;
+
+    for subpath in path.subpaths {
+        let Subpath::Line(line) = subpath else {
+            unreachable!("paths contain only lines once flattened")
+        };
+
+        // a line which doesn't continue from the previous one begins a new subpath
+        if let Some(previous) = lines.last().copied() {
+            let gap = previous.end - line.start;
+
+            if gap.x.abs() > GAP_TOLERANCE || gap.y.abs() > GAP_TOLERANCE {
+                if let Some(start) = subpath_start.take() {
+                    lines.push(Line::new(previous.end, start));
+                }
+            }
+        }
+
+        subpath_start.get_or_insert(line.start);
+        lines.push(line);
+    }
+
+    if let (Some(start), Some(last)) = (subpath_start, lines.last().copied()) {
+        lines.push(Line::new(last.end, start));
+    }
+
+    lines
+}
+
+/// Add the coverage of one sampled row, at device space height `y`, to a row of
+/// pixels whose first pixel begins at device space `x`
+fn fill_row(row: &mut [f32], lines: &[Line], fill_rule: FillRule, y: f32, x: f32) {
+    // where the sampled row crosses each line, and whether the line runs upwards
+    let mut crossings = lines
+        .iter()
+        .filter_map(|line| {
+            let (start, end, winding) = if line.start.y <= line.end.y {
+                (line.start, line.end, 1)
+            } else {
+                (line.end, line.start, -1)
+            };
+
+            if y < start.y || y >= end.y {
+                return None;
+            }
+
+            let t = (y - start.y) / (end.y - start.y);
+
+            Some((start.x + t * (end.x - start.x), winding))
+        })
+        .collect::<Vec<_>>();
+
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let weight = 1.0 / SAMPLES_PER_PIXEL as f32;
+    let mut winding_number = 0;
+
+    for pair in crossings.windows(2) {
+        let ((span_start, winding), (span_end, ..)) = (pair[0], pair[1]);
+
+        winding_number += winding;
+
+        let is_inside = match fill_rule {
+            FillRule::NonZeroWindingNumber => winding_number != 0,
+            FillRule::EvenOdd => winding_number % 2 != 0,
+        };
+
+        if !is_inside {
+            continue;
+        }
+
+        let span_start = (span_start - x).max(0.0);
+        let span_end = (span_end - x).min(row.len() as f32);
+
+        if span_start >= span_end {
+            continue;
+        }
+
+        for (pixel, coverage) in row
+            .iter_mut()
+            .enumerate()
+            .take(span_end.ceil() as usize)
+            .skip(span_start as usize)
+        {
+            let pixel = pixel as f32;
+
+            *coverage += (span_end.min(pixel + 1.0) - span_start.max(pixel)) * weight;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        data_structures::Matrix,
+        geometry::{Path, Point},
+        render::FillRule,
+    };
+
+    use super::{ClipMask, ClippingPath};
+
+    fn square(min: f32, max: f32) -> Path {
+        let mut path = Path::new(Point::new(min, min));
+        path.line_to(Point::new(max, min));
+        path.line_to(Point::new(max, max));
+        path.line_to(Point::new(min, max));
+        path.close_path();
+        path
+    }
+
+    /// A square with a square hole in it, both drawn in the same direction
+    fn frame() -> Path {
+        let mut path = square(2.0, 8.0);
+        path.subpaths.extend(square(4.0, 6.0).subpaths);
+        path
+    }
+
+    #[test]
+    fn clip_mask_follows_fill_rule() {
+        let mut clip = ClippingPath::new(square(0.0, 10.0));
+        clip.intersect(frame(), FillRule::NonZeroWindingNumber);
+
+        let mask = ClipMask::new(&clip, Matrix::identity(), 10, 10);
+
+        assert_eq!(mask.coverage(0, 0), 0.0);
+        assert_eq!(mask.coverage(2, 2), 1.0);
+        assert_eq!(mask.coverage(5, 5), 1.0);
+        assert_eq!(mask.coverage(8, 8), 0.0);
+
+        let mut clip = ClippingPath::new(square(0.0, 10.0));
+        clip.intersect(frame(), FillRule::EvenOdd);
+
+        let mask = ClipMask::new(&clip, Matrix::identity(), 10, 10);
+
+        assert_eq!(mask.coverage(2, 2), 1.0);
+        assert_eq!(mask.coverage(5, 5), 0.0);
+    }
+
+    #[test]
+    fn clip_mask_covers_partial_pixels() {
+        let clip = ClippingPath::new(square(2.5, 7.5));
+
+        let mask = ClipMask::new(&clip, Matrix::identity(), 10, 10);
+
+        assert_eq!(mask.coverage(2, 4), 0.5);
+        assert_eq!(mask.coverage(2, 2), 0.25);
+        assert_eq!(mask.coverage(4, 4), 1.0);
+    }
+}
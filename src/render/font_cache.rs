@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    mem::ManuallyDrop,
+    ptr::NonNull,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    data_structures::Matrix,
+    error::PdfResult,
+    font::{FontEncoding, Glyph},
+    objects::Reference,
+    Resolve,
+};
+
+use super::RenderableFont;
+
+pub(crate) type SharedFont<'a> = Arc<RwLock<dyn RenderableFont<'a, 'a> + 'a>>;
+
+/// Font programs loaded from embedded font files, keyed by the reference to
+/// the font file stream, so that each is only decoded and parsed once
+pub struct FontCache<'a> {
+    fonts: HashMap<Reference, SharedFont<'a>>,
+}
+
+impl<'a> FontCache<'a> {
+    pub fn new() -> Self {
+        Self {
+            fonts: HashMap::new(),
+        }
+    }
+
+    /// The font program loaded from the font file `reference`, if any
+    pub fn get(&self, reference: Reference) -> Option<SharedFont<'a>> {
+        self.fonts.get(&reference).map(Arc::clone)
+    }
+
+    /// Load a font program from the decoded font file `data`, and cache it
+    pub fn insert<F: RenderableFont<'a, 'a> + 'a>(
+        &mut self,
+        reference: Reference,
+        data: Vec<u8>,
+    ) -> PdfResult<SharedFont<'a>> {
+        let font: SharedFont<'a> = Arc::new(RwLock::new(EmbeddedFont::<F>::new(
+            data.into_boxed_slice(),
+        )?));
+
+        self.fonts.insert(reference, Arc::clone(&font));
+
+        Ok(font)
+    }
+}
+
+/// A font program together with the decoded font file it borrows from, which
+/// lives for as long as the font does, however long that is kept after the
+/// cache is dropped
+struct EmbeddedFont<F> {
+    font: ManuallyDrop<F>,
+
+    /// Allocated by [`Box::into_raw`], and only freed once `font` has been
+    /// dropped
+    data: NonNull<[u8]>,
+}
+
+impl<'a, F: RenderableFont<'a, 'a>> EmbeddedFont<F> {
+    fn new(data: Box<[u8]>) -> PdfResult<Self> {
+        let data = NonNull::from(Box::leak(data));
+
+        // SAFETY: the data is neither modified nor freed until the font, the only
+        // thing given this borrow, has been dropped
+        let bytes: &'a [u8] = unsafe { data.as_ref() };
+
+        match F::load(bytes) {
+            Ok(font) => Ok(Self {
+                font: ManuallyDrop::new(font),
+                data,
+            }),
+            Err(err) => {
+                // SAFETY: the data was allocated by a box, and the failed load
+                // holds no borrow of it
+                drop(unsafe { Box::from_raw(data.as_ptr()) });
+
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<F> Drop for EmbeddedFont<F> {
+    fn drop(&mut self) {
+        // SAFETY: the font is dropped before the data it borrows is freed, and
+        // neither is used again
+        unsafe {
+            ManuallyDrop::drop(&mut self.font);
+            drop(Box::from_raw(self.data.as_ptr()));
+        }
+    }
+}
+
+impl<'a, F: RenderableFont<'a, 'a>> RenderableFont<'a, 'a> for EmbeddedFont<F> {
+    fn load(stream: &'a [u8]) -> PdfResult<Self> {
+        Self::new(stream.into())
+    }
+
+    fn glyph_index(
+        &self,
+        char_code: u32,
+        encoding: Option<&FontEncoding>,
+        is_symbolic: bool,
+    ) -> u32 {
+        self.font.glyph_index(char_code, encoding, is_symbolic)
+    }
+
+    fn evaluate(&mut self, codepoint: u32, resolver: &mut dyn Resolve<'a>) -> PdfResult<Glyph> {
+        self.font.evaluate(codepoint, resolver)
+    }
+
+    fn font_matrix(&self) -> Matrix {
+        self.font.font_matrix()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        data_structures::Matrix, error::PdfResult, font::Glyph, geometry::Point,
+        objects::Reference, render::RenderableFont, Resolve,
+    };
+
+    use super::FontCache;
+
+    /// A font whose font matrix is scaled by the first byte of its font file
+    struct BorrowedFont<'a>(&'a [u8]);
+
+    impl<'a, 'b> RenderableFont<'a, 'b> for BorrowedFont<'a> {
+        fn load(stream: &'a [u8]) -> PdfResult<Self> {
+            Ok(Self(stream))
+        }
+
+        fn evaluate(
+            &mut self,
+            _codepoint: u32,
+            _resolver: &mut dyn Resolve<'b>,
+        ) -> PdfResult<Glyph> {
+            Ok(Glyph::empty())
+        }
+
+        fn font_matrix(&self) -> Matrix {
+            Matrix::new_scale(f32::from(self.0[0]), 1.0)
+        }
+    }
+
+    #[test]
+    fn font_cache_loads_each_font_file_once() {
+        let reference = Reference {
+            object_number: 5,
+            generation: 0,
+        };
+
+        let mut cache = FontCache::new();
+
+        assert!(cache.get(reference).is_none());
+
+        let font = cache
+            .insert::<BorrowedFont>(reference, vec![2, 0, 0])
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&font, &cache.get(reference).unwrap()));
+
+        // the font keeps the font file it borrows alive after the cache is gone
+        let font = cache.get(reference).unwrap();
+        drop(cache);
+
+        let mut point = Point::new(1.0, 1.0);
+        point *= font.read().unwrap().font_matrix();
+
+        assert_eq!(point, Point::new(2.0, 1.0));
+    }
+}
@@ -1,14 +1,17 @@
+use std::rc::Rc;
+
 use crate::{
     color::ColorSpace,
     data_structures::Matrix,
     function::{Function, TransferFunction},
-    geometry::{Path, Point},
     halftones::Halftones,
     resources::graphics_state_parameters::{
         BlendMode, LineCapStyle, LineDashPattern, LineJoinStyle, RenderingIntent, SoftMask,
     },
 };
 
+use super::clip::ClippingPath;
+
 #[derive(Debug, Default, Clone)]
 pub(crate) struct GraphicsState<'a> {
     pub device_independent: DeviceIndependentGraphicsState<'a>,
@@ -54,7 +57,7 @@ pub struct DeviceIndependentGraphicsState<'a> {
     ///
     /// Initial value: the boundary of the entire imageable portion of the
     /// output page.
-    pub clipping_path: Path,
+    pub clipping_path: Rc<ClippingPath>,
 
     /// The current colour space in which colour values shall be interpreted.
     /// There are two separate colour space parameters: one for stroking and
@@ -159,7 +162,7 @@ impl Default for DeviceIndependentGraphicsState<'_> {
     fn default() -> Self {
         Self {
             current_transformation_matrix: Matrix::identity(),
-            clipping_path: Path::new(Point::origin()),
+            clipping_path: Rc::new(ClippingPath::default()),
             color_space: GraphicsStateColorSpace::default(),
             line_width: 1.0,
             line_cap_style: LineCapStyle::Butt,
@@ -1,52 +1,69 @@
 pub(crate) mod canvas;
+pub(crate) mod clip;
 pub(super) mod error;
+pub(crate) mod font_cache;
 pub(crate) mod graphics_state;
+pub(crate) mod shading;
 pub(crate) mod text_state;
+mod tiling;
 mod wgpu;
 
 use std::{
     borrow::Cow,
+    collections::HashMap,
     rc::Rc,
     sync::{Arc, RwLock},
 };
 
 use crate::{
-    color::{ColorSpace, ColorSpaceName},
+    color::{ColorSpace, ColorSpaceName, PatternColorSpace},
     content::{ContentLexer, ContentToken, PdfGraphicsOperator},
-    data_structures::Matrix,
+    data_structures::{Matrix, Rectangle},
+    diagnostic::{Diagnostic, ParseOptions},
     error::PdfResult,
     filter::decode_stream,
     font::{
         true_type::{ParsedTrueTypeFontFile, TrueTypeInterpreter},
-        CffCharStringInterpreter, CffFile, CffParser, CidFontSubtype, CidFontWidths, CidToGidMap,
-        Font, FontEncoding, FontEncodingDict, Glyph, TrueTypeFont, Type0Font, Type1Font, Type3Font,
-        Type3FontFile, Widths, BASE_14_FONTS,
+        CffCharStringInterpreter, CffFile, CffParser, CidFontSubtype, CidToGidMap, Font,
+        FontEncoding, FontEncodingDict, Glyph, TrueTypeFont, Type0Font, Type1Font, Type3Font,
+        Type3FontFile, BASE_14_FONTS,
     },
     geometry::{Outline, Path, Point},
-    objects::Object,
+    objects::{Object, Reference},
     page::PageObject,
     postscript::{charstring::CharStringPainter, font::Type1PostscriptFont, PostscriptInterpreter},
     resources::{
         graphics_state_parameters::{
             LineCapStyle, LineDashPattern, LineJoinStyle, RenderingIntent,
         },
+        pattern::{Pattern, TilingPattern},
         Resources,
     },
+    text::FontDecoder,
     xobject::{FormXObject, XObject},
-    FromObj, Resolve,
+    FromObj, Resolve, Stream,
 };
 
 use anyhow::Context;
-use canvas::Canvas;
+use canvas::{Canvas, DecodedImage};
+
+pub use canvas::RgbaImage;
 #[cfg(feature = "window")]
 use minifb::Key;
 
 use self::{
+    clip::{ClipMask, ClippingPath},
     error::PdfRenderError,
+    font_cache::SharedFont,
     graphics_state::{ColorSpacePosition, GraphicsState},
-    text_state::{TextRenderingMode, TextState},
+    shading::Shader,
+    text_state::{ShownGlyph, TextRenderingMode, TextState},
+    tiling::Tile,
 };
 
+/// The largest number of pixels of a rasterized page
+const MAX_PIXELS: f32 = (1 << 28) as f32;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FillRule {
     EvenOdd,
@@ -66,12 +83,44 @@ pub struct Renderer<'a, 'b: 'a> {
     /// painting of text. These include parameters that select the font, scale
     /// the glyphs to an appropriate size, and accomplish other effects.
     text_state: TextState<'b>,
+
+    /// Decoders for every font used so far, keyed by the address of the font
+    font_decoders: HashMap<*const Font<'b>, Rc<FontDecoder>>,
     page: Rc<PageObject<'b>>,
     resources: Option<Rc<Resources<'b>>>,
     current_path: Option<Path>,
     pending_clip: Option<FillRule>,
     marked_content_stack: Vec<MarkedContentMarker<'b>>,
     scale: f32,
+
+    /// Maps default user space to the pixels of the canvas
+    device_transform: Matrix,
+
+    /// Maps the pattern space of the current content stream to default user
+    /// space. This is the CTM at the start of the content stream
+    pattern_matrix: Matrix,
+
+    /// The tiling patterns whose cells are being recorded, innermost last. A cell
+    /// painting with a pattern already being recorded would never finish
+    recording_patterns: Vec<Reference>,
+
+    /// The cells recorded for each tiling pattern, along with the underlying colour
+    /// and the pattern matrix they were recorded with, so that paths filled with the
+    /// same pattern share a cell
+    tiles: HashMap<Reference, Vec<RecordedTile>>,
+}
+
+/// The underlying colour of an uncoloured tiling pattern, the pattern matrix, and
+/// the cell recorded with them
+type RecordedTile = (Option<u32>, Matrix, Rc<Tile>);
+
+/// The area of the page which is shown, where the crop box is cut down to
+/// the media box
+fn visible_box(page: &PageObject) -> Rectangle {
+    let media_box = page.media_box().unwrap_or(Rectangle::US_LETTER);
+
+    page.crop_box()
+        .map_or(media_box, |crop_box| crop_box.intersection(&media_box))
 }
 
 pub(crate) fn scale_to_fit(width: f32, height: f32) -> f32 {
@@ -89,7 +138,21 @@ struct Renderable {
     outline: Outline,
     stroke_color: Option<u32>,
     fill_color: Option<u32>,
+
+    /// Paints the fill with a colour that varies across the outline, such as a
+    /// gradient, in place of `fill_color`
+    fill_shader: Option<Rc<Shader>>,
+
+    /// Paints the fill with a repeating pattern cell, in place of `fill_color`
+    fill_tile: Option<Rc<Tile>>,
     fill_rule: Option<FillRule>,
+
+    /// An image filling the unit square of this transform, which maps it to default
+    /// user space. The outline is the bounds of the image
+    image: Option<(Rc<DecodedImage>, Matrix)>,
+
+    /// The clipping path in effect when the renderable was painted
+    clip: Rc<ClippingPath>,
 }
 
 impl<'a, 'b: 'a> Renderer<'a, 'b> {
@@ -139,7 +202,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         resolver: &'a mut dyn Resolve<'b>,
         page: Rc<PageObject<'b>>,
     ) -> Self {
-        let media_box = page.media_box().unwrap();
+        let media_box = page.media_box().unwrap_or(Rectangle::US_LETTER);
 
         let mut width = media_box.width().ceil();
         let mut height = media_box.height().ceil();
@@ -149,16 +212,18 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         height *= scale;
 
         let mut graphics_state = GraphicsState::default();
-        graphics_state.device_independent.clipping_path = media_box.as_path();
+        graphics_state.device_independent.clipping_path =
+            Rc::new(ClippingPath::new(visible_box(&page).as_path()));
 
         Self {
             content,
             resolver,
-            canvas: Canvas::new(width as usize, height as usize),
+            canvas: Canvas::offscreen(width as usize, height as usize),
             graphics_state_stack: Vec::new(),
             operand_stack: Vec::new(),
             graphics_state,
             text_state: TextState::default(),
+            font_decoders: HashMap::new(),
             resources: page.resources(),
             page,
             current_path: None,
@@ -166,6 +231,10 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             marked_content_stack: Vec::new(),
             scale,
             scene: Vec::new(),
+            device_transform: Matrix::identity(),
+            pattern_matrix: Matrix::identity(),
+            recording_patterns: Vec::new(),
+            tiles: HashMap::new(),
         }
     }
 
@@ -175,6 +244,10 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             .current_transformation_matrix
     }
 
+    fn clipping_path(&self) -> Rc<ClippingPath> {
+        Rc::clone(&self.graphics_state.device_independent.clipping_path)
+    }
+
     fn render_form_xobject(&mut self, mut form: FormXObject<'b>) -> PdfResult<()> {
         let content_buffer: Cow<'b, [u8]> = decode_stream(
             unsafe { &*(&*form.stream.stream as *const _) },
@@ -182,20 +255,40 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             self.resolver,
         )?;
 
-        let mut form_content = ContentLexer::new(content_buffer);
+        let mut form_content = ContentLexer::with_options(
+            content_buffer,
+            ParseOptions {
+                lenient: self.resolver.is_lenient(),
+                ..ParseOptions::default()
+            },
+        );
 
         self.save_graphics_state()?;
         std::mem::swap(self.content, &mut form_content);
         std::mem::swap(&mut self.resources, &mut form.resources);
         self.graphics_state
             .device_independent
-            .current_transformation_matrix *= form.matrix;
+            .current_transformation_matrix = form.matrix * self.current_transformation_matrix();
+
+        let ctm = self.current_transformation_matrix();
+        let pattern_matrix = std::mem::replace(&mut self.pattern_matrix, ctm);
+
+        // the form is clipped to its bounding box
+        let mut bbox = form.bbox.as_path();
+        bbox.apply_transform(ctm);
+        self.intersect_clipping_path(bbox, FillRule::NonZeroWindingNumber);
 
         self.render_content_stream()?;
 
+        self.pattern_matrix = pattern_matrix;
+
         std::mem::swap(self.content, &mut form_content);
         std::mem::swap(&mut self.resources, &mut form.resources);
 
+        for diagnostic in form_content.take_diagnostics() {
+            self.resolver.report(diagnostic);
+        }
+
         self.restore_graphics_state()?;
 
         Ok(())
@@ -213,90 +306,132 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             .nonstroking
     }
 
+    /// The colour paths are stroked with. Paths can't yet be stroked with a
+    /// pattern, other than with the colour given to an uncoloured tiling pattern,
+    /// so these are reported and left unstroked
+    fn stroke_color(&mut self) -> Option<u32> {
+        match self.stroking_color() {
+            ColorSpace::Pattern(PatternColorSpace {
+                underlying: None, ..
+            }) => {
+                self.resolver.report(Diagnostic::new(
+                    None,
+                    None,
+                    "stroking with a pattern is not supported",
+                ));
+
+                None
+            }
+            color => Some(color.as_u32()),
+        }
+    }
+
+    /// The colour paths are filled with, when they aren't filled by a pattern's
+    /// shader or tile. Patterns have no colour of their own, other than the colour
+    /// given to an uncoloured tiling pattern
+    fn fill_color(&self) -> Option<u32> {
+        match self.non_stroking_color() {
+            ColorSpace::Pattern(PatternColorSpace {
+                underlying: None, ..
+            }) => None,
+            color => Some(color.as_u32()),
+        }
+    }
+
     fn render_content_stream(&mut self) -> PdfResult<()> {
         while let Some(token) = self.content.next() {
             let token = token?;
 
             match token {
                 ContentToken::Object(obj) => self.operand_stack.push(obj),
-                ContentToken::Operator(op) => match op {
-                    PdfGraphicsOperator::G => self.set_stroking_gray()?,
-                    PdfGraphicsOperator::g => self.set_nonstroking_gray()?,
-                    PdfGraphicsOperator::BT => self.begin_text()?,
-                    PdfGraphicsOperator::Tf => self.set_font_and_size()?,
-                    PdfGraphicsOperator::Td => self.move_text_position()?,
-                    PdfGraphicsOperator::TJ => self.draw_text_adjusted()?,
-                    PdfGraphicsOperator::Tj => self.draw_text_unadjusted()?,
-                    PdfGraphicsOperator::q => self.save_graphics_state()?,
-                    PdfGraphicsOperator::Q => self.restore_graphics_state()?,
-                    PdfGraphicsOperator::cm => self.transform_ctm()?,
-                    PdfGraphicsOperator::Do => self.draw_xobject()?,
-                    PdfGraphicsOperator::w => self.set_line_width()?,
-                    PdfGraphicsOperator::re => self.create_rectangle()?,
-                    PdfGraphicsOperator::W => self.set_clipping_path_non_zero_winding_number()?,
-                    PdfGraphicsOperator::W_star => self.set_clipping_path_even_odd()?,
-                    PdfGraphicsOperator::n => self.draw_path_nop()?,
-                    PdfGraphicsOperator::RG => self.set_stroking_rgb()?,
-                    PdfGraphicsOperator::rg => self.set_nonstroking_rgb()?,
-                    PdfGraphicsOperator::ET => self.end_text()?,
-                    PdfGraphicsOperator::BDC => {
-                        self.begin_marked_content_sequence_with_property_list()?
-                    }
-                    PdfGraphicsOperator::EMC => self.end_marked_content_sequence()?,
-                    PdfGraphicsOperator::Tm => self.set_text_matrix()?,
-                    PdfGraphicsOperator::gs => self.set_graphics_state_parameters()?,
-                    PdfGraphicsOperator::f | PdfGraphicsOperator::F => {
-                        self.fill_path(FillRule::NonZeroWindingNumber)?
+                ContentToken::Operator(op) => {
+                    if self
+                        .text_state
+                        .apply_operator(op, &mut self.operand_stack, self.resolver)?
+                    {
+                        continue;
                     }
-                    PdfGraphicsOperator::f_star => self.fill_path(FillRule::EvenOdd)?,
-                    PdfGraphicsOperator::m => self.move_to()?,
-                    PdfGraphicsOperator::l => self.line_to()?,
-                    PdfGraphicsOperator::h => self.close_path()?,
-                    PdfGraphicsOperator::S => self.stroke_path()?,
-                    PdfGraphicsOperator::k => self.set_nonstroking_cmyk()?,
-                    PdfGraphicsOperator::K => self.set_stroking_cmyk()?,
-                    PdfGraphicsOperator::TL => self.set_text_leading()?,
-                    PdfGraphicsOperator::c => self.curve_to()?,
-                    PdfGraphicsOperator::v => self.curve_to_initial_replicated()?,
-                    PdfGraphicsOperator::y => self.curve_to_final_replicated()?,
-                    PdfGraphicsOperator::CS => self.set_stroking_color_space()?,
-                    PdfGraphicsOperator::cs => self.set_nonstroking_color_space()?,
-                    PdfGraphicsOperator::SC | PdfGraphicsOperator::SCN => {
-                        self.set_stroking_color()?
-                    }
-                    PdfGraphicsOperator::sc | PdfGraphicsOperator::scn => {
-                        self.set_nonstroking_color()?
+
+                    if let Some(arr) =
+                        self.text_state
+                            .text_to_show(op, &mut self.operand_stack, self.resolver)?
+                    {
+                        self.draw_text(arr)?;
+                        continue;
                     }
-                    PdfGraphicsOperator::i => self.set_flatness_tolerance()?,
-                    PdfGraphicsOperator::Tc => self.set_character_spacing()?,
-                    PdfGraphicsOperator::Tw => self.set_word_spacing()?,
-                    PdfGraphicsOperator::Ts => self.set_text_rise()?,
-                    PdfGraphicsOperator::Tz => self.set_horizontal_scaling()?,
-                    PdfGraphicsOperator::Tr => self.set_text_rendering_mode()?,
-                    PdfGraphicsOperator::TD => self.move_text_position_and_set_leading()?,
-                    PdfGraphicsOperator::T_star => self.move_to_next_line()?,
-                    PdfGraphicsOperator::BMC => self.begin_marked_content_sequence()?,
-                    PdfGraphicsOperator::J => self.set_line_cap_style()?,
-                    PdfGraphicsOperator::d => self.set_line_dash_pattern()?,
-                    PdfGraphicsOperator::j => self.set_line_join_style()?,
-                    PdfGraphicsOperator::B => {
-                        self.stroke_and_fill(FillRule::NonZeroWindingNumber)?
+
+                    match op {
+                        PdfGraphicsOperator::G => self.set_stroking_gray()?,
+                        PdfGraphicsOperator::g => self.set_nonstroking_gray()?,
+                        PdfGraphicsOperator::Tf => self.set_font_and_size()?,
+                        PdfGraphicsOperator::q => self.save_graphics_state()?,
+                        PdfGraphicsOperator::Q => self.restore_graphics_state()?,
+                        PdfGraphicsOperator::cm => self.transform_ctm()?,
+                        PdfGraphicsOperator::Do => self.draw_xobject()?,
+                        PdfGraphicsOperator::w => self.set_line_width()?,
+                        PdfGraphicsOperator::re => self.create_rectangle()?,
+                        PdfGraphicsOperator::W => {
+                            self.set_clipping_path_non_zero_winding_number()?
+                        }
+                        PdfGraphicsOperator::W_star => self.set_clipping_path_even_odd()?,
+                        PdfGraphicsOperator::n => self.draw_path_nop()?,
+                        PdfGraphicsOperator::RG => self.set_stroking_rgb()?,
+                        PdfGraphicsOperator::rg => self.set_nonstroking_rgb()?,
+                        PdfGraphicsOperator::BDC => {
+                            self.begin_marked_content_sequence_with_property_list()?
+                        }
+                        PdfGraphicsOperator::EMC => self.end_marked_content_sequence()?,
+                        PdfGraphicsOperator::gs => self.set_graphics_state_parameters()?,
+                        PdfGraphicsOperator::f | PdfGraphicsOperator::F => {
+                            self.fill_path(FillRule::NonZeroWindingNumber)?
+                        }
+                        PdfGraphicsOperator::f_star => self.fill_path(FillRule::EvenOdd)?,
+                        PdfGraphicsOperator::m => self.move_to()?,
+                        PdfGraphicsOperator::l => self.line_to()?,
+                        PdfGraphicsOperator::h => self.close_path()?,
+                        PdfGraphicsOperator::S => self.stroke_path()?,
+                        PdfGraphicsOperator::k => self.set_nonstroking_cmyk()?,
+                        PdfGraphicsOperator::K => self.set_stroking_cmyk()?,
+                        PdfGraphicsOperator::c => self.curve_to()?,
+                        PdfGraphicsOperator::v => self.curve_to_initial_replicated()?,
+                        PdfGraphicsOperator::y => self.curve_to_final_replicated()?,
+                        PdfGraphicsOperator::CS => self.set_stroking_color_space()?,
+                        PdfGraphicsOperator::cs => self.set_nonstroking_color_space()?,
+                        PdfGraphicsOperator::SC | PdfGraphicsOperator::SCN => {
+                            self.set_stroking_color()?
+                        }
+                        PdfGraphicsOperator::sc | PdfGraphicsOperator::scn => {
+                            self.set_nonstroking_color()?
+                        }
+                        PdfGraphicsOperator::i => self.set_flatness_tolerance()?,
+                        PdfGraphicsOperator::BMC => self.begin_marked_content_sequence()?,
+                        PdfGraphicsOperator::J => self.set_line_cap_style()?,
+                        PdfGraphicsOperator::d => self.set_line_dash_pattern()?,
+                        PdfGraphicsOperator::j => self.set_line_join_style()?,
+                        PdfGraphicsOperator::B => {
+                            self.stroke_and_fill(FillRule::NonZeroWindingNumber)?
+                        }
+                        PdfGraphicsOperator::B_star => self.stroke_and_fill(FillRule::EvenOdd)?,
+                        PdfGraphicsOperator::M => self.set_miter_limit()?,
+                        PdfGraphicsOperator::s => self.close_and_stroke_path()?,
+                        // compat section is handled in lexer
+                        PdfGraphicsOperator::BX | PdfGraphicsOperator::EX => {}
+                        PdfGraphicsOperator::sh => self.paint_using_shading_pattern()?,
+                        PdfGraphicsOperator::ri => self.set_color_rendering_intent()?,
+                        _ => todo!("unimplemented operator: {:?}", op),
                     }
-                    PdfGraphicsOperator::B_star => self.stroke_and_fill(FillRule::EvenOdd)?,
-                    PdfGraphicsOperator::M => self.set_miter_limit()?,
-                    PdfGraphicsOperator::s => self.close_and_stroke_path()?,
-                    // compat section is handled in lexer
-                    PdfGraphicsOperator::BX | PdfGraphicsOperator::EX => {}
-                    PdfGraphicsOperator::sh => self.paint_using_shading_pattern()?,
-                    PdfGraphicsOperator::ri => self.set_color_rendering_intent()?,
-                    _ => todo!("unimplemented operator: {:?}", op),
-                },
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Paint the shape and colour shading described by a shading dictionary,
+    /// subject to the current clipping path. The current colour in the graphics
+    /// state is neither used nor altered
+    ///
+    /// The shading is painted in user space, and its background is ignored
     fn paint_using_shading_pattern(&mut self) -> PdfResult<()> {
         let name = self.pop_name()?;
 
@@ -306,18 +441,247 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                 .as_ref()
                 .and_then(|shading| shading.get(&name));
 
-            match shade {
-                Some(shade) => todo!("{:#?}", shade),
+            let shader = match shade {
+                Some(shade) => Shader::new(shade, self.current_transformation_matrix(), false)?,
                 None => todo!("unable to locate shade {:#?}", name),
+            };
+
+            let Some(shader) = shader else {
+                self.resolver.report(Diagnostic::new(
+                    None,
+                    None,
+                    format!("skipping unsupported shading {}", name),
+                ));
+
+                return Ok(());
+            };
+
+            // the shading covers the entire clipping path, so fill the box around it
+            // and leave the clipping path to confine it
+            let bbox = self.clipping_path().bounding_box();
+
+            if !bbox.width().is_finite() || !bbox.height().is_finite() {
+                return Ok(());
             }
+
+            let mut bounds = Path::new(bbox.min);
+            bounds.line_to(Point::new(bbox.max.x, bbox.min.y));
+            bounds.line_to(bbox.max);
+            bounds.line_to(Point::new(bbox.min.x, bbox.max.y));
+            bounds.close_path();
+
+            self.scene.push(Renderable {
+                outline: Outline::new(vec![bounds]),
+                stroke_color: None,
+                fill_color: None,
+                fill_shader: Some(Rc::new(shader)),
+                fill_tile: None,
+                fill_rule: Some(FillRule::NonZeroWindingNumber),
+                image: None,
+                clip: self.clipping_path(),
+            });
         }
 
         Ok(())
     }
 
+    /// The shading to fill paths with, if the nonstroking colour is a shading
+    /// pattern
+    fn nonstroking_shader(&self) -> PdfResult<Option<Rc<Shader>>> {
+        let pattern = match self.non_stroking_color() {
+            ColorSpace::Pattern(PatternColorSpace {
+                pattern: Some(pattern),
+                ..
+            }) => pattern,
+            _ => return Ok(None),
+        };
+
+        match &**pattern {
+            Pattern::Shading(pattern) => {
+                Ok(
+                    Shader::new(&pattern.shading, pattern.matrix * self.pattern_matrix, true)?
+                        .map(Rc::new),
+                )
+            }
+            Pattern::Tiling(..) => Ok(None),
+        }
+    }
+
+    /// The pattern cell to fill paths with, if the nonstroking colour is a tiling
+    /// pattern
+    fn nonstroking_tile(&mut self) -> PdfResult<Option<Rc<Tile>>> {
+        let nonstroking = &self
+            .graphics_state
+            .device_independent
+            .color_space
+            .nonstroking;
+
+        let (pattern, underlying) = match nonstroking {
+            ColorSpace::Pattern(PatternColorSpace {
+                pattern: Some(pattern),
+                underlying,
+            }) => (Rc::clone(pattern), underlying.clone()),
+            _ => return Ok(None),
+        };
+
+        match &*pattern {
+            Pattern::Tiling(tiling) => {
+                let reference = tiling.indirect_reference;
+
+                if reference.is_some_and(|r| self.recording_patterns.contains(&r)) {
+                    self.resolver.report(Diagnostic::new(
+                        reference,
+                        None,
+                        "skipping tiling pattern painted within its own cell",
+                    ));
+
+                    return Ok(None);
+                }
+
+                let color = if tiling.is_uncolored() {
+                    underlying.as_deref()
+                } else {
+                    None
+                };
+
+                let key = (color.map(ColorSpace::as_u32), self.pattern_matrix);
+
+                let recorded = reference.and_then(|reference| {
+                    self.tiles
+                        .get(&reference)?
+                        .iter()
+                        .find(|(color, matrix, _)| (*color, *matrix) == key)
+                        .map(|(.., tile)| Rc::clone(tile))
+                });
+
+                let tile = match recorded {
+                    Some(tile) => tile,
+                    None => {
+                        self.recording_patterns.extend(reference);
+                        let tile = self.record_tile(tiling, color);
+                        if reference.is_some() {
+                            self.recording_patterns.pop();
+                        }
+                        let tile = Rc::new(tile?);
+
+                        if let Some(reference) = reference {
+                            self.tiles.entry(reference).or_default().push((
+                                key.0,
+                                key.1,
+                                Rc::clone(&tile),
+                            ));
+                        }
+
+                        tile
+                    }
+                };
+
+                if tile.is_too_large(self.device_transform) {
+                    self.resolver.report(Diagnostic::new(
+                        reference,
+                        None,
+                        "skipping tiling pattern with a cell too large to paint",
+                    ));
+
+                    return Ok(None);
+                }
+
+                Ok(Some(tile))
+            }
+            Pattern::Shading(..) => Ok(None),
+        }
+    }
+
+    /// Run the content stream of a tiling pattern's cell, with the pattern's own
+    /// resources, and collect what it paints. Uncoloured patterns are painted with
+    /// `color`
+    fn record_tile(
+        &mut self,
+        pattern: &TilingPattern<'b>,
+        color: Option<&ColorSpace<'b>>,
+    ) -> PdfResult<Tile> {
+        let content_buffer =
+            decode_stream(&pattern.stream.stream, &pattern.stream.dict, self.resolver)?
+                .into_owned();
+
+        let mut cell_content = ContentLexer::with_options(
+            Cow::Owned(content_buffer),
+            ParseOptions {
+                lenient: self.resolver.is_lenient(),
+                ..ParseOptions::default()
+            },
+        );
+
+        // the pattern matrix maps pattern space to the default coordinate space of
+        // the pattern's parent content stream
+        let matrix = pattern.matrix * self.pattern_matrix;
+
+        self.save_graphics_state()?;
+        std::mem::swap(self.content, &mut cell_content);
+
+        let mut resources = Some(Rc::clone(&pattern.resources));
+        std::mem::swap(&mut self.resources, &mut resources);
+
+        let mut scene = std::mem::take(&mut self.scene);
+        let current_path = self.current_path.take();
+        let pending_clip = self.pending_clip.take();
+        let pattern_matrix = std::mem::replace(&mut self.pattern_matrix, matrix);
+
+        // cells are confined to their bounding box when the pattern is painted, so
+        // the clipping path need only bound them. clipping exactly at the bounding
+        // box would leave partly transparent pixels along the edges of every cell
+        let (lower_left, upper_right) = (pattern.bbox.lower_left(), pattern.bbox.upper_right());
+        let margin = upper_right - lower_left;
+        let (min, max) = (lower_left - margin, upper_right + margin);
+
+        let mut clipping_path = Path::new(min);
+        clipping_path.line_to(Point::new(max.x, min.y));
+        clipping_path.line_to(max);
+        clipping_path.line_to(Point::new(min.x, max.y));
+        clipping_path.close_path();
+        clipping_path.apply_transform(matrix);
+
+        let state = &mut self.graphics_state.device_independent;
+        state.current_transformation_matrix = matrix;
+        state.clipping_path = Rc::new(ClippingPath::new(clipping_path));
+
+        if let Some(color) = color {
+            state.color_space.stroking = color.clone();
+            state.color_space.nonstroking = color.clone();
+        }
+
+        let result = self.render_content_stream();
+
+        self.pattern_matrix = pattern_matrix;
+        self.pending_clip = pending_clip;
+        self.current_path = current_path;
+        std::mem::swap(&mut self.scene, &mut scene);
+
+        std::mem::swap(self.content, &mut cell_content);
+        std::mem::swap(&mut self.resources, &mut resources);
+
+        for diagnostic in cell_content.take_diagnostics() {
+            self.resolver.report(diagnostic);
+        }
+
+        self.restore_graphics_state()?;
+
+        result?;
+
+        Ok(Tile::new(
+            scene,
+            matrix,
+            pattern.bbox,
+            pattern.x_step,
+            pattern.y_step,
+        ))
+    }
+
     fn stroke_and_fill(&mut self, fill_rule: FillRule) -> PdfResult<()> {
-        let stroke_color = self.stroking_color().as_u32();
-        let fill_color = self.non_stroking_color().as_u32();
+        let stroke_color = self.stroke_color();
+        let fill_color = self.fill_color();
+        let fill_shader = self.nonstroking_shader()?;
+        let fill_tile = self.nonstroking_tile()?;
 
         let ctm = self.current_transformation_matrix();
         let mut path = self
@@ -327,17 +691,19 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         path.apply_transform(ctm);
 
-        if let Some(clip) = self.pending_clip.take() {
-            println!("unimplemented clipping path operator {:?}", clip);
-        }
+        // the path is painted subject to the clipping path in effect before it
+        let clip = self.clipping_path();
+        self.update_clipping_path(&path);
 
-        // todo: gpu stroke
-        self.canvas.stroke_path(&path, stroke_color);
         self.scene.push(Renderable {
             outline: Outline::new(vec![path]),
-            stroke_color: None,
-            fill_color: Some(fill_color),
+            stroke_color,
+            fill_color,
+            fill_shader,
+            fill_tile,
             fill_rule: Some(fill_rule),
+            image: None,
+            clip,
         });
 
         Ok(())
@@ -346,30 +712,23 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
     fn render_cpu(&mut self) -> PdfResult<()> {
         #[cfg(feature = "window")]
         {
+            self.canvas = Canvas::new(self.canvas.width, self.canvas.height);
+
             let mut transform = Matrix::identity();
             let mut changed = true;
 
-            while self.canvas.window.is_open() && !self.canvas.window.is_key_down(Key::Escape) {
+            while self.canvas.window().is_open() && !self.canvas.window().is_key_down(Key::Escape) {
                 let start = std::time::Instant::now();
                 if changed {
                     self.canvas.clear();
-                    for renderable in &self.scene {
-                        if let Some(stroke_color) = renderable.stroke_color {
-                            let mut outline = renderable.outline.clone();
-                            outline.apply_transform(transform);
-                            // outline.apply_transform(Matrix::new_scale(self.scale, self.scale));
-
-                            self.canvas.fill_outline_even_odd(&outline, stroke_color);
-                            break;
-                        }
-                    }
+                    paint_scene(&mut self.canvas, &self.scene, transform);
                     changed = false;
                 }
                 dbg!(start.elapsed());
 
-                if let Some((x, y)) = self.canvas.window.get_scroll_wheel() {
-                    let is_ctrl = self.canvas.window.is_key_down(Key::LeftSuper)
-                        || self.canvas.window.is_key_down(Key::RightSuper);
+                if let Some((x, y)) = self.canvas.window().get_scroll_wheel() {
+                    let is_ctrl = self.canvas.window().is_key_down(Key::LeftSuper)
+                        || self.canvas.window().is_key_down(Key::RightSuper);
                     if is_ctrl {
                         let y_pct = y / (self.canvas.height as f32 / 2.0);
                         self.scale = 1.0 + y_pct;
@@ -382,7 +741,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                     changed = true;
                 }
 
-                if self.canvas.window.is_key_down(Key::R) {
+                if self.canvas.window().is_key_down(Key::R) {
                     transform = Matrix::identity();
                 }
 
@@ -405,6 +764,78 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
+    /// Render the page entirely on the CPU, without opening a window or
+    /// touching the GPU
+    ///
+    /// `dpi` is the resolution of the resulting bitmap. A page is 72 units of
+    /// default user space per inch, so a `dpi` of 72.0 produces one pixel per
+    /// unit of the crop box. The page is turned by its `Rotate` entry, as it
+    /// would be displayed.
+    pub fn rasterize(self, dpi: f32) -> PdfResult<RgbaImage> {
+        self.rasterize_with_background(dpi, u32::MAX)
+    }
+
+    /// Like [`Renderer::rasterize`], but the page is composited over `background`
+    /// rather than opaque white. The color is in the same little-endian RGBA
+    /// layout used by the canvas, so `0` yields a transparent background
+    pub fn rasterize_with_background(mut self, dpi: f32, background: u32) -> PdfResult<RgbaImage> {
+        let visible_box = visible_box(&self.page);
+        let scale = (dpi / 72.0) * self.page.user_unit;
+
+        let page_width = visible_box.width() * scale;
+        let page_height = visible_box.height() * scale;
+
+        let origin = visible_box.lower_left();
+        let to_pixels = Matrix::new(scale, 0.0, 0.0, scale, -origin.x * scale, -origin.y * scale);
+
+        // the page is turned clockwise, and moved back onto the canvas
+        let (rotation, width, height) = match self.page.rotate().unwrap_or(0).rem_euclid(360) / 90 {
+            1 => (
+                Matrix::new(0.0, -1.0, 1.0, 0.0, 0.0, page_width),
+                page_height,
+                page_width,
+            ),
+            2 => (
+                Matrix::new(-1.0, 0.0, 0.0, -1.0, page_width, page_height),
+                page_width,
+                page_height,
+            ),
+            3 => (
+                Matrix::new(0.0, 1.0, -1.0, 0.0, page_height, 0.0),
+                page_height,
+                page_width,
+            ),
+            _ => (Matrix::identity(), page_width, page_height),
+        };
+
+        self.device_transform = to_pixels * rotation;
+
+        let width = width.ceil();
+        let height = height.ceil();
+
+        let num_pixels = width * height;
+
+        if !num_pixels.is_finite() || num_pixels > MAX_PIXELS {
+            anyhow::bail!(
+                "rasterized page of {}x{} pixels is too large",
+                width,
+                height
+            );
+        }
+
+        let width = width as usize;
+        let height = height as usize;
+
+        self.canvas = Canvas::offscreen(width, height);
+        self.canvas.fill(background);
+
+        self.render_content_stream()?;
+
+        paint_scene(&mut self.canvas, &self.scene, self.device_transform);
+
+        Ok(self.canvas.to_rgba_image())
+    }
+
     pub fn render(mut self) -> PdfResult<()> {
         self.render_content_stream()?;
 
@@ -444,7 +875,8 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             ColorSpace::DeviceN(..) => {
                 todo!()
             }
-            ColorSpace::Pattern(..) => {
+            ColorSpace::Pattern(space) => {
+                let underlying = space.underlying.clone();
                 let name = self.pop_name()?;
 
                 let pattern = self
@@ -457,7 +889,25 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                     .get(&name)
                     .map(Rc::clone);
 
-                ColorSpace::Pattern(pattern)
+                // uncoloured tiling patterns are preceded by the colour to paint
+                // their cell with, in the underlying colour space
+                let underlying = match (&pattern, underlying) {
+                    (Some(pattern), Some(underlying)) if matches!(&**pattern, Pattern::Tiling(tiling) if tiling.is_uncolored()) =>
+                    {
+                        let mut components = (0..underlying.num_components())
+                            .map(|_| self.pop_number())
+                            .collect::<PdfResult<Vec<f32>>>()?;
+                        components.reverse();
+
+                        Some(Rc::new(underlying.with_components(&components)?))
+                    }
+                    (_, underlying) => underlying,
+                };
+
+                ColorSpace::Pattern(PatternColorSpace {
+                    pattern,
+                    underlying,
+                })
             }
             ColorSpace::Indexed { space, .. } => {
                 let space = Rc::clone(space);
@@ -513,25 +963,6 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
-    /// Move to the start of the next line. This operator has the same effect
-    /// as the code
-    ///
-    /// 0 -Tl Td
-    ///
-    /// where Tl denotes the current leading parameter in the text state. The
-    /// negative of Tl is used here because Tl is the text leading expressed as
-    /// a positive number. Going to the next line entails decreasing the y
-    /// coordinate.
-    fn move_to_next_line(&mut self) -> PdfResult<()> {
-        let matrix = Matrix::new_translation(0.0, -self.text_state.leading)
-            * self.text_state.text_line_matrix;
-
-        self.text_state.text_matrix = matrix;
-        self.text_state.text_line_matrix = matrix;
-
-        Ok(())
-    }
-
     /// Set the current colour space to use for stroking operations. The operand
     /// name shall be a name object. If the colour space is one that can be
     /// specified by a name and no additional parameters (DeviceGray, DeviceRGB,
@@ -722,14 +1153,6 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
-    fn set_text_leading(&mut self) -> PdfResult<()> {
-        let leading = self.pop_number()?;
-
-        self.text_state.leading = leading;
-
-        Ok(())
-    }
-
     fn set_flatness_tolerance(&mut self) -> PdfResult<()> {
         let tolerance = self.pop_number()?;
 
@@ -738,46 +1161,6 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
-    fn set_character_spacing(&mut self) -> PdfResult<()> {
-        let spacing = self.pop_number()?;
-
-        self.text_state.character_spacing = spacing;
-
-        Ok(())
-    }
-
-    fn set_word_spacing(&mut self) -> PdfResult<()> {
-        let spacing = self.pop_number()?;
-
-        self.text_state.word_spacing = spacing;
-
-        Ok(())
-    }
-
-    fn set_text_rise(&mut self) -> PdfResult<()> {
-        let rise = self.pop_number()?;
-
-        self.text_state.rise = rise;
-
-        Ok(())
-    }
-
-    fn set_horizontal_scaling(&mut self) -> PdfResult<()> {
-        let horizontal_scaling = self.pop_number()?;
-
-        self.text_state.horizontal_scaling = horizontal_scaling / 100.0;
-
-        Ok(())
-    }
-
-    fn set_text_rendering_mode(&mut self) -> PdfResult<()> {
-        let rendering_mode = self.pop_integer()?;
-
-        self.text_state.rendering_mode = TextRenderingMode::from_integer(rendering_mode)?;
-
-        Ok(())
-    }
-
     /// Set the stroking colour space to DeviceCMYK (or the DefaultCMYK colour
     /// space) and set the colour to use for stroking operations. Each operand
     /// shall be a number between 0.0 (zero concentration) and 1.0 (maximum
@@ -841,7 +1224,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
     /// Stroke the path.
     fn stroke_path(&mut self) -> PdfResult<()> {
-        let color = self.stroking_color().as_u32();
+        let color = self.stroke_color();
 
         let ctm = self.current_transformation_matrix();
         let mut path = self
@@ -851,11 +1234,19 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         path.apply_transform(ctm);
 
-        if let Some(clip) = self.pending_clip.take() {
-            println!("unimplemented clipping path operator {:?}", clip);
-        }
+        let clip = self.clipping_path();
+        self.update_clipping_path(&path);
 
-        self.canvas.stroke_path(&path, color);
+        self.scene.push(Renderable {
+            outline: Outline::new(vec![path]),
+            stroke_color: color,
+            fill_color: None,
+            fill_shader: None,
+            fill_tile: None,
+            fill_rule: None,
+            image: None,
+            clip,
+        });
 
         Ok(())
     }
@@ -863,7 +1254,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
     /// Close and stroke the path. This operator shall have the same effect as
     /// the sequence `h S`.
     fn close_and_stroke_path(&mut self) -> PdfResult<()> {
-        let color = self.stroking_color().as_u32();
+        let color = self.stroke_color();
 
         let ctm = self.current_transformation_matrix();
         let mut path = self
@@ -875,11 +1266,19 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         path.apply_transform(ctm);
 
-        if let Some(clip) = self.pending_clip.take() {
-            println!("unimplemented clipping path operator {:?}", clip);
-        }
+        let clip = self.clipping_path();
+        self.update_clipping_path(&path);
 
-        self.canvas.stroke_path(&path, color);
+        self.scene.push(Renderable {
+            outline: Outline::new(vec![path]),
+            stroke_color: color,
+            fill_color: None,
+            fill_shader: None,
+            fill_tile: None,
+            fill_rule: None,
+            image: None,
+            clip,
+        });
 
         Ok(())
     }
@@ -950,30 +1349,24 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
     fn fill_path(&mut self, fill_rule: FillRule) -> PdfResult<()> {
         let mut path = match self.current_path.take() {
             Some(p) => p,
-            None => return Ok(()),
+            None => {
+                self.update_clipping_path(&Path::new(Point::origin()));
+                return Ok(());
+            }
         };
 
-        let color = self
-            .graphics_state
-            .device_independent
-            .color_space
-            .nonstroking
-            .as_u32();
+        let color = self.fill_color();
+        let fill_shader = self.nonstroking_shader()?;
+        let fill_tile = self.nonstroking_tile()?;
 
-        path.clip(&self.graphics_state.device_independent.clipping_path);
         path.apply_transform(self.current_transformation_matrix());
 
-        if let Some(clip) = self.pending_clip.take() {
-            println!("unimplemented clipping path operator {:?}", clip);
-        }
-
         // todo: don't fill shapes we know to be completely off screen
         if false
             && !path.bounding_box().overlaps(
                 self.graphics_state
                     .device_independent
                     .clipping_path
-                    .clone()
                     .bounding_box(),
             )
         {
@@ -981,11 +1374,18 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             return Ok(());
         }
 
+        let clip = self.clipping_path();
+        self.update_clipping_path(&path);
+
         self.scene.push(Renderable {
             outline: Outline::new(vec![path]),
             stroke_color: None,
-            fill_color: Some(color),
+            fill_color: color,
+            fill_shader,
+            fill_tile,
             fill_rule: Some(fill_rule),
+            image: None,
+            clip,
         });
 
         Ok(())
@@ -1031,23 +1431,6 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
-    /// Begin a text object, initializing the text matrix, Tm, and the text line
-    /// matrix, Tlm, to the identity matrix. Text objects shall not be nested;
-    /// a second BT shall not appear before an ET.
-    fn begin_text(&mut self) -> PdfResult<()> {
-        self.text_state.reinit();
-
-        Ok(())
-    }
-
-    /// End a text object, discarding the text matrix.
-    fn end_text(&mut self) -> PdfResult<()> {
-        self.text_state.text_matrix = Matrix::identity();
-        self.text_state.text_line_matrix = Matrix::identity();
-
-        Ok(())
-    }
-
     /// Set the text font to _font_ and the text font size to _size_. _font_
     /// shall be the name of a font resource in the Font subdictionary of the
     /// current resource dictionary; size shall be a number representing a scale
@@ -1066,6 +1449,13 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         match font {
             Some(font) => {
+                if !self.font_decoders.contains_key(&Rc::as_ptr(&font)) {
+                    let decoder = FontDecoder::new(&font, &font_name, self.resolver)?;
+
+                    self.font_decoders
+                        .insert(Rc::as_ptr(&font), Rc::new(decoder));
+                }
+
                 self.text_state.font = Some(font);
                 self.text_state.font_size = size;
             }
@@ -1075,54 +1465,13 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
         Ok(())
     }
 
-    /// Move to the start of the next line, offset from the start of the current
-    /// line by (t_x, t_y). t_x and t_y shall denote numbers expressed in
-    /// unscaled text space units. More precisely, this operator shall perform
-    /// these assignments:
-    ///
-    /// T_m = T_lm = [1 0 0, 0 1 0, t_x t_y 1] * T_lm
-    fn move_text_position(&mut self) -> PdfResult<()> {
-        let t_y = self.pop_number()?;
-        let t_x = self.pop_number()?;
-
-        let matrix = Matrix::new_translation(t_x, t_y) * self.text_state.text_line_matrix;
-
-        self.text_state.text_matrix = matrix;
-        self.text_state.text_line_matrix = matrix;
-
-        Ok(())
-    }
-
-    /// Move to the start of the next line, offset from the start of the current
-    /// line by (t_x, t_y). As a side effect, this operator shall set the leading
-    /// parameter in the text state. This operator shall have the same effect as
-    /// this code:
-    ///
-    /// −t_y TL
-    /// t_x t_y Td
-    ///
-    fn move_text_position_and_set_leading(&mut self) -> PdfResult<()> {
-        let t_y = self.pop_number()?;
-        let t_x = self.pop_number()?;
-
-        self.text_state.leading = -t_y;
-
-        let matrix = Matrix::new_translation(t_x, t_y) * self.text_state.text_line_matrix;
-
-        self.text_state.text_matrix = matrix;
-        self.text_state.text_line_matrix = matrix;
-
-        Ok(())
-    }
-
     fn draw_text(&mut self, arr: Vec<Object<'b>>) -> PdfResult<()> {
-        // todo: should actually get undefined/default width from the glyph itself
-        let default_width = Widths::new(Some(Vec::new()), Some(0), Some(0), 1000.0).unwrap();
-        let ffs: Cow<[u8]>;
-        let stream: Cow<[u8]>;
-        let font: Arc<RwLock<dyn RenderableFont>>;
-        let cid_font_widths: CidFontWidths;
-        let widths: &dyn FontMetrics;
+        // glyphs are filled, so they are painted with the nonstroking colour
+        let fill_color = self.fill_color();
+        let fill_shader = self.nonstroking_shader()?;
+        let fill_tile = self.nonstroking_tile()?;
+
+        let font: SharedFont<'b>;
 
         match self.text_state.font.as_deref() {
             Some(Font::Type1(Type1Font {
@@ -1131,14 +1480,13 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                 let font_file = base
                     .font_descriptor
                     .as_ref()
-                    .and_then(|desc| desc.font_file.clone());
+                    .and_then(|desc| desc.font_file.as_ref());
 
                 if font_file.is_none() {
                     if base.font_descriptor.is_none() {
                         let base_font = BASE_14_FONTS.get(&base_font.as_ref()).unwrap();
 
                         font = Arc::<RwLock<Type1PostscriptFont>>::clone(base_font);
-                        widths = base.widths.as_ref().unwrap_or(&default_width);
                     } else {
                         let descriptor = base.font_descriptor.as_ref().unwrap();
 
@@ -1146,21 +1494,14 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                             let base_font = BASE_14_FONTS.get(&base_font.as_ref()).unwrap();
 
                             font = Arc::<RwLock<Type1PostscriptFont>>::clone(base_font);
-                            widths = base.widths.as_ref().unwrap_or(&default_width);
                         } else {
-                            match descriptor.font_file_three.as_ref().unwrap().clone() {
+                            match descriptor.font_file_three.as_ref().unwrap() {
                                 Type3FontFile::CompactType1(compact_type1) => {
-                                    ffs = compact_type1.stream.stream;
-                                    stream = decode_stream(
-                                        &ffs,
-                                        &compact_type1.stream.dict,
+                                    font = embedded_font::<CffFile>(
+                                        &compact_type1.stream,
+                                        compact_type1.reference,
                                         self.resolver,
                                     )?;
-
-                                    let cff_file = CffFile::load(&stream)?;
-
-                                    font = Arc::new(RwLock::new(cff_file));
-                                    widths = base.widths.as_ref().unwrap_or(&default_width);
                                 }
                                 Type3FontFile::CompactType0Cid(_) => todo!(),
                                 Type3FontFile::OpenType(_) => todo!(),
@@ -1169,20 +1510,19 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                     }
                 } else {
                     let font_file = font_file.unwrap();
-                    ffs = font_file.stream.stream;
-                    stream = decode_stream(&ffs, &font_file.stream.dict, self.resolver)?;
 
-                    font = Arc::new(RwLock::new(Type1PostscriptFont::load(&stream)?));
-                    widths = base.widths.as_ref().unwrap();
+                    font = embedded_font::<Type1PostscriptFont>(
+                        &font_file.stream,
+                        font_file.reference,
+                        self.resolver,
+                    )?;
                 }
             }
             Some(Font::TrueType(TrueTypeFont { base, .. })) => {
                 let font_file = base
                     .font_descriptor
                     .as_ref()
-                    .and_then(|desc| desc.font_file_two.clone());
-
-                widths = base.widths.as_ref().unwrap();
+                    .and_then(|desc| desc.font_file_two.as_ref());
 
                 if font_file.is_none() {
                     let base_font = BASE_14_FONTS
@@ -1192,10 +1532,12 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                     font = Arc::<RwLock<Type1PostscriptFont>>::clone(base_font);
                 } else {
                     let font_file = font_file.unwrap();
-                    ffs = font_file.stream.stream;
-                    stream = decode_stream(&ffs, &font_file.stream.dict, self.resolver)?;
 
-                    font = Arc::new(RwLock::new(TrueTypeInterpreter::load(&stream)?));
+                    font = embedded_font::<TrueTypeInterpreter>(
+                        &font_file.stream,
+                        font_file.reference,
+                        self.resolver,
+                    )?;
                 }
             }
             Some(Font::Type0(Type0Font {
@@ -1206,41 +1548,36 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
                 match descendant_font.subtype {
                     CidFontSubtype::CidFontType0 => {
-                        let font_file = descendant_font.font_descriptor.font_file.clone();
+                        let font_file = descendant_font.font_descriptor.font_file.as_ref();
                         if font_file.is_none() {
                             println!("skipping unsupported type 0 cid font");
                             return Ok(());
                         }
 
                         let font_file = font_file.unwrap();
-                        ffs = font_file.stream.stream;
-                        stream = decode_stream(&ffs, &font_file.stream.dict, self.resolver)?;
-                        font = Arc::new(RwLock::new(Type1PostscriptFont::load(&stream)?));
+
+                        font = embedded_font::<Type1PostscriptFont>(
+                            &font_file.stream,
+                            font_file.reference,
+                            self.resolver,
+                        )?;
                     }
                     CidFontSubtype::CidFontType2 => {
                         let font_file = descendant_font
                             .font_descriptor
                             .font_file_two
-                            .clone()
+                            .as_ref()
                             .unwrap();
 
-                        ffs = font_file.stream.stream;
-
-                        stream = decode_stream(&ffs, &font_file.stream.dict, self.resolver)?;
-
-                        font = Arc::new(RwLock::new(TrueTypeInterpreter::load(&stream)?));
+                        font = embedded_font::<TrueTypeInterpreter>(
+                            &font_file.stream,
+                            font_file.reference,
+                            self.resolver,
+                        )?;
                     }
                 }
-
-                if descendant_font.default_width != 1000 {
-                    cid_font_widths = CidFontWidths::with_default(descendant_font.default_width);
-                    widths = &cid_font_widths;
-                } else {
-                    widths = &descendant_font.widths;
-                }
             }
             Some(Font::Type3(type3_font)) => {
-                widths = type3_font.base.widths.as_ref().unwrap();
                 font = Arc::new(RwLock::new(type3_font.clone()));
             }
             Some(font @ Font::MmType1(_)) => todo!("unimplemented mm font: {:#?}", font),
@@ -1249,101 +1586,73 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         assert_eq!(self.text_state.rendering_mode, TextRenderingMode::Fill);
 
-        for obj in arr {
-            let obj = self.resolver.resolve(obj)?;
-
-            let s = match obj {
-                Object::String(s) => s,
-                // todo: consolidate integer/float handling here
-                Object::Real(n) => {
-                    self.text_state.text_matrix *=
-                        Matrix::new_translation((-n * self.text_state.font_size) / 1000.0, 0.0);
-                    continue;
-                }
-                Object::Integer(n) => {
-                    self.text_state.text_matrix *= Matrix::new_translation(
-                        (-n as f32 * self.text_state.font_size) / 1000.0,
-                        0.0,
-                    );
-                    continue;
-                }
-                _ => todo!(),
-            };
-
-            for c in s.chars() {
-                let trm = Matrix::new(
-                    self.text_state.font_size * self.text_state.horizontal_scaling,
-                    0.0,
-                    0.0,
-                    self.text_state.font_size,
-                    0.0,
-                    self.text_state.rise,
-                ) * font.read().unwrap().font_matrix()
-                    * self.text_state.text_matrix
-                    * self
-                        .graphics_state
-                        .device_independent
-                        .current_transformation_matrix;
-
-                let mut glyph = font.write().unwrap().evaluate(c as u32, self.resolver)?;
-
-                glyph.outline.apply_transform(trm);
-
-                self.scene.push(Renderable {
-                    outline: glyph.outline,
-                    stroke_color: Some(
-                        self.graphics_state
-                            .device_independent
-                            .color_space
-                            .stroking
-                            .as_u32(),
-                    ),
-                    fill_color: None,
-                    fill_rule: None,
-                });
-
-                let mut x_transform = widths.get(c as u32) * self.text_state.font_size
-                    + self.text_state.character_spacing;
+        let decoder = self.font_decoder()?;
+        let font_matrix = font.read().unwrap().font_matrix();
 
-                // todo: this should actually consult the cmap
-                if c == ' ' {
-                    x_transform += self.text_state.word_spacing * self.scale;
-                }
+        let glyphs = self.text_state.show_text(
+            arr,
+            &decoder,
+            self.current_transformation_matrix(),
+            self.resolver,
+        )?;
 
-                x_transform *= self.text_state.horizontal_scaling;
+        for ShownGlyph {
+            char: c,
+            text_rendering_matrix,
+        } in glyphs
+        {
+            // the CIDToGIDMap of a CIDFont is the identity, so its glyphs are
+            // selected by CID
+            let glyph_id = match decoder.cid(c.code) {
+                Some(cid) => cid,
+                None => font.read().unwrap().glyph_index(
+                    c.code,
+                    decoder.encoding(),
+                    decoder.is_symbolic(),
+                ),
+            };
 
-                self.text_state.text_matrix *= Matrix::new_translation(x_transform, 0.0);
-            }
+            let mut glyph = font.write().unwrap().evaluate(glyph_id, self.resolver)?;
+
+            glyph
+                .outline
+                .apply_transform(font_matrix * text_rendering_matrix);
+
+            self.scene.push(Renderable {
+                outline: glyph.outline,
+                stroke_color: None,
+                fill_color,
+                fill_shader: fill_shader.clone(),
+                fill_tile: fill_tile.clone(),
+                fill_rule: Some(FillRule::NonZeroWindingNumber),
+                image: None,
+                clip: self.clipping_path(),
+            });
         }
 
         Ok(())
     }
 
-    /// Show one or more text strings, allowing individual glyph positioning.
-    ///
-    /// Each element of array shall be either a string or a number. If the element
-    /// is a string, this operator shall show the string. If it is a number,
-    /// the operator shall adjust the text position by that amount; that is, it
-    /// shall translate the text matrix, Tm. The number shall be expressed in
-    /// thousandths of a unit of text space. This amount shall be subtracted
-    /// from the current horizontal or vertical coordinate, depending on the
-    /// writing mode. In the default coordinate system, a positive adjustment has
-    /// the effect of moving the next glyph painted either to the left or down
-    /// by the given amount.
-    fn draw_text_adjusted(&mut self) -> PdfResult<()> {
-        let arr = self.pop_arr()?;
-
-        self.draw_text(arr)?;
+    /// The decoder for the current font, which splits the strings shown into
+    /// character codes and gives their widths
+    fn font_decoder(&mut self) -> PdfResult<Rc<FontDecoder>> {
+        let font = match &self.text_state.font {
+            Some(font) => Rc::clone(font),
+            None => anyhow::bail!("no font selected in text state"),
+        };
 
-        Ok(())
-    }
+        if let Some(decoder) = self.font_decoders.get(&Rc::as_ptr(&font)) {
+            return Ok(Rc::clone(decoder));
+        }
 
-    fn draw_text_unadjusted(&mut self) -> PdfResult<()> {
-        let s = self.pop_string()?;
+        // fonts selected by a graphics state parameter dictionary have no
+        // resource name
+        let decoder = Rc::new(FontDecoder::new(&font, "", self.resolver)?);
 
-        self.draw_text(vec![Object::String(s)])?;
+        self.font_decoders
+            .insert(Rc::as_ptr(&font), Rc::clone(&decoder));
 
-        Ok(())
+        Ok(decoder)
     }
 
     /// Save the current graphics state on the graphics state stack
@@ -1376,7 +1685,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         self.graphics_state
             .device_independent
-            .current_transformation_matrix *= matrix;
+            .current_transformation_matrix = matrix * self.current_transformation_matrix();
 
         Ok(())
     }
@@ -1396,7 +1705,30 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
                 .and_then(|xobject| xobject.get(&name));
 
             match xobject {
-                Some(XObject::Image(image)) => self.canvas.draw_image(image, self.resolver)?,
+                Some(XObject::Image(image)) => {
+                    let fill_color = self.non_stroking_color().as_u32();
+                    let image = DecodedImage::new(image, fill_color, self.resolver)?;
+
+                    let ctm = self.current_transformation_matrix();
+
+                    let mut bounds = Path::new(Point::new(0.0, 0.0));
+                    bounds.line_to(Point::new(1.0, 0.0));
+                    bounds.line_to(Point::new(1.0, 1.0));
+                    bounds.line_to(Point::new(0.0, 1.0));
+                    bounds.close_path();
+                    bounds.apply_transform(ctm);
+
+                    self.scene.push(Renderable {
+                        outline: Outline::new(vec![bounds]),
+                        stroke_color: None,
+                        fill_color: None,
+                        fill_shader: None,
+                        fill_tile: None,
+                        fill_rule: None,
+                        image: Some((Rc::new(image), ctm)),
+                        clip: self.clipping_path(),
+                    });
+                }
                 Some(XObject::Form(form)) => {
                     let form: FormXObject<'b> = FormXObject::clone(form);
 
@@ -1441,6 +1773,7 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
             .get_or_insert_with(|| Path::new(Point::origin()));
 
         path.move_to(Point::new(x, y));
+        path.start = Point::new(x, y);
         path.line_to(Point::new(x + width, y));
         path.line_to(Point::new(x + width, y + height));
         path.line_to(Point::new(x, y + height));
@@ -1471,15 +1804,37 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
     /// be a path-painting no-op, used primarily for the side effect of changing
     /// the current clipping path
     fn draw_path_nop(&mut self) -> PdfResult<()> {
-        if let Some(clip) = self.pending_clip.take() {
-            println!("unimplemented clipping path operator {:?}", clip);
-        }
+        let mut path = self
+            .current_path
+            .take()
+            .unwrap_or_else(|| Path::new(Point::origin()));
+
+        path.apply_transform(self.current_transformation_matrix());
 
-        self.current_path = None;
+        self.update_clipping_path(&path);
 
         Ok(())
     }
 
+    /// Once the current path has been painted, make it part of the clipping path if
+    /// it was marked as such by `W` or `W*`. `path` is in default user space
+    fn update_clipping_path(&mut self, path: &Path) {
+        if let Some(fill_rule) = self.pending_clip.take() {
+            self.intersect_clipping_path(path.clone(), fill_rule);
+        }
+    }
+
+    /// Confine painting to the region enclosed by `path`, in default user space,
+    /// in addition to the current clipping path
+    fn intersect_clipping_path(&mut self, path: Path, fill_rule: FillRule) {
+        let clipping_path = &mut self.graphics_state.device_independent.clipping_path;
+
+        let mut clip = ClippingPath::clone(clipping_path);
+        clip.intersect(path, fill_rule);
+
+        *clipping_path = Rc::new(clip);
+    }
+
     /// Begin a marked-content sequence terminated by a balancing EMC operator.
     /// tag shall be a name object indicating the role or significance of the
     /// sequence.
@@ -1518,25 +1873,54 @@ impl<'a, 'b: 'a> Renderer<'a, 'b> {
 
         Ok(())
     }
+}
 
-    /// Set the text matrix, Tm, and the text line matrix, Tlm:
-    ///
-    /// T_m = T_lm = [a b 0, c d 0, e f 1]
-    fn set_text_matrix(&mut self) -> PdfResult<()> {
-        let f = self.pop_number()?;
-        let e = self.pop_number()?;
-        let d = self.pop_number()?;
-        let c = self.pop_number()?;
-        let b = self.pop_number()?;
-        let a = self.pop_number()?;
+/// Paint every renderable in the scene onto the canvas, in order, after mapping
+/// it into device space
+fn paint_scene(canvas: &mut Canvas, scene: &[Renderable], transform: Matrix) {
+    // consecutive renderables usually share a clipping path, so it is only
+    // rasterized again when it changes
+    let mut clip: Option<&Rc<ClippingPath>> = None;
+
+    for renderable in scene {
+        if !clip.is_some_and(|clip| Rc::ptr_eq(clip, &renderable.clip)) {
+            canvas.set_clip(Some(ClipMask::new(
+                &renderable.clip,
+                transform,
+                canvas.width,
+                canvas.height,
+            )));
+
+            clip = Some(&renderable.clip);
+        }
 
-        let matrix = Matrix::new(a, b, c, d, e, f);
+        let mut outline = renderable.outline.clone();
+        outline.apply_transform(transform);
 
-        self.text_state.text_matrix = matrix;
-        self.text_state.text_line_matrix = matrix;
+        let fill_rule = renderable
+            .fill_rule
+            .unwrap_or(FillRule::NonZeroWindingNumber);
 
-        Ok(())
+        if let Some((image, matrix)) = &renderable.image {
+            canvas.draw_image(image, *matrix * transform);
+        } else if let Some(shader) = &renderable.fill_shader {
+            if let Some(color_at) = shader.device_colors(transform, canvas.width, canvas.height) {
+                canvas.shade_outline(&outline, color_at, fill_rule);
+            }
+        } else if let Some(tile) = &renderable.fill_tile {
+            if let Some(color_at) = tile.device_colors(transform) {
+                canvas.shade_outline(&outline, color_at, fill_rule);
+            }
+        } else if let Some(fill_color) = renderable.fill_color {
+            canvas.fill_outline(&outline, fill_color, fill_rule);
+        }
+
+        if let Some(stroke_color) = renderable.stroke_color {
+            canvas.stroke_outline(&outline, stroke_color);
+        }
     }
+
+    canvas.set_clip(None);
 }
 
 #[pdf_enum(Integer)]
@@ -1577,11 +1961,47 @@ impl<'a, 'b> RenderableFont<'a, 'b> for Type3Font<'b> {
     }
 }
 
+/// Load the font program in an embedded font file, or reuse it if it has
+/// already been loaded from the same font file
+fn embedded_font<'b, F: RenderableFont<'b, 'b> + 'b>(
+    stream: &Stream<'b>,
+    reference: Option<Reference>,
+    resolver: &mut dyn Resolve<'b>,
+) -> PdfResult<SharedFont<'b>> {
+    let reference = match reference {
+        Some(reference) => reference,
+        None => anyhow::bail!("font file streams must be indirect objects"),
+    };
+
+    if let Some(font) = resolver.font_cache().and_then(|cache| cache.get(reference)) {
+        return Ok(font);
+    }
+
+    let data = decode_stream(&stream.stream, &stream.dict, resolver)?.into_owned();
+
+    match resolver.font_cache() {
+        Some(cache) => cache.insert::<F>(reference, data),
+        None => anyhow::bail!("fonts can only be loaded by a resolver with a font cache"),
+    }
+}
+
 pub trait RenderableFont<'a, 'b> {
     fn load(stream: &'a [u8]) -> PdfResult<Self>
     where
         Self: Sized;
-    // todo: optimize this to cache construction of evaluation engine
+
+    /// The glyph selected by a character code of a simple font, to be passed to
+    /// [`RenderableFont::evaluate`]. Font programs with their own encoding are
+    /// evaluated by character code, so this is the code itself
+    fn glyph_index(
+        &self,
+        char_code: u32,
+        _encoding: Option<&FontEncoding>,
+        _is_symbolic: bool,
+    ) -> u32 {
+        char_code
+    }
+
     fn evaluate(&mut self, codepoint: u32, resolver: &mut dyn Resolve<'b>) -> PdfResult<Glyph>;
     fn font_matrix(&self) -> Matrix;
 }
@@ -1618,6 +2038,15 @@ impl<'a, 'b: 'a> RenderableFont<'a, 'b> for TrueTypeInterpreter<'a> {
         Ok(TrueTypeInterpreter::new(file))
     }
 
+    fn glyph_index(
+        &self,
+        char_code: u32,
+        encoding: Option<&FontEncoding>,
+        is_symbolic: bool,
+    ) -> u32 {
+        TrueTypeInterpreter::glyph_index(self, char_code, encoding, is_symbolic)
+    }
+
     fn evaluate(&mut self, codepoint: u32, _resolver: &mut dyn Resolve<'b>) -> PdfResult<Glyph> {
         self.render_glyph(codepoint)
     }
@@ -1657,29 +2086,663 @@ impl<'a, 'b: 'a> RenderableFont<'a, 'b> for CffFile<'a> {
     }
 }
 
-trait FontMetrics {
-    fn get(&self, codepoint: u32) -> f32;
-}
-
-impl FontMetrics for Widths {
-    fn get(&self, codepoint: u32) -> f32 {
-        self.get(codepoint)
-    }
-}
-
-impl FontMetrics for CidFontWidths {
-    fn get(&self, codepoint: u32) -> f32 {
-        self.map
-            .get(&(codepoint as i32))
-            .copied()
-            .unwrap_or(self.default as f32)
-            / 1000.0
-    }
-}
-
 #[derive(Debug, Clone)]
 struct MarkedContentMarker<'a> {
     tag: String,
     // todo: type
     properties: Option<Object<'a>>,
 }
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::{
+        color::ColorSpace,
+        error::PdfResult,
+        geometry::Point,
+        objects::{Dictionary, Object},
+        stream::Stream,
+        test_util::{
+            dict, document, name, numbers, reference, single_page_document,
+            JP2_WITH_PALETTE_AND_ALPHA,
+        },
+        Parser, XrefFormat,
+    };
+
+    use super::{Renderer, RgbaImage};
+
+    fn rasterize(content: &[u8], dpi: f32, background: u32) -> RgbaImage {
+        let file = single_page_document(content, Dictionary::empty(), Vec::new());
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page))
+            .rasterize_with_background(dpi, background)
+            .unwrap()
+    }
+
+    /// The RGBA components of the pixel `x` pixels from the left and `y` pixels
+    /// from the top of `image`
+    fn pixel(image: &RgbaImage, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * image.width + x) * 4) as usize;
+
+        image.data[idx..idx + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn rasterize_filled_rectangle() {
+        let content = b"0 0 1 rg 72 72 144 144 re f";
+
+        let image = rasterize(content, 144.0, u32::MAX);
+
+        assert_eq!((image.width, image.height), (1224, 1584));
+        assert_eq!(image.data.len(), 1224 * 1584 * 4);
+
+        // the rectangle spans 144 to 432 pixels from the left and bottom
+        assert_eq!(pixel(&image, 288, 1584 - 288), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 600, 200), [255, 255, 255, 255]);
+
+        let image = rasterize(content, 144.0, 0);
+
+        assert_eq!(pixel(&image, 288, 1584 - 288), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 600, 200), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn page_without_contents_is_blank() {
+        let file = document(
+            vec![
+                Object::Dictionary(dict(vec![
+                    ("Type", name("Catalog")),
+                    ("Pages", Object::Reference(reference(2))),
+                ])),
+                Object::Dictionary(dict(vec![
+                    ("Type", name("Pages")),
+                    ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+                    ("Count", Object::Integer(1)),
+                ])),
+                Object::Dictionary(dict(vec![
+                    ("Type", name("Page")),
+                    ("Parent", Object::Reference(reference(2))),
+                    ("MediaBox", numbers(&[0.0, 0.0, 100.0, 50.0])),
+                    ("Resources", Object::Dictionary(Dictionary::empty())),
+                ])),
+            ],
+            XrefFormat::Table,
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let image = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page))
+            .rasterize_with_background(72.0, u32::MAX)
+            .unwrap();
+
+        assert_eq!((image.width, image.height), (100, 50));
+        assert!(image.data.iter().all(|&b| b == 255));
+    }
+
+    /// Rasterize a page with the given entries, besides its parent, resources
+    /// and contents, which paints `content`
+    fn rasterize_page(
+        entries: Vec<(&str, Object<'static>)>,
+        content: &[u8],
+        dpi: f32,
+    ) -> PdfResult<RgbaImage> {
+        let mut page = vec![
+            ("Type", name("Page")),
+            ("Parent", Object::Reference(reference(2))),
+            ("Resources", Object::Dictionary(Dictionary::empty())),
+            ("Contents", Object::Reference(reference(4))),
+        ];
+        page.extend(entries);
+
+        let file = document(
+            vec![
+                Object::Dictionary(dict(vec![
+                    ("Type", name("Catalog")),
+                    ("Pages", Object::Reference(reference(2))),
+                ])),
+                Object::Dictionary(dict(vec![
+                    ("Type", name("Pages")),
+                    ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+                    ("Count", Object::Integer(1)),
+                ])),
+                Object::Dictionary(dict(page)),
+                Object::Stream(Stream::new(Dictionary::empty(), content.to_vec()).unwrap()),
+            ],
+            XrefFormat::Table,
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page)).rasterize(dpi)
+    }
+
+    #[test]
+    fn crop_box_is_cut_down_to_media_box() {
+        let image = rasterize_page(
+            vec![
+                ("MediaBox", numbers(&[0.0, 0.0, 200.0, 100.0])),
+                ("CropBox", numbers(&[50.0, 0.0, 250.0, 100.0])),
+            ],
+            b"1 0 0 rg 50 0 10 10 re f",
+            72.0,
+        )
+        .unwrap();
+
+        assert_eq!((image.width, image.height), (150, 100));
+        assert_eq!(pixel(&image, 5, 95), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 15, 95), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn pages_are_rotated_clockwise() {
+        // a square in the bottom left corner of the page
+        let rasterize_rotated = |rotate| {
+            rasterize_page(
+                vec![
+                    ("MediaBox", numbers(&[0.0, 0.0, 200.0, 100.0])),
+                    ("Rotate", Object::Integer(rotate)),
+                ],
+                b"1 0 0 rg 0 0 10 10 re f",
+                72.0,
+            )
+            .unwrap()
+        };
+
+        let image = rasterize_rotated(90);
+        assert_eq!((image.width, image.height), (100, 200));
+        assert_eq!(pixel(&image, 5, 5), [255, 0, 0, 255]);
+
+        let image = rasterize_rotated(180);
+        assert_eq!((image.width, image.height), (200, 100));
+        assert_eq!(pixel(&image, 195, 5), [255, 0, 0, 255]);
+
+        let image = rasterize_rotated(-90);
+        assert_eq!((image.width, image.height), (100, 200));
+        assert_eq!(pixel(&image, 95, 195), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 5, 195), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn oversized_pages_are_rejected() {
+        let media_box = ("MediaBox", numbers(&[0.0, 0.0, 612.0, 792.0]));
+
+        assert!(rasterize_page(vec![media_box.clone()], b"", 1e6).is_err());
+        assert!(rasterize_page(vec![media_box.clone()], b"", f32::INFINITY).is_err());
+        assert!(rasterize_page(vec![media_box.clone()], b"", f32::NAN).is_err());
+        assert!(rasterize_page(vec![media_box], b"", 36.0).is_ok());
+    }
+
+    #[test]
+    fn images_are_painted_in_content_stream_order() {
+        // a single green pixel, stretched over a 100 by 100 square
+        let image = Stream::new(
+            dict(vec![
+                ("Type", name("XObject")),
+                ("Subtype", name("Image")),
+                ("Width", Object::Integer(1)),
+                ("Height", Object::Integer(1)),
+                ("ColorSpace", name("DeviceRGB")),
+                ("BitsPerComponent", Object::Integer(8)),
+            ]),
+            vec![0, 255, 0],
+        )
+        .unwrap();
+        let resources = dict(vec![(
+            "XObject",
+            Object::Dictionary(dict(vec![("Im1", Object::Reference(reference(5)))])),
+        )]);
+
+        let file = single_page_document(
+            b"0 0 1 rg 0 0 612 792 re f q 100 0 0 100 72 72 cm /Im1 Do Q 1 0 0 rg 72 72 50 100 re f",
+            resources,
+            vec![Object::Stream(image)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let image = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page))
+            .rasterize(72.0)
+            .unwrap();
+
+        // the image covers the blue background, and is itself partly covered by
+        // the red rectangle
+        assert_eq!(pixel(&image, 20, 20), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 150, 792 - 100), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 100, 792 - 100), [255, 0, 0, 255]);
+    }
+
+    /// Paint a 2 by 1 pixel image with 8 bit samples in `color_space`, stretched
+    /// over the 200 by 100 rectangle in the bottom left of the page. Any further
+    /// objects it refers to are numbered from 6
+    fn rasterize_image(
+        color_space: Object<'static>,
+        samples: Vec<u8>,
+        objects: Vec<Object<'static>>,
+    ) -> PdfResult<RgbaImage> {
+        let image = Stream::new(
+            dict(vec![
+                ("Type", name("XObject")),
+                ("Subtype", name("Image")),
+                ("Width", Object::Integer(2)),
+                ("Height", Object::Integer(1)),
+                ("ColorSpace", color_space),
+                ("BitsPerComponent", Object::Integer(8)),
+            ]),
+            samples,
+        )
+        .unwrap();
+        let resources = dict(vec![(
+            "XObject",
+            Object::Dictionary(dict(vec![("Im1", Object::Reference(reference(5)))])),
+        )]);
+
+        let mut all_objects = vec![Object::Stream(image)];
+        all_objects.extend(objects);
+
+        let file = single_page_document(b"200 0 0 100 0 0 cm /Im1 Do", resources, all_objects);
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page)).rasterize(72.0)
+    }
+
+    #[test]
+    fn indexed_images_are_painted_through_their_color_table() {
+        let lookup = Stream::new(Dictionary::empty(), vec![255, 0, 0, 0, 0, 255]).unwrap();
+        let color_space = Object::Array(vec![
+            name("Indexed"),
+            name("DeviceRGB"),
+            Object::Integer(1),
+            Object::Reference(reference(6)),
+        ]);
+
+        let image = rasterize_image(color_space, vec![1, 0], vec![Object::Stream(lookup)]).unwrap();
+
+        assert_eq!(pixel(&image, 50, 792 - 50), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 150, 792 - 50), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn icc_based_images_are_painted_in_device_color_space() {
+        // a profile header for an RGB colour space, followed by an empty tag table
+        let mut profile = vec![0; 132];
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile[36..40].copy_from_slice(b"acsp");
+
+        let profile = Stream::new(dict(vec![("N", Object::Integer(3))]), profile).unwrap();
+        let color_space = Object::Array(vec![name("ICCBased"), Object::Reference(reference(6))]);
+
+        let image = rasterize_image(
+            color_space,
+            vec![0, 255, 0, 255, 255, 0],
+            vec![Object::Stream(profile)],
+        )
+        .unwrap();
+
+        assert_eq!(pixel(&image, 50, 792 - 50), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 150, 792 - 50), [255, 255, 0, 255]);
+    }
+
+    #[test]
+    fn images_in_unsupported_color_spaces_are_rejected() {
+        let tint_transform = dict(vec![
+            ("FunctionType", Object::Integer(2)),
+            ("Domain", numbers(&[0.0, 1.0])),
+            ("C0", numbers(&[1.0])),
+            ("C1", numbers(&[0.0])),
+            ("N", Object::Integer(1)),
+        ]);
+        let color_space = Object::Array(vec![
+            name("Separation"),
+            name("Spot"),
+            name("DeviceGray"),
+            Object::Dictionary(tint_transform),
+        ]);
+
+        assert!(rasterize_image(color_space, vec![0, 255], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn jpx_images_with_opacity_are_blended() {
+        let image = Stream::new(
+            dict(vec![
+                ("Type", name("XObject")),
+                ("Subtype", name("Image")),
+                ("Width", Object::Integer(4)),
+                ("Height", Object::Integer(2)),
+                ("Filter", name("JPXDecode")),
+                ("SMaskInData", Object::Integer(1)),
+            ]),
+            JP2_WITH_PALETTE_AND_ALPHA.to_vec(),
+        )
+        .unwrap();
+        let resources = dict(vec![(
+            "XObject",
+            Object::Dictionary(dict(vec![("Im1", Object::Reference(reference(5)))])),
+        )]);
+
+        let file = single_page_document(
+            b"0 0 1 rg 0 0 612 792 re f 400 0 0 200 0 0 cm /Im1 Do",
+            resources,
+            vec![Object::Stream(image)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let image = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page))
+            .rasterize(72.0)
+            .unwrap();
+
+        // the left column is transparent, the right opaque, and those between
+        // are blended with the blue background
+        assert_eq!(pixel(&image, 50, 792 - 150), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 350, 792 - 150), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 350, 792 - 50), [0, 255, 0, 255]);
+
+        let [red, green, blue, _] = pixel(&image, 150, 792 - 150);
+        assert_eq!(red, 0);
+        assert!(green > 64 && blue > 64);
+    }
+
+    #[test]
+    fn text_is_filled_with_nonstroking_color() {
+        // a Type 3 font whose only glyph is a square filling its em box
+        let font = dict(vec![
+            ("Type", name("Font")),
+            ("Subtype", name("Type3")),
+            ("FontBBox", numbers(&[0.0, 0.0, 1000.0, 1000.0])),
+            ("FontMatrix", numbers(&[0.001, 0.0, 0.0, 0.001, 0.0, 0.0])),
+            (
+                "CharProcs",
+                Object::Dictionary(dict(vec![("square", Object::Reference(reference(5)))])),
+            ),
+            (
+                "Encoding",
+                Object::Dictionary(dict(vec![(
+                    "Differences",
+                    Object::Array(vec![Object::Integer(65), name("square")]),
+                )])),
+            ),
+            ("FirstChar", Object::Integer(65)),
+            ("LastChar", Object::Integer(65)),
+            ("Widths", numbers(&[1000.0])),
+        ]);
+        let resources = dict(vec![(
+            "Font",
+            Object::Dictionary(dict(vec![("F1", Object::Dictionary(font))])),
+        )]);
+        let glyph = Stream::new(
+            Dictionary::empty(),
+            b"1000 0 0 0 1000 1000 d1 0 0 1000 1000 re f".to_vec(),
+        )
+        .unwrap();
+
+        let file = single_page_document(
+            b"1 0 0 rg 0 0 1 RG BT /F1 100 Tf 100 100 Td (A) Tj ET",
+            resources,
+            vec![Object::Stream(glyph)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        renderer.render_content_stream().unwrap();
+
+        let red = ColorSpace::DeviceRGB {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+        };
+
+        assert_eq!(renderer.scene.len(), 1);
+        assert_eq!(renderer.scene[0].fill_color, Some(red.as_u32()));
+        assert_eq!(renderer.scene[0].stroke_color, None);
+    }
+
+    #[test]
+    fn cm_and_form_matrices_apply_before_the_ctm() {
+        let form = Stream::new(
+            dict(vec![
+                ("Type", name("XObject")),
+                ("Subtype", name("Form")),
+                ("BBox", numbers(&[0.0, 0.0, 100.0, 100.0])),
+                ("Matrix", numbers(&[2.0, 0.0, 0.0, 2.0, 0.0, 0.0])),
+            ]),
+            b"0 0 10 10 re f".to_vec(),
+        )
+        .unwrap();
+        let resources = dict(vec![(
+            "XObject",
+            Object::Dictionary(dict(vec![("Fm1", Object::Reference(reference(5)))])),
+        )]);
+
+        let file = single_page_document(
+            b"q 1 0 0 1 100 0 cm 2 0 0 2 0 0 cm 0 0 10 10 re f Q q 1 0 0 1 100 0 cm /Fm1 Do Q",
+            resources,
+            vec![Object::Stream(form)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        renderer.render_content_stream().unwrap();
+
+        assert_eq!(renderer.scene.len(), 2);
+
+        // the scale is applied to the square before it is moved right
+        for renderable in &renderer.scene {
+            let bbox = renderable.outline.bounding_box();
+
+            assert_eq!((bbox.min.x, bbox.min.y), (100.0, 0.0));
+            assert_eq!((bbox.max.x, bbox.max.y), (120.0, 20.0));
+        }
+    }
+
+    #[test]
+    fn tj_adjustments_are_scaled_horizontally() {
+        // a Type 3 font whose only glyph is a square filling its em box
+        let font = dict(vec![
+            ("Type", name("Font")),
+            ("Subtype", name("Type3")),
+            ("FontBBox", numbers(&[0.0, 0.0, 1000.0, 1000.0])),
+            ("FontMatrix", numbers(&[0.001, 0.0, 0.0, 0.001, 0.0, 0.0])),
+            (
+                "CharProcs",
+                Object::Dictionary(dict(vec![("square", Object::Reference(reference(5)))])),
+            ),
+            (
+                "Encoding",
+                Object::Dictionary(dict(vec![(
+                    "Differences",
+                    Object::Array(vec![Object::Integer(65), name("square")]),
+                )])),
+            ),
+            ("FirstChar", Object::Integer(65)),
+            ("LastChar", Object::Integer(65)),
+            ("Widths", numbers(&[1000.0])),
+        ]);
+        let glyph = Stream::new(
+            Dictionary::empty(),
+            b"1000 0 0 0 1000 1000 d1 0 0 1000 1000 re f".to_vec(),
+        )
+        .unwrap();
+        let resources = dict(vec![(
+            "Font",
+            Object::Dictionary(dict(vec![("F1", Object::Dictionary(font))])),
+        )]);
+
+        // the text object is left open, as ET resets the text matrix
+        let file = single_page_document(
+            b"BT /F1 10 Tf 50 Tz [(A) -1000 (A)] TJ",
+            resources,
+            vec![Object::Stream(glyph)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        renderer.render_content_stream().unwrap();
+
+        // each glyph advances by 10 * 0.5 units at 50% horizontal scaling, and
+        // the adjustment of 1000 moves the second glyph a further 10 * 0.5 units
+        // right
+        let origin = renderer.text_state.text_matrix * Point::new(0.0, 0.0);
+
+        assert_eq!(origin, Point::new(15.0, 0.0));
+    }
+
+    #[test]
+    fn tiling_pattern_painting_its_own_cell() {
+        let patterns = || {
+            dict(vec![(
+                "Pattern",
+                Object::Dictionary(dict(vec![("P1", Object::Reference(reference(5)))])),
+            )])
+        };
+        let pattern = Stream::new(
+            dict(vec![
+                ("PatternType", Object::Integer(1)),
+                ("PaintType", Object::Integer(1)),
+                ("TilingType", Object::Integer(1)),
+                ("BBox", numbers(&[0.0, 0.0, 10.0, 10.0])),
+                ("XStep", Object::Real(10.0)),
+                ("YStep", Object::Real(10.0)),
+                ("Resources", Object::Dictionary(patterns())),
+            ]),
+            b"/Pattern cs /P1 scn 0 0 10 10 re f".to_vec(),
+        )
+        .unwrap();
+
+        let file = single_page_document(
+            b"/Pattern cs /P1 scn 0 0 100 100 re f",
+            patterns(),
+            vec![Object::Stream(pattern)],
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        renderer.render_content_stream().unwrap();
+
+        assert_eq!(renderer.scene.len(), 1);
+        assert!(renderer.scene[0].fill_tile.is_some());
+        assert_eq!(renderer.scene[0].fill_color, None);
+
+        // reported once when parsed, and once when skipped within its own cell
+        let diagnostics = parser.diagnostics();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.reference == Some(reference(5))));
+    }
+
+    /// A page showing `content`, with the tiling pattern resource `P1` painting
+    /// `cell` in its 10 by 10 cell. The colour space `CS1` gives an uncoloured
+    /// pattern its colour in RGB
+    fn tiling_pattern_document(paint_type: i32, cell: &[u8], content: &[u8]) -> Vec<u8> {
+        let pattern = Stream::new(
+            dict(vec![
+                ("PatternType", Object::Integer(1)),
+                ("PaintType", Object::Integer(paint_type)),
+                ("TilingType", Object::Integer(1)),
+                ("BBox", numbers(&[0.0, 0.0, 10.0, 10.0])),
+                ("XStep", Object::Real(10.0)),
+                ("YStep", Object::Real(10.0)),
+                ("Resources", Object::Dictionary(Dictionary::empty())),
+            ]),
+            cell.to_vec(),
+        )
+        .unwrap();
+        let resources = dict(vec![
+            (
+                "Pattern",
+                Object::Dictionary(dict(vec![("P1", Object::Reference(reference(5)))])),
+            ),
+            (
+                "ColorSpace",
+                Object::Dictionary(dict(vec![(
+                    "CS1",
+                    Object::Array(vec![name("Pattern"), name("DeviceRGB")]),
+                )])),
+            ),
+        ]);
+
+        single_page_document(content, resources, vec![Object::Stream(pattern)])
+    }
+
+    #[test]
+    fn tiling_pattern_cells_are_shared() {
+        let file = tiling_pattern_document(
+            2,
+            b"0 0 5 5 re f",
+            b"/CS1 cs 1 0 0 /P1 scn 0 0 10 10 re f 20 0 10 10 re f \
+            0 1 0 /P1 scn 40 0 10 10 re f \
+            q 2 0 0 2 0 0 cm 0 1 0 /P1 scn 60 0 10 10 re f Q",
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+        renderer.render_content_stream().unwrap();
+
+        let tiles = renderer
+            .scene
+            .iter()
+            .map(|renderable| renderable.fill_tile.clone().unwrap())
+            .collect::<Vec<_>>();
+
+        // the CTM isn't part of the pattern matrix, so doesn't record a new cell
+        assert!(Rc::ptr_eq(&tiles[0], &tiles[1]));
+        assert!(!Rc::ptr_eq(&tiles[0], &tiles[2]));
+        assert!(Rc::ptr_eq(&tiles[2], &tiles[3]));
+    }
+
+    #[test]
+    fn tiling_pattern_failing_to_record_restores_the_page() {
+        let file = tiling_pattern_document(
+            1,
+            b"0 0 re f",
+            b"0 0 10 10 re f /Pattern cs /P1 scn 0 0 100 100 re f",
+        );
+        let mut parser = Parser::from_bytes(file).unwrap();
+
+        let page = parser.page(0).unwrap();
+        let mut content = parser.page_contents(&page).unwrap();
+
+        let mut renderer = Renderer::new(&mut content, &mut parser.lexer, Rc::clone(&page));
+
+        assert!(renderer.render_content_stream().is_err());
+        assert_eq!(renderer.scene.len(), 1);
+        assert!(renderer.graphics_state_stack.is_empty());
+        assert!(Rc::ptr_eq(
+            renderer.resources.as_ref().unwrap(),
+            page.resources().as_ref().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,570 @@
+use crate::{
+    color::ColorSpace,
+    data_structures::{Matrix, Rectangle},
+    error::PdfResult,
+    geometry::Point,
+    shading::{Mesh, ShadingFunction, ShadingObject, SubtypeShadingDictionary},
+};
+
+/// The number of colours sampled from a shading's function. Shading functions are
+/// generally smooth, so sampling them once up front is indistinguishable from
+/// evaluating them at every pixel, and much faster
+const NUM_SAMPLES: usize = 256;
+
+/// The fraction of a pixel by which a pixel's centre may lie outside of a triangle
+/// and still be painted by it, so that no gaps are left between adjacent triangles
+const TRIANGLE_TOLERANCE: f32 = 1e-4;
+
+/// A shading prepared for painting, which gives the colour at any point of the
+/// shading's target coordinate space
+#[derive(Debug)]
+pub(crate) struct Shader {
+    geometry: ShaderGeometry,
+
+    /// Maps the shading's target coordinate space to default user space
+    pub(crate) matrix: Matrix,
+
+    /// Points outside of the bounding box aren't painted, even with the background
+    bbox: Option<Rectangle>,
+
+    /// Painted where the shading isn't defined, only when it's painted as part of
+    /// a shading pattern
+    background: Option<u32>,
+
+    /// Whether to extend the shading beyond its starting and ending points
+    extend: [bool; 2],
+
+    /// Colours at evenly spaced values of the parametric variable, from t0 to t1
+    colors: Vec<u32>,
+}
+
+#[derive(Debug)]
+enum ShaderGeometry {
+    Axial {
+        start: Point,
+        end: Point,
+    },
+    Radial {
+        start: Point,
+        start_radius: f32,
+        end: Point,
+        end_radius: f32,
+    },
+
+    /// The values at each vertex of the mesh are either a parametric value normalized
+    /// to [0 1], or the red, green and blue components of the vertex's colour
+    Mesh {
+        mesh: Mesh,
+        is_parametric: bool,
+    },
+}
+
+impl Shader {
+    /// Prepare `shading` for painting. `matrix` maps the shading's target coordinate
+    /// space to default user space
+    ///
+    /// Returns `None` for the types of shading we don't yet support
+    pub fn new(
+        shading: &ShadingObject,
+        matrix: Matrix,
+        use_background: bool,
+    ) -> PdfResult<Option<Self>> {
+        let color_space = &shading.base.color_space;
+
+        let background = match &shading.base.background {
+            Some(background) if use_background => {
+                Some(color_space.with_components(background)?.as_u32())
+            }
+            _ => None,
+        };
+
+        let (geometry, domain, extend, function) = match &shading.sub_type {
+            SubtypeShadingDictionary::Axial(axial) => (
+                ShaderGeometry::Axial {
+                    start: Point::new(axial.coords.x0, axial.coords.y0),
+                    end: Point::new(axial.coords.x1, axial.coords.y1),
+                },
+                axial.domain,
+                axial.extend,
+                &axial.function,
+            ),
+            SubtypeShadingDictionary::Radial(radial) => (
+                ShaderGeometry::Radial {
+                    start: Point::new(radial.coords.start.x, radial.coords.start.y),
+                    start_radius: radial.coords.start.radius,
+                    end: Point::new(radial.coords.end.x, radial.coords.end.y),
+                    end_radius: radial.coords.end.radius,
+                },
+                radial.domain,
+                radial.extend,
+                &radial.function,
+            ),
+            _ => match shading.mesh() {
+                Some(mesh) => return Self::mesh(shading, mesh, matrix, background).map(Some),
+                None => return Ok(None),
+            },
+        };
+
+        let colors = (0..NUM_SAMPLES)
+            .map(|i| {
+                let t = domain[0] + (domain[1] - domain[0]) * i as f32 / (NUM_SAMPLES - 1) as f32;
+
+                color(color_space, function, t)
+            })
+            .collect::<PdfResult<Vec<u32>>>()?;
+
+        Ok(Some(Self {
+            geometry,
+            matrix,
+            bbox: shading.base.bbox,
+            background,
+            extend,
+            colors,
+        }))
+    }
+
+    /// Prepare a mesh shading. With a function, the parametric values at its vertices
+    /// are interpolated and then looked up in the sampled colours, otherwise the colour
+    /// of each vertex is interpolated directly
+    fn mesh(
+        shading: &ShadingObject,
+        mesh: &Mesh,
+        matrix: Matrix,
+        background: Option<u32>,
+    ) -> PdfResult<Self> {
+        let color_space = &shading.base.color_space;
+
+        let (values, colors, is_parametric) = match shading.mesh_function() {
+            Some(function) => {
+                let (t0, t1) = mesh
+                    .values
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &t| {
+                        (min.min(t), max.max(t))
+                    });
+
+                let colors = (0..NUM_SAMPLES)
+                    .map(|i| {
+                        let t = t0 + (t1 - t0) * i as f32 / (NUM_SAMPLES - 1) as f32;
+
+                        color(color_space, function, t)
+                    })
+                    .collect::<PdfResult<Vec<u32>>>()?;
+
+                let values = mesh
+                    .values
+                    .iter()
+                    .map(|&t| if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 })
+                    .collect();
+
+                (values, colors, true)
+            }
+            None => {
+                let mut values = Vec::with_capacity(mesh.points.len() * 3);
+
+                for idx in 0..mesh.points.len() {
+                    let color = color_space
+                        .with_components(mesh.vertex_values(idx))?
+                        .as_u32();
+
+                    values.extend(
+                        [color & 0xff, (color >> 8) & 0xff, (color >> 16) & 0xff]
+                            .map(|channel| channel as f32),
+                    );
+                }
+
+                (values, Vec::new(), false)
+            }
+        };
+
+        let mesh = Mesh {
+            points: mesh.points.clone(),
+            values,
+            values_per_vertex: if is_parametric { 1 } else { 3 },
+            triangles: mesh.triangles.clone(),
+        };
+
+        Ok(Self {
+            geometry: ShaderGeometry::Mesh {
+                mesh,
+                is_parametric,
+            },
+            matrix,
+            bbox: shading.base.bbox,
+            background,
+            extend: [false, false],
+            colors,
+        })
+    }
+
+    /// The colour at each point of device space, where `transform` maps default user
+    /// space to a device `width` by `height` pixels in size
+    ///
+    /// Returns `None` if the shading can't be mapped to device space
+    pub fn device_colors(
+        &self,
+        transform: Matrix,
+        width: usize,
+        height: usize,
+    ) -> Option<impl Fn(Point) -> Option<u32> + '_> {
+        let to_device = self.matrix * transform;
+        let inverse = to_device.inverse()?;
+
+        // meshes are rasterized up front, as finding the triangle containing each
+        // point would be far slower
+        let layer = match &self.geometry {
+            ShaderGeometry::Mesh {
+                mesh,
+                is_parametric,
+            } => Some(rasterize_mesh(mesh, to_device, width, height, |values| {
+                if *is_parametric {
+                    self.sample(values[0])
+                } else {
+                    let [r, g, b] = [values[0], values[1], values[2]].map(|c| c.round() as u32);
+
+                    (0xff << 24) | (b << 16) | (g << 8) | r
+                }
+            })),
+            _ => None,
+        };
+
+        Some(move |point: Point| match &layer {
+            Some(layer) => {
+                if !self.is_in_bbox(inverse * point) {
+                    return None;
+                }
+
+                let is_on_device = point.x >= 0.0
+                    && point.y >= 0.0
+                    && (point.x as usize) < width
+                    && (point.y as usize) < height;
+
+                let color = if is_on_device {
+                    layer[point.y as usize * width + point.x as usize]
+                } else {
+                    None
+                };
+
+                color.or(self.background)
+            }
+            None => self.color_at(inverse * point),
+        })
+    }
+
+    fn is_in_bbox(&self, point: Point) -> bool {
+        self.bbox.is_none_or(|bbox| bbox.contains(point))
+    }
+
+    /// The sampled colour at `s`, from 0 at t0 to 1 at t1
+    fn sample(&self, s: f32) -> u32 {
+        let idx = (s * (NUM_SAMPLES - 1) as f32).round() as usize;
+
+        self.colors[idx.min(NUM_SAMPLES - 1)]
+    }
+
+    /// The colour at `point`, in the shading's target coordinate space, or `None`
+    /// if nothing should be painted there
+    pub fn color_at(&self, point: Point) -> Option<u32> {
+        if !self.is_in_bbox(point) {
+            return None;
+        }
+
+        match self.parametric_value(point) {
+            Some(s) => Some(self.sample(s)),
+            None => self.background,
+        }
+    }
+
+    /// How far along the shading `point` lies, from 0 at its start to 1 at its end,
+    /// taking `Extend` into account
+    fn parametric_value(&self, point: Point) -> Option<f32> {
+        let s = match self.geometry {
+            ShaderGeometry::Axial { start, end } => {
+                let dx = end.x - start.x;
+                let dy = end.y - start.y;
+                let len_squared = dx * dx + dy * dy;
+
+                if len_squared == 0.0 {
+                    return None;
+                }
+
+                ((point.x - start.x) * dx + (point.y - start.y) * dy) / len_squared
+            }
+            ShaderGeometry::Radial {
+                start,
+                start_radius,
+                end,
+                end_radius,
+            } => self.radial_parametric_value(point, start, start_radius, end, end_radius)?,
+            ShaderGeometry::Mesh { .. } => return None,
+        };
+
+        if s < 0.0 {
+            self.extend[0].then_some(0.0)
+        } else if s > 1.0 {
+            self.extend[1].then_some(1.0)
+        } else {
+            Some(s)
+        }
+    }
+
+    /// Find the largest `s` for which `point` lies on the circle interpolated between the
+    /// starting and ending circles, with a non-negative radius, and which is either in
+    /// [0 1] or in an extended part of the shading
+    ///
+    /// The circle at `s` has centre `start + s * (end - start)` and radius
+    /// `r0 + s * (r1 - r0)`, so this solves a quadratic in `s`
+    fn radial_parametric_value(
+        &self,
+        point: Point,
+        start: Point,
+        r0: f32,
+        end: Point,
+        r1: f32,
+    ) -> Option<f32> {
+        let cdx = end.x - start.x;
+        let cdy = end.y - start.y;
+        let dr = r1 - r0;
+
+        let pdx = point.x - start.x;
+        let pdy = point.y - start.y;
+
+        let a = cdx * cdx + cdy * cdy - dr * dr;
+        let b = pdx * cdx + pdy * cdy + r0 * dr;
+        let c = pdx * pdx + pdy * pdy - r0 * r0;
+
+        let is_valid = |s: f32| {
+            let is_painted = if s < 0.0 {
+                self.extend[0]
+            } else if s > 1.0 {
+                self.extend[1]
+            } else {
+                true
+            };
+
+            is_painted && r0 + s * dr >= 0.0
+        };
+
+        if a.abs() < f32::EPSILON {
+            if b == 0.0 {
+                return None;
+            }
+
+            let s = c / (2.0 * b);
+
+            return is_valid(s).then_some(s);
+        }
+
+        let discriminant = b * b - a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let s1 = (b + root) / a;
+        let s2 = (b - root) / a;
+
+        let (larger, smaller) = if s1 > s2 { (s1, s2) } else { (s2, s1) };
+
+        if is_valid(larger) {
+            Some(larger)
+        } else if is_valid(smaller) {
+            Some(smaller)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rasterize the Gouraud-shaded triangles of `mesh` into a `width` by `height` grid
+/// of pixels, row by row from the bottom of the device, where `transform` maps the
+/// mesh into device space
+///
+/// The values at the vertices of each triangle are interpolated to the centre of each
+/// pixel it covers, and passed to `color`. Later triangles are painted over earlier
+/// ones, and pixels not covered by any triangle are `None`
+///
+/// The mesh of a shading is given by [`ShadingObject::mesh`]
+pub fn rasterize_mesh(
+    mesh: &Mesh,
+    transform: Matrix,
+    width: usize,
+    height: usize,
+    mut color: impl FnMut(&[f32]) -> u32,
+) -> Vec<Option<u32>> {
+    let mut layer = vec![None; width * height];
+
+    let points = mesh
+        .points
+        .iter()
+        .map(|&point| transform * point)
+        .collect::<Vec<_>>();
+
+    let mut values = vec![0.0; mesh.values_per_vertex];
+
+    // twice the signed area of the triangle `a`, `b`, `c`
+    let cross =
+        |a: Point, b: Point, c: Point| (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+
+    for &[a, b, c] in &mesh.triangles {
+        let (pa, pb, pc) = (points[a], points[b], points[c]);
+
+        let area = cross(pa, pb, pc);
+
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let pixel_range = |min: f32, max: f32, len: usize| {
+            let start = min.floor().clamp(0.0, len as f32) as usize;
+            let end = max.ceil().clamp(0.0, len as f32) as usize;
+
+            start..end
+        };
+
+        let rows = pixel_range(pa.y.min(pb.y).min(pc.y), pa.y.max(pb.y).max(pc.y), height);
+        let cols = pixel_range(pa.x.min(pb.x).min(pc.x), pa.x.max(pb.x).max(pc.x), width);
+
+        for y in rows {
+            for x in cols.clone() {
+                let point = Point::new(x as f32 + 0.5, y as f32 + 0.5);
+
+                // the barycentric coordinates of the pixel's centre
+                let wa = cross(pb, pc, point) / area;
+                let wb = cross(pc, pa, point) / area;
+                let wc = 1.0 - wa - wb;
+
+                if wa < -TRIANGLE_TOLERANCE || wb < -TRIANGLE_TOLERANCE || wc < -TRIANGLE_TOLERANCE
+                {
+                    continue;
+                }
+
+                let (va, vb, vc) = (
+                    mesh.vertex_values(a),
+                    mesh.vertex_values(b),
+                    mesh.vertex_values(c),
+                );
+
+                for (i, value) in values.iter_mut().enumerate() {
+                    *value = wa * va[i] + wb * vb[i] + wc * vc[i];
+                }
+
+                layer[y * width + x] = Some(color(&values));
+            }
+        }
+    }
+
+    layer
+}
+
+/// The colour of the shading at the parametric value `t`
+fn color(color_space: &ColorSpace, function: &ShadingFunction, t: f32) -> PdfResult<u32> {
+    Ok(color_space
+        .with_components(&function.evaluate(&[t]))?
+        .as_u32())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        data_structures::Matrix,
+        geometry::Point,
+        objects::Object,
+        shading::{Mesh, ShadingObject},
+        stream::DirectObjects,
+        test_util::{dict, numbers},
+        FromObj,
+    };
+
+    use super::{rasterize_mesh, Shader};
+
+    /// A gray radial shading from black at a point to white on a circle of radius
+    /// 10 around it, extended beyond the circle
+    fn shader(use_background: bool) -> Shader {
+        let entries = vec![
+            ("ShadingType", Object::Integer(3)),
+            ("ColorSpace", Object::Name("DeviceGray".to_owned())),
+            ("Coords", numbers(&[0.0, 0.0, 0.0, 0.0, 0.0, 10.0])),
+            ("Extend", Object::Array(vec![Object::False, Object::True])),
+            ("Background", numbers(&[0.25])),
+            (
+                "Function",
+                Object::Dictionary(dict(vec![
+                    ("FunctionType", Object::Integer(2)),
+                    ("Domain", numbers(&[0.0, 1.0])),
+                    ("N", Object::Integer(1)),
+                ])),
+            ),
+        ];
+
+        let dict = dict(entries);
+
+        let shading =
+            ShadingObject::from_obj(Object::Dictionary(dict), &mut DirectObjects).unwrap();
+
+        Shader::new(&shading, Matrix::identity(), use_background)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn gray(color: Option<u32>) -> Option<u32> {
+        color.map(|color| color & 0xff)
+    }
+
+    #[test]
+    fn radial_shading_interpolates_between_circles() {
+        let shader = shader(false);
+
+        assert_eq!(gray(shader.color_at(Point::new(0.0, 0.0))), Some(0));
+        assert_eq!(gray(shader.color_at(Point::new(0.0, 5.0))), Some(128));
+        assert_eq!(gray(shader.color_at(Point::new(-8.0, 6.0))), Some(255));
+        assert_eq!(gray(shader.color_at(Point::new(30.0, 0.0))), Some(255));
+    }
+
+    #[test]
+    fn axial_shading_background_fills_outside_of_extend() {
+        let shader = shader(true);
+
+        // reuse the colours and background of the radial shading along an
+        // axis which isn't extended
+        let axial = Shader {
+            geometry: super::ShaderGeometry::Axial {
+                start: Point::new(0.0, 0.0),
+                end: Point::new(10.0, 0.0),
+            },
+            extend: [false, false],
+            ..shader
+        };
+
+        assert_eq!(gray(axial.color_at(Point::new(5.0, 3.0))), Some(128));
+        assert_eq!(gray(axial.color_at(Point::new(-1.0, 0.0))), Some(64));
+        assert_eq!(gray(axial.color_at(Point::new(11.0, 0.0))), Some(64));
+        assert_eq!(gray(axial.color_at(Point::new(10.0, 0.0))), Some(255));
+    }
+
+    #[test]
+    fn mesh_triangles_interpolate_vertex_values() {
+        let mesh = Mesh {
+            points: vec![
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 0.0),
+                Point::new(0.0, 2.0),
+            ],
+            values: vec![0.0, 1.0, 0.0],
+            values_per_vertex: 1,
+            triangles: vec![[0, 1, 2]],
+        };
+
+        let layer = rasterize_mesh(&mesh, Matrix::new_scale(2.0, 2.0), 4, 4, |values| {
+            (values[0] * 255.0).round() as u32
+        });
+
+        // the value at the centre of the pixel is weighted by its distance
+        // from each vertex
+        assert_eq!(layer[0], Some(32));
+        assert_eq!(layer[2], Some(159));
+        assert_eq!(layer[4], Some(32));
+        assert_eq!(layer[15], None);
+    }
+}
@@ -1,6 +1,47 @@
 use std::rc::Rc;
 
-use crate::{data_structures::Matrix, font::Font};
+use crate::{
+    content::PdfGraphicsOperator,
+    data_structures::Matrix,
+    error::PdfResult,
+    font::{CidFontWidths, Font, Widths},
+    objects::Object,
+    text::{DecodedChar, FontDecoder},
+    Resolve,
+};
+
+use super::error::PdfRenderError;
+
+/// The horizontal displacement of glyphs, in unscaled text space units
+pub(crate) trait FontMetrics {
+    fn get(&self, codepoint: u32) -> f32;
+}
+
+impl FontMetrics for Widths {
+    fn get(&self, codepoint: u32) -> f32 {
+        self.get(codepoint)
+    }
+}
+
+impl FontMetrics for CidFontWidths {
+    fn get(&self, codepoint: u32) -> f32 {
+        self.map
+            .get(&(codepoint as i32))
+            .copied()
+            .unwrap_or(self.default as f32)
+            / 1000.0
+    }
+}
+
+/// A glyph shown by a text showing operator
+#[derive(Debug)]
+pub(crate) struct ShownGlyph {
+    pub char: DecodedChar,
+
+    /// The text rendering matrix when the glyph was shown, which maps text space
+    /// at a font size of 1 to default user space
+    pub text_rendering_matrix: Matrix,
+}
 
 #[derive(Debug, Clone)]
 pub struct TextState<'a> {
@@ -103,6 +144,238 @@ impl<'a> TextState<'a> {
         self.text_matrix = Matrix::identity();
         self.text_line_matrix = Matrix::identity();
     }
+
+    /// Apply a text object, text state or text positioning operator, popping its
+    /// operands from `operands`. `Tf` is left to the caller, which must look up
+    /// the font in its resources
+    ///
+    /// Returns `false`, leaving the operands untouched, for any other operator
+    pub(crate) fn apply_operator(
+        &mut self,
+        op: PdfGraphicsOperator,
+        operands: &mut Vec<Object<'a>>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<bool> {
+        let mut pop_number = || -> PdfResult<f32> {
+            let obj = operands.pop().ok_or(PdfRenderError::StackUnderflow)?;
+
+            resolver.assert_number(obj)
+        };
+
+        match op {
+            // BT begins a text object, and ET ends it, discarding the text matrix
+            PdfGraphicsOperator::BT | PdfGraphicsOperator::ET => self.reinit(),
+            PdfGraphicsOperator::Tc => self.character_spacing = pop_number()?,
+            PdfGraphicsOperator::Tw => self.word_spacing = pop_number()?,
+            PdfGraphicsOperator::Tz => self.horizontal_scaling = pop_number()? / 100.0,
+            PdfGraphicsOperator::TL => self.leading = pop_number()?,
+            PdfGraphicsOperator::Ts => self.rise = pop_number()?,
+            PdfGraphicsOperator::Tr => {
+                self.rendering_mode = TextRenderingMode::from_integer(pop_number()? as i32)?;
+            }
+            PdfGraphicsOperator::Td => {
+                let t_y = pop_number()?;
+                let t_x = pop_number()?;
+
+                self.move_text_position(t_x, t_y);
+            }
+            PdfGraphicsOperator::TD => {
+                let t_y = pop_number()?;
+                let t_x = pop_number()?;
+
+                self.leading = -t_y;
+                self.move_text_position(t_x, t_y);
+            }
+            PdfGraphicsOperator::Tm => {
+                let f = pop_number()?;
+                let e = pop_number()?;
+                let d = pop_number()?;
+                let c = pop_number()?;
+                let b = pop_number()?;
+                let a = pop_number()?;
+
+                self.set_text_matrix(Matrix::new(a, b, c, d, e, f));
+            }
+            PdfGraphicsOperator::T_star => self.move_to_next_line(),
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Pop the operands of the text showing operator `op`, returning the strings
+    /// and position adjustments to pass to [`TextState::show_text`]
+    ///
+    /// `'` and `"` move to the next line before their string is shown, and `"`
+    /// also sets the word and character spacing. Returns `None`, leaving the
+    /// operands untouched, for any other operator
+    pub(crate) fn text_to_show(
+        &mut self,
+        op: PdfGraphicsOperator,
+        operands: &mut Vec<Object<'a>>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Option<Vec<Object<'a>>>> {
+        if !matches!(
+            op,
+            PdfGraphicsOperator::Tj
+                | PdfGraphicsOperator::TJ
+                | PdfGraphicsOperator::single_quote
+                | PdfGraphicsOperator::double_quote
+        ) {
+            return Ok(None);
+        }
+
+        let operand = operands.pop().ok_or(PdfRenderError::StackUnderflow)?;
+
+        if op == PdfGraphicsOperator::TJ {
+            return Ok(Some(resolver.assert_arr(operand)?));
+        }
+
+        if op == PdfGraphicsOperator::double_quote {
+            let mut pop_number = || -> PdfResult<f32> {
+                let obj = operands.pop().ok_or(PdfRenderError::StackUnderflow)?;
+
+                resolver.assert_number(obj)
+            };
+
+            self.character_spacing = pop_number()?;
+            self.word_spacing = pop_number()?;
+        }
+
+        if op != PdfGraphicsOperator::Tj {
+            self.move_to_next_line();
+        }
+
+        Ok(Some(vec![operand]))
+    }
+
+    /// Move to the start of the next line, offset from the start of the current
+    /// line by (t_x, t_y). t_x and t_y shall denote numbers expressed in
+    /// unscaled text space units. More precisely, this operator shall perform
+    /// these assignments:
+    ///
+    /// T_m = T_lm = [1 0 0, 0 1 0, t_x t_y 1] * T_lm
+    pub(crate) fn move_text_position(&mut self, t_x: f32, t_y: f32) {
+        let matrix = Matrix::new_translation(t_x, t_y) * self.text_line_matrix;
+
+        self.set_text_matrix(matrix);
+    }
+
+    /// Move to the start of the next line. This operator has the same effect
+    /// as the code
+    ///
+    /// 0 -Tl Td
+    ///
+    /// where Tl denotes the current leading parameter in the text state. The
+    /// negative of Tl is used here because Tl is the text leading expressed as
+    /// a positive number. Going to the next line entails decreasing the y
+    /// coordinate.
+    pub(crate) fn move_to_next_line(&mut self) {
+        self.move_text_position(0.0, -self.leading);
+    }
+
+    /// Set the text matrix, Tm, and the text line matrix, Tlm:
+    ///
+    /// T_m = T_lm = [a b 0, c d 0, e f 1]
+    pub(crate) fn set_text_matrix(&mut self, matrix: Matrix) {
+        self.text_matrix = matrix;
+        self.text_line_matrix = matrix;
+    }
+
+    /// Show one or more text strings, allowing individual glyph positioning.
+    ///
+    /// Each element of array shall be either a string or a number. If the element
+    /// is a string, this operator shall show the string. If it is a number,
+    /// the operator shall adjust the text position by that amount; that is, it
+    /// shall translate the text matrix, Tm. The number shall be expressed in
+    /// thousandths of a unit of text space. This amount shall be subtracted
+    /// from the current horizontal or vertical coordinate, depending on the
+    /// writing mode. In the default coordinate system, a positive adjustment has
+    /// the effect of moving the next glyph painted either to the left or down
+    /// by the given amount.
+    ///
+    /// The strings are split into glyphs by `decoder`, the decoder for the current
+    /// font, and the text matrix is advanced past each glyph shown
+    pub(crate) fn show_text(
+        &mut self,
+        arr: Vec<Object<'a>>,
+        decoder: &FontDecoder,
+        current_transformation_matrix: Matrix,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Vec<ShownGlyph>> {
+        let mut glyphs = Vec::new();
+
+        for obj in arr {
+            match resolver.resolve(obj)? {
+                Object::String(s) => {
+                    let bytes = s.chars().map(|c| c as u8).collect::<Vec<u8>>();
+
+                    for c in decoder.decode(&bytes) {
+                        let text_rendering_matrix = Matrix::new(
+                            self.font_size * self.horizontal_scaling,
+                            0.0,
+                            0.0,
+                            self.font_size,
+                            0.0,
+                            self.rise,
+                        ) * self.text_matrix
+                            * current_transformation_matrix;
+
+                        self.advance(&c, decoder.is_vertical());
+
+                        glyphs.push(ShownGlyph {
+                            char: c,
+                            text_rendering_matrix,
+                        });
+                    }
+                }
+                obj => {
+                    let adjustment = resolver.assert_number(obj)? / 1000.0;
+
+                    self.adjust_text_position(adjustment, decoder.is_vertical());
+                }
+            }
+        }
+
+        Ok(glyphs)
+    }
+
+    /// Move the text matrix past a glyph that has just been shown, by its width
+    /// plus the character and word spacing
+    fn advance(&mut self, c: &DecodedChar, is_vertical: bool) {
+        let mut spacing = self.character_spacing;
+
+        if c.is_word_space() {
+            spacing += self.word_spacing;
+        }
+
+        // vertical metrics are not tracked, so every glyph is assumed to have
+        // the default vertical displacement of one em
+        let translation = if is_vertical {
+            Matrix::new_translation(0.0, -self.font_size + spacing)
+        } else {
+            Matrix::new_translation(
+                (c.width * self.font_size + spacing) * self.horizontal_scaling,
+                0.0,
+            )
+        };
+
+        self.text_matrix = translation * self.text_matrix;
+    }
+
+    /// Apply a position adjustment from a `TJ` array, expressed in thousandths
+    /// of a unit of text space
+    fn adjust_text_position(&mut self, adjustment: f32, is_vertical: bool) {
+        let amount = -adjustment * self.font_size;
+
+        let translation = if is_vertical {
+            Matrix::new_translation(0.0, amount)
+        } else {
+            Matrix::new_translation(amount * self.horizontal_scaling, 0.0)
+        };
+
+        self.text_matrix = translation * self.text_matrix;
+    }
 }
 
 impl Default for TextState<'_> {
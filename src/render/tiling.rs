@@ -0,0 +1,354 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    data_structures::{Matrix, Rectangle},
+    geometry::Point,
+};
+
+use super::{canvas::Canvas, paint_scene, Renderable};
+
+/// The largest width or height, in pixels, of a pattern cell. Cells larger than
+/// this aren't painted
+const MAX_CELL_SIZE: f32 = 8192.0;
+
+/// The largest number of overlapping cells, in each direction, looked up for a
+/// single point. When the steps are much smaller than the cell, only the cells
+/// painted last, which are on top, are considered
+const MAX_OVERLAPPING_CELLS: i32 = 64;
+
+/// The pattern cell of a tiling pattern, recorded for painting
+#[derive(Debug)]
+pub(super) struct Tile {
+    /// The result of running the pattern's content stream, in default user space
+    scene: Vec<Renderable>,
+
+    /// Maps pattern space to default user space
+    matrix: Matrix,
+
+    /// The bounds of the pattern cell, in pattern space
+    bbox: Rectangle,
+
+    /// The spacing between pattern cells, in pattern space
+    x_step: f32,
+    y_step: f32,
+
+    /// The cell as last painted in device space. Every renderable filled with the
+    /// tile in a scene shares the same transform to device space, so the cell is
+    /// only painted once for all of them
+    painted: RefCell<Option<Rc<PaintedCell>>>,
+}
+
+/// A pattern cell painted in device space
+#[derive(Debug)]
+struct PaintedCell {
+    /// Maps default user space to device space
+    transform: Matrix,
+
+    /// The colour of each pixel, row by row from the bottom
+    pixels: Vec<Option<u32>>,
+    width: usize,
+    height: usize,
+
+    /// The position in device space of the lower left corner of the cell
+    min_x: f32,
+    min_y: f32,
+}
+
+impl Tile {
+    pub(super) fn new(
+        scene: Vec<Renderable>,
+        matrix: Matrix,
+        bbox: Rectangle,
+        x_step: f32,
+        y_step: f32,
+    ) -> Self {
+        Self {
+            scene,
+            matrix,
+            bbox,
+            x_step,
+            y_step,
+            painted: RefCell::new(None),
+        }
+    }
+
+    /// The colour at each point of device space, where `transform` maps default user
+    /// space to device space
+    ///
+    /// The pattern cell is painted once at device resolution, and every pixel is then
+    /// looked up in the copy of the cell it falls within. The spacing of the cells may
+    /// therefore vary by up to a pixel, as permitted by every `TilingType`
+    ///
+    /// Returns `None` if the pattern can't be mapped to device space
+    pub(super) fn device_colors(
+        &self,
+        transform: Matrix,
+    ) -> Option<impl Fn(Point) -> Option<u32> + '_> {
+        if self.x_step == 0.0 || self.y_step == 0.0 {
+            return None;
+        }
+
+        let to_device = self.matrix * transform;
+        let inverse = to_device.inverse()?;
+
+        let cell = self.painted_cell(transform)?;
+        let (width, height) = (cell.width, cell.height);
+        let (min_x, min_y) = (cell.min_x, cell.min_y);
+
+        // the offset in device space between adjacent cells
+        let origin = to_device * Point::new(0.0, 0.0);
+        let x_offset = to_device * Point::new(self.x_step, 0.0) - origin;
+        let y_offset = to_device * Point::new(0.0, self.y_step) - origin;
+
+        let (bbox_min, bbox_max) = (self.bbox.lower_left(), self.bbox.upper_right());
+
+        Some(move |point: Point| {
+            let p = inverse * point;
+
+            // cell (i, j) covers the bounding box moved by i steps horizontally and j
+            // steps vertically. cells may overlap, so try each one containing the point,
+            // with the later cells on top
+            let cells = |p: f32, min: f32, max: f32, step: f32| {
+                let (a, b) = ((p - max) / step, (p - min) / step);
+                let (first, last) = (a.min(b).ceil() as i32, a.max(b).floor() as i32);
+
+                first.max(last.saturating_sub(MAX_OVERLAPPING_CELLS - 1))..=last
+            };
+
+            for j in cells(p.y, bbox_min.y, bbox_max.y, self.y_step).rev() {
+                for i in cells(p.x, bbox_min.x, bbox_max.x, self.x_step).rev() {
+                    let point = point
+                        - x_offset * i as f32
+                        - y_offset * j as f32
+                        - Point::new(min_x, min_y);
+
+                    if point.x < 0.0 || point.y < 0.0 {
+                        continue;
+                    }
+
+                    let (x, y) = (point.x as usize, point.y as usize);
+
+                    if x >= width || y >= height {
+                        continue;
+                    }
+
+                    if let Some(color) = cell.pixels[y * width + x] {
+                        return Some(color);
+                    }
+                }
+            }
+
+            None
+        })
+    }
+
+    /// The cell painted in device space, where `transform` maps default user space
+    /// to device space, reusing the cell painted last if the transform is the same
+    ///
+    /// Returns `None` if the cell is too large to paint, which is reported when the
+    /// pattern is recorded
+    fn painted_cell(&self, transform: Matrix) -> Option<Rc<PaintedCell>> {
+        if let Some(cell) = &*self.painted.borrow() {
+            if cell.transform == transform {
+                return Some(Rc::clone(cell));
+            }
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.device_bounds(self.matrix * transform);
+
+        if max_x - min_x > MAX_CELL_SIZE || max_y - min_y > MAX_CELL_SIZE {
+            return None;
+        }
+
+        let width = (max_x - min_x).max(1.0) as usize;
+        let height = (max_y - min_y).max(1.0) as usize;
+
+        let pixels = self.paint_cell(
+            transform * Matrix::new_translation(-min_x, -min_y),
+            width,
+            height,
+        );
+
+        let cell = Rc::new(PaintedCell {
+            transform,
+            pixels,
+            width,
+            height,
+            min_x,
+            min_y,
+        });
+
+        *self.painted.borrow_mut() = Some(Rc::clone(&cell));
+
+        Some(cell)
+    }
+
+    /// Whether the pattern cell is too large to paint in device space, where
+    /// `transform` maps default user space to device space
+    pub(super) fn is_too_large(&self, transform: Matrix) -> bool {
+        let (min_x, min_y, max_x, max_y) = self.device_bounds(self.matrix * transform);
+
+        max_x - min_x > MAX_CELL_SIZE || max_y - min_y > MAX_CELL_SIZE
+    }
+
+    /// The bounds of the first cell in whole pixels, as `(min_x, min_y, max_x, max_y)`,
+    /// where `to_device` maps pattern space to device space
+    fn device_bounds(&self, to_device: Matrix) -> (f32, f32, f32, f32) {
+        let corners = [
+            self.bbox.lower_left(),
+            Point::new(self.bbox.lower_left().x, self.bbox.upper_right().y),
+            self.bbox.upper_right(),
+            Point::new(self.bbox.upper_right().x, self.bbox.lower_left().y),
+        ]
+        .map(|corner| to_device * corner);
+
+        let min_x = corners
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let min_y = corners
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::INFINITY, f32::min)
+            .floor();
+        let max_x = corners
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+        let max_y = corners
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .ceil();
+
+        (min_x, min_y, max_x, max_y)
+    }
+
+    /// Paint the cell onto a transparent `width` by `height` grid of pixels, row by
+    /// row from the bottom, with the alpha of each pixel in its highest byte
+    ///
+    /// The canvas has no alpha channel, so the cell is painted over both black and
+    /// white, and the coverage of each pixel recovered from the difference
+    fn paint_cell(&self, transform: Matrix, width: usize, height: usize) -> Vec<Option<u32>> {
+        let mut black = Canvas::offscreen(width, height);
+        black.fill(0xff_00_00_00);
+        paint_scene(&mut black, &self.scene, transform);
+
+        let mut white = Canvas::offscreen(width, height);
+        paint_scene(&mut white, &self.scene, transform);
+
+        let mut cell = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let over_black = black.pixel(x, y).to_le_bytes();
+                let over_white = white.pixel(x, y).to_le_bytes();
+
+                // over black, each channel is `color * alpha`, and over white it is
+                // `color * alpha + 255 * (1 - alpha)`
+                let difference = (0..3)
+                    .map(|c| over_white[c].saturating_sub(over_black[c]) as f32)
+                    .fold(0.0, f32::max);
+                let alpha = 1.0 - difference / 255.0;
+
+                if alpha <= 0.0 {
+                    cell.push(None);
+                    continue;
+                }
+
+                let [r, g, b] =
+                    [0, 1, 2].map(|c| (over_black[c] as f32 / alpha).round().min(255.0) as u32);
+                let alpha = (alpha * 255.0).round() as u32;
+
+                cell.push(Some((alpha << 24) | (b << 16) | (g << 8) | r));
+            }
+        }
+
+        cell
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::{
+        data_structures::{Matrix, Rectangle},
+        geometry::{Outline, Point},
+        objects::Object,
+        render::{clip::ClippingPath, FillRule, Renderable},
+        stream::DirectObjects,
+        FromObj,
+    };
+
+    use super::Tile;
+
+    fn rectangle(coords: [f32; 4]) -> Rectangle {
+        let arr = coords.iter().map(|&n| Object::Real(n)).collect();
+
+        Rectangle::from_obj(Object::Array(arr), &mut DirectObjects).unwrap()
+    }
+
+    /// A tile whose 10 by 10 cell has a black square filling its lower left quarter
+    fn quarter_filled_tile(x_step: f32, y_step: f32) -> Tile {
+        Tile::new(
+            vec![Renderable {
+                outline: Outline::new(vec![rectangle([0.0, 0.0, 5.0, 5.0]).as_path()]),
+                stroke_color: None,
+                fill_color: Some(0xff_00_00_00),
+                fill_shader: None,
+                fill_tile: None,
+                fill_rule: Some(FillRule::NonZeroWindingNumber),
+                image: None,
+                clip: Rc::new(ClippingPath::default()),
+            }],
+            Matrix::identity(),
+            rectangle([0.0, 0.0, 10.0, 10.0]),
+            x_step,
+            y_step,
+        )
+    }
+
+    #[test]
+    fn tiling_pattern_repeats_cell_in_every_direction() {
+        let tile = quarter_filled_tile(10.0, 10.0);
+
+        let color_at = tile.device_colors(Matrix::identity()).unwrap();
+
+        assert!(color_at(Point::new(1.5, 2.5)).is_some());
+        assert!(color_at(Point::new(7.5, 2.5)).is_none());
+        assert!(color_at(Point::new(31.5, 22.5)).is_some());
+        assert!(color_at(Point::new(-8.5, -17.5)).is_some());
+        assert!(color_at(Point::new(-2.5, 12.5)).is_none());
+    }
+
+    #[test]
+    fn tiny_steps_only_look_up_the_topmost_cells() {
+        // millions of cells overlap every point, and the topmost of them cover it
+        let tile = quarter_filled_tile(0.001, 0.001);
+
+        let color_at = tile.device_colors(Matrix::identity()).unwrap();
+
+        assert!(color_at(Point::new(1.5, 2.5)).is_some());
+        assert!(color_at(Point::new(7.5, 2.5)).is_some());
+    }
+
+    #[test]
+    fn cell_is_painted_once_for_each_transform() {
+        let tile = quarter_filled_tile(10.0, 10.0);
+
+        let cell = tile.painted_cell(Matrix::identity()).unwrap();
+
+        assert!(Rc::ptr_eq(
+            &cell,
+            &tile.painted_cell(Matrix::identity()).unwrap()
+        ));
+
+        let scaled = tile.painted_cell(Matrix::new_scale(2.0, 2.0)).unwrap();
+
+        assert!(!Rc::ptr_eq(&cell, &scaled));
+        assert_eq!((scaled.width, scaled.height), (20, 20));
+    }
+}
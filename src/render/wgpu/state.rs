@@ -221,14 +221,12 @@ impl<'a> State<'a> {
         let mut indices = Vec::new();
         let mut vertices = Vec::new();
 
-        for r in to_render {
+        // todo: gpu stroke
+        let filled = to_render.iter().filter(|r| r.fill_color.is_some());
+
+        for r in filled.clone() {
             for p in &r.outline.paths {
-                let paths = vertices_for_path(
-                    p,
-                    width,
-                    height,
-                    r.fill_color.unwrap_or_else(|| r.stroke_color.unwrap_or(0)),
-                );
+                let paths = vertices_for_path(p, width, height, r.fill_color.unwrap());
                 for mut path in paths {
                     indices.append(&mut indices_for_outline(path.len(), vertices.len() as u32));
                     vertices.append(&mut path);
@@ -253,7 +251,7 @@ impl<'a> State<'a> {
         let num_indices = indices.len() as u32;
 
         let mut vertices_on_curve = Vec::new();
-        for r in to_render {
+        for r in filled {
             for p in &r.outline.paths {
                 vertices_on_curve.append(&mut curve_vertices(p, width, height));
             }
@@ -670,7 +668,7 @@ impl<'a> State<'a> {
         self.queue.submit(iter::once(mask_encoder.finish()));
 
         let mut vertices_on_curve = Vec::new();
-        for r in to_render {
+        for r in to_render.iter().filter(|r| r.fill_color.is_some()) {
             for p in &r.outline.paths {
                 vertices_on_curve.append(&mut curve_vertices(p, width, height));
             }
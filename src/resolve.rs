@@ -1,6 +1,8 @@
 use crate::{
+    diagnostic::Diagnostic,
     error::PdfResult,
     objects::{Dictionary, Object, Reference},
+    render::font_cache::FontCache,
     stream::Stream,
 };
 
@@ -10,6 +12,27 @@ pub trait Resolve<'a> {
     /// Whether or not the reference points to an existing object
     fn reference_exists(&mut self, reference: Reference) -> PdfResult<bool>;
 
+    /// Whether problems with the document are reported as diagnostics, rather
+    /// than failing. See [`crate::ParseOptions::lenient`]
+    fn is_lenient(&self) -> bool {
+        false
+    }
+
+    /// Record a problem with the document, which is only done when parsing
+    /// leniently
+    fn report(&mut self, _diagnostic: Diagnostic) {}
+
+    /// Font programs loaded from embedded font files, which are shared by every
+    /// page rendered
+    fn font_cache(&mut self) -> Option<&mut FontCache<'a>> {
+        None
+    }
+
+    /// The tiling patterns whose resources are being parsed, innermost last
+    fn parsing_patterns(&mut self) -> Option<&mut Vec<Reference>> {
+        None
+    }
+
     fn assert_integer(&mut self, obj: Object) -> PdfResult<i32> {
         match obj {
             Object::Integer(i) => Ok(i),
@@ -47,6 +47,7 @@ pub struct Resources<'a> {
 
 impl<'a> FromObj<'a> for Resources<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut dict = resolver.assert_dict(obj)?;
 
         let ext_g_state = dict.get("ExtGState", resolver)?;
@@ -70,7 +71,7 @@ impl<'a> FromObj<'a> for Resources<'a> {
             .transpose()?;
         let properties = dict.get_dict("Properties", resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, reference, resolver);
 
         Ok(Resources {
             ext_g_state,
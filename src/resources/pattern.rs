@@ -1,7 +1,10 @@
+use std::rc::Rc;
+
 use crate::{
     data_structures::{Matrix, Rectangle},
+    diagnostic::Diagnostic,
     error::PdfResult,
-    objects::Object,
+    objects::{Dictionary, Object, Reference},
     shading::ShadingObject,
     stream::Stream,
     FromObj, Resolve,
@@ -24,6 +27,7 @@ pub enum Pattern<'a> {
 
 impl<'a> FromObj<'a> for Pattern<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let obj = resolver.resolve(obj)?;
 
         Ok(
@@ -34,7 +38,40 @@ impl<'a> FromObj<'a> for Pattern<'a> {
 
                 assert_eq!(pattern_type, PatternType::Tiling);
 
-                Pattern::Tiling(TilingPattern::from_obj(Object::Stream(stream), resolver)?)
+                let is_repeat = match (reference, resolver.parsing_patterns()) {
+                    (Some(reference), Some(parsing)) if parsing.contains(&reference) => true,
+                    (Some(reference), Some(parsing)) => {
+                        parsing.push(reference);
+                        false
+                    }
+                    _ => false,
+                };
+
+                // a pattern whose cell paints with the pattern itself would have its
+                // resources parsed forever. the repeat is parsed without resources,
+                // and is skipped when painted
+                if is_repeat {
+                    resolver.report(Diagnostic::new(
+                        reference,
+                        Some("Resources"),
+                        "tiling pattern paints its own cell",
+                    ));
+
+                    dict.insert("Resources", Object::Dictionary(Dictionary::empty()));
+                }
+
+                let pattern = TilingPattern::from_obj(Object::Stream(stream), resolver);
+
+                if !is_repeat && reference.is_some() {
+                    if let Some(parsing) = resolver.parsing_patterns() {
+                        parsing.pop();
+                    }
+                }
+
+                let mut pattern = pattern?;
+                pattern.indirect_reference = reference;
+
+                Pattern::Tiling(pattern)
             } else {
                 let mut dict = resolver.assert_dict(obj)?;
 
@@ -66,11 +103,11 @@ pub struct TilingPattern<'a> {
     /// left, bottom, right, and top edges, respectively, of the pattern cell's bounding box. These
     /// boundaries shall be used to clip the pattern cell
     #[field("BBox")]
-    bbox: Rectangle,
+    pub(crate) bbox: Rectangle,
 
     /// The desired horizontal spacing between pattern cells, measured in the pattern coordinate system
     #[field("XStep")]
-    x_step: f32,
+    pub(crate) x_step: f32,
 
     /// The desired vertical spacing between pattern cells, measured in the pattern coordinate system
     ///
@@ -79,21 +116,34 @@ pub struct TilingPattern<'a> {
     ///
     /// XStep and YStep may be either positive or negative but shall not be zero
     #[field("YStep")]
-    y_step: f32,
+    pub(crate) y_step: f32,
 
     /// A resource dictionary that shall contain all of the named resources required by the pattern's
     /// content stream
     #[field("Resources")]
-    resources: Resources<'a>,
+    pub(crate) resources: Rc<Resources<'a>>,
 
     /// An array of six numbers specifying the pattern matrix.
     ///
     /// Default value: the identity matrix [1 0 0 1 0 0].
-    #[field("Matrix")]
-    matrix: Matrix,
+    #[field("Matrix", default = Matrix::identity())]
+    pub(crate) matrix: Matrix,
+
+    #[field]
+    pub(crate) stream: Stream<'a>,
 
+    /// The pattern stream, by which a pattern whose cell paints with the pattern
+    /// itself is recognised
     #[field]
-    stream: Stream<'a>,
+    pub(crate) indirect_reference: Option<Reference>,
+}
+
+impl<'a> TilingPattern<'a> {
+    /// Whether the pattern cell is painted with the colour given when the pattern is
+    /// used, rather than with colours of its own
+    pub(crate) fn is_uncolored(&self) -> bool {
+        self.paint_type == PaintType::Uncolored
+    }
 }
 
 #[derive(Debug, Clone, FromObj)]
@@ -101,13 +151,13 @@ pub struct TilingPattern<'a> {
 pub struct ShadingPattern<'a> {
     /// A shading object defining the shading pattern's gradient fill
     #[field("Shading")]
-    shading: ShadingObject<'a>,
+    pub(crate) shading: ShadingObject<'a>,
 
     /// An array of six numbers specifying the pattern matrix
     ///
     /// Default value: the identity matrix [1 0 0 1 0 0].
-    #[field("Matrix")]
-    matrix: Matrix,
+    #[field("Matrix", default = Matrix::identity())]
+    pub(crate) matrix: Matrix,
 
     /// A graphics state parameter dictionary containing graphics state parameters to be put
     /// into effect temporarily while the shading pattern is painted. Any parameters that are
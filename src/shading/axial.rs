@@ -1,6 +1,6 @@
-use crate::{
-    catalog::assert_len, error::PdfResult, function::Function, objects::Object, FromObj, Resolve,
-};
+use crate::{catalog::assert_len, error::PdfResult, objects::Object, FromObj, Resolve};
+
+use super::ShadingFunction;
 
 /// Type 2 (axial) shadings define a colour blend that varies along a linear axis between two
 /// endpoints and extends indefinitely perpendicular to that axis. The shading may optionally
@@ -12,7 +12,7 @@ pub struct AxialShading<'a> {
     /// An array of four numbers [x0 y0 x1 y1] specifying the starting and ending coordinates
     /// of the axis, expressed in the shading's target coordinate space
     #[field("Coords")]
-    coords: Coords,
+    pub(crate) coords: Coords,
 
     /// An array of two numbers [t0 t1] specifying the limiting values of a parametric variable
     /// t. The variable is considered to vary linearly between these two values as the colour
@@ -21,7 +21,7 @@ pub struct AxialShading<'a> {
     ///
     /// Default value: [0.0 1.0].
     #[field("Domain", default = [0.0, 1.0])]
-    domain: [f32; 2],
+    pub(crate) domain: [f32; 2],
 
     /// A 1-in, n-out function or an array of n 1-in, 1-out functions (where n is the number of
     /// colour components in the shading dictionary's colour space). The function(s) shall be
@@ -30,22 +30,22 @@ pub struct AxialShading<'a> {
     /// returned by the function for a given colour component is out of range, it shall be adjusted
     /// to the nearest valid value
     #[field("Function")]
-    function: Function<'a>,
+    pub(crate) function: ShadingFunction<'a>,
 
     /// An array of two boolean values specifying whether to extend the shading beyond the starting
     /// and ending points of the axis, respectively
     ///
     /// Default value: [false false].
     #[field("Extend", default = [false, false])]
-    extend: [bool; 2],
+    pub(crate) extend: [bool; 2],
 }
 
 #[derive(Debug, Clone, Copy)]
-struct Coords {
-    x0: f32,
-    y0: f32,
-    x1: f32,
-    y1: f32,
+pub(crate) struct Coords {
+    pub(crate) x0: f32,
+    pub(crate) y0: f32,
+    pub(crate) x1: f32,
+    pub(crate) y1: f32,
 }
 
 impl<'a> FromObj<'a> for Coords {
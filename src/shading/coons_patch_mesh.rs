@@ -1,6 +1,10 @@
-use crate::{filter::flate::BitsPerComponent, function::Function, stream::Stream};
+use crate::{error::PdfResult, filter::decode_stream, stream::Stream, Resolve};
 
-use super::freeform::{BitsPerCoordinate, BitsPerFlag};
+use super::{
+    freeform::{values_per_vertex, BitsPerComponent, BitsPerCoordinate, BitsPerFlag},
+    mesh::{self, Mesh, MeshReader, PatchKind},
+    ShadingFunction,
+};
 
 /// Type 6 shadings (Coons patch meshes) are constructed from one or more colour patches,
 /// each bounded by four cubic Bézier curves. Degenerate Bézier curves are allowed and are
@@ -57,8 +61,31 @@ pub struct CoonsPatchMeshShading<'a> {
     ///
     /// This entry shall not be used with an Indexed colour space
     #[field("Function")]
-    function: Option<Function<'a>>,
+    pub(crate) function: Option<ShadingFunction<'a>>,
 
     #[field]
     stream: Stream<'a>,
 }
+
+impl<'a> CoonsPatchMeshShading<'a> {
+    /// Decode the patches of the shading, subdivided into triangles
+    pub(super) fn mesh(
+        &self,
+        num_components: usize,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Mesh> {
+        let data = decode_stream(&self.stream.stream, &self.stream.dict, resolver)?;
+
+        mesh::decode_patches(
+            MeshReader::new(
+                &data,
+                self.bits_per_coordinate.bits(),
+                self.bits_per_component.bits(),
+                self.bits_per_flag.bits(),
+                &self.decode,
+                values_per_vertex(&self.function, num_components),
+            )?,
+            PatchKind::Coons,
+        )
+    }
+}
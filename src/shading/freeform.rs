@@ -1,4 +1,9 @@
-use crate::{filter::flate::BitsPerComponent, function::Function, stream::Stream};
+use crate::{error::PdfResult, filter::decode_stream, stream::Stream, Resolve};
+
+use super::{
+    mesh::{self, Mesh, MeshReader},
+    ShadingFunction,
+};
 
 /// Type 4 shadings (free-form Gouraud-shaded triangle meshes) are commonly used to
 /// represent complex coloured and shaded three-dimensional shapes. The area to be
@@ -39,25 +44,90 @@ pub struct FreeformShading<'a> {
     decode: Vec<f32>,
 
     #[field("Function")]
-    function: Option<Function<'a>>,
+    pub(crate) function: Option<ShadingFunction<'a>>,
 
     #[field]
     stream: Stream<'a>,
 }
 
+impl<'a> FreeformShading<'a> {
+    /// Decode the triangles of the shading, whose colours have `num_components`
+    /// components when it has no function
+    pub(super) fn mesh(
+        &self,
+        num_components: usize,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Mesh> {
+        let data = decode_stream(&self.stream.stream, &self.stream.dict, resolver)?;
+
+        Ok(mesh::decode_free_form(MeshReader::new(
+            &data,
+            self.bits_per_coordinate.bits(),
+            self.bits_per_component.bits(),
+            self.bits_per_flag.bits(),
+            &self.decode,
+            values_per_vertex(&self.function, num_components),
+        )?))
+    }
+}
+
+/// Each vertex has a single parametric value if the shading has a function, and
+/// otherwise a value for each colour component
+pub(super) fn values_per_vertex(
+    function: &Option<ShadingFunction>,
+    num_components: usize,
+) -> usize {
+    if function.is_some() {
+        1
+    } else {
+        num_components
+    }
+}
+
 #[pdf_enum(Integer)]
 pub enum BitsPerCoordinate {
     One = 1,
     Two = 2,
     Four = 4,
     Eight = 8,
+    Twelve = 12,
     Sixteen = 16,
+    TwentyFour = 24,
     ThirtyTwo = 32,
 }
 
+impl BitsPerCoordinate {
+    pub fn bits(self) -> u32 {
+        self.as_integer() as u32
+    }
+}
+
+/// Unlike images, the colour components of mesh shadings may also be 12 bits
+#[pdf_enum(Integer)]
+pub enum BitsPerComponent {
+    One = 1,
+    Two = 2,
+    Four = 4,
+    Eight = 8,
+    Twelve = 12,
+    Sixteen = 16,
+}
+
+impl BitsPerComponent {
+    pub fn bits(self) -> u32 {
+        self.as_integer() as u32
+    }
+}
+
 #[pdf_enum(Integer)]
 pub enum BitsPerFlag {
     Two = 2,
     Four = 4,
     Eight = 8,
 }
+
+impl BitsPerFlag {
+    pub fn bits(self) -> u32 {
+        self.as_integer() as u32
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
-    catalog::assert_len, data_structures::Matrix, error::PdfResult, function::Function,
-    objects::Object, FromObj, Resolve,
+    catalog::assert_len, data_structures::Matrix, error::PdfResult, objects::Object, FromObj,
+    Resolve,
 };
 
+use super::ShadingFunction;
+
 /// In Type 1 (function-based) shadings, the colour at every point in the domain is defined by a specified
 /// mathematical function. The function need not be smooth or continuous. This type is the most general of the
 /// available shading types and is useful for shadings that cannot be adequately described with any of the other
@@ -29,7 +31,7 @@ pub struct FunctionBasedShading<'a> {
     /// be a superset of that of the shading dictionary. If the value returned by the function for
     /// a given colour component is out of range, it shall be adjusted to the nearest valid value
     #[field("Function")]
-    function: Function<'a>,
+    function: ShadingFunction<'a>,
 }
 
 #[derive(Debug, Clone, Copy)]
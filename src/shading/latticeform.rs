@@ -1,6 +1,10 @@
-use crate::{filter::flate::BitsPerComponent, function::Function, stream::Stream};
+use crate::{error::PdfResult, filter::decode_stream, stream::Stream, Resolve};
 
-use super::freeform::BitsPerCoordinate;
+use super::{
+    freeform::{values_per_vertex, BitsPerComponent, BitsPerCoordinate},
+    mesh::{self, Mesh, MeshReader},
+    ShadingFunction,
+};
 
 /// Type 5 shadings (lattice-form Gouraud-shaded triangle meshes) are similar to type
 /// 4, but instead of using freeform geometry, their vertices are arranged in a
@@ -22,7 +26,7 @@ pub struct LatticeformShading<'a> {
 
     /// The number of vertices in each row of the lattice; the value shall be
     /// greater than or equal to 2. The number of rows need not be specified.
-    #[field("BitsPerFlag")]
+    #[field("VerticesPerRow")]
     vertices_per_row: u32,
 
     /// An array of numbers specifying how to map vertex coordinates and colour
@@ -51,8 +55,32 @@ pub struct LatticeformShading<'a> {
     ///
     /// This entry shall not be used with an Indexed colour space.
     #[field("Function")]
-    function: Option<Function<'a>>,
+    pub(crate) function: Option<ShadingFunction<'a>>,
 
     #[field]
     stream: Stream<'a>,
 }
+
+impl<'a> LatticeformShading<'a> {
+    /// Decode the triangles of the shading
+    pub(super) fn mesh(
+        &self,
+        num_components: usize,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Mesh> {
+        let data = decode_stream(&self.stream.stream, &self.stream.dict, resolver)?;
+
+        mesh::decode_lattice_form(
+            MeshReader::new(
+                &data,
+                self.bits_per_coordinate.bits(),
+                self.bits_per_component.bits(),
+                // vertices of a lattice have no edge flags
+                0,
+                &self.decode,
+                values_per_vertex(&self.function, num_components),
+            )?,
+            self.vertices_per_row as usize,
+        )
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{error::PdfResult, geometry::Point};
+
+/// The number of rows and columns of quadrilaterals each patch is divided into,
+/// before splitting them into triangles
+const PATCH_SUBDIVISIONS: usize = 16;
+
+/// The triangles of a mesh shading (types 4 to 7), decoded from its data stream.
+/// Patches are subdivided into triangles, so every mesh shading can be painted by
+/// interpolating the colours at the vertices of each triangle across it
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    /// The vertices of the mesh, in the shading's target coordinate space
+    pub points: Vec<Point>,
+
+    /// The colour at each vertex, as `values_per_vertex` consecutive values
+    ///
+    /// These are colour components in the shading's colour space or, if the shading
+    /// has a `Function`, a single parametric value to evaluate the function with
+    pub values: Vec<f32>,
+
+    pub values_per_vertex: usize,
+
+    /// Indices into `points` of the vertices of each triangle, in the order the
+    /// triangles are painted
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    fn new(values_per_vertex: usize) -> Self {
+        Self {
+            values_per_vertex,
+            ..Self::default()
+        }
+    }
+
+    /// The colour values at the vertex `idx`
+    pub fn vertex_values(&self, idx: usize) -> &[f32] {
+        &self.values[idx * self.values_per_vertex..(idx + 1) * self.values_per_vertex]
+    }
+
+    fn push_vertex(&mut self, point: Point, values: &[f32]) -> usize {
+        self.points.push(point);
+        self.values.extend_from_slice(values);
+
+        self.points.len() - 1
+    }
+}
+
+/// Reads the packed vertex and patch data of a mesh shading. Each item is a
+/// big-endian integer of the given number of bits, which is mapped to the ranges
+/// in the shading's `Decode` array
+pub(super) struct MeshReader<'data> {
+    data: &'data [u8],
+    bit_offset: usize,
+    bits_per_coordinate: u32,
+    bits_per_component: u32,
+    bits_per_flag: u32,
+    decode: &'data [f32],
+    values_per_vertex: usize,
+}
+
+impl<'data> MeshReader<'data> {
+    pub fn new(
+        data: &'data [u8],
+        bits_per_coordinate: u32,
+        bits_per_component: u32,
+        bits_per_flag: u32,
+        decode: &'data [f32],
+        values_per_vertex: usize,
+    ) -> PdfResult<Self> {
+        if decode.len() < 4 + values_per_vertex * 2 {
+            anyhow::bail!(
+                "expected mesh shading decode array to have {} values, found {:?}",
+                4 + values_per_vertex * 2,
+                decode
+            );
+        }
+
+        Ok(Self {
+            data,
+            bit_offset: 0,
+            bits_per_coordinate,
+            bits_per_component,
+            bits_per_flag,
+            decode,
+            values_per_vertex,
+        })
+    }
+
+    /// Read the next `bits` bits, or `None` if the data ends before them
+    fn read_bits(&mut self, bits: u32) -> Option<u32> {
+        if self.bit_offset + bits as usize > self.data.len() * 8 {
+            return None;
+        }
+
+        let mut value = 0_u64;
+        let mut remaining = bits;
+
+        while remaining > 0 {
+            let byte = self.data[self.bit_offset / 8];
+            let available = 8 - (self.bit_offset % 8) as u32;
+            let take = available.min(remaining);
+            let shift = available - take;
+            let bits = (u64::from(byte) >> shift) & ((1 << take) - 1);
+
+            value = (value << take) | bits;
+            remaining -= take;
+            self.bit_offset += take as usize;
+        }
+
+        Some(value as u32)
+    }
+
+    /// Skip to the start of the next byte
+    fn align(&mut self) {
+        self.bit_offset = self.bit_offset.next_multiple_of(8);
+    }
+
+    /// Only the least significant 2 bits of each flag are used
+    fn read_flag(&mut self) -> Option<u32> {
+        Some(self.read_bits(self.bits_per_flag)? & 0b11)
+    }
+
+    fn read_point(&mut self) -> Option<Point> {
+        let x = self.read_bits(self.bits_per_coordinate)?;
+        let y = self.read_bits(self.bits_per_coordinate)?;
+
+        Some(Point::new(
+            decode(x, self.bits_per_coordinate, self.decode[0], self.decode[1]),
+            decode(y, self.bits_per_coordinate, self.decode[2], self.decode[3]),
+        ))
+    }
+
+    fn read_values(&mut self) -> Option<Vec<f32>> {
+        (0..self.values_per_vertex)
+            .map(|i| {
+                let value = self.read_bits(self.bits_per_component)?;
+
+                Some(decode(
+                    value,
+                    self.bits_per_component,
+                    self.decode[4 + i * 2],
+                    self.decode[5 + i * 2],
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Map a `bits` bit integer to the range [min max]
+fn decode(value: u32, bits: u32, min: f32, max: f32) -> f32 {
+    let max_value = ((1_u64 << bits) - 1) as f64;
+
+    (min as f64 + value as f64 * (max - min) as f64 / max_value) as f32
+}
+
+/// Decode a free-form triangle mesh (type 4), where the edge flag of each vertex
+/// says whether it starts a new triangle or forms one with an edge of the previous
+/// triangle. Each vertex starts at a byte boundary
+pub(super) fn decode_free_form(mut reader: MeshReader) -> Mesh {
+    let mut mesh = Mesh::new(reader.values_per_vertex);
+
+    let mut previous: Option<[usize; 3]> = None;
+    let mut triangle = Vec::with_capacity(3);
+
+    while let (Some(flag), Some(point), Some(values)) = (
+        reader.read_flag(),
+        reader.read_point(),
+        reader.read_values(),
+    ) {
+        reader.align();
+
+        // the flags of the second and third vertices of a new triangle are ignored
+        if triangle.is_empty() {
+            match (flag, previous) {
+                (1, Some([_, b, c])) => triangle.extend([b, c]),
+                (2, Some([a, _, c])) => triangle.extend([a, c]),
+                _ => {}
+            }
+        }
+
+        triangle.push(mesh.push_vertex(point, &values));
+
+        if let [a, b, c] = triangle[..] {
+            mesh.triangles.push([a, b, c]);
+            previous = Some([a, b, c]);
+            triangle.clear();
+        }
+    }
+
+    mesh
+}
+
+/// Decode a lattice-form triangle mesh (type 5), whose vertices are arranged in
+/// rows of `vertices_per_row`. Each quadrilateral of adjacent vertices in two
+/// consecutive rows is split into two triangles
+pub(super) fn decode_lattice_form(
+    mut reader: MeshReader,
+    vertices_per_row: usize,
+) -> PdfResult<Mesh> {
+    if vertices_per_row < 2 {
+        anyhow::bail!(
+            "expected at least 2 vertices per row, found {}",
+            vertices_per_row
+        );
+    }
+
+    let mut mesh = Mesh::new(reader.values_per_vertex);
+
+    while let (Some(point), Some(values)) = (reader.read_point(), reader.read_values()) {
+        mesh.push_vertex(point, &values);
+    }
+
+    let num_rows = mesh.points.len() / vertices_per_row;
+
+    for row in 1..num_rows {
+        for col in 1..vertices_per_row {
+            let upper_right = row * vertices_per_row + col;
+            let upper_left = upper_right - 1;
+            let lower_right = upper_right - vertices_per_row;
+            let lower_left = lower_right - 1;
+
+            mesh.triangles.push([lower_left, lower_right, upper_left]);
+            mesh.triangles.push([lower_right, upper_right, upper_left]);
+        }
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PatchKind {
+    /// Bounded by 12 control points, with the 4 inner control points implied
+    Coons,
+
+    /// All 16 control points are given
+    TensorProduct,
+}
+
+/// A patch as it appears in the data stream
+struct Patch {
+    /// The 12 control points around the boundary of the patch, starting from its
+    /// first corner, followed by the 4 inner control points
+    points: [Point; 16],
+
+    /// The colour at each corner of the patch, which are the control points 0, 3,
+    /// 6 and 9
+    colors: [Vec<f32>; 4],
+}
+
+impl Patch {
+    /// The control points as a grid, where `grid[i][j]` is the control point
+    /// `p(i, j)` of the spec
+    fn grid(&self, kind: PatchKind) -> [[Point; 4]; 4] {
+        let p = &self.points;
+
+        let mut grid = [
+            [p[0], p[1], p[2], p[3]],
+            [p[11], p[12], p[13], p[4]],
+            [p[10], p[15], p[14], p[5]],
+            [p[9], p[8], p[7], p[6]],
+        ];
+
+        if kind == PatchKind::Coons {
+            // the inner control points which make a tensor-product patch equivalent
+            // to the coons patch
+            let inner = |corner: (usize, usize),
+                         neighbours: [(usize, usize); 2],
+                         corners: [(usize, usize); 2],
+                         opposite_edges: [(usize, usize); 2],
+                         opposite: (usize, usize)| {
+                let at = |(i, j): (usize, usize)| grid[i][j];
+                let sum = |a, b| at(a) + at(b);
+
+                (at(corner) * -4.0
+                    + sum(neighbours[0], neighbours[1]) * 6.0
+                    + sum(corners[0], corners[1]) * -2.0
+                    + sum(opposite_edges[0], opposite_edges[1]) * 3.0
+                    - at(opposite))
+                    * (1.0 / 9.0)
+            };
+
+            let p11 = inner(
+                (0, 0),
+                [(0, 1), (1, 0)],
+                [(0, 3), (3, 0)],
+                [(3, 1), (1, 3)],
+                (3, 3),
+            );
+            let p12 = inner(
+                (0, 3),
+                [(0, 2), (1, 3)],
+                [(0, 0), (3, 3)],
+                [(3, 2), (1, 0)],
+                (3, 0),
+            );
+            let p21 = inner(
+                (3, 0),
+                [(3, 1), (2, 0)],
+                [(3, 3), (0, 0)],
+                [(0, 1), (2, 3)],
+                (0, 3),
+            );
+            let p22 = inner(
+                (3, 3),
+                [(3, 2), (2, 3)],
+                [(3, 0), (0, 3)],
+                [(0, 2), (2, 0)],
+                (0, 0),
+            );
+
+            grid[1][1] = p11;
+            grid[1][2] = p12;
+            grid[2][1] = p21;
+            grid[2][2] = p22;
+        }
+
+        grid
+    }
+}
+
+/// Decode a Coons (type 6) or tensor-product (type 7) patch mesh, subdividing each
+/// patch into triangles
+///
+/// A patch whose edge flag is non-zero shares an edge with the previous patch,
+/// along with the colours at either end of it
+pub(super) fn decode_patches(mut reader: MeshReader, kind: PatchKind) -> PdfResult<Mesh> {
+    let num_points = match kind {
+        PatchKind::Coons => 12,
+        PatchKind::TensorProduct => 16,
+    };
+
+    let mut mesh = Mesh::new(reader.values_per_vertex);
+    let mut previous: Option<Patch> = None;
+
+    while let Some(flag) = reader.read_flag() {
+        let mut points = [Point::new(0.0, 0.0); 16];
+        let mut colors: [Vec<f32>; 4] = Default::default();
+
+        // the number of control points and colours shared with the previous patch
+        let (num_shared_points, num_shared_colors) = if flag == 0 {
+            (0, 0)
+        } else {
+            let Some(previous) = &previous else {
+                anyhow::bail!("mesh shading patch continues from a missing patch");
+            };
+
+            // the shared edge starts from the second, third or fourth corner of the
+            // previous patch
+            let start = 3 * flag as usize;
+
+            for (i, point) in points[..4].iter_mut().enumerate() {
+                *point = previous.points[(start + i) % 12];
+            }
+
+            colors[0] = previous.colors[flag as usize].clone();
+            colors[1] = previous.colors[(flag as usize + 1) % 4].clone();
+
+            (4, 2)
+        };
+
+        let mut is_complete = true;
+
+        for point in &mut points[num_shared_points..num_points] {
+            match reader.read_point() {
+                Some(p) => *point = p,
+                None => is_complete = false,
+            }
+        }
+
+        for color in &mut colors[num_shared_colors..] {
+            match reader.read_values() {
+                Some(values) => *color = values,
+                None => is_complete = false,
+            }
+        }
+
+        if !is_complete {
+            break;
+        }
+
+        let patch = Patch { points, colors };
+
+        subdivide_patch(&mut mesh, &patch, kind);
+
+        previous = Some(patch);
+    }
+
+    Ok(mesh)
+}
+
+/// Add the triangles approximating `patch` to the mesh. Points of the patch are
+/// given by `S(u, v) = Σ Σ p(i, j) * B_i(u) * B_j(v)`, where `B_i` are the cubic
+/// Bernstein polynomials, and colours are interpolated bilinearly between the corners
+fn subdivide_patch(mesh: &mut Mesh, patch: &Patch, kind: PatchKind) {
+    let grid = patch.grid(kind);
+
+    let bernstein = |t: f32| {
+        let s = 1.0 - t;
+
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+    };
+
+    let first_vertex = mesh.points.len();
+    let mut values = vec![0.0; mesh.values_per_vertex];
+
+    // where the patch folds over itself, the spec requires points with larger
+    // values of v, then u, to be painted on top, so v varies slowest
+    for row in 0..=PATCH_SUBDIVISIONS {
+        let v = row as f32 / PATCH_SUBDIVISIONS as f32;
+        let bv = bernstein(v);
+
+        for col in 0..=PATCH_SUBDIVISIONS {
+            let u = col as f32 / PATCH_SUBDIVISIONS as f32;
+            let bu = bernstein(u);
+
+            let mut point = Point::new(0.0, 0.0);
+
+            for (i, row) in grid.iter().enumerate() {
+                for (j, &p) in row.iter().enumerate() {
+                    point += p * (bu[i] * bv[j]);
+                }
+            }
+
+            // the corners p(0, 0), p(0, 3), p(3, 3) and p(3, 0)
+            let [c00, c01, c11, c10] = &patch.colors;
+
+            for (k, value) in values.iter_mut().enumerate() {
+                *value = (1.0 - u) * (1.0 - v) * c00[k]
+                    + (1.0 - u) * v * c01[k]
+                    + u * v * c11[k]
+                    + u * (1.0 - v) * c10[k];
+            }
+
+            mesh.push_vertex(point, &values);
+        }
+    }
+
+    let vertex = |row: usize, col: usize| first_vertex + row * (PATCH_SUBDIVISIONS + 1) + col;
+
+    for row in 0..PATCH_SUBDIVISIONS {
+        for col in 0..PATCH_SUBDIVISIONS {
+            mesh.triangles
+                .push([vertex(row, col), vertex(row, col + 1), vertex(row + 1, col)]);
+            mesh.triangles.push([
+                vertex(row, col + 1),
+                vertex(row + 1, col + 1),
+                vertex(row + 1, col),
+            ]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+
+    use super::{decode_free_form, decode_patches, MeshReader, PatchKind};
+
+    #[test]
+    fn free_form_triangles_share_edges_with_previous_triangle() {
+        // 8 bit flags, coordinates and gray components, decoded to [0 255]
+        let decode = [0.0, 255.0, 0.0, 255.0, 0.0, 255.0];
+        let data = [
+            0, 0, 0, 0, //
+            0, 10, 0, 50, //
+            0, 0, 10, 100, //
+            1, 10, 10, 150, //
+            2, 20, 20, 200, //
+        ];
+
+        let mesh = decode_free_form(MeshReader::new(&data, 8, 8, 8, &decode, 1).unwrap());
+
+        assert_eq!(mesh.points.len(), 5);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [1, 2, 3], [1, 3, 4]]);
+        assert_eq!(mesh.points[3], Point::new(10.0, 10.0));
+        assert_eq!(mesh.vertex_values(4), &[200.0]);
+    }
+
+    #[test]
+    fn coons_patch_is_subdivided_between_its_corners() {
+        // a flat unit square patch, with a parametric value of 0 on its left edge
+        // and 1 on its right edge
+        let decode = [0.0, 255.0, 0.0, 255.0, 0.0, 1.0];
+        let mut data = vec![0];
+        let boundary = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (3, 3),
+            (3, 2),
+            (3, 1),
+            (3, 0),
+            (2, 0),
+            (1, 0),
+        ];
+
+        for (x, y) in boundary {
+            data.extend([x, y]);
+        }
+
+        data.extend([0, 0, 255, 255]);
+
+        let mesh = decode_patches(
+            MeshReader::new(&data, 8, 8, 8, &decode, 1).unwrap(),
+            PatchKind::Coons,
+        )
+        .unwrap();
+
+        let last = mesh.points.len() - 1;
+
+        assert_eq!(mesh.triangles.len(), 2 * 16 * 16);
+        assert_eq!(mesh.points[0], Point::new(0.0, 0.0));
+        assert_eq!(mesh.points[last], Point::new(3.0, 3.0));
+
+        // the middle of the patch
+        let middle = mesh.points.len() / 2;
+        assert!((mesh.points[middle].x - 1.5).abs() < 1e-4);
+        assert!((mesh.points[middle].y - 1.5).abs() < 1e-4);
+        assert!((mesh.vertex_values(middle)[0] - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn first_patch_cannot_continue_from_previous_patch() {
+        let decode = [0.0, 255.0, 0.0, 255.0, 0.0, 1.0];
+        let mut data = vec![1];
+        data.extend([0; 8 * 2 + 2]);
+
+        assert!(decode_patches(
+            MeshReader::new(&data, 8, 8, 8, &decode, 1).unwrap(),
+            PatchKind::Coons,
+        )
+        .is_err());
+    }
+}
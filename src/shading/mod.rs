@@ -2,7 +2,7 @@ use crate::{
     color::ColorSpace,
     data_structures::Rectangle,
     error::PdfResult,
-    function::StreamOrDict,
+    function::{Function, StreamOrDict},
     objects::{Dictionary, Object},
     FromObj, Resolve,
 };
@@ -13,18 +13,24 @@ use self::{
     tensor_product_patch_mesh::TensorProductPatchMeshShading,
 };
 
-mod axial;
+pub use self::mesh::Mesh;
+
+pub(crate) mod axial;
 mod coons_patch_mesh;
 mod freeform;
 mod function_based;
 mod latticeform;
-mod radial;
+mod mesh;
+pub(crate) mod radial;
 mod tensor_product_patch_mesh;
 
 #[derive(Debug, Clone)]
 pub struct ShadingObject<'a> {
-    base: BaseShadingDictionary<'a>,
-    sub_type: SubtypeShadingDictionary<'a>,
+    pub(crate) base: BaseShadingDictionary<'a>,
+    pub(crate) sub_type: SubtypeShadingDictionary<'a>,
+
+    /// The triangles of a mesh shading, decoded from its data stream
+    mesh: Option<Mesh>,
 }
 
 impl<'a> FromObj<'a> for ShadingObject<'a> {
@@ -38,7 +44,33 @@ impl<'a> FromObj<'a> for ShadingObject<'a> {
             resolver,
         )?;
 
-        Ok(ShadingObject { base, sub_type })
+        let mesh = sub_type.mesh(base.color_space.num_components(), resolver)?;
+
+        Ok(ShadingObject {
+            base,
+            sub_type,
+            mesh,
+        })
+    }
+}
+
+impl<'a> ShadingObject<'a> {
+    /// For mesh shadings (types 4 to 7), the triangles to paint, with patches
+    /// subdivided into triangles
+    pub fn mesh(&self) -> Option<&Mesh> {
+        self.mesh.as_ref()
+    }
+
+    /// The function of a mesh shading, which gives the colour for each of the
+    /// parametric values of its vertices
+    pub(crate) fn mesh_function(&self) -> Option<&ShadingFunction<'a>> {
+        match &self.sub_type {
+            SubtypeShadingDictionary::Freeform(shading) => shading.function.as_ref(),
+            SubtypeShadingDictionary::Latticeform(shading) => shading.function.as_ref(),
+            SubtypeShadingDictionary::CoonsPatchMesh(shading) => shading.function.as_ref(),
+            SubtypeShadingDictionary::TensorProductPatchMesh(shading) => shading.function.as_ref(),
+            _ => None,
+        }
     }
 }
 
@@ -85,6 +117,68 @@ impl<'a> SubtypeShadingDictionary<'a> {
             }
         })
     }
+
+    /// Decode the data stream of a mesh shading, whose colours have `num_components`
+    /// components when it has no function
+    fn mesh(
+        &self,
+        num_components: usize,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Option<Mesh>> {
+        Ok(Some(match self {
+            SubtypeShadingDictionary::Freeform(shading) => {
+                shading.mesh(num_components, resolver)?
+            }
+            SubtypeShadingDictionary::Latticeform(shading) => {
+                shading.mesh(num_components, resolver)?
+            }
+            SubtypeShadingDictionary::CoonsPatchMesh(shading) => {
+                shading.mesh(num_components, resolver)?
+            }
+            SubtypeShadingDictionary::TensorProductPatchMesh(shading) => {
+                shading.mesh(num_components, resolver)?
+            }
+            SubtypeShadingDictionary::FunctionBased(..)
+            | SubtypeShadingDictionary::Axial(..)
+            | SubtypeShadingDictionary::Radial(..) => return Ok(None),
+        }))
+    }
+}
+
+/// The `Function` entry of a shading dictionary
+#[derive(Debug, Clone)]
+pub enum ShadingFunction<'a> {
+    /// A single function with an output for each colour component
+    Single(Function<'a>),
+
+    /// A 1-out function for each colour component
+    PerComponent(Vec<Function<'a>>),
+}
+
+impl<'a> FromObj<'a> for ShadingFunction<'a> {
+    fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        Ok(match resolver.resolve(obj)? {
+            Object::Array(arr) => ShadingFunction::PerComponent(
+                arr.into_iter()
+                    .map(|obj| Function::from_obj(obj, resolver))
+                    .collect::<PdfResult<Vec<_>>>()?,
+            ),
+            obj => ShadingFunction::Single(Function::from_obj(obj, resolver)?),
+        })
+    }
+}
+
+impl<'a> ShadingFunction<'a> {
+    /// The colour components at `input`
+    pub fn evaluate(&self, input: &[f32]) -> Vec<f32> {
+        match self {
+            ShadingFunction::Single(function) => function.evaluate(input),
+            ShadingFunction::PerComponent(functions) => functions
+                .iter()
+                .flat_map(|function| function.evaluate(input).first().copied())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -93,7 +187,7 @@ pub struct BaseShadingDictionary<'a> {
 
     /// The colour space in which colour values shall be expressed. This may be any device,
     /// CIE-based, or special colour space except a Pattern space
-    color_space: ColorSpace<'a>,
+    pub(crate) color_space: ColorSpace<'a>,
 
     /// An array of colour components appropriate to the colour space, specifying a single
     /// background colour value. If present, this colour shall be used, before any painting
@@ -105,7 +199,7 @@ pub struct BaseShadingDictionary<'a> {
     ///
     /// The background colour is applied only when the shading is used as part of a shading
     /// pattern, not when it is painted directly with the sh operator
-    background: Option<Vec<f32>>,
+    pub(crate) background: Option<Vec<f32>>,
 
     /// An array of four numbers giving the left, bottom, right, and top coordinates,
     /// respectively, of the shading's bounding box. The coordinates shall be interpreted
@@ -113,7 +207,7 @@ pub struct BaseShadingDictionary<'a> {
     /// applied as a temporary clipping boundary when the shading is painted, in addition
     /// to the current clipping path and any other clipping boundaries in effect at that
     /// time
-    bbox: Option<Rectangle>,
+    pub(crate) bbox: Option<Rectangle>,
 
     /// A flag indicating whether to filter the shading function to prevent aliasing artifacts
     ///
@@ -1,6 +1,6 @@
-use crate::{
-    catalog::assert_len, error::PdfResult, function::Function, objects::Object, FromObj, Resolve,
-};
+use crate::{catalog::assert_len, error::PdfResult, objects::Object, FromObj, Resolve};
+
+use super::ShadingFunction;
 
 /// Type 3 (radial) shadings define a colour blend that varies between two circles. Shadings
 /// of this type are commonly used to depict three-dimensional spheres and cones
@@ -14,7 +14,7 @@ pub struct RadialShading<'a> {
     /// radius is 0, the corresponding circle shall be treated as a point; if both are
     /// 0, nothing shall be painted
     #[field("Coords")]
-    coords: Coords,
+    pub(crate) coords: Coords,
 
     /// An array of two numbers [t0 t1] specifying the limiting values of a parametric
     /// variable t. The variable is considered to vary linearly between these two values
@@ -23,7 +23,7 @@ pub struct RadialShading<'a> {
     ///
     /// Default value: [0.0 1.0].
     #[field("Domain", default = [0.0, 1.0])]
-    domain: [f32; 2],
+    pub(crate) domain: [f32; 2],
 
     /// A 1-in, n-out function or an array of n 1-in, 1-out functions (where n is the
     /// number of colour components in the shading dictionary's colour space). The
@@ -33,20 +33,20 @@ pub struct RadialShading<'a> {
     /// for a given colour component is out of range, it shall be adjusted to the nearest
     /// valid value.
     #[field("Function")]
-    function: Function<'a>,
+    pub(crate) function: ShadingFunction<'a>,
 
     /// An array of two boolean values specifying whether to extend the shading beyond the
     /// starting and ending circles, respectively
     ///
     /// Default value: [false false].
     #[field("Extend", default = [false, false])]
-    extend: [bool; 2],
+    pub(crate) extend: [bool; 2],
 }
 
 #[derive(Debug, Clone)]
-struct Coords {
-    start: Circle,
-    end: Circle,
+pub(crate) struct Coords {
+    pub(crate) start: Circle,
+    pub(crate) end: Circle,
 }
 
 impl<'a> FromObj<'a> for Coords {
@@ -63,10 +63,10 @@ impl<'a> FromObj<'a> for Coords {
 }
 
 #[derive(Debug, Clone, Copy)]
-struct Circle {
-    x: f32,
-    y: f32,
-    radius: f32,
+pub(crate) struct Circle {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) radius: f32,
 }
 
 impl<'a> FromObj<'a> for Circle {
@@ -4,7 +4,7 @@ use crate::{
     error::{ParseError, PdfResult},
     file_specification::FileSpecification,
     filter::FilterKind,
-    objects::{Dictionary, Object, ObjectType},
+    objects::{Dictionary, Object, ObjectType, Reference},
     FromObj, Resolve,
 };
 
@@ -23,6 +23,38 @@ impl fmt::Debug for Stream<'_> {
     }
 }
 
+impl<'a> Stream<'a> {
+    /// Create a stream from its dictionary and its data, which should already
+    /// be encoded by the filters the dictionary names. The `Length` entry is
+    /// set from the length of the data
+    ///
+    /// The entries describing the stream's filters must be direct objects
+    pub fn new(mut dict: Dictionary<'a>, stream: impl Into<Cow<'a, [u8]>>) -> PdfResult<Self> {
+        let stream = stream.into();
+
+        dict.insert("Length", Object::Integer(i32::try_from(stream.len())?));
+
+        Ok(Self {
+            dict: StreamDict::from_dict(dict, &mut DirectObjects)?,
+            stream,
+        })
+    }
+}
+
+/// Used to parse stream dictionaries created outside of a document, which have
+/// nothing to resolve references against
+pub(crate) struct DirectObjects;
+
+impl<'a> Resolve<'a> for DirectObjects {
+    fn lex_object_from_reference(&mut self, reference: Reference) -> PdfResult<Object<'a>> {
+        anyhow::bail!("cannot resolve {:?} outside of a document", reference)
+    }
+
+    fn reference_exists(&mut self, _reference: Reference) -> PdfResult<bool> {
+        Ok(true)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct DecodeParams<'a> {
     params: Vec<Option<Dictionary<'a>>>,
@@ -32,6 +64,18 @@ impl<'a> DecodeParams<'a> {
     pub fn get(&self, idx: usize) -> Option<&Dictionary<'a>> {
         self.params.get(idx).and_then(|d| d.as_ref())
     }
+
+    fn to_obj(&self) -> Object<'a> {
+        match self.params.as_slice() {
+            [Some(dict)] => Object::Dictionary(dict.clone()),
+            params => Object::Array(
+                params
+                    .iter()
+                    .map(|dict| dict.clone().map_or(Object::Null, Object::Dictionary))
+                    .collect(),
+            ),
+        }
+    }
 }
 
 impl<'a> FromObj<'a> for DecodeParams<'a> {
@@ -121,6 +165,18 @@ fn get_filters<'a>(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult
     }
 }
 
+fn filters_to_obj<'a>(filters: &[FilterKind]) -> Object<'a> {
+    match filters {
+        [filter] => Object::Name(filter.as_str().to_owned()),
+        filters => Object::Array(
+            filters
+                .iter()
+                .map(|filter| Object::Name(filter.as_str().to_owned()))
+                .collect(),
+        ),
+    }
+}
+
 impl<'a> StreamDict<'a> {
     #[track_caller]
     pub fn from_dict(mut dict: Dictionary<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
@@ -150,4 +206,37 @@ impl<'a> StreamDict<'a> {
             other: dict,
         })
     }
+
+    /// Convert back into a dictionary, as it would appear in a file
+    pub(crate) fn to_dict(&self) -> PdfResult<Dictionary<'a>> {
+        let mut dict = self.other.clone();
+
+        dict.insert("Length", Object::Integer(i32::try_from(self.len)?));
+
+        if let Some(filter) = &self.filter {
+            dict.insert("Filter", filters_to_obj(filter));
+        }
+
+        if let Some(decode_parms) = &self.decode_parms {
+            dict.insert("DecodeParms", decode_parms.to_obj());
+        }
+
+        if let Some(f) = &self.f {
+            dict.insert("F", f.to_obj()?);
+        }
+
+        if let Some(f_filter) = &self.f_filter {
+            dict.insert("FFilter", filters_to_obj(f_filter));
+        }
+
+        if let Some(f_decode_parms) = &self.f_decode_parms {
+            dict.insert("FDecodeParms", f_decode_parms.to_obj());
+        }
+
+        if let Some(decoded_len) = self.decoded_len {
+            dict.insert("DL", Object::Integer(i32::try_from(decoded_len)?));
+        }
+
+        Ok(dict)
+    }
 }
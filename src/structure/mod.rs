@@ -57,6 +57,7 @@ impl<'a> StructTreeRoot<'a> {
 
 impl<'a> FromObj<'a> for StructTreeRoot<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut dict = resolver.assert_dict(obj)?;
 
         dict.expect_type(Self::TYPE, resolver, true)?;
@@ -72,7 +73,7 @@ impl<'a> FromObj<'a> for StructTreeRoot<'a> {
         let role_map = dict.get_dict("RoleMap", resolver)?;
         let class_map = dict.get_dict("ClassMap", resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, reference, resolver);
 
         Ok(Self {
             k,
@@ -206,7 +207,7 @@ impl<'a> StructureElement<'a> {
         let e = dict.get_string("E", resolver)?;
         let actual_text = dict.get_string("ActualText", resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, None, resolver);
 
         Ok(Self {
             s,
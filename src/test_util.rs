@@ -0,0 +1,103 @@
+//! Objects and documents shared by the tests of each module
+
+use std::collections::HashMap;
+
+use crate::{
+    objects::{Dictionary, Object, Reference},
+    stream::Stream,
+    PdfWriter, XrefFormat,
+};
+
+pub(crate) fn dict(entries: Vec<(&str, Object<'static>)>) -> Dictionary<'static> {
+    Dictionary::new(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+pub(crate) fn reference(object_number: usize) -> Reference {
+    Reference {
+        object_number,
+        generation: 0,
+    }
+}
+
+pub(crate) fn numbers(numbers: &[f32]) -> Object<'static> {
+    Object::Array(numbers.iter().map(|&n| Object::Real(n)).collect())
+}
+
+pub(crate) fn name(name: &str) -> Object<'static> {
+    Object::Name(name.to_owned())
+}
+
+/// Write a document made up of `objects`, numbered from 1 in order. The first
+/// object is the document catalog
+pub(crate) fn document(objects: Vec<Object<'static>>, xref_format: XrefFormat) -> Vec<u8> {
+    let trailer = dict(vec![("Root", Object::Reference(reference(1)))]);
+
+    let mut writer = PdfWriter::new(trailer, xref_format);
+
+    for (idx, obj) in objects.into_iter().enumerate() {
+        writer.add_object(reference(idx + 1), obj).unwrap();
+    }
+
+    writer.to_bytes().unwrap()
+}
+
+/// Write a document with a single US Letter page, which paints `content` using
+/// `resources`. Any further objects the resources refer to are numbered from 5
+pub(crate) fn single_page_document(
+    content: &[u8],
+    resources: Dictionary<'static>,
+    objects: Vec<Object<'static>>,
+) -> Vec<u8> {
+    let mut all_objects = vec![
+        Object::Dictionary(dict(vec![
+            ("Type", name("Catalog")),
+            ("Pages", Object::Reference(reference(2))),
+        ])),
+        Object::Dictionary(dict(vec![
+            ("Type", name("Pages")),
+            ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+            ("Count", Object::Integer(1)),
+        ])),
+        Object::Dictionary(dict(vec![
+            ("Type", name("Page")),
+            ("Parent", Object::Reference(reference(2))),
+            ("MediaBox", numbers(&[0.0, 0.0, 612.0, 792.0])),
+            ("Resources", Object::Dictionary(resources)),
+            ("Contents", Object::Reference(reference(4))),
+        ])),
+        Object::Stream(Stream::new(dict(Vec::new()), content.to_vec()).unwrap()),
+    ];
+
+    all_objects.extend(objects);
+
+    document(all_objects, XrefFormat::Table)
+}
+
+/// A 4x2 JP2 file whose colours are looked up in a palette of red, green and
+/// blue by a component cycling through them, and whose opacity channel rises
+/// from transparent on the left to opaque on the right
+pub(crate) const JP2_WITH_PALETTE_AND_ALPHA: &[u8] = &[
+    0x00, 0x00, 0x00, 0x0c, 0x6a, 0x50, 0x20, 0x20, 0x0d, 0x0a, 0x87, 0x0a, 0x00, 0x00, 0x00, 0x14,
+    0x66, 0x74, 0x79, 0x70, 0x6a, 0x70, 0x32, 0x20, 0x00, 0x00, 0x00, 0x00, 0x6a, 0x70, 0x32, 0x20,
+    0x00, 0x00, 0x00, 0x7e, 0x6a, 0x70, 0x32, 0x68, 0x00, 0x00, 0x00, 0x16, 0x69, 0x68, 0x64, 0x72,
+    0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04, 0x00, 0x02, 0x07, 0x07, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x0f, 0x63, 0x6f, 0x6c, 0x72, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+    0x17, 0x70, 0x63, 0x6c, 0x72, 0x00, 0x03, 0x03, 0x07, 0x07, 0x07, 0xff, 0x00, 0x00, 0x00, 0xff,
+    0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0x18, 0x63, 0x6d, 0x61, 0x70, 0x00, 0x00, 0x01, 0x00,
+    0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x22,
+    0x63, 0x64, 0x65, 0x66, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+    0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x7d, 0x6a, 0x70, 0x32, 0x63, 0xff, 0x4f, 0xff, 0x51, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0xff, 0x52, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x01, 0xff, 0x5c, 0x00, 0x07, 0x40, 0x48, 0x50, 0x50, 0x58, 0xff, 0x90, 0x00,
+    0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x01, 0xff, 0x93, 0xc7, 0xda, 0x04, 0x08, 0x60,
+    0xcf, 0xc0, 0x0c, 0x06, 0xea, 0xa7, 0xc0, 0x08, 0x60, 0x0e, 0x83, 0x00, 0x3a, 0x10, 0x03, 0x02,
+    0x02, 0x1f, 0xc3, 0xed, 0x03, 0x00, 0x0b, 0x65, 0x7f, 0xff, 0xd9,
+];
@@ -0,0 +1,357 @@
+use crate::{
+    data_structures::Matrix,
+    error::PdfResult,
+    filter::decode_stream,
+    font::{
+        glyph_name_to_unicode, standard_encoding_glyph_name, CMap, CidFontWidths, Font,
+        FontDescriptor, FontEncoding, PredefinedCjkCmapName, Type0Font, Type0FontEncoding, Widths,
+        BASE_14_FONTS,
+    },
+    geometry::Point,
+    postscript::parse_cmap,
+    render::{text_state::FontMetrics, RenderableFont},
+    Resolve,
+};
+
+/// Used when the font descriptor does not give the ascent or descent, as is
+/// allowed for the standard 14 fonts and Type 3 fonts
+const DEFAULT_ASCENT: f32 = 0.8;
+const DEFAULT_DESCENT: f32 = -0.2;
+
+fn ascent(descriptor: Option<&FontDescriptor>) -> f32 {
+    match descriptor.and_then(|descriptor| descriptor.ascent) {
+        Some(ascent) if ascent > 0.0 => ascent / 1000.0,
+        _ => DEFAULT_ASCENT,
+    }
+}
+
+fn descent(descriptor: Option<&FontDescriptor>) -> f32 {
+    match descriptor.and_then(|descriptor| descriptor.descent) {
+        Some(descent) if descent < 0.0 => descent / 1000.0,
+        _ => DEFAULT_DESCENT,
+    }
+}
+
+/// How the bytes of a string are split into character codes
+#[derive(Debug)]
+enum CodeSource {
+    /// Simple fonts always use single byte codes
+    SingleByte,
+
+    /// A CMap embedded in the PDF as the encoding of a Type 0 font
+    Embedded(CMap),
+
+    /// One of the predefined CMaps named by the encoding of a Type 0 font,
+    /// along with a CMap holding only its codespace ranges
+    Predefined(PredefinedCjkCmapName, CMap),
+}
+
+#[derive(Debug)]
+enum GlyphWidths {
+    /// Widths of a simple font, indexed by character code. Type 3 fonts express
+    /// their widths in glyph space, and so also carry their font matrix
+    Simple {
+        widths: Option<Widths>,
+        font_matrix: Option<Matrix>,
+    },
+
+    /// Widths of a standard 14 font that omits them, taken from its font
+    /// program and indexed by character code
+    Standard(Vec<f32>),
+
+    /// Widths of a CIDFont, indexed by CID
+    Cid(CidFontWidths),
+}
+
+/// A single character code read from a string shown by a text showing operator
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodedChar {
+    pub code: u32,
+
+    /// The number of bytes the code occupies in the string
+    pub len: usize,
+
+    /// The Unicode value of the code, if it could be determined
+    pub unicode: Option<String>,
+
+    /// The horizontal displacement of the glyph, in unscaled text space units
+    pub width: f32,
+}
+
+impl DecodedChar {
+    /// Word spacing only applies to the single byte code 32
+    pub fn is_word_space(&self) -> bool {
+        self.len == 1 && self.code == 32
+    }
+}
+
+/// Maps the strings shown with a particular font to character codes, their
+/// Unicode values and their widths
+///
+/// Unicode values are found, in order of preference, through the font's
+/// ToUnicode CMap, through the glyph names of a simple font's encoding, and
+/// through the predefined CMap used as the encoding of a Type 0 font
+#[derive(Debug)]
+pub(crate) struct FontDecoder {
+    name: String,
+    codes: CodeSource,
+    to_unicode: Option<CMap>,
+
+    /// The encoding of a simple font
+    encoding: Option<FontEncoding>,
+    is_symbolic: bool,
+
+    widths: GlyphWidths,
+    is_vertical: bool,
+
+    /// The extent of the glyphs above and below the baseline, in unscaled text
+    /// space units
+    ascent: f32,
+    descent: f32,
+}
+
+impl FontDecoder {
+    /// Create a decoder for `font`, which was selected under the resource name
+    /// `resource_name`
+    pub fn new<'a>(
+        font: &Font<'a>,
+        resource_name: &str,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
+        let name = font.base_font().unwrap_or(resource_name).to_owned();
+
+        let (to_unicode, encoding, base) = match font {
+            Font::Type1(font) => (&font.to_unicode, font.encoding.clone(), &font.base),
+            Font::MmType1(font) => (
+                &font.type1.to_unicode,
+                font.type1.encoding.clone(),
+                &font.type1.base,
+            ),
+            Font::TrueType(font) => (&font.to_unicode, font.encoding.clone(), &font.base),
+            Font::Type3(font) => {
+                return Ok(Self {
+                    name,
+                    codes: CodeSource::SingleByte,
+                    to_unicode: font
+                        .to_unicode
+                        .as_ref()
+                        .map(|to_unicode| to_unicode.parse(resolver))
+                        .transpose()?,
+                    encoding: Some(font.encoding.clone()),
+                    is_symbolic: false,
+                    widths: GlyphWidths::Simple {
+                        widths: font.base.widths.clone(),
+                        font_matrix: Some(font.font_matrix),
+                    },
+                    is_vertical: false,
+                    ascent: DEFAULT_ASCENT,
+                    descent: DEFAULT_DESCENT,
+                })
+            }
+            Font::Type0(font) => return Self::new_composite(name, font, resolver),
+        };
+
+        let is_symbolic = base
+            .font_descriptor
+            .as_ref()
+            .is_some_and(FontDescriptor::is_symbolic);
+
+        let widths = match (&base.widths, BASE_14_FONTS.get(name.as_str())) {
+            (None, Some(font)) => {
+                let mut font = font.write().unwrap();
+                let font_matrix = font.font_matrix();
+
+                GlyphWidths::Standard(
+                    (0..=255)
+                        .map(|code| {
+                            let glyph_index =
+                                font.glyph_index(code, encoding.as_ref(), is_symbolic);
+                            let glyph = font.evaluate(glyph_index, resolver)?;
+
+                            Ok(font_matrix.transform_vector(glyph.width_vector).x)
+                        })
+                        .collect::<PdfResult<_>>()?,
+                )
+            }
+            (widths, _) => GlyphWidths::Simple {
+                widths: widths.clone(),
+                font_matrix: None,
+            },
+        };
+
+        Ok(Self {
+            name,
+            codes: CodeSource::SingleByte,
+            to_unicode: to_unicode
+                .as_ref()
+                .map(|to_unicode| to_unicode.parse(resolver))
+                .transpose()?,
+            encoding,
+            is_symbolic,
+            widths,
+            is_vertical: false,
+            ascent: ascent(base.font_descriptor.as_ref()),
+            descent: descent(base.font_descriptor.as_ref()),
+        })
+    }
+
+    fn new_composite<'a>(
+        name: String,
+        font: &Type0Font<'a>,
+        resolver: &mut dyn Resolve<'a>,
+    ) -> PdfResult<Self> {
+        let codes = match &font.encoding {
+            Type0FontEncoding::Predefined(name) => {
+                CodeSource::Predefined(*name, CMap::with_codespace_ranges(name.codespace_ranges()))
+            }
+            Type0FontEncoding::Stream(stream) => {
+                let buffer = decode_stream(&stream.stream, &stream.dict, resolver)?;
+
+                CodeSource::Embedded(parse_cmap(&buffer)?)
+            }
+        };
+
+        let is_vertical = match &codes {
+            CodeSource::Predefined(name, ..) => name.is_vertical(),
+            CodeSource::Embedded(cmap) => cmap.is_vertical(),
+            CodeSource::SingleByte => false,
+        };
+
+        let [descendant] = &font.descendant_font;
+
+        Ok(Self {
+            name,
+            codes,
+            to_unicode: font
+                .to_unicode
+                .as_ref()
+                .map(|to_unicode| to_unicode.parse(resolver))
+                .transpose()?,
+            encoding: None,
+            is_symbolic: false,
+            widths: GlyphWidths::Cid(CidFontWidths {
+                map: descendant.widths.map.clone(),
+                default: descendant.default_width,
+            }),
+            is_vertical,
+            ascent: ascent(Some(&descendant.font_descriptor)),
+            descent: descent(Some(&descendant.font_descriptor)),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The encoding of a simple font
+    pub fn encoding(&self) -> Option<&FontEncoding> {
+        self.encoding.as_ref()
+    }
+
+    pub fn is_symbolic(&self) -> bool {
+        self.is_symbolic
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.is_vertical
+    }
+
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    pub fn descent(&self) -> f32 {
+        self.descent
+    }
+
+    /// Split a string into character codes
+    pub fn decode(&self, bytes: &[u8]) -> Vec<DecodedChar> {
+        let mut chars = Vec::new();
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            let remaining = &bytes[idx..];
+
+            let (code, len) = match &self.codes {
+                CodeSource::SingleByte => (remaining[0] as u32, 1),
+                CodeSource::Embedded(cmap) | CodeSource::Predefined(_, cmap) => {
+                    cmap.next_code(remaining)
+                }
+            };
+
+            chars.push(DecodedChar {
+                code,
+                len,
+                unicode: self.unicode(code, &remaining[..len]),
+                width: self.width(code),
+            });
+
+            idx += len.max(1);
+        }
+
+        chars
+    }
+
+    fn unicode(&self, code: u32, bytes: &[u8]) -> Option<String> {
+        if let Some(unicode) = self
+            .to_unicode
+            .as_ref()
+            .and_then(|to_unicode| to_unicode.to_unicode(code))
+        {
+            return Some(unicode);
+        }
+
+        match &self.codes {
+            CodeSource::SingleByte => {
+                let code = code as u8;
+
+                let glyph_name = match &self.encoding {
+                    Some(encoding) => encoding.glyph_name(code, self.is_symbolic),
+                    None => None,
+                }
+                // the built-in encoding of the font program is not known to us,
+                // but it is most often the standard encoding
+                .or_else(|| standard_encoding_glyph_name(code))?;
+
+                glyph_name_to_unicode(glyph_name)
+            }
+            CodeSource::Predefined(name, ..) => name.to_unicode(bytes),
+            CodeSource::Embedded(..) => None,
+        }
+    }
+
+    /// The CID selected by `code`, for the CIDFont of a Type 0 font
+    pub fn cid(&self, code: u32) -> Option<u32> {
+        match &self.codes {
+            CodeSource::SingleByte => None,
+            CodeSource::Embedded(cmap) => cmap.cid(code),
+            CodeSource::Predefined(name, ..) if name.is_identity() => Some(code),
+            CodeSource::Predefined(..) => None,
+        }
+    }
+
+    fn width(&self, code: u32) -> f32 {
+        match &self.widths {
+            GlyphWidths::Simple {
+                widths: Some(widths),
+                font_matrix: Some(font_matrix),
+            } => {
+                font_matrix
+                    .transform_vector(Point::new(FontMetrics::get(widths, code) * 1000.0, 0.0))
+                    .x
+            }
+            GlyphWidths::Simple {
+                widths: Some(widths),
+                font_matrix: None,
+            } => FontMetrics::get(widths, code),
+            // a font may only omit its widths if it is one of the standard
+            // 14 fonts, so this is one whose font program couldn't be found
+            GlyphWidths::Simple { widths: None, .. } => 0.5,
+            GlyphWidths::Standard(widths) => widths.get(code as usize).copied().unwrap_or(0.0),
+            GlyphWidths::Cid(widths) => match self.cid(code) {
+                Some(cid) => FontMetrics::get(widths, cid),
+                None => widths.default as f32 / 1000.0,
+            },
+        }
+    }
+}
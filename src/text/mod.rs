@@ -0,0 +1,880 @@
+//! Extraction of the text shown by the content stream of a page
+//!
+//! Text is recovered by tracking the text state and the current transformation
+//! matrix while walking the content stream, the same way the renderer does,
+//! and mapping each character code shown to Unicode. The resulting glyphs are
+//! joined into lines and words based on their positions on the page
+
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+
+use crate::{
+    color::{ColorSpace, ColorSpaceName},
+    content::{ContentLexer, ContentToken, PdfGraphicsOperator},
+    data_structures::Matrix,
+    diagnostic::{Diagnostic, ParseOptions},
+    error::PdfResult,
+    filter::decode_stream,
+    font::Font,
+    geometry::Point,
+    objects::{Object, Reference},
+    render::{
+        error::PdfRenderError,
+        text_state::{ShownGlyph, TextState},
+    },
+    resources::Resources,
+    xobject::{FormXObject, XObject},
+    FromObj, Resolve,
+};
+
+pub use self::span::{Quad, SpanGlyph, TextSpan};
+
+pub(crate) use self::decoder::{DecodedChar, FontDecoder};
+
+mod decoder;
+mod span;
+
+/// The parts of the graphics state that affect how text is extracted, saved
+/// and restored by `q` and `Q`
+#[derive(Debug, Clone)]
+struct TextGraphicsState<'b> {
+    current_transformation_matrix: Matrix,
+
+    /// The nonstroking color, in the RGBA layout used by the canvas
+    fill_color: u32,
+
+    text_state: TextState<'b>,
+}
+
+impl Default for TextGraphicsState<'_> {
+    fn default() -> Self {
+        Self {
+            current_transformation_matrix: Matrix::identity(),
+            fill_color: ColorSpace::init(ColorSpaceName::DeviceGray).as_u32(),
+            text_state: TextState::default(),
+        }
+    }
+}
+
+pub(crate) struct TextExtractor<'a, 'b> {
+    content: ContentLexer<'b>,
+    resolver: &'a mut dyn Resolve<'b>,
+    resources: Option<Rc<Resources<'b>>>,
+    operand_stack: Vec<Object<'b>>,
+
+    state: TextGraphicsState<'b>,
+    state_stack: Vec<TextGraphicsState<'b>>,
+
+    /// The color space used by `sc` and `scn` to interpret their operands, if
+    /// it is one of the device color spaces
+    fill_color_space: Option<ColorSpaceName>,
+
+    /// Decoders for every font used so far, keyed by the address of the font
+    decoders: HashMap<*const Font<'b>, Rc<FontDecoder>>,
+    decoder: Option<Rc<FontDecoder>>,
+
+    /// The form XObjects being extracted, so that a form which shows itself
+    /// isn't extracted again
+    forms: Vec<Reference>,
+
+    spans: Vec<TextSpan>,
+}
+
+impl<'a, 'b> TextExtractor<'a, 'b> {
+    pub fn new(
+        content: ContentLexer<'b>,
+        resolver: &'a mut dyn Resolve<'b>,
+        resources: Option<Rc<Resources<'b>>>,
+    ) -> Self {
+        Self {
+            content,
+            resolver,
+            resources,
+            operand_stack: Vec::new(),
+            state: TextGraphicsState::default(),
+            state_stack: Vec::new(),
+            fill_color_space: Some(ColorSpaceName::DeviceGray),
+            decoders: HashMap::new(),
+            decoder: None,
+            forms: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Walk the content stream, returning a span for each string shown, in the
+    /// order in which they appear in the stream
+    pub fn spans(mut self) -> PdfResult<Vec<TextSpan>> {
+        self.extract_content_stream()?;
+
+        Ok(self.spans)
+    }
+
+    /// Walk the content stream, returning the text shown with line breaks and
+    /// spaces inserted based on the positions of the glyphs
+    pub fn text(self) -> PdfResult<String> {
+        Ok(layout(&self.spans()?))
+    }
+
+    fn pop<T: FromObj<'b>>(&mut self) -> PdfResult<T> {
+        Ok(self
+            .operand_stack
+            .pop()
+            .map(|obj| T::from_obj(obj, self.resolver))
+            .transpose()?
+            .ok_or(PdfRenderError::StackUnderflow)?)
+    }
+
+    fn pop_number(&mut self) -> PdfResult<f32> {
+        let obj = self.pop()?;
+
+        self.resolver.assert_number(obj)
+    }
+
+    fn pop_name(&mut self) -> PdfResult<String> {
+        let obj = self.pop()?;
+
+        self.resolver.assert_name(obj)
+    }
+
+    fn pop_matrix(&mut self) -> PdfResult<Matrix> {
+        let f = self.pop_number()?;
+        let e = self.pop_number()?;
+        let d = self.pop_number()?;
+        let c = self.pop_number()?;
+        let b = self.pop_number()?;
+        let a = self.pop_number()?;
+
+        Ok(Matrix::new(a, b, c, d, e, f))
+    }
+
+    fn extract_content_stream(&mut self) -> PdfResult<()> {
+        while let Some(token) = self.content.next() {
+            let op = match token? {
+                ContentToken::Object(obj) => {
+                    self.operand_stack.push(obj);
+                    continue;
+                }
+                ContentToken::Operator(op) => op,
+            };
+
+            let text_state = &mut self.state.text_state;
+
+            if let Some(arr) =
+                text_state.text_to_show(op, &mut self.operand_stack, self.resolver)?
+            {
+                self.show_text(arr)?;
+            } else if !text_state.apply_operator(op, &mut self.operand_stack, self.resolver)? {
+                self.apply_operator(op)?;
+            }
+
+            self.operand_stack.clear();
+        }
+
+        for diagnostic in self.content.take_diagnostics() {
+            self.resolver.report(diagnostic);
+        }
+
+        Ok(())
+    }
+
+    /// Apply an operator that affects the graphics state other than the text
+    /// state, or that selects a font or paints a form XObject
+    fn apply_operator(&mut self, op: PdfGraphicsOperator) -> PdfResult<()> {
+        match op {
+            PdfGraphicsOperator::q => self.state_stack.push(self.state.clone()),
+            PdfGraphicsOperator::Q => {
+                if let Some(state) = self.state_stack.pop() {
+                    self.set_state(state);
+                }
+            }
+            PdfGraphicsOperator::cm => {
+                let matrix = self.pop_matrix()?;
+
+                self.state.current_transformation_matrix =
+                    matrix * self.state.current_transformation_matrix;
+            }
+            PdfGraphicsOperator::g => self.set_fill_color(Some(ColorSpaceName::DeviceGray)),
+            PdfGraphicsOperator::rg => self.set_fill_color(Some(ColorSpaceName::DeviceRGB)),
+            PdfGraphicsOperator::k => self.set_fill_color(Some(ColorSpaceName::DeviceCMYK)),
+            PdfGraphicsOperator::cs => self.set_fill_color_space()?,
+            PdfGraphicsOperator::sc | PdfGraphicsOperator::scn => {
+                self.set_fill_color(self.fill_color_space)
+            }
+            PdfGraphicsOperator::Do => self.show_xobject()?,
+            PdfGraphicsOperator::Tf => self.set_font_and_size()?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Restore a graphics state saved by `q`, updating the decoder to match the
+    /// restored font
+    fn set_state(&mut self, state: TextGraphicsState<'b>) {
+        self.decoder = state
+            .text_state
+            .font
+            .as_ref()
+            .and_then(|font| self.decoders.get(&Rc::as_ptr(font)))
+            .map(Rc::clone);
+
+        self.state = state;
+    }
+
+    /// Set the nonstroking color space. Color spaces other than the device
+    /// color spaces are not resolved; their colors are instead interpreted by
+    /// the number of components given to `sc` and `scn`
+    fn set_fill_color_space(&mut self) -> PdfResult<()> {
+        let name = self.pop_name()?;
+
+        self.fill_color_space = match ColorSpaceName::from_str(&name) {
+            Ok(
+                name @ (ColorSpaceName::DeviceGray
+                | ColorSpaceName::DeviceRGB
+                | ColorSpaceName::DeviceCMYK),
+            ) => Some(name),
+            _ => None,
+        };
+
+        self.state.fill_color = ColorSpace::init(ColorSpaceName::DeviceGray).as_u32();
+
+        Ok(())
+    }
+
+    fn set_fill_color(&mut self, color_space: Option<ColorSpaceName>) {
+        let mut components = Vec::new();
+
+        for obj in self.operand_stack.drain(..) {
+            match obj {
+                Object::Integer(n) => components.push(n as f32),
+                Object::Real(n) => components.push(n),
+                // the name of a pattern
+                _ => return,
+            }
+        }
+
+        let color = match (color_space, components.as_slice()) {
+            (Some(ColorSpaceName::DeviceGray) | None, &[gray]) => ColorSpace::DeviceGray(gray),
+            (Some(ColorSpaceName::DeviceRGB) | None, &[red, green, blue]) => {
+                ColorSpace::DeviceRGB { red, green, blue }
+            }
+            (Some(ColorSpaceName::DeviceCMYK) | None, &[cyan, magenta, yellow, key]) => {
+                ColorSpace::DeviceCMYK {
+                    cyan,
+                    magenta,
+                    yellow,
+                    key,
+                }
+            }
+            _ => return,
+        };
+
+        self.state.fill_color = color.as_u32();
+    }
+
+    fn set_font_and_size(&mut self) -> PdfResult<()> {
+        let size = self.pop_number()?;
+        let name = self.pop_name()?;
+
+        let font = match self
+            .resources
+            .as_ref()
+            .and_then(|res| res.font.as_ref())
+            .and_then(|fonts| fonts.get(&name))
+        {
+            Some(font) => Rc::clone(font),
+            None => anyhow::bail!("could not find font with name {:?}", name),
+        };
+
+        let decoder = match self.decoders.get(&Rc::as_ptr(&font)) {
+            Some(decoder) => Rc::clone(decoder),
+            None => {
+                let decoder = Rc::new(FontDecoder::new(&font, &name, self.resolver)?);
+
+                self.decoders.insert(Rc::as_ptr(&font), Rc::clone(&decoder));
+
+                decoder
+            }
+        };
+
+        self.state.text_state.font = Some(font);
+        self.state.text_state.font_size = size;
+        self.decoder = Some(decoder);
+
+        Ok(())
+    }
+
+    fn show_xobject(&mut self) -> PdfResult<()> {
+        let name = self.pop_name()?;
+
+        let form = match self
+            .resources
+            .as_ref()
+            .and_then(|res| res.xobject.as_ref())
+            .and_then(|xobjects| xobjects.get(&name))
+        {
+            Some(XObject::Form(form)) => FormXObject::clone(form),
+            _ => return Ok(()),
+        };
+
+        if let Some(reference) = form.indirect_reference {
+            if self.forms.contains(&reference) {
+                self.resolver.report(Diagnostic::new(
+                    Some(reference),
+                    None,
+                    format!("skipping form XObject {} shown by itself", name),
+                ));
+
+                return Ok(());
+            }
+        }
+
+        let content = decode_stream(&form.stream.stream, &form.stream.dict, self.resolver)?;
+
+        let mut content = ContentLexer::with_options(
+            Cow::Owned(content.into_owned()),
+            ParseOptions {
+                lenient: self.resolver.is_lenient(),
+                ..ParseOptions::default()
+            },
+        );
+        let mut resources = form.resources.or_else(|| self.resources.clone());
+        let state = self.state.clone();
+        let depth = self.forms.len();
+
+        std::mem::swap(&mut self.content, &mut content);
+        std::mem::swap(&mut self.resources, &mut resources);
+        self.state.current_transformation_matrix =
+            form.matrix * state.current_transformation_matrix;
+        self.forms.extend(form.indirect_reference);
+
+        let result = self.extract_content_stream();
+
+        std::mem::swap(&mut self.content, &mut content);
+        std::mem::swap(&mut self.resources, &mut resources);
+        self.set_state(state);
+        self.forms.truncate(depth);
+
+        result
+    }
+
+    /// Show the strings in `arr`, adjusting the text position by any numbers
+    /// between them, as done by `TJ`
+    fn show_text(&mut self, arr: Vec<Object<'b>>) -> PdfResult<()> {
+        let decoder = match &self.decoder {
+            Some(decoder) => Rc::clone(decoder),
+            None => anyhow::bail!("no font selected in text state"),
+        };
+
+        let mut span = TextSpan {
+            text: String::new(),
+            glyphs: Vec::new(),
+            font_name: decoder.name().to_owned(),
+            font_size: 0.0,
+            fill_color: self.state.fill_color.to_le_bytes(),
+            rendering_mode: self.state.text_state.rendering_mode,
+        };
+
+        let glyphs = self.state.text_state.show_text(
+            arr,
+            &decoder,
+            self.state.current_transformation_matrix,
+            self.resolver,
+        )?;
+
+        for glyph in glyphs {
+            show_glyph(glyph, &decoder, &mut span);
+        }
+
+        if !span.glyphs.is_empty() {
+            self.spans.push(span);
+        }
+
+        Ok(())
+    }
+}
+
+/// Add a glyph to the span of the string that showed it
+fn show_glyph(glyph: ShownGlyph, decoder: &FontDecoder, span: &mut TextSpan) {
+    let ShownGlyph {
+        char: c,
+        text_rendering_matrix,
+    } = glyph;
+
+    // in vertical writing, the origin of a glyph is at the top center of
+    // its em box
+    let (left, right, bottom, top) = if decoder.is_vertical() {
+        (-0.5, 0.5, -1.0, 0.0)
+    } else {
+        (0.0, c.width, decoder.descent(), decoder.ascent())
+    };
+
+    let quad = Quad {
+        lower_left: text_rendering_matrix * Point::new(left, bottom),
+        lower_right: text_rendering_matrix * Point::new(right, bottom),
+        upper_right: text_rendering_matrix * Point::new(right, top),
+        upper_left: text_rendering_matrix * Point::new(left, top),
+    };
+
+    let start = span.text.len();
+    if let Some(unicode) = &c.unicode {
+        span.text.push_str(unicode);
+    }
+
+    span.font_size = (text_rendering_matrix * Point::new(0.0, 1.0))
+        .euclidean_distance(text_rendering_matrix * Point::origin());
+    span.glyphs.push(SpanGlyph {
+        text: start..span.text.len(),
+        quad,
+    });
+}
+
+/// Join spans into text, starting a new line when a glyph leaves the baseline
+/// of the previous glyph or moves back along it, and inserting a space when
+/// the gap between two glyphs is wide enough to separate words
+fn layout(spans: &[TextSpan]) -> String {
+    let mut text = String::new();
+    let mut prev: Option<&Quad> = None;
+
+    let glyphs = spans.iter().flat_map(|span| {
+        span.glyphs
+            .iter()
+            .map(move |glyph| (&span.text[glyph.text.clone()], &glyph.quad))
+    });
+
+    for (glyph_text, quad) in glyphs {
+        if glyph_text.is_empty() {
+            continue;
+        }
+
+        if let Some(prev) = prev {
+            let size = prev.height().max(quad.height());
+
+            // the baseline runs perpendicular to the left edge, which unlike
+            // the bottom edge does not vanish for glyphs without width
+            let up = prev.upper_left - prev.lower_left;
+            if up.distance_from_origin() <= f32::EPSILON {
+                text.push_str(glyph_text);
+                continue;
+            }
+            let direction = Point::new(up.y, -up.x).with_distance_from_origin(1.0);
+
+            let offset = quad.lower_left - prev.lower_right;
+            let along = offset.x * direction.x + offset.y * direction.y;
+            let across = offset.y * direction.x - offset.x * direction.y;
+
+            let ends_with_whitespace = text.ends_with(char::is_whitespace);
+            let starts_with_whitespace = glyph_text.starts_with(char::is_whitespace);
+
+            if across.abs() > size * 0.5 || along < -size {
+                text.truncate(text.trim_end_matches(' ').len());
+
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            } else if along > size * 0.2 && !ends_with_whitespace && !starts_with_whitespace {
+                text.push(' ');
+            }
+        }
+
+        text.push_str(glyph_text);
+        prev = Some(quad);
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        objects::{Dictionary, Object},
+        render::text_state::TextRenderingMode,
+        stream::Stream,
+        test_util::{dict, name, numbers, reference, single_page_document},
+        Parser,
+    };
+
+    use super::TextSpan;
+
+    /// A parser for a document whose only page shows `content` with `font` as
+    /// the resource `F1`. Objects the font refers to are numbered from 5
+    fn parser(
+        font: Dictionary<'static>,
+        content: &[u8],
+        objects: Vec<Object<'static>>,
+    ) -> Parser<'static> {
+        let resources = dict(vec![(
+            "Font",
+            Object::Dictionary(dict(vec![("F1", Object::Dictionary(font))])),
+        )]);
+
+        Parser::from_bytes(single_page_document(content, resources, objects)).unwrap()
+    }
+
+    fn page_text(
+        font: Dictionary<'static>,
+        content: &[u8],
+        objects: Vec<Object<'static>>,
+    ) -> String {
+        let mut parser = parser(font, content, objects);
+        let page = parser.page(0).unwrap();
+
+        parser.page_text(&page).unwrap()
+    }
+
+    fn page_text_spans(font: Dictionary<'static>, content: &[u8]) -> Vec<TextSpan> {
+        let mut parser = parser(font, content, Vec::new());
+        let page = parser.page(0).unwrap();
+
+        parser.page_text_spans(&page).unwrap()
+    }
+
+    /// Helvetica with a width of 250 for the space and 500 for every other
+    /// code up to `A`
+    fn helvetica_with_widths() -> Dictionary<'static> {
+        let mut widths = vec![500.0; 34];
+        widths[0] = 250.0;
+
+        helvetica(vec![
+            ("FirstChar", Object::Integer(32)),
+            ("LastChar", Object::Integer(65)),
+            ("Widths", numbers(&widths)),
+        ])
+    }
+
+    fn helvetica(entries: Vec<(&str, Object<'static>)>) -> Dictionary<'static> {
+        let mut font = vec![
+            ("Type", name("Font")),
+            ("Subtype", name("Type1")),
+            ("BaseFont", name("Helvetica")),
+        ];
+
+        font.extend(entries);
+
+        dict(font)
+    }
+
+    #[test]
+    fn to_unicode_bfchar_and_bfrange() {
+        let to_unicode = Stream::new(
+            Dictionary::empty(),
+            b"begincmap
+1 begincodespacerange
+<00> <FF>
+endcodespacerange
+2 beginbfchar
+<01> <0048>
+<02> <00660069>
+endbfchar
+1 beginbfrange
+<10> <12> <0061>
+endbfrange
+endcmap"
+                .to_vec(),
+        )
+        .unwrap();
+
+        let font = helvetica(vec![("ToUnicode", Object::Reference(reference(5)))]);
+
+        let text = page_text(
+            font,
+            b"BT /F1 12 Tf 72 700 Td <011011> Tj 0 -20 Td <021112> Tj ET",
+            vec![Object::Stream(to_unicode)],
+        );
+
+        assert_eq!(text, "Hab\nfibc");
+    }
+
+    #[test]
+    fn encoding_differences_use_glyph_list() {
+        let encoding = dict(vec![
+            ("Type", name("Encoding")),
+            ("BaseEncoding", name("WinAnsiEncoding")),
+            (
+                "Differences",
+                Object::Array(vec![
+                    Object::Integer(65),
+                    name("eacute"),
+                    name("uni00E8"),
+                    name("ffi"),
+                    name("f_f_i"),
+                ]),
+            ),
+        ]);
+
+        let font = helvetica(vec![("Encoding", Object::Dictionary(encoding))]);
+
+        // codes outside of the differences fall back to the base encoding
+        let text = page_text(
+            font,
+            b"BT /F1 12 Tf 72 700 Td (ABCDE\\344) Tj ET",
+            Vec::new(),
+        );
+
+        assert_eq!(text, "éèﬃffiEä");
+    }
+
+    #[test]
+    fn type0_font_without_to_unicode() {
+        let descriptor = dict(vec![
+            ("Type", name("FontDescriptor")),
+            ("FontName", name("KozMinPr6N-Regular")),
+            ("Flags", Object::Integer(4)),
+            ("FontBBox", numbers(&[0.0, -120.0, 1000.0, 880.0])),
+            ("ItalicAngle", Object::Integer(0)),
+            ("Ascent", Object::Integer(880)),
+            ("Descent", Object::Integer(-120)),
+            ("CapHeight", Object::Integer(742)),
+            ("StemV", Object::Integer(80)),
+        ]);
+        let descendant = dict(vec![
+            ("Type", name("Font")),
+            ("Subtype", name("CIDFontType0")),
+            ("BaseFont", name("KozMinPr6N-Regular")),
+            (
+                "CIDSystemInfo",
+                Object::Dictionary(dict(vec![
+                    ("Registry", Object::String("Adobe".to_owned())),
+                    ("Ordering", Object::String("Japan1".to_owned())),
+                    ("Supplement", Object::Integer(6)),
+                ])),
+            ),
+            ("FontDescriptor", Object::Dictionary(descriptor)),
+        ]);
+        let font = |encoding: &str| {
+            dict(vec![
+                ("Type", name("Font")),
+                ("Subtype", name("Type0")),
+                ("BaseFont", name("KozMinPr6N-Regular")),
+                ("Encoding", name(encoding)),
+                (
+                    "DescendantFonts",
+                    Object::Array(vec![Object::Dictionary(descendant.clone())]),
+                ),
+            ])
+        };
+
+        let text = page_text(
+            font("UniJIS-UCS2-H"),
+            b"BT /F1 12 Tf 72 700 Td <65E5672C8A9E> Tj ET",
+            Vec::new(),
+        );
+
+        assert_eq!(text, "日本語");
+
+        // only the ASCII range of legacy encodings is known without the tables
+        // of the character collection
+        let text = page_text(
+            font("90ms-RKSJ-H"),
+            b"BT /F1 12 Tf 72 700 Td <41938F> Tj ET",
+            Vec::new(),
+        );
+
+        assert_eq!(text, "A");
+
+        let text = page_text(
+            font("Identity-H"),
+            b"BT /F1 12 Tf 72 700 Td <00220023> Tj ET",
+            Vec::new(),
+        );
+
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn form_shown_by_itself_is_extracted_once() {
+        // without resources of its own, the form inherits those of the page,
+        // under which it is named
+        let form = Stream::new(
+            dict(vec![
+                ("Type", name("XObject")),
+                ("Subtype", name("Form")),
+                ("BBox", numbers(&[0.0, 0.0, 612.0, 792.0])),
+            ]),
+            b"BT /F1 12 Tf 72 700 Td (A) Tj ET /X1 Do".to_vec(),
+        )
+        .unwrap();
+
+        let resources = dict(vec![
+            (
+                "Font",
+                Object::Dictionary(dict(vec![(
+                    "F1",
+                    Object::Dictionary(helvetica_with_widths()),
+                )])),
+            ),
+            (
+                "XObject",
+                Object::Dictionary(dict(vec![("X1", Object::Reference(reference(5)))])),
+            ),
+        ]);
+
+        let mut parser = Parser::from_bytes(single_page_document(
+            b"/X1 Do",
+            resources,
+            vec![Object::Stream(form)],
+        ))
+        .unwrap();
+        let page = parser.page(0).unwrap();
+
+        assert_eq!(parser.page_text(&page).unwrap(), "A");
+
+        let [diagnostic] = parser.diagnostics() else {
+            panic!(
+                "expected a single diagnostic, found {:?}",
+                parser.diagnostics()
+            );
+        };
+
+        assert_eq!(diagnostic.reference, Some(reference(5)));
+    }
+
+    #[test]
+    fn span_quads_follow_spacing_scaling_and_adjustments() {
+        let spans = page_text_spans(
+            helvetica_with_widths(),
+            b"BT /F1 10 Tf 2 Tc 3 Tw 50 Tz 100 200 Td [(A) -1000 (A A)] TJ ET",
+        );
+
+        let [span] = spans.as_slice() else {
+            panic!("expected a single span, found {:?}", spans);
+        };
+
+        assert_eq!(span.text, "AA A");
+
+        // each glyph advances by (w * Tfs + Tc + Tw) * Th, and the TJ
+        // adjustment moves the second glyph right by 1000/1000 * Tfs * Th
+        let left = span
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.quad.lower_left.x)
+            .collect::<Vec<f32>>();
+        let right = span
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.quad.lower_right.x)
+            .collect::<Vec<f32>>();
+
+        assert_eq!(left, vec![100.0, 108.5, 112.0, 115.75]);
+        assert_eq!(right, vec![102.5, 111.0, 113.25, 118.25]);
+
+        // the default ascent and descent are used without a font descriptor
+        for glyph in &span.glyphs {
+            assert_eq!(glyph.quad.lower_left.y, 198.0);
+            assert_eq!(glyph.quad.upper_left.y, 208.0);
+        }
+    }
+
+    #[test]
+    fn type3_advance_ignores_font_matrix_translation() {
+        let font = dict(vec![
+            ("Type", name("Font")),
+            ("Subtype", name("Type3")),
+            ("FontBBox", numbers(&[0.0, 0.0, 1000.0, 1000.0])),
+            ("FontMatrix", numbers(&[0.001, 0.0, 0.0, 0.001, 10.0, 0.0])),
+            (
+                "CharProcs",
+                Object::Dictionary(dict(vec![("square", Object::Reference(reference(5)))])),
+            ),
+            (
+                "Encoding",
+                Object::Dictionary(dict(vec![(
+                    "Differences",
+                    Object::Array(vec![Object::Integer(65), name("square")]),
+                )])),
+            ),
+            ("FirstChar", Object::Integer(65)),
+            ("LastChar", Object::Integer(65)),
+            ("Widths", numbers(&[500.0])),
+        ]);
+        let glyph = Stream::new(
+            Dictionary::empty(),
+            b"500 0 0 0 1000 1000 d1 0 0 500 1000 re f".to_vec(),
+        )
+        .unwrap();
+
+        let mut parser = parser(
+            font,
+            b"BT /F1 10 Tf 100 200 Td (AA) Tj ET",
+            vec![Object::Stream(glyph)],
+        );
+        let page = parser.page(0).unwrap();
+        let spans = parser.page_text_spans(&page).unwrap();
+
+        let left = spans
+            .iter()
+            .flat_map(|span| &span.glyphs)
+            .map(|glyph| glyph.quad.lower_left.x)
+            .collect::<Vec<f32>>();
+
+        assert_eq!(left, vec![100.0, 105.0]);
+    }
+
+    #[test]
+    fn span_rise_and_font_size_are_in_default_user_space() {
+        let spans = page_text_spans(
+            helvetica_with_widths(),
+            b"2 0 0 2 0 0 cm BT /F1 10 Tf 5 Ts 2 0 0 2 10 20 Tm (A) Tj ET",
+        );
+
+        let [span] = spans.as_slice() else {
+            panic!("expected a single span, found {:?}", spans);
+        };
+
+        let quad = &span.glyphs[0].quad;
+
+        // the rise is in unscaled text space units, so isn't scaled by the
+        // font size
+        assert_eq!((quad.lower_left.x, quad.lower_left.y), (20.0, 52.0));
+        assert_eq!((quad.upper_right.x, quad.upper_right.y), (40.0, 92.0));
+        assert_eq!(span.font_size, 40.0);
+    }
+
+    #[test]
+    fn span_font_fill_color_and_rendering_mode() {
+        let spans = page_text_spans(
+            helvetica_with_widths(),
+            b"BT /F1 12 Tf 72 700 Td (A) Tj 1 0 0 rg 1 Tr (A) ' 0 0 1 0 k 3 Tr 4 2 (AA) \" ET",
+        );
+
+        let styles = spans
+            .iter()
+            .map(|span| {
+                (
+                    span.font_name.as_str(),
+                    span.font_size,
+                    span.fill_color,
+                    span.rendering_mode,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            styles,
+            vec![
+                ("Helvetica", 12.0, [0, 0, 0, 255], TextRenderingMode::Fill),
+                (
+                    "Helvetica",
+                    12.0,
+                    [255, 0, 0, 255],
+                    TextRenderingMode::Stroke
+                ),
+                (
+                    "Helvetica",
+                    12.0,
+                    [255, 255, 0, 255],
+                    TextRenderingMode::Invisible
+                ),
+            ]
+        );
+
+        // ' and " move to the next line, using the leading of 0 here, and "
+        // sets the word and character spacing before showing its string
+        let left = spans
+            .iter()
+            .flat_map(|span| &span.glyphs)
+            .map(|glyph| glyph.quad.lower_left.x)
+            .collect::<Vec<f32>>();
+
+        assert_eq!(left, vec![72.0, 72.0, 72.0, 80.0]);
+    }
+}
@@ -0,0 +1,76 @@
+use std::ops::Range;
+
+use crate::{geometry::Point, render::text_state::TextRenderingMode};
+
+/// A quadrilateral in the default user space of a page, which need not be axis
+/// aligned when the text is rotated or skewed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quad {
+    pub lower_left: Point,
+    pub lower_right: Point,
+    pub upper_right: Point,
+    pub upper_left: Point,
+}
+
+impl Quad {
+    /// The smallest axis aligned rectangle containing the quad, as
+    /// `(lower_left, upper_right)`
+    pub fn bounds(&self) -> (Point, Point) {
+        let points = [
+            self.lower_left,
+            self.lower_right,
+            self.upper_right,
+            self.upper_left,
+        ];
+
+        let min = points
+            .iter()
+            .fold(Point::new(f32::INFINITY, f32::INFINITY), |min, p| {
+                Point::new(min.x.min(p.x), min.y.min(p.y))
+            });
+        let max = points.iter().fold(
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            |max, p| Point::new(max.x.max(p.x), max.y.max(p.y)),
+        );
+
+        (min, max)
+    }
+
+    /// The height of the quad, measured along its left edge
+    pub(crate) fn height(&self) -> f32 {
+        self.lower_left.euclidean_distance(self.upper_left)
+    }
+}
+
+/// A single glyph within a [`TextSpan`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanGlyph {
+    /// The byte range of the span's text produced by this glyph. The range is
+    /// empty if the glyph could not be mapped to Unicode
+    pub text: Range<usize>,
+
+    /// The area covered by the glyph, from its descent to its ascent and
+    /// across its advance width
+    pub quad: Quad,
+}
+
+/// A run of glyphs shown by a single text showing operator, all sharing the
+/// same font, size, fill color and rendering mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub glyphs: Vec<SpanGlyph>,
+
+    /// The PostScript name of the font, or, for Type 3 fonts, the name of the
+    /// font resource
+    pub font_name: String,
+
+    /// The size of the font in default user space units, after applying the
+    /// text matrix and the current transformation matrix
+    pub font_size: f32,
+
+    /// The nonstroking color in effect when the span was shown, as RGBA
+    pub fill_color: [u8; 4],
+
+    pub rendering_mode: TextRenderingMode,
+}
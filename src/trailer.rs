@@ -60,7 +60,7 @@ impl<'a> Trailer<'a> {
     ) -> PdfResult<Self> {
         let trailer = Trailer::from_dict_ref(&mut dict, is_previous, resolver)?;
 
-        assert_empty(dict);
+        assert_empty(dict, None, resolver);
 
         Ok(trailer)
     }
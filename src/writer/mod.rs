@@ -0,0 +1,714 @@
+use std::{collections::BTreeMap, io::Write, rc::Rc};
+
+use crate::{
+    encryption::{CryptFilterMethod, SecurityHandler, UserAccessPermissions},
+    error::PdfResult,
+    file_specification::FileIdentifier,
+    filter::flate::flate_encode,
+    objects::{Dictionary, Object, Reference},
+    stream::Stream,
+    xref::XrefEntry,
+};
+
+use serialize::write_indirect_object;
+
+mod serialize;
+
+/// The binary comment following the header marks the file as containing
+/// binary data to tools that guess based on the first few bytes
+const HEADER: &[u8] = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n";
+
+/// The maximum number of objects written to a single object stream
+const OBJECTS_PER_STREAM: usize = 100;
+
+/// How the cross-reference information of a written file is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrefFormat {
+    /// A cross-reference table followed by a trailer dictionary, readable by
+    /// all PDF versions
+    Table,
+
+    /// A cross-reference stream, introduced in PDF 1.5
+    Stream,
+
+    /// A cross-reference stream, with every object that is allowed to be
+    /// compressed written to a flate compressed object stream
+    ObjectStreams,
+}
+
+/// The file being extended by an incremental update
+#[derive(Debug)]
+struct Original {
+    /// The file, shared with the parser it was read by
+    file: Rc<Vec<u8>>,
+
+    /// The byte offset of the last cross-reference section of the file
+    start_xref: usize,
+
+    /// The `Size` entry of the trailer of the file
+    size: usize,
+}
+
+/// Writes a set of numbered objects and a trailer to a PDF file
+///
+/// The `Size` entry of the trailer is computed when writing, and any `Prev` or
+/// `XRefStm` entries are dropped. A writer created by
+/// [`Parser::incremental_update`](crate::Parser::incremental_update) instead
+/// appends the objects to the original file, along with a cross-reference
+/// section whose `Prev` entry points at the previous section
+#[derive(Debug)]
+pub struct PdfWriter<'a> {
+    objects: BTreeMap<usize, (u16, Object<'a>)>,
+    trailer: Dictionary<'a>,
+    xref_format: XrefFormat,
+    original: Option<Original>,
+
+    /// Encrypts every object as it is written, if the document is encrypted
+    security_handler: Option<SecurityHandler<'a>>,
+}
+
+impl<'a> PdfWriter<'a> {
+    pub fn new(trailer: Dictionary<'a>, xref_format: XrefFormat) -> Self {
+        Self {
+            objects: BTreeMap::new(),
+            trailer,
+            xref_format,
+            original: None,
+            security_handler: None,
+        }
+    }
+
+    /// Create a writer appending an incremental update to `file`, encrypting
+    /// its objects with `security_handler` if the file is encrypted
+    pub(crate) fn incremental(
+        file: Rc<Vec<u8>>,
+        start_xref: usize,
+        size: usize,
+        trailer: Dictionary<'a>,
+        xref_format: XrefFormat,
+        security_handler: Option<SecurityHandler<'a>>,
+    ) -> Self {
+        Self {
+            objects: BTreeMap::new(),
+            trailer,
+            xref_format,
+            original: Some(Original {
+                file,
+                start_xref,
+                size,
+            }),
+            security_handler,
+        }
+    }
+
+    /// Add an indirect object, replacing any object previously added with
+    /// the same object number
+    pub fn add_object(&mut self, reference: Reference, obj: Object<'a>) -> PdfResult<()> {
+        if reference.object_number == 0 {
+            anyhow::bail!("object number 0 is reserved for the head of the free list");
+        }
+
+        let generation = u16::try_from(reference.generation)?;
+
+        self.objects
+            .insert(reference.object_number, (generation, obj));
+
+        Ok(())
+    }
+
+    /// The lowest object number not used by any object added so far, nor by
+    /// the original file of an incremental update
+    pub fn next_object_number(&self) -> usize {
+        let next = self.objects.keys().next_back().map_or(1, |&n| n + 1);
+
+        match &self.original {
+            Some(original) => next.max(original.size),
+            None => next,
+        }
+    }
+
+    /// Encrypt the document with the standard security handler, protected by
+    /// `user_password` and with `permissions` granted to anyone who opens it
+    /// with that password. Opening it with `owner_password` grants every
+    /// permission
+    ///
+    /// `method` is either AESV3 (AES-256), or AESV2 (AES-128) for readers
+    /// older than PDF 2.0. The encryption dictionary is added as the next
+    /// free object, and a file identifier is added to the trailer if it
+    /// doesn't already have one
+    pub fn encrypt(
+        &mut self,
+        user_password: &str,
+        owner_password: &str,
+        permissions: UserAccessPermissions,
+        method: CryptFilterMethod,
+    ) -> PdfResult<()> {
+        if self.original.is_some() {
+            anyhow::bail!("the encryption of a document can't be changed by an incremental update");
+        }
+
+        let id = self
+            .trailer
+            .iter()
+            .find(|(key, _)| *key == "ID")
+            .map(|(_, id)| id.clone());
+
+        let file_identifier = match id {
+            Some(Object::Array(arr)) => match arr.first() {
+                Some(Object::String(id)) => FileIdentifier([id.clone(), id.clone()]),
+                _ => anyhow::bail!("invalid file identifier in trailer"),
+            },
+            Some(..) => anyhow::bail!("invalid file identifier in trailer"),
+            None => {
+                let mut id = [0; 16];
+
+                if let Err(err) = getrandom::fill(&mut id) {
+                    anyhow::bail!("failed to generate file identifier: {}", err);
+                }
+
+                let id = id.iter().map(|&b| b as char).collect::<String>();
+
+                self.trailer.insert(
+                    "ID",
+                    Object::Array(vec![Object::String(id.clone()), Object::String(id.clone())]),
+                );
+
+                FileIdentifier([id.clone(), id])
+            }
+        };
+
+        // replace the encryption dictionary of an earlier call
+        let reference = match (
+            &self.security_handler,
+            self.trailer.iter().find(|(key, _)| *key == "Encrypt"),
+        ) {
+            (Some(..), Some((_, Object::Reference(reference)))) => *reference,
+            _ => Reference {
+                object_number: self.next_object_number(),
+                generation: 0,
+            },
+        };
+
+        let handler = SecurityHandler::with_passwords(
+            user_password,
+            owner_password,
+            permissions,
+            method,
+            reference,
+            file_identifier,
+        )?;
+
+        self.add_object(reference, Object::Dictionary(handler.encryption_dict()))?;
+        self.trailer.insert("Encrypt", Object::Reference(reference));
+        self.security_handler = Some(handler);
+
+        Ok(())
+    }
+
+    /// The object as it is written, with its strings and streams encrypted if
+    /// the document is encrypted
+    fn encrypted(&self, reference: Reference, obj: &Object<'a>) -> PdfResult<Object<'a>> {
+        match &self.security_handler {
+            Some(handler) => handler.encrypt_object(obj.clone(), reference),
+            None => Ok(obj.clone()),
+        }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> PdfResult<()> {
+        writer.write_all(&self.to_bytes()?)?;
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> PdfResult<Vec<u8>> {
+        let mut out = match &self.original {
+            // a cross-reference section must have at least one entry
+            Some(original) if self.objects.is_empty() => return Ok(original.file.to_vec()),
+            Some(original) => {
+                let mut out = original.file.to_vec();
+
+                if !out.ends_with(b"\n") && !out.ends_with(b"\r") {
+                    out.push(b'\n');
+                }
+
+                out
+            }
+            None => HEADER.to_vec(),
+        };
+
+        let mut entries = BTreeMap::new();
+
+        let compressed = match self.xref_format {
+            XrefFormat::ObjectStreams => self.compressible_objects(),
+            XrefFormat::Table | XrefFormat::Stream => Vec::new(),
+        };
+
+        for (&object_number, (generation, obj)) in &self.objects {
+            if compressed.binary_search(&object_number).is_ok() {
+                continue;
+            }
+
+            entries.insert(
+                object_number,
+                XrefEntry::InUse {
+                    byte_offset: out.len(),
+                    generation_number: *generation,
+                },
+            );
+
+            let reference = Reference {
+                object_number,
+                generation: usize::from(*generation),
+            };
+
+            write_indirect_object(
+                &mut out,
+                object_number,
+                *generation,
+                &self.encrypted(reference, obj)?,
+            )?;
+        }
+
+        let mut next_object_number = self.next_object_number();
+
+        for objects in compressed.chunks(OBJECTS_PER_STREAM) {
+            let object_stream = next_object_number;
+            next_object_number += 1;
+
+            let stream = self.object_stream(objects)?;
+
+            for (index, &object_number) in objects.iter().enumerate() {
+                entries.insert(
+                    object_number,
+                    XrefEntry::Compressed {
+                        object_number: object_stream as u64,
+                        index,
+                    },
+                );
+            }
+
+            entries.insert(
+                object_stream,
+                XrefEntry::InUse {
+                    byte_offset: out.len(),
+                    generation_number: 0,
+                },
+            );
+
+            // the objects in an object stream are only encrypted with the
+            // stream itself
+            let reference = Reference {
+                object_number: object_stream,
+                generation: 0,
+            };
+
+            write_indirect_object(
+                &mut out,
+                object_stream,
+                0,
+                &self.encrypted(reference, &Object::Stream(stream))?,
+            )?;
+        }
+
+        match self.xref_format {
+            XrefFormat::Table => self.write_xref_table(&mut out, entries, next_object_number)?,
+            XrefFormat::Stream | XrefFormat::ObjectStreams => {
+                self.write_xref_stream(&mut out, entries, next_object_number)?
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// The object numbers of the objects that may be written to an object
+    /// stream, in ascending order. Streams, objects with a nonzero generation
+    /// and the encryption dictionary must be written directly
+    fn compressible_objects(&self) -> Vec<usize> {
+        let encrypt = match self.trailer.iter().find(|(key, _)| *key == "Encrypt") {
+            Some((_, Object::Reference(reference))) => Some(reference.object_number),
+            _ => None,
+        };
+
+        self.objects
+            .iter()
+            .filter(|(&object_number, (generation, obj))| {
+                *generation == 0
+                    && !matches!(obj, Object::Stream(..))
+                    && Some(object_number) != encrypt
+            })
+            .map(|(&object_number, _)| object_number)
+            .collect()
+    }
+
+    fn object_stream(&self, objects: &[usize]) -> PdfResult<Stream<'a>> {
+        let mut offsets = Vec::new();
+        let mut body = Vec::new();
+
+        for &object_number in objects {
+            write!(offsets, "{} {} ", object_number, body.len())?;
+            serialize::write_object(&mut body, &self.objects[&object_number].1)?;
+            body.push(b'\n');
+        }
+
+        let first = offsets.len();
+        offsets.extend_from_slice(&body);
+
+        let mut dict = Dictionary::empty();
+        dict.insert("Type", Object::Name("ObjStm".to_owned()));
+        dict.insert("N", Object::Integer(i32::try_from(objects.len())?));
+        dict.insert("First", Object::Integer(i32::try_from(first)?));
+        dict.insert("Filter", Object::Name("FlateDecode".to_owned()));
+
+        Stream::new(dict, flate_encode(&offsets)?)
+    }
+
+    fn trailer(&self, size: usize) -> PdfResult<Dictionary<'a>> {
+        let mut trailer = Dictionary::new(
+            self.trailer
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "Prev" | "XRefStm"))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        );
+
+        trailer.insert("Size", Object::Integer(i32::try_from(size)?));
+
+        if let Some(original) = &self.original {
+            trailer.insert("Prev", Object::Integer(i32::try_from(original.start_xref)?));
+        }
+
+        Ok(trailer)
+    }
+
+    /// A complete file has an entry for every object number below `size`, with
+    /// the unused ones forming a linked list of free entries that starts at
+    /// object 0 and ends with a link back to object 0
+    ///
+    /// An incremental update only has entries for the objects it changes
+    fn add_free_entries(&self, entries: &mut BTreeMap<usize, XrefEntry>, size: usize) {
+        if self.original.is_some() {
+            return;
+        }
+
+        let free = (0..size)
+            .filter(|object_number| !entries.contains_key(object_number))
+            .collect::<Vec<usize>>();
+
+        for (idx, &object_number) in free.iter().enumerate() {
+            entries.insert(
+                object_number,
+                XrefEntry::Free {
+                    next_free_object: free.get(idx + 1).copied().unwrap_or(0) as u64,
+                    generation_number: if object_number == 0 { u16::MAX } else { 0 },
+                },
+            );
+        }
+    }
+
+    /// Split the entries into runs of consecutive object numbers, as
+    /// `(first object number, number of entries)`
+    fn subsections(entries: &BTreeMap<usize, XrefEntry>) -> Vec<(usize, usize)> {
+        let mut subsections: Vec<(usize, usize)> = Vec::new();
+
+        for &object_number in entries.keys() {
+            match subsections.last_mut() {
+                Some((start, len)) if *start + *len == object_number => *len += 1,
+                _ => subsections.push((object_number, 1)),
+            }
+        }
+
+        subsections
+    }
+
+    fn write_xref_table(
+        &self,
+        out: &mut Vec<u8>,
+        mut entries: BTreeMap<usize, XrefEntry>,
+        size: usize,
+    ) -> PdfResult<()> {
+        self.add_free_entries(&mut entries, size);
+
+        let start_xref = out.len();
+
+        out.extend_from_slice(b"xref\n");
+
+        for (start, len) in Self::subsections(&entries) {
+            writeln!(out, "{} {}", start, len)?;
+
+            for entry in entries.range(start..start + len).map(|(_, entry)| entry) {
+                // each entry is exactly 20 bytes long, including a two byte
+                // end of line marker
+                match entry {
+                    XrefEntry::InUse {
+                        byte_offset,
+                        generation_number,
+                    } => writeln!(out, "{:010} {:05} n ", byte_offset, generation_number)?,
+                    XrefEntry::Free {
+                        next_free_object,
+                        generation_number,
+                    } => writeln!(out, "{:010} {:05} f ", next_free_object, generation_number)?,
+                    XrefEntry::Compressed { .. } | XrefEntry::Null => unreachable!(),
+                }
+            }
+        }
+
+        out.extend_from_slice(b"trailer\n");
+        serialize::write_object(out, &Object::Dictionary(self.trailer(size)?))?;
+        write!(out, "\nstartxref\n{}\n%%EOF\n", start_xref)?;
+
+        Ok(())
+    }
+
+    /// The cross-reference stream is written as the object following all
+    /// others, and contains an entry for itself
+    fn write_xref_stream(
+        &self,
+        out: &mut Vec<u8>,
+        mut entries: BTreeMap<usize, XrefEntry>,
+        xref_stream: usize,
+    ) -> PdfResult<()> {
+        let size = xref_stream + 1;
+        let start_xref = out.len();
+
+        entries.insert(
+            xref_stream,
+            XrefEntry::InUse {
+                byte_offset: start_xref,
+                generation_number: 0,
+            },
+        );
+
+        self.add_free_entries(&mut entries, size);
+
+        let subsections = Self::subsections(&entries);
+
+        let fields = entries
+            .values()
+            .map(|entry| match *entry {
+                XrefEntry::Free {
+                    next_free_object,
+                    generation_number,
+                } => (0, next_free_object, generation_number as u64),
+                XrefEntry::InUse {
+                    byte_offset,
+                    generation_number,
+                } => (1, byte_offset as u64, generation_number as u64),
+                XrefEntry::Compressed {
+                    object_number,
+                    index,
+                } => (2, object_number, index as u64),
+                XrefEntry::Null => unreachable!(),
+            })
+            .collect::<Vec<(u64, u64, u64)>>();
+
+        let width = |max: u64| (u64::BITS - max.leading_zeros()).div_ceil(8).max(1) as usize;
+
+        let w = [
+            1,
+            width(fields.iter().map(|field| field.1).max().unwrap_or(0)),
+            width(fields.iter().map(|field| field.2).max().unwrap_or(0)),
+        ];
+
+        let mut data = Vec::with_capacity(fields.len() * w.iter().sum::<usize>());
+
+        for (field_one, field_two, field_three) in fields {
+            data.push(field_one as u8);
+            data.extend_from_slice(&field_two.to_be_bytes()[8 - w[1]..]);
+            data.extend_from_slice(&field_three.to_be_bytes()[8 - w[2]..]);
+        }
+
+        let mut dict = self.trailer(size)?;
+        dict.insert("Type", Object::Name("XRef".to_owned()));
+        dict.insert(
+            "W",
+            Object::Array(w.iter().map(|&w| Object::Integer(w as i32)).collect()),
+        );
+        dict.insert("Filter", Object::Name("FlateDecode".to_owned()));
+
+        // the default index covers every object number below `size`
+        if subsections != [(0, size)] {
+            dict.insert(
+                "Index",
+                Object::Array(
+                    subsections
+                        .into_iter()
+                        .flat_map(|(start, len)| [start, len])
+                        .map(|n| Ok(Object::Integer(i32::try_from(n)?)))
+                        .collect::<PdfResult<Vec<Object>>>()?,
+                ),
+            );
+        }
+
+        let stream = Stream::new(dict, flate_encode(&data)?)?;
+
+        write_indirect_object(out, xref_stream, 0, &Object::Stream(stream))?;
+        write!(out, "startxref\n{}\n%%EOF\n", start_xref)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::{
+        encryption::{CryptFilterMethod, UserAccessPermissions},
+        objects::{Dictionary, Object},
+        test_util::{dict, document, name, numbers, reference},
+        Parser,
+    };
+
+    use super::{PdfWriter, XrefFormat};
+
+    /// The catalog, page tree and page of a document with a single empty page
+    fn single_empty_page_objects() -> Vec<Object<'static>> {
+        vec![
+            Object::Dictionary(dict(vec![
+                ("Type", name("Catalog")),
+                ("Pages", Object::Reference(reference(2))),
+            ])),
+            Object::Dictionary(dict(vec![
+                ("Type", name("Pages")),
+                ("Kids", Object::Array(vec![Object::Reference(reference(3))])),
+                ("Count", Object::Integer(1)),
+            ])),
+            page(None),
+        ]
+    }
+
+    fn page(rotate: Option<i32>) -> Object<'static> {
+        let mut page = dict(vec![
+            ("Type", name("Page")),
+            ("Parent", Object::Reference(reference(2))),
+            ("MediaBox", numbers(&[0.0, 0.0, 612.0, 792.0])),
+        ]);
+
+        if let Some(rotate) = rotate {
+            page.insert("Rotate", Object::Integer(rotate));
+        }
+
+        Object::Dictionary(page)
+    }
+
+    /// A document with a single empty page
+    fn single_empty_page(xref_format: XrefFormat) -> Vec<u8> {
+        document(single_empty_page_objects(), xref_format)
+    }
+
+    #[test]
+    fn read_written_document() {
+        for xref_format in [
+            XrefFormat::Table,
+            XrefFormat::Stream,
+            XrefFormat::ObjectStreams,
+        ] {
+            let file = single_empty_page(xref_format);
+
+            let parser = Parser::from_reader(Cursor::new(file.clone())).unwrap();
+            assert_eq!(parser.page_count(), 1);
+
+            let parser = Parser::from_bytes(file).unwrap();
+            assert_eq!(parser.page_count(), 1);
+            assert_eq!(parser.root(), reference(1));
+        }
+    }
+
+    /// The trailer dictionary of the last cross-reference section of `parser`
+    fn last_trailer<'a>(parser: &mut Parser<'a>) -> Dictionary<'a> {
+        let start_xref = parser.start_xref.unwrap();
+
+        parser.lexer.lex_raw_trailer(start_xref).unwrap().0
+    }
+
+    #[test]
+    fn incremental_update() {
+        for xref_format in [XrefFormat::Table, XrefFormat::Stream] {
+            let original = single_empty_page(xref_format);
+
+            let mut parser = Parser::from_bytes(original.clone()).unwrap();
+            let original_start_xref = parser.start_xref.unwrap();
+
+            let mut writer = parser.incremental_update().unwrap();
+
+            let info = reference(writer.next_object_number());
+
+            writer.add_object(reference(3), page(Some(90))).unwrap();
+            writer
+                .add_object(
+                    info,
+                    Object::Dictionary(dict(vec![("Title", Object::String("updated".to_owned()))])),
+                )
+                .unwrap();
+
+            let updated = writer.to_bytes().unwrap();
+
+            assert!(updated.starts_with(&original));
+            assert!(updated.len() > original.len());
+
+            let mut parser = Parser::from_bytes(updated).unwrap();
+
+            let prev = last_trailer(&mut parser)
+                .iter()
+                .find(|(key, _)| *key == "Prev")
+                .map(|(_, prev)| prev.clone());
+            assert_eq!(prev, Some(Object::Integer(original_start_xref as i32)));
+
+            assert_eq!(parser.page_count(), 1);
+            assert_eq!(parser.object(reference(3)).unwrap(), page(Some(90)));
+            assert_eq!(
+                parser.object(info).unwrap(),
+                Object::Dictionary(dict(vec![("Title", Object::String("updated".to_owned()))]))
+            );
+
+            // the catalog and page tree are still read from the original section
+            for (idx, obj) in single_empty_page_objects().into_iter().take(2).enumerate() {
+                assert_eq!(parser.object(reference(idx + 1)).unwrap(), obj);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_update_of_encrypted_document() {
+        for method in [CryptFilterMethod::AesV2, CryptFilterMethod::AesV3] {
+            let mut writer = PdfWriter::new(
+                dict(vec![("Root", Object::Reference(reference(1)))]),
+                XrefFormat::Stream,
+            );
+
+            for (idx, obj) in single_empty_page_objects().into_iter().enumerate() {
+                writer.add_object(reference(idx + 1), obj).unwrap();
+            }
+
+            writer
+                .encrypt("user", "owner", UserAccessPermissions::all(), method)
+                .unwrap();
+
+            let original = writer.to_bytes().unwrap();
+
+            let mut parser = Parser::from_bytes_with_password(original.clone(), "user").unwrap();
+            let mut writer = parser.incremental_update().unwrap();
+
+            let title =
+                Object::Dictionary(dict(vec![("Title", Object::String("updated".to_owned()))]));
+            let info = reference(writer.next_object_number());
+            writer.add_object(info, title.clone()).unwrap();
+
+            let updated = writer.to_bytes().unwrap();
+
+            assert!(updated.starts_with(&original));
+
+            // the title is only readable once decrypted
+            let appended = &updated[original.len()..];
+            assert!(!appended.windows(7).any(|window| window == b"updated"));
+
+            for password in ["user", "owner"] {
+                let mut parser =
+                    Parser::from_bytes_with_password(updated.clone(), password).unwrap();
+
+                assert_eq!(parser.page_count(), 1);
+                assert_eq!(parser.object(info).unwrap(), title);
+            }
+        }
+    }
+}
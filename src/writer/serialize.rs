@@ -0,0 +1,271 @@
+use std::io::Write;
+
+use crate::{
+    error::PdfResult,
+    objects::{Dictionary, Object},
+    stream::Stream,
+};
+
+/// Write an object that is not a stream. Streams may only appear as indirect
+/// objects, and so are written by [`write_indirect_object`]
+pub(super) fn write_object(out: &mut Vec<u8>, obj: &Object) -> PdfResult<()> {
+    match obj {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::True => out.extend_from_slice(b"true"),
+        Object::False => out.extend_from_slice(b"false"),
+        Object::Integer(i) => write!(out, "{}", i)?,
+        Object::Real(r) => write_real(out, *r),
+        Object::String(s) => write_string(out, s),
+        Object::Name(name) => write_name(out, name),
+        Object::Array(arr) => {
+            out.push(b'[');
+
+            for (idx, obj) in arr.iter().enumerate() {
+                if idx != 0 {
+                    out.push(b' ');
+                }
+
+                write_object(out, obj)?;
+            }
+
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dict(out, dict)?,
+        Object::Stream(..) => anyhow::bail!("streams must be indirect objects"),
+        Object::Reference(reference) => write!(
+            out,
+            "{} {} R",
+            reference.object_number, reference.generation
+        )?,
+    }
+
+    Ok(())
+}
+
+pub(super) fn write_indirect_object(
+    out: &mut Vec<u8>,
+    object_number: usize,
+    generation: u16,
+    obj: &Object,
+) -> PdfResult<()> {
+    writeln!(out, "{} {} obj", object_number, generation)?;
+
+    match obj {
+        Object::Stream(stream) => write_stream(out, stream)?,
+        obj => write_object(out, obj)?,
+    }
+
+    out.extend_from_slice(b"\nendobj\n");
+
+    Ok(())
+}
+
+/// Keys are written in sorted order, so that the output does not depend on the
+/// iteration order of the dictionary
+fn write_dict(out: &mut Vec<u8>, dict: &Dictionary) -> PdfResult<()> {
+    let mut entries = dict.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(key, _)| *key);
+
+    out.extend_from_slice(b"<<");
+
+    for (idx, (key, value)) in entries.into_iter().enumerate() {
+        if idx != 0 {
+            out.push(b' ');
+        }
+
+        write_name(out, key);
+        out.push(b' ');
+        write_object(out, value)?;
+    }
+
+    out.extend_from_slice(b">>");
+
+    Ok(())
+}
+
+fn write_stream(out: &mut Vec<u8>, stream: &Stream) -> PdfResult<()> {
+    let mut dict = stream.dict.to_dict()?;
+    dict.insert(
+        "Length",
+        Object::Integer(i32::try_from(stream.stream.len())?),
+    );
+
+    write_dict(out, &dict)?;
+    out.extend_from_slice(b"\nstream\n");
+    out.extend_from_slice(&stream.stream);
+    out.extend_from_slice(b"\nendstream");
+
+    Ok(())
+}
+
+/// PDF does not allow exponents in real numbers, and a real without a decimal
+/// point would be read back as an integer
+fn write_real(out: &mut Vec<u8>, r: f32) {
+    if !r.is_finite() {
+        out.extend_from_slice(b"0.0");
+        return;
+    }
+
+    // `Display` for floats never uses an exponent, and gives the shortest
+    // representation that round trips
+    let s = r.to_string();
+    out.extend_from_slice(s.as_bytes());
+
+    if !s.contains('.') {
+        out.extend_from_slice(b".0");
+    }
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+/// Names are stored with each byte as a `char`. Any other characters are
+/// written as UTF-8
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    out.push(b'/');
+
+    let mut buffer = [0; 4];
+
+    for c in name.chars() {
+        let bytes = match u8::try_from(c) {
+            Ok(b) => {
+                buffer[0] = b;
+                &buffer[..1]
+            }
+            Err(..) => c.encode_utf8(&mut buffer).as_bytes(),
+        };
+
+        for &b in bytes {
+            if b.is_ascii_graphic() && b != b'#' && !is_delimiter(b) {
+                out.push(b);
+            } else {
+                out.extend_from_slice(format!("#{:02X}", b).as_bytes());
+            }
+        }
+    }
+}
+
+/// Strings are stored with each byte as a `char`. Strings containing other
+/// characters are written as UTF-16BE text strings
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    if s.chars().any(|c| u8::try_from(c).is_err()) {
+        out.extend_from_slice(b"<FEFF");
+
+        for unit in s.encode_utf16() {
+            out.extend_from_slice(format!("{:04X}", unit).as_bytes());
+        }
+
+        out.push(b'>');
+
+        return;
+    }
+
+    let bytes = s.chars().map(|c| c as u8).collect::<Vec<u8>>();
+
+    let num_binary = bytes
+        .iter()
+        .filter(|&&b| !b.is_ascii_graphic() && !matches!(b, b' ' | b'\n' | b'\r' | b'\t'))
+        .count();
+
+    // hex strings starting with a byte order mark are read back as UTF-16
+    if num_binary * 4 > bytes.len() && !bytes.starts_with(&[0xfe, 0xff]) {
+        write_hex_string(out, &bytes);
+    } else {
+        write_literal_string(out, &bytes);
+    }
+}
+
+fn write_hex_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.push(b'<');
+
+    for b in bytes {
+        out.extend_from_slice(format!("{:02X}", b).as_bytes());
+    }
+
+    out.push(b'>');
+}
+
+fn write_literal_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.push(b'(');
+
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => out.extend_from_slice(&[b'\\', b]),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            0x08 => out.extend_from_slice(b"\\b"),
+            0x0c => out.extend_from_slice(b"\\f"),
+            0..=0x1f | 0x7f => out.extend_from_slice(format!("\\{:03o}", b).as_bytes()),
+            b => out.push(b),
+        }
+    }
+
+    out.push(b')');
+}
+
+#[cfg(test)]
+mod test {
+    use crate::objects::{Object, Reference};
+
+    use super::write_object;
+
+    fn serialize(obj: Object) -> String {
+        let mut out = Vec::new();
+        write_object(&mut out, &obj).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn reals() {
+        assert_eq!(serialize(Object::Real(1.0)), "1.0");
+        assert_eq!(serialize(Object::Real(-0.25)), "-0.25");
+        assert_eq!(serialize(Object::Real(1e10)), "10000000000.0");
+        assert_eq!(serialize(Object::Real(f32::NAN)), "0.0");
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(serialize(Object::Name("Type".to_owned())), "/Type");
+        assert_eq!(
+            serialize(Object::Name("A B#(C)".to_owned())),
+            "/A#20B#23#28C#29"
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            serialize(Object::String("a (b) \\c\n".to_owned())),
+            "(a \\(b\\) \\\\c\\n)"
+        );
+        assert_eq!(
+            serialize(Object::String("\u{0}\u{1}\u{2}a".to_owned())),
+            "<00010261>"
+        );
+        assert_eq!(
+            serialize(Object::String("\u{1}abcdef".to_owned())),
+            "(\\001abcdef)"
+        );
+        assert_eq!(serialize(Object::String("ж".to_owned())), "<FEFF0436>");
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(
+            serialize(Object::Array(vec![
+                Object::Integer(1),
+                Object::Null,
+                Object::Reference(Reference {
+                    object_number: 3,
+                    generation: 0
+                }),
+            ])),
+            "[1 null 3 0 R]"
+        );
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
     catalog::{GroupAttributes, MetadataStream, PagePiece},
     data_structures::{Matrix, Rectangle},
     date::Date,
-    objects::Name,
+    objects::{Name, Reference},
     optional_content::OptionalContent,
     resources::Resources,
     stream::Stream,
@@ -116,4 +116,8 @@ pub struct FormXObject<'a> {
     /// Default value: 1
     #[field("FormType", default = 1)]
     pub form_type: i32,
+
+    /// The form stream, by which a form that paints itself is recognised
+    #[field]
+    pub(crate) indirect_reference: Option<Reference>,
 }
@@ -30,6 +30,7 @@ impl<'a> XObject<'a> {
 
 impl<'a> FromObj<'a> for XObject<'a> {
     fn from_obj(obj: Object<'a>, resolver: &mut dyn Resolve<'a>) -> PdfResult<Self> {
+        let reference = obj.as_reference();
         let mut stream = resolver.assert_stream(obj)?;
         let dict = &mut stream.dict.other;
 
@@ -46,7 +47,10 @@ impl<'a> FromObj<'a> for XObject<'a> {
                 XObject::Image(ImageXObject::from_obj(Object::Stream(stream), resolver)?)
             }
             XObjectSubtype::Form => {
-                XObject::Form(FormXObject::from_obj(Object::Stream(stream), resolver)?)
+                let mut form = FormXObject::from_obj(Object::Stream(stream), resolver)?;
+                form.indirect_reference = reference;
+
+                XObject::Form(form)
             }
         })
     }
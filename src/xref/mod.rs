@@ -5,6 +5,7 @@ use crate::{PdfResult, Reference};
 pub(crate) use parser::{TrailerOrOffset, XrefParser};
 
 mod parser;
+mod recover;
 pub mod stream;
 
 /// The cross-reference table contains information
@@ -20,7 +21,13 @@ pub struct Xref {
 #[derive(Debug)]
 pub enum ByteOffset {
     MainFile(usize),
-    ObjectStream { byte_offset: usize, index: usize },
+    ObjectStream {
+        byte_offset: usize,
+        index: usize,
+
+        /// The object stream itself
+        stream_reference: Reference,
+    },
 }
 
 impl Xref {
@@ -36,16 +43,22 @@ impl Xref {
                         object_number,
                         index,
                     } => {
-                        let byte_offset = match self.get_offset(Reference {
+                        let stream_reference = Reference {
                             object_number: usize::try_from(object_number)?,
                             generation: 0,
-                        })? {
+                        };
+
+                        let byte_offset = match self.get_offset(stream_reference)? {
                             Some(ByteOffset::MainFile(v)) => v,
                             Some(ByteOffset::ObjectStream { .. }) => todo!(),
                             None => return Ok(None),
                         };
 
-                        Some(ByteOffset::ObjectStream { byte_offset, index })
+                        Some(ByteOffset::ObjectStream {
+                            byte_offset,
+                            index,
+                            stream_reference,
+                        })
                     }
                 }
             } else {
@@ -1,4 +1,8 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use crate::{
     filter::decode_stream,
@@ -19,8 +23,8 @@ const KILOBYTE: usize = 1024;
 
 #[derive(Debug)]
 pub(crate) struct XrefParser {
-    file: Vec<u8>,
-    pos: usize,
+    pub(super) file: Rc<Vec<u8>>,
+    pub(super) pos: usize,
 }
 
 impl<'a> LexBase<'a> for XrefParser {
@@ -68,24 +72,32 @@ pub(crate) struct XrefAndTrailer<'a> {
 }
 
 impl<'a> XrefParser {
-    pub fn new(file: Vec<u8>) -> Self {
+    pub fn new(file: Rc<Vec<u8>>) -> Self {
         Self { file, pos: 0 }
     }
 
-    /// We read backwards in 1024 byte chunks, looking for `"startxref"`
     pub fn read_xref(&mut self) -> PdfResult<XrefAndTrailer<'a>> {
+        let xref_pos = self.start_xref()?;
+
+        self.parse_xref_at_offset(xref_pos)
+    }
+
+    /// The byte offset of the last cross-reference section in the file
+    ///
+    /// We read backwards in 1024 byte chunks, looking for `"startxref"`
+    pub fn start_xref(&mut self) -> PdfResult<usize> {
         let mut pos = self.file.len().saturating_sub(1);
 
         let idx = loop {
             if pos == 0 {
-                todo!("failed to find xref");
+                anyhow::bail!("failed to find startxref");
             }
 
             let next_pos = pos.saturating_sub(KILOBYTE - START_XREF_SIGNATURE.len());
             // todo: use rabin-karp or something similar
             if let Some(start) = self.file[next_pos..=pos]
                 .windows(START_XREF_SIGNATURE.len())
-                .position(|window| window == START_XREF_SIGNATURE)
+                .rposition(|window| window == START_XREF_SIGNATURE)
             {
                 break start + next_pos;
             }
@@ -99,12 +111,12 @@ impl<'a> XrefParser {
 
         self.skip_whitespace();
 
-        let xref_pos = self.lex_whole_number().parse::<usize>().unwrap();
-
-        self.parse_xref_at_offset(xref_pos)
+        Ok(self.lex_whole_number().parse::<usize>()?)
     }
 
     fn parse_xref_stream(&mut self, is_previous: bool) -> PdfResult<XrefAndTrailer<'a>> {
+        let start = self.pos;
+
         self.read_obj_prelude()?;
 
         let xref_stream_dict = match self.lex_object()? {
@@ -121,8 +133,17 @@ impl<'a> XrefParser {
         self.read_obj_trailer()?;
 
         if !is_previous {
+            let mut visited = HashSet::from([start]);
+
             let mut prev = stream.dict.trailer.prev;
             while let Some(prev_offset) = prev {
+                if !visited.insert(prev_offset) {
+                    anyhow::bail!(
+                        "cross-reference stream at byte offset {} is read twice",
+                        prev_offset
+                    );
+                }
+
                 self.pos = prev_offset;
                 let xref_and_trailer = self.parse_xref_stream(true)?;
 
@@ -180,18 +201,18 @@ impl<'a> XrefParser {
         let mut objects = HashMap::new();
 
         loop {
-            let idx_offset = self.lex_whole_number().parse::<usize>().unwrap();
+            let idx_offset = self.lex_whole_number().parse::<usize>()?;
             self.skip_whitespace();
 
-            let num_of_entries = self.lex_whole_number().parse::<usize>().unwrap();
+            let num_of_entries = self.lex_whole_number().parse::<usize>()?;
             self.skip_whitespace();
 
             objects.reserve(num_of_entries);
 
             for i in 0..num_of_entries {
-                let byte_offset = self.lex_whole_number().parse::<usize>().unwrap();
+                let byte_offset = self.lex_whole_number().parse::<usize>()?;
                 self.skip_whitespace();
-                let generation_number = self.lex_whole_number().parse::<u16>().unwrap();
+                let generation_number = self.lex_whole_number().parse::<u16>()?;
                 self.skip_whitespace();
                 let entry_kind = self.next_byte_err()?;
                 self.skip_whitespace();
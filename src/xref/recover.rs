@@ -0,0 +1,469 @@
+//! Rebuilding the cross-reference table of a damaged file, by scanning the
+//! whole file for the headers of indirect objects
+
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+use crate::{
+    filter::decode_stream,
+    lex::{LexBase, LexObject},
+    objects::{Dictionary, Object, Reference},
+    stream::StreamDict,
+    PdfResult,
+};
+
+use super::{Xref, XrefEntry, XrefParser};
+
+/// The entries of a trailer dictionary that remain meaningful once the
+/// cross-reference sections it described are discarded
+const TRAILER_KEYS: [&str; 4] = ["Root", "Encrypt", "ID", "Info"];
+
+/// An object found while scanning, and the byte offset it was found at. Where
+/// an object is defined more than once, the definition furthest into the file
+/// is the most recent
+struct Found {
+    position: usize,
+    entry: XrefEntry,
+}
+
+/// An object stream found while scanning, whose objects are indexed once the
+/// scan is complete
+struct FoundObjectStream<'a> {
+    object_number: usize,
+    position: usize,
+    dict: Dictionary<'a>,
+
+    /// The byte range of the data of the stream
+    data: Range<usize>,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | 0x0c | b'\r' | b' ')
+}
+
+/// Whether the byte is part of a token, rather than whitespace or a delimiter
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b)
+        && !matches!(
+            b,
+            b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+        )
+}
+
+/// Record an object, unless a more recent definition has already been found
+fn add(found: &mut HashMap<usize, Found>, object_number: usize, position: usize, entry: XrefEntry) {
+    if found
+        .get(&object_number)
+        .is_none_or(|found| found.position <= position)
+    {
+        found.insert(object_number, Found { position, entry });
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|idx| idx + from)
+}
+
+/// Find the header `N G obj` ending with the keyword at `keyword`, returning
+/// its byte offset, object number and generation
+fn object_header(file: &[u8], keyword: usize) -> Option<(usize, usize, u16)> {
+    if file.get(keyword + 3).is_some_and(|&b| is_regular(b)) {
+        return None;
+    }
+
+    let mut pos = keyword;
+
+    let number = |pos: &mut usize| -> Option<&[u8]> {
+        let end = *pos;
+
+        if end == 0 || !is_whitespace(file[end - 1]) {
+            return None;
+        }
+
+        while *pos > 0 && is_whitespace(file[*pos - 1]) {
+            *pos -= 1;
+        }
+
+        let digits_end = *pos;
+
+        while *pos > 0 && file[*pos - 1].is_ascii_digit() {
+            *pos -= 1;
+        }
+
+        (*pos < digits_end).then(|| &file[*pos..digits_end])
+    };
+
+    let generation = std::str::from_utf8(number(&mut pos)?).ok()?.parse().ok()?;
+    let object_number = std::str::from_utf8(number(&mut pos)?).ok()?.parse().ok()?;
+
+    if pos > 0 && is_regular(file[pos - 1]) {
+        return None;
+    }
+
+    Some((pos, object_number, generation))
+}
+
+impl<'a> XrefParser {
+    /// Rebuild the cross-reference table by scanning the file for the headers
+    /// of indirect objects and the contents of object streams, and find a
+    /// trailer for it
+    ///
+    /// The trailer is taken from the last trailer dictionary or
+    /// cross-reference stream whose `Root` is an object in the file. Failing
+    /// that, the last object with a `Type` of `Catalog` is used as the root
+    ///
+    /// Objects in encrypted object streams can't be found, as the security
+    /// handler needs the trailer
+    pub fn recover(&mut self) -> PdfResult<(Xref, Dictionary<'a>)> {
+        let file = Rc::clone(&self.file);
+
+        let mut found: HashMap<usize, Found> = HashMap::new();
+        let mut object_streams = Vec::new();
+        let mut trailers = Vec::new();
+        let mut catalog = None;
+
+        let mut pos = 0;
+
+        while let Some(keyword) = find(&file, b"obj", pos) {
+            pos = keyword + 3;
+
+            let Some((position, object_number, generation)) = object_header(&file, keyword) else {
+                continue;
+            };
+
+            add(
+                &mut found,
+                object_number,
+                position,
+                XrefEntry::InUse {
+                    byte_offset: position,
+                    generation_number: generation,
+                },
+            );
+
+            self.pos = pos;
+            self.skip_whitespace();
+
+            if !self.next_matches(b"<<") {
+                continue;
+            }
+
+            let Ok(dict) = self.lex_dict_ignore_stream() else {
+                continue;
+            };
+
+            pos = self.pos;
+
+            let kind = dict.iter().find_map(|(key, value)| match value {
+                Object::Name(name) if key == "Type" => Some(name.as_str()),
+                _ => None,
+            });
+
+            let reference = Reference {
+                object_number,
+                generation: usize::from(generation),
+            };
+
+            match kind {
+                Some("Catalog") => catalog = Some(reference),
+                Some("XRef") => trailers.push((position, dict.clone())),
+                _ => {}
+            }
+
+            self.skip_whitespace();
+
+            if !self.next_matches(b"stream") {
+                continue;
+            }
+
+            self.pos += b"stream".len();
+
+            // the stream is skipped, so that nothing in its data is mistaken
+            // for an object
+            let start = match file.get(self.pos) {
+                Some(b'\r') if file.get(self.pos + 1) == Some(&b'\n') => self.pos + 2,
+                Some(b'\r' | b'\n') => self.pos + 1,
+                _ => self.pos,
+            };
+
+            let Some(end) = find(&file, b"endstream", start) else {
+                break;
+            };
+
+            pos = end + b"endstream".len();
+
+            if kind == Some("ObjStm") {
+                object_streams.push(FoundObjectStream {
+                    object_number,
+                    position,
+                    dict,
+                    // the end of line marker before `endstream` isn't part of
+                    // the data, but filters ignore anything after the end of
+                    // their data
+                    data: start..end,
+                });
+            }
+        }
+
+        for object_stream in object_streams {
+            let data = &file[object_stream.data.clone()];
+
+            let Ok(objects) = self.object_stream_contents(&object_stream, data) else {
+                continue;
+            };
+
+            for (index, object_number) in objects.into_iter().enumerate() {
+                add(
+                    &mut found,
+                    object_number,
+                    object_stream.position,
+                    XrefEntry::Compressed {
+                        object_number: object_stream.object_number as u64,
+                        index,
+                    },
+                );
+            }
+        }
+
+        let mut pos = 0;
+
+        while let Some(keyword) = find(&file, b"trailer", pos) {
+            pos = keyword + b"trailer".len();
+
+            self.pos = pos;
+            self.skip_whitespace();
+
+            if let Ok(dict) = self.lex_dict_ignore_stream() {
+                trailers.push((keyword, dict));
+            }
+        }
+
+        trailers.sort_by_key(|&(position, _)| position);
+
+        let size = found.keys().max().map_or(1, |&n| n + 1);
+
+        let xref = Xref {
+            objects: found
+                .into_iter()
+                .map(|(object_number, found)| (object_number, found.entry))
+                .collect(),
+        };
+
+        let trailers = trailers
+            .into_iter()
+            .map(|(_, dict)| {
+                dict.entries()
+                    .filter(|(key, _)| TRAILER_KEYS.contains(&key.as_str()))
+                    .collect::<HashMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+
+        let has_root = |trailer: &HashMap<String, Object>| match trailer.get("Root") {
+            Some(Object::Reference(root)) => xref.objects.contains_key(&root.object_number),
+            _ => false,
+        };
+
+        let mut trailer = match trailers.iter().rposition(has_root) {
+            Some(idx) => trailers[idx].clone(),
+            None => {
+                let Some(catalog) = catalog else {
+                    anyhow::bail!("failed to find the document catalog of a damaged file");
+                };
+
+                let mut trailer = trailers.last().cloned().unwrap_or_default();
+                trailer.insert("Root".to_owned(), Object::Reference(catalog));
+                trailer
+            }
+        };
+
+        trailer.insert("Size".to_owned(), Object::Integer(i32::try_from(size)?));
+
+        Ok((xref, Dictionary::new(trailer)))
+    }
+
+    /// The object numbers of the objects in an object stream, in order
+    fn object_stream_contents(
+        &mut self,
+        object_stream: &FoundObjectStream<'a>,
+        data: &[u8],
+    ) -> PdfResult<Vec<usize>> {
+        let mut dict = object_stream.dict.clone();
+
+        let n = dict.expect_unsigned_integer("N", self)?;
+        let first = usize::try_from(dict.expect_unsigned_integer("First", self)?)?;
+
+        // the length may be an indirect object, or simply wrong
+        dict.insert("Length", Object::Integer(i32::try_from(data.len())?));
+
+        let stream_dict = StreamDict::from_dict(dict, self)?;
+        let decoded = decode_stream(data, &stream_dict, self)?;
+
+        let Some(header) = decoded.get(..first) else {
+            anyhow::bail!("invalid object stream");
+        };
+
+        std::str::from_utf8(header)?
+            .split_ascii_whitespace()
+            .step_by(2)
+            .take(usize::try_from(n)?)
+            .map(|object_number| Ok(object_number.parse()?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::{
+        objects::{Object, Reference},
+        test_util::{self, dict, name, reference, single_page_document},
+        xref::{XrefEntry, XrefParser},
+        ParseOptions, Parser, XrefFormat,
+    };
+
+    fn document() -> Vec<u8> {
+        single_page_document(b"", dict(Vec::new()), Vec::new())
+    }
+
+    fn position(file: &[u8], needle: &[u8]) -> usize {
+        file.windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap()
+    }
+
+    #[test]
+    fn recover_without_xref() {
+        let file = b"%PDF-1.7\n\
+            1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n\
+            2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n\
+            3 0 obj\n<< /Length 13 >>\nstream\n4 0 obj (fake)\nendstream\nendobj\n\
+            2 0 obj\n<< /Type /Pages /Kids [] /Count 0 /Rotate 90 >>\nendobj\n"
+            .to_vec();
+
+        let (xref, trailer) = XrefParser::new(Rc::new(file.clone())).recover().unwrap();
+
+        let offset = |needle: &[u8]| {
+            file.windows(needle.len())
+                .rposition(|window| window == needle)
+                .unwrap()
+        };
+
+        assert_eq!(xref.objects.len(), 3);
+        assert!(matches!(
+            xref.objects[&2],
+            XrefEntry::InUse { byte_offset, .. } if byte_offset == offset(b"2 0 obj")
+        ));
+        assert!(matches!(
+            trailer.entries().find(|(key, _)| key == "Root"),
+            Some((
+                _,
+                Object::Reference(Reference {
+                    object_number: 1,
+                    generation: 0
+                })
+            ))
+        ));
+    }
+
+    #[test]
+    fn missing_startxref_is_recovered() {
+        let mut file = document();
+        file.truncate(position(&file, b"startxref"));
+
+        let mut parser = Parser::from_bytes(file.clone()).unwrap();
+
+        assert_eq!(parser.page_count(), 1);
+        parser.page(0).unwrap();
+
+        let [diagnostic] = parser.diagnostics() else {
+            panic!(
+                "expected a single diagnostic, found {:?}",
+                parser.diagnostics()
+            );
+        };
+        assert_eq!(diagnostic.reference, None);
+
+        let options = ParseOptions {
+            recover: false,
+            ..ParseOptions::default()
+        };
+
+        assert!(Parser::from_bytes_with_options(file, "", options).is_err());
+    }
+
+    #[test]
+    fn wrong_xref_offset_is_recovered() {
+        let mut file = document();
+
+        // point the entry for the page at the page tree node instead
+        let page_offset = format!("{:010}", position(&file, b"3 0 obj"));
+        let pages_offset = format!("{:010}", position(&file, b"2 0 obj"));
+        let xref = position(&file, b"xref");
+        let entry = xref + position(&file[xref..], page_offset.as_bytes());
+        file[entry..entry + 10].copy_from_slice(pages_offset.as_bytes());
+
+        let mut parser = Parser::from_bytes(file.clone()).unwrap();
+        parser.page(0).unwrap();
+
+        assert_eq!(parser.diagnostics().len(), 1);
+        assert_eq!(parser.diagnostics()[0].reference, Some(reference(3)));
+
+        let options = ParseOptions {
+            recover: false,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::from_bytes_with_options(file, "", options).unwrap();
+
+        assert!(parser.page(0).is_err());
+    }
+
+    #[test]
+    fn cyclic_prev_is_recovered() {
+        let mut file = document();
+
+        // point the trailer back at the cross-reference table it belongs to
+        let xref = position(&file, b"xref");
+        let trailer = position(&file, b"trailer");
+        let dict = trailer + position(&file[trailer..], b"<<") + 2;
+        file.splice(dict..dict, format!(" /Prev {}", xref).into_bytes());
+
+        let mut parser = Parser::from_bytes(file.clone()).unwrap();
+        parser.page(0).unwrap();
+
+        assert_eq!(parser.diagnostics().len(), 1);
+
+        let options = ParseOptions {
+            recover: false,
+            ..ParseOptions::default()
+        };
+
+        assert!(Parser::from_bytes_with_options(file, "", options).is_err());
+    }
+
+    #[test]
+    fn cyclic_prev_in_xref_stream_is_an_error() {
+        let objects = vec![Object::Dictionary(dict(vec![("Type", name("Catalog"))]))];
+        let mut file = test_util::document(objects, XrefFormat::Stream);
+
+        let xref = position(&file, b"startxref") + b"startxref".len();
+        let xref = std::str::from_utf8(&file[xref..])
+            .unwrap()
+            .split_ascii_whitespace()
+            .next()
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        let dict = xref + position(&file[xref..], b"<<") + 2;
+        file.splice(dict..dict, format!(" /Prev {}", xref).into_bytes());
+
+        let mut parser = XrefParser::new(Rc::new(file));
+        let start_xref = parser.start_xref().unwrap();
+
+        assert!(parser.parse_xref_at_offset(start_xref).is_err());
+    }
+}